@@ -0,0 +1,208 @@
+//! Deposit confirmation tracking for services crediting payments on tumuchain.
+//!
+//! A [`Follower`] follows the best and finalized heads of a node over WebSocket and reports
+//! through a callback when a watched output is created, when its block has enough
+//! confirmations to credit it, when a reorg removes that block again, and when it is finalized:
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), tumuchain_confirm::Error> {
+//! use tumuchain_confirm::{DepositEvent, Follower};
+//!
+//! let follower = Follower::connect("ws://127.0.0.1:9944", 6).await?;
+//! # let outpoint = Default::default();
+//! follower.watcher().watch(outpoint);
+//! follower
+//! 	.run(|event| match event {
+//! 		DepositEvent::Confirmed { outpoint, .. } => println!("credit {outpoint:?}"),
+//! 		DepositEvent::Reorged { outpoint, .. } => println!("revert {outpoint:?}"),
+//! 		_ => {},
+//! 	})
+//! 	.await
+//! # }
+//! ```
+//!
+//! Reorgs are found from the parent hashes of the new heads, fetching the headers of the new
+//! branch back to a block already followed, so the outputs of every block of the new best chain
+//! are looked up in order. The node is asked for each watched output not created yet at each new
+//! block with `utxo_output`: the output is created by the first block whose state has it.
+//! Outputs spent in the block creating them are never seen. The bookkeeping itself is done by a
+//! [`Tracker`], which can be fed by hand.
+
+use std::{
+	fmt,
+	sync::{Arc, Mutex, MutexGuard},
+};
+
+use futures::StreamExt;
+use jsonrpsee::{
+	core::client::{ClientT, Error as ClientError, SubscriptionClientT},
+	rpc_params,
+	ws_client::{WsClient, WsClientBuilder},
+};
+use sp_core::H256;
+use sp_runtime::{
+	generic,
+	traits::{BlakeTwo256, Header as _},
+};
+use utxo::TransactionOutput;
+
+mod tracker;
+
+#[cfg(test)]
+mod tests;
+
+pub use tracker::{BlockRef, DepositEvent, Tracker};
+
+/// Block numbers, as in the runtime.
+pub type BlockNumber = u32;
+
+/// Block headers, as served by the node.
+type Header = generic::Header<BlockNumber, BlakeTwo256>;
+
+/// Reasons following the chain stops.
+#[derive(Debug)]
+pub enum Error {
+	/// A request or subscription to the node failed
+	Rpc(ClientError),
+	/// The node does not know the parent of a block it announced
+	MissingHeader(H256),
+	/// The node closed a subscription
+	SubscriptionClosed,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Rpc(e) => write!(f, "RPC request failed: {e}"),
+			Self::MissingHeader(hash) => write!(f, "the node has no header {hash:?}"),
+			Self::SubscriptionClosed => write!(f, "the node closed the subscription"),
+		}
+	}
+}
+
+impl std::error::Error for Error {}
+
+impl From<ClientError> for Error {
+	fn from(e: ClientError) -> Self {
+		Self::Rpc(e)
+	}
+}
+
+/// Adds and removes the outputs a [`Follower`] watches, also while it runs.
+#[derive(Clone)]
+pub struct Watcher(Arc<Mutex<Tracker>>);
+
+impl Watcher {
+	/// Watch for the output at `outpoint`, e.g. the first output of a transaction paying a
+	/// deposit address, `utxo::Transaction::outpoint_of(txid, 0)`.
+	pub fn watch(&self, outpoint: H256) {
+		self.lock().watch(outpoint)
+	}
+
+	/// Stop watching `outpoint`. Returns whether it was watched.
+	pub fn unwatch(&self, outpoint: &H256) -> bool {
+		self.lock().unwatch(outpoint)
+	}
+
+	fn lock(&self) -> MutexGuard<Tracker> {
+		self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+}
+
+/// Follows a node's chain and reports on the outputs its [`Watcher`] watches.
+pub struct Follower {
+	client: WsClient,
+	watcher: Watcher,
+}
+
+impl Follower {
+	/// Connect to the WebSocket RPC endpoint at `url`, to confirm deposits after
+	/// `confirmations` blocks.
+	pub async fn connect(url: &str, confirmations: u32) -> Result<Self, Error> {
+		let client = WsClientBuilder::default().build(url).await?;
+		Ok(Self::new(client, Tracker::new(confirmations)))
+	}
+
+	/// Follow the node of `client`, starting from the state of `tracker`.
+	pub fn new(client: WsClient, tracker: Tracker) -> Self {
+		Self { client, watcher: Watcher(Arc::new(Mutex::new(tracker))) }
+	}
+
+	/// The handle adding and removing watched outputs.
+	pub fn watcher(&self) -> Watcher {
+		self.watcher.clone()
+	}
+
+	/// Follow the chain, calling `on_event` for each event in the order they happen, until the
+	/// connection fails.
+	pub async fn run(self, mut on_event: impl FnMut(DepositEvent)) -> Result<(), Error> {
+		let mut heads = self
+			.client
+			.subscribe::<Header, _>(
+				"chain_subscribeNewHeads",
+				rpc_params![],
+				"chain_unsubscribeNewHeads",
+			)
+			.await?;
+		let mut finalized = self
+			.client
+			.subscribe::<Header, _>(
+				"chain_subscribeFinalizedHeads",
+				rpc_params![],
+				"chain_unsubscribeFinalizedHeads",
+			)
+			.await?;
+
+		loop {
+			tokio::select! {
+				head = heads.next() => {
+					let head = head.ok_or(Error::SubscriptionClosed)??;
+					for block in self.route(&head).await? {
+						let created = self.created(&block).await?;
+						let events = self.watcher.lock().import(block, created);
+						events.into_iter().for_each(&mut on_event);
+					}
+				},
+				head = finalized.next() => {
+					let head = head.ok_or(Error::SubscriptionClosed)??;
+					let events = self.watcher.lock().finalize(head.hash(), *head.number());
+					events.into_iter().for_each(&mut on_event);
+				},
+			}
+		}
+	}
+
+	/// The blocks to import for `head` to become the best block, parent first: `head` and its
+	/// ancestors back to one the tracker follows.
+	async fn route(&self, head: &Header) -> Result<Vec<BlockRef>, Error> {
+		let mut route = vec![block_ref(head)];
+		loop {
+			let oldest = route[route.len() - 1];
+			if self.watcher.lock().connects(&oldest) {
+				break
+			}
+			let parent: Option<Header> =
+				self.client.request("chain_getHeader", rpc_params![oldest.parent]).await?;
+			let parent = parent.ok_or(Error::MissingHeader(oldest.parent))?;
+			route.push(block_ref(&parent));
+		}
+		route.reverse();
+		Ok(route)
+	}
+
+	/// The watched outputs that `block` creates, as far as the tracker knows what to look for.
+	async fn created(&self, block: &BlockRef) -> Result<Vec<(H256, TransactionOutput)>, Error> {
+		let pending = self.watcher.lock().pending(block);
+		let mut created = Vec::new();
+		for outpoint in pending {
+			let output: Option<TransactionOutput> =
+				self.client.request("utxo_output", rpc_params![outpoint, block.hash]).await?;
+			created.extend(output.map(|output| (outpoint, output)));
+		}
+		Ok(created)
+	}
+}
+
+fn block_ref(header: &Header) -> BlockRef {
+	BlockRef { hash: header.hash(), number: *header.number(), parent: *header.parent_hash() }
+}
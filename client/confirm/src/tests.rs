@@ -0,0 +1,103 @@
+use crate::{BlockNumber, BlockRef, DepositEvent, Tracker};
+use sp_core::H256;
+use utxo::TransactionOutput;
+
+/// Block `number` of the branch `fork`, whose blocks up to `forked_at` are those of branch 0.
+fn block(number: BlockNumber, fork: u8, forked_at: BlockNumber) -> BlockRef {
+    let hash = |number: BlockNumber| {
+        let branch = if number > forked_at { fork } else { 0 };
+        H256::from_low_u64_be((u64::from(number) << 8) | u64::from(branch))
+    };
+    BlockRef { hash: hash(number), number, parent: hash(number.saturating_sub(1)) }
+}
+
+fn deposit(value: u128) -> TransactionOutput {
+    TransactionOutput { value, pubkey: H256::repeat_byte(9), memo: None }
+}
+
+#[test]
+fn deposit_confirms_after_required_blocks() {
+    let outpoint = H256::repeat_byte(1);
+    let mut tracker = Tracker::new(3);
+    tracker.watch(outpoint);
+
+    assert_eq!(tracker.import(block(1, 0, 0), vec![]), vec![]);
+    assert_eq!(tracker.pending(&block(2, 0, 0)), vec![outpoint]);
+    assert_eq!(
+        tracker.import(block(2, 0, 0), vec![(outpoint, deposit(50)), (H256::repeat_byte(2), deposit(1))]),
+        vec![DepositEvent::Included { outpoint, block_hash: block(2, 0, 0).hash, block_number: 2, output: deposit(50) }],
+    );
+    // Created by a block the next ones build on
+    assert_eq!(tracker.pending(&block(3, 0, 0)), vec![]);
+    assert_eq!(tracker.import(block(3, 0, 0), vec![(outpoint, deposit(50))]), vec![]);
+    assert_eq!(
+        tracker.import(block(4, 0, 0), vec![]),
+        vec![DepositEvent::Confirmed { outpoint, block_hash: block(2, 0, 0).hash, confirmations: 3 }],
+    );
+    // Reported once
+    assert_eq!(tracker.import(block(5, 0, 0), vec![]), vec![]);
+}
+
+#[test]
+fn reorg_retracts_and_reincludes_deposit() {
+    let outpoint = H256::repeat_byte(1);
+    let mut tracker = Tracker::new(2);
+    tracker.watch(outpoint);
+    for number in 1..=4 {
+        let created = if number == 3 { vec![(outpoint, deposit(50))] } else { vec![] };
+        tracker.import(block(number, 0, 0), created);
+    }
+
+    // A longer branch forking off block 2 is announced by its head, whose ancestors are missing
+    let head = block(5, 1, 2);
+    assert!(!tracker.connects(&head));
+    assert!(!tracker.connects(&block(4, 1, 2)));
+    assert!(tracker.connects(&block(3, 1, 2)));
+
+    // Its first block retracts the one creating the deposit, which is looked for again
+    assert_eq!(tracker.pending(&block(3, 1, 2)), vec![outpoint]);
+    assert_eq!(
+        tracker.import(block(3, 1, 2), vec![]),
+        vec![DepositEvent::Reorged { outpoint, block_hash: block(3, 0, 0).hash }],
+    );
+    assert_eq!(tracker.best_hash(4), None);
+    assert_eq!(
+        tracker.import(block(4, 1, 2), vec![(outpoint, deposit(50))]),
+        vec![DepositEvent::Included { outpoint, block_hash: block(4, 1, 2).hash, block_number: 4, output: deposit(50) }],
+    );
+    assert_eq!(
+        tracker.import(head, vec![]),
+        vec![DepositEvent::Confirmed { outpoint, block_hash: block(4, 1, 2).hash, confirmations: 2 }],
+    );
+
+    // Going back to a block of the best chain retracts its descendants only
+    assert_eq!(tracker.import(block(4, 1, 2), vec![]), vec![]);
+    assert_eq!(tracker.best_hash(5), None);
+    assert_eq!(tracker.best_hash(4), Some(block(4, 1, 2).hash));
+}
+
+#[test]
+fn finality_reports_and_forgets_deposits() {
+    let (early, late) = (H256::repeat_byte(1), H256::repeat_byte(2));
+    let mut tracker = Tracker::new(10);
+    tracker.watch(early);
+    tracker.watch(late);
+    tracker.import(block(1, 0, 0), vec![(early, deposit(5))]);
+    tracker.import(block(2, 0, 0), vec![]);
+    tracker.import(block(3, 0, 0), vec![(late, deposit(7))]);
+
+    // Blocks off the tracked best chain are ignored
+    assert_eq!(tracker.finalize(block(2, 1, 1).hash, 2), vec![]);
+    assert_eq!(
+        tracker.finalize(block(2, 0, 0).hash, 2),
+        vec![DepositEvent::Finalized { outpoint: early, block_hash: block(1, 0, 0).hash, block_number: 1 }],
+    );
+    assert!(!tracker.unwatch(&early));
+    assert_eq!(tracker.best_hash(1), None);
+
+    // Nothing older than the finalized block is asked for
+    assert!(tracker.connects(&block(2, 0, 0)));
+    assert!(tracker.connects(&block(1, 5, 0)));
+    assert!(tracker.unwatch(&late));
+    assert_eq!(tracker.pending(&block(4, 0, 0)), vec![]);
+}
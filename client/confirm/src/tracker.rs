@@ -0,0 +1,219 @@
+//! Confirmation bookkeeping for watched outputs, fed the blocks of the best chain in order.
+//!
+//! The [`Tracker`] does no I/O: [`crate::Follower`] feeds it from a node, and tests or other
+//! transports can feed it by hand.
+
+use std::collections::BTreeMap;
+
+use sp_core::H256;
+use utxo::TransactionOutput;
+
+use crate::BlockNumber;
+
+/// A block, with what links it to the chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockRef {
+	/// The block hash
+	pub hash: H256,
+	/// The block number
+	pub number: BlockNumber,
+	/// Hash of the parent block
+	pub parent: H256,
+}
+
+/// What happened to a watched output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DepositEvent {
+	/// The output was created in a block of the best chain
+	Included {
+		/// The watched outpoint
+		outpoint: H256,
+		/// The block creating the output
+		block_hash: H256,
+		/// Its number
+		block_number: BlockNumber,
+		/// The output, with the value deposited
+		output: TransactionOutput,
+	},
+	/// The block creating the output reached the required number of confirmations, counting
+	/// itself. Credit the deposit now.
+	Confirmed {
+		/// The watched outpoint
+		outpoint: H256,
+		/// The block creating the output
+		block_hash: H256,
+		/// Confirmations of the block
+		confirmations: u32,
+	},
+	/// The block creating the output left the best chain. Any credit given on [`Self::Confirmed`]
+	/// must be reverted: the output is watched for again, and is likely created again by a block
+	/// of the new best chain.
+	Reorged {
+		/// The watched outpoint
+		outpoint: H256,
+		/// The block that created the output and was reorged out
+		block_hash: H256,
+	},
+	/// The block creating the output was finalized, whatever its confirmations: the deposit can
+	/// no longer be reorged out, and the output is not watched any more.
+	Finalized {
+		/// The watched outpoint
+		outpoint: H256,
+		/// The block creating the output
+		block_hash: H256,
+		/// Its number
+		block_number: BlockNumber,
+	},
+}
+
+/// Where a watched output was created.
+#[derive(Debug, Clone)]
+struct Inclusion {
+	hash: H256,
+	number: BlockNumber,
+	confirmed: bool,
+}
+
+/// Tracks the confirmations of watched outputs over the best chain.
+///
+/// Blocks must be imported parent first. A block that does not extend the last one imported
+/// retracts the other blocks from its number on, reporting [`DepositEvent::Reorged`] for the outputs
+/// they created. An output already on chain when it is watched counts its confirmations from the
+/// first block it is seen in, which is never earlier than the block creating it.
+#[derive(Debug)]
+pub struct Tracker {
+	confirmations: u32,
+	/// The best chain from the last finalized block on
+	best: BTreeMap<BlockNumber, H256>,
+	deposits: BTreeMap<H256, Option<Inclusion>>,
+}
+
+impl Tracker {
+	/// A tracker confirming deposits after `confirmations` blocks, at least 1.
+	pub fn new(confirmations: u32) -> Self {
+		Self {
+			confirmations: confirmations.max(1),
+			best: BTreeMap::new(),
+			deposits: BTreeMap::new(),
+		}
+	}
+
+	/// Watch for the output at `outpoint`, unless it is watched already.
+	pub fn watch(&mut self, outpoint: H256) {
+		self.deposits.entry(outpoint).or_insert(None);
+	}
+
+	/// Stop watching `outpoint`. Returns whether it was watched.
+	pub fn unwatch(&mut self, outpoint: &H256) -> bool {
+		self.deposits.remove(outpoint).is_some()
+	}
+
+	/// The hash of block `number` of the best chain, if tracked.
+	pub fn best_hash(&self, number: BlockNumber) -> Option<H256> {
+		self.best.get(&number).copied()
+	}
+
+	/// Whether `block` can be imported without importing its parent first: it extends a tracked
+	/// block, or nothing tracked is older than it.
+	pub fn connects(&self, block: &BlockRef) -> bool {
+		let Some(parent_number) = block.number.checked_sub(1) else { return true };
+		match (self.best.first_key_value(), self.best.get(&parent_number)) {
+			(_, Some(parent)) => *parent == block.parent,
+			(Some((first, _)), None) => parent_number < *first,
+			(None, None) => true,
+		}
+	}
+
+	/// The watched outputs to look for in `block`: those not created by a block it builds on.
+	pub fn pending(&self, block: &BlockRef) -> Vec<H256> {
+		self.deposits
+			.iter()
+			.filter(|(_, deposit)| {
+				deposit.as_ref().map_or(true, |inclusion| inclusion.number >= block.number)
+			})
+			.map(|(outpoint, _)| *outpoint)
+			.collect()
+	}
+
+	/// Import `block` as the new best block, with the watched outputs it holds in its state that
+	/// its parent did not, as found by looking up [`Self::pending`].
+	pub fn import(
+		&mut self,
+		block: BlockRef,
+		created: Vec<(H256, TransactionOutput)>,
+	) -> Vec<DepositEvent> {
+		let mut events = Vec::new();
+
+		// A block imported again, e.g. when the best chain goes back to it, retracts its
+		// descendants only
+		let from = match self.best_hash(block.number) {
+			Some(hash) if hash == block.hash => block.number.saturating_add(1),
+			_ => block.number,
+		};
+		let retracted = self.best.split_off(&from);
+		for (outpoint, deposit) in &mut self.deposits {
+			let reorged = deposit
+				.as_ref()
+				.filter(|inclusion| retracted.get(&inclusion.number) == Some(&inclusion.hash));
+			if let Some(inclusion) = reorged {
+				let block_hash = inclusion.hash;
+				events.push(DepositEvent::Reorged { outpoint: *outpoint, block_hash });
+				*deposit = None;
+			}
+		}
+		self.best.insert(block.number, block.hash);
+
+		for (outpoint, output) in created {
+			// Unwatched meanwhile, or seen in a block this one builds on
+			let Some(deposit) = self.deposits.get_mut(&outpoint).filter(|deposit| deposit.is_none())
+			else {
+				continue
+			};
+			*deposit = Some(Inclusion { hash: block.hash, number: block.number, confirmed: false });
+			events.push(DepositEvent::Included {
+				outpoint,
+				block_hash: block.hash,
+				block_number: block.number,
+				output,
+			});
+		}
+
+		for (outpoint, deposit) in &mut self.deposits {
+			let Some(inclusion) = deposit.as_mut().filter(|inclusion| !inclusion.confirmed) else {
+				continue
+			};
+			let confirmations = block.number.saturating_sub(inclusion.number).saturating_add(1);
+			if confirmations >= self.confirmations {
+				inclusion.confirmed = true;
+				events.push(DepositEvent::Confirmed {
+					outpoint: *outpoint,
+					block_hash: inclusion.hash,
+					confirmations,
+				});
+			}
+		}
+		events
+	}
+
+	/// Record block `number`, of hash `hash`, as finalized. Reports and forgets the outputs
+	/// created up to it. Ignored unless the block is on the tracked best chain.
+	pub fn finalize(&mut self, hash: H256, number: BlockNumber) -> Vec<DepositEvent> {
+		if self.best_hash(number) != Some(hash) {
+			return Vec::new()
+		}
+		let mut events = Vec::new();
+		self.deposits.retain(|outpoint, deposit| match deposit {
+			Some(inclusion) if inclusion.number <= number => {
+				events.push(DepositEvent::Finalized {
+					outpoint: *outpoint,
+					block_hash: inclusion.hash,
+					block_number: inclusion.number,
+				});
+				false
+			},
+			_ => true,
+		});
+		self.best = self.best.split_off(&number);
+		events
+	}
+}
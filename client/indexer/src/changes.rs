@@ -0,0 +1,89 @@
+//! The rows a block adds to the index, derived from the events of the UTXO pallet.
+//!
+//! Every executed spend deposits a `TransactionSuccess` event carrying the whole transaction, so
+//! transactions, their spends and their outputs are read from the events alone. Outputs created
+//! by the runtime, rewards and mints, are read from the events reporting them too, but for the
+//! block rewards of `RewardsIssued`, whose owner is read from the state of the block.
+
+use frame_system::Phase;
+use sp_core::H256;
+use sp_runtime::traits::{BlakeTwo256, Hash};
+use tumuchain_client::{TransactionOutput, UtxoEvent};
+use tumuchain_runtime::{utxo, utxo::Value, BlockNumber};
+
+/// A row a block adds to the index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+	/// Transaction `txid` was executed, at `position` among the transactions of the block
+	Transaction { txid: H256, position: u32, fee: Value },
+	/// Output `outpoint` was created, by output `index` of transaction `txid` or by the runtime
+	Output { outpoint: H256, created_by: Option<(H256, u32)>, output: TransactionOutput },
+	/// Output `outpoint` was spent by input `index` of transaction `txid`
+	Spend { outpoint: H256, txid: H256, index: u32 },
+}
+
+/// The outpoints of the block rewards issued by `records`, whose outputs are read from the state.
+pub fn issued_rewards(records: &[(Phase, UtxoEvent)]) -> Vec<H256> {
+	records
+		.iter()
+		.filter_map(|(_, event)| match event {
+			utxo::Event::RewardsIssued { utxo_hash, .. } => Some(*utxo_hash),
+			_ => None,
+		})
+		.collect()
+}
+
+/// The changes of block `number` according to its UTXO event `records`, in order. `issued` gives
+/// the outputs of [`issued_rewards`]; those it has not, spent already, are left out.
+pub fn changes(
+	number: BlockNumber,
+	records: Vec<(Phase, UtxoEvent)>,
+	mut issued: impl FnMut(&H256) -> Option<TransactionOutput>,
+) -> Vec<Change> {
+	let mut changes = Vec::new();
+	let mut position = 0;
+	for (phase, event) in records {
+		match event {
+			utxo::Event::TransactionSuccess { transaction, fee, .. } => {
+				let txid = transaction.txid();
+				changes.push(Change::Transaction { txid, position, fee });
+				position += 1;
+				changes.extend(transaction.inputs.iter().enumerate().map(|(index, input)| {
+					Change::Spend { outpoint: input.outpoint, txid, index: index as u32 }
+				}));
+				changes.extend(transaction.outputs.iter().enumerate().map(|(index, output)| {
+					Change::Output {
+						outpoint: transaction.outpoint(index as u64),
+						created_by: Some((txid, index as u32)),
+						output: output.clone(),
+					}
+				}));
+			},
+			utxo::Event::RewardsIssued { utxo_hash, .. } =>
+				if let Some(output) = issued(&utxo_hash) {
+					changes.push(Change::Output { outpoint: utxo_hash, created_by: None, output });
+				},
+			utxo::Event::PendingRewardPaid { author, amount, utxo_hash } |
+			utxo::Event::UncleRewarded { author, amount, utxo_hash, .. } => {
+				let output = TransactionOutput { value: amount, pubkey: author, memo: None };
+				changes.push(Change::Output { outpoint: utxo_hash, created_by: None, output });
+			},
+			utxo::Event::Minted { outputs, .. } => {
+				let Phase::ApplyExtrinsic(extrinsic_index) = phase else { continue };
+				// Derived like the pallet's `mint` derives them
+				for (index, output) in outputs.iter().enumerate() {
+					let outpoint = BlakeTwo256::hash_of(&(
+						&outputs,
+						u64::from(number),
+						extrinsic_index,
+						index as u64,
+					));
+					let output = output.clone();
+					changes.push(Change::Output { outpoint, created_by: None, output });
+				}
+			},
+			_ => {},
+		}
+	}
+	changes
+}
@@ -0,0 +1,45 @@
+//! The indexer's configuration file.
+
+use std::{fs, net::SocketAddr, path::Path};
+
+use serde::Deserialize;
+use tumuchain_runtime::BlockNumber;
+
+use crate::{webhooks::Webhook, Error};
+
+/// Settings of the indexer, read from a TOML file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+	/// WebSocket URL of the node to index
+	#[serde(default = "default_node_url")]
+	pub node_url: String,
+	/// PostgreSQL connection string, such as `host=localhost user=indexer dbname=tumuchain`, or
+	/// URL
+	pub database_url: String,
+	/// First block to index into an empty database
+	#[serde(default)]
+	pub start_block: BlockNumber,
+	/// Index the finalized chain only rather than the best chain, so nothing is rolled back
+	#[serde(default)]
+	pub finalized_only: bool,
+	/// Address to serve the GraphQL API on, such as `127.0.0.1:8000`. Not served when unset
+	#[serde(default)]
+	pub graphql_listen: Option<SocketAddr>,
+	/// Webhooks notified of the outputs their pubkeys receive and spend
+	#[serde(default)]
+	pub webhooks: Vec<Webhook>,
+}
+
+impl Config {
+	/// Read the configuration file at `path`.
+	pub fn read(path: &Path) -> Result<Self, Error> {
+		let text = fs::read_to_string(path)
+			.map_err(|e| Error::Config(format!("cannot read {}: {e}", path.display())))?;
+		toml::from_str(&text).map_err(|e| Error::Config(format!("{}: {e}", path.display())))
+	}
+}
+
+fn default_node_url() -> String {
+	"ws://127.0.0.1:9944".into()
+}
@@ -0,0 +1,473 @@
+//! GraphQL API over the index, for explorers and frontends.
+//!
+//! Served by the indexer at `graphql_listen`, with GraphiQL on `GET /` to explore the schema:
+//!
+//! - `block(number, hash)` and `blocks(fromBlock, toBlock)`: blocks with their transactions
+//! - `transaction(txid)`: a transaction with the outputs it spent and created
+//! - `outputs(pubkey, memo, unspent, range)`: outputs matching the filters, such as the unspent
+//!   outputs of a pubkey, a slice of the UTXO set
+//! - `addressHistory(pubkey, range)`: the outputs a pubkey received and spent
+//!
+//! `range` filters by value, block number and block time. Lists are newest first, `first`
+//! entries after skipping `offset`. Hashes and pubkeys are 0x-prefixed hex, values decimal
+//! strings as they may not fit 64 bits, and times milliseconds since the Unix epoch. Outputs
+//! created before the index's first block are missing from the inputs of transactions.
+
+use std::net::SocketAddr;
+
+use async_graphql::{
+	http::GraphiQLSource, ComplexObject, Context, EmptyMutation, EmptySubscription, Enum,
+	InputObject, Object, Result, Schema, SimpleObject,
+};
+use async_graphql_axum::GraphQL;
+use axum::{response::Html, routing::get, Router};
+use sp_core::bytes;
+use tokio_postgres::{types::ToSql, Client, Row};
+
+/// Most entries of a list.
+const MAX_PAGE: u32 = 1000;
+
+/// Deepest query nesting allowed.
+const MAX_DEPTH: usize = 8;
+
+/// Columns of the outputs, as [`output`] reads them.
+const OUTPUT_COLUMNS: &str = "
+	SELECT o.outpoint, o.pubkey, o.value::TEXT, o.memo, o.txid, o.output_index,
+		b.number, b.hash, b.timestamp_ms, s.txid, s.input_index, sb.number, sb.hash
+	FROM outputs o
+	JOIN blocks b ON b.hash = o.block_hash
+	LEFT JOIN spends s ON s.outpoint = o.outpoint
+	LEFT JOIN blocks sb ON sb.hash = s.block_hash
+";
+
+/// Columns of the transactions, as [`transaction`] reads them.
+const TRANSACTION_COLUMNS: &str = "
+	SELECT t.txid, t.position, t.fee::TEXT, b.number, b.hash, b.timestamp_ms
+	FROM transactions t
+	JOIN blocks b ON b.hash = t.block_hash
+";
+
+/// Serve the API on `addr`, querying the index through `client`.
+pub async fn serve(addr: SocketAddr, client: Client) {
+	let schema = Schema::build(Query, EmptyMutation, EmptySubscription)
+		.data(client)
+		.limit_depth(MAX_DEPTH)
+		.finish();
+	let app = Router::new().route("/", get(graphiql).post_service(GraphQL::new(schema)));
+
+	let listener = match tokio::net::TcpListener::bind(addr).await {
+		Ok(listener) => listener,
+		Err(e) => {
+			log::error!("Cannot listen for GraphQL on {addr}: {e}");
+			return
+		},
+	};
+	log::info!("Serving GraphQL on http://{addr}");
+	if let Err(e) = axum::serve(listener, app).await {
+		log::error!("GraphQL server on {addr} failed: {e}");
+	}
+}
+
+async fn graphiql() -> Html<String> {
+	Html(GraphiQLSource::build().endpoint("/").finish())
+}
+
+/// A block of the index.
+#[derive(SimpleObject)]
+#[graphql(complex)]
+struct Block {
+	hash: String,
+	number: u32,
+	parent_hash: String,
+	timestamp: Option<i64>,
+}
+
+#[ComplexObject]
+impl Block {
+	/// The transactions of the block, in order.
+	async fn transactions(&self, ctx: &Context<'_>) -> Result<Vec<Transaction>> {
+		let mut filter = Conditions::default();
+		filter.add("b.hash = $", hash(&self.hash)?);
+		let query = format!("{TRANSACTION_COLUMNS} {} ORDER BY t.position", filter.sql());
+		let rows = ctx.data::<Client>()?.query(&query, &filter.params()).await?;
+		Ok(rows.iter().map(transaction).collect())
+	}
+}
+
+/// A transaction of the index.
+#[derive(SimpleObject)]
+#[graphql(complex)]
+struct Transaction {
+	txid: String,
+	/// Position among the transactions of the block
+	position: i32,
+	fee: String,
+	block_number: u32,
+	block_hash: String,
+	timestamp: Option<i64>,
+}
+
+#[ComplexObject]
+impl Transaction {
+	/// The outputs the transaction spent, in the order of its inputs.
+	async fn inputs(&self, ctx: &Context<'_>) -> Result<Vec<Output>> {
+		outputs_where(ctx, "s.txid = $", hash(&self.txid)?, "s.input_index").await
+	}
+
+	/// The outputs the transaction created, in order.
+	async fn outputs(&self, ctx: &Context<'_>) -> Result<Vec<Output>> {
+		outputs_where(ctx, "o.txid = $", hash(&self.txid)?, "o.output_index").await
+	}
+}
+
+/// An output of the index.
+#[derive(SimpleObject)]
+struct Output {
+	outpoint: String,
+	pubkey: String,
+	value: String,
+	memo: Option<String>,
+	/// The transaction creating the output, none for rewards and mints
+	txid: Option<String>,
+	output_index: Option<i32>,
+	block_number: u32,
+	block_hash: String,
+	timestamp: Option<i64>,
+	/// The spend of the output, none while it is unspent
+	spent_by: Option<Spend>,
+}
+
+/// The spend of an output.
+#[derive(SimpleObject)]
+struct Spend {
+	txid: String,
+	input_index: i32,
+	block_number: u32,
+	block_hash: String,
+}
+
+/// Whether an entry of an address history received or spent an output.
+#[derive(Enum, Clone, Copy, PartialEq, Eq)]
+enum HistoryKind {
+	Received,
+	Spent,
+}
+
+/// An output received or spent by a pubkey.
+#[derive(SimpleObject)]
+struct HistoryEntry {
+	kind: HistoryKind,
+	outpoint: String,
+	value: String,
+	/// The transaction receiving or spending the output, none for rewards and mints received
+	txid: Option<String>,
+	block_number: u32,
+	block_hash: String,
+	timestamp: Option<i64>,
+}
+
+/// Bounds of values, block numbers and block times, all inclusive.
+#[derive(InputObject, Default)]
+struct Range {
+	min_value: Option<String>,
+	max_value: Option<String>,
+	from_block: Option<u32>,
+	to_block: Option<u32>,
+	/// Milliseconds since the Unix epoch
+	from_time: Option<i64>,
+	/// Milliseconds since the Unix epoch
+	to_time: Option<i64>,
+}
+
+impl Range {
+	/// Add the bounds to `filter`, on the columns `value`, `number` and `timestamp`.
+	fn apply(
+		&self,
+		filter: &mut Conditions,
+		value: &str,
+		number: &str,
+		timestamp: &str,
+	) -> Result<()> {
+		if let Some(min) = &self.min_value {
+			filter.add(&format!("{value} >= $::TEXT::NUMERIC"), decimal(min)?);
+		}
+		if let Some(max) = &self.max_value {
+			filter.add(&format!("{value} <= $::TEXT::NUMERIC"), decimal(max)?);
+		}
+		if let Some(from) = self.from_block {
+			filter.add(&format!("{number} >= $"), i64::from(from));
+		}
+		if let Some(to) = self.to_block {
+			filter.add(&format!("{number} <= $"), i64::from(to));
+		}
+		if let Some(from) = self.from_time {
+			filter.add(&format!("{timestamp} >= $"), from);
+		}
+		if let Some(to) = self.to_time {
+			filter.add(&format!("{timestamp} <= $"), to);
+		}
+		Ok(())
+	}
+}
+
+struct Query;
+
+#[Object]
+impl Query {
+	/// A block, by number or by hash.
+	async fn block(
+		&self,
+		ctx: &Context<'_>,
+		number: Option<u32>,
+		hash: Option<String>,
+	) -> Result<Option<Block>> {
+		let mut filter = Conditions::default();
+		match (number, hash) {
+			(Some(number), None) => filter.add("number = $", i64::from(number)),
+			(None, Some(hash)) => filter.add("hash = $", self::hash(&hash)?),
+			_ => return Err("give either `number` or `hash`".into()),
+		}
+		let query = format!(
+			"SELECT hash, number, parent_hash, timestamp_ms FROM blocks {}",
+			filter.sql()
+		);
+		let row = ctx.data::<Client>()?.query_opt(&query, &filter.params()).await?;
+		Ok(row.as_ref().map(block))
+	}
+
+	/// Blocks between `fromBlock` and `toBlock`, newest first.
+	async fn blocks(
+		&self,
+		ctx: &Context<'_>,
+		from_block: Option<u32>,
+		to_block: Option<u32>,
+		#[graphql(default = 20)] first: u32,
+		#[graphql(default)] offset: u32,
+	) -> Result<Vec<Block>> {
+		let mut filter = Conditions::default();
+		if let Some(from) = from_block {
+			filter.add("number >= $", i64::from(from));
+		}
+		if let Some(to) = to_block {
+			filter.add("number <= $", i64::from(to));
+		}
+		let query = format!(
+			"SELECT hash, number, parent_hash, timestamp_ms FROM blocks {} \
+			 ORDER BY number DESC {}",
+			filter.sql(),
+			page(first, offset),
+		);
+		let rows = ctx.data::<Client>()?.query(&query, &filter.params()).await?;
+		Ok(rows.iter().map(block).collect())
+	}
+
+	/// A transaction, by txid.
+	async fn transaction(&self, ctx: &Context<'_>, txid: String) -> Result<Option<Transaction>> {
+		let mut filter = Conditions::default();
+		filter.add("t.txid = $", hash(&txid)?);
+		let query = format!("{TRANSACTION_COLUMNS} {}", filter.sql());
+		let row = ctx.data::<Client>()?.query_opt(&query, &filter.params()).await?;
+		Ok(row.as_ref().map(transaction))
+	}
+
+	/// Outputs paying `pubkey`, carrying `memo`, unspent or spent as `unspent` says and within
+	/// `range`, newest first.
+	#[allow(clippy::too_many_arguments)]
+	async fn outputs(
+		&self,
+		ctx: &Context<'_>,
+		pubkey: Option<String>,
+		memo: Option<String>,
+		unspent: Option<bool>,
+		#[graphql(default)] range: Range,
+		#[graphql(default = 20)] first: u32,
+		#[graphql(default)] offset: u32,
+	) -> Result<Vec<Output>> {
+		let mut filter = Conditions::default();
+		if let Some(pubkey) = pubkey {
+			filter.add("o.pubkey = $", hash(&pubkey)?);
+		}
+		if let Some(memo) = memo {
+			filter.add("o.memo = $", hash(&memo)?);
+		}
+		match unspent {
+			Some(true) => filter.push("s.outpoint IS NULL"),
+			Some(false) => filter.push("s.outpoint IS NOT NULL"),
+			None => {},
+		}
+		range.apply(&mut filter, "o.value", "b.number", "b.timestamp_ms")?;
+		let query = format!(
+			"{OUTPUT_COLUMNS} {} ORDER BY b.number DESC, o.outpoint {}",
+			filter.sql(),
+			page(first, offset),
+		);
+		let rows = ctx.data::<Client>()?.query(&query, &filter.params()).await?;
+		Ok(rows.iter().map(output).collect())
+	}
+
+	/// The outputs `pubkey` received and spent within `range`, newest first.
+	async fn address_history(
+		&self,
+		ctx: &Context<'_>,
+		pubkey: String,
+		#[graphql(default)] range: Range,
+		#[graphql(default = 20)] first: u32,
+		#[graphql(default)] offset: u32,
+	) -> Result<Vec<HistoryEntry>> {
+		let mut filter = Conditions::default();
+		let pubkey = filter.param(hash(&pubkey)?);
+		range.apply(&mut filter, "h.value", "h.number", "h.timestamp_ms")?;
+		let query = format!(
+			"SELECT h.kind, h.outpoint, h.value::TEXT, h.txid, h.number, h.hash, h.timestamp_ms
+			FROM (
+				SELECT 'received' AS kind, o.outpoint, o.value, o.txid, b.number, b.hash,
+					b.timestamp_ms
+				FROM outputs o JOIN blocks b ON b.hash = o.block_hash
+				WHERE o.pubkey = {pubkey}
+				UNION ALL
+				SELECT 'spent', o.outpoint, o.value, s.txid, b.number, b.hash, b.timestamp_ms
+				FROM spends s
+				JOIN outputs o ON o.outpoint = s.outpoint
+				JOIN blocks b ON b.hash = s.block_hash
+				WHERE o.pubkey = {pubkey}
+			) h {}
+			ORDER BY h.number DESC, h.kind DESC, h.outpoint {}",
+			filter.sql(),
+			page(first, offset),
+		);
+		let rows = ctx.data::<Client>()?.query(&query, &filter.params()).await?;
+		Ok(rows
+			.iter()
+			.map(|row| HistoryEntry {
+				kind: match row.get::<_, &str>(0) {
+					"spent" => HistoryKind::Spent,
+					_ => HistoryKind::Received,
+				},
+				outpoint: hex(row.get(1)),
+				value: row.get(2),
+				txid: row.get::<_, Option<&[u8]>>(3).map(hex),
+				block_number: row.get::<_, i64>(4) as u32,
+				block_hash: hex(row.get(5)),
+				timestamp: row.get(6),
+			})
+			.collect())
+	}
+}
+
+/// The outputs matching `condition` on `param`, ordered by `order`.
+async fn outputs_where(
+	ctx: &Context<'_>,
+	condition: &str,
+	param: Vec<u8>,
+	order: &str,
+) -> Result<Vec<Output>> {
+	let mut filter = Conditions::default();
+	filter.add(condition, param);
+	let query = format!("{OUTPUT_COLUMNS} {} ORDER BY {order}", filter.sql());
+	let rows = ctx.data::<Client>()?.query(&query, &filter.params()).await?;
+	Ok(rows.iter().map(output).collect())
+}
+
+/// The conditions of a query and their parameters.
+#[derive(Default)]
+struct Conditions {
+	conditions: Vec<String>,
+	params: Vec<Box<dyn ToSql + Sync + Send>>,
+}
+
+impl Conditions {
+	/// Add the parameter `param`, returning its placeholder.
+	fn param(&mut self, param: impl ToSql + Sync + Send + 'static) -> String {
+		self.params.push(Box::new(param));
+		format!("${}", self.params.len())
+	}
+
+	/// Add `condition`, in which `$` stands for the parameter `param`.
+	fn add(&mut self, condition: &str, param: impl ToSql + Sync + Send + 'static) {
+		let placeholder = self.param(param);
+		self.conditions.push(condition.replace('$', &placeholder));
+	}
+
+	/// Add `condition`, which takes no parameter.
+	fn push(&mut self, condition: &str) {
+		self.conditions.push(condition.into());
+	}
+
+	/// The `WHERE` clause of the conditions.
+	fn sql(&self) -> String {
+		match self.conditions.is_empty() {
+			true => String::new(),
+			false => format!("WHERE {}", self.conditions.join(" AND ")),
+		}
+	}
+
+	fn params(&self) -> Vec<&(dyn ToSql + Sync)> {
+		self.params.iter().map(|param| param.as_ref() as &(dyn ToSql + Sync)).collect()
+	}
+}
+
+/// The `LIMIT` and `OFFSET` of a page of `first` entries after `offset`.
+fn page(first: u32, offset: u32) -> String {
+	format!("LIMIT {} OFFSET {offset}", first.min(MAX_PAGE))
+}
+
+fn block(row: &Row) -> Block {
+	Block {
+		hash: hex(row.get(0)),
+		number: row.get::<_, i64>(1) as u32,
+		parent_hash: hex(row.get(2)),
+		timestamp: row.get(3),
+	}
+}
+
+fn transaction(row: &Row) -> Transaction {
+	Transaction {
+		txid: hex(row.get(0)),
+		position: row.get(1),
+		fee: row.get(2),
+		block_number: row.get::<_, i64>(3) as u32,
+		block_hash: hex(row.get(4)),
+		timestamp: row.get(5),
+	}
+}
+
+fn output(row: &Row) -> Output {
+	let spent_by = row.get::<_, Option<&[u8]>>(9).map(|txid| Spend {
+		txid: hex(txid),
+		input_index: row.get(10),
+		block_number: row.get::<_, i64>(11) as u32,
+		block_hash: hex(row.get(12)),
+	});
+	Output {
+		outpoint: hex(row.get(0)),
+		pubkey: hex(row.get(1)),
+		value: row.get(2),
+		memo: row.get::<_, Option<&[u8]>>(3).map(hex),
+		txid: row.get::<_, Option<&[u8]>>(4).map(hex),
+		output_index: row.get(5),
+		block_number: row.get::<_, i64>(6) as u32,
+		block_hash: hex(row.get(7)),
+		timestamp: row.get(8),
+		spent_by,
+	}
+}
+
+fn hex(bytes: &[u8]) -> String {
+	bytes::to_hex(bytes, false)
+}
+
+/// The 32 bytes of the hash or pubkey `hex`.
+fn hash(hex: &str) -> Result<Vec<u8>> {
+	match bytes::from_hex(hex) {
+		Ok(bytes) if bytes.len() == 32 => Ok(bytes),
+		_ => Err(format!("{hex} is not a 0x-prefixed 32-byte hex string").into()),
+	}
+}
+
+/// The decimal value `value`, checked.
+fn decimal(value: &str) -> Result<String> {
+	value
+		.parse::<u128>()
+		.map(|value| value.to_string())
+		.map_err(|_| format!("{value} is not a decimal value").into())
+}
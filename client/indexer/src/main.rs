@@ -0,0 +1,184 @@
+//! Indexer of tumuchain blocks into PostgreSQL, the backbone of explorers and analytics.
+//!
+//! The indexer follows the best chain of a node, or its finalized chain, and writes the blocks,
+//! transactions, outputs and spends of each to PostgreSQL, as laid out in [`store`]. When the
+//! node's chain reorganizes, the blocks indexed off the new chain are rolled back through the undo
+//! log, newest first, and the blocks of the new chain applied. On restart it carries on from the
+//! last block indexed. The index can be queried through the GraphQL API of [`graphql`], and
+//! servers notified of the outputs of watched pubkeys through [`webhooks`]. Settings are read from
+//! a TOML file, `indexer.toml` by default:
+//!
+//! ```sh
+//! tumuchain-indexer --config client/indexer/indexer.toml
+//! ```
+
+use std::{collections::HashMap, fmt, path::PathBuf};
+
+use clap::Parser;
+use futures::StreamExt;
+use sp_core::H256;
+use tumuchain_client::Client;
+use tumuchain_runtime::BlockNumber;
+
+use crate::{config::Config, store::Store};
+
+mod changes;
+mod config;
+mod graphql;
+mod store;
+mod webhooks;
+
+#[cfg(test)]
+mod tests;
+
+/// Blocks indexed between two progress reports while catching up.
+const PROGRESS_INTERVAL: BlockNumber = 1000;
+
+/// Index the chain of a tumuchain node into PostgreSQL.
+#[derive(Debug, Parser)]
+struct Cli {
+	/// Configuration file, in TOML.
+	#[arg(long, short, value_name = "PATH", default_value = "indexer.toml")]
+	config: PathBuf,
+}
+
+/// Reasons indexing stops.
+#[derive(Debug)]
+enum Error {
+	/// The configuration file is missing or invalid
+	Config(String),
+	/// A request or subscription to the node failed
+	Node(tumuchain_client::Error),
+	/// A query to the database failed
+	Database(tokio_postgres::Error),
+	/// The node closed the subscription to new heads
+	SubscriptionClosed,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Config(e) => write!(f, "invalid configuration: {e}"),
+			Self::Node(e) => write!(f, "node request failed: {e}"),
+			Self::Database(e) => write!(f, "database query failed: {e}"),
+			Self::SubscriptionClosed => write!(f, "the node closed the subscription"),
+		}
+	}
+}
+
+impl std::error::Error for Error {}
+
+impl From<tumuchain_client::Error> for Error {
+	fn from(e: tumuchain_client::Error) -> Self {
+		Self::Node(e)
+	}
+}
+
+impl From<tokio_postgres::Error> for Error {
+	fn from(e: tokio_postgres::Error) -> Self {
+		Self::Database(e)
+	}
+}
+
+#[tokio::main]
+async fn main() {
+	env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+	if let Err(e) = run(Cli::parse()).await {
+		log::error!("{e}");
+		std::process::exit(1);
+	}
+}
+
+async fn run(cli: Cli) -> Result<(), Error> {
+	let config = Config::read(&cli.config)?;
+	let client = Client::connect(&config.node_url).await?;
+	let store = Store::connect(&config.database_url, config.webhooks.clone()).await?;
+	if let Some(addr) = config.graphql_listen {
+		tokio::spawn(graphql::serve(addr, store::connect(&config.database_url).await?));
+	}
+	if !config.webhooks.is_empty() {
+		let client = store::connect(&config.database_url).await?;
+		tokio::spawn(webhooks::deliver(client, config.webhooks.clone()));
+	}
+	Indexer { client, store, config }.run().await
+}
+
+/// Keeps the index on the chain of the node.
+struct Indexer {
+	client: Client,
+	store: Store,
+	config: Config,
+}
+
+impl Indexer {
+	/// Index the blocks of the node up to each head it announces, until it disconnects.
+	async fn run(&mut self) -> Result<(), Error> {
+		let blocks = self.client.subxt().blocks();
+		let mut heads = match self.config.finalized_only {
+			true => blocks.subscribe_finalized().await?,
+			false => blocks.subscribe_best().await?,
+		};
+		while let Some(head) = heads.next().await {
+			self.sync(head?.number()).await?;
+		}
+		Err(Error::SubscriptionClosed)
+	}
+
+	/// Bring the index to block `head` of the node's chain.
+	async fn sync(&mut self, head: BlockNumber) -> Result<(), Error> {
+		loop {
+			// Back to the last block indexed the node's chain still has
+			let mut tip = self.store.tip().await?;
+			while let Some((number, hash)) = tip {
+				if self.client.block_hash(number).await? == Some(hash) {
+					break
+				}
+				log::info!("Rolling back block #{number} ({hash:?}), reorged out");
+				self.store.rollback(hash).await?;
+				tip = self.store.tip().await?;
+			}
+
+			let (mut next, mut parent) = match tip {
+				Some((number, hash)) => (number + 1, Some(hash)),
+				None => (self.config.start_block, None),
+			};
+			while next <= head {
+				// The node's chain may have reorganized since, and be shorter now
+				let Some(hash) = self.client.block_hash(next).await? else { return Ok(()) };
+				let block = self.block(hash, next).await?;
+				if parent.is_some_and(|parent| parent != block.parent_hash) {
+					break
+				}
+				self.store.apply(&block).await?;
+				if next == head || next % PROGRESS_INTERVAL == 0 {
+					log::info!("Indexed block #{next} ({hash:?})");
+				}
+				parent = Some(hash);
+				next += 1;
+			}
+			if next > head {
+				return Ok(())
+			}
+		}
+	}
+
+	/// Block `number` of hash `hash`, read from the node.
+	async fn block(&self, hash: H256, number: BlockNumber) -> Result<store::Block, Error> {
+		let parent_hash = self.client.subxt().blocks().at(hash).await?.header().parent_hash;
+		let state = self.client.at(hash);
+		let records = state.utxo_event_records().await?;
+		let mut issued = HashMap::new();
+		for outpoint in changes::issued_rewards(&records) {
+			if let Some(output) = state.utxo(outpoint).await? {
+				issued.insert(outpoint, output);
+			}
+		}
+		Ok(store::Block {
+			hash,
+			number,
+			parent_hash,
+			timestamp: state.timestamp().await?,
+			changes: changes::changes(number, records, |outpoint| issued.remove(outpoint)),
+		})
+	}
+}
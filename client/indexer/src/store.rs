@@ -0,0 +1,234 @@
+//! The PostgreSQL index, and its undo log.
+//!
+//! The index holds a single chain of blocks, the node's best or finalized chain, with the
+//! transactions, outputs and spends of each. Rows are only ever added by applying a block, each
+//! recorded in `undo_log` under the hash of the block adding it. Rolling a block back deletes its
+//! rows in the reverse order of the log, then the block itself. A block is applied or rolled back
+//! in a single database transaction, so the index is never left with part of a block.
+//!
+//! Unspent outputs are those without a row in `spends`. The notifications of [`webhooks`] are
+//! queued in `webhook_deliveries` as blocks confirm them, and not rolled back.
+
+use sp_core::H256;
+use tokio_postgres::{Client, NoTls};
+use tumuchain_runtime::BlockNumber;
+
+use crate::{changes::Change, webhooks, webhooks::Webhook};
+
+/// Tables of the index, created on startup where missing.
+const SCHEMA: &str = "
+	CREATE TABLE IF NOT EXISTS blocks (
+		hash BYTEA PRIMARY KEY,
+		number BIGINT NOT NULL UNIQUE,
+		parent_hash BYTEA NOT NULL,
+		timestamp_ms BIGINT
+	);
+	CREATE TABLE IF NOT EXISTS transactions (
+		txid BYTEA PRIMARY KEY,
+		block_hash BYTEA NOT NULL REFERENCES blocks (hash),
+		position INTEGER NOT NULL,
+		fee NUMERIC(39, 0) NOT NULL
+	);
+	CREATE TABLE IF NOT EXISTS outputs (
+		outpoint BYTEA PRIMARY KEY,
+		block_hash BYTEA NOT NULL REFERENCES blocks (hash),
+		txid BYTEA,
+		output_index INTEGER,
+		pubkey BYTEA NOT NULL,
+		value NUMERIC(39, 0) NOT NULL,
+		memo BYTEA
+	);
+	CREATE INDEX IF NOT EXISTS outputs_pubkey ON outputs (pubkey);
+	CREATE INDEX IF NOT EXISTS outputs_memo ON outputs (memo) WHERE memo IS NOT NULL;
+	CREATE TABLE IF NOT EXISTS spends (
+		outpoint BYTEA PRIMARY KEY,
+		block_hash BYTEA NOT NULL REFERENCES blocks (hash),
+		txid BYTEA NOT NULL,
+		input_index INTEGER NOT NULL
+	);
+	CREATE INDEX IF NOT EXISTS spends_txid ON spends (txid);
+	CREATE TABLE IF NOT EXISTS undo_log (
+		block_hash BYTEA NOT NULL REFERENCES blocks (hash),
+		seq INTEGER NOT NULL,
+		table_name TEXT NOT NULL,
+		row_key BYTEA NOT NULL,
+		PRIMARY KEY (block_hash, seq)
+	);
+	CREATE TABLE IF NOT EXISTS webhook_deliveries (
+		id BIGSERIAL PRIMARY KEY,
+		url TEXT NOT NULL,
+		body TEXT NOT NULL,
+		attempts INTEGER NOT NULL DEFAULT 0,
+		next_attempt TIMESTAMPTZ NOT NULL DEFAULT now()
+	);
+";
+
+/// A block to apply to the index.
+pub struct Block {
+	pub hash: H256,
+	pub number: BlockNumber,
+	pub parent_hash: H256,
+	/// Milliseconds since the Unix epoch, if the block has a timestamp
+	pub timestamp: Option<u64>,
+	pub changes: Vec<Change>,
+}
+
+/// The tables blocks add rows to.
+#[derive(Debug, Clone, Copy)]
+enum Table {
+	Transactions,
+	Outputs,
+	Spends,
+}
+
+impl Table {
+	fn name(self) -> &'static str {
+		match self {
+			Self::Transactions => "transactions",
+			Self::Outputs => "outputs",
+			Self::Spends => "spends",
+		}
+	}
+
+	fn of(name: &str) -> Option<Self> {
+		[Self::Transactions, Self::Outputs, Self::Spends].into_iter().find(|t| t.name() == name)
+	}
+
+	/// The statement deleting the row of a key.
+	fn delete(self) -> &'static str {
+		match self {
+			Self::Transactions => "DELETE FROM transactions WHERE txid = $1",
+			Self::Outputs => "DELETE FROM outputs WHERE outpoint = $1",
+			Self::Spends => "DELETE FROM spends WHERE outpoint = $1",
+		}
+	}
+}
+
+/// Connect to the database of `config`, a libpq connection string or URL.
+pub async fn connect(config: &str) -> Result<Client, tokio_postgres::Error> {
+	let (client, connection) = tokio_postgres::connect(config, NoTls).await?;
+	tokio::spawn(async move {
+		if let Err(e) = connection.await {
+			log::error!("Database connection failed: {e}");
+		}
+	});
+	Ok(client)
+}
+
+/// A connection to the index.
+pub struct Store {
+	client: Client,
+	webhooks: Vec<Webhook>,
+}
+
+impl Store {
+	/// Connect to the database of `config`, a libpq connection string or URL, creating the
+	/// tables of the index where missing. Applying blocks queues the notifications of `webhooks`.
+	pub async fn connect(
+		config: &str,
+		webhooks: Vec<Webhook>,
+	) -> Result<Self, tokio_postgres::Error> {
+		let client = connect(config).await?;
+		client.batch_execute(SCHEMA).await?;
+		Ok(Self { client, webhooks })
+	}
+
+	/// The number and hash of the last block indexed, if any.
+	pub async fn tip(&self) -> Result<Option<(BlockNumber, H256)>, tokio_postgres::Error> {
+		let row = self
+			.client
+			.query_opt("SELECT number, hash FROM blocks ORDER BY number DESC LIMIT 1", &[])
+			.await?;
+		Ok(row.map(|row| (row.get::<_, i64>(0) as BlockNumber, H256::from_slice(row.get(1)))))
+	}
+
+	/// Add `block`, a child of the last block indexed, and its rows to the index.
+	pub async fn apply(&mut self, block: &Block) -> Result<(), tokio_postgres::Error> {
+		let tx = self.client.transaction().await?;
+		let block_hash = block.hash.as_bytes();
+		tx.execute(
+			"INSERT INTO blocks (hash, number, parent_hash, timestamp_ms) VALUES ($1, $2, $3, $4)",
+			&[
+				&block_hash,
+				&i64::from(block.number),
+				&block.parent_hash.as_bytes(),
+				&block.timestamp.map(|ms| ms as i64),
+			],
+		)
+		.await?;
+
+		for (seq, change) in block.changes.iter().enumerate() {
+			let (table, key) = match change {
+				Change::Transaction { txid, position, fee } => {
+					tx.execute(
+						"INSERT INTO transactions (txid, block_hash, position, fee) \
+						 VALUES ($1, $2, $3, $4::TEXT::NUMERIC)",
+						&[&txid.as_bytes(), &block_hash, &(*position as i32), &fee.to_string()],
+					)
+					.await?;
+					(Table::Transactions, txid)
+				},
+				Change::Output { outpoint, created_by, output } => {
+					tx.execute(
+						"INSERT INTO outputs \
+						 (outpoint, block_hash, txid, output_index, pubkey, value, memo) \
+						 VALUES ($1, $2, $3, $4, $5, $6::TEXT::NUMERIC, $7)",
+						&[
+							&outpoint.as_bytes(),
+							&block_hash,
+							&created_by.as_ref().map(|(txid, _)| txid.as_bytes()),
+							&created_by.map(|(_, index)| index as i32),
+							&output.pubkey.as_bytes(),
+							&output.value.to_string(),
+							&output.memo.as_ref().map(|memo| memo.as_bytes()),
+						],
+					)
+					.await?;
+					(Table::Outputs, outpoint)
+				},
+				Change::Spend { outpoint, txid, index } => {
+					tx.execute(
+						"INSERT INTO spends (outpoint, block_hash, txid, input_index) \
+						 VALUES ($1, $2, $3, $4)",
+						&[&outpoint.as_bytes(), &block_hash, &txid.as_bytes(), &(*index as i32)],
+					)
+					.await?;
+					(Table::Spends, outpoint)
+				},
+			};
+			tx.execute(
+				"INSERT INTO undo_log (block_hash, seq, table_name, row_key) \
+				 VALUES ($1, $2, $3, $4)",
+				&[&block_hash, &(seq as i32), &table.name(), &key.as_bytes()],
+			)
+			.await?;
+		}
+		webhooks::enqueue(&tx, &self.webhooks, block.number).await?;
+		tx.commit().await
+	}
+
+	/// Remove the block `hash`, the last block indexed, and its rows from the index.
+	pub async fn rollback(&mut self, hash: H256) -> Result<(), tokio_postgres::Error> {
+		let tx = self.client.transaction().await?;
+		let block_hash = hash.as_bytes();
+		let undo = tx
+			.query(
+				"SELECT table_name, row_key FROM undo_log WHERE block_hash = $1 ORDER BY seq DESC",
+				&[&block_hash],
+			)
+			.await?;
+		for row in undo {
+			let name: &str = row.get(0);
+			let key: &[u8] = row.get(1);
+			match Table::of(name) {
+				Some(table) => {
+					tx.execute(table.delete(), &[&key]).await?;
+				},
+				None => log::warn!("Undo log of block {hash:?} names unknown table {name}"),
+			}
+		}
+		tx.execute("DELETE FROM undo_log WHERE block_hash = $1", &[&block_hash]).await?;
+		tx.execute("DELETE FROM blocks WHERE hash = $1", &[&block_hash]).await?;
+		tx.commit().await
+	}
+}
@@ -0,0 +1,91 @@
+use crate::{
+    changes::{changes, issued_rewards, Change},
+    webhooks::{backoff, signature},
+};
+use frame_system::Phase;
+use std::time::Duration;
+use sp_core::H256;
+use sp_runtime::{
+    traits::{BlakeTwo256, Hash},
+    BoundedVec,
+};
+use tumuchain_runtime::utxo::{self, Transaction, TransactionInput, TransactionOutput, TRANSACTION_VERSION};
+
+fn output(value: u128, owner: u8) -> TransactionOutput {
+    TransactionOutput { value, pubkey: H256::repeat_byte(owner), memo: None }
+}
+
+fn transaction(spent: u8) -> Transaction {
+    Transaction {
+        version: TRANSACTION_VERSION,
+        inputs: vec![TransactionInput { outpoint: H256::repeat_byte(spent), sigscript: None, witness: None }],
+        outputs: vec![output(40, 2), output(50, 3)],
+    }
+}
+
+fn success(transaction: Transaction) -> utxo::Event<tumuchain_runtime::Runtime> {
+    utxo::Event::TransactionSuccess { transaction, input_total: 100, output_total: 90, fee: 10 }
+}
+
+#[test]
+fn transactions_add_their_spends_and_outputs() {
+    let (first, second) = (transaction(1), transaction(2));
+    let records = vec![(Phase::ApplyExtrinsic(1), success(first.clone())), (Phase::ApplyExtrinsic(2), success(second.clone()))];
+
+    let changes = changes(5, records, |_| None);
+    assert_eq!(changes.len(), 8);
+    assert_eq!(
+        changes[..4],
+        [
+            Change::Transaction { txid: first.txid(), position: 0, fee: 10 },
+            Change::Spend { outpoint: H256::repeat_byte(1), txid: first.txid(), index: 0 },
+            Change::Output { outpoint: first.outpoint(0), created_by: Some((first.txid(), 0)), output: output(40, 2) },
+            Change::Output { outpoint: first.outpoint(1), created_by: Some((first.txid(), 1)), output: output(50, 3) },
+        ],
+    );
+    assert_eq!(changes[4], Change::Transaction { txid: second.txid(), position: 1, fee: 10 });
+}
+
+#[test]
+fn runtime_outputs_are_added_without_transaction() {
+    let minted: BoundedVec<_, _> = vec![output(7, 4), output(8, 5)].try_into().unwrap();
+    let records = vec![
+        (Phase::ApplyExtrinsic(2), utxo::Event::Minted { outputs: minted.clone(), total: 15 }),
+        (Phase::Finalization, utxo::Event::PendingRewardPaid { author: H256::repeat_byte(6), amount: 3, utxo_hash: H256::repeat_byte(7) }),
+        (Phase::Finalization, utxo::Event::RewardsIssued { amount: 9, utxo_hash: H256::repeat_byte(8) }),
+        (Phase::Finalization, utxo::Event::RewardsIssued { amount: 9, utxo_hash: H256::repeat_byte(9) }),
+        (Phase::Finalization, utxo::Event::RewardsWasted),
+    ];
+    assert_eq!(issued_rewards(&records), vec![H256::repeat_byte(8), H256::repeat_byte(9)]);
+
+    // The second reward was spent already, and is not read from the state
+    let issued = |outpoint: &H256| (*outpoint == H256::repeat_byte(8)).then(|| output(9, 6));
+    let minted_at = |index: u64| BlakeTwo256::hash_of(&(&minted, 12u64, 2u32, index));
+    assert_eq!(
+        changes(12, records, issued),
+        vec![
+            Change::Output { outpoint: minted_at(0), created_by: None, output: output(7, 4) },
+            Change::Output { outpoint: minted_at(1), created_by: None, output: output(8, 5) },
+            Change::Output { outpoint: H256::repeat_byte(7), created_by: None, output: output(3, 6) },
+            Change::Output { outpoint: H256::repeat_byte(8), created_by: None, output: output(9, 6) },
+        ],
+    );
+}
+
+#[test]
+fn webhook_signature_is_hmac_sha256_of_body() {
+    // RFC 4231, test case 2
+    assert_eq!(
+        signature("Jefe", "what do ya want for nothing?"),
+        "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+    );
+}
+
+#[test]
+fn webhook_backoff_doubles_up_to_an_hour() {
+    assert_eq!(backoff(0), Duration::from_secs(10));
+    assert_eq!(backoff(1), Duration::from_secs(20));
+    assert_eq!(backoff(5), Duration::from_secs(320));
+    assert_eq!(backoff(9), Duration::from_secs(3600));
+    assert_eq!(backoff(u32::MAX), Duration::from_secs(3600));
+}
@@ -0,0 +1,230 @@
+//! Webhooks notifying servers of the outputs watched pubkeys receive and spend.
+//!
+//! Each webhook of the configuration watches a set of pubkeys. Once the block receiving an output
+//! of one of them, or spending one, has `confirmations` confirmations, a notification is queued
+//! in `webhook_deliveries`, in the database transaction applying the block confirming it, so
+//! none is lost on restart. Notifications are then POSTed as JSON to the webhook's URL:
+//!
+//! ```json
+//! {"event":"received","outpoint":"0x…","pubkey":"0x…","value":"1000","txid":"0x…",
+//!  "blockNumber":120,"blockHash":"0x…","timestamp":1700000000000,"confirmations":6}
+//! ```
+//!
+//! `event` is `received` or `spent`, and `txid` the transaction creating or spending the output,
+//! null for rewards and mints received. The `X-Tumuchain-Signature` header is `sha256=` and the
+//! hex HMAC-SHA256 of the body under the webhook's secret, and `X-Tumuchain-Delivery` the id of
+//! the notification, the same across retries. A notification is delivered once the server
+//! answers 2xx, retried with exponential backoff until then, and dropped after
+//! [`MAX_ATTEMPTS`]. Notifications are not withdrawn when a reorganization deeper than
+//! `confirmations` rolls their block back.
+
+use std::time::Duration;
+
+use futures::future::join_all;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use sp_core::{bytes, hexdisplay::HexDisplay, H256};
+use tokio_postgres::{Client, Row, Transaction};
+use tumuchain_runtime::BlockNumber;
+
+/// Header carrying the signature of a notification.
+const SIGNATURE_HEADER: &str = "X-Tumuchain-Signature";
+
+/// Header carrying the id of a notification.
+const DELIVERY_HEADER: &str = "X-Tumuchain-Delivery";
+
+/// Attempts to deliver a notification before dropping it.
+const MAX_ATTEMPTS: i32 = 12;
+
+/// Wait before the first retry, doubled on each retry after.
+const BASE_BACKOFF: Duration = Duration::from_secs(10);
+
+/// Longest wait between two attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// Time a server has to answer a notification.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Wait between two polls of the queue while no notification is due.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Most notifications sent at once.
+const BATCH: i64 = 100;
+
+/// A webhook of the configuration.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Webhook {
+	/// URL to POST notifications to, which identifies the webhook
+	pub url: String,
+	/// Key of the HMAC signing notifications
+	pub secret: String,
+	/// Pubkeys whose outputs are watched
+	pub pubkeys: Vec<H256>,
+	/// Confirmations of a block before notifying of it, 1 notifying of the best block
+	#[serde(default = "default_confirmations")]
+	pub confirmations: u32,
+}
+
+fn default_confirmations() -> u32 {
+	1
+}
+
+/// Outputs received and spent by the pubkeys of a webhook in a block, as [`notification`] reads
+/// them.
+const WATCHED: &str = "
+	SELECT 'received', o.outpoint, o.pubkey, o.value::TEXT, o.txid, b.number, b.hash,
+		b.timestamp_ms
+	FROM outputs o
+	JOIN blocks b ON b.hash = o.block_hash
+	WHERE b.number = $1 AND o.pubkey = ANY($2)
+	UNION ALL
+	SELECT 'spent', s.outpoint, o.pubkey, o.value::TEXT, s.txid, b.number, b.hash,
+		b.timestamp_ms
+	FROM spends s
+	JOIN outputs o ON o.outpoint = s.outpoint
+	JOIN blocks b ON b.hash = s.block_hash
+	WHERE b.number = $1 AND o.pubkey = ANY($2)
+";
+
+/// Queue the notifications of `webhooks` confirmed by block `number` in `tx`, the transaction
+/// applying it.
+pub async fn enqueue(
+	tx: &Transaction<'_>,
+	webhooks: &[Webhook],
+	number: BlockNumber,
+) -> Result<(), tokio_postgres::Error> {
+	for webhook in webhooks {
+		let confirmations = webhook.confirmations.max(1);
+		let Some(confirmed) = (number + 1).checked_sub(confirmations) else { continue };
+		let pubkeys: Vec<&[u8]> = webhook.pubkeys.iter().map(|pubkey| pubkey.as_bytes()).collect();
+		for row in tx.query(WATCHED, &[&i64::from(confirmed), &pubkeys]).await? {
+			tx.execute(
+				"INSERT INTO webhook_deliveries (url, body) VALUES ($1, $2)",
+				&[&webhook.url, &notification(&row, confirmations).to_string()],
+			)
+			.await?;
+		}
+	}
+	Ok(())
+}
+
+/// The notification of a row of [`WATCHED`].
+fn notification(row: &Row, confirmations: u32) -> serde_json::Value {
+	serde_json::json!({
+		"event": row.get::<_, &str>(0),
+		"outpoint": hex(row.get(1)),
+		"pubkey": hex(row.get(2)),
+		"value": row.get::<_, &str>(3),
+		"txid": row.get::<_, Option<&[u8]>>(4).map(hex),
+		"blockNumber": row.get::<_, i64>(5),
+		"blockHash": hex(row.get(6)),
+		"timestamp": row.get::<_, Option<i64>>(7),
+		"confirmations": confirmations,
+	})
+}
+
+/// Deliver the queued notifications of `webhooks`, reading the queue through `client`, until the
+/// indexer stops.
+pub async fn deliver(client: Client, webhooks: Vec<Webhook>) {
+	let http = match reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build() {
+		Ok(http) => http,
+		Err(e) => {
+			log::error!("Cannot deliver webhook notifications: {e}");
+			return
+		},
+	};
+	loop {
+		match deliver_due(&client, &http, &webhooks).await {
+			Ok(0) => tokio::time::sleep(POLL_INTERVAL).await,
+			Ok(_) => {},
+			Err(e) => {
+				log::warn!("Webhook queue query failed: {e}");
+				tokio::time::sleep(POLL_INTERVAL).await;
+			},
+		}
+	}
+}
+
+/// Attempt the delivery of the notifications due, returning how many there were.
+async fn deliver_due(
+	client: &Client,
+	http: &reqwest::Client,
+	webhooks: &[Webhook],
+) -> Result<usize, tokio_postgres::Error> {
+	let due = client
+		.query(
+			"SELECT id, url, body, attempts FROM webhook_deliveries WHERE next_attempt <= now() \
+			 ORDER BY id LIMIT $1",
+			&[&BATCH],
+		)
+		.await?;
+	let attempts = due.iter().map(|row| {
+		let (id, url, body): (i64, &str, &str) = (row.get(0), row.get(1), row.get(2));
+		let webhook = webhooks.iter().find(|webhook| webhook.url == url);
+		async move {
+			let Some(webhook) = webhook else {
+				log::warn!("Dropping notification {id} to {url}, no longer a webhook");
+				return true
+			};
+			let response = http
+				.post(url)
+				.header(reqwest::header::CONTENT_TYPE, "application/json")
+				.header(SIGNATURE_HEADER, signature(&webhook.secret, body))
+				.header(DELIVERY_HEADER, id.to_string())
+				.body(body.to_owned())
+				.send()
+				.await;
+			match response {
+				Ok(response) if response.status().is_success() => true,
+				Ok(response) => {
+					log::debug!("Notification {id} to {url} answered {}", response.status());
+					false
+				},
+				Err(e) => {
+					log::debug!("Notification {id} to {url} failed: {e}");
+					false
+				},
+			}
+		}
+	});
+	let delivered = join_all(attempts).await;
+
+	for (row, delivered) in due.iter().zip(delivered) {
+		let (id, url, attempts): (i64, &str, i32) = (row.get(0), row.get(1), row.get(3));
+		if delivered || attempts + 1 >= MAX_ATTEMPTS {
+			if !delivered {
+				log::warn!("Dropping notification {id} to {url} after {MAX_ATTEMPTS} attempts");
+			}
+			client.execute("DELETE FROM webhook_deliveries WHERE id = $1", &[&id]).await?;
+		} else {
+			client
+				.execute(
+					"UPDATE webhook_deliveries SET attempts = attempts + 1, \
+					 next_attempt = now() + make_interval(secs => $2) WHERE id = $1",
+					&[&id, &(backoff(attempts as u32).as_secs() as f64)],
+				)
+				.await?;
+		}
+	}
+	Ok(due.len())
+}
+
+/// The wait before retrying a notification that failed `attempts` times before.
+pub fn backoff(attempts: u32) -> Duration {
+	BASE_BACKOFF.saturating_mul(1 << attempts.min(16)).min(MAX_BACKOFF)
+}
+
+/// The signature header of `body` under `secret`.
+pub fn signature(secret: &str, body: &str) -> String {
+	let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+		.expect("HMAC takes keys of any length");
+	mac.update(body.as_bytes());
+	let digest = mac.finalize().into_bytes();
+	format!("sha256={}", HexDisplay::from(&digest.as_slice()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+	bytes::to_hex(bytes, false)
+}
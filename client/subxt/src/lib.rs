@@ -0,0 +1,232 @@
+//! Typed client of tumuchain nodes for off-chain services, over subxt.
+//!
+//! A [`Client`] connects to a node over WebSocket to submit spends, and gives the [`State`] at a
+//! block to read unspent outputs, decode the UTXO pallet's events and call the runtime APIs:
+//!
+//! ```no_run
+//! # use tumuchain_client::{Client, Error, Transaction};
+//! # async fn run(transaction: Transaction) -> Result<(), Error> {
+//! let client = Client::connect("ws://127.0.0.1:9944").await?;
+//! let block = client.spend_finalized(transaction.clone()).await?;
+//! for event in client.at(block).utxo_events().await? {
+//! 	println!("{event:?}");
+//! }
+//! let state = client.at_finalized().await?;
+//! let output = state.utxo(transaction.outpoint(0)).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Calls, storage entries, events and runtime API results are encoded and decoded with the types
+//! of the runtime the crate is built against, not from bindings generated from the node's
+//! metadata: services share these instead of keeping their own, and upgrade the crate along with
+//! the runtime. [`Client::subxt`] gives the underlying client for the rest, such as following new
+//! blocks.
+
+use codec::{Decode, Encode};
+use frame_system::{EventRecord, Phase};
+use sp_core::{twox_128, H256, U256};
+use subxt::{
+	backend::{legacy::LegacyRpcMethods, rpc::RpcClient},
+	runtime_api::RuntimeApi,
+	storage::Storage,
+	tx::SubmittableExtrinsic,
+	OnlineClient, SubstrateConfig,
+};
+use tumuchain_runtime::{
+	pallet_difficulty::HashrateEstimate, utxo, Balance, BlockNumber, Runtime, RuntimeCall,
+	RuntimeEvent, UncheckedExtrinsic,
+};
+
+#[cfg(test)]
+mod tests;
+
+pub use subxt::Error;
+pub use utxo::{AuthorStatistics, Transaction, TransactionOutput};
+
+/// Events of the UTXO pallet.
+pub type UtxoEvent = utxo::Event<Runtime>;
+
+/// The subxt client underlying a [`Client`].
+pub type SubxtClient = OnlineClient<SubstrateConfig>;
+
+/// A connection to a tumuchain node.
+#[derive(Clone)]
+pub struct Client {
+	api: SubxtClient,
+	rpc: LegacyRpcMethods<SubstrateConfig>,
+}
+
+impl Client {
+	/// Connect to the node at `url`, such as `ws://127.0.0.1:9944`. Unencrypted connections are
+	/// only made to local nodes.
+	pub async fn connect(url: &str) -> Result<Self, Error> {
+		Self::from_rpc_client(RpcClient::from_url(url).await?).await
+	}
+
+	/// Talk to the node over `rpc`, an RPC client connected some other way.
+	pub async fn from_rpc_client(rpc: RpcClient) -> Result<Self, Error> {
+		let api = OnlineClient::from_rpc_client(rpc.clone()).await?;
+		Ok(Self { api, rpc: LegacyRpcMethods::new(rpc) })
+	}
+
+	/// The underlying subxt client.
+	pub fn subxt(&self) -> &SubxtClient {
+		&self.api
+	}
+
+	/// Submit `transaction` to the node's pool, returning the hash of its extrinsic.
+	pub async fn spend(&self, transaction: Transaction) -> Result<H256, Error> {
+		self.spend_extrinsic(transaction).submit().await
+	}
+
+	/// Submit `transaction` and wait for a finalized block to include it, returning the hash of
+	/// the block. Fails if the pool drops the transaction or the spend fails.
+	pub async fn spend_finalized(&self, transaction: Transaction) -> Result<H256, Error> {
+		let progress = self.spend_extrinsic(transaction).submit_and_watch().await?;
+		let in_block = progress.wait_for_finalized().await?;
+		in_block.wait_for_success().await?;
+		Ok(in_block.block_hash())
+	}
+
+	fn spend_extrinsic(
+		&self,
+		transaction: Transaction,
+	) -> SubmittableExtrinsic<SubstrateConfig, SubxtClient> {
+		SubmittableExtrinsic::from_bytes(self.api.clone(), spend_extrinsic(transaction))
+	}
+
+	/// The state at the block `hash`.
+	pub fn at(&self, hash: H256) -> State {
+		State {
+			hash,
+			storage: self.api.storage().at(hash),
+			runtime_api: self.api.runtime_api().at(hash),
+		}
+	}
+
+	/// The state at the last finalized block.
+	pub async fn at_finalized(&self) -> Result<State, Error> {
+		let hash = self.api.blocks().at_latest().await?.hash();
+		Ok(self.at(hash))
+	}
+
+	/// The hash of block `number` of the node's best chain, if it has one.
+	pub async fn block_hash(&self, number: BlockNumber) -> Result<Option<H256>, Error> {
+		self.rpc.chain_get_block_hash(Some(u64::from(number).into())).await
+	}
+}
+
+/// The state of the chain at a block.
+pub struct State {
+	hash: H256,
+	storage: Storage<SubstrateConfig, SubxtClient>,
+	runtime_api: RuntimeApi<SubstrateConfig, SubxtClient>,
+}
+
+impl State {
+	/// The hash of the block.
+	pub fn hash(&self) -> H256 {
+		self.hash
+	}
+
+	/// The unspent output at `outpoint`, if any.
+	pub async fn utxo(&self, outpoint: H256) -> Result<Option<TransactionOutput>, Error> {
+		self.fetch(utxo::UtxoStore::<Runtime>::hashed_key_for(outpoint)).await
+	}
+
+	/// The events the UTXO pallet deposited in the block, in order.
+	pub async fn utxo_events(&self) -> Result<Vec<UtxoEvent>, Error> {
+		Ok(self.utxo_event_records().await?.into_iter().map(|(_, event)| event).collect())
+	}
+
+	/// The events the UTXO pallet deposited in the block, in order, with the phase of the block
+	/// each was deposited in: by an extrinsic, such as a mint, or at finalization.
+	pub async fn utxo_event_records(&self) -> Result<Vec<(Phase, UtxoEvent)>, Error> {
+		match self.storage.fetch_raw(events_key()).await? {
+			Some(records) => Ok(utxo_events(&records)?),
+			None => Ok(Vec::new()),
+		}
+	}
+
+	/// The time the block was authored at, in milliseconds since the Unix epoch.
+	pub async fn timestamp(&self) -> Result<Option<u64>, Error> {
+		self.fetch([twox_128(b"Timestamp"), twox_128(b"Now")].concat()).await
+	}
+
+	/// The issuance awarded to the author of block `number`.
+	pub async fn reward_at(&self, number: BlockNumber) -> Result<Balance, Error> {
+		self.call("IssuanceApi_reward_at", number).await
+	}
+
+	/// The first block after `number` at which the issuance is halved, if any.
+	pub async fn next_halving_block(
+		&self,
+		number: BlockNumber,
+	) -> Result<Option<BlockNumber>, Error> {
+		self.call("IssuanceApi_next_halving_block", number).await
+	}
+
+	/// The reward of the author of the next block: the fees collected so far plus its issuance.
+	pub async fn pending_reward(&self) -> Result<Balance, Error> {
+		self.call("RewardApi_pending_reward", ()).await
+	}
+
+	/// What the author paid at `pubkey` has mined since genesis.
+	pub async fn author_stats(&self, pubkey: H256) -> Result<AuthorStatistics, Error> {
+		self.call("MinerStatsApi_author_stats", pubkey).await
+	}
+
+	/// The `limit` authors with the most blocks, ties broken by the rewards earned.
+	pub async fn leaderboard(&self, limit: u32) -> Result<Vec<(H256, AuthorStatistics)>, Error> {
+		self.call("MinerStatsApi_leaderboard", limit).await
+	}
+
+	/// The difficulty the next block has to be sealed at.
+	pub async fn difficulty(&self) -> Result<U256, Error> {
+		self.call("DifficultyApi_difficulty", ()).await
+	}
+
+	/// The network hashrate estimated over the last `window` blocks, if enough are recorded.
+	pub async fn network_hashrate(&self, window: u32) -> Result<Option<HashrateEstimate>, Error> {
+		self.call("DifficultyApi_network_hashrate", window).await
+	}
+
+	/// The unspent outputs carrying `memo`, with their outpoints.
+	pub async fn utxos_by_memo(&self, memo: H256) -> Result<Vec<(H256, TransactionOutput)>, Error> {
+		self.call("MemoApi_utxos_by_memo", memo).await
+	}
+
+	async fn fetch<T: Decode>(&self, key: Vec<u8>) -> Result<Option<T>, Error> {
+		match self.storage.fetch_raw(key).await? {
+			Some(data) => Ok(Some(T::decode(&mut &data[..])?)),
+			None => Ok(None),
+		}
+	}
+
+	async fn call<R: Decode>(&self, function: &str, args: impl Encode) -> Result<R, Error> {
+		self.runtime_api.call_raw(function, Some(&args.encode())).await
+	}
+}
+
+/// The unsigned extrinsic spending `transaction`, encoded.
+fn spend_extrinsic(transaction: Transaction) -> Vec<u8> {
+	UncheckedExtrinsic::new_unsigned(RuntimeCall::Utxo(utxo::Call::spend { transaction })).encode()
+}
+
+/// The storage key of the events of the block, `System::Events`.
+fn events_key() -> Vec<u8> {
+	[twox_128(b"System"), twox_128(b"Events")].concat()
+}
+
+/// The UTXO pallet's events among the encoded event `records` of a block, with their phases.
+fn utxo_events(records: &[u8]) -> Result<Vec<(Phase, UtxoEvent)>, codec::Error> {
+	let records = Vec::<EventRecord<RuntimeEvent, H256>>::decode(&mut &records[..])?;
+	Ok(records
+		.into_iter()
+		.filter_map(|record| match record.event {
+			RuntimeEvent::Utxo(event) => Some((record.phase, event)),
+			_ => None,
+		})
+		.collect())
+}
@@ -0,0 +1,39 @@
+use crate::{spend_extrinsic, utxo_events};
+use codec::{Decode, Encode};
+use frame_system::{EventRecord, Phase};
+use sp_core::H256;
+use tumuchain_runtime::{utxo, RuntimeCall, RuntimeEvent, UncheckedExtrinsic};
+use utxo::{Transaction, TransactionInput, TransactionOutput, TRANSACTION_VERSION};
+
+#[test]
+fn spend_is_unsigned_utxo_call() {
+    let transaction = Transaction {
+        version: TRANSACTION_VERSION,
+        inputs: vec![TransactionInput { outpoint: H256::repeat_byte(1), sigscript: None, witness: None }],
+        outputs: vec![TransactionOutput { value: 50, pubkey: H256::repeat_byte(2), memo: None }],
+    };
+
+    let extrinsic = UncheckedExtrinsic::decode(&mut &spend_extrinsic(transaction.clone())[..]).unwrap();
+    assert!(extrinsic.signature.is_none());
+    assert_eq!(extrinsic.function, RuntimeCall::Utxo(utxo::Call::spend { transaction }));
+}
+
+#[test]
+fn utxo_events_are_picked_from_records() {
+    let record = |phase, event| Box::new(EventRecord { phase, event, topics: vec![] });
+    // Stored boxed, which encodes as the record itself
+    let records = vec![
+        record(Phase::ApplyExtrinsic(0), RuntimeEvent::System(frame_system::Event::CodeUpdated)),
+        record(Phase::ApplyExtrinsic(1), RuntimeEvent::Utxo(utxo::Event::RewardsWasted)),
+        record(Phase::Finalization, RuntimeEvent::Utxo(utxo::Event::UncleRejected { uncle: H256::repeat_byte(3) })),
+    ];
+
+    assert_eq!(
+        utxo_events(&records.encode()).unwrap(),
+        vec![
+            (Phase::ApplyExtrinsic(1), utxo::Event::RewardsWasted),
+            (Phase::Finalization, utxo::Event::UncleRejected { uncle: H256::repeat_byte(3) }),
+        ],
+    );
+    assert!(utxo_events(&[1]).is_err());
+}
@@ -0,0 +1,178 @@
+//! WebAssembly bindings building and signing tumuchain transactions, for browser wallets.
+//!
+//! Build with `wasm-pack build client/wasm --target web`. The bindings wrap the `no_std`
+//! [`utxo_primitives`], so a transaction built here is the one the node validates:
+//!
+//! ```js
+//! import init, { TransactionBuilder } from "./pkg/tumuchain_wasm.js";
+//!
+//! await init();
+//! const builder = new TransactionBuilder();
+//! builder.input(outpoint);
+//! builder.output("90000", recipient);
+//! const transaction = builder.build();
+//! transaction.sign(0, seed);
+//! // Submit with `author_submitPackage([transaction.encode()])`
+//! ```
+//!
+//! Values are decimal strings, as they do not fit JavaScript numbers. Hashes, keys, signatures
+//! and encodings are `Uint8Array`s. Keys are given by their 32-byte secret seed, as shown by
+//! `subkey inspect`; signers holding keys elsewhere sign [`Transaction::signing_payload`]
+//! themselves and set the result with [`Transaction::set_signature`].
+
+use codec::{Decode, Encode};
+use schnorrkel::{signing_context, ExpansionMode, MiniSecretKey};
+use sp_core::{H256, H512};
+use utxo_primitives::{
+	script::ScriptWitness, BuildError, TransactionInput, TransactionOutput, Value,
+};
+use wasm_bindgen::prelude::*;
+
+/// Signing context of sr25519 signatures verified by the runtime.
+const SIGNING_CTX: &[u8] = b"substrate";
+
+/// Assembles a transaction input by input and output by output.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct TransactionBuilder(utxo_primitives::TransactionBuilder);
+
+#[wasm_bindgen]
+impl TransactionBuilder {
+	#[wasm_bindgen(constructor)]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Spend the output at `outpoint`.
+	pub fn input(&mut self, outpoint: &[u8]) -> Result<(), JsError> {
+		let outpoint = hash(outpoint, "outpoint")?;
+		self.0 = core::mem::take(&mut self.0).input(outpoint).map_err(build_error)?;
+		Ok(())
+	}
+
+	/// Pay `value` base units to `pubkey`, with the 32-byte `memo` if any.
+	pub fn output(
+		&mut self,
+		value: &str,
+		pubkey: &[u8],
+		memo: Option<Vec<u8>>,
+	) -> Result<(), JsError> {
+		let output = TransactionOutput {
+			value: value.parse::<Value>().map_err(|_| JsError::new("invalid value"))?,
+			pubkey: hash(pubkey, "pubkey")?,
+			memo: memo.map(|memo| hash(&memo, "memo")).transpose()?,
+		};
+		self.0 = core::mem::take(&mut self.0).output(output).map_err(build_error)?;
+		Ok(())
+	}
+
+	/// Build the transaction with its inputs and outputs in canonical order, sorted rather than
+	/// in the order given, so their order tells nothing of the wallet.
+	#[wasm_bindgen(js_name = canonicalOrder)]
+	pub fn canonical_order(&mut self) {
+		self.0 = core::mem::take(&mut self.0).canonical_order();
+	}
+
+	/// The transaction, unsigned. The builder is left empty.
+	pub fn build(&mut self) -> Result<Transaction, JsError> {
+		core::mem::take(&mut self.0).build().map(Transaction).map_err(build_error)
+	}
+}
+
+/// A transaction being signed.
+#[wasm_bindgen]
+pub struct Transaction(utxo_primitives::Transaction);
+
+#[wasm_bindgen]
+impl Transaction {
+	/// Decode a SCALE-encoded transaction, e.g. one to co-sign.
+	pub fn decode(encoded: &[u8]) -> Result<Transaction, JsError> {
+		utxo_primitives::Transaction::decode(&mut &encoded[..])
+			.map(Self)
+			.map_err(|_| JsError::new("invalid transaction"))
+	}
+
+	/// What every input's signature signs.
+	#[wasm_bindgen(js_name = signingPayload)]
+	pub fn signing_payload(&self) -> Vec<u8> {
+		self.0.signing_payload()
+	}
+
+	/// The transaction identifier, which signatures do not change.
+	pub fn txid(&self) -> Vec<u8> {
+		self.0.txid().as_bytes().to_vec()
+	}
+
+	/// The outpoint of the output at `index`, to spend it by.
+	pub fn outpoint(&self, index: u32) -> Vec<u8> {
+		self.0.outpoint(index.into()).as_bytes().to_vec()
+	}
+
+	/// Sign input `index` with the key of secret `seed`. Other inputs spending outputs of the
+	/// same key need no signature of their own.
+	pub fn sign(&mut self, index: usize, seed: &[u8]) -> Result<(), JsError> {
+		let keypair = MiniSecretKey::from_bytes(seed)
+			.map_err(|_| JsError::new("seed must be 32 bytes"))?
+			.expand_to_keypair(ExpansionMode::Ed25519);
+		let payload = self.0.signing_payload();
+		let signature = keypair.sign(signing_context(SIGNING_CTX).bytes(&payload));
+		self.set_signature(index, &signature.to_bytes())
+	}
+
+	/// Set the 64-byte sr25519 `signature` of input `index`, made elsewhere over the
+	/// [`Self::signing_payload`].
+	#[wasm_bindgen(js_name = setSignature)]
+	pub fn set_signature(&mut self, index: usize, signature: &[u8]) -> Result<(), JsError> {
+		if signature.len() != 64 {
+			return Err(JsError::new("signature must be 64 bytes"))
+		}
+		self.input_mut(index)?.sigscript = Some(H512::from_slice(signature));
+		Ok(())
+	}
+
+	/// Set the SCALE-encoded script witness of input `index`, spending an output locked to a
+	/// script.
+	#[wasm_bindgen(js_name = setWitness)]
+	pub fn set_witness(&mut self, index: usize, witness: &[u8]) -> Result<(), JsError> {
+		let witness = ScriptWitness::decode(&mut &witness[..])
+			.map_err(|_| JsError::new("invalid script witness"))?;
+		self.input_mut(index)?.witness = Some(witness);
+		Ok(())
+	}
+
+	/// The SCALE encoding of the transaction, as the node's RPC methods take it.
+	pub fn encode(&self) -> Vec<u8> {
+		self.0.encode()
+	}
+}
+
+impl Transaction {
+	fn input_mut(&mut self, index: usize) -> Result<&mut TransactionInput, JsError> {
+		self.0.inputs.get_mut(index).ok_or_else(|| JsError::new("no such input"))
+	}
+}
+
+/// The public key of secret `seed`, to be paid to.
+#[wasm_bindgen(js_name = publicKey)]
+pub fn public_key(seed: &[u8]) -> Result<Vec<u8>, JsError> {
+	let secret =
+		MiniSecretKey::from_bytes(seed).map_err(|_| JsError::new("seed must be 32 bytes"))?;
+	Ok(secret.expand_to_public(ExpansionMode::Ed25519).to_bytes().to_vec())
+}
+
+/// The pubkey an output locked to `script` pays.
+#[wasm_bindgen(js_name = scriptHash)]
+pub fn script_hash(script: &[u8]) -> Vec<u8> {
+	utxo_primitives::script::script_hash(script).as_bytes().to_vec()
+}
+
+fn build_error(error: BuildError) -> JsError {
+	JsError::new(&error.to_string())
+}
+
+fn hash(bytes: &[u8], what: &str) -> Result<H256, JsError> {
+	if bytes.len() != 32 {
+		return Err(JsError::new(&format!("{what} must be 32 bytes")))
+	}
+	Ok(H256::from_slice(bytes))
+}
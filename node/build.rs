@@ -4,4 +4,11 @@ fn main() {
 	generate_cargo_keys();
 
 	rerun_if_git_head_changed();
+
+	// Generate the gRPC services with a vendored `protoc`, so that none has to be installed
+	std::env::set_var(
+		"PROTOC",
+		protoc_bin_vendored::protoc_bin_path().expect("protoc is vendored for this platform"),
+	);
+	tonic_build::compile_protos("proto/tumuchain.proto").expect("proto/tumuchain.proto compiles");
 }
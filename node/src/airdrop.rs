@@ -0,0 +1,96 @@
+//! The `airdrop-snapshot` subcommand, splitting an airdrop among the owners of the UTXO set at
+//! a block.
+
+use crate::{rpc::utxo::utxo_store_prefix, service::FullClient};
+use codec::Decode;
+use sc_cli::{BlockNumberOrHash, CliConfiguration, DatabaseParams, SharedParams};
+use sc_client_api::StorageProvider;
+use sp_blockchain::HeaderBackend;
+use sp_core::{storage::StorageKey, H256};
+use std::{fs, io, path::PathBuf, sync::Arc};
+use tumuchain_runtime::{opaque::Block, pallet_airdrop, Balance};
+use utxo::TransactionOutput;
+
+/// Snapshot the UTXO set at a block and write the airdrop claims of its owners as JSON.
+///
+/// The output is a list of `[pubkey, amount]` pairs, the format of the `airdrop.claims` field of
+/// a chain spec's genesis config. Owners that are no sr25519 key, such as most script hashes,
+/// could never sign a claim and are left out, so their share goes to the others.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct AirdropSnapshotCmd {
+	/// Block to snapshot, by number or hash. Defaults to the best block.
+	#[arg(long, value_name = "HASH or NUMBER")]
+	pub at: Option<BlockNumberOrHash>,
+
+	/// Amount of the airdropped asset to split, in its smallest unit.
+	#[arg(long, value_name = "AMOUNT")]
+	pub total: Balance,
+
+	/// File to write the claims to. Defaults to stdout.
+	#[arg(long, short, value_name = "PATH")]
+	pub output: Option<PathBuf>,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub database_params: DatabaseParams,
+}
+
+impl AirdropSnapshotCmd {
+	/// Run the snapshot against the node's database.
+	pub async fn run(&self, client: Arc<FullClient>) -> sc_cli::Result<()> {
+		let at = match &self.at {
+			Some(at) => client.expect_block_hash_from_id(&at.parse::<Block>()?)?,
+			None => client.info().best_hash,
+		};
+
+		let prefix = StorageKey(utxo_store_prefix());
+		let (outputs, unclaimable): (Vec<_>, Vec<_>) = client
+			.storage_pairs(at, Some(&prefix), None)?
+			.filter_map(|(_, data)| TransactionOutput::decode(&mut &data.0[..]).ok())
+			.partition(|output| is_signing_key(&output.pubkey));
+		let claims = pallet_airdrop::proportional_claims(outputs, self.total);
+		log::info!(
+			"📸 Snapshot at {at:?}: {} claims, {} outputs of no key left out",
+			claims.len(),
+			unclaimable.len(),
+		);
+
+		match &self.output {
+			Some(path) => serde_json::to_writer_pretty(fs::File::create(path)?, &claims),
+			None => serde_json::to_writer_pretty(io::stdout().lock(), &claims),
+		}
+		.map_err(|e| sc_cli::Error::Input(e.to_string()))
+	}
+}
+
+/// Whether `pubkey` is an sr25519 public key, which the hash of a script is only by chance.
+fn is_signing_key(pubkey: &H256) -> bool {
+	schnorrkel::PublicKey::from_bytes(pubkey.as_bytes()).is_ok()
+}
+
+impl CliConfiguration for AirdropSnapshotCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_keyring::Sr25519Keyring;
+
+	#[test]
+	fn only_sr25519_keys_can_claim() {
+		assert!(is_signing_key(&H256(Sr25519Keyring::Alice.public().0)));
+		// Not the canonical encoding of any point
+		assert!(!is_signing_key(&H256::repeat_byte(0xff)));
+	}
+}
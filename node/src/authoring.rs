@@ -0,0 +1,149 @@
+//! Pre-runtime digests added to the blocks this node authors.
+//!
+//! The runtime reads some choices of a block author from its header: pallet-capacity adjusts
+//! the block length limit from the `--block-length-signal` preferences, pallet-upgrade applies
+//! the pending runtime upgrade once enough blocks carry its `--upgrade-signal`, and the UTXO
+//! pallet pays the block reward to the `--author` key, or to the keystore's [`REWARD_KEY_TYPE`]
+//! key. This wraps the proposer factory so every block this node authors carries those digests.
+//!
+//! The reward key is looked up for each block through a [`PayoutKey`], which `miner_setPayoutKey`
+//! overrides, so it can be rotated without restarting the node.
+
+use futures::{future::BoxFuture, FutureExt, TryFutureExt};
+use sp_consensus::{Environment, Proposer};
+use sp_inherents::InherentData;
+use sp_core::{crypto::KeyTypeId, sr25519, H256};
+use sp_keystore::Keystore;
+use sp_runtime::{traits::Block as BlockT, Digest, DigestItem};
+use std::{
+	sync::{Arc, RwLock},
+	time::Duration,
+};
+use tumuchain_runtime::{pallet_capacity, pallet_upgrade};
+
+use crate::cli::AuthoringParams;
+
+/// Key type under which the keystore holds the key block rewards are paid to when no
+/// `--author` is given.
+pub const REWARD_KEY_TYPE: KeyTypeId = KeyTypeId(*b"rwrd");
+
+/// The key to pay block rewards to: `--author`, else the first [`REWARD_KEY_TYPE`] key in
+/// `keystore`. `None` leaves the reward to the Aura key authoring the block.
+pub fn reward_key(params: &AuthoringParams, keystore: &dyn Keystore) -> Option<sr25519::Public> {
+	params.author.or_else(|| keystore.sr25519_public_keys(REWARD_KEY_TYPE).into_iter().next())
+}
+
+/// The key the blocks this node authors pay their rewards to, shared between the proposer and
+/// the RPC rotating it.
+///
+/// Until a key is set, it is the [`reward_key`] of the node's settings, read from the keystore
+/// anew for each block, so a [`REWARD_KEY_TYPE`] key inserted at runtime is picked up as well. A
+/// key set is kept in memory only: after a restart, the settings apply again.
+#[derive(Clone)]
+pub struct PayoutKey {
+	params: AuthoringParams,
+	keystore: Arc<dyn Keystore>,
+	rotated: Arc<RwLock<Option<sr25519::Public>>>,
+}
+
+impl PayoutKey {
+	pub fn new(params: AuthoringParams, keystore: Arc<dyn Keystore>) -> Self {
+		Self { params, keystore, rotated: Default::default() }
+	}
+
+	/// The key to pay the next block's reward to, `None` for the Aura key authoring it.
+	pub fn get(&self) -> Option<sr25519::Public> {
+		let rotated = *self.rotated.read().expect("never poisoned; qed");
+		rotated.or_else(|| reward_key(&self.params, &*self.keystore))
+	}
+
+	/// Pay the rewards of the blocks authored from now on to `key`, or, with `None`, to the key
+	/// of the node's settings again. Returns the key now paid.
+	pub fn set(&self, key: Option<sr25519::Public>) -> Option<sr25519::Public> {
+		self.swap(key);
+		self.announce()
+	}
+
+	/// Set `key` quietly, returning the key set before, if any.
+	pub fn swap(&self, key: Option<sr25519::Public>) -> Option<sr25519::Public> {
+		std::mem::replace(&mut *self.rotated.write().expect("never poisoned; qed"), key)
+	}
+
+	/// Log the key paid, and return it.
+	pub fn announce(&self) -> Option<sr25519::Public> {
+		let paid = self.get();
+		match &paid {
+			Some(key) => log::info!("💰 Paying block rewards to {}", key),
+			None => log::info!("💰 Paying block rewards to the authoring Aura key"),
+		}
+		paid
+	}
+}
+
+/// A proposer factory whose blocks signal `block_length` and `upgrade`, and pay the current
+/// `payout_key`, if any.
+pub struct AuthoringEnvironment<E> {
+	inner: E,
+	digests: Vec<DigestItem>,
+	payout_key: PayoutKey,
+}
+
+impl<E> AuthoringEnvironment<E> {
+	pub fn new(
+		inner: E,
+		block_length: Option<u32>,
+		upgrade: Option<H256>,
+		payout_key: PayoutKey,
+	) -> Self {
+		let digests = block_length
+			.map(pallet_capacity::signal_digest)
+			.into_iter()
+			.chain(upgrade.map(pallet_upgrade::signal_digest))
+			.collect();
+		Self { inner, digests, payout_key }
+	}
+}
+
+impl<B, E> Environment<B> for AuthoringEnvironment<E>
+where
+	B: BlockT,
+	E: Environment<B>,
+	E::CreateProposer: Send + 'static,
+{
+	type Proposer = AuthoringProposer<E::Proposer>;
+	type CreateProposer = BoxFuture<'static, Result<Self::Proposer, Self::Error>>;
+	type Error = E::Error;
+
+	fn init(&mut self, parent_header: &B::Header) -> Self::CreateProposer {
+		let mut digests = self.digests.clone();
+		digests.extend(self.payout_key.get().as_ref().map(utxo::reward_digest));
+		self.inner
+			.init(parent_header)
+			.map_ok(move |inner| AuthoringProposer { inner, digests })
+			.boxed()
+	}
+}
+
+/// A proposer adding the author's digests to the block it builds.
+pub struct AuthoringProposer<P> {
+	inner: P,
+	digests: Vec<DigestItem>,
+}
+
+impl<B: BlockT, P: Proposer<B>> Proposer<B> for AuthoringProposer<P> {
+	type Error = P::Error;
+	type Proposal = P::Proposal;
+	type ProofRecording = P::ProofRecording;
+	type Proof = P::Proof;
+
+	fn propose(
+		self,
+		inherent_data: InherentData,
+		mut inherent_digests: Digest,
+		max_duration: Duration,
+		block_size_limit: Option<usize>,
+	) -> Self::Proposal {
+		inherent_digests.logs.extend(self.digests);
+		self.inner.propose(inherent_data, inherent_digests, max_duration, block_size_limit)
+	}
+}
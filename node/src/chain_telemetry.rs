@@ -0,0 +1,102 @@
+//! Chain-specific statistics sent to telemetry alongside Substrate's own.
+//!
+//! Every [`REPORT_INTERVAL`] best blocks, a `tumuchain.stats` message reports the difficulty, the
+//! size of the UTXO set and the fee rates paid in the ready queue, so a telemetry dashboard shows
+//! more of this chain than the generic block and peer counts. Counting the UTXO set walks its
+//! keys, hence the interval.
+
+use std::sync::Arc;
+
+use futures::StreamExt;
+use sc_client_api::{BlockchainEvents, StorageProvider};
+use sc_telemetry::{telemetry, TelemetryHandle, SUBSTRATE_INFO};
+use sc_transaction_pool_api::{InPoolTransaction, TransactionPool};
+use sp_api::ProvideRuntimeApi;
+use sp_core::{storage::StorageKey, Encode, H256};
+use sp_runtime::traits::Header as _;
+use tumuchain_runtime::{apis::DifficultyApi, opaque::Block};
+
+use crate::{mempool::virtual_size, rpc::utxo::utxo_store_prefix, service::FullClient};
+
+/// Number of best blocks between two reports.
+const REPORT_INTERVAL: u32 = 10;
+
+/// Fee rates, in fee per byte of virtual size, of the transactions ready for inclusion.
+#[derive(Default)]
+struct FeeRates {
+	min: u64,
+	median: u64,
+	max: u64,
+}
+
+impl FeeRates {
+	/// The spread of `rates`, all zero when there are none.
+	fn new(mut rates: Vec<u64>) -> Self {
+		rates.sort_unstable();
+		match (rates.first(), rates.last()) {
+			(Some(min), Some(max)) => Self { min: *min, median: rates[rates.len() / 2], max: *max },
+			_ => Self::default(),
+		}
+	}
+}
+
+/// Reports to `telemetry` until the client's import stream ends.
+pub async fn run<P>(client: Arc<FullClient>, pool: Arc<P>, telemetry: TelemetryHandle)
+where
+	P: TransactionPool<Block = Block> + 'static,
+{
+	let mut imports = client.import_notification_stream();
+	while let Some(notification) = imports.next().await {
+		let number = *notification.header.number();
+		if !notification.is_new_best || number % REPORT_INTERVAL != 0 {
+			continue
+		}
+		let hash = notification.hash;
+
+		let difficulty = match client.runtime_api().difficulty(hash) {
+			Ok(difficulty) => difficulty,
+			Err(e) => {
+				log::debug!(target: "telemetry", "No difficulty at #{number}: {e}");
+				continue
+			},
+		};
+		let utxo_count = match count_utxos(&client, hash) {
+			Ok(count) => count,
+			Err(e) => {
+				log::debug!(target: "telemetry", "Could not count the UTXO set at #{number}: {e}");
+				continue
+			},
+		};
+
+		// Priority is the fee paid, see `crate::mempool`
+		let ready: Vec<_> = pool.ready().collect();
+		let sizes: Vec<_> = ready.iter().map(|tx| tx.data().encoded_size()).collect();
+		let fee_rates = FeeRates::new(
+			ready
+				.iter()
+				.map(|tx| *tx.priority() / virtual_size(tx.data()).max(1) as u64)
+				.collect(),
+		);
+
+		telemetry!(
+			Some(telemetry.clone());
+			SUBSTRATE_INFO;
+			"tumuchain.stats";
+			"height" => number,
+			"best" => ?hash,
+			"difficulty" => ?difficulty,
+			"utxo_count" => utxo_count,
+			"pool_ready" => ready.len(),
+			"pool_bytes" => sizes.iter().sum::<usize>(),
+			"fee_rate_min" => fee_rates.min,
+			"fee_rate_median" => fee_rates.median,
+			"fee_rate_max" => fee_rates.max
+		);
+	}
+}
+
+/// The number of unspent outputs in the state of block `at`.
+fn count_utxos(client: &FullClient, at: H256) -> sp_blockchain::Result<usize> {
+	let prefix = StorageKey(utxo_store_prefix());
+	Ok(client.storage_keys(at, Some(&prefix), None)?.count())
+}
@@ -7,6 +7,323 @@ pub struct Cli {
 
 	#[clap(flatten)]
 	pub run: RunCmd,
+
+	#[clap(flatten)]
+	pub mempool: MempoolParams,
+
+	#[clap(flatten)]
+	pub authoring: AuthoringParams,
+
+	#[clap(flatten)]
+	pub utxo_cache: UtxoCacheParams,
+
+	#[clap(flatten)]
+	pub prune: PruneParams,
+
+	#[clap(flatten)]
+	pub index: IndexParams,
+
+	#[clap(flatten)]
+	pub grpc: GrpcParams,
+
+	#[clap(flatten)]
+	pub rest: RestParams,
+
+	#[clap(flatten)]
+	pub rosetta: RosettaParams,
+
+	#[clap(flatten)]
+	pub electrum: ElectrumParams,
+
+	#[clap(flatten)]
+	pub zmq: ZmqParams,
+
+	#[clap(flatten)]
+	pub rpc_gateway: RpcGatewayParams,
+}
+
+/// Settings of the gRPC server, for integrations that do not speak JSON-RPC.
+#[derive(Debug, Clone, clap::Args)]
+pub struct GrpcParams {
+	/// Serve the gRPC services of `node/proto/tumuchain.proto` on this port. The server is off
+	/// when unset.
+	#[arg(long, value_name = "PORT")]
+	pub grpc_port: Option<u16>,
+
+	/// Listen for gRPC on all interfaces rather than on localhost only. Broadcasting is open to
+	/// anyone reaching the port, so put the server behind a proxy or firewall.
+	#[arg(long)]
+	pub grpc_external: bool,
+}
+
+impl GrpcParams {
+	/// The address to serve gRPC on, if enabled.
+	pub fn addr(&self) -> Option<std::net::SocketAddr> {
+		self.grpc_port.map(|port| listen_addr(port, self.grpc_external))
+	}
+}
+
+/// Settings of the read-only REST gateway, for web explorers.
+#[derive(Debug, Clone, clap::Args)]
+pub struct RestParams {
+	/// Serve the REST gateway on this port: `/block/{n}`, `/tx/{id}` (with `--spent-index`) and
+	/// `/address/{pubkey}/utxos`. The gateway is off when unset.
+	#[arg(long, value_name = "PORT")]
+	pub rest_port: Option<u16>,
+
+	/// Listen for REST requests on all interfaces rather than on localhost only.
+	#[arg(long)]
+	pub rest_external: bool,
+}
+
+impl RestParams {
+	/// The address to serve the REST gateway on, if enabled.
+	pub fn addr(&self) -> Option<std::net::SocketAddr> {
+		self.rest_port.map(|port| listen_addr(port, self.rest_external))
+	}
+}
+
+/// Settings of the Rosetta API, for exchanges and custodians integrating the chain.
+#[derive(Debug, Clone, clap::Args)]
+pub struct RosettaParams {
+	/// Serve the Rosetta Data and Construction APIs on this port. They are off when unset.
+	#[arg(long, value_name = "PORT")]
+	pub rosetta_port: Option<u16>,
+
+	/// Listen for Rosetta requests on all interfaces rather than on localhost only.
+	#[arg(long)]
+	pub rosetta_external: bool,
+}
+
+impl RosettaParams {
+	/// The address to serve the Rosetta APIs on, if enabled.
+	pub fn addr(&self) -> Option<std::net::SocketAddr> {
+		self.rosetta_port.map(|port| listen_addr(port, self.rosetta_external))
+	}
+}
+
+/// Settings of the Electrum protocol server, for wallets derived from Electrum.
+#[derive(Debug, Clone, clap::Args)]
+pub struct ElectrumParams {
+	/// Serve the Electrum protocol on this port. Histories come from the index kept with
+	/// `--spent-index`, which is required. The server is off when unset.
+	#[arg(long, value_name = "PORT", requires = "spent_index")]
+	pub electrum_port: Option<u16>,
+
+	/// Listen for Electrum connections on all interfaces rather than on localhost only.
+	#[arg(long)]
+	pub electrum_external: bool,
+}
+
+impl ElectrumParams {
+	/// The address to serve the Electrum protocol on, if enabled.
+	pub fn addr(&self) -> Option<std::net::SocketAddr> {
+		self.electrum_port.map(|port| listen_addr(port, self.electrum_external))
+	}
+}
+
+/// Settings of the ZeroMQ publisher, for wallet daemons built on Bitcoin's `-zmqpub*` options.
+#[derive(Debug, Clone, clap::Args)]
+pub struct ZmqParams {
+	/// Publish the hashes of new best blocks (`hashblock`) and the txids of new transactions
+	/// (`hashtx`) to ZeroMQ SUB sockets connecting to this port. The publisher is off when unset.
+	#[arg(long, value_name = "PORT")]
+	pub zmq_port: Option<u16>,
+
+	/// Listen for ZeroMQ subscribers on all interfaces rather than on localhost only.
+	#[arg(long)]
+	pub zmq_external: bool,
+}
+
+impl ZmqParams {
+	/// The address to publish on, if enabled.
+	pub fn addr(&self) -> Option<std::net::SocketAddr> {
+		self.zmq_port.map(|port| listen_addr(port, self.zmq_external))
+	}
+}
+
+/// Settings of the role-gated RPC gateway, for exposing a public endpoint safely.
+#[derive(Debug, Clone, clap::Args)]
+pub struct RpcGatewayParams {
+	/// Serve JSON-RPC over HTTP on this port, with each method allowed to the `public`, `wallet`
+	/// or `admin` role only. The gateway is off when unset.
+	#[arg(long, value_name = "PORT")]
+	pub rpc_gateway_port: Option<u16>,
+
+	/// Listen for gateway requests on all interfaces rather than on localhost only.
+	#[arg(long)]
+	pub rpc_gateway_external: bool,
+
+	/// Grant ROLE to gateway requests bearing `Authorization: Bearer TOKEN`. Can be repeated.
+	#[arg(
+		long = "rpc-token",
+		value_name = "ROLE=TOKEN",
+		value_parser = crate::rpc_gateway::parse_token
+	)]
+	pub rpc_tokens: Vec<(crate::rpc_gateway::Role, String)>,
+
+	/// Role of gateway requests from localhost without a token. Others are `public`.
+	#[arg(long, value_name = "ROLE", value_enum, default_value = "public")]
+	pub rpc_local_role: crate::rpc_gateway::Role,
+}
+
+impl RpcGatewayParams {
+	/// The address to serve the gateway on, if enabled.
+	pub fn addr(&self) -> Option<std::net::SocketAddr> {
+		self.rpc_gateway_port.map(|port| listen_addr(port, self.rpc_gateway_external))
+	}
+}
+
+/// The address to listen on `port`, on all interfaces if `external`, else on localhost.
+fn listen_addr(port: u16, external: bool) -> std::net::SocketAddr {
+	let ip = match external {
+		true => std::net::Ipv4Addr::UNSPECIFIED,
+		false => std::net::Ipv4Addr::LOCALHOST,
+	};
+	(ip, port).into()
+}
+
+/// Optional indexes kept by the node for RPC queries.
+#[derive(Debug, Clone, clap::Args)]
+pub struct IndexParams {
+	/// Record the transaction spending each output in finalized blocks, served by
+	/// `utxo_getSpendingTx`, and the history of each pubkey, served by the Electrum server.
+	/// Enabling it on a synced node indexes the blocks whose bodies are still kept, in the
+	/// background.
+	#[arg(long)]
+	pub spent_index: bool,
+}
+
+/// Fewest blocks `--prune` keeps, so that blocks not yet finalized can still be reorged.
+pub const MIN_PRUNE_BLOCKS: u32 = 256;
+
+/// Pruning of old block bodies, on top of the standard `--state-pruning` and `--blocks-pruning`.
+#[derive(Debug, Clone, clap::Args)]
+pub struct PruneParams {
+	/// Keep the bodies and states of the last BLOCKS blocks only, but every header.
+	///
+	/// The current UTXO set is part of the latest state, so it is always kept. RPC calls about
+	/// older bodies or states fail with a pruned error. Sets both `--blocks-pruning` and
+	/// `--state-pruning` to BLOCKS. State pruning cannot be changed on an existing archive
+	/// database, so this has to be set when the node first syncs.
+	#[arg(
+		long,
+		value_name = "BLOCKS",
+		value_parser = clap::value_parser!(u32).range(MIN_PRUNE_BLOCKS as i64..),
+	)]
+	pub prune: Option<u32>,
+}
+
+impl PruneParams {
+	/// Apply `--prune` to the node's configuration.
+	pub fn apply(&self, config: &mut sc_service::Configuration) {
+		if let Some(blocks) = self.prune {
+			config.blocks_pruning = sc_service::BlocksPruning::Some(blocks);
+			config.state_pruning = Some(sc_service::PruningMode::blocks_pruning(blocks));
+		}
+	}
+}
+
+/// Settings for blocks authored by this node.
+#[derive(Debug, Clone, clap::Args)]
+pub struct AuthoringParams {
+	/// Block length in bytes to signal in authored blocks, voting to move the chain's block
+	/// length limit towards it. Authored blocks vote for the current limit when unset.
+	#[arg(long, value_name = "BYTES")]
+	pub block_length_signal: Option<u32>,
+
+	/// Hash of the pending runtime upgrade's code to approve in authored blocks, as 0x-prefixed
+	/// hex. The upgrade is applied once enough blocks approve it. Check the hash against a
+	/// reproducible build of the proposed runtime before signalling it.
+	#[arg(long, value_name = "CODE_HASH")]
+	pub upgrade_signal: Option<sp_core::H256>,
+
+	/// Key to pay the rewards of authored blocks to, as SS58 or 0x-prefixed hex, so they do not
+	/// go to the session key kept on this node. Defaults to the key inserted into the keystore
+	/// under the `rwrd` key type, if any, and otherwise to the Aura key authoring the block.
+	#[arg(long, value_name = "PUBKEY", value_parser = parse_author)]
+	pub author: Option<sp_core::sr25519::Public>,
+
+	/// Seal a block as soon as a transaction enters the pool, instead of in Aura slots, on
+	/// development chains only. The blocks are not sealed with the Aura key, so other nodes
+	/// reject them.
+	#[arg(long)]
+	pub dev_instant_mine: bool,
+}
+
+fn parse_author(s: &str) -> Result<sp_core::sr25519::Public, String> {
+	crate::wallet::parse_public(s).map_err(|e| e.to_string())
+}
+
+/// Transaction pool and relay settings layered on top of the standard `--pool-*` options.
+#[derive(Debug, Clone, clap::Args)]
+pub struct MempoolParams {
+	/// Drop pool transactions that are still pending after this many blocks. `0` disables expiry.
+	///
+	/// Dropped and evicted transactions are banned from the pool for `--tx-ban-seconds`.
+	#[arg(long, value_name = "BLOCKS", default_value_t = 720)]
+	pub pool_max_age: u32,
+
+	/// Evict the lowest fee-rate transactions once ready transactions exceed this many kilobytes.
+	///
+	/// Defaults to the `--pool-kbytes` budget.
+	#[arg(long, value_name = "KBYTES")]
+	pub pool_evict_kbytes: Option<usize>,
+
+	/// Drop transactions waiting for the outputs of transactions the pool has not seen after this
+	/// many blocks. `0` disables expiry.
+	#[arg(long, value_name = "BLOCKS", default_value_t = 200)]
+	pub pool_orphan_max_age: u32,
+
+	/// Evict the oldest transactions waiting for their inputs once they exceed this many
+	/// kilobytes, on top of the pool's own limit on them.
+	#[arg(long, value_name = "KBYTES", default_value_t = 1024)]
+	pub pool_orphan_kbytes: usize,
+
+	/// Raise the priority of pool transactions by this fee per byte for every block they wait,
+	/// so low-fee transactions eventually get into blocks. `0` disables aging.
+	#[arg(long, value_name = "FEE_PER_BYTE", default_value_t = 0)]
+	pub pool_priority_aging: u64,
+
+	/// Reject transactions submitted to the pool, or relayed to it by peers, paying less than
+	/// this fee per byte in base units. Blocks including them are still valid.
+	#[arg(long, value_name = "FEE_PER_BYTE", default_value_t = 0)]
+	pub min_relay_fee_rate: u64,
+
+	/// Reject transactions submitted to the pool, or relayed to it by peers, whose inputs are not
+	/// sorted by outpoint and outputs by value, then pubkey. Blocks including them are still valid
+	/// until the runtime enforces the order.
+	#[arg(long)]
+	pub require_canonical_order: bool,
+
+	/// Ban peers whose reputation fell below the cost of relaying this many transactions the
+	/// pool rejects, bad signatures and missing inputs among them. `0` disables banning.
+	#[arg(long, value_name = "TRANSACTIONS", default_value_t = 50)]
+	pub relay_ban_after: u32,
+
+	/// Transactions each client address may submit a minute over Electrum, gRPC, Rosetta and the
+	/// RPC gateway.
+	/// `0` disables the limit.
+	#[arg(long, value_name = "COUNT", default_value_t = 60)]
+	pub submit_rate_limit: u32,
+
+	/// Transactions each client address may submit at once before `--submit-rate-limit` applies.
+	#[arg(long, value_name = "COUNT", default_value_t = 20)]
+	pub submit_burst: u32,
+}
+
+/// Settings of the cache of UTXOs looked up while importing blocks.
+#[derive(Debug, Clone, clap::Args)]
+pub struct UtxoCacheParams {
+	/// Memory in MiB for the UTXOs looked up while importing blocks, kept in front of the on-disk
+	/// UTXO index and the state. `0` disables the cache and the index.
+	#[arg(long, value_name = "MiB", default_value_t = 300)]
+	pub utxo_cache_size: usize,
+
+	/// Write the UTXOs created and spent by imported blocks to the on-disk index every this many
+	/// blocks. Pending ones are written on shutdown.
+	#[arg(long, value_name = "BLOCKS", default_value_t = 1000)]
+	pub utxo_cache_flush_interval: u32,
 }
 
 #[derive(Debug, clap::Subcommand)]
@@ -43,4 +360,17 @@ pub enum Subcommand {
 
 	/// Db meta columns information.
 	ChainInfo(sc_cli::ChainInfoCmd),
+
+	/// UTXO wallet utilities.
+	#[command(subcommand)]
+	Wallet(crate::wallet::WalletCmd),
+
+	/// Split an airdrop among the owners of the UTXO set at a block.
+	AirdropSnapshot(crate::airdrop::AirdropSnapshotCmd),
+
+	/// Export the transaction graph of a range of blocks as CSV or Parquet.
+	ExportGraph(crate::export::ExportGraphCmd),
+
+	/// Generate TypeScript types of a pallet's calls and events for polkadot-js frontends.
+	ExportTypes(crate::typegen::ExportTypesCmd),
 }
@@ -0,0 +1,761 @@
+//! Electrum protocol server, so that wallets derived from Electrum can use the chain with few
+//! changes.
+//!
+//! Served when the node is started with `--electrum-port`, which needs `--spent-index`. Requests
+//! are newline-delimited JSON-RPC over TCP, as in version 1.4 of the protocol, mapped onto the
+//! UTXO model:
+//!
+//! - A scripthash is the `pubkey` outputs are locked to, in hex: the key itself, or the hash of
+//!   the script (`utxo::script::script_hash`) for script outputs.
+//! - A `tx_hash` is a txid or, for outputs created by the runtime such as rewards, the outpoint of
+//!   the first output. Unspent outputs are listed with their outpoint as `tx_hash` and a `tx_pos`
+//!   of 0, as the outpoint is what an input spends.
+//! - Transactions are SCALE-encoded `Transaction`s and headers SCALE-encoded headers, in hex.
+//!   Extrinsics calling `Utxo::spend` can be broadcast too.
+//! - A height of 0 is a transaction in the pool.
+//!
+//! Histories are read from the index up to the blocks it has yet to index, which are scanned on
+//! every request along with the pool. The statuses of subscribed scripthashes are checked again
+//! on every new best block and on every transaction entering the pool.
+
+use std::{
+	collections::{HashMap, HashSet},
+	fmt::Display,
+	net::{IpAddr, SocketAddr},
+	sync::Arc,
+};
+
+use codec::{Decode, Encode};
+use futures::StreamExt;
+use jsonrpsee::types::error::ErrorObjectOwned;
+use sc_client_api::{BlockBackend, BlockchainEvents};
+use sc_transaction_pool_api::{InPoolTransaction, TransactionPool, TransactionSource};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value as Json;
+use sp_blockchain::HeaderBackend;
+use sp_core::{bytes, hashing::sha2_256, hexdisplay::HexDisplay, H256};
+use sp_runtime::{traits::Header as _, OpaqueExtrinsic};
+use tokio::{
+	io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+	net::{TcpListener, TcpStream},
+};
+use tumuchain_runtime::{
+	opaque::{Block, Header},
+	BlockNumber, RuntimeCall, UncheckedExtrinsic,
+};
+use utxo::{denomination, Transaction, TransactionOutput, Value};
+
+use crate::{
+	import::spends,
+	mempool::RelayPolicy,
+	rate_limit::SubmissionLimiter,
+	rpc::utxo::{decode_transaction, spent_transaction, Utxo, UtxoApiServer, DECODE_ERROR},
+	service::{FullBackend, FullClient, FullPool},
+	spent_index::{self, BlockScan},
+};
+
+/// Version of the Electrum protocol spoken.
+const PROTOCOL_VERSION: &str = "1.4";
+
+/// Name and version of the server, as told to clients.
+const SERVER_VERSION: &str = concat!("tumuchain-node ", env!("CARGO_PKG_VERSION"));
+
+/// Longest request line accepted, in bytes. Connections sending longer ones are closed.
+const MAX_LINE: u64 = 1 << 20;
+
+/// Most scripthashes a connection can subscribe to.
+const MAX_SUBSCRIPTIONS: usize = 1000;
+
+/// Most blocks the index may lag behind the best block. Past it, requests fail until the index
+/// catches up, rather than scanning every block it is missing.
+const MAX_UNINDEXED: BlockNumber = 64;
+
+/// Error codes of JSON-RPC.
+const PARSE_ERROR: i32 = -32700;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+
+/// Error codes of the Electrum protocol.
+const BAD_REQUEST: i32 = 1;
+const DAEMON_ERROR: i32 = 2;
+
+/// Serve the Electrum protocol on `addr` until the node shuts down.
+pub async fn run(
+	addr: SocketAddr,
+	client: Arc<FullClient>,
+	pool: Arc<FullPool>,
+	submissions: SubmissionLimiter,
+	policy: RelayPolicy,
+) {
+	let listener = match TcpListener::bind(addr).await {
+		Ok(listener) => listener,
+		Err(e) => {
+			log::error!(target: "electrum", "Cannot listen for Electrum on {addr}: {e}");
+			return
+		},
+	};
+	log::info!(target: "electrum", "Electrum server listening on {addr}");
+
+	let electrum = Electrum { client, pool, submissions, policy };
+	loop {
+		match listener.accept().await {
+			Ok((stream, peer)) => {
+				log::debug!(target: "electrum", "Electrum connection from {peer}");
+				tokio::spawn(Session::new(electrum.clone(), peer.ip()).serve(stream));
+			},
+			Err(e) => log::warn!(target: "electrum", "Cannot accept an Electrum connection: {e}"),
+		}
+	}
+}
+
+/// A request, with positional parameters.
+#[derive(Deserialize)]
+struct Request {
+	#[serde(default)]
+	id: Json,
+	method: String,
+	#[serde(default)]
+	params: Vec<Json>,
+}
+
+#[derive(Serialize)]
+struct Reply<'a, T> {
+	jsonrpc: &'static str,
+	id: &'a Json,
+	result: &'a T,
+}
+
+#[derive(Serialize)]
+struct ErrorReply<'a> {
+	jsonrpc: &'static str,
+	id: &'a Json,
+	error: &'a Error,
+}
+
+#[derive(Serialize)]
+struct Notification<'a, T> {
+	jsonrpc: &'static str,
+	method: &'a str,
+	params: &'a T,
+}
+
+/// An error answered to a request.
+#[derive(Debug, Serialize)]
+struct Error {
+	code: i32,
+	message: String,
+}
+
+impl Error {
+	fn new(code: i32, message: impl Display) -> Self {
+		Self { code, message: message.to_string() }
+	}
+
+	fn bad_request(message: impl Display) -> Self {
+		Self::new(BAD_REQUEST, message)
+	}
+
+	fn daemon(error: impl Display) -> Self {
+		Self::new(DAEMON_ERROR, error)
+	}
+}
+
+impl From<sp_blockchain::Error> for Error {
+	fn from(error: sp_blockchain::Error) -> Self {
+		Self::daemon(error)
+	}
+}
+
+impl From<ErrorObjectOwned> for Error {
+	fn from(error: ErrorObjectOwned) -> Self {
+		let message = match error.data() {
+			Some(data) => format!("{}: {}", error.message(), data.get()),
+			None => error.message().to_string(),
+		};
+		match error.code() {
+			DECODE_ERROR => Self::bad_request(message),
+			_ => Self::daemon(message),
+		}
+	}
+}
+
+/// A header, as `blockchain.headers.subscribe` answers and notifies.
+#[derive(Serialize)]
+struct HeaderInfo {
+	height: BlockNumber,
+	hex: String,
+}
+
+/// What the server supports, as `server.features` answers.
+#[derive(Serialize)]
+struct Features {
+	genesis_hash: String,
+	hash_function: &'static str,
+	server_version: &'static str,
+	protocol_min: &'static str,
+	protocol_max: &'static str,
+	pruning: Option<BlockNumber>,
+	hosts: serde_json::Map<String, Json>,
+}
+
+#[derive(Serialize)]
+struct Balance {
+	confirmed: Value,
+	/// Paid to the scripthash by the pool, less what the pool spends from it
+	unconfirmed: i128,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct HistoryItem {
+	tx_hash: String,
+	height: BlockNumber,
+	/// Fee of a transaction in the pool, if the values of its inputs are known
+	#[serde(skip_serializing_if = "Option::is_none")]
+	fee: Option<Value>,
+}
+
+#[derive(Serialize)]
+struct UnspentItem {
+	/// The outpoint
+	tx_hash: String,
+	tx_pos: u32,
+	height: BlockNumber,
+	value: Value,
+}
+
+/// A transaction in the pool.
+struct PoolTransaction {
+	txid: H256,
+	/// The outpoints spent, with their outputs if known
+	spent: Vec<(H256, Option<TransactionOutput>)>,
+	/// The outputs created, by outpoint
+	created: Vec<(H256, TransactionOutput)>,
+}
+
+impl PoolTransaction {
+	fn touches(&self, pubkey: &H256) -> bool {
+		self.spent.iter().any(|(_, output)| output.as_ref().is_some_and(|o| o.pubkey == *pubkey)) ||
+			self.created.iter().any(|(_, output)| output.pubkey == *pubkey)
+	}
+
+	/// The value of the inputs less that of the outputs, if the values of the inputs are known.
+	fn fee(&self) -> Option<Value> {
+		let spent = self.spent.iter().try_fold(0, |sum: Value, (_, output)| {
+			output.as_ref().map(|output| sum.saturating_add(output.value))
+		})?;
+		let created = self.created.iter().fold(0, |sum: Value, (_, o)| sum.saturating_add(o.value));
+		Some(spent.saturating_sub(created))
+	}
+}
+
+/// What the index does not have yet: the best chain past it, and the pool.
+struct Recent {
+	best_hash: H256,
+	finalized_number: BlockNumber,
+	/// The blocks not indexed yet, in order
+	blocks: Vec<(BlockNumber, BlockScan)>,
+	/// The transactions in the pool, in order
+	pool: Vec<PoolTransaction>,
+}
+
+/// Reads the chain for every connection.
+#[derive(Clone)]
+struct Electrum {
+	client: Arc<FullClient>,
+	pool: Arc<FullPool>,
+	submissions: SubmissionLimiter,
+	policy: RelayPolicy,
+}
+
+impl Electrum {
+	/// The lowest fee the pool admits, in coins per kilobyte as the protocol has it.
+	fn relay_fee(&self) -> f64 {
+		(self.policy.min_fee_rate() as f64) * 1000.0 / denomination::UNIT as f64
+	}
+
+	fn features(&self) -> Features {
+		Features {
+			genesis_hash: hex(self.client.info().genesis_hash.as_bytes()),
+			hash_function: "blake2_256",
+			server_version: SERVER_VERSION,
+			protocol_min: PROTOCOL_VERSION,
+			protocol_max: PROTOCOL_VERSION,
+			pruning: None,
+			hosts: Default::default(),
+		}
+	}
+
+	fn best_header(&self) -> Result<HeaderInfo, Error> {
+		let best = self.client.info().best_hash;
+		let header = self
+			.client
+			.header(best)?
+			.ok_or_else(|| Error::daemon(format!("no header for the best block {best:?}")))?;
+		Ok(header_info(&header))
+	}
+
+	/// The header at `height` of the best chain.
+	fn header_at(&self, height: BlockNumber) -> Result<String, Error> {
+		let header = match self.client.hash(height)? {
+			Some(hash) => self.client.header(hash)?,
+			None => None,
+		};
+		let header = header.ok_or_else(|| Error::bad_request(format!("no block #{height}")))?;
+		Ok(hex(&header.encode()))
+	}
+
+	/// The blocks of the best chain the index does not have yet.
+	fn unindexed_blocks(&self) -> Result<Vec<(BlockNumber, H256)>, Error> {
+		let next = spent_index::next_block(&*self.client)?;
+		let best = self.client.info().best_number;
+		if best.saturating_sub(next) >= MAX_UNINDEXED {
+			return Err(Error::daemon(format!("the index is catching up, at block #{next}")))
+		}
+		(next..=best)
+			.map(|number| match self.client.hash(number)? {
+				Some(hash) => Ok((number, hash)),
+				None => Err(Error::daemon(format!("no block #{number}"))),
+			})
+			.collect()
+	}
+
+	/// The transactions in the pool, in the order they can be included.
+	fn pool_transactions(&self) -> impl Iterator<Item = Transaction> + '_ {
+		self.pool.ready().filter_map(|ready| {
+			let extrinsic = UncheckedExtrinsic::decode(&mut &ready.data().encode()[..]).ok()?;
+			spent_transaction(extrinsic.function)
+		})
+	}
+
+	fn recent(&self) -> Result<Recent, Error> {
+		let info = self.client.info();
+		let blocks = self
+			.unindexed_blocks()?
+			.into_iter()
+			.map(|(number, hash)| {
+				Ok::<_, Error>((number, spent_index::scan_block(&self.client, hash)?))
+			})
+			.collect::<Result<_, _>>()?;
+
+		// Outputs created by earlier transactions of the pool, which are not in the UTXO set
+		let mut created_in_pool = HashMap::new();
+		let mut pool = Vec::new();
+		for transaction in self.pool_transactions() {
+			let txid = transaction.txid();
+			let mut spent = Vec::with_capacity(transaction.inputs.len());
+			for input in &transaction.inputs {
+				let output = match created_in_pool.remove(&input.outpoint) {
+					Some(output) => Some(output),
+					None => spent_index::output_at(&self.client, info.best_hash, &input.outpoint)?,
+				};
+				spent.push((input.outpoint, output));
+			}
+			let created: Vec<_> = transaction
+				.outputs
+				.iter()
+				.enumerate()
+				.map(|(index, output)| {
+					(Transaction::outpoint_of(&txid, index as u64), output.clone())
+				})
+				.collect();
+			created_in_pool.extend(created.iter().cloned());
+			pool.push(PoolTransaction { txid, spent, created });
+		}
+
+		Ok(Recent {
+			best_hash: info.best_hash,
+			finalized_number: info.finalized_number,
+			blocks,
+			pool,
+		})
+	}
+
+	/// The transactions paying or spending from `pubkey`: those in blocks, oldest first, then
+	/// those in the pool.
+	fn history(&self, pubkey: &H256, recent: &Recent) -> Result<Vec<HistoryItem>, Error> {
+		let mut items: Vec<_> = spent_index::history(&*self.client, pubkey)?
+			.into_iter()
+			.map(|(height, id)| HistoryItem { tx_hash: hex(id.as_bytes()), height, fee: None })
+			.collect();
+		for (number, scan) in &recent.blocks {
+			for (touched, id) in &scan.touched {
+				let item = HistoryItem { tx_hash: hex(id.as_bytes()), height: *number, fee: None };
+				// The block may have been indexed since the scan, and may touch a pubkey twice
+				let listed =
+					items.iter().rev().take_while(|i| i.height == *number).any(|i| *i == item);
+				if touched == pubkey && !listed {
+					items.push(item);
+				}
+			}
+		}
+		items.extend(self.mempool(pubkey, recent));
+		Ok(items)
+	}
+
+	fn mempool(&self, pubkey: &H256, recent: &Recent) -> Vec<HistoryItem> {
+		recent
+			.pool
+			.iter()
+			.filter(|transaction| transaction.touches(pubkey))
+			.map(|transaction| HistoryItem {
+				tx_hash: hex(transaction.txid.as_bytes()),
+				height: 0,
+				fee: transaction.fee(),
+			})
+			.collect()
+	}
+
+	/// The status of `pubkey` as Electrum defines it: the SHA-256 of its history, if it has one.
+	fn status(&self, pubkey: &H256, recent: &Recent) -> Result<Option<String>, Error> {
+		let history = self.history(pubkey, recent)?;
+		if history.is_empty() {
+			return Ok(None)
+		}
+		let preimage: String =
+			history.iter().map(|item| format!("{}:{}:", item.tx_hash, item.height)).collect();
+		Ok(Some(hex(&sha2_256(preimage.as_bytes()))))
+	}
+
+	/// The unspent outputs paying `pubkey` as of the best block.
+	fn unspent_outputs(
+		&self,
+		pubkey: H256,
+		recent: &Recent,
+	) -> Result<Vec<(H256, TransactionOutput)>, Error> {
+		let mut outputs = Utxo::<FullClient, Block, FullBackend>::new(self.client.clone(), false)
+			.unspent_outputs(pubkey, Some(recent.best_hash))?;
+		outputs.sort_by_key(|(outpoint, _)| *outpoint);
+		Ok(outputs)
+	}
+
+	fn balance(&self, pubkey: H256, recent: &Recent) -> Result<Balance, Error> {
+		let confirmed = self
+			.unspent_outputs(pubkey, recent)?
+			.iter()
+			.fold(0, |sum: Value, (_, output)| sum.saturating_add(output.value));
+		let mut unconfirmed = 0i128;
+		for transaction in &recent.pool {
+			for output in transaction.spent.iter().filter_map(|(_, output)| output.as_ref()) {
+				if output.pubkey == pubkey {
+					unconfirmed = unconfirmed.saturating_sub(signed(output.value));
+				}
+			}
+			for (_, output) in &transaction.created {
+				if output.pubkey == pubkey {
+					unconfirmed = unconfirmed.saturating_add(signed(output.value));
+				}
+			}
+		}
+		Ok(Balance { confirmed, unconfirmed })
+	}
+
+	/// The outputs paying `pubkey` that the pool does not spend, with the heights of the blocks
+	/// creating them.
+	fn list_unspent(&self, pubkey: H256, recent: &Recent) -> Result<Vec<UnspentItem>, Error> {
+		let spent_in_pool: HashSet<_> = recent
+			.pool
+			.iter()
+			.flat_map(|transaction| transaction.spent.iter().map(|(outpoint, _)| *outpoint))
+			.collect();
+
+		let mut unspent = Vec::new();
+		for (outpoint, output) in self.unspent_outputs(pubkey, recent)? {
+			if spent_in_pool.contains(&outpoint) {
+				continue
+			}
+			let height = match spent_index::created_at(&*self.client, &outpoint)? {
+				Some(height) => height,
+				// Created past the index, or in a block it could not read the state of, in which
+				// case the output is at least as old as the finalized block
+				None => recent
+					.blocks
+					.iter()
+					.find(|(_, scan)| scan.created.contains(&outpoint))
+					.map_or(recent.finalized_number, |(number, _)| *number),
+			};
+			unspent.push(UnspentItem {
+				tx_hash: hex(outpoint.as_bytes()),
+				tx_pos: 0,
+				height,
+				value: output.value,
+			});
+		}
+		for (outpoint, output) in recent.pool.iter().flat_map(|transaction| &transaction.created) {
+			if output.pubkey == pubkey && !spent_in_pool.contains(outpoint) {
+				unspent.push(UnspentItem {
+					tx_hash: hex(outpoint.as_bytes()),
+					tx_pos: 0,
+					height: 0,
+					value: output.value,
+				});
+			}
+		}
+		Ok(unspent)
+	}
+
+	/// The transaction `txid`, from a block of the best chain or from the pool.
+	fn transaction(&self, txid: H256) -> Result<Transaction, Error> {
+		let blocks = match spent_index::tx_location(&*self.client, &txid)? {
+			Some(location) => vec![location.block_hash],
+			None => self.unindexed_blocks()?.into_iter().map(|(_, hash)| hash).collect(),
+		};
+		for hash in blocks {
+			let body = self
+				.client
+				.block_body(hash)?
+				.ok_or_else(|| Error::daemon(format!("the body of block {hash:?} was pruned")))?;
+			if let Some(transaction) = spends(&body).into_iter().find(|t| t.txid() == txid) {
+				return Ok(transaction)
+			}
+		}
+		self.pool_transactions()
+			.find(|transaction| transaction.txid() == txid)
+			.ok_or_else(|| Error::bad_request(format!("no transaction {txid:?}")))
+	}
+
+	async fn broadcast(&self, raw: &str) -> Result<H256, Error> {
+		let raw = bytes::from_hex(raw).map_err(|e| Error::bad_request(format!("bad hex: {e}")))?;
+		let transaction = decode_transaction(&raw)?;
+		let txid = transaction.txid();
+		let extrinsic =
+			UncheckedExtrinsic::new_unsigned(RuntimeCall::Utxo(utxo::Call::spend { transaction }));
+		let extrinsic = OpaqueExtrinsic::decode(&mut &extrinsic.encode()[..])
+			.map_err(|e| Error::daemon(format!("extrinsic does not re-decode: {e}")))?;
+
+		self.pool
+			.submit_one(self.client.info().best_hash, TransactionSource::External, extrinsic)
+			.await
+			.map_err(|e| Error::bad_request(format!("transaction rejected: {e}")))?;
+		Ok(txid)
+	}
+}
+
+/// What woke a connection up.
+enum Wakeup {
+	Read(std::io::Result<usize>),
+	NewBest(Header),
+	Pool,
+}
+
+/// A connection, with its subscriptions.
+struct Session {
+	electrum: Electrum,
+	/// The client's address, which broadcasts are limited by
+	peer: IpAddr,
+	/// Whether new best headers are sent
+	headers: bool,
+	/// The subscribed scripthashes, with the status last sent
+	scripthashes: HashMap<H256, Option<String>>,
+}
+
+impl Session {
+	fn new(electrum: Electrum, peer: IpAddr) -> Self {
+		Self { electrum, peer, headers: false, scripthashes: HashMap::new() }
+	}
+
+	/// Answer requests and send notifications until the client disconnects.
+	async fn serve(mut self, stream: TcpStream) {
+		let (reader, mut writer) = stream.into_split();
+		let mut reader = BufReader::new(reader);
+		let mut imports = self.electrum.client.import_notification_stream();
+		let mut pool_imports = self.electrum.pool.import_notification_stream();
+		let mut line = Vec::new();
+
+		loop {
+			let mut limited = (&mut reader).take(MAX_LINE - line.len() as u64);
+			let wakeup = tokio::select! {
+				read = limited.read_until(b'\n', &mut line) => Wakeup::Read(read),
+				Some(notification) = imports.next() => match notification.is_new_best {
+					true => Wakeup::NewBest(notification.header),
+					false => continue,
+				},
+				Some(_) = pool_imports.next() => Wakeup::Pool,
+			};
+
+			let messages = match wakeup {
+				Wakeup::Read(Ok(0) | Err(_)) => return,
+				Wakeup::Read(Ok(_)) => {
+					if line.last() != Some(&b'\n') {
+						if line.len() as u64 >= MAX_LINE {
+							let error = Error::new(PARSE_ERROR, "request too long");
+							let error = format!("{}\n", error_reply(&Json::Null, &error));
+							let _ = writer.write_all(error.as_bytes()).await;
+							return
+						}
+						continue
+					}
+					let reply = self.handle(&std::mem::take(&mut line)).await;
+					reply.into_iter().collect()
+				},
+				Wakeup::NewBest(header) => {
+					let mut messages = self.status_changes();
+					if self.headers {
+						let info = header_info(&header);
+						messages.insert(0, notification("blockchain.headers.subscribe", &[info]));
+					}
+					messages
+				},
+				Wakeup::Pool => self.status_changes(),
+			};
+			for message in messages {
+				if writer.write_all(format!("{message}\n").as_bytes()).await.is_err() {
+					return
+				}
+			}
+		}
+	}
+
+	/// The reply to a line holding a request or a batch of them, unless it is blank.
+	async fn handle(&mut self, line: &[u8]) -> Option<String> {
+		let line = String::from_utf8_lossy(line);
+		let line = line.trim();
+		if line.is_empty() {
+			return None
+		}
+		if line.starts_with('[') {
+			let requests = match serde_json::from_str::<Vec<Request>>(line) {
+				Ok(requests) => requests,
+				Err(e) => return Some(error_reply(&Json::Null, &Error::new(PARSE_ERROR, e))),
+			};
+			let mut replies = Vec::with_capacity(requests.len());
+			for request in requests {
+				replies.push(self.call(request).await);
+			}
+			return Some(format!("[{}]", replies.join(",")))
+		}
+		Some(match serde_json::from_str::<Request>(line) {
+			Ok(request) => self.call(request).await,
+			Err(e) => error_reply(&Json::Null, &Error::new(PARSE_ERROR, e)),
+		})
+	}
+
+	async fn call(&mut self, request: Request) -> String {
+		let (id, params) = (&request.id, &request.params[..]);
+		let electrum = self.electrum.clone();
+		let result = match request.method.as_str() {
+			"server.version" => Ok(reply(id, &[SERVER_VERSION, PROTOCOL_VERSION])),
+			"server.banner" => Ok(reply(id, &SERVER_VERSION)),
+			"server.ping" => Ok(reply(id, &())),
+			"server.features" => Ok(reply(id, &electrum.features())),
+			"blockchain.relayfee" => Ok(reply(id, &electrum.relay_fee())),
+			"blockchain.estimatefee" => Ok(reply(id, &-1)),
+			"blockchain.headers.subscribe" => electrum.best_header().map(|header| {
+				self.headers = true;
+				reply(id, &header)
+			}),
+			"blockchain.block.header" => param(params, 0)
+				.and_then(|height| electrum.header_at(height))
+				.map(|header| reply(id, &header)),
+			"blockchain.scripthash.get_balance" => scripthash(params)
+				.and_then(|pubkey| electrum.balance(pubkey, &electrum.recent()?))
+				.map(|balance| reply(id, &balance)),
+			"blockchain.scripthash.get_history" => scripthash(params)
+				.and_then(|pubkey| electrum.history(&pubkey, &electrum.recent()?))
+				.map(|history| reply(id, &history)),
+			"blockchain.scripthash.get_mempool" => scripthash(params)
+				.and_then(|pubkey| Ok(electrum.mempool(&pubkey, &electrum.recent()?)))
+				.map(|mempool| reply(id, &mempool)),
+			"blockchain.scripthash.listunspent" => scripthash(params)
+				.and_then(|pubkey| electrum.list_unspent(pubkey, &electrum.recent()?))
+				.map(|unspent| reply(id, &unspent)),
+			"blockchain.scripthash.subscribe" => scripthash(params).and_then(|pubkey| {
+				if self.scripthashes.len() >= MAX_SUBSCRIPTIONS &&
+					!self.scripthashes.contains_key(&pubkey)
+				{
+					let limit = format!("at most {MAX_SUBSCRIPTIONS} scripthashes per connection");
+					return Err(Error::bad_request(limit))
+				}
+				let status = electrum.status(&pubkey, &electrum.recent()?)?;
+				self.scripthashes.insert(pubkey, status.clone());
+				Ok(reply(id, &status))
+			}),
+			"blockchain.scripthash.unsubscribe" => scripthash(params)
+				.map(|pubkey| reply(id, &self.scripthashes.remove(&pubkey).is_some())),
+			"blockchain.transaction.get" => hash_param(params, 0).and_then(|txid| {
+				if param::<Option<bool>>(params, 1)?.unwrap_or(false) {
+					return Err(Error::bad_request("verbose transactions are not supported"))
+				}
+				Ok(reply(id, &hex(&electrum.transaction(txid)?.encode())))
+			}),
+			"blockchain.transaction.broadcast" => match param::<String>(params, 0) {
+				Ok(_) if !electrum.submissions.allow(self.peer) =>
+					Err(Error::bad_request("too many broadcasts, try again later")),
+				Ok(raw) => electrum.broadcast(&raw).await.map(|txid| reply(id, &hex(&txid[..]))),
+				Err(e) => Err(e),
+			},
+			method => Err(Error::new(METHOD_NOT_FOUND, format!("unknown method {method}"))),
+		};
+		result.unwrap_or_else(|error| error_reply(id, &error))
+	}
+
+	/// Notifications for the subscribed scripthashes whose status changed.
+	fn status_changes(&mut self) -> Vec<String> {
+		if self.scripthashes.is_empty() {
+			return Vec::new()
+		}
+		let recent = match self.electrum.recent() {
+			Ok(recent) => recent,
+			Err(e) => {
+				log::debug!(target: "electrum", "Cannot check statuses: {}", e.message);
+				return Vec::new()
+			},
+		};
+
+		let mut notifications = Vec::new();
+		for (pubkey, sent) in &mut self.scripthashes {
+			let Ok(status) = self.electrum.status(pubkey, &recent) else { continue };
+			if status != *sent {
+				let params = (hex(pubkey.as_bytes()), &status);
+				notifications.push(notification("blockchain.scripthash.subscribe", &params));
+				*sent = status;
+			}
+		}
+		notifications
+	}
+}
+
+fn header_info(header: &Header) -> HeaderInfo {
+	HeaderInfo { height: *header.number(), hex: hex(&header.encode()) }
+}
+
+/// Hex without a prefix, as Electrum clients send and expect it.
+fn hex(data: &[u8]) -> String {
+	HexDisplay::from(&data).to_string()
+}
+
+fn signed(value: Value) -> i128 {
+	i128::try_from(value).unwrap_or(i128::MAX)
+}
+
+fn param<T: DeserializeOwned>(params: &[Json], index: usize) -> Result<T, Error> {
+	let param = params.get(index).cloned().unwrap_or_default();
+	serde_json::from_value(param)
+		.map_err(|e| Error::new(INVALID_PARAMS, format!("parameter {index}: {e}")))
+}
+
+/// Parse a 32-byte hash parameter, with or without a 0x prefix.
+fn hash_param(params: &[Json], index: usize) -> Result<H256, Error> {
+	let hex: String = param(params, index)?;
+	match bytes::from_hex(&hex) {
+		Ok(hash) if hash.len() == 32 => Ok(H256::from_slice(&hash)),
+		_ => Err(Error::new(INVALID_PARAMS, format!("{hex} is not a 32-byte hex hash"))),
+	}
+}
+
+fn scripthash(params: &[Json]) -> Result<H256, Error> {
+	hash_param(params, 0)
+}
+
+fn reply<T: Serialize>(id: &Json, result: &T) -> String {
+	serde_json::to_string(&Reply { jsonrpc: "2.0", id, result }).expect("replies serialize; qed")
+}
+
+fn error_reply(id: &Json, error: &Error) -> String {
+	serde_json::to_string(&ErrorReply { jsonrpc: "2.0", id, error })
+		.expect("errors serialize; qed")
+}
+
+fn notification<T: Serialize>(method: &str, params: &T) -> String {
+	serde_json::to_string(&Notification { jsonrpc: "2.0", method, params })
+		.expect("notifications serialize; qed")
+}
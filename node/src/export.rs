@@ -0,0 +1,355 @@
+//! The `export-graph` subcommand, writing the transaction graph of a range of blocks as an edge
+//! list for compliance and analytics tools.
+//!
+//! Each row links an output spent to an output created by the same transaction, so a transaction
+//! with two inputs and two outputs makes four rows. Outputs created by the runtime, such as
+//! rewards and mints, make rows without a spent output, with the outpoint of the first output of
+//! their event as txid.
+//!
+//! Spent outputs are read from the state of the parent block and runtime outputs from the events
+//! of the block, so exporting old blocks needs a node keeping their state
+//! (`--state-pruning archive`). Where the state was pruned, the owner and value of spent outputs
+//! are left empty and runtime outputs are left out.
+
+use crate::{
+	import::spends,
+	service::FullClient,
+	spent_index::{output_at, runtime_outputs},
+};
+use codec::{Decode, Encode};
+use parquet::{
+	data_type::{ByteArray, ByteArrayType, Int32Type, Int64Type},
+	errors::ParquetError,
+	file::{properties::WriterProperties, writer::SerializedFileWriter},
+	schema::parser::parse_message_type,
+};
+use sc_cli::{CliConfiguration, DatabaseParams, SharedParams};
+use sc_client_api::BlockBackend;
+use sp_blockchain::HeaderBackend;
+use sp_core::H256;
+use sp_runtime::{traits::Header as _, OpaqueExtrinsic};
+use std::{
+	collections::HashMap,
+	fs::File,
+	io::{self, BufWriter, Write},
+	path::PathBuf,
+	sync::Arc,
+};
+use tumuchain_runtime::{BlockNumber, RuntimeCall, TimestampCall, UncheckedExtrinsic};
+use utxo::{Transaction, TransactionOutput};
+
+/// Names of the columns, in order.
+const COLUMNS: [&str; 10] = [
+	"block_number",
+	"block_hash",
+	"timestamp",
+	"txid",
+	"spent_outpoint",
+	"spent_pubkey",
+	"spent_value",
+	"created_outpoint",
+	"created_pubkey",
+	"created_value",
+];
+
+/// Schema of Parquet exports. Values are decimal strings, as they may not fit 64 bits.
+const PARQUET_SCHEMA: &str = "
+	message edge {
+		REQUIRED INT32 block_number (INTEGER(32, false));
+		REQUIRED BINARY block_hash (STRING);
+		REQUIRED INT64 timestamp (TIMESTAMP(MILLIS, true));
+		REQUIRED BINARY txid (STRING);
+		OPTIONAL BINARY spent_outpoint (STRING);
+		OPTIONAL BINARY spent_pubkey (STRING);
+		OPTIONAL BINARY spent_value (STRING);
+		REQUIRED BINARY created_outpoint (STRING);
+		REQUIRED BINARY created_pubkey (STRING);
+		REQUIRED BINARY created_value (STRING);
+	}
+";
+
+/// Rows per row group of Parquet exports.
+const ROW_GROUP_SIZE: usize = 64 * 1024;
+
+/// Export the outputs spent and created by the transactions of a range of blocks, as an edge
+/// list in CSV or Parquet.
+///
+/// Columns are `block_number`, `block_hash`, `timestamp` (milliseconds since the Unix epoch),
+/// `txid`, then the outpoint, pubkey and value of the output spent and of the output created.
+/// Hashes and pubkeys are 0x-prefixed hex.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct ExportGraphCmd {
+	/// First block to export.
+	#[arg(long, value_name = "NUMBER", default_value_t = 1)]
+	pub from: BlockNumber,
+
+	/// Last block to export. Defaults to the best block.
+	#[arg(long, value_name = "NUMBER")]
+	pub to: Option<BlockNumber>,
+
+	/// Format of the export.
+	#[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+	pub format: ExportFormat,
+
+	/// File to write the export to. Defaults to stdout, for CSV only.
+	#[arg(long, short, value_name = "PATH")]
+	pub output: Option<PathBuf>,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub database_params: DatabaseParams,
+}
+
+/// Formats of the export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+	/// Comma-separated values, with a header row
+	Csv,
+	/// Apache Parquet
+	Parquet,
+}
+
+impl ExportGraphCmd {
+	/// Run the export against the node's database.
+	pub async fn run(&self, client: Arc<FullClient>) -> sc_cli::Result<()> {
+		let to = self.to.unwrap_or_else(|| client.info().best_number);
+		if self.from > to {
+			return Err(sc_cli::Error::Input(format!("block #{} is past #{to}", self.from)))
+		}
+
+		let mut writer = match (self.format, &self.output) {
+			(ExportFormat::Csv, Some(path)) =>
+				Writer::csv(Box::new(BufWriter::new(File::create(path)?)))?,
+			(ExportFormat::Csv, None) => Writer::csv(Box::new(io::stdout().lock()))?,
+			(ExportFormat::Parquet, Some(path)) => Writer::parquet(File::create(path)?)?,
+			(ExportFormat::Parquet, None) =>
+				return Err(sc_cli::Error::Input("Parquet exports need `--output`".into())),
+		};
+		let mut rows = 0;
+		for number in self.from..=to {
+			let edges = block_edges(&client, number)?;
+			rows += edges.len();
+			writer.write(edges)?;
+		}
+		writer.finish()?;
+
+		log::info!("📤 Exported {rows} edges of blocks #{}..=#{to}", self.from);
+		Ok(())
+	}
+}
+
+impl CliConfiguration for ExportGraphCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+}
+
+/// A row of the export.
+struct Edge {
+	block_number: BlockNumber,
+	block_hash: H256,
+	/// Milliseconds since the Unix epoch
+	timestamp: u64,
+	txid: H256,
+	/// The outpoint spent, `None` for outputs created by the runtime
+	spent_outpoint: Option<H256>,
+	/// The output spent, if the parent block's state is kept
+	spent: Option<TransactionOutput>,
+	created_outpoint: H256,
+	created: TransactionOutput,
+}
+
+impl Edge {
+	/// The values of the row, in the order of [`COLUMNS`].
+	fn cells(&self) -> [Option<String>; 10] {
+		[
+			Some(self.block_number.to_string()),
+			Some(format!("{:?}", self.block_hash)),
+			Some(self.timestamp.to_string()),
+			Some(format!("{:?}", self.txid)),
+			self.spent_outpoint.map(|outpoint| format!("{outpoint:?}")),
+			self.spent.as_ref().map(|output| format!("{:?}", output.pubkey)),
+			self.spent.as_ref().map(|output| output.value.to_string()),
+			Some(format!("{:?}", self.created_outpoint)),
+			Some(format!("{:?}", self.created.pubkey)),
+			Some(self.created.value.to_string()),
+		]
+	}
+}
+
+/// The edges of block `number`: those of its spends in order, then those of the outputs created
+/// by the runtime.
+fn block_edges(client: &FullClient, number: BlockNumber) -> sc_cli::Result<Vec<Edge>> {
+	let unknown = || sc_cli::Error::Input(format!("no block #{number}"));
+	let block_hash = client.hash(number)?.ok_or_else(unknown)?;
+	let header = client.header(block_hash)?.ok_or_else(unknown)?;
+	let body = client.block_body(block_hash)?.ok_or_else(|| {
+		sc_cli::Error::Input(format!("the body of block #{number} was pruned"))
+	})?;
+	let timestamp = timestamp(&body);
+	let edge = |txid, spent_outpoint, spent, created_outpoint, created| Edge {
+		block_number: number,
+		block_hash,
+		timestamp,
+		txid,
+		spent_outpoint,
+		spent,
+		created_outpoint,
+		created,
+	};
+	let parent = *header.parent_hash();
+
+	let mut edges = Vec::new();
+	// Outputs created earlier in the block, which are not in the parent's UTXO set
+	let mut created_here = HashMap::new();
+	for transaction in spends(&body) {
+		let txid = transaction.txid();
+		let spent: Vec<_> = transaction
+			.inputs
+			.iter()
+			.map(|input| {
+				let output = match created_here.remove(&input.outpoint) {
+					Some(output) => Some(output),
+					None => output_at(client, parent, &input.outpoint).ok().flatten(),
+				};
+				(input.outpoint, output)
+			})
+			.collect();
+		for (index, output) in transaction.outputs.iter().enumerate() {
+			let outpoint = Transaction::outpoint_of(&txid, index as u64);
+			edges.extend(spent.iter().map(|(spent_outpoint, spent)| {
+				edge(txid, Some(*spent_outpoint), spent.clone(), outpoint, output.clone())
+			}));
+			created_here.insert(outpoint, output.clone());
+		}
+	}
+
+	match runtime_outputs(client, block_hash, number) {
+		Ok(groups) =>
+			for outputs in groups {
+				let Some(&(id, _)) = outputs.first() else { continue };
+				edges.extend(
+					outputs
+						.into_iter()
+						.map(|(outpoint, output)| edge(id, None, None, outpoint, output)),
+				);
+			},
+		Err(e) => log::warn!("Leaving out the outputs created by the runtime in #{number}: {e}"),
+	}
+	Ok(edges)
+}
+
+/// The time `body` sets, in milliseconds since the Unix epoch.
+pub(crate) fn timestamp(body: &[OpaqueExtrinsic]) -> u64 {
+	body.iter()
+		.find_map(|extrinsic| {
+			match UncheckedExtrinsic::decode(&mut &extrinsic.encode()[..]).ok()?.function {
+				RuntimeCall::Timestamp(TimestampCall::set { now }) => Some(now),
+				_ => None,
+			}
+		})
+		.unwrap_or_default()
+}
+
+/// Writes the rows of an export.
+enum Writer {
+	Csv(Box<dyn Write>),
+	Parquet { file: SerializedFileWriter<File>, rows: Vec<Edge> },
+}
+
+impl Writer {
+	fn csv(mut out: Box<dyn Write>) -> sc_cli::Result<Self> {
+		writeln!(out, "{}", COLUMNS.join(","))?;
+		Ok(Self::Csv(out))
+	}
+
+	fn parquet(file: File) -> sc_cli::Result<Self> {
+		let schema = parse_message_type(PARQUET_SCHEMA).map_err(parquet_error)?;
+		let properties = WriterProperties::builder().build();
+		let file = SerializedFileWriter::new(file, Arc::new(schema), Arc::new(properties))
+			.map_err(parquet_error)?;
+		Ok(Self::Parquet { file, rows: Vec::new() })
+	}
+
+	fn write(&mut self, edges: Vec<Edge>) -> sc_cli::Result<()> {
+		match self {
+			Self::Csv(out) =>
+				for edge in &edges {
+					writeln!(out, "{}", edge.cells().map(Option::unwrap_or_default).join(","))?;
+				},
+			Self::Parquet { file, rows } => {
+				rows.extend(edges);
+				if rows.len() >= ROW_GROUP_SIZE {
+					write_row_group(file, &std::mem::take(rows)).map_err(parquet_error)?;
+				}
+			},
+		}
+		Ok(())
+	}
+
+	fn finish(self) -> sc_cli::Result<()> {
+		match self {
+			Self::Csv(mut out) => out.flush()?,
+			Self::Parquet { mut file, rows } => {
+				if !rows.is_empty() {
+					write_row_group(&mut file, &rows).map_err(parquet_error)?;
+				}
+				file.close().map_err(parquet_error)?;
+			},
+		}
+		Ok(())
+	}
+}
+
+/// Write `rows` as a row group, column by column.
+fn write_row_group(
+	file: &mut SerializedFileWriter<File>,
+	rows: &[Edge],
+) -> Result<(), ParquetError> {
+	let cells: Vec<_> = rows.iter().map(Edge::cells).collect();
+	let mut group = file.next_row_group()?;
+
+	for (index, name) in COLUMNS.iter().enumerate() {
+		let mut column = group
+			.next_column()?
+			.ok_or_else(|| ParquetError::General(format!("no column {name} in the schema")))?;
+		match *name {
+			"block_number" => {
+				// Unsigned in the schema, stored in the bits of an INT32
+				let numbers: Vec<_> = rows.iter().map(|edge| edge.block_number as i32).collect();
+				column.typed::<Int32Type>().write_batch(&numbers, None, None)?;
+			},
+			"timestamp" => {
+				let times: Vec<_> = rows.iter().map(|edge| edge.timestamp as i64).collect();
+				column.typed::<Int64Type>().write_batch(&times, None, None)?;
+			},
+			_ => {
+				let values: Vec<ByteArray> = cells
+					.iter()
+					.filter_map(|row| row[index].clone())
+					.map(|value| ByteArray::from(value.into_bytes()))
+					.collect();
+				// Only the spent columns are optional, with a definition level per row
+				let levels: Vec<i16> =
+					cells.iter().map(|row| i16::from(row[index].is_some())).collect();
+				let levels = name.starts_with("spent_").then_some(&levels[..]);
+				column.typed::<ByteArrayType>().write_batch(&values, levels, None)?;
+			},
+		}
+		column.close()?;
+	}
+	group.close()?;
+	Ok(())
+}
+
+fn parquet_error(error: ParquetError) -> sc_cli::Error {
+	sc_cli::Error::Input(format!("cannot write Parquet: {error}"))
+}
@@ -0,0 +1,241 @@
+//! gRPC interface for exchange and custody integrations, served next to JSON-RPC.
+//!
+//! Implements the services of `proto/tumuchain.proto` on top of the same lookups as the `utxo_*`
+//! JSON-RPC methods, so balances and unspent outputs still come from a scan of the UTXO set.
+//! The server only runs when the node is started with `--grpc-port`.
+
+use std::{net::SocketAddr, pin::Pin, sync::Arc};
+
+use codec::{Decode, Encode};
+use futures::{
+	future::ready,
+	stream::{self, BoxStream},
+	Stream, StreamExt,
+};
+use jsonrpsee::types::error::ErrorObjectOwned;
+use sc_client_api::{BlockBackend, BlockchainEvents};
+use sc_transaction_pool_api::{TransactionPool, TransactionSource};
+use sp_blockchain::HeaderBackend;
+use sp_core::H256;
+use sp_runtime::{traits::Header as _, OpaqueExtrinsic};
+use tonic::{Request, Response, Status};
+use tumuchain_runtime::{opaque::Block, RuntimeCall, UncheckedExtrinsic};
+use utxo::TransactionOutput;
+
+use crate::{
+	rate_limit::SubmissionLimiter,
+	rpc::utxo::{
+		decode_transaction, spent_transaction, Utxo as UtxoRpc, UtxoApiServer, DECODE_ERROR,
+		PRUNED_ERROR,
+	},
+	service::{FullBackend, FullClient, FullPool},
+};
+
+/// Code generated from `proto/tumuchain.proto`.
+pub mod proto {
+	tonic::include_proto!("tumuchain.v1");
+}
+
+use proto::{
+	blocks_server::{Blocks, BlocksServer},
+	transactions_server::{Transactions, TransactionsServer},
+	utxo_server::{Utxo, UtxoServer},
+	BlockSummary, BroadcastRequest, BroadcastResponse, GetBalanceRequest, GetBalanceResponse,
+	ListUnspentRequest, ListUnspentResponse, SubscribeRequest, UnspentOutput,
+};
+
+/// Serve the gRPC services on `addr` until the node shuts down.
+pub async fn run(
+	addr: SocketAddr,
+	client: Arc<FullClient>,
+	pool: Arc<FullPool>,
+	submissions: SubmissionLimiter,
+) {
+	let node = Node { client, pool, submissions };
+	log::info!(target: "grpc", "gRPC server listening on {addr}");
+
+	let served = tonic::transport::Server::builder()
+		.add_service(UtxoServer::new(node.clone()))
+		.add_service(TransactionsServer::new(node.clone()))
+		.add_service(BlocksServer::new(node))
+		.serve(addr)
+		.await;
+	if let Err(e) = served {
+		log::error!(target: "grpc", "gRPC server on {addr} failed: {e}");
+	}
+}
+
+/// Implements every gRPC service.
+#[derive(Clone)]
+struct Node {
+	client: Arc<FullClient>,
+	pool: Arc<FullPool>,
+	submissions: SubmissionLimiter,
+}
+
+impl Node {
+	/// The unspent outputs paying `pubkey` at block `at`, or the best block if empty.
+	fn unspent_outputs(
+		&self,
+		pubkey: &[u8],
+		at: &[u8],
+	) -> Result<(H256, Vec<(H256, TransactionOutput)>), Status> {
+		let pubkey = hash_arg("pubkey", pubkey)?;
+		let at = match at {
+			[] => self.client.info().best_hash,
+			at => hash_arg("at", at)?,
+		};
+		let mut outputs = UtxoRpc::<FullClient, Block, FullBackend>::new(self.client.clone(), false)
+			.unspent_outputs(pubkey, Some(at))
+			.map_err(rpc_status)?;
+		outputs.sort_by_key(|(outpoint, _)| *outpoint);
+		Ok((at, outputs))
+	}
+}
+
+#[tonic::async_trait]
+impl Utxo for Node {
+	async fn get_balance(
+		&self,
+		request: Request<GetBalanceRequest>,
+	) -> Result<Response<GetBalanceResponse>, Status> {
+		let request = request.into_inner();
+		let (at, outputs) = self.unspent_outputs(&request.pubkey, &request.at)?;
+		let value = outputs.iter().fold(0u128, |sum, (_, output)| sum.saturating_add(output.value));
+
+		Ok(Response::new(GetBalanceResponse {
+			value: value.to_string(),
+			outputs: outputs.len() as u32,
+			at: at.as_bytes().to_vec(),
+		}))
+	}
+
+	async fn list_unspent(
+		&self,
+		request: Request<ListUnspentRequest>,
+	) -> Result<Response<ListUnspentResponse>, Status> {
+		let request = request.into_inner();
+		let (at, outputs) = self.unspent_outputs(&request.pubkey, &request.at)?;
+
+		Ok(Response::new(ListUnspentResponse {
+			outputs: outputs
+				.into_iter()
+				.map(|(outpoint, output)| UnspentOutput {
+					outpoint: outpoint.as_bytes().to_vec(),
+					value: output.value.to_string(),
+					pubkey: output.pubkey.as_bytes().to_vec(),
+					memo: output.memo.map(|memo| memo.as_bytes().to_vec()).unwrap_or_default(),
+				})
+				.collect(),
+			at: at.as_bytes().to_vec(),
+		}))
+	}
+}
+
+#[tonic::async_trait]
+impl Transactions for Node {
+	async fn broadcast(
+		&self,
+		request: Request<BroadcastRequest>,
+	) -> Result<Response<BroadcastResponse>, Status> {
+		if let Some(addr) = request.remote_addr() {
+			if !self.submissions.allow(addr.ip()) {
+				return Err(Status::resource_exhausted("too many transactions, try again later"))
+			}
+		}
+		let transaction =
+			decode_transaction(&request.into_inner().transaction).map_err(rpc_status)?;
+		let txid = transaction.txid();
+		let extrinsic =
+			UncheckedExtrinsic::new_unsigned(RuntimeCall::Utxo(utxo::Call::spend { transaction }));
+		let extrinsic = OpaqueExtrinsic::decode(&mut &extrinsic.encode()[..])
+			.map_err(|e| Status::internal(format!("extrinsic does not re-decode: {e}")))?;
+
+		let extrinsic_hash = self
+			.pool
+			.submit_one(self.client.info().best_hash, TransactionSource::External, extrinsic)
+			.await
+			.map_err(|e| Status::failed_precondition(format!("transaction rejected: {e}")))?;
+		Ok(Response::new(BroadcastResponse {
+			txid: txid.as_bytes().to_vec(),
+			extrinsic_hash: extrinsic_hash.as_bytes().to_vec(),
+		}))
+	}
+}
+
+#[tonic::async_trait]
+impl Blocks for Node {
+	type SubscribeStream = Pin<Box<dyn Stream<Item = Result<BlockSummary, Status>> + Send>>;
+
+	async fn subscribe(
+		&self,
+		request: Request<SubscribeRequest>,
+	) -> Result<Response<Self::SubscribeStream>, Status> {
+		let hashes: BoxStream<'static, H256> = if request.into_inner().finalized {
+			// One notification can finalize several blocks, which are all streamed
+			self.client
+				.finality_notification_stream()
+				.flat_map(|notification| {
+					let mut hashes = notification.tree_route.to_vec();
+					hashes.push(notification.hash);
+					stream::iter(hashes)
+				})
+				.boxed()
+		} else {
+			self.client
+				.import_notification_stream()
+				.filter(|notification| ready(notification.is_new_best))
+				.map(|notification| notification.hash)
+				.boxed()
+		};
+
+		let client = self.client.clone();
+		Ok(Response::new(Box::pin(hashes.map(move |hash| block_summary(&client, hash)))))
+	}
+}
+
+/// The summary streamed for block `hash`.
+fn block_summary(client: &FullClient, hash: H256) -> Result<BlockSummary, Status> {
+	let header = client
+		.header(hash)
+		.map_err(|e| Status::internal(e.to_string()))?
+		.ok_or_else(|| Status::not_found(format!("block {hash:?} is unknown")))?;
+	let body = client.block_body(hash).map_err(|e| Status::internal(e.to_string()))?;
+
+	Ok(BlockSummary {
+		hash: hash.as_bytes().to_vec(),
+		number: *header.number(),
+		parent_hash: header.parent_hash().as_bytes().to_vec(),
+		txids: body
+			.unwrap_or_default()
+			.iter()
+			.filter_map(|extrinsic| {
+				UncheckedExtrinsic::decode(&mut &extrinsic.encode()[..])
+					.ok()
+					.and_then(|extrinsic| spent_transaction(extrinsic.function))
+			})
+			.map(|transaction| transaction.txid().as_bytes().to_vec())
+			.collect(),
+	})
+}
+
+/// Parse a 32-byte hash argument.
+fn hash_arg(name: &str, bytes: &[u8]) -> Result<H256, Status> {
+	if bytes.len() != 32 {
+		return Err(Status::invalid_argument(format!("{name} must be 32 bytes")))
+	}
+	Ok(H256::from_slice(bytes))
+}
+
+/// Converts the error of a JSON-RPC lookup into a gRPC status.
+fn rpc_status(error: ErrorObjectOwned) -> Status {
+	let message = match error.data() {
+		Some(data) => format!("{}: {}", error.message(), data.get()),
+		None => error.message().to_string(),
+	};
+	match error.code() {
+		DECODE_ERROR => Status::invalid_argument(message),
+		PRUNED_ERROR => Status::not_found(message),
+		_ => Status::internal(message),
+	}
+}
@@ -0,0 +1,157 @@
+//! Block import verifying the spend signatures of a block on all cores before executing it.
+//!
+//! The runtime checks the sigscripts of a block one transaction after the other. Inputs only
+//! spend outputs that exist and are not spent twice within the block, both of which execution
+//! still checks, so their signatures can be verified independently beforehand. The valid ones
+//! reach the runtime's `sr25519_verify` calls through [`VerifiedSignatures`], and are not verified
+//! again. Invalid signatures are left out and fail during execution as usual, so the outcome of a
+//! block does not depend on this.
+//!
+//! Only blocks received from the network are handled. Blocks authored by this node were already
+//! executed while being built.
+//!
+//! The spent outputs are looked up through the [`UtxoCache`], which imported blocks keep up to
+//! date.
+
+use crate::{
+	sigcache::{cache_key, VerifiedSignatures},
+	utxo_cache::UtxoCache,
+};
+use codec::{Decode, Encode};
+use rayon::prelude::*;
+use sc_client_api::execution_extensions::ExtensionsFactory;
+use sc_consensus::{BlockCheckParams, BlockImport, BlockImportParams, ImportResult, StateAction};
+use sp_consensus::{BlockOrigin, Error as ConsensusError};
+use sp_core::{
+	sr25519::{Pair, Public, Signature},
+	Pair as _, H256,
+};
+use sp_externalities::Extensions;
+use sp_runtime::traits::{BlakeTwo256, Hash, Header as _};
+use std::{
+	collections::{HashMap, HashSet},
+	sync::{Arc, Mutex},
+};
+use tumuchain_runtime::{
+	opaque::{Block, UncheckedExtrinsic as OpaqueExtrinsic},
+	BlockNumber, RuntimeCall, UncheckedExtrinsic,
+};
+use utxo::Transaction;
+
+/// Signatures verified ahead of execution, by the hash of the block executed on top of.
+///
+/// Registered as the client's extensions factory, so that executing a block on top of a parent
+/// with an entry gets the entry as [`VerifiedSignatures`].
+#[derive(Clone, Default)]
+pub struct SignatureCache(Arc<Mutex<HashMap<H256, Arc<HashSet<H256>>>>>);
+
+impl ExtensionsFactory<Block> for SignatureCache {
+	fn extensions_for(&self, block_hash: H256, _block_number: BlockNumber) -> Extensions {
+		let mut extensions = Extensions::new();
+		if let Some(verified) = self.0.lock().expect("not poisoned").get(&block_hash) {
+			extensions.register(VerifiedSignatures(verified.clone()));
+		}
+		extensions
+	}
+}
+
+/// Wraps the block import of the import queue, verifying the spend signatures of each block in
+/// parallel before passing it on.
+#[derive(Clone)]
+pub struct ParallelSignatureImport<I> {
+	inner: I,
+	utxos: Arc<UtxoCache>,
+	cache: SignatureCache,
+}
+
+impl<I> ParallelSignatureImport<I> {
+	/// Wrap `inner`, looking up spent outputs in `utxos` and handing verified signatures to the
+	/// runtime through `cache`, which has to be the extensions factory of the client.
+	pub fn new(inner: I, utxos: Arc<UtxoCache>, cache: SignatureCache) -> Self {
+		Self { inner, utxos, cache }
+	}
+
+	/// The sigscripts of `spends` that the runtime will verify, verified in parallel on the
+	/// rayon pool. Returns the [`cache_key`]s of the valid ones.
+	fn verify_signatures(&self, parent: H256, spends: &[Transaction]) -> HashSet<H256> {
+		// Outputs created earlier in the block, which are not in the parent's state
+		let mut created = HashMap::new();
+		let mut checks = Vec::new();
+
+		for transaction in spends {
+			let payload = Arc::new(transaction.signing_payload());
+			let txid = BlakeTwo256::hash(&payload);
+
+			for input in transaction.inputs.iter().filter(|input| input.witness.is_none()) {
+				let Some(sigscript) = input.sigscript else { continue };
+				let pubkey = created.get(&input.outpoint).copied().or_else(|| {
+					self.utxos.get(parent, &input.outpoint).map(|output| output.pubkey)
+				});
+				// Missing outputs fail execution anyway
+				if let Some(pubkey) = pubkey {
+					let signature = Signature::from_raw(*sigscript.as_fixed_bytes());
+					checks.push((signature, payload.clone(), Public::from_h256(pubkey)));
+				}
+			}
+			for (index, output) in transaction.outputs.iter().enumerate() {
+				created.insert(Transaction::outpoint_of(&txid, index as u64), output.pubkey);
+			}
+		}
+
+		checks
+			.into_par_iter()
+			.filter(|(signature, payload, pubkey)| Pair::verify(signature, &payload[..], pubkey))
+			.map(|(signature, payload, pubkey)| cache_key(&signature, &payload[..], &pubkey))
+			.collect()
+	}
+}
+
+/// The UTXO transactions of `body`.
+pub(crate) fn spends(body: &[OpaqueExtrinsic]) -> Vec<Transaction> {
+	body.iter()
+		.filter_map(|extrinsic| {
+			match UncheckedExtrinsic::decode(&mut &extrinsic.encode()[..]).ok()?.function {
+				RuntimeCall::Utxo(utxo::Call::spend { transaction }) => Some(transaction),
+				_ => None,
+			}
+		})
+		.collect()
+}
+
+#[async_trait::async_trait]
+impl<I> BlockImport<Block> for ParallelSignatureImport<I>
+where
+	I: BlockImport<Block, Error = ConsensusError> + Send + Sync,
+{
+	type Error = ConsensusError;
+
+	async fn check_block(
+		&self,
+		block: BlockCheckParams<Block>,
+	) -> Result<ImportResult, Self::Error> {
+		self.inner.check_block(block).await
+	}
+
+	async fn import_block(
+		&self,
+		block: BlockImportParams<Block>,
+	) -> Result<ImportResult, Self::Error> {
+		let executes =
+			matches!(block.state_action, StateAction::Execute | StateAction::ExecuteIfPossible);
+		let spends = match &block.body {
+			Some(body) if executes && block.origin != BlockOrigin::Own => spends(body),
+			_ => return self.inner.import_block(block).await,
+		};
+
+		let parent = *block.header.parent_hash();
+		let verified = self.verify_signatures(parent, &spends);
+		self.cache.0.lock().expect("not poisoned").insert(parent, Arc::new(verified));
+		let result = self.inner.import_block(block).await;
+		self.cache.0.lock().expect("not poisoned").remove(&parent);
+
+		if let Ok(ImportResult::Imported(_)) = &result {
+			self.utxos.connect_block(&spends);
+		}
+		result
+	}
+}
@@ -0,0 +1,155 @@
+//! Instant sealing for local development, enabled by `--dev-instant-mine`.
+//!
+//! Instead of waiting for its Aura slot, the node seals a block as soon as a transaction enters
+//! the pool, with the same proposer as in slots, so the block still carries the author's digests,
+//! retargets the difficulty and pays its reward. The runtime requires the Aura slot to increase
+//! from block to block, so a block is timestamped at least a slot past its parent: the chain's
+//! clock runs ahead of the wall clock while transactions come in faster than slots. The blocks
+//! are not sealed with the Aura key, so no other node imports them.
+//!
+//! A [`BlockGenerator`] seals blocks on demand as well, for `miner_generateBlocks` to script the
+//! chain's progress from integration tests.
+
+use std::{future::Future, sync::Arc};
+
+use futures::{
+	channel::{mpsc, oneshot},
+	SinkExt, StreamExt,
+};
+use sc_consensus::BlockImport;
+use sc_consensus_manual_seal::{
+	consensus::aura::AuraConsensusDataProvider, run_manual_seal, EngineCommand, ManualSealParams,
+};
+use sc_transaction_pool_api::TransactionPool;
+use sp_blockchain::HeaderBackend;
+use sp_consensus::{Environment, Proposer, SelectChain};
+use sp_consensus_aura::{digests::CompatibleDigestItem, sr25519::AuthoritySignature, SlotDuration};
+use sp_core::{sr25519, H256};
+use sp_runtime::{traits::Header as _, DigestItem};
+use sp_timestamp::Timestamp;
+use tumuchain_runtime::opaque::Block;
+
+use crate::{
+	authoring::PayoutKey,
+	service::{FullClient, FullPool},
+};
+
+/// A request to seal a block, answered with its hash once imported.
+type SealCommand = EngineCommand<H256>;
+
+/// Seals blocks paying a given key on request, through the loop of [`run`].
+#[derive(Clone)]
+pub struct BlockGenerator {
+	commands: mpsc::Sender<SealCommand>,
+	payout_key: PayoutKey,
+	/// Held while generating, so blocks of concurrent requests do not interleave
+	generating: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl BlockGenerator {
+	/// A generator paying through `payout_key`, and the commands to hand to [`run`].
+	pub fn new(payout_key: PayoutKey) -> (Self, mpsc::Receiver<SealCommand>) {
+		let (commands, receiver) = mpsc::channel(16);
+		(Self { commands, payout_key, generating: Default::default() }, receiver)
+	}
+
+	/// Seal `n` blocks, empty or not, one on top of the other, paying their rewards to `pubkey`,
+	/// and return their hashes. Blocks sealed meanwhile for transactions entering the pool pay
+	/// `pubkey` too.
+	pub async fn generate(&self, n: u32, pubkey: sr25519::Public) -> Result<Vec<H256>, String> {
+		let _generating = self.generating.lock().await;
+		let rotated = self.payout_key.swap(Some(pubkey));
+		let mut hashes = Vec::with_capacity(n as usize);
+		let mut result = Ok(());
+		for _ in 0..n {
+			match self.seal().await {
+				Ok(hash) => hashes.push(hash),
+				Err(e) => {
+					result = Err(e);
+					break
+				},
+			}
+		}
+		self.payout_key.swap(rotated);
+		result.map(|()| hashes)
+	}
+
+	/// Seal a block on top of the best one.
+	async fn seal(&self) -> Result<H256, String> {
+		let (sender, receiver) = oneshot::channel();
+		let command = EngineCommand::SealNewBlock {
+			create_empty: true,
+			finalize: false,
+			parent_hash: None,
+			sender: Some(sender),
+		};
+		self.commands.clone().send(command).await.map_err(|e| e.to_string())?;
+		let created = receiver.await.map_err(|_| "the sealing task stopped".to_string())?;
+		created.map(|block| block.hash).map_err(|e| e.to_string())
+	}
+}
+
+/// Seal a block on top of the best one each time a transaction is imported into `pool`, and
+/// whenever `commands` asks for one.
+pub fn run<E, BI, SC>(
+	client: Arc<FullClient>,
+	pool: Arc<FullPool>,
+	select_chain: SC,
+	block_import: BI,
+	env: E,
+	slot_duration: SlotDuration,
+	commands: mpsc::Receiver<SealCommand>,
+) -> impl Future<Output = ()>
+where
+	E: Environment<Block> + 'static,
+	E::Proposer: Proposer<Block, Proof = ()>,
+	BI: BlockImport<Block, Error = sp_consensus::Error> + Send + Sync + 'static,
+	SC: SelectChain<Block> + 'static,
+{
+	// No empty block is sealed when an earlier block included the transactions already
+	let pool_commands = pool.import_notification_stream().map(|_| EngineCommand::SealNewBlock {
+		create_empty: false,
+		finalize: false,
+		parent_hash: None,
+		sender: None,
+	});
+	let commands_stream = futures::stream::select(pool_commands, commands);
+
+	let timestamp_client = client.clone();
+	run_manual_seal(ManualSealParams {
+		block_import,
+		env,
+		client: client.clone(),
+		pool,
+		commands_stream,
+		select_chain,
+		consensus_data_provider: Some(Box::new(AuraConsensusDataProvider::new(client))),
+		create_inherent_data_providers: move |parent, ()| {
+			let timestamp = next_timestamp(&timestamp_client, parent, slot_duration);
+			async move { Ok(sp_timestamp::InherentDataProvider::new(timestamp)) }
+		},
+	})
+}
+
+/// The timestamp of the block on top of `parent`: the current time, but no earlier than the start
+/// of the slot after `parent`'s.
+fn next_timestamp(
+	client: &FullClient,
+	parent: <Block as sp_runtime::traits::Block>::Hash,
+	slot_duration: SlotDuration,
+) -> Timestamp {
+	let now = Timestamp::current();
+	// Genesis has no slot
+	let parent_slot = client.header(parent).ok().flatten().and_then(|header| {
+		header.digest().logs().iter().find_map(|log| {
+			<DigestItem as CompatibleDigestItem<AuthoritySignature>>::as_aura_pre_digest(log)
+		})
+	});
+	match parent_slot {
+		Some(slot) => {
+			let next = (u64::from(slot) + 1).saturating_mul(slot_duration.as_millis());
+			now.max(Timestamp::new(next))
+		},
+		None => now,
+	}
+}
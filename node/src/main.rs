@@ -1,12 +1,33 @@
 //! Substrate Node Template CLI library.
 #![warn(missing_docs)]
 
+mod airdrop;
+mod authoring;
 mod benchmarking;
 mod chain_spec;
+mod chain_telemetry;
 mod cli;
 mod command;
+mod electrum;
+mod export;
+mod grpc;
+mod import;
+mod instant_seal;
+mod mempool;
+mod rate_limit;
+mod relay;
+mod rest;
+mod rosetta;
 mod rpc;
+mod rpc_gateway;
 mod service;
+mod sigcache;
+mod spent_index;
+mod typegen;
+mod utxo_cache;
+mod wallet;
+mod watch;
+mod zmq;
 
 fn main() -> sc_cli::Result<()> {
 	command::run()
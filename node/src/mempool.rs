@@ -0,0 +1,594 @@
+//! Mempool maintenance tailored to a fee-market UTXO chain.
+//!
+//! The stock Substrate pool orders transactions by priority (the UTXO fee, raised for
+//! consolidations) and only evicts once its count or byte limits are hit while importing. This
+//! module runs a background task that, on every new best block, drops transactions that have been
+//! waiting for more than `--pool-max-age` blocks and, when the ready queue grows past its byte
+//! budget, evicts the transactions paying the lowest fee per byte first. Fees are worked out from
+//! the outputs spent, of the chain through the [`UtxoCache`] or of other pooled transactions, less
+//! the outputs created; only extrinsics other than UTXO spends are ranked by their priority.
+//!
+//! Fee rates are per byte of [`virtual_size`]: the witness bytes of UTXO transactions count for a
+//! `WITNESS_SCALE_FACTOR`th, as the runtime prices spending outputs below creating them. The byte
+//! budget of the ready queue still counts every byte.
+//!
+//! A transaction spending the outputs of another in the pool counts towards its parent's fee
+//! rate: the parent is ranked by the fee rate of itself and its descendants when that is higher
+//! than its own, so a child paying a higher fee for it (CPFP) keeps it from being evicted.
+//! Evicting a transaction evicts its descendants with it.
+//!
+//! Transactions spending outputs the pool has not seen yet, orphans, wait in the pool's future
+//! queue and join the ready queue as soon as the parents creating those outputs arrive, in a
+//! block or in the pool. As their fee is unknown until then, they are bounded apart: orphans
+//! waiting for more than `--pool-orphan-max-age` blocks are dropped, and the oldest are evicted
+//! first once they take more than `--pool-orphan-kbytes`.
+//!
+//! The runtime only knows the fee of a transaction whose inputs are all on chain, so a child
+//! submitted while its parent is still in the pool is pooled with no priority. The fees of
+//! transactions submitted together as a package, see [`crate::rpc::package`], are worked out
+//! from the package instead and recorded in [`PackageFees`], which admission goes by.
+//!
+//! Admission to the pool also goes through a node-local [`RelayPolicy`]: transactions submitted
+//! from outside paying less than `--min-relay-fee-rate` per byte are rejected, as the runtime has
+//! no minimum fee. The fee is the value of the outputs a transaction spends, looked up through the
+//! [`UtxoCache`], less that of those it creates; the priority the runtime gives it is raised for
+//! consolidations and by aging, so it is no measure of what was paid. Only the pool applies it,
+//! through [`PolicyChainApi`]; blocks carrying such transactions are still valid, so operators
+//! can tune it without any consensus change.
+//! Transactions whose fee is not known yet, waiting for their inputs or submitted in a package
+//! paying enough as a whole, are let through. With `--require-canonical-order`, the policy also
+//! rejects transactions from outside whose inputs and outputs are not in canonical order, ahead
+//! of the runtime's `CanonicalOrderingFrom` making that a consensus rule.
+//!
+//! With `--pool-priority-aging`, the policy also raises the priority of waiting transactions by
+//! that fee per byte for every block since they were first validated, so that low-fee
+//! transactions eventually win a place in blocks during lulls. Priorities are only updated when
+//! the pool revalidates its transactions in the background, so they climb in steps. Eviction adds
+//! the same credit to the fee, keeping long-waiting transactions over new ones paying as little.
+//!
+//! [`run_eviction`] removes transactions through the pool's `remove_invalid`, which also bans
+//! them for `--tx-ban-seconds` (30 minutes by default). This is deliberate. Peers still holding an
+//! evicted transaction relay it again, and without the ban it would be admitted and evicted again
+//! at every block, as Bitcoin Core avoids by raising its minimum fee after evicting. Peers are not
+//! penalized for relaying a banned transaction. Outputs or a fee changed by the sender make a new
+//! transaction, with a new hash the ban does not cover; only the very same transaction has to wait
+//! out the ban to return once the pool drains.
+//!
+//! The policy also notes when the node first validated each pooled transaction, as
+//! `utxo_pendingTransactions` of [`crate::rpc::pending`] reports it.
+
+use std::{
+	cmp::Ordering,
+	collections::{HashMap, HashSet},
+	sync::{
+		atomic::{AtomicBool, AtomicU64, Ordering as AtomicOrdering},
+		Arc, Mutex,
+	},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use futures::{future::BoxFuture, FutureExt, StreamExt};
+use sc_client_api::BlockchainEvents;
+use sc_transaction_pool::ChainApi;
+use sc_transaction_pool_api::{InPoolTransaction, TransactionPool, TransactionSource, TxHash};
+use sp_blockchain::TreeRoute;
+use sp_core::{Decode, Encode, H256};
+use sp_runtime::{
+	generic::BlockId,
+	traits::{Block as BlockT, Header as HeaderT, NumberFor, SaturatedConversion},
+	transaction_validity::{InvalidTransaction, TransactionTag, TransactionValidity},
+};
+use tumuchain_runtime::{opaque::Block, Runtime, UncheckedExtrinsic};
+use utxo::{Transaction, Value};
+
+use crate::{cli::MempoolParams, rpc::utxo::spent_transaction, utxo_cache::UtxoCache};
+
+/// Fees of pooled transactions that the pool does not know, by transaction hash.
+///
+/// Entries are added by package submission and dropped once their transaction leaves the ready
+/// queue.
+#[derive(Clone, Default)]
+pub struct PackageFees(Arc<Mutex<HashMap<H256, u64>>>);
+
+impl PackageFees {
+	/// Record the fee of each transaction of `fees`.
+	pub fn record(&self, fees: impl IntoIterator<Item = (H256, u64)>) {
+		self.0.lock().expect("not poisoned").extend(fees);
+	}
+
+	/// Forget the fees of `hashes`.
+	pub fn forget<'a>(&self, hashes: impl IntoIterator<Item = &'a H256>) {
+		let mut fees = self.0.lock().expect("not poisoned");
+		for hash in hashes {
+			fees.remove(hash);
+		}
+	}
+
+	/// The fee recorded for `hash`, if any.
+	fn get(&self, hash: &H256) -> Option<u64> {
+		self.0.lock().expect("not poisoned").get(hash).copied()
+	}
+
+	/// Forget the fees of the transactions not in `live`.
+	fn retain(&self, live: &HashSet<H256>) {
+		self.0.lock().expect("not poisoned").retain(|hash, _| live.contains(hash));
+	}
+}
+
+/// Best block numbers transactions were first validated at, by transaction hash.
+type FirstValidated = Arc<Mutex<HashMap<H256, u64>>>;
+
+/// Times transactions were first validated at, in milliseconds since the Unix epoch, by
+/// transaction hash.
+type FirstSeen = Arc<Mutex<HashMap<H256, u64>>>;
+
+/// Node-local rules for admitting transactions to the pool, on top of the runtime's.
+#[derive(Clone, Default)]
+pub struct RelayPolicy {
+	/// Lowest fee per byte of the transactions admitted from outside, `0` for none
+	min_fee_rate: Arc<AtomicU64>,
+	/// Fee per byte added to priorities for each block waited, `0` for no aging
+	aging_rate: Arc<AtomicU64>,
+	/// Whether transactions admitted from outside must be in canonical order
+	canonical_order: Arc<AtomicBool>,
+	first_validated: FirstValidated,
+	first_seen: FirstSeen,
+	package_fees: PackageFees,
+}
+
+impl RelayPolicy {
+	/// Reject transactions from outside paying less than `rate` per byte.
+	pub fn set_min_fee_rate(&self, rate: u64) {
+		self.min_fee_rate.store(rate, AtomicOrdering::Relaxed);
+	}
+
+	/// The lowest fee per byte admitted.
+	pub fn min_fee_rate(&self) -> u64 {
+		self.min_fee_rate.load(AtomicOrdering::Relaxed)
+	}
+
+	/// The fees of the transactions submitted as packages, which are admitted whatever their own
+	/// fee rate.
+	pub fn package_fees(&self) -> &PackageFees {
+		&self.package_fees
+	}
+
+	/// Whether `size` bytes paying `fee` pay the minimum fee rate.
+	pub fn admits(&self, fee: u64, size: usize) -> bool {
+		fee_rate_cmp((fee, size), (self.min_fee_rate(), 1)) != Ordering::Less
+	}
+
+	/// Raise the priority of waiting transactions by `rate` per byte for each block waited.
+	pub fn set_aging_rate(&self, rate: u64) {
+		self.aging_rate.store(rate, AtomicOrdering::Relaxed);
+	}
+
+	/// Reject transactions from outside whose inputs and outputs are not in canonical order.
+	pub fn set_require_canonical_order(&self, required: bool) {
+		self.canonical_order.store(required, AtomicOrdering::Relaxed);
+	}
+
+	/// When the node first validated transaction `hash`, in milliseconds since the Unix epoch, if
+	/// it is still pooled.
+	pub fn first_seen(&self, hash: &H256) -> Option<u64> {
+		self.first_seen.lock().expect("not poisoned").get(hash).copied()
+	}
+
+	/// Note that transaction `hash` was validated now, unless it was before.
+	fn saw(&self, hash: H256) {
+		let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64);
+		self.first_seen.lock().expect("not poisoned").entry(hash).or_insert(now);
+	}
+
+	/// Whether `transaction` breaks the canonical order required of it, if any is.
+	fn out_of_order(&self, transaction: Option<&Transaction>) -> bool {
+		self.canonical_order.load(AtomicOrdering::Relaxed) &&
+			transaction.is_some_and(|transaction| !transaction.is_canonically_ordered())
+	}
+
+	/// The priority of transaction `hash`, of `size` bytes, validated with `priority` on top of
+	/// block `number`.
+	fn aged(&self, hash: H256, number: Option<u64>, priority: u64, size: usize) -> u64 {
+		number.map_or(priority, |number| priority.saturating_add(self.aging(hash, number, size)))
+	}
+
+	/// The fee credited to transaction `hash`, of `size` bytes, for waiting until block `number`.
+	fn aging(&self, hash: H256, number: u64, size: usize) -> u64 {
+		let rate = self.aging_rate.load(AtomicOrdering::Relaxed);
+		if rate == 0 {
+			return 0
+		}
+		let mut first_validated = self.first_validated.lock().expect("not poisoned");
+		let first = *first_validated.entry(hash).or_insert(number);
+		let waited = number.saturating_sub(first);
+		rate.saturating_mul(waited).saturating_mul(size as u64)
+	}
+
+	/// Forget the transactions not in `live`, the ready queue, keeping when those `waiting` for
+	/// their inputs were first seen.
+	fn retain(&self, live: &HashSet<H256>, waiting: &HashSet<H256>) {
+		self.package_fees.retain(live);
+		self.first_validated.lock().expect("not poisoned").retain(|hash, _| live.contains(hash));
+		self.first_seen
+			.lock()
+			.expect("not poisoned")
+			.retain(|hash, _| live.contains(hash) || waiting.contains(hash));
+	}
+}
+
+/// The pool's access to the chain, validating transactions with the runtime through `A` and
+/// then against the [`RelayPolicy`].
+pub struct PolicyChainApi<A> {
+	inner: A,
+	policy: RelayPolicy,
+	utxos: Arc<UtxoCache>,
+}
+
+impl<A> PolicyChainApi<A> {
+	/// Apply `policy` to the transactions validated by `inner`, looking the outputs they spend up
+	/// in `utxos`.
+	pub fn new(inner: A, policy: RelayPolicy, utxos: Arc<UtxoCache>) -> Self {
+		Self { inner, policy, utxos }
+	}
+}
+
+impl<A> ChainApi for PolicyChainApi<A>
+where
+	A: ChainApi<Block = Block>,
+	A::ValidationFuture: 'static,
+{
+	type Block = Block;
+	type Error = A::Error;
+	type ValidationFuture = BoxFuture<'static, Result<TransactionValidity, Self::Error>>;
+	type BodyFuture = A::BodyFuture;
+
+	fn validate_transaction(
+		&self,
+		at: H256,
+		source: TransactionSource,
+		uxt: <Block as BlockT>::Extrinsic,
+	) -> Self::ValidationFuture {
+		let (hash, size) = self.inner.hash_and_length(&uxt);
+		let transaction = utxo_transaction(&uxt.encode());
+		let size = discounted(size, transaction.as_ref());
+		let number = self.inner.block_id_to_number(&BlockId::Hash(at)).ok().flatten();
+		let out_of_order = source == TransactionSource::External &&
+			self.policy.out_of_order(transaction.as_ref());
+		let validation = self.inner.validate_transaction(at, source, uxt);
+		let policy = self.policy.clone();
+		let utxos = self.utxos.clone();
+		async move {
+			Ok(validation.await?.and_then(|mut valid| {
+				if out_of_order {
+					let code = utxo::Error::<Runtime>::NonCanonicalOrder.encode()[0];
+					return Err(InvalidTransaction::Custom(code).into())
+				}
+				// Transactions from blocks and from the node itself are not relayed ones, and the
+				// fee of those missing inputs is unknown
+				let exempt = source != TransactionSource::External ||
+					!valid.requires.is_empty() ||
+					policy.package_fees.get(&hash).is_some();
+				if !exempt {
+					// Every output spent is in the state at `at`, or `requires` would not be empty.
+					// Other extrinsics are charged by their priority.
+					let fee = match &transaction {
+						Some(transaction) =>
+							fee(transaction, |outpoint| utxos.get(at, outpoint).map(|o| o.value)),
+						None => Some(valid.priority.into()),
+					};
+					let fee = fee.map(|fee| fee.saturated_into::<u64>());
+					if !fee.is_some_and(|fee| policy.admits(fee, size)) {
+						return Err(InvalidTransaction::Payment.into())
+					}
+				}
+				let number = number.map(u64::from);
+				valid.priority = policy.aged(hash, number, valid.priority, size);
+				policy.saw(hash);
+				Ok(valid)
+			}))
+		}
+		.boxed()
+	}
+
+	fn block_id_to_number(
+		&self,
+		at: &BlockId<Block>,
+	) -> Result<Option<NumberFor<Block>>, Self::Error> {
+		self.inner.block_id_to_number(at)
+	}
+
+	fn block_id_to_hash(&self, at: &BlockId<Block>) -> Result<Option<H256>, Self::Error> {
+		self.inner.block_id_to_hash(at)
+	}
+
+	fn hash_and_length(&self, uxt: &<Block as BlockT>::Extrinsic) -> (H256, usize) {
+		self.inner.hash_and_length(uxt)
+	}
+
+	fn block_body(&self, at: H256) -> Self::BodyFuture {
+		self.inner.block_body(at)
+	}
+
+	fn block_header(
+		&self,
+		at: H256,
+	) -> Result<Option<<Block as BlockT>::Header>, Self::Error> {
+		self.inner.block_header(at)
+	}
+
+	fn tree_route(&self, from: H256, to: H256) -> Result<TreeRoute<Block>, Self::Error> {
+		self.inner.tree_route(from, to)
+	}
+}
+
+/// The UTXO transaction of the extrinsic encoded as `encoded`, if it is one.
+fn utxo_transaction(encoded: &[u8]) -> Option<Transaction> {
+	let extrinsic = UncheckedExtrinsic::decode(&mut &encoded[..]).ok()?;
+	spent_transaction(extrinsic.function)
+}
+
+/// The fee `transaction` pays: the value of the outputs it spends, as `value_of` finds them, less
+/// that of those it creates. `None` if an output spent is not found.
+pub(crate) fn fee(
+	transaction: &Transaction,
+	value_of: impl Fn(&H256) -> Option<Value>,
+) -> Option<Value> {
+	let mut input_value: Value = 0;
+	for input in &transaction.inputs {
+		input_value = input_value.saturating_add(value_of(&input.outpoint)?);
+	}
+	input_value.checked_sub(transaction.output_value())
+}
+
+/// `size`, less the witness bytes of `transaction` that [`Transaction::virtual_size`] discounts.
+fn discounted(size: usize, transaction: Option<&Transaction>) -> usize {
+	let discount = transaction.map_or(0, |transaction| {
+		transaction.encoded_size().saturating_sub(transaction.virtual_size() as usize)
+	});
+	size.saturating_sub(discount)
+}
+
+/// The size fee rates are worked out over for `extrinsic`: its encoded size, with the witness
+/// bytes of its UTXO transaction, if it is one, discounted.
+pub fn virtual_size(extrinsic: &impl Encode) -> usize {
+	let encoded = extrinsic.encode();
+	discounted(encoded.len(), utxo_transaction(&encoded).as_ref())
+}
+
+/// Compares two `(fee, virtual_size)` pool entries by fee rate, lowest first.
+///
+/// The comparison cross-multiplies to stay in integer arithmetic. Empty sizes count as one byte.
+fn fee_rate_cmp(a: (u64, usize), b: (u64, usize)) -> Ordering {
+	let lhs = (a.0 as u128).saturating_mul(b.1.max(1) as u128);
+	let rhs = (b.0 as u128).saturating_mul(a.1.max(1) as u128);
+	lhs.cmp(&rhs)
+}
+
+/// The descendants of each of the `ready` transactions, itself included, as indices into
+/// `ready`, which holds the tags each requires and provides.
+fn descendants(ready: &[(&[TransactionTag], &[TransactionTag])]) -> Vec<Vec<usize>> {
+	let providers: HashMap<_, _> = ready
+		.iter()
+		.enumerate()
+		.flat_map(|(n, (_, provides))| provides.iter().map(move |tag| (tag, n)))
+		.collect();
+	let mut children = vec![Vec::new(); ready.len()];
+	for (n, (requires, _)) in ready.iter().enumerate() {
+		for parent in requires.iter().filter_map(|tag| providers.get(tag)) {
+			children[*parent].push(n);
+		}
+	}
+
+	(0..ready.len())
+		.map(|n| {
+			let mut package = vec![n];
+			let mut seen = HashSet::from([n]);
+			let mut at = 0;
+			while let Some(parent) = package.get(at).copied() {
+				at += 1;
+				package.extend(children[parent].iter().filter(|child| seen.insert(**child)));
+			}
+			package
+		})
+		.collect()
+}
+
+/// The ready transactions of `packages`, their [`descendants`], in the order they are evicted
+/// in: lowest fee rate first, each ranked by the fee rate of itself and its descendants when that
+/// is higher than its own. `fees` and `vsizes` are those of each transaction.
+fn eviction_order(packages: &[Vec<usize>], fees: &[u64], vsizes: &[usize]) -> Vec<usize> {
+	let mut ranked: Vec<_> = packages
+		.iter()
+		.enumerate()
+		.map(|(n, package)| {
+			let own = (fees[n], vsizes[n]);
+			let package_fee = package.iter().fold(0u64, |total, m| total.saturating_add(fees[*m]));
+			let all = (package_fee, package.iter().map(|m| vsizes[*m]).sum());
+			match fee_rate_cmp(own, all) {
+				Ordering::Less => (n, all),
+				_ => (n, own),
+			}
+		})
+		.collect();
+	ranked.sort_by(|a, b| fee_rate_cmp(a.1, b.1));
+	ranked.into_iter().map(|(n, _)| n).collect()
+}
+
+/// Runs the eviction policy until the client's import stream ends.
+///
+/// `max_bytes` is the size of the ready queue above which low fee-rate transactions are evicted.
+/// The outputs spent by pooled transactions are looked up in `utxos`. Evicted transactions are
+/// banned from the pool for a while; see the module documentation.
+pub async fn run_eviction<C, P>(
+	client: Arc<C>,
+	pool: Arc<P>,
+	params: MempoolParams,
+	max_bytes: usize,
+	policy: RelayPolicy,
+	utxos: Arc<UtxoCache>,
+) where
+	C: BlockchainEvents<Block> + Send + Sync + 'static,
+	P: TransactionPool<Block = Block, Hash = H256> + 'static,
+{
+	let max_age: u64 = params.pool_max_age.into();
+	let max_bytes = params.pool_evict_kbytes.map(|kb| kb * 1024).unwrap_or(max_bytes);
+	let max_orphan_age: u64 = params.pool_orphan_max_age.into();
+	let max_orphan_bytes = params.pool_orphan_kbytes * 1024;
+	let mut first_seen: HashMap<TxHash<P>, u64> = HashMap::new();
+	let mut imports = client.import_notification_stream();
+
+	while let Some(notification) = imports.next().await {
+		if !notification.is_new_best {
+			continue
+		}
+		let number: u64 = (*notification.header.number()).saturated_into::<u64>();
+
+		let mut ready: Vec<_> = pool.ready().collect();
+		let futures = pool.futures();
+		let live: HashSet<_> = ready.iter().map(|tx| tx.hash().clone()).collect();
+		let waiting: HashSet<_> = futures.iter().map(|tx| tx.hash().clone()).collect();
+		first_seen.retain(|hash, _| live.contains(hash) || waiting.contains(hash));
+		policy.retain(&live, &waiting);
+
+		let mut evicted = Vec::new();
+		ready.retain(|tx| {
+			let seen = *first_seen.entry(tx.hash().clone()).or_insert(number);
+			if max_age > 0 && number.saturating_sub(seen) >= max_age {
+				evicted.push(tx.hash().clone());
+				false
+			} else {
+				true
+			}
+		});
+		let expired = evicted.len();
+
+		// Orphans, oldest first, those expired and then those past the budget
+		let mut orphans: Vec<_> = futures
+			.iter()
+			.map(|tx| (*first_seen.entry(tx.hash().clone()).or_insert(number), tx))
+			.collect();
+		orphans.sort_by_key(|(seen, _)| *seen);
+		let mut orphan_bytes: usize = orphans.iter().map(|(_, tx)| tx.data().encoded_size()).sum();
+		for (seen, tx) in orphans {
+			let stale = max_orphan_age > 0 && number.saturating_sub(seen) >= max_orphan_age;
+			if !stale && orphan_bytes <= max_orphan_bytes {
+				break
+			}
+			orphan_bytes = orphan_bytes.saturating_sub(tx.data().encoded_size());
+			evicted.push(tx.hash().clone());
+		}
+		let orphaned = evicted.len() - expired;
+
+		let sizes: Vec<_> = ready.iter().map(|tx| tx.data().encoded_size()).collect();
+		let mut total: usize = sizes.iter().sum();
+		if total > max_bytes {
+			let vsizes: Vec<_> = ready.iter().map(|tx| virtual_size(tx.data())).collect();
+			let spends: Vec<_> =
+				ready.iter().map(|tx| utxo_transaction(&tx.data().encode())).collect();
+			// Outputs of ready transactions, which their descendants spend
+			let created: HashMap<H256, Value> = spends
+				.iter()
+				.flatten()
+				.flat_map(|transaction| {
+					transaction.outputs.iter().enumerate().map(|(index, output)| {
+						(transaction.outpoint(index as u64), output.value)
+					})
+				})
+				.collect();
+			let value_of = |outpoint: &H256| {
+				created.get(outpoint).copied().or_else(|| {
+					utxos.get(notification.hash, outpoint).map(|output| output.value)
+				})
+			};
+			let fees: Vec<_> = ready
+				.iter()
+				.zip(&spends)
+				.zip(&vsizes)
+				.map(|((tx, transaction), vsize)| {
+					// Other extrinsics pay by priority, aged already
+					let Some(transaction) = transaction else { return *tx.priority() };
+					let paid = fee(transaction, value_of).map(|fee| fee.saturated_into::<u64>());
+					let paid = paid.or_else(|| policy.package_fees.get(tx.hash()));
+					let aging = policy.aging(*tx.hash(), number, *vsize);
+					paid.unwrap_or_default().saturating_add(aging)
+				})
+				.collect();
+			let tags: Vec<_> = ready.iter().map(|tx| (tx.requires(), tx.provides())).collect();
+			let packages = descendants(&tags);
+
+			let mut gone = HashSet::new();
+			for n in eviction_order(&packages, &fees, &vsizes) {
+				if total <= max_bytes {
+					break
+				}
+				for m in &packages[n] {
+					if gone.insert(*m) {
+						total = total.saturating_sub(sizes[*m]);
+						evicted.push(ready[*m].hash().clone());
+					}
+				}
+			}
+		}
+
+		if !evicted.is_empty() {
+			log::debug!(
+				target: "mempool",
+				"Evicting {} transactions at #{} ({} expired, {} orphans, {} below fee cut-off)",
+				evicted.len(),
+				number,
+				expired,
+				orphaned,
+				evicted.len() - expired - orphaned,
+			);
+			pool.remove_invalid(&evicted);
+			for hash in &evicted {
+				first_seen.remove(hash);
+			}
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fee_rates_compare_by_fee_per_byte() {
+		assert_eq!(fee_rate_cmp((10, 2), (4, 1)), Ordering::Greater);
+		assert_eq!(fee_rate_cmp((10, 5), (4, 2)), Ordering::Equal);
+		assert_eq!(fee_rate_cmp((0, 100), (1, 100)), Ordering::Less);
+		// Empty sizes count as a byte
+		assert_eq!(fee_rate_cmp((3, 0), (3, 1)), Ordering::Equal);
+		// Cross-multiplying does not overflow
+		assert_eq!(fee_rate_cmp((u64::MAX, usize::MAX), (u64::MAX, 1)), Ordering::Less);
+	}
+
+	#[test]
+	fn descendants_follow_spent_outputs() {
+		let tag = |n: u8| vec![n];
+		// 0 creates 1 and 2, 1 spends 1 and creates 3, 2 spends 2 and 3, 3 stands alone
+		let provides = [vec![tag(1), tag(2)], vec![tag(3)], vec![tag(4)], vec![tag(5)]];
+		let requires = [vec![], vec![tag(1)], vec![tag(2), tag(3)], vec![tag(9)]];
+		let ready: Vec<_> =
+			requires.iter().zip(&provides).map(|(r, p)| (&r[..], &p[..])).collect();
+
+		let packages = descendants(&ready);
+		assert_eq!(packages, vec![vec![0, 1, 2], vec![1, 2], vec![2], vec![3]]);
+	}
+
+	#[test]
+	fn eviction_ranks_parents_by_their_children() {
+		// A parent paying nothing, its child paying for both, and a stranger in between
+		let packages = vec![vec![0, 1], vec![1], vec![2]];
+		let fees = [0, 1_000, 300];
+		let vsizes = [100, 100, 100];
+
+		// The parent goes at the rate of the package, 5 per byte, after the stranger's 3
+		assert_eq!(eviction_order(&packages, &fees, &vsizes), vec![2, 0, 1]);
+
+		// A child paying less than its parent does not drag it down
+		let fees = [1_000, 0, 300];
+		assert_eq!(eviction_order(&packages, &fees, &vsizes), vec![1, 2, 0]);
+	}
+}
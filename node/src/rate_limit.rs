@@ -0,0 +1,156 @@
+//! Per-address rate limiting of transaction submissions.
+//!
+//! Spends are unsigned extrinsics: the pool runs the whole validation of each one submitted,
+//! signatures and scripts included, before it can tell spam from a payment, and nobody pays for
+//! the ones that fail. The servers that see their clients' addresses, Electrum, gRPC, Rosetta and
+//! the [`crate::rpc_gateway`], share a [`SubmissionLimiter`] giving every address a burst of
+//! `--submit-burst` submissions, refilled at `--submit-rate-limit` a minute, and turn away
+//! submissions over it before decoding them. IPv6 addresses are limited by /64, the block a
+//! single host is usually given.
+//!
+//! Two layers are left out on purpose. Calls to Substrate's own JSON-RPC server are limited per
+//! connection by `--rpc-rate-limit` instead, as its methods do not see the caller's address; a
+//! node serving the public does so through the gateway. And Substrate's transaction protocol
+//! imports relayed transactions without telling the pool which peer sent them, so there is no
+//! per-peer allowance to keep: peers relaying transactions the pool turns away are scored and
+//! banned by [`crate::relay`] instead.
+
+use std::{
+	collections::HashMap,
+	net::{IpAddr, Ipv6Addr},
+	sync::{Arc, Mutex},
+	time::Instant,
+};
+
+/// Addresses tracked before those with a full allowance are forgotten.
+const MAX_TRACKED: usize = 1 << 16;
+
+/// What an address has left to submit.
+struct Allowance {
+	/// Submissions left, in thousandths
+	millis: u64,
+	/// When `millis` was last refilled
+	refilled: Instant,
+}
+
+/// Submissions allowed to each client address, shared by the servers taking submissions.
+#[derive(Clone)]
+pub struct SubmissionLimiter {
+	/// Submissions refilled per minute, `0` for no limit
+	per_minute: u32,
+	/// Most submissions in a burst
+	burst: u32,
+	allowances: Arc<Mutex<HashMap<IpAddr, Allowance>>>,
+}
+
+impl SubmissionLimiter {
+	/// Allow each address `burst` submissions at once, refilled at `per_minute`. A `per_minute`
+	/// of `0` allows any number.
+	pub fn new(per_minute: u32, burst: u32) -> Self {
+		Self { per_minute, burst: burst.max(1), allowances: Default::default() }
+	}
+
+	/// Whether `addr` may submit a transaction now, counting it if so.
+	pub fn allow(&self, addr: IpAddr) -> bool {
+		self.allow_at(addr, Instant::now())
+	}
+
+	/// Whether `addr` may submit a transaction at `now`, counting it if so.
+	fn allow_at(&self, addr: IpAddr, now: Instant) -> bool {
+		if self.per_minute == 0 {
+			return true
+		}
+		let full = u64::from(self.burst) * 1000;
+		let refill = |allowance: &Allowance| {
+			// Thousandths of a submission per millisecond is the rate per minute over 60
+			let elapsed = now.saturating_duration_since(allowance.refilled).as_millis() as u64;
+			let refilled = elapsed.saturating_mul(u64::from(self.per_minute)) / 60;
+			allowance.millis.saturating_add(refilled).min(full)
+		};
+
+		let mut allowances = self.allowances.lock().expect("not poisoned");
+		if allowances.len() >= MAX_TRACKED {
+			allowances.retain(|_, allowance| refill(allowance) < full);
+		}
+		let allowance =
+			allowances.entry(key(addr)).or_insert(Allowance { millis: full, refilled: now });
+		allowance.millis = refill(allowance);
+		allowance.refilled = now;
+		match allowance.millis.checked_sub(1000) {
+			Some(left) => {
+				allowance.millis = left;
+				true
+			},
+			None => false,
+		}
+	}
+}
+
+/// The address `addr` is limited under: itself, or its /64 for IPv6.
+fn key(addr: IpAddr) -> IpAddr {
+	match addr {
+		IpAddr::V4(_) => addr,
+		IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+			Some(v4) => IpAddr::V4(v4),
+			None => IpAddr::V6(Ipv6Addr::from(u128::from(v6) & !u128::from(u64::MAX))),
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::{net::Ipv4Addr, time::Duration};
+
+	#[test]
+	fn bursts_are_refilled_at_the_rate() {
+		let limiter = SubmissionLimiter::new(60, 3);
+		let addr = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1));
+		let start = Instant::now();
+
+		assert!((0..3).all(|_| limiter.allow_at(addr, start)));
+		assert!(!limiter.allow_at(addr, start));
+		// Others keep their own allowance
+		assert!(limiter.allow_at(IpAddr::V4(Ipv4Addr::new(198, 51, 100, 2)), start));
+
+		// One a second at 60 a minute, counted in thousandths
+		assert!(!limiter.allow_at(addr, start + Duration::from_millis(999)));
+		assert!(limiter.allow_at(addr, start + Duration::from_millis(1_000)));
+		assert!(!limiter.allow_at(addr, start + Duration::from_millis(1_000)));
+
+		// Waiting refills no more than the burst
+		let later = start + Duration::from_secs(3_600);
+		assert!((0..3).all(|_| limiter.allow_at(addr, later)));
+		assert!(!limiter.allow_at(addr, later));
+	}
+
+	#[test]
+	fn no_rate_allows_everything() {
+		let limiter = SubmissionLimiter::new(0, 1);
+		let addr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+		assert!((0..1_000).all(|_| limiter.allow(addr)));
+	}
+
+	#[test]
+	fn ipv6_addresses_are_limited_by_their_64() {
+		let host: Ipv6Addr = "2001:db8:1:2:aaaa:bbbb:cccc:dddd".parse().unwrap();
+		let neighbour: Ipv6Addr = "2001:db8:1:2::1".parse().unwrap();
+		let other: Ipv6Addr = "2001:db8:1:3:aaaa:bbbb:cccc:dddd".parse().unwrap();
+		let prefix: Ipv6Addr = "2001:db8:1:2::".parse().unwrap();
+
+		assert_eq!(key(IpAddr::V6(host)), IpAddr::V6(prefix));
+		assert_eq!(key(IpAddr::V6(neighbour)), IpAddr::V6(prefix));
+		assert_ne!(key(IpAddr::V6(other)), IpAddr::V6(prefix));
+		// IPv4 addresses, mapped or not, are limited one by one
+		let v4 = Ipv4Addr::new(192, 0, 2, 1);
+		assert_eq!(key(IpAddr::V4(v4)), IpAddr::V4(v4));
+		assert_eq!(key(IpAddr::V6(v4.to_ipv6_mapped())), IpAddr::V4(v4));
+
+		// So a host rotating through its /64 shares one allowance
+		let limiter = SubmissionLimiter::new(60, 1);
+		let now = Instant::now();
+		assert!(limiter.allow_at(IpAddr::V6(host), now));
+		assert!(!limiter.allow_at(IpAddr::V6(neighbour), now));
+		assert!(limiter.allow_at(IpAddr::V6(other), now));
+	}
+}
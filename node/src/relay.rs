@@ -0,0 +1,310 @@
+//! Peer scoring of transaction relay, tuned for UTXO spam.
+//!
+//! Substrate's transaction protocol lowers the reputation of a peer relaying a transaction the
+//! pool rejects, but knows nothing of why. A transaction with missing inputs is not rejected: it
+//! waits in the pool's future queue, and the peer relaying it is rewarded as for a good one. And
+//! the penalty of a bad signature is too small for any rate of them to get a peer banned.
+//!
+//! [`RelayPool`] is the pool handed to the network, so it only sees the transactions peers relay.
+//! It counts the outcome of each import in the metrics, and reports a transaction still waiting
+//! for its inputs shortly after its import as rejected, so the peers relaying it are penalized as
+//! for an invalid one. Peers only relay ready transactions, parents first, so honest ones rarely
+//! send such a transaction; it stays pooled all the same until its inputs appear.
+//!
+//! [`run_banning`] bans the peers whose reputation falls below what `--relay-ban-after` rejected
+//! transactions cost. Reputation decays back towards zero by about 2% a second, and other
+//! misbehaviour lowers it too: the threshold is reached by a burst of that many rejected
+//! transactions, or by one a second sustained for every 50 of the setting.
+
+use std::{collections::HashMap, pin::Pin, sync::Arc, time::Duration};
+
+use codec::Encode;
+use futures::{Future, FutureExt};
+use sc_network::{service::traits::NetworkService, NetworkPeers, ReputationChange};
+use sc_network_sync::SyncingService;
+use sc_transaction_pool_api::{
+	error::{Error as PoolError, IntoPoolError},
+	ImportNotificationStream, InPoolTransaction, PoolFuture, PoolStatus, ReadyTransactions,
+	TransactionFor, TransactionPool, TransactionSource, TransactionStatusStreamFor, TxHash,
+};
+use sp_core::H256;
+use sp_runtime::{traits::NumberFor, transaction_validity::InvalidTransaction};
+use substrate_prometheus_endpoint::{
+	register, Counter, CounterVec, Opts, PrometheusError, Registry, U64,
+};
+use tumuchain_runtime::{opaque::Block, Runtime};
+
+/// Reputation a peer loses for each transaction it relays that the pool rejects, as set by
+/// Substrate's transaction protocol.
+const BAD_TRANSACTION_COST: i32 = 1 << 12;
+
+/// How long a relayed transaction may wait for its inputs, e.g. for a parent relayed along with
+/// it to be validated, before its peers are penalized for it.
+const ORPHAN_GRACE: Duration = Duration::from_secs(2);
+
+/// Time between two checks of the reputations of the connected peers.
+const BAN_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How the import of a relayed transaction went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+	Accepted,
+	AlreadyImported,
+	MissingInputs,
+	BadSignature,
+	Invalid,
+	Rejected,
+}
+
+impl Outcome {
+	/// The outcome of an import the pool failed with `error`.
+	fn of(error: &PoolError) -> Self {
+		let signature_errors = [
+			utxo::Error::<Runtime>::InvalidSignature,
+			utxo::Error::<Runtime>::MissingSignature,
+			utxo::Error::<Runtime>::NonCanonicalSignature,
+			utxo::Error::<Runtime>::SignatureWithWitness,
+			utxo::Error::<Runtime>::RedundantSignature,
+		]
+		.map(|error| error.encode()[0]);
+		match error {
+			PoolError::AlreadyImported(_) => Self::AlreadyImported,
+			PoolError::InvalidTransaction(InvalidTransaction::BadProof) => Self::BadSignature,
+			PoolError::InvalidTransaction(InvalidTransaction::Custom(code))
+				if signature_errors.contains(code) =>
+				Self::BadSignature,
+			PoolError::InvalidTransaction(_) | PoolError::UnknownTransaction(_) => Self::Invalid,
+			// Pool limits, bans and the like, which are no fault of the peer
+			_ => Self::Rejected,
+		}
+	}
+
+	fn label(self) -> &'static str {
+		match self {
+			Self::Accepted => "accepted",
+			Self::AlreadyImported => "already_imported",
+			Self::MissingInputs => "missing_inputs",
+			Self::BadSignature => "bad_signature",
+			Self::Invalid => "invalid",
+			Self::Rejected => "rejected",
+		}
+	}
+}
+
+/// Prometheus metrics of transaction relay.
+#[derive(Clone)]
+pub struct RelayMetrics {
+	transactions: CounterVec<U64>,
+	banned_peers: Counter<U64>,
+}
+
+impl RelayMetrics {
+	/// Registers the metrics with `registry`.
+	pub fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			transactions: register(
+				CounterVec::new(
+					Opts::new(
+						"tumuchain_relay_transactions_total",
+						"Transactions relayed by peers, by outcome of their import",
+					),
+					&["outcome"],
+				)?,
+				registry,
+			)?,
+			banned_peers: register(
+				Counter::new(
+					"tumuchain_relay_banned_peers_total",
+					"Peers banned for relaying rejected transactions",
+				)?,
+				registry,
+			)?,
+		})
+	}
+}
+
+/// The transaction pool as the network sees it: scores the imports of relayed transactions and
+/// passes everything else through.
+pub struct RelayPool<P> {
+	pool: Arc<P>,
+	metrics: Option<RelayMetrics>,
+}
+
+impl<P> RelayPool<P> {
+	/// Wrap `pool`, counting the outcomes of imports in `metrics`, if any.
+	pub fn new(pool: Arc<P>, metrics: Option<RelayMetrics>) -> Self {
+		Self { pool, metrics }
+	}
+}
+
+impl<P> TransactionPool for RelayPool<P>
+where
+	P: TransactionPool<Block = Block, Hash = H256> + 'static,
+{
+	type Block = Block;
+	type Hash = H256;
+	type InPoolTransaction = P::InPoolTransaction;
+	type Error = P::Error;
+
+	fn submit_at(
+		&self,
+		at: H256,
+		source: TransactionSource,
+		xts: Vec<TransactionFor<Self>>,
+	) -> PoolFuture<Vec<Result<H256, Self::Error>>, Self::Error> {
+		self.pool.submit_at(at, source, xts)
+	}
+
+	/// The network imports relayed transactions one by one, through here.
+	fn submit_one(
+		&self,
+		at: H256,
+		source: TransactionSource,
+		xt: TransactionFor<Self>,
+	) -> PoolFuture<H256, Self::Error> {
+		let pool = self.pool.clone();
+		let metrics = self.metrics.clone();
+		async move {
+			let waiting = |hash: &H256| pool.futures().iter().any(|tx| tx.hash() == hash);
+			let (outcome, result) = match pool.submit_one(at, source, xt).await {
+				Ok(hash) => {
+					let mut orphan = waiting(&hash);
+					if orphan {
+						tokio::time::sleep(ORPHAN_GRACE).await;
+						orphan = waiting(&hash);
+					}
+					if orphan {
+						let missing = utxo::Error::<Runtime>::MissingInputUtxo.encode()[0];
+						let error =
+							PoolError::InvalidTransaction(InvalidTransaction::Custom(missing));
+						(Outcome::MissingInputs, Err(error.into()))
+					} else {
+						(Outcome::Accepted, Ok(hash))
+					}
+				},
+				Err(e) => match e.into_pool_error() {
+					Ok(error) => (Outcome::of(&error), Err(error.into())),
+					Err(e) => (Outcome::Rejected, Err(e)),
+				},
+			};
+			if let Some(metrics) = metrics {
+				metrics.transactions.with_label_values(&[outcome.label()]).inc();
+			}
+			result
+		}
+		.boxed()
+	}
+
+	fn submit_and_watch(
+		&self,
+		at: H256,
+		source: TransactionSource,
+		xt: TransactionFor<Self>,
+	) -> PoolFuture<Pin<Box<TransactionStatusStreamFor<Self>>>, Self::Error> {
+		self.pool.submit_and_watch(at, source, xt)
+	}
+
+	fn ready_at(
+		&self,
+		at: NumberFor<Block>,
+	) -> Pin<
+		Box<
+			dyn Future<
+					Output = Box<dyn ReadyTransactions<Item = Arc<Self::InPoolTransaction>> + Send>,
+				> + Send,
+		>,
+	> {
+		self.pool.ready_at(at)
+	}
+
+	fn ready(&self) -> Box<dyn ReadyTransactions<Item = Arc<Self::InPoolTransaction>> + Send> {
+		self.pool.ready()
+	}
+
+	fn remove_invalid(&self, hashes: &[H256]) -> Vec<Arc<Self::InPoolTransaction>> {
+		self.pool.remove_invalid(hashes)
+	}
+
+	fn futures(&self) -> Vec<Self::InPoolTransaction> {
+		self.pool.futures()
+	}
+
+	fn status(&self) -> PoolStatus {
+		self.pool.status()
+	}
+
+	fn import_notification_stream(&self) -> ImportNotificationStream<H256> {
+		self.pool.import_notification_stream()
+	}
+
+	fn on_broadcasted(&self, propagations: HashMap<TxHash<Self>, Vec<String>>) {
+		self.pool.on_broadcasted(propagations)
+	}
+
+	fn hash_of(&self, xt: &TransactionFor<Self>) -> H256 {
+		self.pool.hash_of(xt)
+	}
+
+	fn ready_transaction(&self, hash: &H256) -> Option<Arc<Self::InPoolTransaction>> {
+		self.pool.ready_transaction(hash)
+	}
+}
+
+/// Bans the connected peers whose reputation fell below the cost of `ban_after` rejected
+/// transactions, until the syncing service stops. `0` disables banning.
+pub async fn run_banning(
+	network: Arc<dyn NetworkService>,
+	sync: Arc<SyncingService<Block>>,
+	ban_after: u32,
+	metrics: Option<RelayMetrics>,
+) {
+	if ban_after == 0 {
+		return
+	}
+	let threshold =
+		i32::try_from(ban_after).unwrap_or(i32::MAX).saturating_mul(-BAD_TRANSACTION_COST);
+	let mut interval = tokio::time::interval(BAN_CHECK_INTERVAL);
+	loop {
+		interval.tick().await;
+		let Ok(peers) = sync.peers_info().await else { return };
+		for (peer, _) in peers {
+			let reputation = network.peer_reputation(&peer);
+			if reputation >= threshold {
+				continue
+			}
+			log::info!(
+				target: "relay",
+				"Banning peer {peer} for relaying rejected transactions (reputation {reputation})",
+			);
+			network.report_peer(
+				peer,
+				ReputationChange::new_fatal("Relayed too many rejected transactions"),
+			);
+			if let Some(metrics) = &metrics {
+				metrics.banned_peers.inc();
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn relaying_banned_transactions_is_no_fault_of_the_peer() {
+		// Transactions the pool evicted are banned, and peers may still relay them
+		assert_eq!(Outcome::of(&PoolError::TemporarilyBanned), Outcome::Rejected);
+		let known = PoolError::AlreadyImported(Box::new(()));
+		assert_eq!(Outcome::of(&known), Outcome::AlreadyImported);
+
+		let bad_signature = utxo::Error::<Runtime>::InvalidSignature.encode()[0];
+		let invalid = PoolError::InvalidTransaction;
+		assert_eq!(Outcome::of(&invalid(InvalidTransaction::BadProof)), Outcome::BadSignature);
+		assert_eq!(
+			Outcome::of(&invalid(InvalidTransaction::Custom(bad_signature))),
+			Outcome::BadSignature
+		);
+		assert_eq!(Outcome::of(&invalid(InvalidTransaction::Payment)), Outcome::Invalid);
+	}
+}
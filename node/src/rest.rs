@@ -0,0 +1,205 @@
+//! Read-only REST gateway for web explorers that cannot use JSON-RPC.
+//!
+//! Served when the node is started with `--rest-port`:
+//!
+//! - `GET /block/{n}`: the header of block `n` and the txids it includes
+//! - `GET /tx/{id}`: a transaction in a finalized block and where it is, from the index kept with
+//!   `--spent-index`
+//! - `GET /address/{pubkey}/utxos`: the unspent outputs paying `pubkey` at the best block
+//!
+//! Hashes and pubkeys are 0x-prefixed hex. Errors are a JSON object with an `error` message.
+
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+	extract::{Path, State},
+	http::StatusCode,
+	response::{IntoResponse, Response},
+	routing::get,
+	Json, Router,
+};
+use codec::{Decode, Encode};
+use jsonrpsee::types::error::ErrorObjectOwned;
+use sc_client_api::BlockBackend;
+use serde::Serialize;
+use sp_blockchain::HeaderBackend;
+use sp_core::H256;
+use sp_runtime::traits::Header as _;
+use tumuchain_runtime::{opaque::Block, BlockNumber, UncheckedExtrinsic};
+use utxo::{Transaction, TransactionOutput};
+
+use crate::{
+	import::spends,
+	rpc::utxo::{spent_transaction, Utxo, UtxoApiServer, DECODE_ERROR, PRUNED_ERROR},
+	service::{FullBackend, FullClient},
+	spent_index::{self, TxLocation},
+};
+
+/// A block as served by `/block/{n}`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BlockInfo {
+	hash: H256,
+	number: BlockNumber,
+	parent_hash: H256,
+	state_root: H256,
+	extrinsics_root: H256,
+	/// The transactions spending outputs, in order
+	txids: Vec<H256>,
+}
+
+/// A transaction as served by `/tx/{id}`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TxInfo {
+	location: TxLocation,
+	transaction: Transaction,
+}
+
+/// An output as served by `/address/{pubkey}/utxos`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UnspentOutput {
+	outpoint: H256,
+	output: TransactionOutput,
+}
+
+#[derive(Clone)]
+struct Gateway {
+	client: Arc<FullClient>,
+	spent_index: bool,
+}
+
+/// Serve the REST gateway on `addr` until the node shuts down. `/tx/{id}` is only answered if
+/// the node keeps the spent index.
+pub async fn run(addr: SocketAddr, client: Arc<FullClient>, spent_index: bool) {
+	let app = Router::new()
+		.route("/block/:number", get(block))
+		.route("/tx/:txid", get(transaction))
+		.route("/address/:pubkey/utxos", get(unspent_outputs))
+		.with_state(Gateway { client, spent_index });
+
+	let listener = match tokio::net::TcpListener::bind(addr).await {
+		Ok(listener) => listener,
+		Err(e) => {
+			log::error!(target: "rest", "Cannot listen for REST on {addr}: {e}");
+			return
+		},
+	};
+	log::info!(target: "rest", "REST gateway listening on {addr}");
+	if let Err(e) = axum::serve(listener, app).await {
+		log::error!(target: "rest", "REST gateway on {addr} failed: {e}");
+	}
+}
+
+async fn block(
+	State(gateway): State<Gateway>,
+	Path(number): Path<BlockNumber>,
+) -> Result<Json<BlockInfo>, ApiError> {
+	let client = &gateway.client;
+	let hash = client
+		.hash(number)
+		.map_err(ApiError::internal)?
+		.ok_or_else(|| ApiError::not_found(format!("no block #{number}")))?;
+	let header = client
+		.header(hash)
+		.map_err(ApiError::internal)?
+		.ok_or_else(|| ApiError::not_found(format!("no header for block #{number}")))?;
+	let body = client
+		.block_body(hash)
+		.map_err(ApiError::internal)?
+		.ok_or_else(|| ApiError::pruned(format!("the body of block #{number} was pruned")))?;
+
+	Ok(Json(BlockInfo {
+		hash,
+		number,
+		parent_hash: *header.parent_hash(),
+		state_root: *header.state_root(),
+		extrinsics_root: *header.extrinsics_root(),
+		txids: spends(&body).iter().map(Transaction::txid).collect(),
+	}))
+}
+
+async fn transaction(
+	State(gateway): State<Gateway>,
+	Path(txid): Path<H256>,
+) -> Result<Json<TxInfo>, ApiError> {
+	if !gateway.spent_index {
+		return Err(ApiError(
+			StatusCode::NOT_IMPLEMENTED,
+			"this node does not index transactions, start it with `--spent-index`".into(),
+		))
+	}
+	let client = &gateway.client;
+	let location = spent_index::tx_location(&**client, &txid)
+		.map_err(ApiError::internal)?
+		.ok_or_else(|| ApiError::not_found(format!("no finalized transaction {txid:?}")))?;
+	let body = client
+		.block_body(location.block_hash)
+		.map_err(ApiError::internal)?
+		.ok_or_else(|| {
+			ApiError::pruned(format!("the body of block #{} was pruned", location.block_number))
+		})?;
+
+	let transaction = body
+		.get(location.index as usize)
+		.and_then(|extrinsic| UncheckedExtrinsic::decode(&mut &extrinsic.encode()[..]).ok())
+		.and_then(|extrinsic| spent_transaction(extrinsic.function))
+		.filter(|transaction| transaction.txid() == txid)
+		.ok_or_else(|| ApiError::internal("the index does not match the block body"))?;
+	Ok(Json(TxInfo { location, transaction }))
+}
+
+async fn unspent_outputs(
+	State(gateway): State<Gateway>,
+	Path(pubkey): Path<H256>,
+) -> Result<Json<Vec<UnspentOutput>>, ApiError> {
+	let mut outputs = Utxo::<FullClient, Block, FullBackend>::new(gateway.client, false)
+		.unspent_outputs(pubkey, None)
+		.map_err(ApiError::from)?;
+	outputs.sort_by_key(|(outpoint, _)| *outpoint);
+
+	Ok(Json(
+		outputs
+			.into_iter()
+			.map(|(outpoint, output)| UnspentOutput { outpoint, output })
+			.collect(),
+	))
+}
+
+/// An error answered with its status code and a JSON message.
+struct ApiError(StatusCode, String);
+
+impl ApiError {
+	fn internal(error: impl std::fmt::Display) -> Self {
+		Self(StatusCode::INTERNAL_SERVER_ERROR, error.to_string())
+	}
+
+	fn not_found(message: String) -> Self {
+		Self(StatusCode::NOT_FOUND, message)
+	}
+
+	fn pruned(message: String) -> Self {
+		Self(StatusCode::GONE, message)
+	}
+}
+
+impl From<ErrorObjectOwned> for ApiError {
+	fn from(error: ErrorObjectOwned) -> Self {
+		let message = match error.data() {
+			Some(data) => format!("{}: {}", error.message(), data.get()),
+			None => error.message().to_string(),
+		};
+		match error.code() {
+			DECODE_ERROR => Self(StatusCode::BAD_REQUEST, message),
+			PRUNED_ERROR => Self::pruned(message),
+			_ => Self::internal(message),
+		}
+	}
+}
+
+impl IntoResponse for ApiError {
+	fn into_response(self) -> Response {
+		(self.0, Json(serde_json::json!({ "error": self.1 }))).into_response()
+	}
+}
@@ -0,0 +1,335 @@
+//! The Rosetta Construction API.
+//!
+//! Transactions under construction are passed around as a hex [`PartialTransaction`]: the
+//! transaction along with the outputs its inputs spend. Like a PSBT, it lets signers and parsers
+//! check amounts and owners without asking a node. The flow:
+//!
+//! 1. `/construction/preprocess` collects the outpoints spent by the `INPUT` operations.
+//! 2. `/construction/metadata`, the only step reading the chain, looks up those outputs.
+//! 3. `/construction/payloads` builds the unsigned transaction, with one payload per key owning
+//!    spent outputs. Every input signs the same payload.
+//! 4. `/construction/combine` checks the signatures and fills in the sigscripts.
+//! 5. `/construction/submit` submits the transaction to the node's pool.
+//!
+//! Outputs locked to a script cannot be spent this way, as no key signs for them.
+
+use std::{collections::HashSet, net::SocketAddr};
+
+use axum::{
+	extract::{ConnectInfo, State},
+	Json,
+};
+use codec::{Decode, Encode};
+use sc_transaction_pool_api::{TransactionPool, TransactionSource};
+use serde::{Deserialize, Serialize};
+use sp_blockchain::HeaderBackend;
+use sp_core::{
+	sr25519::{Pair, Public, Signature},
+	Pair as _, H256, H512,
+};
+use sp_runtime::{BoundedVec, OpaqueExtrinsic};
+use tumuchain_runtime::{RuntimeCall, UncheckedExtrinsic};
+use utxo::{Transaction, TransactionInput, TransactionOutput, Value, TRANSACTION_VERSION};
+
+use super::{
+	account, hex_bytes, node_error, operations, parse_hash, parse_hex_bytes,
+	types::{
+		ConstructionCombineRequest, ConstructionCombineResponse, ConstructionDeriveRequest,
+		ConstructionDeriveResponse, ConstructionMetadataRequest, ConstructionMetadataResponse,
+		ConstructionParseRequest, ConstructionParseResponse, ConstructionPayloadsRequest,
+		ConstructionPayloadsResponse, ConstructionPreprocessRequest,
+		ConstructionPreprocessResponse, ConstructionTransactionRequest, Operation, SigningPayload,
+		TransactionIdentifier, TransactionIdentifierResponse,
+	},
+	ApiError, ApiResult, ErrorKind, Rosetta, INPUT, OUTPUT, SR25519,
+};
+
+/// A transaction under construction, with the outputs spent by its inputs, in order.
+#[derive(Encode, Decode)]
+struct PartialTransaction {
+	transaction: Transaction,
+	spent: Vec<TransactionOutput>,
+}
+
+/// Options returned by preprocess, for metadata.
+#[derive(Serialize, Deserialize)]
+struct Options {
+	outpoints: Vec<H256>,
+}
+
+/// Metadata returned by metadata, for payloads.
+#[derive(Serialize, Deserialize)]
+struct Metadata {
+	/// The SCALE-encoded outputs at the outpoints of the options, in hex
+	spent: Vec<String>,
+}
+
+pub(super) async fn derive(
+	State(rosetta): State<Rosetta>,
+	Json(request): Json<ConstructionDeriveRequest>,
+) -> ApiResult<ConstructionDeriveResponse> {
+	rosetta.check_network(&request.network_identifier)?;
+	if request.public_key.curve_type != SR25519 {
+		return Err(ErrorKind::InvalidRequest.with("keys are sr25519"))
+	}
+	let pubkey = parse_hex_bytes(&request.public_key.hex_bytes)?;
+	if pubkey.len() != 32 {
+		return Err(ErrorKind::InvalidRequest.with("sr25519 public keys are 32 bytes"))
+	}
+	Ok(Json(ConstructionDeriveResponse { account_identifier: account(H256::from_slice(&pubkey)) }))
+}
+
+pub(super) async fn preprocess(
+	State(rosetta): State<Rosetta>,
+	Json(request): Json<ConstructionPreprocessRequest>,
+) -> ApiResult<ConstructionPreprocessResponse> {
+	rosetta.check_network(&request.network_identifier)?;
+	let (inputs, _) = parse_operations(&request.operations)?;
+	let outpoints = inputs.into_iter().map(|(outpoint, _)| outpoint).collect();
+	Ok(Json(ConstructionPreprocessResponse {
+		options: serde_json::to_value(Options { outpoints }).map_err(node_error)?,
+	}))
+}
+
+pub(super) async fn metadata(
+	State(rosetta): State<Rosetta>,
+	Json(request): Json<ConstructionMetadataRequest>,
+) -> ApiResult<ConstructionMetadataResponse> {
+	rosetta.check_network(&request.network_identifier)?;
+	let options: Options = serde_json::from_value(request.options)
+		.map_err(|e| ErrorKind::InvalidRequest.with(format!("bad options: {e}")))?;
+
+	let best = rosetta.client.info().best_hash;
+	let spent = options
+		.outpoints
+		.iter()
+		.map(|outpoint| match rosetta.output(best, outpoint)? {
+			Some(output) => Ok(hex_bytes(&output.encode())),
+			None => Err(ErrorKind::InvalidRequest.with(format!("{outpoint:?} is not unspent"))),
+		})
+		.collect::<Result<_, _>>()?;
+	Ok(Json(ConstructionMetadataResponse {
+		metadata: serde_json::to_value(Metadata { spent }).map_err(node_error)?,
+	}))
+}
+
+pub(super) async fn payloads(
+	State(rosetta): State<Rosetta>,
+	Json(request): Json<ConstructionPayloadsRequest>,
+) -> ApiResult<ConstructionPayloadsResponse> {
+	rosetta.check_network(&request.network_identifier)?;
+	let (inputs, outputs) = parse_operations(&request.operations)?;
+	let metadata: Metadata = serde_json::from_value(request.metadata)
+		.map_err(|e| ErrorKind::InvalidRequest.with(format!("bad metadata: {e}")))?;
+	if metadata.spent.len() != inputs.len() {
+		return Err(ErrorKind::InvalidRequest.with("metadata does not match the inputs"))
+	}
+
+	let mut spent = Vec::with_capacity(inputs.len());
+	for ((outpoint, value), output) in inputs.iter().zip(&metadata.spent) {
+		let output = TransactionOutput::decode(&mut &parse_hex_bytes(output)?[..])
+			.map_err(|e| ErrorKind::InvalidRequest.with(format!("bad metadata: {e}")))?;
+		if value.is_some_and(|value| value != output.value) {
+			let mismatch = format!("the amount spent from {outpoint:?} is {}", output.value);
+			return Err(ErrorKind::InvalidRequest.with(mismatch))
+		}
+		spent.push(output);
+	}
+
+	let transaction = Transaction {
+		version: TRANSACTION_VERSION,
+		inputs: bounded(
+			inputs
+				.iter()
+				.map(|(outpoint, _)| TransactionInput {
+					outpoint: *outpoint,
+					sigscript: None,
+					witness: None,
+				})
+				.collect(),
+		)?,
+		outputs: bounded(outputs)?,
+	};
+	let payload = hex_bytes(&transaction.signing_payload());
+	let payloads = signers(spent.iter().map(|output| output.pubkey))
+		.map(|pubkey| SigningPayload {
+			account_identifier: account(pubkey),
+			hex_bytes: payload.clone(),
+			signature_type: SR25519.into(),
+		})
+		.collect();
+
+	Ok(Json(ConstructionPayloadsResponse {
+		unsigned_transaction: hex_bytes(&PartialTransaction { transaction, spent }.encode()),
+		payloads,
+	}))
+}
+
+pub(super) async fn combine(
+	State(rosetta): State<Rosetta>,
+	Json(request): Json<ConstructionCombineRequest>,
+) -> ApiResult<ConstructionCombineResponse> {
+	rosetta.check_network(&request.network_identifier)?;
+	let mut partial = decode_partial(&request.unsigned_transaction)?;
+	let payload = partial.transaction.signing_payload();
+
+	for signature in &request.signatures {
+		let pubkey = parse_hex_bytes(&signature.public_key.hex_bytes)?;
+		let sigscript = parse_hex_bytes(&signature.hex_bytes)?;
+		let (Ok(pubkey), Ok(sigscript)) =
+			(<[u8; 32]>::try_from(pubkey), <[u8; 64]>::try_from(sigscript))
+		else {
+			let sizes = "sr25519 keys and signatures are 32 and 64 bytes";
+			return Err(ErrorKind::InvalidRequest.with(sizes))
+		};
+		if !Pair::verify(&Signature::from_raw(sigscript), &payload, &Public::from_raw(pubkey)) {
+			let bad = format!("bad signature by 0x{}", hex_bytes(&pubkey));
+			return Err(ErrorKind::InvalidRequest.with(bad))
+		}
+
+		// Only the first input spending outputs of the key carries the signature
+		let pubkey = H256(pubkey);
+		let mut inputs = partial.transaction.inputs.iter_mut().zip(&partial.spent);
+		if let Some((input, _)) = inputs.find(|(_, spent)| spent.pubkey == pubkey) {
+			input.sigscript = Some(H512(sigscript));
+		}
+	}
+	let mut signed = HashSet::new();
+	for (input, spent) in partial.transaction.inputs.iter().zip(&partial.spent) {
+		if signed.insert(spent.pubkey) && input.sigscript.is_none() {
+			let missing = format!("no signature for the input spending {:?}", input.outpoint);
+			return Err(ErrorKind::InvalidRequest.with(missing))
+		}
+	}
+
+	Ok(Json(ConstructionCombineResponse { signed_transaction: hex_bytes(&partial.encode()) }))
+}
+
+pub(super) async fn parse(
+	State(rosetta): State<Rosetta>,
+	Json(request): Json<ConstructionParseRequest>,
+) -> ApiResult<ConstructionParseResponse> {
+	rosetta.check_network(&request.network_identifier)?;
+	let PartialTransaction { transaction, spent } = decode_partial(&request.transaction)?;
+
+	let account_identifier_signers = match request.signed {
+		true => signers(
+			transaction
+				.inputs
+				.iter()
+				.zip(&spent)
+				.filter(|(input, _)| input.sigscript.is_some())
+				.map(|(_, output)| output.pubkey),
+		)
+		.map(account)
+		.collect(),
+		false => Vec::new(),
+	};
+	let spent: Vec<_> = spent.into_iter().map(Some).collect();
+	Ok(Json(ConstructionParseResponse {
+		operations: operations(&transaction, &spent, None),
+		account_identifier_signers,
+	}))
+}
+
+pub(super) async fn hash(
+	State(rosetta): State<Rosetta>,
+	Json(request): Json<ConstructionTransactionRequest>,
+) -> ApiResult<TransactionIdentifierResponse> {
+	rosetta.check_network(&request.network_identifier)?;
+	let partial = decode_partial(&request.signed_transaction)?;
+	Ok(Json(TransactionIdentifierResponse {
+		transaction_identifier: TransactionIdentifier {
+			hash: format!("{:?}", partial.transaction.txid()),
+		},
+	}))
+}
+
+pub(super) async fn submit(
+	State(rosetta): State<Rosetta>,
+	ConnectInfo(client): ConnectInfo<SocketAddr>,
+	Json(request): Json<ConstructionTransactionRequest>,
+) -> ApiResult<TransactionIdentifierResponse> {
+	rosetta.check_network(&request.network_identifier)?;
+	if !rosetta.submissions.allow(client.ip()) {
+		return Err(ErrorKind::RateLimited.with("too many transactions, try again later"))
+	}
+	let PartialTransaction { transaction, .. } = decode_partial(&request.signed_transaction)?;
+	let txid = transaction.txid();
+	let extrinsic =
+		UncheckedExtrinsic::new_unsigned(RuntimeCall::Utxo(utxo::Call::spend { transaction }));
+	let extrinsic = OpaqueExtrinsic::decode(&mut &extrinsic.encode()[..]).map_err(node_error)?;
+
+	rosetta
+		.pool
+		.submit_one(rosetta.client.info().best_hash, TransactionSource::External, extrinsic)
+		.await
+		.map_err(|e| ErrorKind::TransactionRejected.with(e))?;
+	Ok(Json(TransactionIdentifierResponse {
+		transaction_identifier: TransactionIdentifier { hash: format!("{txid:?}") },
+	}))
+}
+
+/// The inputs, with the amount spent if given, and the outputs described by `operations`.
+#[allow(clippy::type_complexity)]
+fn parse_operations(
+	operations: &[Operation],
+) -> Result<(Vec<(H256, Option<Value>)>, Vec<TransactionOutput>), ApiError> {
+	let mut inputs = Vec::new();
+	let mut outputs = Vec::new();
+	for operation in operations {
+		let amount = operation
+			.amount
+			.as_ref()
+			.map(|amount| amount.value.parse::<i128>())
+			.transpose()
+			.map_err(|e| ErrorKind::InvalidRequest.with(format!("bad amount: {e}")))?;
+
+		match operation.kind.as_str() {
+			INPUT => {
+				let coin = operation.coin_change.as_ref().ok_or_else(|| {
+					ErrorKind::InvalidRequest.with("INPUT operations need a coin_change")
+				})?;
+				let value = match amount {
+					Some(amount) if amount < 0 => Some(amount.unsigned_abs()),
+					Some(_) => return Err(ErrorKind::InvalidRequest.with("inputs are negative")),
+					None => None,
+				};
+				inputs.push((parse_hash(&coin.coin_identifier.identifier)?, value));
+			},
+			OUTPUT => {
+				let owner = operation.account.as_ref().ok_or_else(|| {
+					ErrorKind::InvalidRequest.with("OUTPUT operations need an account")
+				})?;
+				let value = match amount {
+					Some(amount) if amount > 0 => amount.unsigned_abs(),
+					_ => return Err(ErrorKind::InvalidRequest.with("outputs are positive")),
+				};
+				outputs.push(TransactionOutput {
+					value,
+					pubkey: parse_hash(&owner.address)?,
+					memo: None,
+				});
+			},
+			kind => return Err(ErrorKind::InvalidRequest.with(format!("unknown operation {kind}"))),
+		}
+	}
+	Ok((inputs, outputs))
+}
+
+/// The distinct keys among `pubkeys`, in order of first appearance.
+fn signers(pubkeys: impl Iterator<Item = H256>) -> impl Iterator<Item = H256> {
+	let mut seen = std::collections::BTreeSet::new();
+	pubkeys.filter(move |pubkey| seen.insert(*pubkey))
+}
+
+fn bounded<T, S: sp_core::Get<u32>>(items: Vec<T>) -> Result<BoundedVec<T, S>, ApiError> {
+	items.try_into().map_err(|_| {
+		ErrorKind::InvalidRequest.with(format!("at most {} inputs and outputs", S::get()))
+	})
+}
+
+fn decode_partial(hex: &str) -> Result<PartialTransaction, ApiError> {
+	PartialTransaction::decode(&mut &parse_hex_bytes(hex)?[..])
+		.map_err(|e| ErrorKind::InvalidRequest.with(format!("bad transaction: {e}")))
+}
@@ -0,0 +1,265 @@
+//! The Rosetta Data API: networks, balances, coins, blocks and the mempool.
+
+use std::collections::HashMap;
+
+use axum::{extract::State, Json};
+use codec::{Decode, Encode};
+use sc_client_api::BlockBackend;
+use sc_transaction_pool_api::{InPoolTransaction, TransactionPool};
+use sp_blockchain::HeaderBackend;
+use sp_core::H256;
+use sp_runtime::traits::Header as _;
+use tumuchain_runtime::{opaque::Block, BlockNumber, UncheckedExtrinsic};
+use utxo::{Transaction, TransactionOutput, Value};
+
+use super::{
+	amount, block_identifier, created, node_error, operations, parse_hash,
+	types::{
+		AccountBalanceRequest, AccountBalanceResponse, AccountCoinsRequest, AccountCoinsResponse,
+		Allow, BlockRequest, BlockResponse, BlockTransactionRequest, BlockTransactionResponse,
+		Coin, CoinIdentifier, MempoolResponse, NetworkListResponse, NetworkOptionsResponse,
+		NetworkRequest, NetworkStatusResponse, OperationStatus, PartialBlockIdentifier,
+		TransactionIdentifier, Version,
+	},
+	types, ApiError, ApiResult, ErrorKind, Rosetta, INPUT, OUTPUT, ROSETTA_VERSION, SUCCESS,
+};
+use crate::{
+	rpc::utxo::{spent_transaction, Utxo, UtxoApiServer, PRUNED_ERROR},
+	service::{FullBackend, FullClient},
+	spent_index,
+};
+
+pub(super) async fn network_list(
+	State(rosetta): State<Rosetta>,
+	Json(_): Json<serde_json::Value>,
+) -> ApiResult<NetworkListResponse> {
+	Ok(Json(NetworkListResponse { network_identifiers: vec![rosetta.network] }))
+}
+
+pub(super) async fn network_options(
+	State(rosetta): State<Rosetta>,
+	Json(request): Json<NetworkRequest>,
+) -> ApiResult<NetworkOptionsResponse> {
+	rosetta.check_network(&request.network_identifier)?;
+	Ok(Json(NetworkOptionsResponse {
+		version: Version {
+			rosetta_version: ROSETTA_VERSION.into(),
+			node_version: env!("CARGO_PKG_VERSION").into(),
+		},
+		allow: Allow {
+			operation_statuses: vec![OperationStatus { status: SUCCESS.into(), successful: true }],
+			operation_types: vec![INPUT.into(), OUTPUT.into()],
+			errors: ErrorKind::ALL.into_iter().map(ErrorKind::model).collect(),
+			historical_balance_lookup: true,
+			mempool_coins: false,
+		},
+	}))
+}
+
+pub(super) async fn network_status(
+	State(rosetta): State<Rosetta>,
+	Json(request): Json<NetworkRequest>,
+) -> ApiResult<NetworkStatusResponse> {
+	rosetta.check_network(&request.network_identifier)?;
+	let info = rosetta.client.info();
+	Ok(Json(NetworkStatusResponse {
+		current_block_identifier: block_identifier(info.best_hash, info.best_number),
+		current_block_timestamp: rosetta.timestamp(info.best_hash)?,
+		genesis_block_identifier: block_identifier(info.genesis_hash, 0),
+		peers: Vec::new(),
+	}))
+}
+
+pub(super) async fn account_balance(
+	State(rosetta): State<Rosetta>,
+	Json(request): Json<AccountBalanceRequest>,
+) -> ApiResult<AccountBalanceResponse> {
+	rosetta.check_network(&request.network_identifier)?;
+	let pubkey = parse_hash(&request.account_identifier.address)?;
+	let (at, number) = rosetta.resolve(&request.block_identifier.unwrap_or_default())?;
+
+	let balance = rosetta
+		.unspent_outputs(pubkey, at)?
+		.iter()
+		.fold(0, |sum: Value, (_, output)| sum.saturating_add(output.value));
+	Ok(Json(AccountBalanceResponse {
+		block_identifier: block_identifier(at, number),
+		balances: vec![amount(balance, false)],
+	}))
+}
+
+pub(super) async fn account_coins(
+	State(rosetta): State<Rosetta>,
+	Json(request): Json<AccountCoinsRequest>,
+) -> ApiResult<AccountCoinsResponse> {
+	rosetta.check_network(&request.network_identifier)?;
+	let pubkey = parse_hash(&request.account_identifier.address)?;
+	let (at, number) = rosetta.resolve(&PartialBlockIdentifier::default())?;
+
+	let coins = rosetta
+		.unspent_outputs(pubkey, at)?
+		.into_iter()
+		.map(|(outpoint, output)| Coin {
+			coin_identifier: CoinIdentifier { identifier: format!("{outpoint:?}") },
+			amount: amount(output.value, false),
+		})
+		.collect();
+	Ok(Json(AccountCoinsResponse { block_identifier: block_identifier(at, number), coins }))
+}
+
+pub(super) async fn block(
+	State(rosetta): State<Rosetta>,
+	Json(request): Json<BlockRequest>,
+) -> ApiResult<BlockResponse> {
+	rosetta.check_network(&request.network_identifier)?;
+	let (hash, number) = rosetta.resolve(&request.block_identifier)?;
+	Ok(Json(BlockResponse { block: rosetta.block(hash, number)? }))
+}
+
+pub(super) async fn block_transaction(
+	State(rosetta): State<Rosetta>,
+	Json(request): Json<BlockTransactionRequest>,
+) -> ApiResult<BlockTransactionResponse> {
+	rosetta.check_network(&request.network_identifier)?;
+	let (hash, number) = rosetta.resolve(&PartialBlockIdentifier {
+		index: Some(request.block_identifier.index),
+		hash: Some(request.block_identifier.hash),
+	})?;
+
+	let transaction = rosetta
+		.block(hash, number)?
+		.transactions
+		.into_iter()
+		.find(|transaction| transaction.transaction_identifier == request.transaction_identifier)
+		.ok_or_else(|| ErrorKind::TransactionNotFound.with(format!("not in block {hash:?}")))?;
+	Ok(Json(BlockTransactionResponse { transaction }))
+}
+
+pub(super) async fn mempool(
+	State(rosetta): State<Rosetta>,
+	Json(request): Json<NetworkRequest>,
+) -> ApiResult<MempoolResponse> {
+	rosetta.check_network(&request.network_identifier)?;
+	let transaction_identifiers = rosetta
+		.pool
+		.ready()
+		.filter_map(|ready| {
+			let extrinsic = UncheckedExtrinsic::decode(&mut &ready.data().encode()[..]).ok()?;
+			spent_transaction(extrinsic.function)
+		})
+		.map(|transaction| TransactionIdentifier { hash: format!("{:?}", transaction.txid()) })
+		.collect();
+	Ok(Json(MempoolResponse { transaction_identifiers }))
+}
+
+impl Rosetta {
+	/// The unspent outputs paying `pubkey` as of block `at`, sorted by outpoint.
+	fn unspent_outputs(
+		&self,
+		pubkey: H256,
+		at: H256,
+	) -> Result<Vec<(H256, TransactionOutput)>, ApiError> {
+		let mut outputs = Utxo::<FullClient, Block, FullBackend>::new(self.client.clone(), false)
+			.unspent_outputs(pubkey, Some(at))
+			.map_err(|e| match e.code() {
+				PRUNED_ERROR => ErrorKind::Pruned.with(e.message()),
+				_ => node_error(e.message()),
+			})?;
+		outputs.sort_by_key(|(outpoint, _)| *outpoint);
+		Ok(outputs)
+	}
+
+	/// Block `hash` with the transactions changing the UTXO set, in order: the spends, then the
+	/// outputs created by the runtime.
+	fn block(&self, hash: H256, number: BlockNumber) -> Result<types::Block, ApiError> {
+		let header = self
+			.client
+			.header(hash)
+			.map_err(node_error)?
+			.ok_or_else(|| ErrorKind::BlockNotFound.with(format!("no block {hash:?}")))?;
+		let body = self.client.block_body(hash).map_err(node_error)?.ok_or_else(|| {
+			ErrorKind::Pruned.with(format!("the body of block {hash:?} was pruned"))
+		})?;
+		// The genesis block is its own parent
+		let parent = match number {
+			0 => block_identifier(hash, number),
+			_ => block_identifier(*header.parent_hash(), number - 1),
+		};
+
+		let mut transactions = Vec::new();
+		// Outputs created earlier in the block, which are not in the parent's UTXO set
+		let mut created_here = HashMap::new();
+		for extrinsic in &body {
+			let Some(transaction) = UncheckedExtrinsic::decode(&mut &extrinsic.encode()[..])
+				.ok()
+				.and_then(|extrinsic| spent_transaction(extrinsic.function))
+			else {
+				continue
+			};
+			let spent = transaction
+				.inputs
+				.iter()
+				.map(|input| match created_here.remove(&input.outpoint) {
+					Some(output) => Ok(Some(output)),
+					None => self.output(*header.parent_hash(), &input.outpoint),
+				})
+				.collect::<Result<Vec<_>, _>>()?;
+
+			created_here.extend(output_pairs(&transaction));
+			transactions.push(types::Transaction {
+				transaction_identifier: TransactionIdentifier {
+					hash: format!("{:?}", transaction.txid()),
+				},
+				operations: operations(&transaction, &spent, Some(SUCCESS)),
+			});
+		}
+		transactions.extend(self.runtime_outputs(hash, number)?);
+
+		Ok(types::Block {
+			block_identifier: block_identifier(hash, number),
+			parent_block_identifier: parent,
+			timestamp: self.timestamp(hash)?,
+			transactions,
+		})
+	}
+
+	/// The outputs created by the runtime in block `at` rather than by spends, as transactions
+	/// identified by the outpoint of their first output.
+	fn runtime_outputs(
+		&self,
+		at: H256,
+		number: BlockNumber,
+	) -> Result<Vec<types::Transaction>, ApiError> {
+		let groups = spent_index::runtime_outputs(&self.client, at, number)
+			.map_err(|e| self.state_error(at, e))?;
+
+		let mut transactions = Vec::new();
+		for outputs in groups {
+			let Some((id, _)) = outputs.first() else { continue };
+
+			transactions.push(types::Transaction {
+				transaction_identifier: TransactionIdentifier { hash: format!("{id:?}") },
+				operations: outputs
+					.iter()
+					.enumerate()
+					.map(|(index, (outpoint, output))| {
+						let mut operation = created(*outpoint, output, Some(SUCCESS));
+						operation.operation_identifier.index = index as u64;
+						operation
+					})
+					.collect(),
+			});
+		}
+		Ok(transactions)
+	}
+}
+
+/// The outputs of `transaction` by outpoint.
+fn output_pairs(
+	transaction: &Transaction,
+) -> impl Iterator<Item = (H256, TransactionOutput)> + '_ {
+	let txid = transaction.txid();
+	transaction.outputs.iter().enumerate().map(move |(index, output)| {
+		(Transaction::outpoint_of(&txid, index as u64), output.clone())
+	})
+}
@@ -0,0 +1,327 @@
+//! Rosetta Data and Construction APIs, the interface exchanges list chains through.
+//!
+//! Served when the node is started with `--rosetta-port`. The mapping onto the UTXO chain:
+//!
+//! - An account is a pubkey, its address the 0x-prefixed hex of the key, and its balance the sum
+//!   of the unspent outputs paying it. Outputs are the coins of the account.
+//! - A spend is a transaction with an `INPUT` operation per output spent, for minus its value, and
+//!   an `OUTPUT` operation per output created. The fee is the difference and is not an operation
+//!   of its own: it reaches the block author through the reward.
+//! - Outputs created by the runtime rather than by a spend (block, pending and uncle rewards, and
+//!   mints) are transactions with only `OUTPUT` operations, identified by the outpoint of their
+//!   first output.
+//!
+//! The construction flow passes around the transaction along with the outputs it spends, like a
+//! PSBT does, so that signing, combining and parsing need no node state. See [`construction`].
+//!
+//! Keys are sr25519, for which Rosetta has no curve or signature type, so both are given as
+//! `sr25519`.
+
+mod construction;
+mod data;
+mod types;
+
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+	http::StatusCode,
+	response::{IntoResponse, Response},
+	routing::post,
+	Json, Router,
+};
+use codec::Decode;
+use sc_client_api::StorageProvider;
+use sp_blockchain::HeaderBackend;
+use sp_core::{bytes, hashing::twox_128, storage::StorageKey, H256};
+use tumuchain_runtime::BlockNumber;
+use utxo::{denomination, Transaction, TransactionOutput, Value};
+
+use crate::{
+	rate_limit::SubmissionLimiter,
+	service::{FullClient, FullPool},
+	spent_index,
+};
+use types::{
+	AccountIdentifier, Amount, BlockIdentifier, CoinAction, CoinChange, CoinIdentifier, Currency,
+	NetworkIdentifier, Operation, OperationIdentifier, PartialBlockIdentifier,
+};
+
+/// The `blockchain` of this chain's network identifier.
+const BLOCKCHAIN: &str = "tumuchain";
+
+/// Version of the Rosetta specification implemented.
+const ROSETTA_VERSION: &str = "1.4.13";
+
+/// Operation spending an output.
+const INPUT: &str = "INPUT";
+
+/// Operation creating an output.
+const OUTPUT: &str = "OUTPUT";
+
+/// Status of the operations of transactions included in blocks, which all succeeded.
+const SUCCESS: &str = "SUCCESS";
+
+/// Curve and signature type of the keys.
+const SR25519: &str = "sr25519";
+
+/// State shared by the Rosetta handlers.
+#[derive(Clone)]
+struct Rosetta {
+	client: Arc<FullClient>,
+	pool: Arc<FullPool>,
+	submissions: SubmissionLimiter,
+	network: NetworkIdentifier,
+}
+
+/// Serve the Rosetta APIs on `addr` until the node shuts down, for the chain named `network`.
+pub async fn run(
+	addr: SocketAddr,
+	client: Arc<FullClient>,
+	pool: Arc<FullPool>,
+	submissions: SubmissionLimiter,
+	network: String,
+) {
+	let network = NetworkIdentifier { blockchain: BLOCKCHAIN.into(), network };
+	let app = Router::new()
+		.route("/network/list", post(data::network_list))
+		.route("/network/options", post(data::network_options))
+		.route("/network/status", post(data::network_status))
+		.route("/account/balance", post(data::account_balance))
+		.route("/account/coins", post(data::account_coins))
+		.route("/block", post(data::block))
+		.route("/block/transaction", post(data::block_transaction))
+		.route("/mempool", post(data::mempool))
+		.route("/construction/derive", post(construction::derive))
+		.route("/construction/preprocess", post(construction::preprocess))
+		.route("/construction/metadata", post(construction::metadata))
+		.route("/construction/payloads", post(construction::payloads))
+		.route("/construction/combine", post(construction::combine))
+		.route("/construction/parse", post(construction::parse))
+		.route("/construction/hash", post(construction::hash))
+		.route("/construction/submit", post(construction::submit))
+		.with_state(Rosetta { client, pool, submissions, network });
+
+	let listener = match tokio::net::TcpListener::bind(addr).await {
+		Ok(listener) => listener,
+		Err(e) => {
+			log::error!(target: "rosetta", "Cannot listen for Rosetta on {addr}: {e}");
+			return
+		},
+	};
+	log::info!(target: "rosetta", "Rosetta API listening on {addr}");
+	// Submissions are limited by the client's address
+	let app = app.into_make_service_with_connect_info::<SocketAddr>();
+	if let Err(e) = axum::serve(listener, app).await {
+		log::error!(target: "rosetta", "Rosetta API on {addr} failed: {e}");
+	}
+}
+
+/// The errors the APIs answer with, listed by `/network/options`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorKind {
+	UnknownNetwork = 1,
+	InvalidRequest = 2,
+	BlockNotFound = 3,
+	TransactionNotFound = 4,
+	Pruned = 5,
+	NodeError = 6,
+	TransactionRejected = 7,
+	RateLimited = 8,
+}
+
+impl ErrorKind {
+	const ALL: [ErrorKind; 8] = [
+		Self::UnknownNetwork,
+		Self::InvalidRequest,
+		Self::BlockNotFound,
+		Self::TransactionNotFound,
+		Self::Pruned,
+		Self::NodeError,
+		Self::TransactionRejected,
+		Self::RateLimited,
+	];
+
+	fn message(self) -> &'static str {
+		match self {
+			Self::UnknownNetwork => "Unknown network",
+			Self::InvalidRequest => "Invalid request",
+			Self::BlockNotFound => "Block not found",
+			Self::TransactionNotFound => "Transaction not found",
+			Self::Pruned => "Pruned by this node",
+			Self::NodeError => "Node error",
+			Self::TransactionRejected => "Transaction rejected",
+			Self::RateLimited => "Too many submissions",
+		}
+	}
+
+	/// The error without details, as listed by `/network/options`.
+	fn model(self) -> types::Error {
+		types::Error {
+			code: self as u32,
+			message: self.message().into(),
+			// Blocks may be imported, reads succeed and allowances refill later
+			retriable: matches!(self, Self::BlockNotFound | Self::NodeError | Self::RateLimited),
+			details: None,
+		}
+	}
+
+	fn with(self, details: impl std::fmt::Display) -> ApiError {
+		ApiError(types::Error {
+			details: Some(serde_json::json!({ "error": details.to_string() })),
+			..self.model()
+		})
+	}
+}
+
+/// An error answered with status 500, as Rosetta asks.
+struct ApiError(types::Error);
+
+impl IntoResponse for ApiError {
+	fn into_response(self) -> Response {
+		(StatusCode::INTERNAL_SERVER_ERROR, Json(self.0)).into_response()
+	}
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+impl Rosetta {
+	fn check_network(&self, network: &NetworkIdentifier) -> Result<(), ApiError> {
+		if *network != self.network {
+			let serves = format!("this node serves {:?}", self.network);
+			return Err(ErrorKind::UnknownNetwork.with(serves))
+		}
+		Ok(())
+	}
+
+	/// The block identified by `block`, or the best block if neither field is set.
+	fn resolve(&self, block: &PartialBlockIdentifier) -> Result<(H256, BlockNumber), ApiError> {
+		let hash = match (&block.hash, block.index) {
+			(Some(hash), _) => parse_hash(hash)?,
+			(None, Some(index)) => {
+				let number = BlockNumber::try_from(index)
+					.map_err(|_| ErrorKind::BlockNotFound.with(format!("no block #{index}")))?;
+				self.client
+					.hash(number)
+					.map_err(node_error)?
+					.ok_or_else(|| ErrorKind::BlockNotFound.with(format!("no block #{index}")))?
+			},
+			(None, None) => self.client.info().best_hash,
+		};
+		let number = self
+			.client
+			.number(hash)
+			.map_err(node_error)?
+			.ok_or_else(|| ErrorKind::BlockNotFound.with(format!("no block {hash:?}")))?;
+		if block.index.is_some_and(|index| index != u64::from(number)) {
+			return Err(ErrorKind::InvalidRequest.with("block hash and index do not match"))
+		}
+		Ok((hash, number))
+	}
+
+	/// The unspent output at `outpoint` as of block `at`.
+	fn output(&self, at: H256, outpoint: &H256) -> Result<Option<TransactionOutput>, ApiError> {
+		spent_index::output_at(&self.client, at, outpoint).map_err(|e| self.state_error(at, e))
+	}
+
+	/// The time of block `at`, in milliseconds since the Unix epoch.
+	fn timestamp(&self, at: H256) -> Result<u64, ApiError> {
+		let key = StorageKey([twox_128(b"Timestamp"), twox_128(b"Now")].concat());
+		let data = self.client.storage(at, &key).map_err(|e| self.state_error(at, e))?;
+		Ok(data.and_then(|data| u64::decode(&mut &data.0[..]).ok()).unwrap_or_default())
+	}
+
+	/// Converts a failure to read the state at `at`, telling pruned state apart.
+	fn state_error(&self, at: H256, error: sp_blockchain::Error) -> ApiError {
+		match (error, self.client.header(at)) {
+			(sp_blockchain::Error::UnknownBlock(_), Ok(Some(_))) =>
+				ErrorKind::Pruned.with(format!("the state of block {at:?} was pruned")),
+			(error, _) => node_error(error),
+		}
+	}
+}
+
+fn node_error(error: impl std::fmt::Display) -> ApiError {
+	ErrorKind::NodeError.with(error)
+}
+
+fn block_identifier(hash: H256, number: BlockNumber) -> BlockIdentifier {
+	BlockIdentifier { index: number.into(), hash: format!("{hash:?}") }
+}
+
+fn currency() -> Currency {
+	// Values are in base units, which Rosetta scales by the decimals
+	Currency { symbol: denomination::SYMBOL.into(), decimals: denomination::DECIMALS }
+}
+
+fn amount(value: Value, negative: bool) -> Amount {
+	let sign = if negative { "-" } else { "" };
+	Amount { value: format!("{sign}{value}"), currency: currency() }
+}
+
+fn account(pubkey: H256) -> AccountIdentifier {
+	AccountIdentifier { address: format!("{pubkey:?}") }
+}
+
+/// Parse a 0x-prefixed 32-byte hash, as found in identifiers and addresses.
+fn parse_hash(hex: &str) -> Result<H256, ApiError> {
+	match bytes::from_hex(hex) {
+		Ok(bytes) if bytes.len() == 32 => Ok(H256::from_slice(&bytes)),
+		_ => Err(ErrorKind::InvalidRequest.with(format!("{hex} is not a 32-byte hex hash"))),
+	}
+}
+
+/// Hex without a prefix, as Rosetta's `hex_bytes` are.
+fn hex_bytes(data: &[u8]) -> String {
+	bytes::to_hex(data, false).trim_start_matches("0x").into()
+}
+
+fn parse_hex_bytes(hex: &str) -> Result<Vec<u8>, ApiError> {
+	bytes::from_hex(hex).map_err(|e| ErrorKind::InvalidRequest.with(format!("bad hex: {e}")))
+}
+
+/// The operations of `transaction`, given the outputs its inputs spend where known.
+fn operations(
+	transaction: &Transaction,
+	spent: &[Option<TransactionOutput>],
+	status: Option<&str>,
+) -> Vec<Operation> {
+	let inputs = transaction.inputs.iter().zip(spent).map(|(input, spent)| Operation {
+		operation_identifier: OperationIdentifier { index: 0 },
+		kind: INPUT.into(),
+		status: status.map(Into::into),
+		account: spent.as_ref().map(|output| account(output.pubkey)),
+		amount: spent.as_ref().map(|output| amount(output.value, true)),
+		coin_change: Some(CoinChange {
+			coin_identifier: CoinIdentifier { identifier: format!("{:?}", input.outpoint) },
+			coin_action: CoinAction::CoinSpent,
+		}),
+	});
+	let txid = transaction.txid();
+	let outputs = transaction.outputs.iter().enumerate().map(|(index, output)| {
+		created(Transaction::outpoint_of(&txid, index as u64), output, status)
+	});
+
+	inputs
+		.chain(outputs)
+		.enumerate()
+		.map(|(index, operation)| Operation {
+			operation_identifier: OperationIdentifier { index: index as u64 },
+			..operation
+		})
+		.collect()
+}
+
+/// The operation creating `output` at `outpoint`.
+fn created(outpoint: H256, output: &TransactionOutput, status: Option<&str>) -> Operation {
+	Operation {
+		operation_identifier: OperationIdentifier { index: 0 },
+		kind: OUTPUT.into(),
+		status: status.map(Into::into),
+		account: Some(account(output.pubkey)),
+		amount: Some(amount(output.value, false)),
+		coin_change: Some(CoinChange {
+			coin_identifier: CoinIdentifier { identifier: format!("{outpoint:?}") },
+			coin_action: CoinAction::CoinCreated,
+		}),
+	}
+}
@@ -0,0 +1,324 @@
+//! The Rosetta models used by this node.
+//!
+//! Only the fields the node reads or fills in are declared. Unknown request fields are ignored.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkIdentifier {
+	pub blockchain: String,
+	pub network: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockIdentifier {
+	pub index: u64,
+	pub hash: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartialBlockIdentifier {
+	pub index: Option<u64>,
+	pub hash: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionIdentifier {
+	pub hash: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountIdentifier {
+	pub address: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Currency {
+	pub symbol: String,
+	pub decimals: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Amount {
+	pub value: String,
+	pub currency: Currency,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoinIdentifier {
+	pub identifier: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoinAction {
+	CoinCreated,
+	CoinSpent,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoinChange {
+	pub coin_identifier: CoinIdentifier,
+	pub coin_action: CoinAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Coin {
+	pub coin_identifier: CoinIdentifier,
+	pub amount: Amount,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OperationIdentifier {
+	pub index: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Operation {
+	pub operation_identifier: OperationIdentifier,
+	#[serde(rename = "type")]
+	pub kind: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub status: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub account: Option<AccountIdentifier>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub amount: Option<Amount>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub coin_change: Option<CoinChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transaction {
+	pub transaction_identifier: TransactionIdentifier,
+	pub operations: Vec<Operation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Block {
+	pub block_identifier: BlockIdentifier,
+	pub parent_block_identifier: BlockIdentifier,
+	/// Milliseconds since the Unix epoch
+	pub timestamp: u64,
+	pub transactions: Vec<Transaction>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublicKey {
+	pub hex_bytes: String,
+	pub curve_type: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SigningPayload {
+	pub account_identifier: AccountIdentifier,
+	pub hex_bytes: String,
+	pub signature_type: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Signature {
+	pub signing_payload: SigningPayload,
+	pub public_key: PublicKey,
+	pub signature_type: String,
+	pub hex_bytes: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Error {
+	pub code: u32,
+	pub message: String,
+	pub retriable: bool,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub details: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkRequest {
+	pub network_identifier: NetworkIdentifier,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkListResponse {
+	pub network_identifiers: Vec<NetworkIdentifier>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Version {
+	pub rosetta_version: String,
+	pub node_version: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationStatus {
+	pub status: String,
+	pub successful: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Allow {
+	pub operation_statuses: Vec<OperationStatus>,
+	pub operation_types: Vec<String>,
+	pub errors: Vec<Error>,
+	pub historical_balance_lookup: bool,
+	pub mempool_coins: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkOptionsResponse {
+	pub version: Version,
+	pub allow: Allow,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Peer {
+	pub peer_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkStatusResponse {
+	pub current_block_identifier: BlockIdentifier,
+	pub current_block_timestamp: u64,
+	pub genesis_block_identifier: BlockIdentifier,
+	pub peers: Vec<Peer>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountBalanceRequest {
+	pub network_identifier: NetworkIdentifier,
+	pub account_identifier: AccountIdentifier,
+	#[serde(default)]
+	pub block_identifier: Option<PartialBlockIdentifier>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountBalanceResponse {
+	pub block_identifier: BlockIdentifier,
+	pub balances: Vec<Amount>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountCoinsRequest {
+	pub network_identifier: NetworkIdentifier,
+	pub account_identifier: AccountIdentifier,
+	#[serde(default)]
+	pub include_mempool: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountCoinsResponse {
+	pub block_identifier: BlockIdentifier,
+	pub coins: Vec<Coin>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockRequest {
+	pub network_identifier: NetworkIdentifier,
+	pub block_identifier: PartialBlockIdentifier,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockResponse {
+	pub block: Block,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockTransactionRequest {
+	pub network_identifier: NetworkIdentifier,
+	pub block_identifier: BlockIdentifier,
+	pub transaction_identifier: TransactionIdentifier,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockTransactionResponse {
+	pub transaction: Transaction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MempoolResponse {
+	pub transaction_identifiers: Vec<TransactionIdentifier>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstructionDeriveRequest {
+	pub network_identifier: NetworkIdentifier,
+	pub public_key: PublicKey,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstructionDeriveResponse {
+	pub account_identifier: AccountIdentifier,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstructionPreprocessRequest {
+	pub network_identifier: NetworkIdentifier,
+	pub operations: Vec<Operation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstructionPreprocessResponse {
+	pub options: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstructionMetadataRequest {
+	pub network_identifier: NetworkIdentifier,
+	#[serde(default)]
+	pub options: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstructionMetadataResponse {
+	pub metadata: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstructionPayloadsRequest {
+	pub network_identifier: NetworkIdentifier,
+	pub operations: Vec<Operation>,
+	#[serde(default)]
+	pub metadata: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstructionPayloadsResponse {
+	pub unsigned_transaction: String,
+	pub payloads: Vec<SigningPayload>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstructionCombineRequest {
+	pub network_identifier: NetworkIdentifier,
+	pub unsigned_transaction: String,
+	pub signatures: Vec<Signature>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstructionCombineResponse {
+	pub signed_transaction: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstructionParseRequest {
+	pub network_identifier: NetworkIdentifier,
+	pub signed: bool,
+	pub transaction: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstructionParseResponse {
+	pub operations: Vec<Operation>,
+	pub account_identifier_signers: Vec<AccountIdentifier>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstructionTransactionRequest {
+	pub network_identifier: NetworkIdentifier,
+	pub signed_transaction: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionIdentifierResponse {
+	pub transaction_identifier: TransactionIdentifier,
+}
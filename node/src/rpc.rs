@@ -16,37 +16,95 @@ use sp_blockchain::{Error as BlockChainError, HeaderBackend, HeaderMetadata};
 
 pub use sc_rpc_api::DenyUnsafe;
 
+pub mod difficulty;
+pub mod events;
+pub mod health;
+pub mod issuance;
+pub mod miner;
+pub mod package;
+pub mod pending;
+pub mod utxo;
+pub mod watch;
+
 /// Full client dependencies.
 pub struct FullDeps<C, P> {
 	/// The client instance to use.
 	pub client: Arc<C>,
 	/// Transaction pool instance.
 	pub pool: Arc<P>,
+	/// Whether the node is syncing or offline
+	pub sync: Arc<dyn sp_consensus::SyncOracle + Send + Sync>,
 	/// Whether to deny unsafe calls
 	pub deny_unsafe: DenyUnsafe,
+	/// Whether the node keeps the index of spending transactions
+	pub spent_index: bool,
+	/// The descriptors the node watches
+	pub watchlist: crate::watch::Watchlist,
+	/// The key authored blocks pay their rewards to, if the node is an authority
+	pub payout_key: Option<crate::authoring::PayoutKey>,
+	/// Seals blocks on request, if the node runs with `--dev-instant-mine`
+	pub block_generator: Option<crate::instant_seal::BlockGenerator>,
+	/// The pool's admission policy, which records the fees of packages and when transactions
+	/// were first seen
+	pub policy: crate::mempool::RelayPolicy,
 }
 
 /// Instantiate all full RPC extensions.
-pub fn create_full<C, P>(
+pub fn create_full<C, P, BE>(
 	deps: FullDeps<C, P>,
 ) -> Result<RpcModule<()>, Box<dyn std::error::Error + Send + Sync>>
 where
+	BE: sc_client_api::Backend<Block> + 'static,
 	C: ProvideRuntimeApi<Block>,
+	C: sc_client_api::StorageProvider<Block, BE> + sc_client_api::BlockBackend<Block>,
+	C: sc_client_api::AuxStore + sc_client_api::BlockchainEvents<Block>,
 	C: HeaderBackend<Block> + HeaderMetadata<Block, Error = BlockChainError> + 'static,
 	C: Send + Sync + 'static,
 	C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Nonce>,
 	C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>,
+	C::Api: tumuchain_runtime::apis::IssuanceApi<Block>,
+	C::Api: tumuchain_runtime::apis::RewardApi<Block>,
+	C::Api: tumuchain_runtime::apis::DifficultyApi<Block>,
+	C::Api: tumuchain_runtime::apis::MinerStatsApi<Block>,
 	C::Api: BlockBuilder<Block>,
-	P: TransactionPool + 'static,
+	P: TransactionPool<Block = Block, Hash = sp_core::H256> + 'static,
 {
+	use difficulty::{Difficulty, DifficultyApiServer};
+	use events::{Events, EventsApiServer};
+	use health::{Health, HealthApiServer};
+	use issuance::{Issuance, IssuanceApiServer};
+	use miner::{Miner, MinerApiServer};
+	use package::{Package, PackageApiServer};
+	use pending::{Pending, PendingApiServer};
 	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApiServer};
 	use substrate_frame_rpc_system::{System, SystemApiServer};
+	use self::utxo::{Utxo, UtxoApiServer};
+	use watch::{Watch, WatchApiServer};
 
 	let mut module = RpcModule::new(());
-	let FullDeps { client, pool, deny_unsafe } = deps;
+	let FullDeps {
+		client,
+		pool,
+		sync,
+		deny_unsafe,
+		spent_index,
+		watchlist,
+		payout_key,
+		block_generator,
+		policy,
+	} = deps;
 
-	module.merge(System::new(client.clone(), pool, deny_unsafe).into_rpc())?;
-	module.merge(TransactionPayment::new(client).into_rpc())?;
+	module.merge(System::new(client.clone(), pool.clone(), deny_unsafe).into_rpc())?;
+	module.merge(TransactionPayment::new(client.clone()).into_rpc())?;
+	module.merge(Issuance::new(client.clone()).into_rpc())?;
+	module.merge(Difficulty::new(client.clone()).into_rpc())?;
+	module.merge(Miner::new(client.clone(), payout_key, block_generator, deny_unsafe).into_rpc())?;
+	module.merge(Events::new(client.clone()).into_rpc())?;
+	module.merge(Health::new(client.clone(), pool.clone(), sync).into_rpc())?;
+	module.merge(Package::new(client.clone(), pool.clone(), policy.clone()).into_rpc())?;
+	module.merge(Pending::new(client.clone(), pool, policy).into_rpc())?;
+	module.merge(Utxo::new(client, spent_index).into_rpc())?;
+	module.merge(Watch::new(watchlist, deny_unsafe).into_rpc())?;
 
 	// Extend this RPC with a custom API by using the following syntax.
 	// `YourRpcStruct` should have a reference to a client, which is needed
@@ -0,0 +1,102 @@
+//! RPC interface for the difficulty and the network hashrate estimated from it.
+//!
+//! The estimate is the runtime's, so pools and dashboards asking different nodes get the same
+//! figures.
+
+use std::{marker::PhantomData, sync::Arc};
+
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::error::{ErrorObject, ErrorObjectOwned},
+};
+use serde::{Deserialize, Serialize};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::U256;
+use sp_runtime::traits::Block as BlockT;
+use tumuchain_runtime::{apis::DifficultyApi as DifficultyRuntimeApi, pallet_difficulty};
+
+/// Error code returned when the runtime API call itself fails.
+const RUNTIME_ERROR: i32 = 1;
+
+/// The network hashrate estimated over recent blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkHashrate {
+	/// Hashes per second
+	pub hashrate: U256,
+	/// Blocks the estimate covers
+	pub blocks: u32,
+	/// Average time between those blocks, in milliseconds
+	pub average_block_time: u128,
+	/// The block time the difficulty is retargeted towards, in milliseconds
+	pub target_block_time: u128,
+	/// The difficulty the next block has to be sealed at
+	pub difficulty: U256,
+}
+
+impl From<pallet_difficulty::HashrateEstimate> for NetworkHashrate {
+	fn from(estimate: pallet_difficulty::HashrateEstimate) -> Self {
+		Self {
+			hashrate: estimate.hashrate,
+			blocks: estimate.blocks,
+			average_block_time: estimate.average_block_time,
+			target_block_time: estimate.target_block_time,
+			difficulty: estimate.difficulty,
+		}
+	}
+}
+
+/// Difficulty RPC methods.
+#[rpc(server)]
+pub trait DifficultyApi<BlockHash> {
+	/// The network hashrate estimated over the last `window` blocks as of `at`, by default all
+	/// those the difficulty pallet keeps for retargeting, 60. `null` until two blocks with
+	/// distinct timestamps were recorded.
+	#[method(name = "chain_getNetworkHashrate")]
+	fn network_hashrate(
+		&self,
+		window: Option<u32>,
+		at: Option<BlockHash>,
+	) -> RpcResult<Option<NetworkHashrate>>;
+}
+
+/// Implements the [`DifficultyApiServer`] RPC trait on top of the runtime API.
+pub struct Difficulty<C, Block> {
+	client: Arc<C>,
+	_marker: PhantomData<Block>,
+}
+
+impl<C, Block> Difficulty<C, Block> {
+	/// Creates a new instance of the difficulty RPC handler.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: Default::default() }
+	}
+}
+
+impl<C, Block> DifficultyApiServer<<Block as BlockT>::Hash> for Difficulty<C, Block>
+where
+	Block: BlockT,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: DifficultyRuntimeApi<Block>,
+{
+	fn network_hashrate(
+		&self,
+		window: Option<u32>,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Option<NetworkHashrate>> {
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+		let estimate = self
+			.client
+			.runtime_api()
+			.network_hashrate(at, window.unwrap_or(u32::MAX))
+			.map_err(runtime_error_into_rpc_err)?;
+		Ok(estimate.map(Into::into))
+	}
+}
+
+/// Converts a runtime trap into an RPC error.
+fn runtime_error_into_rpc_err(err: impl std::fmt::Debug) -> ErrorObjectOwned {
+	ErrorObject::owned(RUNTIME_ERROR, "Runtime error", Some(format!("{:?}", err)))
+}
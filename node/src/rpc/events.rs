@@ -0,0 +1,255 @@
+//! RPC subscription to chain events, decoded and filtered on the node.
+//!
+//! `events_subscribe` streams the `TransactionSuccess` and `RewardsIssued` events of the UTXO
+//! pallet and the `DifficultyUpdated` events of the difficulty pallet as blocks become best, so
+//! services do not have to fetch and decode `System::Events` themselves. Events of a block later
+//! reorged out are not retracted: services needing certainty should wait for finality.
+
+use std::{marker::PhantomData, sync::Arc};
+
+use codec::Decode;
+use futures::StreamExt;
+use jsonrpsee::{
+	core::{async_trait, SubscriptionResult},
+	proc_macros::rpc,
+	PendingSubscriptionSink, SubscriptionMessage,
+};
+use sc_client_api::{Backend, BlockchainEvents, StorageProvider};
+use serde::{Deserialize, Serialize};
+use sp_core::{hashing::twox_128, storage::StorageKey, H256, U256};
+use sp_runtime::traits::{Block as BlockT, Header as _, UniqueSaturatedInto};
+use tumuchain_runtime::{pallet_difficulty, BlockNumber, RuntimeEvent};
+use utxo::{TransactionOutput, Value};
+
+use super::utxo::utxo_store_prefix;
+
+/// The kinds of events that can be subscribed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EventKind {
+	/// [`DecodedEvent::TransactionSuccess`]
+	TransactionSuccess,
+	/// [`DecodedEvent::RewardsIssued`]
+	RewardsIssued,
+	/// [`DecodedEvent::DifficultyUpdated`]
+	DifficultyUpdated,
+}
+
+/// Which events a subscription receives. Unset fields let every event through.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EventFilter {
+	/// Kinds of events to receive
+	pub kinds: Option<Vec<EventKind>>,
+	/// Only events paying this pubkey. Difficulty updates never match.
+	pub pubkey: Option<H256>,
+	/// Only events moving at least this value. Difficulty updates never match.
+	pub min_amount: Option<Value>,
+}
+
+impl EventFilter {
+	/// Whether `event` passes the filter.
+	pub fn matches(&self, event: &DecodedEvent) -> bool {
+		self.kinds.as_ref().map_or(true, |kinds| kinds.contains(&event.kind())) &&
+			self.pubkey.map_or(true, |pubkey| event.pays(&pubkey)) &&
+			self.min_amount
+				.map_or(true, |min| event.amount().is_some_and(|amount| amount >= min))
+	}
+}
+
+/// An event, with the fields services look at.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all_fields = "camelCase")]
+pub enum DecodedEvent {
+	/// A transaction was executed
+	TransactionSuccess {
+		/// The transaction
+		txid: H256,
+		/// Sum of the outputs
+		value: Value,
+		/// Sum of the outputs spent
+		input_total: Value,
+		/// Fee left to the block author, the inputs less the outputs
+		fee: Value,
+		/// The outputs created
+		outputs: Vec<TransactionOutput>,
+	},
+	/// A block reward was issued
+	RewardsIssued {
+		/// Value of the reward output
+		amount: Value,
+		/// The reward output
+		outpoint: H256,
+		/// Owner of the reward output, if still unspent at the block
+		pubkey: Option<H256>,
+	},
+	/// The mining difficulty was adjusted
+	DifficultyUpdated {
+		/// The new difficulty
+		difficulty: U256,
+	},
+}
+
+impl DecodedEvent {
+	fn kind(&self) -> EventKind {
+		match self {
+			Self::TransactionSuccess { .. } => EventKind::TransactionSuccess,
+			Self::RewardsIssued { .. } => EventKind::RewardsIssued,
+			Self::DifficultyUpdated { .. } => EventKind::DifficultyUpdated,
+		}
+	}
+
+	fn pays(&self, pubkey: &H256) -> bool {
+		match self {
+			Self::TransactionSuccess { outputs, .. } =>
+				outputs.iter().any(|output| output.pubkey == *pubkey),
+			Self::RewardsIssued { pubkey: owner, .. } => owner.as_ref() == Some(pubkey),
+			Self::DifficultyUpdated { .. } => false,
+		}
+	}
+
+	fn amount(&self) -> Option<Value> {
+		match self {
+			Self::TransactionSuccess { value, .. } => Some(*value),
+			Self::RewardsIssued { amount, .. } => Some(*amount),
+			Self::DifficultyUpdated { .. } => None,
+		}
+	}
+}
+
+/// An event sent to subscribers, with the block it was emitted in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainEvent {
+	/// Block emitting the event
+	pub block_hash: H256,
+	/// Number of that block
+	pub block_number: BlockNumber,
+	/// The event
+	pub event: DecodedEvent,
+}
+
+/// Chain event RPC methods.
+#[rpc(server)]
+pub trait EventsApi {
+	/// Stream the events of each new best block passing `filter`, or all of them.
+	#[subscription(
+		name = "events_subscribe" => "events_event",
+		unsubscribe = "events_unsubscribe",
+		item = ChainEvent
+	)]
+	async fn subscribe(&self, filter: Option<EventFilter>) -> SubscriptionResult;
+}
+
+/// Implements the [`EventsApiServer`] RPC trait by decoding the events in the node's state.
+pub struct Events<C, Block, BE> {
+	client: Arc<C>,
+	_marker: PhantomData<(Block, BE)>,
+}
+
+impl<C, Block, BE> Events<C, Block, BE> {
+	/// Creates a new instance of the events RPC handler.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: Default::default() }
+	}
+}
+
+#[async_trait]
+impl<C, Block, BE> EventsApiServer for Events<C, Block, BE>
+where
+	Block: BlockT<Hash = H256>,
+	BE: Backend<Block> + 'static,
+	C: BlockchainEvents<Block> + StorageProvider<Block, BE> + Send + Sync + 'static,
+{
+	async fn subscribe(
+		&self,
+		pending: PendingSubscriptionSink,
+		filter: Option<EventFilter>,
+	) -> SubscriptionResult {
+		let filter = filter.unwrap_or_default();
+		let mut imports = self.client.import_notification_stream();
+		let sink = pending.accept().await?;
+
+		loop {
+			let notification = tokio::select! {
+				_ = sink.closed() => return Ok(()),
+				notification = imports.next() => match notification {
+					Some(notification) => notification,
+					None => return Ok(()),
+				},
+			};
+			if !notification.is_new_best {
+				continue
+			}
+
+			let block_number = (*notification.header.number()).unique_saturated_into();
+			for event in self.block_events(notification.hash) {
+				if !filter.matches(&event) {
+					continue
+				}
+				let event = ChainEvent { block_hash: notification.hash, block_number, event };
+				if sink.send(SubscriptionMessage::from_json(&event)?).await.is_err() {
+					return Ok(())
+				}
+			}
+		}
+	}
+}
+
+impl<C, Block, BE> Events<C, Block, BE>
+where
+	Block: BlockT<Hash = H256>,
+	BE: Backend<Block> + 'static,
+	C: StorageProvider<Block, BE>,
+{
+	/// The events of block `at` that can be subscribed to, in order.
+	fn block_events(&self, at: H256) -> Vec<DecodedEvent> {
+		type EventRecord = frame_system::EventRecord<RuntimeEvent, H256>;
+
+		let key = StorageKey([twox_128(b"System"), twox_128(b"Events")].concat());
+		let records = match self.client.storage(at, &key) {
+			Ok(Some(data)) => Vec::<EventRecord>::decode(&mut &data.0[..]),
+			Ok(None) => Ok(Vec::new()),
+			Err(e) => {
+				log::warn!(target: "rpc", "Cannot read the events of block {at:?}: {e}");
+				return Vec::new()
+			},
+		};
+		// Events of blocks from before a runtime upgrade changing their layout do not decode
+		let Ok(records) = records else { return Vec::new() };
+
+		records
+			.into_iter()
+			.filter_map(|record| match record.event {
+				RuntimeEvent::Utxo(utxo::Event::TransactionSuccess {
+					transaction,
+					input_total,
+					output_total,
+					fee,
+				}) => Some(DecodedEvent::TransactionSuccess {
+					txid: transaction.txid(),
+					value: output_total,
+					input_total,
+					fee,
+					outputs: transaction.outputs.into_inner(),
+				}),
+				RuntimeEvent::Utxo(utxo::Event::RewardsIssued { amount, utxo_hash }) =>
+					Some(DecodedEvent::RewardsIssued {
+						amount,
+						outpoint: utxo_hash,
+						pubkey: self.output_owner(at, &utxo_hash),
+					}),
+				RuntimeEvent::Difficulty(pallet_difficulty::Event::DifficultyUpdated {
+					difficulty,
+				}) => Some(DecodedEvent::DifficultyUpdated { difficulty }),
+				_ => None,
+			})
+			.collect()
+	}
+
+	/// The pubkey paid by the unspent output at `outpoint` as of block `at`.
+	fn output_owner(&self, at: H256, outpoint: &H256) -> Option<H256> {
+		let key = StorageKey([utxo_store_prefix(), outpoint.as_bytes().to_vec()].concat());
+		let data = self.client.storage(at, &key).ok()??;
+		TransactionOutput::decode(&mut &data.0[..]).ok().map(|output| output.pubkey)
+	}
+}
@@ -0,0 +1,214 @@
+//! RPC interface summarizing whether the node serves sane data, for load balancers and monitoring.
+//!
+//! `system_health` only tells whether the node is syncing and has peers. `system_utxoHealth` adds
+//! what a UTXO chain can go wrong in: whether the value of the UTXO set adds up to what was issued,
+//! how far the last retarget moved the difficulty, and how long ago the best block was sealed
+//! against the target block time, along with the depth of the pool. `healthy` is false, with the
+//! `problems` saying why, when the node is major syncing or has no peers, when the UTXO set does
+//! not hold the supply expected, or when the best block is more than [`STALE_BLOCK_TIMES`] target
+//! block times old.
+
+use std::{
+	marker::PhantomData,
+	sync::Arc,
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use codec::Decode;
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::error::{ErrorObject, ErrorObjectOwned},
+};
+use sc_client_api::{Backend, StorageProvider};
+use sc_transaction_pool_api::TransactionPool;
+use serde::{Deserialize, Serialize};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_consensus::SyncOracle;
+use sp_core::{hashing::twox_128, storage::StorageKey, H256, U256};
+use sp_runtime::traits::Header as HeaderT;
+use tumuchain_runtime::{
+	apis::DifficultyApi as DifficultyRuntimeApi, opaque::Block, BlockNumber, MILLISECS_PER_BLOCK,
+};
+use utxo::Value;
+
+/// Error code returned when the node's state cannot be read.
+const STATE_ERROR: i32 = 1;
+
+/// Target block times since the best block past which the node is unhealthy.
+pub const STALE_BLOCK_TIMES: u64 = 10;
+
+/// The health of the node and of the chain it serves.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UtxoHealth {
+	/// Whether the node looks fit to serve requests
+	pub healthy: bool,
+	/// Why it does not, empty when healthy
+	pub problems: Vec<String>,
+	/// Number of the best block
+	pub best_number: BlockNumber,
+	/// Hash of the best block
+	pub best_hash: H256,
+	/// Number of the last finalized block
+	pub finalized_number: BlockNumber,
+	/// Whether the node is catching up with the network
+	pub syncing: bool,
+	/// Whether the node is connected to any peer
+	pub has_peers: bool,
+	/// Sum of the values of the unspent outputs
+	pub utxo_value: Value,
+	/// Value issued less the rewards and fees not paid out yet, which the UTXO set should hold
+	pub expected_supply: Value,
+	/// The difficulty the next block has to be sealed at
+	pub difficulty: U256,
+	/// Change of the difficulty at the best block's retarget, in parts per million of the
+	/// difficulty before it
+	pub retarget_delta_ppm: Option<i64>,
+	/// Milliseconds since the timestamp of the best block, if it has one
+	pub since_last_block: Option<u64>,
+	/// The block time the difficulty is retargeted towards, in milliseconds
+	pub target_block_time: u128,
+	/// Transactions in the pool ready for the next block
+	pub pool_ready: usize,
+	/// Encoded length of those, in bytes
+	pub pool_ready_bytes: usize,
+	/// Transactions in the pool waiting for the outputs they spend
+	pub pool_future: usize,
+}
+
+/// Health check RPC methods.
+#[rpc(server, client)]
+pub trait HealthApi {
+	/// The health of the node and of the chain it serves as of its best block.
+	#[method(name = "system_utxoHealth")]
+	fn utxo_health(&self) -> RpcResult<UtxoHealth>;
+}
+
+/// Implements the [`HealthApiServer`] RPC trait.
+pub struct Health<C, P, BE> {
+	client: Arc<C>,
+	pool: Arc<P>,
+	sync: Arc<dyn SyncOracle + Send + Sync>,
+	_marker: PhantomData<BE>,
+}
+
+impl<C, P, BE> Health<C, P, BE> {
+	/// Creates a new instance of the health RPC handler, reading the sync status from `sync`.
+	pub fn new(client: Arc<C>, pool: Arc<P>, sync: Arc<dyn SyncOracle + Send + Sync>) -> Self {
+		Self { client, pool, sync, _marker: Default::default() }
+	}
+}
+
+impl<C, P, BE> Health<C, P, BE>
+where
+	BE: Backend<Block> + 'static,
+	C: HeaderBackend<Block> + StorageProvider<Block, BE>,
+{
+	/// The storage value `item` of `pallet` in the state at `at`, if set.
+	fn read_value<T: Decode>(&self, at: H256, pallet: &[u8], item: &[u8]) -> RpcResult<Option<T>> {
+		let key = StorageKey([twox_128(pallet), twox_128(item)].concat());
+		let data = self.client.storage(at, &key).map_err(state_error_into_rpc_err)?;
+		data.map(|data| T::decode(&mut &data.0[..])).transpose().map_err(state_error_into_rpc_err)
+	}
+}
+
+impl<C, P, BE> HealthApiServer for Health<C, P, BE>
+where
+	BE: Backend<Block> + 'static,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + StorageProvider<Block, BE>,
+	C: Send + Sync + 'static,
+	C::Api: DifficultyRuntimeApi<Block>,
+	P: TransactionPool<Block = Block, Hash = H256> + 'static,
+{
+	fn utxo_health(&self) -> RpcResult<UtxoHealth> {
+		let info = self.client.info();
+		let at = info.best_hash;
+		let mut problems = Vec::new();
+
+		let syncing = self.sync.is_major_syncing();
+		if syncing {
+			problems.push("the node is syncing".to_string());
+		}
+		let has_peers = !self.sync.is_offline();
+		if !has_peers {
+			problems.push("the node has no peers".to_string());
+		}
+
+		let value = |item: &[u8]| -> RpcResult<Value> {
+			Ok(self.read_value(at, b"Utxo", item)?.unwrap_or_default())
+		};
+		let utxo_value = value(b"UtxoTotalValue")?;
+		let unpaid = value(b"PendingRewardTotal")?.saturating_add(value(b"RewardTotal")?);
+		let expected_supply = value(b"TotalIssuance")?.saturating_sub(unpaid);
+		if utxo_value != expected_supply {
+			problems.push(format!(
+				"the UTXO set holds {utxo_value} instead of the {expected_supply} issued"
+			));
+		}
+
+		let api = self.client.runtime_api();
+		let difficulty = api.difficulty(at).map_err(state_error_into_rpc_err)?;
+		let header = self.client.header(at).map_err(state_error_into_rpc_err)?;
+		// Genesis was not retargeted at
+		let parent = header
+			.filter(|header| *header.number() > 0)
+			.map(|header| *header.parent_hash());
+		let retarget_delta_ppm = match parent {
+			Some(parent) => {
+				let before = api.difficulty(parent).map_err(state_error_into_rpc_err)?;
+				change_ppm(before, difficulty)
+			},
+			None => None,
+		};
+
+		let target_block_time = api
+			.network_hashrate(at, u32::MAX)
+			.map_err(state_error_into_rpc_err)?
+			.map_or(u128::from(MILLISECS_PER_BLOCK), |estimate| estimate.target_block_time);
+		let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64);
+		let since_last_block = self
+			.read_value::<u64>(at, b"Timestamp", b"Now")?
+			.filter(|timestamp| *timestamp > 0)
+			.map(|timestamp| now.saturating_sub(timestamp));
+		let stale_after = target_block_time.saturating_mul(STALE_BLOCK_TIMES.into());
+		if since_last_block.is_some_and(|since| u128::from(since) > stale_after) {
+			problems.push(format!("the best block is older than {stale_after} ms"));
+		}
+
+		let status = self.pool.status();
+		Ok(UtxoHealth {
+			healthy: problems.is_empty(),
+			problems,
+			best_number: info.best_number,
+			best_hash: at,
+			finalized_number: info.finalized_number,
+			syncing,
+			has_peers,
+			utxo_value,
+			expected_supply,
+			difficulty,
+			retarget_delta_ppm,
+			since_last_block,
+			target_block_time,
+			pool_ready: status.ready,
+			pool_ready_bytes: status.ready_bytes,
+			pool_future: status.future,
+		})
+	}
+}
+
+/// The change from `from` to `to`, in parts per million of `from`, saturating.
+fn change_ppm(from: U256, to: U256) -> Option<i64> {
+	if from.is_zero() {
+		return None
+	}
+	let (change, sign) = if to >= from { (to - from, 1) } else { (from - to, -1) };
+	let ppm = change.saturating_mul(U256::from(1_000_000u32)) / from;
+	Some(sign * ppm.min(U256::from(i64::MAX)).low_u64() as i64)
+}
+
+fn state_error_into_rpc_err(err: impl std::fmt::Debug) -> ErrorObjectOwned {
+	ErrorObject::owned(STATE_ERROR, "State error", Some(format!("{:?}", err)))
+}
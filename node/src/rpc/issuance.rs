@@ -0,0 +1,94 @@
+//! RPC interface for the block reward schedule.
+
+use std::{marker::PhantomData, sync::Arc};
+
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::error::{ErrorObject, ErrorObjectOwned},
+};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::Block as BlockT;
+use tumuchain_runtime::{
+	apis::{IssuanceApi as IssuanceRuntimeApi, RewardApi as RewardRuntimeApi},
+	Balance, BlockNumber,
+};
+
+/// Error code returned when the runtime API call itself fails.
+const RUNTIME_ERROR: i32 = 1;
+
+/// Emission schedule RPC methods.
+#[rpc(server)]
+pub trait IssuanceApi<BlockHash> {
+	/// The issuance awarded to the author of `block_number`.
+	#[method(name = "issuance_rewardAt")]
+	fn reward_at(&self, block_number: BlockNumber, at: Option<BlockHash>) -> RpcResult<Balance>;
+
+	/// The first block after `block_number` at which the issuance is halved, if any.
+	#[method(name = "issuance_nextHalvingBlock")]
+	fn next_halving_block(
+		&self,
+		block_number: BlockNumber,
+		at: Option<BlockHash>,
+	) -> RpcResult<Option<BlockNumber>>;
+
+	/// The reward of the author of the block following `at`: the fees collected so far plus its
+	/// issuance. Fees of transactions still in the pool are not included.
+	#[method(name = "issuance_pendingReward")]
+	fn pending_reward(&self, at: Option<BlockHash>) -> RpcResult<Balance>;
+}
+
+/// Implements the [`IssuanceApiServer`] RPC trait on top of the runtime API.
+pub struct Issuance<C, Block> {
+	client: Arc<C>,
+	_marker: PhantomData<Block>,
+}
+
+impl<C, Block> Issuance<C, Block> {
+	/// Creates a new instance of the issuance RPC handler.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: Default::default() }
+	}
+}
+
+impl<C, Block> IssuanceApiServer<<Block as BlockT>::Hash> for Issuance<C, Block>
+where
+	Block: BlockT,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: IssuanceRuntimeApi<Block> + RewardRuntimeApi<Block>,
+{
+	fn reward_at(
+		&self,
+		block_number: BlockNumber,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Balance> {
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+		self.client
+			.runtime_api()
+			.reward_at(at, block_number)
+			.map_err(runtime_error_into_rpc_err)
+	}
+
+	fn next_halving_block(
+		&self,
+		block_number: BlockNumber,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Option<BlockNumber>> {
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+		self.client
+			.runtime_api()
+			.next_halving_block(at, block_number)
+			.map_err(runtime_error_into_rpc_err)
+	}
+
+	fn pending_reward(&self, at: Option<<Block as BlockT>::Hash>) -> RpcResult<Balance> {
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+		self.client.runtime_api().pending_reward(at).map_err(runtime_error_into_rpc_err)
+	}
+}
+
+/// Converts a runtime trap into an RPC error.
+fn runtime_error_into_rpc_err(err: impl std::fmt::Debug) -> ErrorObjectOwned {
+	ErrorObject::owned(RUNTIME_ERROR, "Runtime error", Some(format!("{:?}", err)))
+}
@@ -0,0 +1,185 @@
+//! RPC interface for what each author has mined.
+//!
+//! The counters are kept by the runtime as rewards are paid, so pool members can check the
+//! blocks and rewards a pool reports against the chain rather than against the pool.
+//!
+//! `miner_setPayoutKey` is unsafe, as it redirects the rewards of the blocks this node authors:
+//! expose it to the operator only. See [`crate::authoring::PayoutKey`]. So is
+//! `miner_generateBlocks`, which seals blocks on demand on nodes run with `--dev-instant-mine`.
+
+use std::{marker::PhantomData, sync::Arc};
+
+use jsonrpsee::{
+	core::{async_trait, RpcResult},
+	proc_macros::rpc,
+	types::error::{ErrorObject, ErrorObjectOwned},
+};
+use serde::{Deserialize, Serialize};
+use sc_rpc_api::DenyUnsafe;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::{sr25519, H256};
+use sp_runtime::traits::Block as BlockT;
+use tumuchain_runtime::apis::MinerStatsApi as MinerStatsRuntimeApi;
+use utxo::{AuthorStatistics, Value};
+
+use crate::{authoring::PayoutKey, instant_seal::BlockGenerator};
+
+/// Error code returned when the runtime API call itself fails.
+const RUNTIME_ERROR: i32 = 1;
+
+/// Error code returned when rotating the payout key of a node that does not author blocks.
+const NOT_AUTHORING_ERROR: i32 = 7;
+
+/// Error code returned when generating blocks on a node not run with `--dev-instant-mine`.
+const NOT_INSTANT_MINING_ERROR: i32 = 8;
+
+/// Error code returned when a block could not be sealed.
+const SEAL_ERROR: i32 = 9;
+
+/// Number of authors returned by `miner_leaderboard` when no limit is given.
+const DEFAULT_LEADERBOARD_SIZE: u32 = 20;
+
+/// What an author has mined.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MinerStats {
+	/// The pubkey the author's rewards were paid to
+	pub pubkey: H256,
+	/// Blocks of the chain whose reward went to the author
+	pub blocks_authored: u32,
+	/// Uncles of the author rewarded
+	pub uncles_rewarded: u32,
+	/// Block and uncle rewards earned, including those still being paid out
+	pub rewards_earned: Value,
+}
+
+impl From<(H256, AuthorStatistics)> for MinerStats {
+	fn from((pubkey, stats): (H256, AuthorStatistics)) -> Self {
+		Self {
+			pubkey,
+			blocks_authored: stats.blocks_authored,
+			uncles_rewarded: stats.uncles_rewarded,
+			rewards_earned: stats.rewards_earned,
+		}
+	}
+}
+
+/// Mining statistics and payout RPC methods.
+#[rpc(server)]
+pub trait MinerApi<BlockHash> {
+	/// What the author paid at `pubkey` has mined as of `at`, all zero for a key never paid.
+	#[method(name = "miner_authorStats")]
+	fn author_stats(&self, pubkey: H256, at: Option<BlockHash>) -> RpcResult<MinerStats>;
+
+	/// The `limit` authors, 20 by default, with the most blocks as of `at`, ties broken by the
+	/// rewards earned.
+	#[method(name = "miner_leaderboard")]
+	fn leaderboard(
+		&self,
+		limit: Option<u32>,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<MinerStats>>;
+
+	/// Pay the rewards of the blocks this node authors from now on to `pubkey`, or, with `null`,
+	/// to the `--author` or keystore key again. Returns the key now paid, `null` for the Aura key
+	/// authoring each block. The key is not persisted across restarts.
+	#[method(name = "miner_setPayoutKey")]
+	fn set_payout_key(&self, pubkey: Option<H256>) -> RpcResult<Option<H256>>;
+
+	/// Seal `n` blocks, one on top of the other, paying their rewards to `pubkey`, and return
+	/// their hashes once all are imported. Only on nodes run with `--dev-instant-mine`.
+	#[method(name = "miner_generateBlocks")]
+	async fn generate_blocks(&self, n: u32, pubkey: H256) -> RpcResult<Vec<H256>>;
+}
+
+/// Implements the [`MinerApiServer`] RPC trait on top of the runtime API.
+pub struct Miner<C, Block> {
+	client: Arc<C>,
+	payout_key: Option<PayoutKey>,
+	block_generator: Option<BlockGenerator>,
+	deny_unsafe: DenyUnsafe,
+	_marker: PhantomData<Block>,
+}
+
+impl<C, Block> Miner<C, Block> {
+	/// Creates a new instance of the mining RPC handler. `payout_key` is that of the proposer, if
+	/// the node authors blocks, and `block_generator` that of the instant sealing loop, if any.
+	pub fn new(
+		client: Arc<C>,
+		payout_key: Option<PayoutKey>,
+		block_generator: Option<BlockGenerator>,
+		deny_unsafe: DenyUnsafe,
+	) -> Self {
+		Self { client, payout_key, block_generator, deny_unsafe, _marker: Default::default() }
+	}
+}
+
+#[async_trait]
+impl<C, Block> MinerApiServer<<Block as BlockT>::Hash> for Miner<C, Block>
+where
+	Block: BlockT,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: MinerStatsRuntimeApi<Block>,
+{
+	fn author_stats(
+		&self,
+		pubkey: H256,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<MinerStats> {
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+		let stats = self
+			.client
+			.runtime_api()
+			.author_stats(at, pubkey)
+			.map_err(runtime_error_into_rpc_err)?;
+		Ok((pubkey, stats).into())
+	}
+
+	fn leaderboard(
+		&self,
+		limit: Option<u32>,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Vec<MinerStats>> {
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+		let authors = self
+			.client
+			.runtime_api()
+			.leaderboard(at, limit.unwrap_or(DEFAULT_LEADERBOARD_SIZE))
+			.map_err(runtime_error_into_rpc_err)?;
+		Ok(authors.into_iter().map(Into::into).collect())
+	}
+
+	fn set_payout_key(&self, pubkey: Option<H256>) -> RpcResult<Option<H256>> {
+		self.deny_unsafe.check_if_safe()?;
+		let payout_key = self.payout_key.as_ref().ok_or_else(|| {
+			ErrorObject::owned(
+				NOT_AUTHORING_ERROR,
+				"Not authoring",
+				Some("this node does not author blocks, start it with `--validator`"),
+			)
+		})?;
+		let paid = payout_key.set(pubkey.map(|pubkey| sr25519::Public::from_raw(pubkey.0)));
+		Ok(paid.map(|key| H256(key.0)))
+	}
+
+	async fn generate_blocks(&self, n: u32, pubkey: H256) -> RpcResult<Vec<H256>> {
+		self.deny_unsafe.check_if_safe()?;
+		let block_generator = self.block_generator.as_ref().ok_or_else(|| {
+			ErrorObject::owned(
+				NOT_INSTANT_MINING_ERROR,
+				"Not instant mining",
+				Some("blocks are only generated on request with `--dev-instant-mine`"),
+			)
+		})?;
+		block_generator
+			.generate(n, sr25519::Public::from_raw(pubkey.0))
+			.await
+			.map_err(|e| ErrorObject::owned(SEAL_ERROR, "Seal error", Some(e)))
+	}
+}
+
+/// Converts a runtime trap into an RPC error.
+fn runtime_error_into_rpc_err(err: impl std::fmt::Debug) -> ErrorObjectOwned {
+	ErrorObject::owned(RUNTIME_ERROR, "Runtime error", Some(format!("{:?}", err)))
+}
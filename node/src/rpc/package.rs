@@ -0,0 +1,238 @@
+//! RPC interface for submitting packages: transactions spending one another's outputs, submitted
+//! together.
+//!
+//! The runtime prices a transaction against the chain state only, so a child spending the
+//! outputs of a parent still in the pool is pooled with no priority, and a parent paying no fee
+//! is only kept by the fee of a child the pool cannot see. `author_submitPackage` works out the
+//! fee of each transaction from the outputs of the chain and of the package, records them in the
+//! [`PackageFees`] the admission policy of [`crate::mempool`] checks, and submits the
+//! transactions parent first. If the pool rejects one, those submitted before it are removed
+//! again: a package is pooled whole or not at all.
+//!
+//! The pool still imports each transaction on its own: when the ready queue is at its limits,
+//! the pool itself may turn away a parent paying no fee before its child arrives, and with it the
+//! package. The package as a whole has to pay `--min-relay-fee-rate`, which its transactions
+//! are then exempt from one by one.
+
+use std::{
+	collections::{HashMap, HashSet},
+	marker::PhantomData,
+	sync::Arc,
+};
+
+use codec::{Decode, Encode};
+use jsonrpsee::{
+	core::{async_trait, RpcResult},
+	proc_macros::rpc,
+	types::error::{ErrorObject, ErrorObjectOwned},
+};
+use sc_client_api::{Backend, StorageProvider};
+use sc_transaction_pool_api::{
+	error::{Error as PoolError, IntoPoolError},
+	TransactionPool, TransactionSource,
+};
+use serde::{Deserialize, Serialize};
+use sp_blockchain::HeaderBackend;
+use sp_core::{storage::StorageKey, Bytes, H256};
+use sp_runtime::{traits::SaturatedConversion, OpaqueExtrinsic};
+use tumuchain_runtime::{opaque::Block, RuntimeCall, UncheckedExtrinsic};
+use utxo::{Transaction, TransactionOutput, Value};
+
+use super::utxo::{decode_transaction, utxo_store_prefix};
+use crate::mempool::{virtual_size, RelayPolicy};
+
+/// Error code returned when the node's state cannot be read.
+const STATE_ERROR: i32 = 1;
+
+/// Error code returned when the transactions do not make a valid package.
+const INVALID_PACKAGE_ERROR: i32 = 10;
+
+/// Error code returned when the pool rejects a transaction of the package.
+const PACKAGE_REJECTED_ERROR: i32 = 11;
+
+/// Most transactions in a package.
+pub const MAX_PACKAGE_TRANSACTIONS: usize = 25;
+
+/// A package the pool accepted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmittedPackage {
+	/// Pool hashes of the transactions, in order
+	pub hashes: Vec<H256>,
+	/// Fee paid by each transaction
+	pub fees: Vec<Value>,
+	/// Fee paid by the whole package
+	pub fee: Value,
+	/// Encoded length of the package's extrinsics, in bytes
+	pub size: u32,
+}
+
+/// Package relay RPC methods.
+#[rpc(server, client)]
+pub trait PackageApi {
+	/// Submit `transactions`, SCALE-encoded transactions or extrinsics spending them, in order:
+	/// each spends outputs of the best block's state or of transactions before it. The pool
+	/// accepts all of them or none.
+	#[method(name = "author_submitPackage")]
+	async fn submit_package(&self, transactions: Vec<Bytes>) -> RpcResult<SubmittedPackage>;
+}
+
+/// Implements the [`PackageApiServer`] RPC trait for submitting packages to the pool.
+pub struct Package<C, P, BE> {
+	client: Arc<C>,
+	pool: Arc<P>,
+	policy: RelayPolicy,
+	_marker: PhantomData<BE>,
+}
+
+impl<C, P, BE> Package<C, P, BE> {
+	/// Creates a new instance of the package RPC handler, recording fees with the pool's
+	/// `policy`.
+	pub fn new(client: Arc<C>, pool: Arc<P>, policy: RelayPolicy) -> Self {
+		Self { client, pool, policy, _marker: Default::default() }
+	}
+}
+
+impl<C, P, BE> Package<C, P, BE>
+where
+	BE: Backend<Block> + 'static,
+	C: HeaderBackend<Block> + StorageProvider<Block, BE>,
+{
+	/// The fee of each of `transactions` on top of the state at `at`, checking that they spend
+	/// no output twice and none they do not have.
+	fn fees(&self, at: H256, transactions: &[Transaction]) -> RpcResult<Vec<Value>> {
+		let mut created = HashMap::new();
+		let mut spent = HashSet::new();
+		let mut fees = Vec::with_capacity(transactions.len());
+		for (n, transaction) in transactions.iter().enumerate() {
+			let mut input_value: Value = 0;
+			for input in &transaction.inputs {
+				if !spent.insert(input.outpoint) {
+					return Err(invalid_package(format!(
+						"transaction {n} spends {:?} twice in the package",
+						input.outpoint
+					)))
+				}
+				let value = match created.get(&input.outpoint) {
+					Some(value) => *value,
+					None => self
+						.read_output(at, &input.outpoint)?
+						.ok_or_else(|| {
+							invalid_package(format!(
+								"transaction {n} spends {:?}, which is neither unspent nor \
+								 created earlier in the package",
+								input.outpoint
+							))
+						})?
+						.value,
+				};
+				input_value = input_value.saturating_add(value);
+			}
+			let fee = input_value.checked_sub(transaction.output_value()).ok_or_else(|| {
+				invalid_package(format!("transaction {n} pays out more than it spends"))
+			})?;
+			created.extend(
+				transaction
+					.outputs
+					.iter()
+					.enumerate()
+					.map(|(index, output)| (transaction.outpoint(index as u64), output.value)),
+			);
+			fees.push(fee);
+		}
+		Ok(fees)
+	}
+
+	/// The unspent output at `outpoint` in the state at `at`.
+	fn read_output(&self, at: H256, outpoint: &H256) -> RpcResult<Option<TransactionOutput>> {
+		let key = StorageKey([utxo_store_prefix(), outpoint.as_bytes().to_vec()].concat());
+		let data = self.client.storage(at, &key).map_err(state_error_into_rpc_err)?;
+		data.map(|data| TransactionOutput::decode(&mut &data.0[..]))
+			.transpose()
+			.map_err(state_error_into_rpc_err)
+	}
+}
+
+#[async_trait]
+impl<C, P, BE> PackageApiServer for Package<C, P, BE>
+where
+	BE: Backend<Block> + 'static,
+	C: HeaderBackend<Block> + StorageProvider<Block, BE> + Send + Sync + 'static,
+	P: TransactionPool<Block = Block, Hash = H256> + 'static,
+{
+	async fn submit_package(&self, transactions: Vec<Bytes>) -> RpcResult<SubmittedPackage> {
+		if transactions.is_empty() || transactions.len() > MAX_PACKAGE_TRANSACTIONS {
+			return Err(invalid_package(format!(
+				"a package holds 1 to {MAX_PACKAGE_TRANSACTIONS} transactions, not {}",
+				transactions.len()
+			)))
+		}
+		let transactions = transactions
+			.iter()
+			.map(|raw| decode_transaction(raw))
+			.collect::<RpcResult<Vec<_>>>()?;
+		let at = self.client.info().best_hash;
+		let fees = self.fees(at, &transactions)?;
+
+		let mut extrinsics = Vec::with_capacity(transactions.len());
+		let mut recorded = Vec::with_capacity(transactions.len());
+		for (transaction, fee) in transactions.into_iter().zip(&fees) {
+			let call = RuntimeCall::Utxo(utxo::Call::spend { transaction });
+			let extrinsic = UncheckedExtrinsic::new_unsigned(call);
+			let extrinsic = OpaqueExtrinsic::decode(&mut &extrinsic.encode()[..])
+				.map_err(state_error_into_rpc_err)?;
+			recorded.push((self.pool.hash_of(&extrinsic), (*fee).saturated_into::<u64>()));
+			extrinsics.push(extrinsic);
+		}
+		let hashes: Vec<_> = recorded.iter().map(|(hash, _)| *hash).collect();
+		let size = extrinsics.iter().map(virtual_size).sum::<usize>();
+		let fee = recorded.iter().fold(0u64, |total, (_, fee)| total.saturating_add(*fee));
+		if !self.policy.admits(fee, size) {
+			return Err(invalid_package(format!(
+				"the package pays less than the minimum fee rate of {} per byte",
+				self.policy.min_fee_rate()
+			)))
+		}
+
+		// Recorded first, so neither admission nor eviction prices the package by the pool alone
+		self.policy.package_fees().record(recorded);
+		let mut submitted = Vec::new();
+		for (n, extrinsic) in extrinsics.into_iter().enumerate() {
+			match self.pool.submit_one(at, TransactionSource::External, extrinsic).await {
+				Ok(hash) => submitted.push(hash),
+				Err(e) => match e.into_pool_error() {
+					// Pooled on its own before, and left to the pool
+					Ok(PoolError::AlreadyImported(_)) => {},
+					e => {
+						self.pool.remove_invalid(&submitted);
+						self.policy.package_fees().forget(&hashes);
+						let reason = match e {
+							Ok(e) => e.to_string(),
+							Err(e) => e.to_string(),
+						};
+						return Err(ErrorObject::owned(
+							PACKAGE_REJECTED_ERROR,
+							"Package rejected",
+							Some(format!("transaction {n}: {reason}")),
+						))
+					},
+				},
+			}
+		}
+
+		Ok(SubmittedPackage {
+			hashes,
+			fee: fees.iter().fold(0 as Value, |total, fee| total.saturating_add(*fee)),
+			fees,
+			size: size as u32,
+		})
+	}
+}
+
+fn invalid_package(reason: String) -> ErrorObjectOwned {
+	ErrorObject::owned(INVALID_PACKAGE_ERROR, "Invalid package", Some(reason))
+}
+
+fn state_error_into_rpc_err(err: impl std::fmt::Debug) -> ErrorObjectOwned {
+	ErrorObject::owned(STATE_ERROR, "State error", Some(format!("{:?}", err)))
+}
@@ -0,0 +1,176 @@
+//! RPC interface for inspecting the UTXO transactions waiting in the pool.
+//!
+//! `author_pendingExtrinsics` returns the pool's extrinsics as opaque bytes.
+//! `utxo_pendingTransactions` decodes the UTXO spends among them instead, with what operators act
+//! on: the outpoints each spends and the outputs it creates, its fee and fee rate, and how long it
+//! has been waiting.
+//!
+//! Fees are worked out from the outputs of the best block's state and of the other pooled
+//! transactions, so the fee of a child spending its parent's outputs is known while both wait.
+//! First-seen times are those the [`RelayPolicy`] noted when the node first validated each
+//! transaction, and are lost on restart.
+
+use std::{collections::HashMap, marker::PhantomData, sync::Arc};
+
+use codec::{Decode, Encode};
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::error::{ErrorObject, ErrorObjectOwned},
+};
+use sc_client_api::{Backend, StorageProvider};
+use sc_transaction_pool_api::{InPoolTransaction, TransactionPool};
+use serde::{Deserialize, Serialize};
+use sp_blockchain::HeaderBackend;
+use sp_core::{storage::StorageKey, H256};
+use tumuchain_runtime::{opaque::Block, UncheckedExtrinsic};
+use utxo::{Transaction, TransactionOutput, Value};
+
+use super::utxo::{spent_transaction, utxo_store_prefix, DecodedOutput};
+use crate::mempool::{virtual_size, RelayPolicy};
+
+/// Error code returned when the node's state cannot be read.
+const STATE_ERROR: i32 = 1;
+
+/// A UTXO transaction waiting in the pool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingTransaction {
+	/// Hash of the extrinsic in the pool, as `author_pendingExtrinsics` hashes it
+	pub hash: H256,
+	/// Identifier of the transaction, committing to everything but its signatures
+	pub txid: H256,
+	/// Whether it can go in the next block, rather than waiting for the outputs it spends
+	pub ready: bool,
+	/// Encoded length of the extrinsic, in bytes
+	pub size: u32,
+	/// Size fee rates are charged by, counting witness bytes at a discount
+	pub vsize: u32,
+	/// Input value minus output value, if every spent output is known
+	pub fee: Option<Value>,
+	/// Fee per byte of `vsize`, rounded down
+	pub fee_rate: Option<Value>,
+	/// Outpoints spent, in order
+	pub inputs: Vec<H256>,
+	/// Outputs created, in order
+	pub outputs: Vec<DecodedOutput>,
+	/// When the node first validated it, in milliseconds since the Unix epoch, if it did since
+	/// starting
+	pub first_seen: Option<u64>,
+}
+
+/// Pool inspection RPC methods.
+#[rpc(server, client)]
+pub trait PendingApi {
+	/// The UTXO transactions in the pool, those ready for the next block first in the order
+	/// blocks would take them.
+	#[method(name = "utxo_pendingTransactions")]
+	fn pending_transactions(&self) -> RpcResult<Vec<PendingTransaction>>;
+}
+
+/// Implements the [`PendingApiServer`] RPC trait for inspecting the pool.
+pub struct Pending<C, P, BE> {
+	client: Arc<C>,
+	pool: Arc<P>,
+	policy: RelayPolicy,
+	_marker: PhantomData<BE>,
+}
+
+impl<C, P, BE> Pending<C, P, BE> {
+	/// Creates a new instance of the pool inspection RPC handler, reading first-seen times from
+	/// the pool's `policy`.
+	pub fn new(client: Arc<C>, pool: Arc<P>, policy: RelayPolicy) -> Self {
+		Self { client, pool, policy, _marker: Default::default() }
+	}
+}
+
+impl<C, P, BE> Pending<C, P, BE>
+where
+	BE: Backend<Block> + 'static,
+	C: HeaderBackend<Block> + StorageProvider<Block, BE>,
+{
+	/// The unspent output at `outpoint` in the state at `at`.
+	fn read_output(&self, at: H256, outpoint: &H256) -> RpcResult<Option<TransactionOutput>> {
+		let key = StorageKey([utxo_store_prefix(), outpoint.as_bytes().to_vec()].concat());
+		let data = self.client.storage(at, &key).map_err(state_error_into_rpc_err)?;
+		data.map(|data| TransactionOutput::decode(&mut &data.0[..]))
+			.transpose()
+			.map_err(state_error_into_rpc_err)
+	}
+}
+
+impl<C, P, BE> PendingApiServer for Pending<C, P, BE>
+where
+	BE: Backend<Block> + 'static,
+	C: HeaderBackend<Block> + StorageProvider<Block, BE> + Send + Sync + 'static,
+	P: TransactionPool<Block = Block, Hash = H256> + 'static,
+{
+	fn pending_transactions(&self) -> RpcResult<Vec<PendingTransaction>> {
+		let ready = self.pool.ready().map(|tx| (*tx.hash(), tx.data().clone(), true));
+		let futures = self.pool.futures().into_iter();
+		let futures = futures.map(|tx| (*tx.hash(), tx.data().clone(), false));
+		let pooled: Vec<(H256, _, bool, Transaction)> = ready
+			.chain(futures)
+			.filter_map(|(hash, extrinsic, ready)| {
+				let decoded = UncheckedExtrinsic::decode(&mut &extrinsic.encode()[..]).ok()?;
+				Some((hash, extrinsic, ready, spent_transaction(decoded.function)?))
+			})
+			.collect();
+
+		// Outputs of pooled transactions, which their children spend
+		let created: HashMap<H256, Value> = pooled
+			.iter()
+			.flat_map(|(.., transaction)| {
+				transaction
+					.outputs
+					.iter()
+					.enumerate()
+					.map(|(index, output)| (transaction.outpoint(index as u64), output.value))
+			})
+			.collect();
+
+		let at = self.client.info().best_hash;
+		let mut pending = Vec::with_capacity(pooled.len());
+		for (hash, extrinsic, ready, transaction) in pooled {
+			let mut input_value = Some(0 as Value);
+			for input in &transaction.inputs {
+				let value = match created.get(&input.outpoint) {
+					Some(value) => Some(*value),
+					None => self.read_output(at, &input.outpoint)?.map(|output| output.value),
+				};
+				input_value =
+					input_value.zip(value).map(|(total, value)| total.saturating_add(value));
+			}
+			let fee = input_value.and_then(|input| input.checked_sub(transaction.output_value()));
+			let vsize = virtual_size(&extrinsic);
+			let txid = transaction.txid();
+			pending.push(PendingTransaction {
+				hash,
+				txid,
+				ready,
+				size: extrinsic.encoded_size() as u32,
+				vsize: vsize as u32,
+				fee,
+				fee_rate: fee.map(|fee| fee / vsize.max(1) as Value),
+				inputs: transaction.inputs.iter().map(|input| input.outpoint).collect(),
+				outputs: transaction
+					.outputs
+					.iter()
+					.enumerate()
+					.map(|(index, output)| DecodedOutput {
+						outpoint: Transaction::outpoint_of(&txid, index as u64),
+						value: output.value,
+						pubkey: output.pubkey,
+						memo: output.memo,
+					})
+					.collect(),
+				first_seen: self.policy.first_seen(&hash),
+			});
+		}
+		Ok(pending)
+	}
+}
+
+fn state_error_into_rpc_err(err: impl std::fmt::Debug) -> ErrorObjectOwned {
+	ErrorObject::owned(STATE_ERROR, "State error", Some(format!("{:?}", err)))
+}
@@ -0,0 +1,446 @@
+//! RPC interface for looking up unspent outputs and inspecting transactions.
+//!
+//! The runtime keeps no index of outputs by pubkey, so finding the outputs of a key scans the
+//! whole UTXO set in the node's state. That is fine for wallets sweeping or checking a handful
+//! of keys, not for serving an explorer.
+//!
+//! Inclusion proofs let light clients check that a transaction is in a block with only its
+//! header: a [`TxProof`] is a trie proof of the extrinsic against the header's extrinsics root,
+//! checked with [`verify_tx_proof`].
+
+use std::{marker::PhantomData, sync::Arc};
+
+use codec::{Decode, DecodeAll, Encode};
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::error::{ErrorObject, ErrorObjectOwned},
+};
+use sc_client_api::{AuxStore, Backend, BlockBackend, StorageProvider};
+use serde::{Deserialize, Serialize};
+use sp_blockchain::HeaderBackend;
+use sp_core::{hashing::twox_128, storage::StorageKey, Bytes, H256, H512};
+use sp_runtime::traits::{BlakeTwo256, Block as BlockT, Header as _};
+use sp_trie::{LayoutV0, LayoutV1, MemoryDB, TrieConfiguration, TrieDBMutBuilder, TrieMut};
+use tumuchain_runtime::{RuntimeCall, UncheckedExtrinsic};
+use utxo::{descriptor::Descriptor, script, Transaction, TransactionOutput, Value};
+
+use crate::spent_index::{self, TxLocation};
+
+/// Error code returned when the node's state cannot be read.
+const STATE_ERROR: i32 = 1;
+
+/// Error code returned when the bytes given do not decode to a transaction.
+pub(crate) const DECODE_ERROR: i32 = 2;
+
+/// Error code returned when the block body or state asked for was pruned by this node.
+pub(crate) const PRUNED_ERROR: i32 = 3;
+
+/// Error code returned when a lookup needs an index this node does not keep.
+const INDEX_DISABLED_ERROR: i32 = 4;
+
+/// A transaction broken down for inspection.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedTransaction {
+	/// Identifier committing to everything but the sigscripts and witnesses
+	pub txid: H256,
+	/// Identifier committing to the whole transaction
+	pub wtxid: H256,
+	/// Version byte, which the runtime rejects unless it is a `TransactionVersion` it knows
+	pub version: u8,
+	/// Encoded length in bytes
+	pub size: u32,
+	/// Size fee rates are charged by, with witness bytes counting for a `WITNESS_SCALE_FACTOR`th
+	pub vsize: u32,
+	/// The inputs, in order
+	pub inputs: Vec<DecodedInput>,
+	/// The outputs, in order
+	pub outputs: Vec<DecodedOutput>,
+	/// Input value minus output value, if every spent output is known
+	pub fee: Option<Value>,
+}
+
+/// An input with the output it spends, if that is still unspent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedInput {
+	/// The outpoint spent
+	pub outpoint: H256,
+	/// The signature, absent when another input's signature covers this one
+	pub sigscript: Option<H512>,
+	/// The output spent, unless it is unknown or already spent
+	pub previous_output: Option<TransactionOutput>,
+	/// The revealed script, for outputs locked to one
+	pub witness: Option<DecodedWitness>,
+}
+
+/// A revealed script and its interpretations.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedWitness {
+	/// The raw script
+	pub script: Bytes,
+	/// The output pubkey the script unlocks
+	pub script_hash: H256,
+	/// Assembly notation, or the reason the script does not parse
+	pub asm: String,
+	/// The descriptor of the spent output
+	pub descriptor: String,
+	/// Initial stack, bottom first
+	pub stack: Vec<Bytes>,
+}
+
+/// An output with the outpoint it will be created at.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedOutput {
+	/// Outpoint the output is created at, derived from the txid
+	pub outpoint: H256,
+	/// Value of the output
+	pub value: Value,
+	/// Key or script hash the output is locked to
+	pub pubkey: H256,
+	/// Memo attached by the payer
+	pub memo: Option<H256>,
+}
+
+/// Proof that an extrinsic spending a transaction is part of a block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxProof {
+	/// The block containing the transaction
+	pub block_hash: H256,
+	/// Position of the extrinsic in the block
+	pub index: u32,
+	/// The encoded extrinsic
+	pub extrinsic: Bytes,
+	/// Trie nodes linking the extrinsic to the header's extrinsics root
+	pub proof: Vec<Bytes>,
+}
+
+/// Unspent output RPC methods.
+#[rpc(server, client)]
+pub trait UtxoApi<BlockHash> {
+	/// All unspent outputs paying `pubkey`, with their outpoints.
+	///
+	/// Outputs created in a block become visible once it is finalized by the runtime, i.e. as of
+	/// the block itself.
+	#[method(name = "utxo_unspentOutputs")]
+	fn unspent_outputs(
+		&self,
+		pubkey: H256,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<(H256, TransactionOutput)>>;
+
+	/// The unspent output at `outpoint`, if there is one.
+	///
+	/// Like the other lookups taking `at`, fails with a pruned error for blocks whose state this
+	/// node no longer keeps.
+	#[method(name = "utxo_output")]
+	fn output(&self, outpoint: H256, at: Option<BlockHash>) -> RpcResult<Option<TransactionOutput>>;
+
+	/// Decode a SCALE-encoded transaction, or an extrinsic submitting one, resolving the
+	/// outputs it spends against the UTXO set.
+	#[method(name = "utxo_decodeRawTransaction")]
+	fn decode_raw_transaction(
+		&self,
+		raw: Bytes,
+		at: Option<BlockHash>,
+	) -> RpcResult<DecodedTransaction>;
+
+	/// Proof that the transaction `txid` is in block `block_hash`, to be checked against the
+	/// block's header with [`verify_tx_proof`]. `None` if the block is unknown or does not contain
+	/// the transaction, and a pruned error if this node discarded the block's body.
+	#[method(name = "utxo_getTxProof")]
+	fn get_tx_proof(&self, txid: H256, block_hash: BlockHash) -> RpcResult<Option<TxProof>>;
+
+	/// The transaction that spent the output at `outpoint`. `None` if the output is unspent,
+	/// unknown, or spent in a block that is not finalized yet.
+	///
+	/// Only served by nodes running with `--spent-index`.
+	#[method(name = "utxo_getSpendingTx")]
+	fn get_spending_tx(&self, outpoint: H256) -> RpcResult<Option<TxLocation>>;
+}
+
+/// Implements the [`UtxoApiServer`] RPC trait by reading the UTXO set from the node's state.
+pub struct Utxo<C, Block, BE> {
+	client: Arc<C>,
+	spent_index: bool,
+	_marker: PhantomData<(Block, BE)>,
+}
+
+impl<C, Block, BE> Utxo<C, Block, BE> {
+	/// Creates a new instance of the UTXO RPC handler, serving `utxo_getSpendingTx` if the node
+	/// keeps the spent index.
+	pub fn new(client: Arc<C>, spent_index: bool) -> Self {
+		Self { client, spent_index, _marker: Default::default() }
+	}
+}
+
+/// The storage key prefix of the runtime's `Utxo::UtxoStore` map.
+pub(crate) fn utxo_store_prefix() -> Vec<u8> {
+	[twox_128(b"Utxo"), twox_128(b"UtxoStore")].concat()
+}
+
+impl<C, Block, BE> UtxoApiServer<<Block as BlockT>::Hash> for Utxo<C, Block, BE>
+where
+	Block: BlockT<Hash = H256>,
+	BE: Backend<Block> + 'static,
+	C: HeaderBackend<Block> + BlockBackend<Block> + StorageProvider<Block, BE> + AuxStore,
+	C: Send + Sync + 'static,
+{
+	fn unspent_outputs(
+		&self,
+		pubkey: H256,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Vec<(H256, TransactionOutput)>> {
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+		let prefix = StorageKey(utxo_store_prefix());
+		let pairs = self
+			.client
+			.storage_pairs(at, Some(&prefix), None)
+			.map_err(|e| self.read_error(at, e))?;
+
+		// Keys are the prefix followed by the outpoint itself
+		Ok(pairs
+			.filter_map(|(key, data)| {
+				let outpoint = key.0.get(prefix.0.len()..).filter(|rest| rest.len() == 32)?;
+				let output = TransactionOutput::decode(&mut &data.0[..]).ok()?;
+				(output.pubkey == pubkey).then(|| (H256::from_slice(outpoint), output))
+			})
+			.collect())
+	}
+
+	fn output(
+		&self,
+		outpoint: H256,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Option<TransactionOutput>> {
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+		self.read_output(at, &outpoint)
+	}
+
+	fn decode_raw_transaction(
+		&self,
+		raw: Bytes,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<DecodedTransaction> {
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+		let transaction = decode_transaction(&raw)?;
+
+		let mut inputs = Vec::with_capacity(transaction.inputs.len());
+		let mut total_input = Some(0 as Value);
+		for input in transaction.inputs.iter() {
+			let previous_output = self.read_output(at, &input.outpoint)?;
+			total_input = total_input
+				.zip(previous_output.as_ref())
+				.and_then(|(total, output)| total.checked_add(output.value));
+			inputs.push(DecodedInput {
+				outpoint: input.outpoint,
+				sigscript: input.sigscript,
+				previous_output,
+				witness: input.witness.as_ref().map(|witness| DecodedWitness {
+					script: Bytes(witness.script.to_vec()),
+					script_hash: script::script_hash(&witness.script),
+					asm: script::disassemble(&witness.script)
+						.unwrap_or_else(|e| format!("invalid script: {:?}", e)),
+					descriptor: Descriptor::from_script(&witness.script).to_string(),
+					stack: witness.stack.iter().map(|item| Bytes(item.to_vec())).collect(),
+				}),
+			});
+		}
+
+		let txid = transaction.txid();
+		let outputs: Vec<DecodedOutput> = transaction
+			.outputs
+			.iter()
+			.enumerate()
+			.map(|(index, output)| DecodedOutput {
+				outpoint: Transaction::outpoint_of(&txid, index as u64),
+				value: output.value,
+				pubkey: output.pubkey,
+				memo: output.memo,
+			})
+			.collect();
+		let total_output = outputs
+			.iter()
+			.try_fold(0 as Value, |total, output| total.checked_add(output.value));
+
+		Ok(DecodedTransaction {
+			txid,
+			wtxid: transaction.wtxid(),
+			version: transaction.version,
+			size: transaction.encoded_size() as u32,
+			vsize: transaction.virtual_size(),
+			inputs,
+			outputs,
+			fee: total_input.zip(total_output).and_then(|(input, output)| input.checked_sub(output)),
+		})
+	}
+
+	fn get_tx_proof(&self, txid: H256, block_hash: H256) -> RpcResult<Option<TxProof>> {
+		let header = self.client.header(block_hash).map_err(state_error_into_rpc_err)?;
+		let Some(header) = header else { return Ok(None) };
+		let body = self.client.block_body(block_hash).map_err(state_error_into_rpc_err)?;
+		// Headers are kept for every block, bodies only as far back as `--blocks-pruning`
+		let Some(body) = body else { return Err(pruned_error("body", block_hash)) };
+
+		let extrinsics: Vec<Vec<u8>> = body.iter().map(Encode::encode).collect();
+		let Some(index) = extrinsics.iter().position(|extrinsic| {
+			UncheckedExtrinsic::decode_all(&mut &extrinsic[..])
+				.ok()
+				.and_then(|extrinsic| spent_transaction(extrinsic.function))
+				.is_some_and(|transaction| transaction.txid() == txid)
+		}) else {
+			return Ok(None)
+		};
+
+		let root = *header.extrinsics_root();
+		let proof = extrinsics_proof::<LayoutV1<BlakeTwo256>>(&extrinsics, index, &root)
+			.or_else(|| extrinsics_proof::<LayoutV0<BlakeTwo256>>(&extrinsics, index, &root))
+			.ok_or_else(|| {
+				state_error_into_rpc_err("block body does not match its extrinsics root")
+			})?;
+
+		Ok(Some(TxProof {
+			block_hash,
+			index: index as u32,
+			extrinsic: Bytes(extrinsics[index].clone()),
+			proof: proof.into_iter().map(Bytes).collect(),
+		}))
+	}
+
+	fn get_spending_tx(&self, outpoint: H256) -> RpcResult<Option<TxLocation>> {
+		if !self.spent_index {
+			return Err(index_disabled_error("--spent-index"))
+		}
+		spent_index::spending_tx(&*self.client, &outpoint).map_err(state_error_into_rpc_err)
+	}
+}
+
+impl<C, Block, BE> Utxo<C, Block, BE>
+where
+	Block: BlockT,
+	BE: Backend<Block> + 'static,
+	C: HeaderBackend<Block> + BlockBackend<Block> + StorageProvider<Block, BE> + Send + Sync + 'static,
+{
+	/// The unspent output at `outpoint` as of block `at`.
+	fn read_output(
+		&self,
+		at: <Block as BlockT>::Hash,
+		outpoint: &H256,
+	) -> RpcResult<Option<TransactionOutput>> {
+		let key = StorageKey([utxo_store_prefix(), outpoint.as_bytes().to_vec()].concat());
+		let data = self.client.storage(at, &key).map_err(|e| self.read_error(at, e))?;
+		data.map(|data| TransactionOutput::decode(&mut &data.0[..]).map_err(state_error_into_rpc_err))
+			.transpose()
+	}
+
+	/// Converts a failure to read the state at `at` into an RPC error, telling state discarded
+	/// by pruning apart from unknown blocks by whether the header is known.
+	fn read_error(
+		&self,
+		at: <Block as BlockT>::Hash,
+		err: sp_blockchain::Error,
+	) -> ErrorObjectOwned {
+		let header_known = matches!(self.client.header(at), Ok(Some(_)));
+		match err {
+			sp_blockchain::Error::UnknownBlock(_) if header_known => pruned_error("state", at),
+			err => state_error_into_rpc_err(err),
+		}
+	}
+}
+
+/// Decode `raw` as a bare transaction, or as an extrinsic calling `Utxo::spend`.
+pub(crate) fn decode_transaction(raw: &[u8]) -> RpcResult<Transaction> {
+	if let Ok(transaction) = Transaction::decode_all(&mut &raw[..]) {
+		return Ok(transaction)
+	}
+	match UncheckedExtrinsic::decode_all(&mut &raw[..]) {
+		Ok(extrinsic) => spent_transaction(extrinsic.function)
+			.ok_or_else(|| decode_error("extrinsic does not spend outputs")),
+		Err(_) => Err(decode_error("neither a transaction nor an extrinsic")),
+	}
+}
+
+/// The transaction submitted by `call`, if it is a `Utxo::spend`.
+pub(crate) fn spent_transaction(call: RuntimeCall) -> Option<Transaction> {
+	match call {
+		RuntimeCall::Utxo(utxo::Call::spend { transaction }) => Some(transaction),
+		_ => None,
+	}
+}
+
+/// Proof of the extrinsic at `index` in the ordered trie of `extrinsics` built with layout `L`,
+/// or `None` if that trie does not have the expected `root`.
+fn extrinsics_proof<L>(extrinsics: &[Vec<u8>], index: usize, root: &H256) -> Option<Vec<Vec<u8>>>
+where
+	L: TrieConfiguration<Hash = BlakeTwo256>,
+{
+	let mut db = MemoryDB::<BlakeTwo256>::default();
+	let mut built = H256::default();
+	{
+		let mut trie = TrieDBMutBuilder::<L>::new(&mut db, &mut built).build();
+		for (i, extrinsic) in extrinsics.iter().enumerate() {
+			trie.insert(&codec::Compact(i as u32).encode(), extrinsic).ok()?;
+		}
+	}
+	if built != *root {
+		return None
+	}
+	let key = codec::Compact(index as u32).encode();
+	sp_trie::generate_trie_proof::<L, _, _, _>(&db, built, &[key]).ok()
+}
+
+/// The transaction `proof` shows to be in a block whose header has `extrinsics_root`, or `None`
+/// if the proof does not hold.
+///
+/// The runtime version decides whether extrinsics are committed to with the V0 or the V1 trie
+/// layout, so a proof is accepted under either.
+pub fn verify_tx_proof(proof: &TxProof, extrinsics_root: &H256) -> Option<Transaction> {
+	let key = codec::Compact(proof.index).encode();
+	let items = [(key, Some(&proof.extrinsic.0))];
+	let nodes: Vec<Vec<u8>> = proof.proof.iter().map(|node| node.0.clone()).collect();
+	sp_trie::verify_trie_proof::<LayoutV1<BlakeTwo256>, _, _, _>(extrinsics_root, &nodes, &items)
+		.ok()
+		.or_else(|| {
+			sp_trie::verify_trie_proof::<LayoutV0<BlakeTwo256>, _, _, _>(
+				extrinsics_root,
+				&nodes,
+				&items,
+			)
+			.ok()
+		})?;
+
+	let extrinsic = UncheckedExtrinsic::decode_all(&mut &proof.extrinsic[..]).ok()?;
+	spent_transaction(extrinsic.function)
+}
+
+/// An RPC error for bytes that do not decode to a transaction.
+fn decode_error(reason: &str) -> ErrorObjectOwned {
+	ErrorObject::owned(DECODE_ERROR, "Decode error", Some(reason))
+}
+
+/// An RPC error for the `what` of block `at`, which this node pruned.
+fn pruned_error(what: &str, at: impl std::fmt::Debug) -> ErrorObjectOwned {
+	ErrorObject::owned(
+		PRUNED_ERROR,
+		"Pruned",
+		Some(format!("the {what} of block {at:?} was pruned by this node")),
+	)
+}
+
+/// An RPC error for a lookup in an index this node does not keep.
+fn index_disabled_error(flag: &str) -> ErrorObjectOwned {
+	ErrorObject::owned(
+		INDEX_DISABLED_ERROR,
+		"Index disabled",
+		Some(format!("this node does not keep the index, start it with `{flag}`")),
+	)
+}
+
+/// Converts a failure to read or decode the state into an RPC error.
+fn state_error_into_rpc_err(err: impl std::fmt::Debug) -> ErrorObjectOwned {
+	ErrorObject::owned(STATE_ERROR, "State error", Some(format!("{:?}", err)))
+}
@@ -0,0 +1,98 @@
+//! RPC interface for importing watch-only descriptors and following their rescans.
+//!
+//! `utxo_importAddress` is unsafe, as it makes the node scan the chain and keep histories on the
+//! caller's behalf: expose it to the custodian's services only. See [`crate::watch`].
+
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::error::{ErrorObject, ErrorObjectOwned},
+};
+use sc_rpc_api::DenyUnsafe;
+use sp_core::H256;
+use tumuchain_runtime::BlockNumber;
+use utxo::descriptor::Descriptor;
+
+use crate::watch::{WatchStatus, Watched, Watchlist};
+
+/// Error code returned when the node's auxiliary storage cannot be read or written.
+const STORAGE_ERROR: i32 = 1;
+
+/// Error code returned when the address is neither a descriptor nor a key.
+const INVALID_ADDRESS_ERROR: i32 = 5;
+
+/// Error code returned when asking for the history of a pubkey not imported.
+const NOT_WATCHED_ERROR: i32 = 6;
+
+/// Watch-only RPC methods.
+#[rpc(server, client)]
+pub trait WatchApi {
+	/// Watch `address`, a descriptor or a key as hex or SS58, from the next finalized block on.
+	/// With `rescan_from`, the blocks from that height are rescanned first, in the background.
+	#[method(name = "utxo_importAddress")]
+	fn import_address(
+		&self,
+		address: String,
+		rescan_from: Option<BlockNumber>,
+	) -> RpcResult<Watched>;
+
+	/// The imported descriptors, the progress of their rescans, and the last block tracked.
+	#[method(name = "utxo_rescanStatus")]
+	fn rescan_status(&self) -> RpcResult<WatchStatus>;
+
+	/// The transactions paying or spending from the imported `pubkey` found so far, oldest first,
+	/// with the numbers of their blocks.
+	#[method(name = "utxo_watchedHistory")]
+	fn watched_history(&self, pubkey: H256) -> RpcResult<Vec<(BlockNumber, H256)>>;
+}
+
+/// Implements the [`WatchApiServer`] RPC trait over the node's [`Watchlist`].
+pub struct Watch {
+	watchlist: Watchlist,
+	deny_unsafe: DenyUnsafe,
+}
+
+impl Watch {
+	/// Creates a new instance of the watch-only RPC handler.
+	pub fn new(watchlist: Watchlist, deny_unsafe: DenyUnsafe) -> Self {
+		Self { watchlist, deny_unsafe }
+	}
+}
+
+impl WatchApiServer for Watch {
+	fn import_address(
+		&self,
+		address: String,
+		rescan_from: Option<BlockNumber>,
+	) -> RpcResult<Watched> {
+		self.deny_unsafe.check_if_safe()?;
+		// A bare key is shorthand for its `pk` descriptor
+		let descriptor = if address.contains('(') {
+			address.parse::<Descriptor>()
+		} else {
+			format!("pk({})", address.trim()).parse()
+		};
+		let descriptor = descriptor.map_err(|e| {
+			ErrorObject::owned(INVALID_ADDRESS_ERROR, "Invalid address", Some(e.to_string()))
+		})?;
+		self.watchlist.import(&descriptor, rescan_from).map_err(storage_error)
+	}
+
+	fn rescan_status(&self) -> RpcResult<WatchStatus> {
+		Ok(self.watchlist.status())
+	}
+
+	fn watched_history(&self, pubkey: H256) -> RpcResult<Vec<(BlockNumber, H256)>> {
+		self.watchlist.history(&pubkey).map_err(storage_error)?.ok_or_else(|| {
+			ErrorObject::owned(
+				NOT_WATCHED_ERROR,
+				"Not watched",
+				Some(format!("{pubkey:?} was not imported with `utxo_importAddress`")),
+			)
+		})
+	}
+}
+
+fn storage_error(err: sp_blockchain::Error) -> ErrorObjectOwned {
+	ErrorObject::owned(STORAGE_ERROR, "Storage error", Some(err.to_string()))
+}
@@ -0,0 +1,345 @@
+//! JSON-RPC over HTTP with methods gated by role, so a node can expose a public endpoint while
+//! keeping its wallet and admin methods to those holding a token.
+//!
+//! Served when the node is started with `--rpc-gateway-port`, next to Substrate's own RPC server,
+//! whose port can then stay on localhost. Roles are ordered, each granting the methods of those
+//! below it:
+//!
+//! - `public`: chain and state queries, fee estimates, and broadcasting transactions
+//! - `wallet`: the history of the watchlist and the keys of the keystore
+//! - `admin`: every method, such as `miner_setPayoutKey` and `utxo_importAddress`
+//!
+//! A request bearing `Authorization: Bearer <token>` has the role of the `--rpc-token` it matches,
+//! and is refused if it matches none. Requests without a token have the `--rpc-local-role` when
+//! they come from localhost, and the `public` role otherwise. Methods not listed here, such as
+//! those added to the node later, need `admin`. Calls to methods beyond the role fail with code
+//! [`UNAUTHORIZED`], and `rpc_methods` lists those the role may call. Subscriptions need a
+//! WebSocket, so are served by Substrate's RPC server only.
+//!
+//! Submissions through [`SUBMIT_METHODS`] count against the client address's allowance in the
+//! [`SubmissionLimiter`] shared with the other servers taking transactions, whatever the role,
+//! and fail with code [`RATE_LIMITED`] beyond it.
+
+use std::{
+	collections::HashMap,
+	net::{IpAddr, SocketAddr},
+};
+
+use axum::{
+	extract::{ConnectInfo, State},
+	http::{header, HeaderMap, StatusCode},
+	response::{IntoResponse, Response},
+	routing::post,
+	Router,
+};
+use sc_service::RpcHandlers;
+use serde_json::{json, Value as Json};
+use sp_core::hashing::blake2_256;
+
+use crate::rate_limit::SubmissionLimiter;
+
+/// Error code of calls to methods beyond the caller's role.
+const UNAUTHORIZED: i32 = -32010;
+
+/// Error code of submissions beyond the client's allowance.
+const RATE_LIMITED: i32 = -32011;
+
+/// Error codes of JSON-RPC.
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+
+/// Methods of the `public` role.
+const PUBLIC_METHODS: &[&str] = &[
+	"rpc_methods",
+	"chain_getBlock",
+	"chain_getBlockHash",
+	"chain_getHead",
+	"chain_getFinalizedHead",
+	"chain_getFinalisedHead",
+	"chain_getHeader",
+	"chain_getRuntimeVersion",
+	"chain_getNetworkHashrate",
+	"state_call",
+	"state_callAt",
+	"state_getKeysPaged",
+	"state_getKeysPagedAt",
+	"state_getMetadata",
+	"state_getReadProof",
+	"state_getRuntimeVersion",
+	"state_getStorage",
+	"state_getStorageAt",
+	"state_getStorageHash",
+	"state_getStorageHashAt",
+	"state_getStorageSize",
+	"state_getStorageSizeAt",
+	"state_queryStorageAt",
+	"system_chain",
+	"system_chainType",
+	"system_health",
+	"system_name",
+	"system_properties",
+	"system_syncState",
+	"system_version",
+	"system_utxoHealth",
+	"system_accountNextIndex",
+	"account_nextIndex",
+	"payment_queryInfo",
+	"payment_queryFeeDetails",
+	"author_submitExtrinsic",
+	"author_submitPackage",
+	"author_pendingExtrinsics",
+	"issuance_rewardAt",
+	"issuance_nextHalvingBlock",
+	"issuance_pendingReward",
+	"miner_authorStats",
+	"miner_leaderboard",
+	"utxo_unspentOutputs",
+	"utxo_output",
+	"utxo_decodeRawTransaction",
+	"utxo_getTxProof",
+	"utxo_getSpendingTx",
+	"utxo_pendingTransactions",
+];
+
+/// Methods submitting transactions to the pool, limited per client address.
+const SUBMIT_METHODS: &[&str] = &["author_submitExtrinsic", "author_submitPackage"];
+
+/// Methods of the `wallet` role, on top of those of `public`.
+const WALLET_METHODS: &[&str] =
+	&["utxo_watchedHistory", "utxo_rescanStatus", "author_hasKey", "author_hasSessionKeys"];
+
+/// Permission tiers of the RPC methods, each granting the methods of those before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum Role {
+	/// Chain queries and broadcasts
+	Public,
+	/// The watchlist and the keystore, on top of `public`
+	Wallet,
+	/// Every method
+	Admin,
+}
+
+impl Role {
+	/// The role needed to call `method`.
+	pub fn of(method: &str) -> Self {
+		if PUBLIC_METHODS.contains(&method) {
+			Self::Public
+		} else if WALLET_METHODS.contains(&method) {
+			Self::Wallet
+		} else {
+			Self::Admin
+		}
+	}
+
+	fn name(self) -> &'static str {
+		match self {
+			Self::Public => "public",
+			Self::Wallet => "wallet",
+			Self::Admin => "admin",
+		}
+	}
+}
+
+/// Parse a `--rpc-token` argument, `<role>=<token>`.
+pub fn parse_token(arg: &str) -> Result<(Role, String), String> {
+	let (role, token) = arg.split_once('=').ok_or("expected <role>=<token>")?;
+	let role = <Role as clap::ValueEnum>::from_str(role, true)?;
+	if token.is_empty() {
+		return Err("the token is empty".into())
+	}
+	Ok((role, token.into()))
+}
+
+/// How requests are given their role.
+#[derive(Clone)]
+struct Roles {
+	/// The role of each token, by the hash of the token so it is not compared byte by byte
+	tokens: HashMap<[u8; 32], Role>,
+	local_role: Role,
+}
+
+impl Roles {
+	fn new(tokens: Vec<(Role, String)>, local_role: Role) -> Self {
+		let tokens = tokens.into_iter().map(|(role, token)| (blake2_256(token.as_bytes()), role));
+		Self { tokens: tokens.collect(), local_role }
+	}
+
+	/// The role of a request from `client` with the `authorization` header, `None` if it bears
+	/// an unknown token.
+	fn of(&self, authorization: Option<&str>, client: IpAddr) -> Option<Role> {
+		match authorization {
+			Some(value) => {
+				let token = value.strip_prefix("Bearer ")?;
+				self.tokens.get(&blake2_256(token.as_bytes())).copied()
+			},
+			None if client.is_loopback() => Some(self.local_role),
+			None => Some(Role::Public),
+		}
+	}
+}
+
+#[derive(Clone)]
+struct Gateway {
+	rpc: RpcHandlers,
+	roles: Roles,
+	submissions: SubmissionLimiter,
+}
+
+/// Serve the gateway on `addr` until the node shuts down, calling the methods of `rpc` and
+/// limiting submissions with `submissions`.
+pub async fn run(
+	addr: SocketAddr,
+	rpc: RpcHandlers,
+	tokens: Vec<(Role, String)>,
+	local_role: Role,
+	submissions: SubmissionLimiter,
+) {
+	let gateway = Gateway { rpc, roles: Roles::new(tokens, local_role), submissions };
+	let app = Router::new().route("/", post(call)).with_state(gateway);
+
+	let listener = match tokio::net::TcpListener::bind(addr).await {
+		Ok(listener) => listener,
+		Err(e) => {
+			log::error!(target: "rpc-gateway", "Cannot listen for RPC on {addr}: {e}");
+			return
+		},
+	};
+	log::info!(target: "rpc-gateway", "Role-gated RPC listening on {addr}");
+	// Requests without a token are trusted by the client's address
+	let app = app.into_make_service_with_connect_info::<SocketAddr>();
+	if let Err(e) = axum::serve(listener, app).await {
+		log::error!(target: "rpc-gateway", "Role-gated RPC on {addr} failed: {e}");
+	}
+}
+
+async fn call(
+	State(gateway): State<Gateway>,
+	ConnectInfo(client): ConnectInfo<SocketAddr>,
+	headers: HeaderMap,
+	body: String,
+) -> Response {
+	// A header that is not text matches no token
+	let authorization =
+		headers.get(header::AUTHORIZATION).map(|value| value.to_str().unwrap_or_default());
+	let Some(role) = gateway.roles.of(authorization, client.ip()) else {
+		return (StatusCode::UNAUTHORIZED, "unknown token").into_response()
+	};
+
+	let reply = match serde_json::from_str::<Json>(&body) {
+		Ok(Json::Array(requests)) if !requests.is_empty() => {
+			let mut replies = Vec::with_capacity(requests.len());
+			for request in requests {
+				replies.push(gateway.call(role, client.ip(), request).await);
+			}
+			Json::Array(replies)
+		},
+		Ok(Json::Array(_)) => error(Json::Null, INVALID_REQUEST, "empty batch"),
+		Ok(request) => gateway.call(role, client.ip(), request).await,
+		Err(e) => error(Json::Null, PARSE_ERROR, &e.to_string()),
+	};
+	([(header::CONTENT_TYPE, "application/json")], reply.to_string()).into_response()
+}
+
+impl Gateway {
+	/// The reply to `request` made by `client` with `role`.
+	async fn call(&self, role: Role, client: IpAddr, request: Json) -> Json {
+		let id = request.get("id").cloned().unwrap_or(Json::Null);
+		let Some(method) = request.get("method").and_then(Json::as_str) else {
+			return error(id, INVALID_REQUEST, "no method")
+		};
+		let needed = Role::of(method);
+		if needed > role {
+			let message = format!("{method} needs the {} role", needed.name());
+			return error(id, UNAUTHORIZED, &message)
+		}
+		if SUBMIT_METHODS.contains(&method) && !self.submissions.allow(client) {
+			return error(id, RATE_LIMITED, "too many submissions, try again later")
+		}
+
+		let mut reply = match self.rpc.rpc_query(&request.to_string()).await {
+			Ok((reply, _)) => serde_json::from_str(&reply).unwrap_or(Json::Null),
+			Err(e) => return error(id, INVALID_REQUEST, &e.to_string()),
+		};
+		if method == "rpc_methods" {
+			retain_methods(&mut reply, role);
+		}
+		reply
+	}
+}
+
+/// Leave in the reply to `rpc_methods` the methods `role` may call.
+fn retain_methods(reply: &mut Json, role: Role) {
+	if let Some(Json::Array(methods)) = reply.pointer_mut("/result/methods") {
+		methods.retain(|method| method.as_str().is_some_and(|m| Role::of(m) <= role));
+	}
+}
+
+fn error(id: Json, code: i32, message: &str) -> Json {
+	json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::net::{Ipv4Addr, Ipv6Addr};
+
+	#[test]
+	fn methods_need_the_role_they_are_listed_under() {
+		assert_eq!(Role::of("chain_getBlock"), Role::Public);
+		assert_eq!(Role::of("author_submitPackage"), Role::Public);
+		assert_eq!(Role::of("utxo_watchedHistory"), Role::Wallet);
+		assert_eq!(Role::of("author_hasKey"), Role::Wallet);
+		assert_eq!(Role::of("miner_setPayoutKey"), Role::Admin);
+		// Methods not listed, such as new ones, are kept to admins
+		assert_eq!(Role::of("author_rotateKeys"), Role::Admin);
+		assert_eq!(Role::of(""), Role::Admin);
+		assert!(Role::Public < Role::Wallet && Role::Wallet < Role::Admin);
+	}
+
+	#[test]
+	fn tokens_parse_as_role_and_secret() {
+		assert_eq!(parse_token("wallet=s3cret"), Ok((Role::Wallet, "s3cret".into())));
+		assert_eq!(parse_token("ADMIN=a=b"), Ok((Role::Admin, "a=b".into())));
+		assert!(parse_token("wallet").is_err());
+		assert!(parse_token("wallet=").is_err());
+		assert!(parse_token("root=s3cret").is_err());
+	}
+
+	#[test]
+	fn rpc_methods_lists_those_of_the_role() {
+		let methods = ["chain_getBlock", "utxo_watchedHistory", "miner_setPayoutKey"];
+		let reply = json!({ "jsonrpc": "2.0", "id": 1, "result": { "methods": methods } });
+		let listed = |role| {
+			let mut reply = reply.clone();
+			retain_methods(&mut reply, role);
+			reply["result"]["methods"].clone()
+		};
+
+		assert_eq!(listed(Role::Public), json!(["chain_getBlock"]));
+		assert_eq!(listed(Role::Wallet), json!(["chain_getBlock", "utxo_watchedHistory"]));
+		assert_eq!(listed(Role::Admin), json!(methods));
+		// Errors are passed on as they are
+		let mut failed = json!({ "jsonrpc": "2.0", "id": 1, "error": { "code": 1 } });
+		retain_methods(&mut failed, Role::Public);
+		assert_eq!(failed["error"]["code"], 1);
+	}
+
+	#[test]
+	fn requests_get_the_role_of_their_token_or_address() {
+		let tokens = vec![(Role::Admin, "root".into()), (Role::Wallet, "w".into())];
+		let roles = Roles::new(tokens, Role::Wallet);
+		let remote = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+		let local = IpAddr::V6(Ipv6Addr::LOCALHOST);
+
+		assert_eq!(roles.of(Some("Bearer root"), remote), Some(Role::Admin));
+		assert_eq!(roles.of(Some("Bearer w"), local), Some(Role::Wallet));
+		// A token is checked even from localhost, and must be a bearer token
+		assert_eq!(roles.of(Some("Bearer wrong"), local), None);
+		assert_eq!(roles.of(Some("root"), remote), None);
+		assert_eq!(roles.of(Some(""), remote), None);
+		// Without one, localhost gets the local role and everyone else the public one
+		assert_eq!(roles.of(None, local), Some(Role::Wallet));
+		assert_eq!(roles.of(None, IpAddr::V4(Ipv4Addr::LOCALHOST)), Some(Role::Wallet));
+		assert_eq!(roles.of(None, remote), Some(Role::Public));
+	}
+}
@@ -1,22 +1,33 @@
 //! Service and ServiceFactory implementation. Specialized wrapper over substrate service.
 
+use crate::{
+	cli::{
+		AuthoringParams, ElectrumParams, GrpcParams, IndexParams, MempoolParams, RestParams,
+		RosettaParams, RpcGatewayParams, UtxoCacheParams, ZmqParams,
+	},
+	import::{ParallelSignatureImport, SignatureCache},
+	mempool::{PolicyChainApi, RelayPolicy},
+	sigcache::HostFunctions,
+	utxo_cache::UtxoCache,
+};
 use futures::FutureExt;
-use sc_client_api::{Backend, BlockBackend};
+use sc_client_api::{Backend, BlockBackend, ExecutorProvider, UsageProvider};
 use sc_consensus_aura::{ImportQueueParams, SlotProportion, StartAuraParams};
 use sc_consensus_grandpa::SharedVoterState;
 use sc_service::{error::Error as ServiceError, Configuration, TaskManager, WarpSyncParams};
 use sc_telemetry::{Telemetry, TelemetryWorker};
-use sc_transaction_pool_api::OffchainTransactionPoolFactory;
 use tumuchain_runtime::{self, opaque::Block, RuntimeApi};
 use sp_consensus_aura::sr25519::AuthorityPair as AuraPair;
 use std::{sync::Arc, time::Duration};
 
-pub(crate) type FullClient = sc_service::TFullClient<
+pub(crate) type FullClient =
+	sc_service::TFullClient<Block, RuntimeApi, sc_executor::WasmExecutor<HostFunctions>>;
+pub(crate) type FullBackend = sc_service::TFullBackend<Block>;
+/// The transaction pool, admitting transactions by the runtime's rules and the node's policy.
+pub(crate) type FullPool = sc_transaction_pool::BasicPool<
+	crate::mempool::PolicyChainApi<sc_transaction_pool::FullChainApi<FullClient, Block>>,
 	Block,
-	RuntimeApi,
-	sc_executor::WasmExecutor<sp_io::SubstrateHostFunctions>,
 >;
-type FullBackend = sc_service::TFullBackend<Block>;
 type FullSelectChain = sc_consensus::LongestChain<FullBackend, Block>;
 
 /// The minimum period of blocks on which justifications will be
@@ -28,15 +39,20 @@ pub type Service = sc_service::PartialComponents<
 	FullBackend,
 	FullSelectChain,
 	sc_consensus::DefaultImportQueue<Block>,
-	sc_transaction_pool::FullPool<Block, FullClient>,
+	FullPool,
 	(
 		sc_consensus_grandpa::GrandpaBlockImport<FullBackend, Block, FullClient, FullSelectChain>,
 		sc_consensus_grandpa::LinkHalf<Block, FullClient, FullSelectChain>,
 		Option<Telemetry>,
+		RelayPolicy,
+		Arc<UtxoCache>,
 	),
 >;
 
-pub fn new_partial(config: &Configuration) -> Result<Service, ServiceError> {
+pub fn new_partial(
+	config: &Configuration,
+	utxo_cache: &UtxoCacheParams,
+) -> Result<Service, ServiceError> {
 	let telemetry = config
 		.telemetry_endpoints
 		.clone()
@@ -48,7 +64,7 @@ pub fn new_partial(config: &Configuration) -> Result<Service, ServiceError> {
 		})
 		.transpose()?;
 
-	let executor = sc_service::new_wasm_executor::<sp_io::SubstrateHostFunctions>(config);
+	let executor = sc_service::new_wasm_executor::<HostFunctions>(config);
 	let (client, backend, keystore_container, task_manager) =
 		sc_service::new_full_parts::<Block, RuntimeApi, _>(
 			config,
@@ -57,6 +73,9 @@ pub fn new_partial(config: &Configuration) -> Result<Service, ServiceError> {
 		)?;
 	let client = Arc::new(client);
 
+	let signature_cache = SignatureCache::default();
+	client.execution_extensions().set_extensions_factory(signature_cache.clone());
+
 	let telemetry = telemetry.map(|(worker, telemetry)| {
 		task_manager.spawn_handle().spawn("telemetry", None, worker.run());
 		telemetry
@@ -64,12 +83,30 @@ pub fn new_partial(config: &Configuration) -> Result<Service, ServiceError> {
 
 	let select_chain = sc_consensus::LongestChain::new(backend.clone());
 
-	let transaction_pool = sc_transaction_pool::BasicPool::new_full(
+	// As `BasicPool::new_full`, but with the node's admission policy, set by `new_full`
+	let policy = RelayPolicy::default();
+	let pool_api = sc_transaction_pool::FullChainApi::new(
+		client.clone(),
+		config.prometheus_registry(),
+		&task_manager.spawn_essential_handle(),
+	);
+	let utxos = Arc::new(UtxoCache::new(client.clone(), utxo_cache));
+	let chain_info = client.usage_info().chain;
+	let transaction_pool = Arc::new(sc_transaction_pool::BasicPool::with_revalidation_type(
 		config.transaction_pool.clone(),
 		config.role.is_authority().into(),
+		Arc::new(PolicyChainApi::new(pool_api, policy.clone(), utxos.clone())),
 		config.prometheus_registry(),
+		sc_transaction_pool::RevalidationType::Full,
 		task_manager.spawn_essential_handle(),
-		client.clone(),
+		chain_info.best_number,
+		chain_info.best_hash,
+		chain_info.finalized_hash,
+	));
+	task_manager.spawn_essential_handle().spawn(
+		"txpool-notifications",
+		Some("transaction-pool"),
+		sc_transaction_pool::notification_future(client.clone(), transaction_pool.clone()),
 	);
 
 	let (grandpa_block_import, grandpa_link) = sc_consensus_grandpa::block_import(
@@ -83,7 +120,11 @@ pub fn new_partial(config: &Configuration) -> Result<Service, ServiceError> {
 	let cidp_client = client.clone();
 	let import_queue =
 		sc_consensus_aura::import_queue::<AuraPair, _, _, _, _, _>(ImportQueueParams {
-			block_import: grandpa_block_import.clone(),
+			block_import: ParallelSignatureImport::new(
+				grandpa_block_import.clone(),
+				utxos.clone(),
+				signature_cache,
+			),
 			justification_import: Some(Box::new(grandpa_block_import.clone())),
 			client: client.clone(),
 			create_inherent_data_providers: move |parent_hash, _| {
@@ -119,16 +160,35 @@ pub fn new_partial(config: &Configuration) -> Result<Service, ServiceError> {
 		keystore_container,
 		select_chain,
 		transaction_pool,
-		other: (grandpa_block_import, grandpa_link, telemetry),
+		other: (grandpa_block_import, grandpa_link, telemetry, policy, utxos),
 	})
 }
 
 /// Builds a new service for a full client.
+#[allow(clippy::too_many_arguments)]
 pub fn new_full<
 	N: sc_network::NetworkBackend<Block, <Block as sp_runtime::traits::Block>::Hash>,
 >(
 	config: Configuration,
+	mempool: MempoolParams,
+	authoring: AuthoringParams,
+	utxo_cache: UtxoCacheParams,
+	index: IndexParams,
+	grpc: GrpcParams,
+	rest: RestParams,
+	rosetta: RosettaParams,
+	electrum: ElectrumParams,
+	zmq: ZmqParams,
+	rpc_gateway: RpcGatewayParams,
 ) -> Result<TaskManager, ServiceError> {
+	if authoring.dev_instant_mine &&
+		config.chain_spec.chain_type() != sc_service::ChainType::Development
+	{
+		return Err(ServiceError::Other(
+			"`--dev-instant-mine` is for development chains only".into(),
+		))
+	}
+
 	let sc_service::PartialComponents {
 		client,
 		backend,
@@ -137,8 +197,8 @@ pub fn new_full<
 		keystore_container,
 		select_chain,
 		transaction_pool,
-		other: (block_import, grandpa_link, mut telemetry),
-	} = new_partial(&config)?;
+		other: (block_import, grandpa_link, mut telemetry, policy, utxos),
+	} = new_partial(&config, &utxo_cache)?;
 
 	let mut net_config = sc_network::config::FullNetworkConfiguration::<
 		Block,
@@ -166,12 +226,20 @@ pub fn new_full<
 		Vec::default(),
 	));
 
+	// The network imports the transactions peers relay through the relay pool, which scores them
+	let relay_metrics = config
+		.prometheus_registry()
+		.map(crate::relay::RelayMetrics::register)
+		.transpose()?;
+	let relay_pool =
+		Arc::new(crate::relay::RelayPool::new(transaction_pool.clone(), relay_metrics.clone()));
+
 	let (network, system_rpc_tx, tx_handler_controller, network_starter, sync_service) =
 		sc_service::build_network(sc_service::BuildNetworkParams {
 			config: &config,
 			net_config,
 			client: client.clone(),
-			transaction_pool: transaction_pool.clone(),
+			transaction_pool: relay_pool,
 			spawn_handle: task_manager.spawn_handle(),
 			import_queue,
 			block_announce_validator_builder: None,
@@ -189,9 +257,9 @@ pub fn new_full<
 				is_validator: config.role.is_authority(),
 				keystore: Some(keystore_container.keystore()),
 				offchain_db: backend.offchain_storage(),
-				transaction_pool: Some(OffchainTransactionPoolFactory::new(
-					transaction_pool.clone(),
-				)),
+				// No pallet submits transactions from offchain workers, and only the stock
+				// `FullPool` takes local submissions
+				transaction_pool: None,
 				network_provider: Arc::new(network.clone()),
 				enable_http_requests: true,
 				custom_extensions: |_| vec![],
@@ -201,6 +269,114 @@ pub fn new_full<
 		);
 	}
 
+	task_manager.spawn_handle().spawn(
+		"relay-banning",
+		Some("networking"),
+		crate::relay::run_banning(
+			network.clone(),
+			sync_service.clone(),
+			mempool.relay_ban_after,
+			relay_metrics,
+		),
+	);
+
+	// Shared by the servers taking submissions from addresses they know
+	let submissions =
+		crate::rate_limit::SubmissionLimiter::new(mempool.submit_rate_limit, mempool.submit_burst);
+
+	policy.set_min_fee_rate(mempool.min_relay_fee_rate);
+	policy.set_aging_rate(mempool.pool_priority_aging);
+	policy.set_require_canonical_order(mempool.require_canonical_order);
+	task_manager.spawn_handle().spawn(
+		"mempool-eviction",
+		Some("transaction-pool"),
+		crate::mempool::run_eviction(
+			client.clone(),
+			transaction_pool.clone(),
+			mempool,
+			config.transaction_pool.ready.total_bytes,
+			policy.clone(),
+			utxos,
+		),
+	);
+
+	if let Some(telemetry) = &telemetry {
+		task_manager.spawn_handle().spawn_blocking(
+			"chain-telemetry",
+			None,
+			crate::chain_telemetry::run(
+				client.clone(),
+				transaction_pool.clone(),
+				telemetry.handle(),
+			),
+		);
+	}
+
+	if index.spent_index {
+		task_manager.spawn_handle().spawn_blocking(
+			"spent-index",
+			None,
+			crate::spent_index::run(client.clone()),
+		);
+	}
+
+	let watchlist = crate::watch::Watchlist::new(client.clone())?;
+	task_manager
+		.spawn_handle()
+		.spawn_blocking("watch-only", None, crate::watch::run(watchlist.clone()));
+
+	if let Some(addr) = grpc.addr() {
+		task_manager.spawn_handle().spawn(
+			"grpc-server",
+			None,
+			crate::grpc::run(addr, client.clone(), transaction_pool.clone(), submissions.clone()),
+		);
+	}
+
+	if let Some(addr) = rest.addr() {
+		task_manager.spawn_handle().spawn(
+			"rest-gateway",
+			None,
+			crate::rest::run(addr, client.clone(), index.spent_index),
+		);
+	}
+
+	if let Some(addr) = rosetta.addr() {
+		task_manager.spawn_handle().spawn(
+			"rosetta",
+			None,
+			crate::rosetta::run(
+				addr,
+				client.clone(),
+				transaction_pool.clone(),
+				submissions.clone(),
+				config.chain_spec.name().to_string(),
+			),
+		);
+	}
+
+	if let Some(addr) = electrum.addr() {
+		task_manager.spawn_handle().spawn(
+			"electrum",
+			None,
+			crate::electrum::run(
+				addr,
+				client.clone(),
+				transaction_pool.clone(),
+				submissions.clone(),
+				policy.clone(),
+			),
+		);
+	}
+
+	if let Some(addr) = zmq.addr() {
+		task_manager.spawn_handle().spawn(
+			"zmq",
+			None,
+			crate::zmq::run(addr, client.clone(), transaction_pool.clone()),
+		);
+	}
+
 	let role = config.role.clone();
 	let force_authoring = config.force_authoring;
 	let backoff_authoring_blocks: Option<()> = None;
@@ -208,18 +384,46 @@ pub fn new_full<
 	let enable_grandpa = !config.disable_grandpa;
 	let prometheus_registry = config.prometheus_registry().cloned();
 
+	// Only authorities pay rewards, so only theirs can be rotated over RPC
+	let payout_key = role.is_authority().then(|| {
+		crate::authoring::PayoutKey::new(authoring.clone(), keystore_container.keystore())
+	});
+	// Blocks are generated on request by the instant sealing loop only
+	let (block_generator, seal_commands) = match &payout_key {
+		Some(payout_key) if authoring.dev_instant_mine => {
+			let (generator, commands) =
+				crate::instant_seal::BlockGenerator::new(payout_key.clone());
+			(Some(generator), Some(commands))
+		},
+		_ => (None, None),
+	};
+
 	let rpc_extensions_builder = {
 		let client = client.clone();
 		let pool = transaction_pool.clone();
+		let watchlist = watchlist.clone();
+		let payout_key = payout_key.clone();
+		let block_generator = block_generator.clone();
+		let policy = policy.clone();
+		let sync = sync_service.clone();
 
 		Box::new(move |deny_unsafe, _| {
-			let deps =
-				crate::rpc::FullDeps { client: client.clone(), pool: pool.clone(), deny_unsafe };
+			let deps = crate::rpc::FullDeps {
+				client: client.clone(),
+				pool: pool.clone(),
+				sync: sync.clone(),
+				deny_unsafe,
+				spent_index: index.spent_index,
+				watchlist: watchlist.clone(),
+				payout_key: payout_key.clone(),
+				block_generator: block_generator.clone(),
+				policy: policy.clone(),
+			};
 			crate::rpc::create_full(deps).map_err(Into::into)
 		})
 	};
 
-	let _rpc_handlers = sc_service::spawn_tasks(sc_service::SpawnTasksParams {
+	let rpc_handlers = sc_service::spawn_tasks(sc_service::SpawnTasksParams {
 		network: Arc::new(network.clone()),
 		client: client.clone(),
 		keystore: keystore_container.keystore(),
@@ -234,52 +438,89 @@ pub fn new_full<
 		telemetry: telemetry.as_mut(),
 	})?;
 
-	if role.is_authority() {
-		let proposer_factory = sc_basic_authorship::ProposerFactory::new(
-			task_manager.spawn_handle(),
-			client.clone(),
-			transaction_pool.clone(),
-			prometheus_registry.as_ref(),
-			telemetry.as_ref().map(|x| x.handle()),
+	if let Some(addr) = rpc_gateway.addr() {
+		task_manager.spawn_handle().spawn(
+			"rpc-gateway",
+			None,
+			crate::rpc_gateway::run(
+				addr,
+				rpc_handlers,
+				rpc_gateway.rpc_tokens,
+				rpc_gateway.rpc_local_role,
+				submissions,
+			),
 		);
+	}
 
-		let slot_duration = sc_consensus_aura::slot_duration(&*client)?;
-
-		let aura = sc_consensus_aura::start_aura::<AuraPair, _, _, _, _, _, _, _, _, _, _>(
-			StartAuraParams {
-				slot_duration,
-				client,
-				select_chain,
-				block_import,
-				proposer_factory,
-				create_inherent_data_providers: move |_, ()| async move {
-					let timestamp = sp_timestamp::InherentDataProvider::from_system_time();
+	if let Some(payout_key) = payout_key {
+		payout_key.announce();
+		let proposer_factory = crate::authoring::AuthoringEnvironment::new(
+			sc_basic_authorship::ProposerFactory::new(
+				task_manager.spawn_handle(),
+				client.clone(),
+				transaction_pool.clone(),
+				prometheus_registry.as_ref(),
+				telemetry.as_ref().map(|x| x.handle()),
+			),
+			authoring.block_length_signal,
+			authoring.upgrade_signal,
+			payout_key,
+		);
 
-					let slot =
-						sp_consensus_aura::inherents::InherentDataProvider::from_timestamp_and_slot_duration(
-							*timestamp,
-							slot_duration,
-						);
+		let slot_duration = sc_consensus_aura::slot_duration(&*client)?;
 
-					Ok((slot, timestamp))
+		if let Some(seal_commands) = seal_commands {
+			log::info!("⚡ Sealing a block for every transaction imported into the pool");
+			task_manager.spawn_essential_handle().spawn_blocking(
+				"instant-seal",
+				Some("block-authoring"),
+				crate::instant_seal::run(
+					client.clone(),
+					transaction_pool.clone(),
+					select_chain,
+					block_import,
+					proposer_factory,
+					slot_duration,
+					seal_commands,
+				),
+			);
+		} else {
+			let aura = sc_consensus_aura::start_aura::<AuraPair, _, _, _, _, _, _, _, _, _, _>(
+				StartAuraParams {
+					slot_duration,
+					client,
+					select_chain,
+					block_import,
+					proposer_factory,
+					create_inherent_data_providers: move |_, ()| async move {
+						let timestamp = sp_timestamp::InherentDataProvider::from_system_time();
+
+						let slot =
+							sp_consensus_aura::inherents::InherentDataProvider::from_timestamp_and_slot_duration(
+								*timestamp,
+								slot_duration,
+							);
+
+						Ok((slot, timestamp))
+					},
+					force_authoring,
+					backoff_authoring_blocks,
+					keystore: keystore_container.keystore(),
+					sync_oracle: sync_service.clone(),
+					justification_sync_link: sync_service.clone(),
+					block_proposal_slot_portion: SlotProportion::new(2f32 / 3f32),
+					max_block_proposal_slot_portion: None,
+					telemetry: telemetry.as_ref().map(|x| x.handle()),
+					compatibility_mode: Default::default(),
 				},
-				force_authoring,
-				backoff_authoring_blocks,
-				keystore: keystore_container.keystore(),
-				sync_oracle: sync_service.clone(),
-				justification_sync_link: sync_service.clone(),
-				block_proposal_slot_portion: SlotProportion::new(2f32 / 3f32),
-				max_block_proposal_slot_portion: None,
-				telemetry: telemetry.as_ref().map(|x| x.handle()),
-				compatibility_mode: Default::default(),
-			},
-		)?;
-
-		// the AURA authoring task is considered essential, i.e. if it
-		// fails we take down the service with it.
-		task_manager
-			.spawn_essential_handle()
-			.spawn_blocking("aura", Some("block-authoring"), aura);
+			)?;
+
+			// the AURA authoring task is considered essential, i.e. if it
+			// fails we take down the service with it.
+			task_manager
+				.spawn_essential_handle()
+				.spawn_blocking("aura", Some("block-authoring"), aura);
+		}
 	}
 
 	if enable_grandpa {
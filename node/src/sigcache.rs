@@ -0,0 +1,86 @@
+//! Spend signatures checked by the node ahead of block execution.
+//!
+//! The runtime verifies sigscripts through the standard `sp_io::crypto::sr25519_verify`. The
+//! executor of this node replaces that host function with [`crypto::sr25519_verify`], which
+//! looks a signature up in the [`VerifiedSignatures`] extension before verifying it itself. The
+//! [`import`](crate::import) of a block fills the extension on all cores beforehand.
+//!
+//! The outcome does not depend on the extension: it only holds signatures that
+//! `sr25519::Pair::verify` accepted, which is what the host function falls back to, as Substrate's
+//! does. The runtime and the host API are unchanged, so other clients execute blocks as usual,
+//! only slower.
+
+use sp_core::{
+	sr25519::{Pair, Public, Signature},
+	Pair as _, H256,
+};
+use sp_externalities::ExternalitiesExt;
+use sp_runtime::traits::{BlakeTwo256, Hash};
+use sp_runtime_interface::{runtime_interface, sp_wasm_interface::ExtendedHostFunctions};
+
+sp_externalities::decl_extension! {
+	/// Signatures already verified valid, by [`cache_key`].
+	pub struct VerifiedSignatures(std::sync::Arc<std::collections::HashSet<H256>>);
+}
+
+/// Host functions of the runtime: Substrate's, `sr25519_verify` consulting
+/// [`VerifiedSignatures`] in place of theirs.
+pub(crate) type HostFunctions =
+	ExtendedHostFunctions<sp_io::SubstrateHostFunctions, crypto::HostFunctions>;
+
+/// The entry of [`VerifiedSignatures`] recording that `signature` of `message` by `pubkey` is
+/// valid.
+pub fn cache_key(signature: &Signature, message: &[u8], pubkey: &Public) -> H256 {
+	BlakeTwo256::hash_of(&(signature, message, pubkey))
+}
+
+/// The `sr25519_verify` host functions of `sp_io::crypto`, under the same names.
+#[runtime_interface]
+pub trait Crypto {
+	/// Whether `sig` is a valid sr25519 signature of `msg` by `pubkey`, of either signature
+	/// version, as Substrate's version 1.
+	fn sr25519_verify(sig: &Signature, msg: &[u8], pubkey: &Public) -> bool {
+		Pair::verify_deprecated(sig, msg, pubkey)
+	}
+
+	/// Whether `sig` is a valid sr25519 signature of `msg` by `pubkey`, as Substrate's version 2,
+	/// short-circuited by [`VerifiedSignatures`].
+	#[version(2)]
+	fn sr25519_verify(&mut self, sig: &Signature, msg: &[u8], pubkey: &Public) -> bool {
+		let cached = self
+			.extension::<VerifiedSignatures>()
+			.is_some_and(|verified| verified.contains(&cache_key(sig, msg, pubkey)));
+		cached || Pair::verify(sig, msg, pubkey)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn verified_signatures_short_circuit_verification() {
+		let pair = Pair::from_seed(&[1; 32]);
+		let message = b"payload";
+		let signature = pair.sign(message);
+		let unchecked = Signature::from_raw([7; 64]);
+
+		sp_io::TestExternalities::default().execute_with(|| {
+			// Without the extension signatures are verified as usual
+			assert!(crypto::sr25519_verify(&signature, message, &pair.public()));
+			assert!(!crypto::sr25519_verify(&unchecked, message, &pair.public()));
+		});
+
+		let mut ext = sp_io::TestExternalities::default();
+		let verified = [cache_key(&unchecked, message, &pair.public())].into_iter().collect();
+		ext.register_extension(VerifiedSignatures(std::sync::Arc::new(verified)));
+		ext.execute_with(|| {
+			// Entries are trusted, which is why the node only adds signatures it verified
+			assert!(crypto::sr25519_verify(&unchecked, message, &pair.public()));
+			assert!(!crypto::sr25519_verify(&unchecked, b"other", &pair.public()));
+			assert!(crypto::sr25519_verify(&signature, message, &pair.public()));
+			// Runtimes calling Substrate's function see the same outcome without the cache
+			assert!(!sp_io::crypto::sr25519_verify(&unchecked, message, &pair.public()));
+		});
+	}
+}
@@ -0,0 +1,330 @@
+//! Index of the transaction spending each output, behind `utxo_getSpendingTx`, of where each
+//! transaction is, behind the REST gateway's `/tx/{id}`, and of the history of each pubkey, behind
+//! the Electrum server.
+//!
+//! The runtime forgets outputs once they are spent. With `--spent-index`, a background task reads
+//! the body of every finalized block and records, for each outpoint spent, the spending
+//! transaction in the client's auxiliary storage, along with the location of each transaction.
+//! It also appends the transactions paying or spending from a pubkey to its history, and the
+//! number of the block creating each output. Outputs created by the runtime, such as rewards, are
+//! found in the block's events and listed in histories under the outpoint of their first output.
+//! Only finalized blocks are indexed, so an output spent in a block that is not yet finalized has
+//! no entry until finality catches up, and entries are never undone by a reorg.
+//!
+//! Enabling the index on a node that already synced backfills it from genesis, skipping blocks
+//! whose bodies were pruned. Where the state was pruned too, histories miss the spends and the
+//! outputs created by the runtime of those blocks.
+
+use crate::{import::spends, rpc::utxo::utxo_store_prefix, service::FullClient};
+use codec::{Decode, Encode};
+use futures::StreamExt;
+use sc_client_api::{AuxStore, BlockBackend, BlockchainEvents, StorageProvider};
+use serde::{Deserialize, Serialize};
+use sp_blockchain::HeaderBackend;
+use sp_core::{hashing::twox_128, storage::StorageKey, H256};
+use sp_runtime::traits::{BlakeTwo256, Hash, Header as _};
+use std::{
+	collections::{BTreeMap, HashMap},
+	sync::Arc,
+};
+use tumuchain_runtime::{BlockNumber, RuntimeCall, RuntimeEvent, UncheckedExtrinsic};
+use utxo::{Transaction, TransactionOutput};
+
+/// Prefix of the entries, followed by the spent outpoint.
+const SPENT_BY_PREFIX: &[u8] = b"spent_by";
+
+/// Prefix of the locations of transactions, followed by the txid.
+const TX_PREFIX: &[u8] = b"tx_by_id";
+
+/// Key of the number of the next block to index.
+const CURSOR_KEY: &[u8] = b"spent_by_cursor";
+
+/// Prefix of the history of each pubkey, followed by the pubkey. A history is a single entry,
+/// rewritten as it grows.
+const HISTORY_PREFIX: &[u8] = b"history";
+
+/// Prefix of the number of the block creating each output, followed by the outpoint.
+const CREATED_PREFIX: &[u8] = b"created_at";
+
+/// Where a transaction is in the chain.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxLocation {
+	/// Hash of the block containing the spending transaction
+	pub block_hash: H256,
+	/// Number of that block
+	pub block_number: BlockNumber,
+	/// Position of the spending extrinsic in the block
+	pub index: u32,
+	/// The transaction
+	pub txid: H256,
+}
+
+/// The transaction that spent `outpoint`, if the index has one.
+pub fn spending_tx<C: AuxStore>(
+	client: &C,
+	outpoint: &H256,
+) -> sp_blockchain::Result<Option<TxLocation>> {
+	read_location(client, &entry_key(outpoint))
+}
+
+/// Where the transaction `txid` is, if the index has it.
+pub fn tx_location<C: AuxStore>(
+	client: &C,
+	txid: &H256,
+) -> sp_blockchain::Result<Option<TxLocation>> {
+	read_location(client, &[TX_PREFIX, txid.as_bytes()].concat())
+}
+
+/// The transactions paying or spending from `pubkey` in finalized blocks, oldest first, with the
+/// numbers of their blocks.
+pub fn history<C: AuxStore>(
+	client: &C,
+	pubkey: &H256,
+) -> sp_blockchain::Result<Vec<(BlockNumber, H256)>> {
+	let Some(data) = client.get_aux(&[HISTORY_PREFIX, pubkey.as_bytes()].concat())? else {
+		return Ok(Vec::new())
+	};
+	Vec::decode(&mut &data[..])
+		.map_err(|e| sp_blockchain::Error::Backend(format!("corrupt history entry: {e}")))
+}
+
+/// The number of the finalized block creating the output at `outpoint`, if the index has it.
+pub fn created_at<C: AuxStore>(
+	client: &C,
+	outpoint: &H256,
+) -> sp_blockchain::Result<Option<BlockNumber>> {
+	let Some(data) = client.get_aux(&[CREATED_PREFIX, outpoint.as_bytes()].concat())? else {
+		return Ok(None)
+	};
+	BlockNumber::decode(&mut &data[..])
+		.map(Some)
+		.map_err(|e| sp_blockchain::Error::Backend(format!("corrupt created_at entry: {e}")))
+}
+
+/// The number of the next block to index: the blocks before it are in the index.
+pub fn next_block<C: AuxStore>(client: &C) -> sp_blockchain::Result<BlockNumber> {
+	match client.get_aux(CURSOR_KEY)? {
+		Some(cursor) => BlockNumber::decode(&mut &cursor[..])
+			.map_err(|e| sp_blockchain::Error::Backend(format!("corrupt index cursor: {e}"))),
+		// Genesis spends nothing
+		None => Ok(1),
+	}
+}
+
+fn read_location<C: AuxStore>(
+	client: &C,
+	key: &[u8],
+) -> sp_blockchain::Result<Option<TxLocation>> {
+	let Some(data) = client.get_aux(key)? else { return Ok(None) };
+	TxLocation::decode(&mut &data[..])
+		.map(Some)
+		.map_err(|e| sp_blockchain::Error::Backend(format!("corrupt spent index entry: {e}")))
+}
+
+/// Index finalized blocks as they come, until the client's finality stream ends.
+pub async fn run(client: Arc<FullClient>) {
+	let mut finality = client.finality_notification_stream();
+	let mut next = next_block(&*client).unwrap_or(1);
+
+	loop {
+		while next <= client.info().finalized_number {
+			if let Err(e) = index_block(&client, next) {
+				log::warn!(target: "spent-index", "Failed to index block #{next}: {e}");
+				break
+			}
+			next += 1;
+		}
+		if finality.next().await.is_none() {
+			break
+		}
+	}
+}
+
+/// Record the outputs spent, the transactions and the histories of finalized block `number`, and
+/// move the cursor past it.
+fn index_block(client: &FullClient, number: BlockNumber) -> sp_blockchain::Result<()> {
+	let hash = client
+		.hash(number)?
+		.ok_or_else(|| sp_blockchain::Error::UnknownBlock(format!("#{number}")))?;
+	let mut entries = Vec::new();
+
+	// Bodies pruned before the index was enabled cannot be indexed
+	for (index, extrinsic) in client.block_body(hash)?.unwrap_or_default().iter().enumerate() {
+		let Ok(UncheckedExtrinsic {
+			function: RuntimeCall::Utxo(utxo::Call::spend { transaction }), ..
+		}) = UncheckedExtrinsic::decode(&mut &extrinsic.encode()[..])
+		else {
+			continue
+		};
+		let txid = transaction.txid();
+		let location =
+			TxLocation { block_hash: hash, block_number: number, index: index as u32, txid };
+		let location = location.encode();
+		entries.extend(
+			transaction.inputs.iter().map(|input| (entry_key(&input.outpoint), location.clone())),
+		);
+		entries.push(([TX_PREFIX, txid.as_bytes()].concat(), location));
+	}
+
+	let scan = scan_block(client, hash)?;
+	let mut touched = BTreeMap::<H256, Vec<H256>>::new();
+	for (pubkey, id) in scan.touched {
+		let ids = touched.entry(pubkey).or_default();
+		if !ids.contains(&id) {
+			ids.push(id);
+		}
+	}
+	for (pubkey, ids) in touched {
+		let mut history = history(client, &pubkey)?;
+		history.extend(ids.into_iter().map(|id| (number, id)));
+		entries.push(([HISTORY_PREFIX, pubkey.as_bytes()].concat(), history.encode()));
+	}
+	entries.extend(
+		scan.created
+			.iter()
+			.map(|outpoint| ([CREATED_PREFIX, outpoint.as_bytes()].concat(), number.encode())),
+	);
+
+	let cursor = (number + 1).encode();
+	let inserts = entries
+		.iter()
+		.map(|(key, value)| (&key[..], &value[..]))
+		.chain([(CURSOR_KEY, &cursor[..])])
+		.collect::<Vec<_>>();
+	client.insert_aux(&inserts, &[])
+}
+
+/// The pubkeys a block pays or spends from, and the outputs it creates.
+#[derive(Debug, Default)]
+pub struct BlockScan {
+	/// Each pubkey paid or spent from, with the txid or runtime outputs doing so, in block order
+	pub touched: Vec<(H256, H256)>,
+	/// The outpoints created
+	pub created: Vec<H256>,
+}
+
+/// Scan block `hash` for what it changes in the histories, whether or not it is finalized.
+pub fn scan_block(client: &FullClient, hash: H256) -> sp_blockchain::Result<BlockScan> {
+	let header = client
+		.header(hash)?
+		.ok_or_else(|| sp_blockchain::Error::UnknownBlock(format!("{hash:?}")))?;
+	let mut scan = BlockScan::default();
+	// Outputs created earlier in the block, which are not in the parent's UTXO set
+	let mut created_here = HashMap::new();
+
+	for transaction in spends(&client.block_body(hash)?.unwrap_or_default()) {
+		let txid = transaction.txid();
+		for input in &transaction.inputs {
+			let spent = match created_here.remove(&input.outpoint) {
+				Some(output) => Some(output),
+				// Unknown if the parent's state was pruned
+				None => output_at(client, *header.parent_hash(), &input.outpoint).ok().flatten(),
+			};
+			scan.touched.extend(spent.map(|output| (output.pubkey, txid)));
+		}
+		for (index, output) in transaction.outputs.iter().enumerate() {
+			let outpoint = Transaction::outpoint_of(&txid, index as u64);
+			scan.touched.push((output.pubkey, txid));
+			scan.created.push(outpoint);
+			created_here.insert(outpoint, output.clone());
+		}
+	}
+
+	// None if the state was pruned, or the events are of an older runtime
+	for outputs in runtime_outputs(client, hash, *header.number()).unwrap_or_default() {
+		let Some(&(id, _)) = outputs.first() else { continue };
+		for (outpoint, output) in outputs {
+			scan.touched.push((output.pubkey, id));
+			scan.created.push(outpoint);
+		}
+	}
+	Ok(scan)
+}
+
+/// An event of a block, as kept in `System::Events`.
+pub type EventRecord = frame_system::EventRecord<RuntimeEvent, H256>;
+
+/// The storage key of the events of a block.
+pub fn events_key() -> StorageKey {
+	StorageKey([twox_128(b"System"), twox_128(b"Events")].concat())
+}
+
+/// The outputs created by the runtime in block `at` rather than by spends, grouped by the event
+/// reporting them, in order.
+pub fn runtime_outputs(
+	client: &FullClient,
+	at: H256,
+	number: BlockNumber,
+) -> sp_blockchain::Result<Vec<Vec<(H256, TransactionOutput)>>> {
+	let records = match client.storage(at, &events_key())? {
+		Some(data) => Vec::<EventRecord>::decode(&mut &data.0[..])
+			.map_err(|e| sp_blockchain::Error::Backend(format!("events do not decode: {e}")))?,
+		None => Vec::new(),
+	};
+	event_outputs(records, number, |outpoint| output_at(client, at, outpoint))
+}
+
+/// The outputs created by the runtime in block `number` according to its events `records`,
+/// grouped by event. `output` reads an output from the block's state, for the events not
+/// carrying it.
+pub fn event_outputs<E>(
+	records: Vec<EventRecord>,
+	number: BlockNumber,
+	mut output: impl FnMut(&H256) -> Result<Option<TransactionOutput>, E>,
+) -> Result<Vec<Vec<(H256, TransactionOutput)>>, E> {
+	let mut created = Vec::new();
+	for record in records {
+		let outputs = match record.event {
+			RuntimeEvent::Utxo(utxo::Event::RewardsIssued { utxo_hash, .. }) =>
+				match output(&utxo_hash)? {
+					Some(output) => vec![(utxo_hash, output)],
+					None => continue,
+				},
+			RuntimeEvent::Utxo(utxo::Event::PendingRewardPaid { author, amount, utxo_hash }) |
+			RuntimeEvent::Utxo(utxo::Event::UncleRewarded { author, amount, utxo_hash, .. }) => {
+				let output = TransactionOutput { value: amount, pubkey: author, memo: None };
+				vec![(utxo_hash, output)]
+			},
+			RuntimeEvent::Utxo(utxo::Event::Minted { outputs, .. }) => {
+				let frame_system::Phase::ApplyExtrinsic(extrinsic_index) = record.phase else {
+					continue
+				};
+				// Derived like the pallet's `mint` derives them
+				outputs
+					.iter()
+					.enumerate()
+					.map(|(index, output)| {
+						let outpoint = BlakeTwo256::hash_of(&(
+							&outputs,
+							u64::from(number),
+							extrinsic_index,
+							index as u64,
+						));
+						(outpoint, output.clone())
+					})
+					.collect()
+			},
+			_ => continue,
+		};
+		created.push(outputs);
+	}
+	Ok(created)
+}
+
+/// The unspent output at `outpoint` as of block `at`.
+pub fn output_at(
+	client: &FullClient,
+	at: H256,
+	outpoint: &H256,
+) -> sp_blockchain::Result<Option<TransactionOutput>> {
+	let key = StorageKey([utxo_store_prefix(), outpoint.as_bytes().to_vec()].concat());
+	let Some(data) = client.storage(at, &key)? else { return Ok(None) };
+	TransactionOutput::decode(&mut &data.0[..])
+		.map(Some)
+		.map_err(|e| sp_blockchain::Error::Backend(format!("output does not decode: {e}")))
+}
+
+fn entry_key(outpoint: &H256) -> Vec<u8> {
+	[SPENT_BY_PREFIX, outpoint.as_bytes()].concat()
+}
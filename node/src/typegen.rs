@@ -0,0 +1,477 @@
+//! The `export-types` subcommand, generating TypeScript types of a pallet's calls and events from
+//! the runtime metadata, for polkadot-js frontends.
+//!
+//! The generated module declares the pallet's `Call` and `Event` enums, as `UtxoCall` and
+//! `UtxoEvent` for the UTXO pallet, and every type they reference, such as `Transaction`,
+//! `TransactionInput` and `TransactionOutput`:
+//!
+//! - as interfaces and type aliases of the values polkadot-js takes for them, so a frontend can
+//!   build a `Transaction` for `api.tx.utxo.spend` or `api.createType` type-checked, and
+//! - as `definitions` to register with polkadot-js, for `api.createType("Transaction", ...)` and
+//!   decoding to know the types by name.
+//!
+//! Field names are camelCased, as polkadot-js does with the types of the metadata. SCALE encodes
+//! fields in order, not by name, so the encoding is the runtime's all the same. Types are read
+//! from the metadata of the runtime built into the node, or from `--metadata`, what
+//! `state_getMetadata` returns on a node running another runtime version.
+
+use codec::Decode;
+use frame_metadata::{RuntimeMetadata, RuntimeMetadataPrefixed, META_RESERVED};
+use scale_info::{form::PortableForm, Field, PortableRegistry, Type, TypeDef, TypeDefPrimitive};
+use std::{
+	collections::{HashMap, HashSet},
+	fmt::Write as _,
+	fs,
+	io::{self, Write},
+	path::PathBuf,
+};
+use tumuchain_runtime::Runtime;
+
+/// Version of the metadata read from the runtime built into the node.
+const METADATA_VERSION: u32 = 15;
+
+/// Rust types encoded as the polkadot-js types of the same name, by path.
+const BUILTINS: [&str; 3] =
+	["primitive_types::H160", "primitive_types::H256", "primitive_types::H512"];
+
+/// Names the generated module declares itself.
+const RESERVED: [&str; 5] = ["HexString", "Bytes", "BigIntLike", "definitions", "registerTypes"];
+
+/// Declarations the generated types are written in terms of.
+const PREAMBLE: &str = "\
+/** Hex string, 0x-prefixed. */
+export type HexString = `0x${string}`;
+
+/** Bytes, as hex or as an array. */
+export type Bytes = HexString | Uint8Array;
+
+/** Integer wider than 32 bits, as a bigint or a decimal or hex string. */
+export type BigIntLike = bigint | string;
+";
+
+/// Declarations registering the generated definitions.
+const EPILOGUE: &str = "
+/** Register the types with a polkadot-js `registry`, such as `api.registry`. */
+export function registerTypes(registry: { register(types: typeof definitions): void }): void {
+  registry.register(definitions);
+}
+";
+
+/// Generate TypeScript types and polkadot-js type definitions of a pallet's calls and events.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct ExportTypesCmd {
+	/// Pallet whose calls and events to export, by its name in the runtime.
+	#[arg(long, value_name = "NAME", default_value = "Utxo")]
+	pub pallet: String,
+
+	/// File holding the SCALE-encoded runtime metadata to read the types from, raw or as the
+	/// 0x-prefixed hex `state_getMetadata` returns. Defaults to the metadata of the runtime built
+	/// into the node.
+	#[arg(long, value_name = "PATH")]
+	pub metadata: Option<PathBuf>,
+
+	/// File to write the TypeScript module to. Defaults to stdout.
+	#[arg(long, short, value_name = "PATH")]
+	pub output: Option<PathBuf>,
+}
+
+impl ExportTypesCmd {
+	/// Generate the module and write it out.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		let metadata = match &self.metadata {
+			Some(path) => read_metadata(path)?,
+			None => Runtime::metadata_at_version(METADATA_VERSION)
+				.ok_or_else(|| sc_cli::Error::Input("the runtime has no V15 metadata".into()))?
+				.to_vec(),
+		};
+		let metadata = RuntimeMetadataPrefixed::decode(&mut &metadata[..])
+			.map_err(|e| sc_cli::Error::Input(format!("invalid metadata: {e}")))?;
+		if metadata.0 != META_RESERVED {
+			return Err(sc_cli::Error::Input("invalid metadata: bad magic number".into()))
+		}
+		let (registry, roots) = match metadata.1 {
+			RuntimeMetadata::V14(metadata) => {
+				let pallet = metadata.pallets.iter().find(|pallet| pallet.name == self.pallet);
+				let roots = pallet.map(|pallet| {
+					(pallet.calls.as_ref().map(|c| c.ty.id), pallet.event.as_ref().map(|e| e.ty.id))
+				});
+				(metadata.types, roots)
+			},
+			RuntimeMetadata::V15(metadata) => {
+				let pallet = metadata.pallets.iter().find(|pallet| pallet.name == self.pallet);
+				let roots = pallet.map(|pallet| {
+					(pallet.calls.as_ref().map(|c| c.ty.id), pallet.event.as_ref().map(|e| e.ty.id))
+				});
+				(metadata.types, roots)
+			},
+			_ => return Err(sc_cli::Error::Input("metadata older than V14 has no types".into())),
+		};
+		let (calls, events) = roots.ok_or_else(|| {
+			sc_cli::Error::Input(format!("the runtime has no pallet named {}", self.pallet))
+		})?;
+
+		let mut generator = Generator::new(&registry);
+		for (id, kind) in [(calls, "Call"), (events, "Event")] {
+			if let Some(id) = id {
+				generator.assign(id, format!("{}{kind}", self.pallet));
+			}
+		}
+		let module = generator.module(&self.pallet)?;
+		match &self.output {
+			Some(path) => fs::write(path, module)?,
+			None => io::stdout().lock().write_all(module.as_bytes())?,
+		}
+		Ok(())
+	}
+}
+
+/// Read the metadata in `path`, raw or hex-encoded.
+fn read_metadata(path: &PathBuf) -> sc_cli::Result<Vec<u8>> {
+	let raw = fs::read(path)?;
+	match std::str::from_utf8(&raw).map(|text| text.trim().trim_matches('"')) {
+		Ok(hex) if hex.starts_with("0x") => sp_core::bytes::from_hex(hex)
+			.map_err(|e| sc_cli::Error::Input(format!("bad hex in {}: {e}", path.display()))),
+		_ => Ok(raw),
+	}
+}
+
+/// How a type of the registry is written out.
+enum Shape {
+	/// A type polkadot-js knows by this name
+	Builtin(&'static str),
+	/// A wrapper of the type of this id, such as a `BoundedVec`, encoded as the type itself
+	Transparent(u32),
+	/// An `Option` of the type of this id
+	Option(u32),
+	/// A `Result` of the types of these ids
+	Result(u32, u32),
+	/// A struct or enum declared by name
+	Named,
+	/// Any other type, written out where used
+	Anonymous,
+}
+
+/// Generates the declarations of types of `registry`, from the roots assigned names on.
+struct Generator<'a> {
+	registry: &'a PortableRegistry,
+	/// Names of the types declared, by id
+	names: HashMap<u32, String>,
+	/// Names taken, by generated types or the module itself
+	taken: HashSet<String>,
+	/// Ids of the types to declare, in the order they were reached
+	declared: Vec<u32>,
+}
+
+impl<'a> Generator<'a> {
+	fn new(registry: &'a PortableRegistry) -> Self {
+		Self {
+			registry,
+			names: HashMap::new(),
+			taken: RESERVED.iter().map(|name| name.to_string()).collect(),
+			declared: Vec::new(),
+		}
+	}
+
+	/// Declare the type of `id` as `name`, or as `name` with a number if it is taken.
+	fn assign(&mut self, id: u32, name: String) -> String {
+		if let Some(name) = self.names.get(&id) {
+			return name.clone()
+		}
+		let mut unique = name.clone();
+		let mut n = 1;
+		while self.taken.contains(&unique) {
+			n += 1;
+			unique = format!("{name}{n}");
+		}
+		self.taken.insert(unique.clone());
+		self.names.insert(id, unique.clone());
+		self.declared.push(id);
+		unique
+	}
+
+	/// The name of the named type `ty` of `id`, declaring it if it was not yet.
+	fn name(&mut self, id: u32, ty: &Type<PortableForm>) -> String {
+		let name = ty.path.segments.last().cloned().unwrap_or_else(|| format!("Type{id}"));
+		self.assign(id, name)
+	}
+
+	fn resolve(&self, id: u32) -> sc_cli::Result<&'a Type<PortableForm>> {
+		self.registry
+			.resolve(id)
+			.ok_or_else(|| sc_cli::Error::Input(format!("type {id} is not in the metadata")))
+	}
+
+	fn shape(&self, ty: &Type<PortableForm>) -> Shape {
+		let path = ty.path.segments.join("::");
+		if let Some(builtin) = BUILTINS.iter().find(|builtin| **builtin == path) {
+			return Shape::Builtin(builtin.rsplit("::").next().expect("split yields one item"))
+		}
+		let params: Vec<_> =
+			ty.type_params.iter().filter_map(|p| p.ty.as_ref().map(|ty| ty.id)).collect();
+		match (&ty.type_def, path.as_str(), &params[..]) {
+			(TypeDef::Variant(_), "Option", [some]) => Shape::Option(*some),
+			(TypeDef::Variant(_), "Result", [ok, err]) => Shape::Result(*ok, *err),
+			(TypeDef::Composite(composite), _, _) => match &composite.fields[..] {
+				[field] if field.name.is_none() => Shape::Transparent(field.ty.id),
+				_ => Shape::Named,
+			},
+			(TypeDef::Variant(_), _, _) => Shape::Named,
+			_ => Shape::Anonymous,
+		}
+	}
+
+	fn is_u8(&self, id: u32) -> sc_cli::Result<bool> {
+		Ok(matches!(self.resolve(id)?.type_def, TypeDef::Primitive(TypeDefPrimitive::U8)))
+	}
+
+	/// The polkadot-js type string of the type of `id`.
+	fn codec(&mut self, id: u32) -> sc_cli::Result<String> {
+		let ty = self.resolve(id)?;
+		Ok(match self.shape(ty) {
+			Shape::Builtin(name) => name.into(),
+			Shape::Transparent(inner) => self.codec(inner)?,
+			Shape::Option(some) => format!("Option<{}>", self.codec(some)?),
+			Shape::Result(ok, err) => format!("Result<{}, {}>", self.codec(ok)?, self.codec(err)?),
+			Shape::Named => self.name(id, ty),
+			Shape::Anonymous => match &ty.type_def {
+				TypeDef::Primitive(primitive) => primitive_codec(primitive).into(),
+				TypeDef::Sequence(sequence) if self.is_u8(sequence.type_param.id)? =>
+					"Bytes".into(),
+				TypeDef::Sequence(sequence) =>
+					format!("Vec<{}>", self.codec(sequence.type_param.id)?),
+				TypeDef::Array(array) =>
+					format!("[{}; {}]", self.codec(array.type_param.id)?, array.len),
+				TypeDef::Tuple(tuple) if tuple.fields.is_empty() => "Null".into(),
+				TypeDef::Tuple(tuple) => {
+					let fields = tuple.fields.iter().map(|field| self.codec(field.id));
+					format!("({})", fields.collect::<sc_cli::Result<Vec<_>>>()?.join(", "))
+				},
+				TypeDef::Compact(compact) =>
+					format!("Compact<{}>", self.codec(compact.type_param.id)?),
+				TypeDef::BitSequence(_) => "BitVec".into(),
+				TypeDef::Composite(_) | TypeDef::Variant(_) => unreachable!("shaped as named"),
+			},
+		})
+	}
+
+	/// The TypeScript type of the values polkadot-js takes for the type of `id`.
+	fn ts(&mut self, id: u32) -> sc_cli::Result<String> {
+		let ty = self.resolve(id)?;
+		Ok(match self.shape(ty) {
+			Shape::Builtin(_) => "Bytes".into(),
+			Shape::Transparent(inner) => self.ts(inner)?,
+			Shape::Option(some) => format!("{} | null", self.ts(some)?),
+			Shape::Result(ok, err) =>
+				format!("{{ Ok: {} }} | {{ Err: {} }}", self.ts(ok)?, self.ts(err)?),
+			Shape::Named => self.name(id, ty),
+			Shape::Anonymous => match &ty.type_def {
+				TypeDef::Primitive(primitive) => primitive_ts(primitive).into(),
+				TypeDef::Sequence(sequence) if self.is_u8(sequence.type_param.id)? =>
+					"Bytes".into(),
+				TypeDef::Sequence(sequence) => array_of(self.ts(sequence.type_param.id)?),
+				TypeDef::Array(array) if self.is_u8(array.type_param.id)? => "Bytes".into(),
+				TypeDef::Array(array) => array_of(self.ts(array.type_param.id)?),
+				TypeDef::Tuple(tuple) if tuple.fields.is_empty() => "null".into(),
+				TypeDef::Tuple(tuple) => {
+					let fields = tuple.fields.iter().map(|field| self.ts(field.id));
+					format!("[{}]", fields.collect::<sc_cli::Result<Vec<_>>>()?.join(", "))
+				},
+				TypeDef::Compact(compact) => self.ts(compact.type_param.id)?,
+				TypeDef::BitSequence(_) => "Bytes".into(),
+				TypeDef::Composite(_) | TypeDef::Variant(_) => unreachable!("shaped as named"),
+			},
+		})
+	}
+
+	/// The TypeScript type and polkadot-js definition of `fields`, those of a struct or of an
+	/// enum variant.
+	fn fields(&mut self, fields: &[Field<PortableForm>]) -> sc_cli::Result<(String, String)> {
+		match fields {
+			[] => Ok(("null".into(), "\"Null\"".into())),
+			[field] if field.name.is_none() =>
+				Ok((self.ts(field.ty.id)?, format!("\"{}\"", self.codec(field.ty.id)?))),
+			_ if fields.iter().all(|field| field.name.is_none()) => {
+				let (mut ts, mut codec) = (Vec::new(), Vec::new());
+				for field in fields {
+					ts.push(self.ts(field.ty.id)?);
+					codec.push(self.codec(field.ty.id)?);
+				}
+				Ok((format!("[{}]", ts.join(", ")), format!("\"({})\"", codec.join(", "))))
+			},
+			_ => {
+				let (mut ts, mut codec) = (Vec::new(), Vec::new());
+				for field in fields {
+					let name = camel_case(field.name.as_deref().unwrap_or_default());
+					ts.push(format!("{name}: {}", self.ts(field.ty.id)?));
+					codec.push(format!("{name}: \"{}\"", self.codec(field.ty.id)?));
+				}
+				Ok((format!("{{ {} }}", ts.join("; ")), format!("{{ {} }}", codec.join(", "))))
+			},
+		}
+	}
+
+	/// The declaration and polkadot-js definition of the named type of `id`.
+	fn declare(&mut self, id: u32) -> sc_cli::Result<(String, String)> {
+		let ty = self.resolve(id)?;
+		let name = self.names[&id].clone();
+		let mut ts = doc(&ty.docs, "");
+		let codec = match &ty.type_def {
+			TypeDef::Composite(composite) if composite.fields.iter().all(|f| f.name.is_some()) &&
+				!composite.fields.is_empty() =>
+			{
+				writeln!(ts, "export interface {name} {{").expect("writes to a string");
+				let mut codec = String::from("{\n");
+				for field in &composite.fields {
+					let field_name = camel_case(field.name.as_deref().unwrap_or_default());
+					ts += &doc(&field.docs, "  ");
+					writeln!(ts, "  {field_name}: {};", self.ts(field.ty.id)?)
+						.expect("writes to a string");
+					writeln!(codec, "    {field_name}: \"{}\",", self.codec(field.ty.id)?)
+						.expect("writes to a string");
+				}
+				ts += "}\n";
+				codec + "  }"
+			},
+			TypeDef::Composite(composite) => {
+				let (fields_ts, fields_codec) = self.fields(&composite.fields)?;
+				writeln!(ts, "export type {name} = {fields_ts};").expect("writes to a string");
+				fields_codec
+			},
+			TypeDef::Variant(variant) => {
+				let mut variants: Vec<_> = variant.variants.iter().collect();
+				variants.sort_by_key(|variant| variant.index);
+				writeln!(ts, "export type {name} =").expect("writes to a string");
+				if variants.is_empty() {
+					ts += "  never\n";
+				}
+				let mut codec = String::from("{\n    _enum: {\n");
+				let mut next = 0;
+				for variant in variants {
+					// polkadot-js indexes variants by position: pad the gaps
+					for unused in next..variant.index {
+						writeln!(codec, "      __Unused{unused}: \"Null\",")
+							.expect("writes to a string");
+					}
+					next = variant.index.saturating_add(1);
+					let (fields_ts, fields_codec) = self.fields(&variant.fields)?;
+					ts += &doc(&variant.docs, "  ");
+					let member = match variant.fields.is_empty() {
+						true => format!("\"{}\"", variant.name),
+						false => format!("{{ {}: {fields_ts} }}", variant.name),
+					};
+					writeln!(ts, "  | {member}").expect("writes to a string");
+					writeln!(codec, "      {}: {fields_codec},", variant.name)
+						.expect("writes to a string");
+				}
+				ts += "  ;\n";
+				codec + "    },\n  }"
+			},
+			_ => unreachable!("only structs and enums are named"),
+		};
+		Ok((ts, codec))
+	}
+
+	/// The TypeScript module declaring the types assigned names and those they reference.
+	fn module(mut self, pallet: &str) -> sc_cli::Result<String> {
+		let mut declarations = Vec::new();
+		let mut definitions = Vec::new();
+		// Declaring a type names those of its fields, to be declared in turn
+		let mut n = 0;
+		while let Some(id) = self.declared.get(n).copied() {
+			let (ts, codec) = self.declare(id)?;
+			definitions.push(format!("  {}: {codec},\n", self.names[&id]));
+			declarations.push(ts);
+			n += 1;
+		}
+
+		let mut module = format!(
+			"// Generated from the runtime metadata by `tumuchain-node export-types --pallet \
+			 {pallet}`.\n// Do not edit.\n\n{PREAMBLE}"
+		);
+		for declaration in declarations {
+			module += "\n";
+			module += &declaration;
+		}
+		module += "\n/** polkadot-js definitions of the types above, to register by name. */\n";
+		module += "export const definitions = {\n";
+		module.extend(definitions);
+		module += "};\n";
+		module += EPILOGUE;
+		Ok(module)
+	}
+}
+
+fn primitive_codec(primitive: &TypeDefPrimitive) -> &'static str {
+	match primitive {
+		TypeDefPrimitive::Bool => "bool",
+		// Encoded as its code point
+		TypeDefPrimitive::Char => "u32",
+		TypeDefPrimitive::Str => "Text",
+		TypeDefPrimitive::U8 => "u8",
+		TypeDefPrimitive::U16 => "u16",
+		TypeDefPrimitive::U32 => "u32",
+		TypeDefPrimitive::U64 => "u64",
+		TypeDefPrimitive::U128 => "u128",
+		TypeDefPrimitive::U256 => "u256",
+		TypeDefPrimitive::I8 => "i8",
+		TypeDefPrimitive::I16 => "i16",
+		TypeDefPrimitive::I32 => "i32",
+		TypeDefPrimitive::I64 => "i64",
+		TypeDefPrimitive::I128 => "i128",
+		TypeDefPrimitive::I256 => "i256",
+	}
+}
+
+fn primitive_ts(primitive: &TypeDefPrimitive) -> &'static str {
+	match primitive {
+		TypeDefPrimitive::Bool => "boolean",
+		TypeDefPrimitive::Str => "string",
+		TypeDefPrimitive::Char |
+		TypeDefPrimitive::U8 |
+		TypeDefPrimitive::U16 |
+		TypeDefPrimitive::U32 |
+		TypeDefPrimitive::I8 |
+		TypeDefPrimitive::I16 |
+		TypeDefPrimitive::I32 => "number",
+		_ => "BigIntLike",
+	}
+}
+
+/// The TypeScript array of `ts`.
+fn array_of(ts: String) -> String {
+	match ts.contains(' ') {
+		true => format!("({ts})[]"),
+		false => format!("{ts}[]"),
+	}
+}
+
+/// `snake_case` as `camelCase`.
+fn camel_case(name: &str) -> String {
+	let mut camel = String::with_capacity(name.len());
+	let mut upper = false;
+	for c in name.chars() {
+		match c {
+			'_' if !camel.is_empty() => upper = true,
+			c if upper => {
+				camel.extend(c.to_uppercase());
+				upper = false;
+			},
+			c => camel.push(c),
+		}
+	}
+	camel
+}
+
+/// A doc comment of `docs`, indented by `indent`.
+fn doc(docs: &[String], indent: &str) -> String {
+	if docs.is_empty() {
+		return String::new()
+	}
+	let mut comment = format!("{indent}/**\n");
+	for line in docs {
+		let line = line.strip_prefix(' ').unwrap_or(line).replace("*/", "*\\/");
+		writeln!(comment, "{indent} *{}{line}", if line.is_empty() { "" } else { " " })
+			.expect("writes to a string");
+	}
+	comment + indent + " */\n"
+}
@@ -0,0 +1,406 @@
+//! Cache of the UTXOs looked up while importing blocks.
+//!
+//! Importing a block looks up the output spent by every input. Reading them from the state trie
+//! costs a walk down the trie per input, so lookups go through two layers first, like Bitcoin
+//! Core's coins cache over its chainstate database:
+//!
+//! - an in-memory map of recently created and looked up outputs, bounded by
+//!   `--utxo-cache-size`;
+//! - a flat on-disk index in the client's auxiliary storage, to which outputs created and spent
+//!   by imported blocks are written every `--utxo-cache-flush-interval` blocks and on shutdown.
+//!
+//! An outpoint commits to the output it names, so a cached output is never stale, at worst
+//! already spent or created on an abandoned fork. Outputs spent by imported blocks are not
+//! answered from the cache, since another fork may not spend them: they are read from the state
+//! at the block asked about. The cache is therefore only good for what
+//! does not depend on whether an output exists, such as the pubkey a signature is checked
+//! against in [`import`](crate::import) or the value a fee is computed from in the
+//! [`mempool`](crate::mempool). Block execution in the runtime does not go through the cache: it
+//! still reads the state trie.
+
+use crate::{cli::UtxoCacheParams, rpc::utxo::utxo_store_prefix, service::FullClient};
+use codec::{Decode, Encode};
+use sc_client_api::{AuxStore, StorageProvider};
+use sp_core::{storage::StorageKey, H256};
+use std::{
+	collections::{HashMap, VecDeque},
+	sync::{Arc, Mutex},
+};
+use utxo::{Transaction, TransactionOutput};
+
+/// Prefix of the on-disk index entries in the auxiliary storage.
+const INDEX_PREFIX: &[u8] = b"utxo_cache";
+
+/// Approximate memory taken by a cached output, map and eviction queue included.
+const ENTRY_SIZE: usize = 160;
+
+#[derive(Default)]
+struct Layers {
+	/// Outputs held in memory
+	entries: HashMap<H256, TransactionOutput>,
+	/// Outpoints of `entries` by age, evicted from the front. May hold outpoints since removed.
+	order: VecDeque<H256>,
+	/// Changes not yet written to the on-disk index, `None` for spent outputs
+	dirty: HashMap<H256, Option<TransactionOutput>>,
+	/// Blocks connected since the last write
+	blocks: u32,
+}
+
+/// What a [`UtxoCache`] sits on: the auxiliary storage of its index and the state.
+pub trait UtxoState: AuxStore {
+	/// The output at `outpoint` in the state at `at`.
+	fn state_output(&self, at: H256, outpoint: &H256) -> Option<TransactionOutput>;
+}
+
+impl UtxoState for FullClient {
+	fn state_output(&self, at: H256, outpoint: &H256) -> Option<TransactionOutput> {
+		let key = StorageKey([&utxo_store_prefix()[..], outpoint.as_bytes()].concat());
+		let data = self.storage(at, &key).ok()??;
+		TransactionOutput::decode(&mut &data.0[..]).ok()
+	}
+}
+
+/// The UTXO cache of a client. Writes pending changes to disk when dropped.
+pub struct UtxoCache<C: UtxoState = FullClient> {
+	client: Arc<C>,
+	capacity: usize,
+	flush_interval: u32,
+	layers: Mutex<Layers>,
+}
+
+impl<C: UtxoState> UtxoCache<C> {
+	/// An empty cache over the state of `client`.
+	pub fn new(client: Arc<C>, params: &UtxoCacheParams) -> Self {
+		Self::with_capacity(
+			client,
+			params.utxo_cache_size.saturating_mul(1024 * 1024) / ENTRY_SIZE,
+			params.utxo_cache_flush_interval,
+		)
+	}
+
+	/// An empty cache over the state of `client` holding up to `capacity` outputs in memory.
+	fn with_capacity(client: Arc<C>, capacity: usize, flush_interval: u32) -> Self {
+		Self { client, capacity, flush_interval: flush_interval.max(1), layers: Default::default() }
+	}
+
+	/// The output at `outpoint`, from the cache or else the state at `at`.
+	///
+	/// The output may have been spent since, or not exist at `at`; see the module documentation.
+	/// Outputs an imported block spent are always read from the state at `at`.
+	pub fn get(&self, at: H256, outpoint: &H256) -> Option<TransactionOutput> {
+		if self.capacity == 0 {
+			return self.client.state_output(at, outpoint)
+		}
+
+		{
+			let layers = self.layers.lock().expect("not poisoned");
+			if let Some(output) = layers.entries.get(outpoint) {
+				return Some(output.clone())
+			}
+			match layers.dirty.get(outpoint) {
+				Some(Some(output)) => return Some(output.clone()),
+				// Spent on the fork of some imported block, not necessarily that of `at`
+				Some(None) => return self.client.state_output(at, outpoint),
+				None => {},
+			}
+		}
+
+		let output = self.index_output(outpoint).or_else(|| self.client.state_output(at, outpoint))?;
+		self.layers.lock().expect("not poisoned").insert(*outpoint, output.clone(), self.capacity);
+		Some(output)
+	}
+
+	/// Record the outputs created and spent by `transactions` of a newly imported block, writing
+	/// them to disk once `--utxo-cache-flush-interval` blocks have accumulated.
+	pub fn connect_block(&self, transactions: &[Transaction]) {
+		if self.capacity == 0 {
+			return
+		}
+
+		let mut layers = self.layers.lock().expect("not poisoned");
+		for transaction in transactions {
+			for input in transaction.inputs.iter() {
+				layers.entries.remove(&input.outpoint);
+				layers.dirty.insert(input.outpoint, None);
+			}
+			let txid = transaction.txid();
+			for (index, output) in transaction.outputs.iter().enumerate() {
+				let outpoint = Transaction::outpoint_of(&txid, index as u64);
+				layers.insert(outpoint, output.clone(), self.capacity);
+				layers.dirty.insert(outpoint, Some(output.clone()));
+			}
+		}
+
+		layers.blocks += 1;
+		if layers.blocks >= self.flush_interval {
+			self.flush(&mut layers);
+		}
+	}
+
+	/// Write the pending changes to the on-disk index.
+	fn flush(&self, layers: &mut Layers) {
+		layers.blocks = 0;
+		if layers.dirty.is_empty() {
+			return
+		}
+
+		let (written, spent): (Vec<_>, Vec<_>) =
+			layers.dirty.drain().partition(|(_, output)| output.is_some());
+		let keys = written.iter().map(|(outpoint, _)| index_key(outpoint)).collect::<Vec<_>>();
+		let values = written.iter().map(|(_, output)| output.encode()).collect::<Vec<_>>();
+		let inserts = keys
+			.iter()
+			.zip(&values)
+			.map(|(key, value)| (&key[..], &value[..]))
+			.collect::<Vec<_>>();
+		let deleted = spent.iter().map(|(outpoint, _)| index_key(outpoint)).collect::<Vec<_>>();
+		let deletes = deleted.iter().map(|key| &key[..]).collect::<Vec<_>>();
+
+		match self.client.insert_aux(&inserts, &deletes) {
+			Ok(()) => log::debug!(
+				target: "utxo-cache",
+				"Flushed {} created and {} spent outputs",
+				written.len(),
+				spent.len(),
+			),
+			// Outputs missing from the index are read from the state instead
+			Err(e) => log::warn!(target: "utxo-cache", "Failed to flush the UTXO cache: {e}"),
+		}
+	}
+
+	fn index_output(&self, outpoint: &H256) -> Option<TransactionOutput> {
+		let data = self.client.get_aux(&index_key(outpoint)).ok()??;
+		TransactionOutput::decode(&mut &data[..]).ok()
+	}
+}
+
+impl Layers {
+	/// Hold `output` in memory, evicting the oldest entries beyond `capacity`.
+	fn insert(&mut self, outpoint: H256, output: TransactionOutput, capacity: usize) {
+		if self.entries.insert(outpoint, output).is_none() {
+			self.order.push_back(outpoint);
+		}
+		while self.entries.len() > capacity {
+			let Some(oldest) = self.order.pop_front() else { break };
+			self.entries.remove(&oldest);
+		}
+		// Drop outpoints removed from the map on spending, once they pile up
+		if self.order.len() > capacity.saturating_mul(2) {
+			let entries = &self.entries;
+			self.order.retain(|outpoint| entries.contains_key(outpoint));
+		}
+	}
+}
+
+impl<C: UtxoState> Drop for UtxoCache<C> {
+	fn drop(&mut self) {
+		let layers = self.layers.get_mut().expect("not poisoned");
+		let mut layers = std::mem::take(layers);
+		self.flush(&mut layers);
+	}
+}
+
+fn index_key(outpoint: &H256) -> Vec<u8> {
+	[INDEX_PREFIX, outpoint.as_bytes()].concat()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sc_client_api::in_mem;
+	use std::{
+		collections::HashSet,
+		sync::atomic::{AtomicUsize, Ordering},
+	};
+	use tumuchain_runtime::opaque::Block;
+	use utxo::TransactionInput;
+
+	/// A state holding `outputs` except those `spent` at a block, counting the reads of it, over an
+	/// in-memory auxiliary storage.
+	struct TestState {
+		aux: in_mem::Backend<Block>,
+		outputs: HashMap<H256, TransactionOutput>,
+		spent: HashSet<(H256, H256)>,
+		reads: AtomicUsize,
+	}
+
+	impl AuxStore for TestState {
+		fn insert_aux<
+			'a,
+			'b: 'a,
+			'c: 'a,
+			I: IntoIterator<Item = &'a (&'c [u8], &'c [u8])>,
+			D: IntoIterator<Item = &'a &'b [u8]>,
+		>(
+			&self,
+			insert: I,
+			delete: D,
+		) -> sp_blockchain::Result<()> {
+			self.aux.insert_aux(insert, delete)
+		}
+
+		fn get_aux(&self, key: &[u8]) -> sp_blockchain::Result<Option<Vec<u8>>> {
+			self.aux.get_aux(key)
+		}
+	}
+
+	impl UtxoState for TestState {
+		fn state_output(&self, at: H256, outpoint: &H256) -> Option<TransactionOutput> {
+			self.reads.fetch_add(1, Ordering::Relaxed);
+			if self.spent.contains(&(at, *outpoint)) {
+				return None
+			}
+			self.outputs.get(outpoint).cloned()
+		}
+	}
+
+	const GENESIS: H256 = H256::repeat_byte(1);
+
+	fn output(value: u8) -> TransactionOutput {
+		TransactionOutput { value: value.into(), pubkey: H256::repeat_byte(value), memo: None }
+	}
+
+	fn state() -> Arc<TestState> {
+		state_spending(&[])
+	}
+
+	/// The state holding the genesis output, spent at the blocks `spent_at`.
+	fn state_spending(spent_at: &[H256]) -> Arc<TestState> {
+		Arc::new(TestState {
+			aux: in_mem::Backend::new(),
+			outputs: [(GENESIS, output(1))].into_iter().collect(),
+			spent: spent_at.iter().map(|block| (*block, GENESIS)).collect(),
+			reads: AtomicUsize::new(0),
+		})
+	}
+
+	/// A transaction spending `outpoints` to `values`, and the outpoints it creates.
+	fn spend(outpoints: &[H256], values: &[u8]) -> (Transaction, Vec<H256>) {
+		let transaction = Transaction {
+			inputs: outpoints
+				.iter()
+				.map(|outpoint| TransactionInput {
+					outpoint: *outpoint,
+					sigscript: None,
+					witness: None,
+				})
+				.collect::<Vec<_>>()
+				.try_into()
+				.unwrap(),
+			outputs: values.iter().map(|value| output(*value)).collect::<Vec<_>>().try_into().unwrap(),
+			..Default::default()
+		};
+		let txid = transaction.txid();
+		let created = (0..values.len() as u64).map(|i| Transaction::outpoint_of(&txid, i)).collect();
+		(transaction, created)
+	}
+
+	fn indexed(state: &TestState, outpoint: &H256) -> Option<TransactionOutput> {
+		let data = state.get_aux(&index_key(outpoint)).unwrap()?;
+		Some(TransactionOutput::decode(&mut &data[..]).unwrap())
+	}
+
+	#[test]
+	fn lookups_read_the_state_once() {
+		let state = state();
+		let cache = UtxoCache::with_capacity(state.clone(), 10, 1);
+		assert_eq!(cache.get(H256::zero(), &GENESIS), Some(output(1)));
+		assert_eq!(cache.get(H256::zero(), &GENESIS), Some(output(1)));
+		assert_eq!(state.reads.load(Ordering::Relaxed), 1);
+
+		// Without memory for it the cache reads through, and keeps no index
+		let disabled = UtxoCache::with_capacity(state.clone(), 0, 1);
+		disabled.get(H256::zero(), &GENESIS);
+		disabled.connect_block(&[spend(&[GENESIS], &[2]).0]);
+		assert_eq!(state.reads.load(Ordering::Relaxed), 2);
+		assert_eq!(indexed(&state, &GENESIS), None);
+	}
+
+	#[test]
+	fn changes_are_written_to_the_index_every_flush_interval() {
+		let state = state();
+		state.insert_aux(&[(&index_key(&GENESIS)[..], &output(1).encode()[..])], &[]).unwrap();
+		let cache = UtxoCache::with_capacity(state.clone(), 10, 2);
+
+		let (transaction, created) = spend(&[GENESIS], &[2, 3]);
+		cache.connect_block(&[transaction]);
+		assert_eq!(indexed(&state, &created[0]), None);
+		assert_eq!(indexed(&state, &GENESIS), Some(output(1)));
+		// Pending changes are served before they are written
+		assert_eq!(cache.get(H256::zero(), &created[1]), Some(output(3)));
+
+		cache.connect_block(&[]);
+		assert_eq!(indexed(&state, &created[0]), Some(output(2)));
+		assert_eq!(indexed(&state, &created[1]), Some(output(3)));
+		assert_eq!(indexed(&state, &GENESIS), None);
+		assert_eq!(state.reads.load(Ordering::Relaxed), 0);
+	}
+
+	#[test]
+	fn outputs_spent_on_one_fork_are_found_on_the_other() {
+		let (spending, sibling) = (H256::repeat_byte(0xa), H256::repeat_byte(0xb));
+		let state = state_spending(&[spending]);
+		let cache = UtxoCache::with_capacity(state.clone(), 10, 2);
+		assert_eq!(cache.get(sibling, &GENESIS), Some(output(1)));
+
+		// Imported on the fork of `spending`
+		cache.connect_block(&[spend(&[GENESIS], &[2]).0]);
+		assert_eq!(cache.get(spending, &GENESIS), None);
+		assert_eq!(cache.get(sibling, &GENESIS), Some(output(1)));
+
+		// And once written to the index
+		cache.connect_block(&[]);
+		assert_eq!(indexed(&state, &GENESIS), None);
+		assert_eq!(cache.get(spending, &GENESIS), None);
+		assert_eq!(cache.get(sibling, &GENESIS), Some(output(1)));
+	}
+
+	#[test]
+	fn oldest_outputs_are_evicted_beyond_the_capacity() {
+		let state = state();
+		let cache = UtxoCache::with_capacity(state.clone(), 2, 1);
+
+		let (transaction, created) = spend(&[GENESIS], &[2, 3, 4]);
+		cache.connect_block(&[transaction]);
+		{
+			let layers = cache.layers.lock().unwrap();
+			assert_eq!(layers.entries.len(), 2);
+			assert!(!layers.entries.contains_key(&created[0]));
+		}
+
+		// The evicted output comes back from the index, evicting the next oldest
+		assert_eq!(cache.get(H256::zero(), &created[0]), Some(output(2)));
+		assert_eq!(state.reads.load(Ordering::Relaxed), 0);
+		let layers = cache.layers.lock().unwrap();
+		assert_eq!(layers.entries.len(), 2);
+		assert!(!layers.entries.contains_key(&created[1]));
+	}
+
+	#[test]
+	fn spent_outpoints_do_not_pile_up_in_the_eviction_queue() {
+		let cache = UtxoCache::with_capacity(state(), 2, 1);
+
+		let mut outpoint = GENESIS;
+		for value in 2..20 {
+			let (transaction, created) = spend(&[outpoint], &[value]);
+			cache.connect_block(&[transaction]);
+			outpoint = created[0];
+		}
+		let layers = cache.layers.lock().unwrap();
+		assert_eq!(layers.entries.len(), 1);
+		assert!(layers.order.len() <= 4);
+	}
+
+	#[test]
+	fn pending_changes_are_written_when_dropped() {
+		let state = state();
+		let cache = UtxoCache::with_capacity(state.clone(), 10, 100);
+
+		let (transaction, created) = spend(&[GENESIS], &[2]);
+		cache.connect_block(&[transaction]);
+		assert_eq!(indexed(&state, &created[0]), None);
+
+		drop(cache);
+		assert_eq!(indexed(&state, &created[0]), Some(output(2)));
+	}
+}
@@ -0,0 +1,144 @@
+//! The `wallet bump` command.
+//!
+//! Speeds up a transaction sent from the wallet with a child paying for its parent (CPFP): a
+//! transaction spending the parent's change back to the wallet, paying a fee high enough for the
+//! two together to pay the fee rate asked for. Confirmed outputs are added to the child when the
+//! change does not cover its fee. The pool ranks the parent by the fee rate of the two when
+//! evicting, see [`crate::mempool`].
+//!
+//! Replacing the parent by a transaction spending the same outputs at a higher fee (RBF) is not
+//! offered: the pool does not treat such transactions as conflicting, and would keep both until
+//! one of them is included.
+
+use std::collections::HashSet;
+
+use sc_cli::Error;
+use sp_core::{Pair, H256};
+use utxo::{TransactionOutput, Value};
+
+use super::{
+	extrinsic,
+	manage::WalletParams,
+	report::Node,
+	send::{signed_transaction, sum, sum_outputs},
+	store::WalletKey,
+};
+use crate::mempool::virtual_size;
+
+/// The `wallet bump` command.
+#[derive(Debug, clap::Parser)]
+pub struct BumpCmd {
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub wallet: WalletParams,
+
+	/// The txid of the transaction to speed up, sent with `wallet send` or `wallet bump`.
+	#[arg(long, value_name = "TXID")]
+	pub txid: H256,
+
+	/// Fee per virtual byte the transaction and its child are to pay together, in base units.
+	#[arg(long, value_name = "FEE")]
+	pub fee_rate: Value,
+
+	/// HTTP RPC endpoint of the node to submit to.
+	#[arg(long, default_value = "http://127.0.0.1:9944")]
+	pub url: String,
+
+	/// Only print the child transaction.
+	#[arg(long)]
+	pub dry_run: bool,
+}
+
+impl BumpCmd {
+	/// Run the command.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		let mut wallet = self.wallet.unlock()?;
+		let data = &wallet.data;
+		let parent = data.pending.iter().find(|transaction| transaction.txid() == self.txid);
+		let parent = parent.ok_or_else(|| {
+			Error::Input(format!("{:?} is not a transaction the wallet is waiting on", self.txid))
+		})?;
+		let pairs = data.keys.iter().map(WalletKey::pair).collect::<sc_cli::Result<Vec<_>>>()?;
+		let keys: HashSet<_> = pairs.iter().map(|pair| H256(pair.public().0)).collect();
+
+		// The parent may spend the outputs of another pending transaction
+		let pending_outputs = data.pending_outputs();
+		let parent_inputs = parent
+			.inputs
+			.iter()
+			.map(|input| {
+				let cached = data.outputs.iter().find(|(outpoint, _)| *outpoint == input.outpoint);
+				cached.map(|(_, output)| output).or_else(|| pending_outputs.get(&input.outpoint))
+			})
+			.collect::<Option<Vec<_>>>()
+			.ok_or_else(|| Error::Input("the outputs the transaction spends are unknown".into()))?;
+		let parent_fee =
+			sum_outputs(parent_inputs).saturating_sub(sum_outputs(parent.outputs.iter()));
+		// Sized as the pool ranks them, with witness bytes discounted
+		let parent_size = virtual_size(&extrinsic(parent)) as Value;
+		if parent_fee >= self.fee_rate.saturating_mul(parent_size) {
+			return Err(Error::Input(format!(
+				"the transaction pays {parent_fee} for {parent_size} bytes already"
+			)))
+		}
+
+		// The parent's change, unless a later transaction spent it or it was locked
+		let spent: HashSet<_> = data
+			.pending
+			.iter()
+			.flat_map(|transaction| transaction.inputs.iter().map(|input| input.outpoint))
+			.collect();
+		let mut inputs: Vec<_> = (0..parent.outputs.len() as u64)
+			.map(|n| (parent.outpoint(n), parent.outputs[n as usize].clone()))
+			.filter(|(outpoint, output)| {
+				keys.contains(&output.pubkey) &&
+					!spent.contains(outpoint) &&
+					!data.locked.contains(outpoint)
+			})
+			.collect();
+		let Some((_, change)) = inputs.first() else {
+			return Err(Error::Input("the transaction has no change left to spend".into()))
+		};
+		let pubkey = change.pubkey;
+
+		let mut confirmed = data.spendable();
+		confirmed.sort_by(|(a, x), (b, y)| x.value.cmp(&y.value).then(b.cmp(a)));
+		let (child, fee) = loop {
+			// Sized with the inputs signed, as the value paid does not change the size
+			let total = sum(&inputs);
+			let output = TransactionOutput { value: total, pubkey, memo: None };
+			let unpaid = signed_transaction(&inputs, vec![output], &pairs)?;
+			let child_size = virtual_size(&extrinsic(&unpaid)) as Value;
+			let fee = self
+				.fee_rate
+				.saturating_mul(parent_size + child_size)
+				.saturating_sub(parent_fee);
+			if total > fee {
+				let output = TransactionOutput { value: total - fee, pubkey, memo: None };
+				break (signed_transaction(&inputs, vec![output], &pairs)?, fee)
+			}
+			let more = confirmed.pop().ok_or_else(|| {
+				Error::Input(format!("the wallet cannot pay the {fee} the child needs"))
+			})?;
+			inputs.push(more);
+		};
+
+		let package_size = parent_size + virtual_size(&extrinsic(&child)) as Value;
+		println!("Parent {:?} pays {parent_fee} for {parent_size} bytes", self.txid);
+		println!(
+			"Child {:?} spends {} inputs and pays {fee}, {} for {package_size} bytes together",
+			child.txid(),
+			child.inputs.len(),
+			parent_fee + fee,
+		);
+		if self.dry_run {
+			return Ok(())
+		}
+
+		Node::connect(&self.url)?.submit(&child)?;
+		wallet.data.pending.push(child);
+		wallet.save()?;
+		println!("Submitted");
+		Ok(())
+	}
+}
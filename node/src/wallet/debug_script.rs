@@ -0,0 +1,112 @@
+//! The `wallet debug-script` command.
+//!
+//! Steps through a script spend offline, printing the stack after each instruction, so a script
+//! can be tried before an output is locked to it. The script and its initial stack are given on
+//! the command line, or taken from the witness of an input of a spending transaction, whose
+//! signing payload the signature opcodes then check against. The limits are the runtime's.
+
+use codec::Decode;
+use sc_cli::Error;
+use sp_core::{hexdisplay::HexDisplay, Bytes, Get};
+use tumuchain_runtime::{MaxScriptOps, MaxScriptSize, MaxStackDepth};
+use utxo::{
+	debugger::trace,
+	script::{script_hash, ScriptContext, ScriptLimits},
+	Transaction,
+};
+
+/// The `wallet debug-script` command.
+#[derive(Debug, clap::Parser)]
+pub struct DebugScriptCmd {
+	/// The script, as hex. Defaults to the witness script of the input of `--transaction`.
+	#[arg(long, value_name = "HEX", required_unless_present = "transaction")]
+	pub script: Option<Bytes>,
+
+	/// An item of the initial stack, as hex, bottom first. Repeat for each item. Defaults to the
+	/// witness stack of the input of `--transaction`.
+	#[arg(long = "stack-item", value_name = "HEX")]
+	pub stack: Vec<Bytes>,
+
+	/// The spending transaction, SCALE-encoded as hex. Without it, signatures check against an
+	/// empty payload, and so fail.
+	#[arg(long, value_name = "HEX")]
+	pub transaction: Option<Bytes>,
+
+	/// The input of `--transaction` spending the output locked to the script.
+	#[arg(long, default_value_t = 0)]
+	pub input: usize,
+
+	/// Number of the block the spend is validated in, compared to by `OP_CHECKLOCKTIMEVERIFY`.
+	#[arg(long, value_name = "NUMBER", default_value_t = 0)]
+	pub block_number: u64,
+}
+
+impl DebugScriptCmd {
+	/// Run the command.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		let transaction = match &self.transaction {
+			Some(hex) => Some(
+				Transaction::decode(&mut &hex[..])
+					.map_err(|e| Error::Input(format!("invalid transaction: {e}")))?,
+			),
+			None => None,
+		};
+		let witness = match &transaction {
+			Some(transaction) => {
+				let input = transaction.inputs.get(self.input).ok_or_else(|| {
+					Error::Input(format!("the transaction has no input {}", self.input))
+				})?;
+				input.witness.clone()
+			},
+			None => None,
+		};
+
+		let script = match (&self.script, &witness) {
+			(Some(script), _) => script.to_vec(),
+			(None, Some(witness)) => witness.script.to_vec(),
+			(None, None) => return Err(Error::Input("the input has no witness script".into())),
+		};
+		let stack: Vec<Vec<u8>> = match (self.stack.is_empty(), &witness) {
+			(true, Some(witness)) => witness.stack.iter().map(|item| item.to_vec()).collect(),
+			_ => self.stack.iter().map(|item| item.to_vec()).collect(),
+		};
+		let payload = transaction.as_ref().map(Transaction::signing_payload).unwrap_or_default();
+		let context = ScriptContext { payload: &payload, block_number: self.block_number };
+		let limits = ScriptLimits {
+			max_script_size: MaxScriptSize::get(),
+			max_ops: MaxScriptOps::get(),
+			max_stack_depth: MaxStackDepth::get(),
+		};
+
+		println!("Script hash {:?}", script_hash(&script));
+		println!("Initial stack");
+		print_stack(&stack);
+		let traced = trace(&script, stack, &context, &limits);
+		for step in &traced.steps {
+			let skipped = if step.executed { "" } else { " (skipped)" };
+			println!("{:>5}  {}{skipped}", step.offset, step.instruction);
+			print_stack(&step.stack);
+		}
+		match traced.result {
+			Ok(()) => {
+				println!("The spend succeeds");
+				Ok(())
+			},
+			// The instruction that failed is the one after the last step
+			Err(e) => Err(Error::Input(format!(
+				"the spend fails after {} steps: {e:?}",
+				traced.steps.len()
+			))),
+		}
+	}
+}
+
+/// Print `stack`, top first, an item a line.
+fn print_stack(stack: &[Vec<u8>]) {
+	if stack.is_empty() {
+		println!("       (empty)");
+	}
+	for (depth, item) in stack.iter().rev().enumerate() {
+		println!("       [{depth}] 0x{}", HexDisplay::from(item));
+	}
+}
@@ -0,0 +1,78 @@
+//! The `wallet history` and `wallet label` commands.
+//!
+//! Labels are the user's own notes on transactions and addresses, kept in the wallet only and
+//! never sent to a node.
+
+use super::{manage::WalletParams, parse_public};
+use sp_core::H256;
+use utxo::denomination::FormattedValue;
+
+/// The `wallet history` command, listing the transactions found by syncing, oldest first, with
+/// their labels and those of their counterparties.
+#[derive(Debug, clap::Parser)]
+pub struct HistoryCmd {
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub wallet: WalletParams,
+}
+
+impl HistoryCmd {
+	/// Run the command.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		let data = self.wallet.unlock()?.data;
+		let labelled = |pubkey: &H256| match data.labels.get(pubkey) {
+			Some(label) => format!("{label} ({pubkey:?})"),
+			None => format!("{pubkey:?}"),
+		};
+
+		println!("block\ttxid\tdirection\treceived\tsent\tfee\tbalance\tlabel\tcounterparties");
+		for row in &data.history {
+			let counterparties: Vec<_> = row.counterparties.iter().map(labelled).collect();
+			println!(
+				"{}\t{:?}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+				row.block_number,
+				row.txid,
+				row.direction.as_str(),
+				FormattedValue(row.received),
+				FormattedValue(row.sent),
+				row.fee.map(|fee| FormattedValue(fee).to_string()).unwrap_or_default(),
+				FormattedValue(row.balance),
+				data.labels.get(&row.txid).map(String::as_str).unwrap_or_default(),
+				counterparties.join(", "),
+			);
+		}
+		if data.synced_number.is_none() {
+			eprintln!("Never synced, run `wallet unlock --sync`");
+		}
+		Ok(())
+	}
+}
+
+/// The `wallet label` command, attaching a label or memo to a transaction or address.
+#[derive(Debug, clap::Parser)]
+pub struct LabelCmd {
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub wallet: WalletParams,
+
+	/// The txid, or the pubkey as SS58 or 0x-prefixed hex.
+	#[arg(value_name = "TXID_OR_PUBKEY")]
+	pub target: String,
+
+	/// The label or memo. Leave out to remove the label.
+	#[arg(value_name = "TEXT")]
+	pub text: Option<String>,
+}
+
+impl LabelCmd {
+	/// Run the command.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		let target = H256(parse_public(&self.target)?.0);
+		let mut wallet = self.wallet.unlock()?;
+		match self.text.as_deref().map(str::trim).filter(|text| !text.is_empty()) {
+			Some(text) => wallet.data.labels.insert(target, text.into()),
+			None => wallet.data.labels.remove(&target),
+		};
+		wallet.save()
+	}
+}
@@ -0,0 +1,80 @@
+//! The `wallet lock-utxo` and `wallet unlock-utxo` commands.
+//!
+//! Locked outputs, e.g. vault outputs or outputs to keep apart, are never picked to fund `wallet
+//! send` or `wallet bump`. Locks are kept in the wallet only, so the outputs can still be spent
+//! by other tools.
+
+use sp_core::H256;
+use utxo::denomination::FormattedValue;
+
+use super::manage::WalletParams;
+
+/// The `wallet lock-utxo` command. Without outpoints, lists the locked outputs.
+#[derive(Debug, clap::Parser)]
+pub struct LockUtxoCmd {
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub wallet: WalletParams,
+
+	/// The outpoints of the outputs to lock.
+	#[arg(value_name = "OUTPOINT")]
+	pub outpoints: Vec<H256>,
+}
+
+impl LockUtxoCmd {
+	/// Run the command.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		let mut wallet = self.wallet.unlock()?;
+		for outpoint in &self.outpoints {
+			if !wallet.data.outputs.iter().any(|(cached, _)| cached == outpoint) {
+				eprintln!("{outpoint:?} is not among the wallet's unspent outputs as of its sync");
+			}
+			wallet.data.locked.insert(*outpoint);
+		}
+		if !self.outpoints.is_empty() {
+			wallet.save()?;
+		}
+
+		for outpoint in &wallet.data.locked {
+			match wallet.data.outputs.iter().find(|(cached, _)| cached == outpoint) {
+				Some((_, output)) =>
+					println!("{outpoint:?}\t{}\t{:?}", FormattedValue(output.value), output.pubkey),
+				// Spent, or not synced yet
+				None => println!("{outpoint:?}"),
+			}
+		}
+		Ok(())
+	}
+}
+
+/// The `wallet unlock-utxo` command.
+#[derive(Debug, clap::Parser)]
+pub struct UnlockUtxoCmd {
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub wallet: WalletParams,
+
+	/// The outpoints of the outputs to unlock.
+	#[arg(value_name = "OUTPOINT", required_unless_present = "all")]
+	pub outpoints: Vec<H256>,
+
+	/// Unlock every locked output.
+	#[arg(long, conflicts_with = "outpoints")]
+	pub all: bool,
+}
+
+impl UnlockUtxoCmd {
+	/// Run the command.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		let mut wallet = self.wallet.unlock()?;
+		if self.all {
+			wallet.data.locked.clear();
+		}
+		for outpoint in &self.outpoints {
+			if !wallet.data.locked.remove(outpoint) {
+				eprintln!("{outpoint:?} was not locked");
+			}
+		}
+		wallet.save()
+	}
+}
@@ -0,0 +1,352 @@
+//! The `wallet create`, `list`, `unlock`, `backup` and `restore` commands, managing the named
+//! wallets of [`super::store`].
+
+use std::{collections::HashSet, fs, mem, path::PathBuf};
+
+use sc_cli::{Error, SubstrateCli};
+use sc_service::BasePath;
+use sp_core::{crypto::Ss58Codec, sr25519, H256};
+use tumuchain_runtime::BlockNumber;
+use utxo::{denomination::FormattedValue, Transaction};
+
+use super::{
+	mnemonic,
+	report::{Node, Report},
+	store::{self, Wallet, WalletData, WalletDir, WalletKey},
+};
+use crate::cli::Cli;
+
+/// Where a wallet is and how to unlock it.
+#[derive(Debug, Clone, clap::Args)]
+pub struct WalletParams {
+	/// Name of the wallet.
+	#[arg(long, value_name = "NAME")]
+	pub name: String,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub dir: WalletDirParams,
+
+	/// File to read the passphrase from, instead of prompting for it.
+	#[arg(long, value_name = "PATH")]
+	pub passphrase_file: Option<PathBuf>,
+}
+
+impl WalletParams {
+	/// The file of the wallet.
+	pub fn path(&self) -> sc_cli::Result<PathBuf> {
+		self.dir.open()?.path_of(&self.name)
+	}
+
+	/// Read the passphrase, asking for it twice when prompting for a new one.
+	pub fn passphrase(&self, new: bool) -> sc_cli::Result<String> {
+		if let Some(path) = &self.passphrase_file {
+			return Ok(fs::read_to_string(path)?.trim_end_matches(['\r', '\n']).into())
+		}
+		match new {
+			true => prompt_new("Wallet passphrase: "),
+			false => Ok(rpassword::prompt_password("Wallet passphrase: ")?),
+		}
+	}
+
+	/// Unlock the wallet.
+	pub fn unlock(&self) -> sc_cli::Result<Wallet> {
+		let path = self.path()?;
+		if !path.exists() {
+			return Err(Error::Input(format!("no wallet named {}", self.name)))
+		}
+		Wallet::open(&path, &self.passphrase(false)?)
+	}
+}
+
+/// Where the wallets are.
+#[derive(Debug, Clone, clap::Args)]
+pub struct WalletDirParams {
+	/// Directory holding the wallets. Defaults to `wallets` in the node's default base path.
+	#[arg(long, value_name = "PATH")]
+	pub wallet_dir: Option<PathBuf>,
+}
+
+impl WalletDirParams {
+	fn open(&self) -> sc_cli::Result<WalletDir> {
+		let path = match &self.wallet_dir {
+			Some(path) => path.clone(),
+			None => BasePath::from_project("", "", &Cli::executable_name()).path().join("wallets"),
+		};
+		WalletDir::open(path)
+	}
+}
+
+/// The `wallet create` command.
+#[derive(Debug, clap::Parser)]
+pub struct CreateCmd {
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub wallet: WalletParams,
+
+	/// Secret URI of a key to import rather than generating one, e.g. a 0x-prefixed seed.
+	/// Repeat to import several keys.
+	#[arg(long = "secret-uri", value_name = "SECRET_URI")]
+	pub secret_uris: Vec<String>,
+
+	/// Words of the mnemonic generated for the key, 12 or 24.
+	#[arg(long, value_name = "COUNT", default_value_t = 12)]
+	pub words: usize,
+
+	/// Recover the key from a mnemonic, prompted for, rather than generating one.
+	#[arg(long, conflicts_with = "secret_uris")]
+	pub recover: bool,
+
+	/// Protect the mnemonic with a passphrase of its own, prompted for. Recovering the key needs
+	/// both the words and this passphrase, which is unrelated to the wallet's.
+	#[arg(long, conflicts_with = "secret_uris")]
+	pub mnemonic_passphrase: bool,
+}
+
+impl CreateCmd {
+	/// Run the command.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		let path = self.wallet.path()?;
+		if path.exists() {
+			return Err(Error::Input(format!("a wallet named {} exists", self.wallet.name)))
+		}
+
+		let mut generated = None;
+		let keys = if !self.secret_uris.is_empty() {
+			self.secret_uris.iter().map(|uri| WalletKey { secret_uri: uri.clone() }).collect()
+		} else {
+			let words = if self.recover {
+				mnemonic::parse(&rpassword::prompt_password("Mnemonic: ")?)?
+			} else {
+				mnemonic::generate(self.words)?
+			};
+			let passphrase = match self.mnemonic_passphrase {
+				true => Some(prompt_new("Mnemonic passphrase: ")?),
+				false => None,
+			};
+			let secret_uri = mnemonic::secret_uri(&words, passphrase.as_deref());
+			generated = (!self.recover).then_some(words);
+			vec![WalletKey { secret_uri }]
+		};
+		let data = WalletData { keys, ..Default::default() };
+		let pubkeys = data.pubkeys()?;
+
+		let passphrase = self.wallet.passphrase(true)?;
+		if passphrase.is_empty() {
+			return Err(Error::Input("an empty passphrase would leave the keys readable".into()))
+		}
+		let wallet = Wallet::new(path, &passphrase, data)?;
+		wallet.save()?;
+
+		println!("Created {}", wallet.path().display());
+		for pubkey in pubkeys {
+			print_pubkey(pubkey);
+		}
+		if let Some(words) = generated {
+			println!("Write down the mnemonic, which recovers the key if the wallet is lost:");
+			println!("{words}");
+		}
+		Ok(())
+	}
+}
+
+/// The `wallet list` command.
+#[derive(Debug, clap::Parser)]
+pub struct ListCmd {
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub dir: WalletDirParams,
+}
+
+impl ListCmd {
+	/// Run the command.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		for name in self.dir.open()?.list()? {
+			println!("{name}");
+		}
+		Ok(())
+	}
+}
+
+/// The `wallet unlock` command.
+///
+/// Decrypts the wallet and shows its keys and cached balance, refreshing the cache first with
+/// `--sync`. Syncing reads the blocks finalized since the last sync, so the node must keep their
+/// state, as it does for the last 256 blocks unless configured otherwise.
+#[derive(Debug, clap::Parser)]
+pub struct UnlockCmd {
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub wallet: WalletParams,
+
+	/// Read the history and unspent outputs of the keys from the node, up to its last finalized
+	/// block, and save them in the wallet.
+	#[arg(long)]
+	pub sync: bool,
+
+	/// Rebuild the history from this block when syncing, e.g. for keys recovered from a
+	/// mnemonic. A first sync otherwise starts the history at the node's last finalized block.
+	#[arg(long, value_name = "NUMBER", requires = "sync")]
+	pub rescan_from: Option<BlockNumber>,
+
+	/// HTTP RPC endpoint of the node to sync with.
+	#[arg(long, default_value = "http://127.0.0.1:9944")]
+	pub url: String,
+
+	/// Also print the secret URIs of the keys, e.g. to write their mnemonics down again.
+	#[arg(long)]
+	pub show_secrets: bool,
+}
+
+impl UnlockCmd {
+	/// Run the command.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		let mut wallet = self.wallet.unlock()?;
+		let pubkeys = wallet.data.pubkeys()?;
+
+		if self.sync {
+			self.catch_up(&mut wallet.data, &pubkeys)?;
+			wallet.save()?;
+		}
+
+		for (pubkey, key) in pubkeys.into_iter().zip(&wallet.data.keys) {
+			print_pubkey(pubkey);
+			if self.show_secrets {
+				println!("\t{}", key.secret_uri);
+			}
+		}
+		match wallet.data.synced_at {
+			Some(at) => println!(
+				"{} unspent outputs, balance {} as of {at:?}",
+				wallet.data.outputs.len(),
+				FormattedValue(wallet.data.balance())
+			),
+			None => println!("Never synced, run with --sync"),
+		}
+		if !wallet.data.locked.is_empty() {
+			println!("{} outputs locked", wallet.data.locked.len());
+		}
+		Ok(())
+	}
+
+	/// Add the transactions of the blocks finalized since the last sync to the history, and
+	/// bring the unspent outputs up to date.
+	fn catch_up(&self, data: &mut WalletData, pubkeys: &[H256]) -> sc_cli::Result<()> {
+		let node = Node::connect(&self.url)?;
+		let to = node.finalized_number()?;
+		let keys = pubkeys.iter().copied().collect();
+
+		let (mut report, from) = match (self.rescan_from, data.synced_number) {
+			(Some(from), _) => {
+				let from = from.clamp(1, to + 1);
+				data.history.retain(|row| row.block_number < from);
+				(Report::open(&node, keys, from)?, from)
+			},
+			// The outputs cached are those as of the last sync
+			(None, Some(synced)) =>
+				(Report::resume(keys, data.outputs.iter().cloned().collect()), synced + 1),
+			// Keys new to the chain have no history before their first sync
+			(None, None) => (Report::open(&node, keys, to + 1)?, to + 1),
+		};
+		for number in from..=to {
+			report.add_block(&node, number)?;
+		}
+
+		println!("Synced to #{to}, {} new transactions", report.rows.len());
+		let mut outputs: Vec<_> = report.owned.into_iter().collect();
+		outputs.sort_by_key(|(outpoint, _)| *outpoint);
+
+		let cached: HashSet<_> = outputs.iter().map(|(outpoint, _)| *outpoint).collect();
+		let pooled: HashSet<_> = node.pooled()?.iter().map(Transaction::txid).collect();
+		let mut pending = Vec::new();
+		// Outputs of the transactions still pending, which those sent after them may spend
+		let mut unconfirmed = HashSet::new();
+		for transaction in mem::take(&mut data.pending) {
+			// Its inputs are spent together, so any one tells whether it was finalized, or
+			// spends the output of a transaction that was dropped
+			let outpoint = transaction.inputs[0].outpoint;
+			if !cached.contains(&outpoint) && !unconfirmed.contains(&outpoint) {
+				continue
+			}
+			// Neither in the pool nor in a block: the node dropped it, releasing its inputs
+			let txid = transaction.txid();
+			if !pooled.contains(&txid) && node.is_unspent(outpoint)? {
+				println!("Sent transaction {txid:?} was dropped");
+				continue
+			}
+			unconfirmed.extend(
+				(0..transaction.outputs.len() as u64).map(|n| Transaction::outpoint_of(&txid, n)),
+			);
+			pending.push(transaction);
+		}
+		data.outputs = outputs;
+		data.pending = pending;
+		data.history.extend(report.rows);
+		data.synced_at = Some(node.hash(to)?);
+		data.synced_number = Some(to);
+		Ok(())
+	}
+}
+
+/// The `wallet backup` command, copying the encrypted wallet file once its passphrase checks out.
+#[derive(Debug, clap::Parser)]
+pub struct BackupCmd {
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub wallet: WalletParams,
+
+	/// File to write the backup to, which must not exist.
+	#[arg(long, short, value_name = "PATH")]
+	pub output: PathBuf,
+}
+
+impl BackupCmd {
+	/// Run the command.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		// A backup nobody can unlock is worse than none
+		let wallet = self.wallet.unlock()?;
+		store::copy(wallet.path(), &self.output)?;
+		println!("Backed up {} to {}", self.wallet.name, self.output.display());
+		Ok(())
+	}
+}
+
+/// The `wallet restore` command, adding a backup to the wallets under a name.
+#[derive(Debug, clap::Parser)]
+pub struct RestoreCmd {
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub wallet: WalletParams,
+
+	/// The backup to restore.
+	#[arg(long, short, value_name = "PATH")]
+	pub input: PathBuf,
+}
+
+impl RestoreCmd {
+	/// Run the command.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		let path = self.wallet.path()?;
+		let backup = Wallet::open(&self.input, &self.wallet.passphrase(false)?)?;
+		store::copy(&self.input, &path)?;
+
+		println!("Restored {} to {}", self.input.display(), path.display());
+		for pubkey in backup.data.pubkeys()? {
+			print_pubkey(pubkey);
+		}
+		Ok(())
+	}
+}
+
+/// Prompt for a new passphrase, twice.
+fn prompt_new(prompt: &str) -> sc_cli::Result<String> {
+	let passphrase = rpassword::prompt_password(prompt)?;
+	if rpassword::prompt_password("Repeat it: ")? != passphrase {
+		return Err(Error::Input("the passphrases differ".into()))
+	}
+	Ok(passphrase)
+}
+
+fn print_pubkey(pubkey: H256) {
+	println!("{}\t{pubkey:?}", sr25519::Public::from_raw(pubkey.0).to_ss58check());
+}
@@ -0,0 +1,37 @@
+//! BIP-39 mnemonics for wallet keys.
+//!
+//! A mnemonic maps to an sr25519 key the way Substrate tools such as `subkey` and polkadot-js map
+//! it: the mnemonic's entropy, not its seed as BIP-39 defines it, is stretched with PBKDF2 and the
+//! optional passphrase into the key's mini secret. The same words therefore give the same key in
+//! any of these tools, but not in wallets of other chains.
+
+use bip39::Mnemonic;
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+use sc_cli::Error;
+
+/// Word counts of the mnemonics generated.
+pub const WORD_COUNTS: [usize; 2] = [12, 24];
+
+/// A new random mnemonic of `words` words.
+pub fn generate(words: usize) -> sc_cli::Result<Mnemonic> {
+	if !WORD_COUNTS.contains(&words) {
+		return Err(Error::Input(format!("mnemonics have 12 or 24 words, not {words}")))
+	}
+	// 11 bits per word, of which 32 of every 33 are entropy
+	let mut entropy = vec![0; words / 3 * 4];
+	OsRng.fill_bytes(&mut entropy);
+	Mnemonic::from_entropy(&entropy).map_err(|e| Error::Application(Box::new(e)))
+}
+
+/// Parse `phrase`, checking its words and checksum.
+pub fn parse(phrase: &str) -> sc_cli::Result<Mnemonic> {
+	Mnemonic::parse(phrase.trim()).map_err(|e| Error::Input(format!("invalid mnemonic: {e}")))
+}
+
+/// The secret URI of the key of `mnemonic` under `passphrase`, as wallets store it.
+pub fn secret_uri(mnemonic: &Mnemonic, passphrase: Option<&str>) -> String {
+	match passphrase {
+		Some(passphrase) if !passphrase.is_empty() => format!("{mnemonic}///{passphrase}"),
+		_ => mnemonic.to_string(),
+	}
+}
@@ -0,0 +1,177 @@
+//! Wallet command line utilities for the UTXO chain.
+
+use sc_cli::Error;
+use sp_core::{
+	crypto::{KeyTypeId, Ss58Codec},
+	sr25519, H256,
+};
+use tumuchain_runtime::{RuntimeCall, UncheckedExtrinsic};
+use utxo::{derivation, Transaction};
+
+mod bump;
+mod debug_script;
+mod history;
+mod lock;
+mod manage;
+mod mnemonic;
+mod report;
+mod send;
+mod store;
+mod sweep;
+
+pub use bump::BumpCmd;
+pub use debug_script::DebugScriptCmd;
+pub use history::{HistoryCmd, LabelCmd};
+pub use lock::{LockUtxoCmd, UnlockUtxoCmd};
+pub use manage::{BackupCmd, CreateCmd, ListCmd, RestoreCmd, UnlockCmd};
+pub use report::ReportCmd;
+pub use send::SendCmd;
+pub use sweep::SweepCmd;
+
+/// Key type under which keystores hold wallet keys.
+pub const WALLET_KEY_TYPE: KeyTypeId = KeyTypeId(*b"wllt");
+
+/// Wallet sub-commands.
+#[derive(Debug, clap::Subcommand)]
+pub enum WalletCmd {
+	/// Derive custodian deposit pubkeys for an account over a range of indices.
+	DepositAddresses(DepositAddressesCmd),
+
+	/// Move every output of a key to another pubkey.
+	Sweep(SweepCmd),
+
+	/// Report the transactions of a keyset over a range of blocks, for accounting.
+	Report(ReportCmd),
+
+	/// Create a named wallet, encrypted under a passphrase.
+	Create(CreateCmd),
+
+	/// List the named wallets.
+	List(ListCmd),
+
+	/// Show the keys and balance of a wallet, optionally syncing it with a node.
+	Unlock(UnlockCmd),
+
+	/// Copy a wallet to a backup file.
+	Backup(BackupCmd),
+
+	/// Add a wallet from a backup file.
+	Restore(RestoreCmd),
+
+	/// List the transactions of a wallet found by syncing, with their labels.
+	History(HistoryCmd),
+
+	/// Attach a label or memo to a transaction or address in a wallet.
+	Label(LabelCmd),
+
+	/// Pay a pubkey from a wallet, optionally splitting the change for privacy.
+	Send(SendCmd),
+
+	/// Speed up a transaction sent from a wallet with a child paying for it.
+	Bump(BumpCmd),
+
+	/// Keep outputs of a wallet from being spent by `send` and `bump`, or list those kept.
+	LockUtxo(LockUtxoCmd),
+
+	/// Let locked outputs of a wallet be spent again.
+	UnlockUtxo(UnlockUtxoCmd),
+
+	/// Step through a script spend, printing the stack after each instruction.
+	DebugScript(DebugScriptCmd),
+}
+
+impl WalletCmd {
+	/// Run the wallet command.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		match self {
+			WalletCmd::DepositAddresses(cmd) => cmd.run(),
+			WalletCmd::Sweep(cmd) => cmd.run(),
+			WalletCmd::Report(cmd) => cmd.run(),
+			WalletCmd::Create(cmd) => cmd.run(),
+			WalletCmd::List(cmd) => cmd.run(),
+			WalletCmd::Unlock(cmd) => cmd.run(),
+			WalletCmd::Backup(cmd) => cmd.run(),
+			WalletCmd::Restore(cmd) => cmd.run(),
+			WalletCmd::History(cmd) => cmd.run(),
+			WalletCmd::Label(cmd) => cmd.run(),
+			WalletCmd::Send(cmd) => cmd.run(),
+			WalletCmd::Bump(cmd) => cmd.run(),
+			WalletCmd::LockUtxo(cmd) => cmd.run(),
+			WalletCmd::UnlockUtxo(cmd) => cmd.run(),
+			WalletCmd::DebugScript(cmd) => cmd.run(),
+		}
+	}
+}
+
+/// The `wallet deposit-addresses` command.
+#[derive(Debug, clap::Parser)]
+pub struct DepositAddressesCmd {
+	/// The custodian root public key, as SS58 or 0x-prefixed hex.
+	#[arg(long, value_name = "PUBLIC")]
+	pub root: String,
+
+	/// The account (user) the deposit keys belong to.
+	#[arg(long, default_value_t = 0)]
+	pub account: u32,
+
+	/// First deposit index to derive.
+	#[arg(long, default_value_t = 0)]
+	pub from: u32,
+
+	/// Number of consecutive deposit indices to derive.
+	#[arg(long, default_value_t = 20)]
+	pub count: u32,
+
+	/// Instead of listing, look up which index in the range maps to this pubkey.
+	#[arg(long, value_name = "PUBKEY")]
+	pub find: Option<H256>,
+}
+
+impl DepositAddressesCmd {
+	/// Run the command.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		let root = parse_public(&self.root)?;
+		let indices = self.from..self.from.saturating_add(self.count);
+
+		if let Some(pubkey) = &self.find {
+			match derivation::find_deposit_index(&root, self.account, pubkey, indices) {
+				Some(index) => println!("{}/{}", self.account, index),
+				None => return Err(Error::Input(format!("{:?} not found in range", pubkey))),
+			}
+			return Ok(())
+		}
+
+		for index in indices {
+			let pubkey = derivation::deposit_pubkey(&root, self.account, index)
+				.ok_or_else(|| Error::Input("root key does not support soft derivation".into()))?;
+			println!("{}/{}\t{:?}", self.account, index, pubkey);
+		}
+		Ok(())
+	}
+}
+
+/// Parses an sr25519 public key given as SS58 or 0x-prefixed hex.
+pub fn parse_public(s: &str) -> sc_cli::Result<sr25519::Public> {
+	if let Some(hex) = s.strip_prefix("0x") {
+		let bytes = sp_core::bytes::from_hex(hex)
+			.map_err(|e| Error::Input(format!("invalid public key hex: {:?}", e)))?;
+		let raw: [u8; 32] =
+			bytes.try_into().map_err(|_| Error::Input("public key must be 32 bytes".into()))?;
+		Ok(sr25519::Public::from_raw(raw))
+	} else {
+		sr25519::Public::from_ss58check(s)
+			.map_err(|e| Error::Input(format!("invalid SS58 public key: {:?}", e)))
+	}
+}
+
+/// The extrinsic submitting `transaction`.
+fn extrinsic(transaction: &Transaction) -> UncheckedExtrinsic {
+	UncheckedExtrinsic::new_unsigned(RuntimeCall::Utxo(utxo::Call::spend {
+		transaction: transaction.clone(),
+	}))
+}
+
+/// Converts a failed RPC call into a CLI error.
+fn rpc_error(err: impl std::fmt::Debug) -> Error {
+	Error::Application(format!("RPC request failed: {:?}", err).into())
+}
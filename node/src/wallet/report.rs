@@ -0,0 +1,463 @@
+//! The `wallet report` command.
+//!
+//! Lists every transaction of a range of blocks paying or spending from a keyset, one row each,
+//! for accounting and tax exports. A row balances as a double entry: the keyset's balance moves
+//! by what it `received` minus what it `sent`, and for outgoing transactions what was sent is
+//! the change received back plus the payments to the counterparties plus the fee.
+//!
+//! The report starts from the outputs the keyset owned before the first block, and reads the
+//! outputs spent from the state of each block's parent, so old ranges need a node keeping their
+//! state (`--state-pruning archive`).
+
+use std::{
+	collections::{HashMap, HashSet},
+	fs::File,
+	io::{self, BufWriter, Write},
+	path::PathBuf,
+};
+
+use codec::{Decode, Encode};
+use jsonrpsee::{
+	core::client::ClientT,
+	http_client::{HttpClient, HttpClientBuilder},
+	rpc_params,
+};
+use sc_cli::Error;
+use serde::{Deserialize, Serialize};
+use sp_core::{storage::StorageData, Bytes, H256};
+use sp_runtime::{generic::SignedBlock, traits::Header as _};
+use tokio::runtime::Runtime;
+use tumuchain_runtime::{
+	opaque::{Block, Header, UncheckedExtrinsic as OpaqueExtrinsic},
+	BlockNumber,
+};
+use utxo::{Transaction, TransactionOutput, Value};
+
+use super::{extrinsic, parse_public, rpc_error};
+use crate::{
+	export::timestamp,
+	import::spends,
+	rpc::utxo::UtxoApiClient,
+	spent_index::{event_outputs, events_key, EventRecord},
+};
+
+/// Names of the CSV columns, in order.
+const COLUMNS: [&str; 10] = [
+	"block_number",
+	"block_hash",
+	"timestamp",
+	"txid",
+	"direction",
+	"received",
+	"sent",
+	"fee",
+	"counterparties",
+	"balance",
+];
+
+/// The `wallet report` command.
+///
+/// Columns are `block_number`, `block_hash`, `timestamp` (milliseconds since the Unix epoch),
+/// `txid`, `direction`, the value `received` by and `sent` from the keyset, the `fee` of
+/// transactions the keyset spent in, the `counterparties` separated by `;`, and the `balance`
+/// of the keyset after the transaction.
+#[derive(Debug, clap::Parser)]
+pub struct ReportCmd {
+	/// A pubkey of the keyset, as SS58 or 0x-prefixed hex. Repeat for each key.
+	#[arg(long = "key", value_name = "PUBKEY", required = true)]
+	pub keys: Vec<String>,
+
+	/// First block of the report.
+	#[arg(long, value_name = "NUMBER", default_value_t = 1)]
+	pub from_block: BlockNumber,
+
+	/// Last block of the report. Defaults to the last finalized block.
+	#[arg(long, value_name = "NUMBER")]
+	pub to_block: Option<BlockNumber>,
+
+	/// Format of the report.
+	#[arg(long, value_enum, default_value_t = ReportFormat::Csv)]
+	pub format: ReportFormat,
+
+	/// File to write the report to. Defaults to stdout.
+	#[arg(long, short, value_name = "PATH")]
+	pub output: Option<PathBuf>,
+
+	/// HTTP RPC endpoint of the node to read blocks from.
+	#[arg(long, default_value = "http://127.0.0.1:9944")]
+	pub url: String,
+}
+
+/// Formats of the report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ReportFormat {
+	/// Comma-separated values, with a header row
+	Csv,
+	/// A JSON array of rows
+	Json,
+}
+
+/// How a transaction moves value for the keyset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+	/// Paid by others
+	Incoming,
+	/// Paying others, with any change back
+	Outgoing,
+	/// Spent from and paying only the keyset
+	Internal,
+	/// Created by the runtime rather than a spend: rewards and mints
+	Issued,
+}
+
+impl Direction {
+	/// The name of the direction in reports.
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::Incoming => "incoming",
+			Self::Outgoing => "outgoing",
+			Self::Internal => "internal",
+			Self::Issued => "issued",
+		}
+	}
+}
+
+/// A row of the report, also kept as the history of wallets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Row {
+	/// Number of the block including the transaction
+	pub block_number: BlockNumber,
+	/// Hash of that block
+	pub block_hash: H256,
+	/// Milliseconds since the Unix epoch
+	pub timestamp: u64,
+	/// The txid, or the outpoint of the first output for outputs created by the runtime
+	pub txid: H256,
+	/// How the transaction moves value for the keyset
+	pub direction: Direction,
+	/// Value of the outputs paying the keyset
+	pub received: Value,
+	/// Value of the keyset's outputs spent
+	pub sent: Value,
+	/// Inputs minus outputs, for transactions the keyset spent in and whose inputs are all known
+	pub fee: Option<Value>,
+	/// For incoming transactions the owners of the inputs, for outgoing ones the pubkeys paid
+	pub counterparties: Vec<H256>,
+	/// Balance of the keyset after the transaction
+	pub balance: Value,
+}
+
+impl Row {
+	/// The values of the row, in the order of [`COLUMNS`].
+	fn cells(&self) -> [String; 10] {
+		let counterparties: Vec<_> =
+			self.counterparties.iter().map(|pubkey| format!("{pubkey:?}")).collect();
+		[
+			self.block_number.to_string(),
+			format!("{:?}", self.block_hash),
+			self.timestamp.to_string(),
+			format!("{:?}", self.txid),
+			self.direction.as_str().into(),
+			self.received.to_string(),
+			self.sent.to_string(),
+			self.fee.map(|fee| fee.to_string()).unwrap_or_default(),
+			counterparties.join(";"),
+			self.balance.to_string(),
+		]
+	}
+}
+
+impl ReportCmd {
+	/// Run the command.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		let keys = self
+			.keys
+			.iter()
+			.map(|key| parse_public(key).map(|key| H256(key.0)))
+			.collect::<sc_cli::Result<HashSet<_>>>()?;
+		let node = Node::connect(&self.url)?;
+		let to = match self.to_block {
+			Some(to) => to,
+			None => node.finalized_number()?,
+		};
+		if self.from_block == 0 || self.from_block > to {
+			return Err(Error::Input(format!("no blocks in #{}..=#{to}", self.from_block)))
+		}
+
+		let mut report = Report::open(&node, keys, self.from_block)?;
+		let opening_balance = report.balance;
+		for number in self.from_block..=to {
+			report.add_block(&node, number)?;
+		}
+
+		let mut out: Box<dyn Write> = match &self.output {
+			Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+			None => Box::new(io::stdout().lock()),
+		};
+		match self.format {
+			ReportFormat::Csv => {
+				writeln!(out, "{}", COLUMNS.join(","))?;
+				for row in &report.rows {
+					writeln!(out, "{}", row.cells().join(","))?;
+				}
+			},
+			ReportFormat::Json => {
+				serde_json::to_writer_pretty(&mut out, &report.rows)
+					.map_err(|e| Error::Application(Box::new(e)))?;
+				writeln!(out)?;
+			},
+		}
+		out.flush()?;
+
+		eprintln!(
+			"{} transactions in #{}..=#{to}, balance {opening_balance} -> {}",
+			report.rows.len(),
+			self.from_block,
+			report.balance,
+		);
+		Ok(())
+	}
+}
+
+/// The report, as the blocks are added to it.
+pub struct Report {
+	keys: HashSet<H256>,
+	/// The outputs the keyset owns after the blocks added
+	pub owned: HashMap<H256, TransactionOutput>,
+	/// Their total value
+	pub balance: Value,
+	/// The rows so far
+	pub rows: Vec<Row>,
+}
+
+impl Report {
+	/// A report of `keys` starting at block `from`, with the outputs they owned before it.
+	pub fn open(node: &Node, keys: HashSet<H256>, from: BlockNumber) -> sc_cli::Result<Self> {
+		let opening = node.hash(from - 1)?;
+		let mut owned = HashMap::new();
+		for key in &keys {
+			owned.extend(node.unspent_outputs(*key, opening)?);
+		}
+		Ok(Self::resume(keys, owned))
+	}
+
+	/// A report of `keys` continuing from the point where they owned `owned`.
+	pub fn resume(keys: HashSet<H256>, owned: HashMap<H256, TransactionOutput>) -> Self {
+		let balance = sum(owned.values());
+		Self { keys, owned, balance, rows: Vec::new() }
+	}
+
+	/// Add the rows of block `number`: those of its spends in order, then those of the outputs
+	/// created by the runtime.
+	pub fn add_block(&mut self, node: &Node, number: BlockNumber) -> sc_cli::Result<()> {
+		let hash = node.hash(number)?;
+		let block = node.block(hash)?;
+		let parent = *block.header.parent_hash();
+		let timestamp = timestamp(&block.extrinsics);
+
+		// Outputs created earlier in the block, which are not in the parent's UTXO set
+		let mut created_here = HashMap::new();
+		for transaction in spends(&block.extrinsics) {
+			let txid = transaction.txid();
+			let ours_spent: Vec<_> = transaction
+				.inputs
+				.iter()
+				.filter_map(|input| self.owned.remove(&input.outpoint))
+				.collect();
+			let created: Vec<_> = transaction
+				.outputs
+				.iter()
+				.enumerate()
+				.map(|(index, output)| {
+					(Transaction::outpoint_of(&txid, index as u64), output.clone())
+				})
+				.collect();
+			let ours_created: Vec<_> = created
+				.iter()
+				.filter(|(_, output)| self.keys.contains(&output.pubkey))
+				.cloned()
+				.collect();
+			created_here.extend(created);
+			if ours_spent.is_empty() && ours_created.is_empty() {
+				continue
+			}
+
+			let sent = sum(ours_spent.iter());
+			let received = sum(ours_created.iter().map(|(_, output)| output));
+			let direction = if sent == 0 {
+				Direction::Incoming
+			} else if ours_created.len() == transaction.outputs.len() {
+				Direction::Internal
+			} else {
+				Direction::Outgoing
+			};
+			self.owned.extend(ours_created);
+
+			// The inputs, read again for their owners and values
+			let spent = transaction
+				.inputs
+				.iter()
+				.map(|input| match created_here.get(&input.outpoint) {
+					Some(output) => Ok(Some(output.clone())),
+					None => node.output(input.outpoint, parent),
+				})
+				.collect::<sc_cli::Result<Vec<_>>>()?;
+			let all_spent = spent.iter().map(Option::as_ref).collect::<Option<Vec<_>>>();
+			let fee = match (direction, all_spent) {
+				(Direction::Incoming, _) | (_, None) => None,
+				(_, Some(spent)) =>
+					sum(spent.into_iter()).checked_sub(sum(transaction.outputs.iter())),
+			};
+			let counterparties = match direction {
+				Direction::Incoming => spent.iter().flatten().map(|output| output.pubkey).collect(),
+				_ => transaction.outputs.iter().map(|output| output.pubkey).collect(),
+			};
+			self.push(Row {
+				block_number: number,
+				block_hash: hash,
+				timestamp,
+				txid,
+				direction,
+				received,
+				sent,
+				fee,
+				counterparties,
+				balance: 0,
+			});
+		}
+
+		let records = node.events(hash)?;
+		let groups = event_outputs(records, number, |outpoint| node.output(*outpoint, hash))?;
+		for outputs in groups {
+			let Some(&(id, _)) = outputs.first() else { continue };
+			let ours: Vec<_> = outputs
+				.into_iter()
+				.filter(|(_, output)| self.keys.contains(&output.pubkey))
+				.collect();
+			if ours.is_empty() {
+				continue
+			}
+			let received = sum(ours.iter().map(|(_, output)| output));
+			self.owned.extend(ours);
+			self.push(Row {
+				block_number: number,
+				block_hash: hash,
+				timestamp,
+				txid: id,
+				direction: Direction::Issued,
+				received,
+				sent: 0,
+				fee: None,
+				counterparties: Vec::new(),
+				balance: 0,
+			});
+		}
+		Ok(())
+	}
+
+	/// Add `row`, leaving out the keyset from its counterparties and filling in the balance.
+	fn push(&mut self, mut row: Row) {
+		row.counterparties.retain(|pubkey| !self.keys.contains(pubkey));
+		row.counterparties.sort();
+		row.counterparties.dedup();
+		self.balance = self.balance.saturating_add(row.received).saturating_sub(row.sent);
+		row.balance = self.balance;
+		self.rows.push(row);
+	}
+}
+
+fn sum<'a>(outputs: impl Iterator<Item = &'a TransactionOutput>) -> Value {
+	outputs.fold(0, |sum, output| sum.saturating_add(output.value))
+}
+
+/// A node the wallet reads from over RPC.
+pub struct Node {
+	runtime: Runtime,
+	client: HttpClient,
+}
+
+impl Node {
+	/// Connect to the HTTP RPC endpoint `url`.
+	pub fn connect(url: &str) -> sc_cli::Result<Self> {
+		Ok(Self {
+			runtime: sc_cli::build_runtime()?,
+			client: HttpClientBuilder::default().build(url).map_err(rpc_error)?,
+		})
+	}
+
+	/// The number of the last finalized block.
+	pub fn finalized_number(&self) -> sc_cli::Result<BlockNumber> {
+		let hash: H256 = self.request("chain_getFinalizedHead", rpc_params![])?;
+		let header: Option<Header> = self.request("chain_getHeader", rpc_params![hash])?;
+		header
+			.map(|header| header.number)
+			.ok_or_else(|| Error::Input(format!("no header for {hash:?}")))
+	}
+
+	/// The hash of block `number` of the best chain.
+	pub fn hash(&self, number: BlockNumber) -> sc_cli::Result<H256> {
+		let hash: Option<H256> = self.request("chain_getBlockHash", rpc_params![number])?;
+		hash.ok_or_else(|| Error::Input(format!("no block #{number}")))
+	}
+
+	/// Submit `transaction` to the node's pool, returning the hash of its extrinsic.
+	pub fn submit(&self, transaction: &Transaction) -> sc_cli::Result<H256> {
+		self.request("author_submitExtrinsic", rpc_params![Bytes(extrinsic(transaction).encode())])
+	}
+
+	/// The transactions in the node's pool.
+	pub fn pooled(&self) -> sc_cli::Result<Vec<Transaction>> {
+		let pool: Vec<OpaqueExtrinsic> = self.request("author_pendingExtrinsics", rpc_params![])?;
+		Ok(spends(&pool))
+	}
+
+	/// Whether `outpoint` is unspent as of the node's best block.
+	pub fn is_unspent(&self, outpoint: H256) -> sc_cli::Result<bool> {
+		let output = self
+			.runtime
+			.block_on(UtxoApiClient::<H256>::output(&self.client, outpoint, None))
+			.map_err(rpc_error)?;
+		Ok(output.is_some())
+	}
+
+	fn block(&self, hash: H256) -> sc_cli::Result<Block> {
+		let block: Option<SignedBlock<Block>> = self.request("chain_getBlock", rpc_params![hash])?;
+		block
+			.map(|signed| signed.block)
+			.ok_or_else(|| Error::Input(format!("the body of block {hash:?} was pruned")))
+	}
+
+	fn events(&self, at: H256) -> sc_cli::Result<Vec<EventRecord>> {
+		let data: Option<StorageData> =
+			self.request("state_getStorage", rpc_params![events_key(), at])?;
+		let Some(data) = data else { return Ok(Vec::new()) };
+		Vec::decode(&mut &data.0[..])
+			.map_err(|e| Error::Input(format!("the events of {at:?} do not decode: {e}")))
+	}
+
+	fn unspent_outputs(
+		&self,
+		pubkey: H256,
+		at: H256,
+	) -> sc_cli::Result<Vec<(H256, TransactionOutput)>> {
+		self.runtime
+			.block_on(UtxoApiClient::<H256>::unspent_outputs(&self.client, pubkey, Some(at)))
+			.map_err(rpc_error)
+	}
+
+	fn output(&self, outpoint: H256, at: H256) -> sc_cli::Result<Option<TransactionOutput>> {
+		self.runtime
+			.block_on(UtxoApiClient::<H256>::output(&self.client, outpoint, Some(at)))
+			.map_err(rpc_error)
+	}
+
+	fn request<R: serde::de::DeserializeOwned>(
+		&self,
+		method: &str,
+		params: jsonrpsee::core::params::ArrayParams,
+	) -> sc_cli::Result<R> {
+		self.runtime.block_on(self.client.request(method, params)).map_err(rpc_error)
+	}
+}
@@ -0,0 +1,270 @@
+//! The `wallet send` command.
+//!
+//! Pays a pubkey from the outputs a wallet cached at its last sync, leaving out those spent by
+//! the transactions it sent since, with the change going back to the wallet. Inputs paying the
+//! same key share one signature, carried by the first of them.
+//!
+//! With `--split-change`, the change is split into two or three outputs of unremarkable values,
+//! paying the wallet's keys in turn, and the outputs are shuffled, so that neither their order nor
+//! a lone odd value tells the payment from the change. The split and the order are drawn from a
+//! hash of the inputs and the payment, so building the same payment again yields the same
+//! transaction. No change output is made below the dust limit: the change is split into fewer
+//! outputs, and change below the limit altogether is left to the fee.
+
+use std::collections::HashSet;
+
+use codec::Encode;
+use sc_cli::Error;
+use sp_core::{hashing::blake2_256, sr25519, Pair, H256, H512};
+use utxo::{
+	denomination::{parse_value, FormattedValue},
+	Transaction, TransactionInput, TransactionOutput, Value, MAX_TRANSACTION_PARTS,
+	TRANSACTION_VERSION,
+};
+
+use super::{manage::WalletParams, parse_public, report::Node, store::WalletKey};
+
+/// Smallest change output made unless `--dust-limit` says otherwise, in coins.
+const DUST_LIMIT: &str = "0.00001";
+
+/// The `wallet send` command.
+#[derive(Debug, clap::Parser)]
+pub struct SendCmd {
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub wallet: WalletParams,
+
+	/// The pubkey to pay, as SS58 or 0x-prefixed hex.
+	#[arg(long, value_name = "PUBKEY")]
+	pub to: String,
+
+	/// The amount to pay, in coins, e.g. `2.5`.
+	#[arg(long, value_parser = parse_value)]
+	pub amount: Value,
+
+	/// Fee paid by the transaction, in coins, besides any change below the dust limit.
+	#[arg(long, value_parser = parse_value, default_value = "0")]
+	pub fee: Value,
+
+	/// Split the change into two or three outputs and shuffle the outputs.
+	#[arg(long)]
+	pub split_change: bool,
+
+	/// Smallest change output to make, in coins.
+	#[arg(long, value_name = "AMOUNT", value_parser = parse_value, default_value = DUST_LIMIT)]
+	pub dust_limit: Value,
+
+	/// HTTP RPC endpoint of the node to submit to.
+	#[arg(long, default_value = "http://127.0.0.1:9944")]
+	pub url: String,
+
+	/// Only print the transaction.
+	#[arg(long)]
+	pub dry_run: bool,
+}
+
+impl SendCmd {
+	/// Run the command.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		let to = H256(parse_public(&self.to)?.0);
+		if self.amount == 0 {
+			return Err(Error::Input("the amount must be positive".into()))
+		}
+		let target = self
+			.amount
+			.checked_add(self.fee)
+			.ok_or_else(|| Error::Input("the amount and fee overflow".into()))?;
+
+		let mut wallet = self.wallet.unlock()?;
+		if wallet.data.synced_number.is_none() {
+			return Err(Error::Input("never synced, run `wallet unlock --sync` first".into()))
+		}
+		let pairs =
+			wallet.data.keys.iter().map(WalletKey::pair).collect::<sc_cli::Result<Vec<_>>>()?;
+		let keys: Vec<_> = pairs.iter().map(|pair| H256(pair.public().0)).collect();
+
+		let inputs = select(wallet.data.spendable(), target)?;
+		let change = sum(&inputs) - target;
+		let payment = TransactionOutput { value: self.amount, pubkey: to, memo: None };
+		let mut seed = Seed::new(&inputs, &payment);
+
+		let mut outputs = vec![payment];
+		let pieces = if self.split_change { 3 } else { 1 };
+		outputs.extend(change_outputs(change, pieces, self.dust_limit, &keys, &mut seed));
+		if self.split_change {
+			seed.shuffle(&mut outputs);
+		}
+		let transaction = signed_transaction(&inputs, outputs, &pairs)?;
+
+		println!("txid {:?}, {} inputs", transaction.txid(), transaction.inputs.len());
+		for output in &transaction.outputs {
+			let change = keys.contains(&output.pubkey) && output.pubkey != to;
+			let note = if change { "\tchange" } else { "" };
+			println!("{} to {:?}{note}", FormattedValue(output.value), output.pubkey);
+		}
+		let fee = sum(&inputs) - sum_outputs(transaction.outputs.iter());
+		println!("fee {}", FormattedValue(fee));
+		if self.dry_run {
+			return Ok(())
+		}
+
+		Node::connect(&self.url)?.submit(&transaction)?;
+		wallet.data.pending.push(transaction);
+		wallet.save()?;
+		println!("Submitted");
+		Ok(())
+	}
+}
+
+/// Outputs worth at least `target`, the largest first so as few as possible are spent.
+fn select(
+	mut outputs: Vec<(H256, TransactionOutput)>,
+	target: Value,
+) -> sc_cli::Result<Vec<(H256, TransactionOutput)>> {
+	outputs.sort_by(|(a, x), (b, y)| y.value.cmp(&x.value).then(a.cmp(b)));
+	let mut total: Value = 0;
+	let selected: Vec<_> = outputs
+		.into_iter()
+		.take(MAX_TRANSACTION_PARTS as usize)
+		.take_while(|(_, output)| {
+			let needed = total < target;
+			if needed {
+				total = total.saturating_add(output.value);
+			}
+			needed
+		})
+		.collect();
+	if total < target {
+		return Err(Error::Input(format!(
+			"the {} spendable in {} outputs falls short of {target}",
+			total,
+			selected.len()
+		)))
+	}
+	Ok(selected)
+}
+
+/// Up to `pieces` outputs of `change`, none below `dust_limit`, paying `keys` in turn.
+fn change_outputs(
+	change: Value,
+	pieces: usize,
+	dust_limit: Value,
+	keys: &[H256],
+	seed: &mut Seed,
+) -> Vec<TransactionOutput> {
+	let dust_limit = dust_limit.max(1);
+	let Some(pieces) = (1..=pieces).rev().find(|n| change / *n as Value >= dust_limit) else {
+		return Vec::new()
+	};
+	let first = if pieces > 1 { seed.below(keys.len() as u64) as usize } else { 0 };
+
+	// Each piece gets the dust limit and a random share of the rest, rounded to two significant
+	// digits as payments often are, but for the last one
+	let spare = change - dust_limit * pieces as Value;
+	let weights: Vec<Value> = (0..pieces).map(|_| Value::from(seed.below(1000)) + 1).collect();
+	let total_weight: Value = weights.iter().sum();
+	let mut values: Vec<Value> = weights[..pieces - 1]
+		.iter()
+		.map(|weight| round(dust_limit + spare.saturating_mul(*weight) / total_weight))
+		.map(|value| value.max(dust_limit))
+		.collect();
+	values.push(change - values.iter().sum::<Value>());
+
+	// Equal pieces paying the same key would be the same output, which the chain rejects
+	let distinct: HashSet<_> = values
+		.iter()
+		.enumerate()
+		.map(|(n, value)| (keys[(first + n) % keys.len()], value))
+		.collect();
+	if distinct.len() < values.len() {
+		return change_outputs(change, pieces - 1, dust_limit, keys, seed)
+	}
+	values
+		.into_iter()
+		.enumerate()
+		.map(|(n, value)| TransactionOutput {
+			value,
+			pubkey: keys[(first + n) % keys.len()],
+			memo: None,
+		})
+		.collect()
+}
+
+/// `value` rounded down to two significant digits.
+fn round(value: Value) -> Value {
+	let mut unit = 1;
+	while value / unit >= 100 {
+		unit *= 10;
+	}
+	value - value % unit
+}
+
+/// A transaction spending `inputs` into `outputs`, signed by the keys of `pairs` they pay.
+pub(super) fn signed_transaction(
+	inputs: &[(H256, TransactionOutput)],
+	outputs: Vec<TransactionOutput>,
+	pairs: &[sr25519::Pair],
+) -> sc_cli::Result<Transaction> {
+	let transaction_inputs = inputs
+		.iter()
+		.map(|(outpoint, _)| TransactionInput {
+			outpoint: *outpoint,
+			sigscript: None,
+			witness: None,
+		})
+		.collect::<Vec<_>>();
+	let mut transaction = Transaction {
+		version: TRANSACTION_VERSION,
+		inputs: transaction_inputs.try_into().map_err(|_| Error::Input("too many inputs".into()))?,
+		outputs: outputs.try_into().map_err(|_| Error::Input("too many outputs".into()))?,
+	};
+
+	let payload = transaction.signing_payload();
+	let mut signed = HashSet::new();
+	for (input, (_, output)) in transaction.inputs.iter_mut().zip(inputs) {
+		if !signed.insert(output.pubkey) {
+			continue
+		}
+		let pair = pairs
+			.iter()
+			.find(|pair| pair.public().0 == output.pubkey.0)
+			.ok_or_else(|| Error::Input(format!("no key for {:?}", output.pubkey)))?;
+		input.sigscript = Some(H512::from_slice(pair.sign(&payload).as_ref()));
+	}
+	Ok(transaction)
+}
+
+pub(super) fn sum(outputs: &[(H256, TransactionOutput)]) -> Value {
+	sum_outputs(outputs.iter().map(|(_, output)| output))
+}
+
+pub(super) fn sum_outputs<'a>(outputs: impl IntoIterator<Item = &'a TransactionOutput>) -> Value {
+	outputs.into_iter().fold(0, |sum, output| sum.saturating_add(output.value))
+}
+
+/// Numbers drawn from a hash of a payment and the inputs funding it.
+struct Seed {
+	hash: [u8; 32],
+	drawn: u64,
+}
+
+impl Seed {
+	fn new(inputs: &[(H256, TransactionOutput)], payment: &TransactionOutput) -> Self {
+		let outpoints: Vec<_> = inputs.iter().map(|(outpoint, _)| *outpoint).collect();
+		Self { hash: blake2_256(&(outpoints, payment).encode()), drawn: 0 }
+	}
+
+	/// The next number, below `bound`.
+	fn below(&mut self, bound: u64) -> u64 {
+		self.drawn += 1;
+		let draw = blake2_256(&(self.hash, self.drawn).encode());
+		u64::from_le_bytes(draw[..8].try_into().expect("a hash has 8 bytes; qed")) % bound
+	}
+
+	/// Shuffle `items`, Fisher-Yates.
+	fn shuffle<T>(&mut self, items: &mut [T]) {
+		for n in (1..items.len()).rev() {
+			items.swap(n, self.below(n as u64 + 1) as usize);
+		}
+	}
+}
@@ -0,0 +1,480 @@
+//! Encrypted on-disk storage of named wallets.
+//!
+//! Each wallet is a file `<name>.wallet` in the wallet directory, and that file is also its
+//! backup. It is a JSON envelope around the wallet's contents:
+//!
+//! ```json
+//! {
+//!   "format": "tumuchain-wallet",
+//!   "version": 1,
+//!   "kdf": { "algorithm": "scrypt", "logN": 15, "r": 8, "p": 1, "salt": "0x…" },
+//!   "cipher": { "algorithm": "xchacha20poly1305", "nonce": "0x…" },
+//!   "ciphertext": "0x…"
+//! }
+//! ```
+//!
+//! The 32-byte key is derived from the passphrase with scrypt over the 32-byte `salt`, and the
+//! contents, as JSON, are encrypted with XChaCha20-Poly1305 under the 24-byte `nonce`, with
+//! `tumuchain-wallet-v1` as associated data. A new nonce is drawn each time the wallet is saved;
+//! the salt, and so the key, only change with the passphrase. Binary fields are 0x-prefixed hex.
+//!
+//! The contents are [`WalletData`]: the keys as secret URIs, a cache of the unspent outputs paying
+//! them and of their history, the transactions sent since the last sync, and the user's labels and
+//! locked outputs.
+//! Fields added later default to empty, so older wallets keep opening.
+
+use std::{
+	collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+	fs::{self, File},
+	io::Write,
+	path::{Path, PathBuf},
+};
+
+use chacha20poly1305::{
+	aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng, Payload},
+	XChaCha20Poly1305, XNonce,
+};
+use sc_cli::Error;
+use serde::{Deserialize, Serialize};
+use sp_core::{sr25519, Bytes, Pair, H256};
+use tumuchain_runtime::BlockNumber;
+use utxo::{Transaction, TransactionOutput, Value};
+
+use super::report::Row;
+
+/// The `format` of wallet files.
+const FORMAT: &str = "tumuchain-wallet";
+
+/// The `version` of wallet files written.
+const VERSION: u32 = 1;
+
+/// Extension of wallet files.
+const EXTENSION: &str = "wallet";
+
+/// Associated data of the ciphertext.
+const AAD: &[u8] = b"tumuchain-wallet-v1";
+
+/// scrypt cost of new wallets: 2^15 rounds over 8 blocks, about 32 MiB and a tenth of a second.
+const LOG_N: u8 = 15;
+const R: u32 = 8;
+const P: u32 = 1;
+
+/// Highest scrypt cost accepted from a file, so a crafted backup cannot exhaust memory. Files
+/// must also use the block size `R` and parallelism `P` of new wallets.
+const MAX_LOG_N: u8 = 20;
+
+/// The contents of a wallet.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WalletData {
+	/// The keys, in the order they were added
+	pub keys: Vec<WalletKey>,
+	/// The unspent outputs paying the keys, as of `synced_at`
+	pub outputs: Vec<(H256, TransactionOutput)>,
+	/// The finalized block the wallet was last synced to
+	pub synced_at: Option<H256>,
+	/// The number of that block
+	pub synced_number: Option<BlockNumber>,
+	/// The transactions of the keys found by syncing, oldest first
+	pub history: Vec<Row>,
+	/// Labels and memos of transactions and addresses, by txid or pubkey
+	pub labels: BTreeMap<H256, String>,
+	/// Transactions sent from the wallet and not finalized as of `synced_at`
+	pub pending: Vec<Transaction>,
+	/// Outputs the user set aside, which coin selection leaves alone
+	pub locked: BTreeSet<H256>,
+}
+
+impl WalletData {
+	/// The pubkeys of the keys.
+	pub fn pubkeys(&self) -> sc_cli::Result<Vec<H256>> {
+		self.keys.iter().map(|key| Ok(H256(key.pair()?.public().0))).collect()
+	}
+
+	/// The total value of the cached outputs.
+	pub fn balance(&self) -> Value {
+		self.outputs.iter().fold(0, |sum: Value, (_, output)| sum.saturating_add(output.value))
+	}
+
+	/// The cached outputs neither locked nor spent by a pending transaction.
+	pub fn spendable(&self) -> Vec<(H256, TransactionOutput)> {
+		let spent: HashSet<_> = self
+			.pending
+			.iter()
+			.flat_map(|transaction| transaction.inputs.iter().map(|input| input.outpoint))
+			.collect();
+		self.outputs
+			.iter()
+			.filter(|(outpoint, _)| !spent.contains(outpoint) && !self.locked.contains(outpoint))
+			.cloned()
+			.collect()
+	}
+
+	/// The outputs of the pending transactions, by outpoint.
+	pub fn pending_outputs(&self) -> HashMap<H256, TransactionOutput> {
+		self.pending
+			.iter()
+			.flat_map(|transaction| {
+				let txid = transaction.txid();
+				transaction.outputs.iter().enumerate().map(move |(n, output)| {
+					(Transaction::outpoint_of(&txid, n as u64), output.clone())
+				})
+			})
+			.collect()
+	}
+}
+
+/// A key of a wallet.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletKey {
+	/// Secret URI of the sr25519 key: a mnemonic, followed by `///` and its passphrase if it has
+	/// one, or a 0x-prefixed seed
+	pub secret_uri: String,
+}
+
+impl WalletKey {
+	/// The key pair.
+	pub fn pair(&self) -> sc_cli::Result<sr25519::Pair> {
+		sr25519::Pair::from_string(&self.secret_uri, None)
+			.map_err(|e| Error::Input(format!("invalid secret URI in wallet: {:?}", e)))
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope {
+	format: String,
+	version: u32,
+	kdf: Kdf,
+	cipher: Cipher,
+	ciphertext: Bytes,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Kdf {
+	algorithm: String,
+	log_n: u8,
+	r: u32,
+	p: u32,
+	salt: Bytes,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Cipher {
+	algorithm: String,
+	nonce: Bytes,
+}
+
+impl Kdf {
+	/// New parameters, with a random salt.
+	fn new() -> Self {
+		let mut salt = [0; 32];
+		OsRng.fill_bytes(&mut salt);
+		Self { algorithm: "scrypt".into(), log_n: LOG_N, r: R, p: P, salt: salt.to_vec().into() }
+	}
+
+	fn derive(&self, passphrase: &str) -> sc_cli::Result<[u8; 32]> {
+		if self.algorithm != "scrypt" {
+			return Err(Error::Input(format!("unsupported key derivation {}", self.algorithm)))
+		}
+		if self.log_n > MAX_LOG_N {
+			return Err(Error::Input(format!("scrypt cost 2^{} is too high", self.log_n)))
+		}
+		if self.r != R || self.p != P {
+			return Err(Error::Input(format!(
+				"unsupported scrypt parameters r = {}, p = {}",
+				self.r, self.p
+			)))
+		}
+		let params = scrypt::Params::new(self.log_n, self.r, self.p, 32)
+			.map_err(|e| Error::Input(format!("invalid scrypt parameters: {e}")))?;
+		let mut key = [0; 32];
+		scrypt::scrypt(passphrase.as_bytes(), &self.salt, &params, &mut key)
+			.map_err(|e| Error::Input(format!("invalid scrypt output length: {e}")))?;
+		Ok(key)
+	}
+}
+
+/// An unlocked wallet, saved back under the passphrase it was unlocked with.
+pub struct Wallet {
+	path: PathBuf,
+	kdf: Kdf,
+	key: [u8; 32],
+	/// The contents
+	pub data: WalletData,
+}
+
+impl Wallet {
+	/// A new wallet at `path`, encrypted under `passphrase`. Nothing is written until it is
+	/// saved.
+	pub fn new(path: PathBuf, passphrase: &str, data: WalletData) -> sc_cli::Result<Self> {
+		let kdf = Kdf::new();
+		let key = kdf.derive(passphrase)?;
+		Ok(Self { path, kdf, key, data })
+	}
+
+	/// Decrypt the wallet file at `path`.
+	pub fn open(path: &Path, passphrase: &str) -> sc_cli::Result<Self> {
+		let file = fs::read(path)
+			.map_err(|e| Error::Input(format!("cannot read {}: {e}", path.display())))?;
+		let envelope: Envelope = serde_json::from_slice(&file)
+			.map_err(|e| Error::Input(format!("{} is not a wallet: {e}", path.display())))?;
+		if envelope.format != FORMAT || envelope.version != VERSION {
+			return Err(Error::Input(format!(
+				"{} is a {} file of version {}",
+				path.display(),
+				envelope.format,
+				envelope.version
+			)))
+		}
+		if envelope.cipher.algorithm != "xchacha20poly1305" || envelope.cipher.nonce.len() != 24 {
+			return Err(Error::Input(format!("unsupported cipher {}", envelope.cipher.algorithm)))
+		}
+
+		let key = envelope.kdf.derive(passphrase)?;
+		let payload = Payload { msg: &envelope.ciphertext, aad: AAD };
+		let plaintext = XChaCha20Poly1305::new(&key.into())
+			.decrypt(XNonce::from_slice(&envelope.cipher.nonce), payload)
+			.map_err(|_| Error::Input("wrong passphrase, or the wallet is corrupt".into()))?;
+		let data = serde_json::from_slice(&plaintext)
+			.map_err(|e| Error::Input(format!("wallet contents do not parse: {e}")))?;
+		Ok(Self { path: path.into(), kdf: envelope.kdf, key, data })
+	}
+
+	/// The file the wallet is saved to.
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Encrypt the wallet and write it to its file, replacing the previous version.
+	pub fn save(&self) -> sc_cli::Result<()> {
+		let plaintext = serde_json::to_vec(&self.data).map_err(|e| Error::Application(e.into()))?;
+		let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+		let ciphertext = XChaCha20Poly1305::new(&self.key.into())
+			.encrypt(&nonce, Payload { msg: &plaintext, aad: AAD })
+			.map_err(|_| Error::Application("wallet encryption failed".into()))?;
+		let envelope = Envelope {
+			format: FORMAT.into(),
+			version: VERSION,
+			kdf: self.kdf.clone(),
+			cipher: Cipher { algorithm: "xchacha20poly1305".into(), nonce: nonce.to_vec().into() },
+			ciphertext: ciphertext.into(),
+		};
+		let file =
+			serde_json::to_vec_pretty(&envelope).map_err(|e| Error::Application(e.into()))?;
+
+		// Written aside and moved over, so a crash never leaves half a wallet
+		let temporary = self.path.with_extension("tmp");
+		write_private(&temporary, &file, false)?;
+		fs::rename(&temporary, &self.path)?;
+		Ok(())
+	}
+}
+
+/// Copy the wallet file at `from` to `to`, which must not exist yet.
+pub fn copy(from: &Path, to: &Path) -> sc_cli::Result<()> {
+	write_private(to, &fs::read(from)?, true).map_err(|e| match e {
+		Error::Io(e) if e.kind() == std::io::ErrorKind::AlreadyExists =>
+			Error::Input(format!("{} already exists", to.display())),
+		e => e,
+	})
+}
+
+/// The directory holding the wallets.
+pub struct WalletDir(PathBuf);
+
+impl WalletDir {
+	/// The wallet directory at `path`, created if missing.
+	pub fn open(path: PathBuf) -> sc_cli::Result<Self> {
+		fs::create_dir_all(&path)?;
+		Ok(Self(path))
+	}
+
+	/// The file of the wallet named `name`.
+	pub fn path_of(&self, name: &str) -> sc_cli::Result<PathBuf> {
+		let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+		if name.is_empty() || !name.chars().all(valid) {
+			return Err(Error::Input(format!(
+				"invalid wallet name {name:?}: use letters, digits, `-` and `_`"
+			)))
+		}
+		Ok(self.0.join(name).with_extension(EXTENSION))
+	}
+
+	/// The names of the wallets, sorted.
+	pub fn list(&self) -> sc_cli::Result<Vec<String>> {
+		let mut names = Vec::new();
+		for entry in fs::read_dir(&self.0)? {
+			let path = entry?.path();
+			if path.extension().is_some_and(|extension| extension == EXTENSION) {
+				names.extend(path.file_stem().and_then(|stem| stem.to_str()).map(String::from));
+			}
+		}
+		names.sort();
+		Ok(names)
+	}
+}
+
+/// Write `data` to a file at `path` only the current user can read, on Unix, failing if it
+/// exists when `create_new` is set.
+fn write_private(path: &Path, data: &[u8], create_new: bool) -> sc_cli::Result<()> {
+	let mut options = File::options();
+	options.write(true).create(true).truncate(true).create_new(create_new);
+	#[cfg(unix)]
+	std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+	let mut file = options.open(path)?;
+	file.write_all(data)?;
+	file.sync_all()?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A file for the test `name`, removed when dropped.
+	struct TestFile(PathBuf);
+
+	impl TestFile {
+		fn new(name: &str) -> Self {
+			let path = std::env::temp_dir()
+				.join(format!("tumuchain-{}-{name}", std::process::id()))
+				.with_extension(EXTENSION);
+			Self(path)
+		}
+	}
+
+	impl Drop for TestFile {
+		fn drop(&mut self) {
+			let _ = fs::remove_file(&self.0);
+		}
+	}
+
+	/// A wallet at `path` holding a key and a label, with a cheap scrypt cost for speed.
+	fn wallet(path: &Path, passphrase: &str) -> Wallet {
+		let kdf = Kdf { log_n: 4, ..Kdf::new() };
+		let key = kdf.derive(passphrase).unwrap();
+		let data = WalletData {
+			keys: vec![WalletKey { secret_uri: "//Alice".into() }],
+			labels: [(H256::repeat_byte(1), "rent".into())].into_iter().collect(),
+			..Default::default()
+		};
+		Wallet { path: path.into(), kdf, key, data }
+	}
+
+	fn envelope(path: &Path) -> Envelope {
+		serde_json::from_slice(&fs::read(path).unwrap()).unwrap()
+	}
+
+	fn write_envelope(path: &Path, envelope: &Envelope) {
+		fs::write(path, serde_json::to_vec(envelope).unwrap()).unwrap();
+	}
+
+	/// The message of the error opening `path` with `passphrase`.
+	fn open_error(path: &Path, passphrase: &str) -> String {
+		match Wallet::open(path, passphrase) {
+			Err(Error::Input(message)) => message,
+			Err(e) => panic!("unexpected error {e}"),
+			Ok(_) => panic!("the wallet opened"),
+		}
+	}
+
+	#[test]
+	fn saved_wallets_open_with_their_passphrase() {
+		let file = TestFile::new("round-trip");
+		wallet(&file.0, "hunter2").save().unwrap();
+
+		// The contents are not stored in the clear
+		let saved = fs::read_to_string(&file.0).unwrap();
+		assert!(!saved.contains("//Alice") && !saved.contains("rent"));
+		assert_eq!(envelope(&file.0).kdf.log_n, 4);
+
+		let opened = Wallet::open(&file.0, "hunter2").unwrap();
+		assert_eq!(opened.data.keys[0].secret_uri, "//Alice");
+		assert_eq!(opened.data.labels.get(&H256::repeat_byte(1)).unwrap(), "rent");
+
+		// Saving again draws a new nonce under the same key
+		let nonce = envelope(&file.0).cipher.nonce;
+		opened.save().unwrap();
+		assert_ne!(envelope(&file.0).cipher.nonce, nonce);
+		assert!(Wallet::open(&file.0, "hunter2").is_ok());
+	}
+
+	#[test]
+	fn wrong_passphrases_are_rejected() {
+		let file = TestFile::new("wrong-passphrase");
+		wallet(&file.0, "hunter2").save().unwrap();
+		assert_eq!(open_error(&file.0, "hunter3"), "wrong passphrase, or the wallet is corrupt");
+	}
+
+	#[test]
+	fn tampered_wallets_are_rejected() {
+		let file = TestFile::new("tampered");
+		let wallet = wallet(&file.0, "hunter2");
+		wallet.save().unwrap();
+		let saved = envelope(&file.0);
+
+		let mut tampered = envelope(&file.0);
+		tampered.ciphertext.0[0] ^= 1;
+		write_envelope(&file.0, &tampered);
+		assert_eq!(open_error(&file.0, "hunter2"), "wrong passphrase, or the wallet is corrupt");
+
+		let mut tampered = envelope(&file.0);
+		tampered.ciphertext = saved.ciphertext.clone();
+		tampered.cipher.nonce.0[0] ^= 1;
+		write_envelope(&file.0, &tampered);
+		assert_eq!(open_error(&file.0, "hunter2"), "wrong passphrase, or the wallet is corrupt");
+
+		// Contents encrypted under the same key and nonce with other associated data
+		let plaintext = serde_json::to_vec(&wallet.data).unwrap();
+		let other = XChaCha20Poly1305::new(&wallet.key.into())
+			.encrypt(
+				XNonce::from_slice(&saved.cipher.nonce),
+				Payload { msg: &plaintext, aad: b"tumuchain-wallet-v2" },
+			)
+			.unwrap();
+		write_envelope(&file.0, &Envelope { ciphertext: other.into(), ..saved });
+		assert_eq!(open_error(&file.0, "hunter2"), "wrong passphrase, or the wallet is corrupt");
+	}
+
+	#[test]
+	fn excessive_scrypt_costs_are_rejected() {
+		let file = TestFile::new("scrypt-cost");
+		wallet(&file.0, "hunter2").save().unwrap();
+
+		let mut costly = envelope(&file.0);
+		costly.kdf.log_n = MAX_LOG_N + 1;
+		write_envelope(&file.0, &costly);
+		let expected = format!("scrypt cost 2^{} is too high", MAX_LOG_N + 1);
+		assert_eq!(open_error(&file.0, "hunter2"), expected);
+
+		// The default cost is within the limit
+		assert!(Kdf::new().log_n <= MAX_LOG_N);
+	}
+
+	#[test]
+	fn other_scrypt_block_sizes_and_parallelism_are_rejected() {
+		let file = TestFile::new("scrypt-r-p");
+		wallet(&file.0, "hunter2").save().unwrap();
+		let saved = envelope(&file.0);
+
+		let mut costly = envelope(&file.0);
+		costly.kdf.r = 1 << 20;
+		write_envelope(&file.0, &costly);
+		let expected = format!("unsupported scrypt parameters r = {}, p = {P}", 1 << 20);
+		assert_eq!(open_error(&file.0, "hunter2"), expected);
+
+		let mut costly = envelope(&file.0);
+		costly.kdf.r = R;
+		costly.kdf.p = u32::MAX;
+		write_envelope(&file.0, &costly);
+		let expected = format!("unsupported scrypt parameters r = {R}, p = {}", u32::MAX);
+		assert_eq!(open_error(&file.0, "hunter2"), expected);
+
+		write_envelope(&file.0, &saved);
+		assert!(Wallet::open(&file.0, "hunter2").is_ok());
+	}
+}
@@ -0,0 +1,241 @@
+//! The `wallet sweep` command.
+//!
+//! Collects every output of a key into a single output paying another pubkey. A transaction has
+//! at most [`MAX_TRANSACTION_PARTS`] inputs, so larger sets are swept by a chain of
+//! transactions: each one consolidates a chunk of outputs together with the output of the
+//! previous one, back to the swept key, and only the last pays the destination.
+
+use std::{
+	path::PathBuf,
+	thread,
+	time::{Duration, Instant},
+};
+
+use codec::Encode;
+use jsonrpsee::{
+	core::client::ClientT,
+	http_client::HttpClientBuilder,
+	rpc_params,
+};
+use sc_cli::Error;
+use sc_keystore::LocalKeystore;
+use sp_core::{sr25519, Bytes, Pair, H256, H512};
+use sp_keystore::Keystore;
+use tumuchain_runtime::{RuntimeCall, UncheckedExtrinsic};
+use utxo::{
+	denomination::{parse_value, FormattedValue},
+	Transaction, TransactionInput, TransactionOutput, Value, MAX_TRANSACTION_PARTS,
+	TRANSACTION_VERSION,
+};
+
+use super::{parse_public, rpc_error, WALLET_KEY_TYPE};
+use crate::rpc::utxo::UtxoApiClient;
+
+/// How often to check whether a submitted transaction was included.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The `wallet sweep` command.
+#[derive(Debug, clap::Parser)]
+pub struct SweepCmd {
+	/// Secret URI of the key to sweep, e.g. a mnemonic phrase or `//Alice`. With
+	/// `--keystore-path`, the public key of a key held in the keystore instead.
+	#[arg(long, value_name = "SECRET_URI")]
+	pub from: String,
+
+	/// Keystore holding the key to sweep under the `wllt` key type, e.g. a node's keystore the
+	/// key was added to with `key insert` or the `author_insertKey` RPC.
+	#[arg(long, value_name = "PATH")]
+	pub keystore_path: Option<PathBuf>,
+
+	/// The pubkey receiving the funds, as SS58 or 0x-prefixed hex.
+	#[arg(long, value_name = "PUBKEY")]
+	pub to: String,
+
+	/// Fee paid by each transaction of the sweep, in coins.
+	#[arg(long, value_parser = parse_value, default_value = "0")]
+	pub fee: Value,
+
+	/// HTTP RPC endpoint of the node to read outputs from and submit to.
+	#[arg(long, default_value = "http://127.0.0.1:9944")]
+	pub url: String,
+
+	/// Seconds to wait for each transaction to be included before giving up.
+	#[arg(long, default_value_t = 120)]
+	pub timeout: u64,
+
+	/// Only print the transactions the sweep would submit.
+	#[arg(long)]
+	pub dry_run: bool,
+}
+
+impl SweepCmd {
+	/// Run the command.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		let signer = match &self.keystore_path {
+			Some(path) => {
+				let keystore = LocalKeystore::open(path, None)
+					.map_err(|e| Error::Input(format!("cannot open keystore: {}", e)))?;
+				let public = parse_public(&self.from)?;
+				if !keystore.has_keys(&[(public.0.to_vec(), WALLET_KEY_TYPE)]) {
+					return Err(Error::Input(format!("{} is not in the keystore", self.from)))
+				}
+				Signer::Keystore(keystore, public)
+			},
+			None => Signer::Pair(
+				sr25519::Pair::from_string(&self.from, None)
+					.map_err(|e| Error::Input(format!("invalid secret URI: {:?}", e)))?,
+			),
+		};
+		let from = H256(signer.public().0);
+		let to = H256(parse_public(&self.to)?.0);
+
+		let runtime = sc_cli::build_runtime()?;
+		let client = HttpClientBuilder::default().build(&self.url).map_err(rpc_error)?;
+
+		// Poll the node until `outpoint` is in the UTXO set, which also means the transaction
+		// creating it was included
+		let wait_for_output = |outpoint: H256| -> sc_cli::Result<()> {
+			let deadline = Instant::now() + Duration::from_secs(self.timeout);
+			loop {
+				let output = runtime
+					.block_on(UtxoApiClient::<H256>::output(&client, outpoint, None))
+					.map_err(rpc_error)?;
+				if output.is_some() {
+					return Ok(())
+				}
+				if Instant::now() >= deadline {
+					return Err(Error::Input(format!(
+						"output {:?} not included after {}s",
+						outpoint, self.timeout
+					)))
+				}
+				thread::sleep(POLL_INTERVAL);
+			}
+		};
+
+		let mut outputs = runtime
+			.block_on(UtxoApiClient::<H256>::unspent_outputs(&client, from, None))
+			.map_err(rpc_error)?;
+		if outputs.is_empty() {
+			return Err(Error::Input(format!("no unspent outputs pay {:?}", from)))
+		}
+		// Sweep in a reproducible order
+		outputs.sort_by_key(|(outpoint, _)| *outpoint);
+
+		let transactions = sweep_transactions(&outputs, &signer, to, self.fee)?;
+		for (n, transaction) in transactions.iter().enumerate() {
+			let output = &transaction.outputs[0];
+			println!(
+				"[{}/{}] {} inputs -> {} to {:?}, txid {:?}",
+				n + 1,
+				transactions.len(),
+				transaction.inputs.len(),
+				FormattedValue(output.value),
+				output.pubkey,
+				transaction.txid(),
+			);
+			if self.dry_run {
+				continue
+			}
+
+			let extrinsic = UncheckedExtrinsic::new_unsigned(RuntimeCall::Utxo(utxo::Call::spend {
+				transaction: transaction.clone(),
+			}));
+			let _: H256 = runtime
+				.block_on(
+					client.request("author_submitExtrinsic", rpc_params![Bytes(extrinsic.encode())]),
+				)
+				.map_err(rpc_error)?;
+			wait_for_output(transaction.outpoint(0))?;
+			println!("[{}/{}] included", n + 1, transactions.len());
+		}
+		Ok(())
+	}
+}
+
+/// The key whose outputs are swept.
+enum Signer {
+	/// Secret given on the command line
+	Pair(sr25519::Pair),
+	/// Secret held in a keystore under [`WALLET_KEY_TYPE`]
+	Keystore(LocalKeystore, sr25519::Public),
+}
+
+impl Signer {
+	fn public(&self) -> sr25519::Public {
+		match self {
+			Signer::Pair(pair) => pair.public(),
+			Signer::Keystore(_, public) => *public,
+		}
+	}
+
+	fn sign(&self, payload: &[u8]) -> sc_cli::Result<H512> {
+		let signature = match self {
+			Signer::Pair(pair) => pair.sign(payload),
+			Signer::Keystore(keystore, public) => keystore
+				.sr25519_sign(WALLET_KEY_TYPE, public, payload)
+				.map_err(|e| Error::Application(Box::new(e)))?
+				.ok_or_else(|| Error::Input("key disappeared from the keystore".into()))?,
+		};
+		Ok(H512::from_slice(signature.as_ref()))
+	}
+}
+
+/// The chain of signed transactions moving `outputs` to `to`, each paying `fee`.
+fn sweep_transactions(
+	outputs: &[(H256, TransactionOutput)],
+	signer: &Signer,
+	to: H256,
+	fee: Value,
+) -> sc_cli::Result<Vec<Transaction>> {
+	let from = H256(signer.public().0);
+	let mut transactions = Vec::new();
+	let mut carried: Option<(H256, Value)> = None;
+	let mut remaining = outputs;
+
+	while !remaining.is_empty() {
+		let room = MAX_TRANSACTION_PARTS as usize - usize::from(carried.is_some());
+		let (chunk, rest) = remaining.split_at(room.min(remaining.len()));
+		remaining = rest;
+
+		let inputs: Vec<(H256, Value)> = carried
+			.into_iter()
+			.chain(chunk.iter().map(|(outpoint, output)| (*outpoint, output.value)))
+			.collect();
+		let total = inputs
+			.iter()
+			.try_fold(0, |total: Value, (_, value)| total.checked_add(*value))
+			.ok_or_else(|| Error::Input("swept value overflows".into()))?;
+		let value = total
+			.checked_sub(fee)
+			.filter(|value| *value > 0)
+			.ok_or_else(|| Error::Input(format!("fee exceeds the {} swept by a transaction", total)))?;
+
+		let pubkey = if remaining.is_empty() { to } else { from };
+		let transaction = signed_transaction(&inputs, TransactionOutput { value, pubkey, memo: None }, signer)?;
+		carried = Some((transaction.outpoint(0), value));
+		transactions.push(transaction);
+	}
+	Ok(transactions)
+}
+
+/// A transaction spending `inputs`, all paying `signer`, into `output`.
+///
+/// Every input would carry the same signature, so only the first one does.
+fn signed_transaction(
+	inputs: &[(H256, Value)],
+	output: TransactionOutput,
+	signer: &Signer,
+) -> sc_cli::Result<Transaction> {
+	let inputs = inputs
+		.iter()
+		.map(|(outpoint, _)| TransactionInput { outpoint: *outpoint, sigscript: None, witness: None })
+		.collect::<Vec<_>>();
+	let mut transaction = Transaction {
+		version: TRANSACTION_VERSION,
+		inputs: inputs.try_into().map_err(|_| Error::Input("too many inputs".into()))?,
+		outputs: vec![output].try_into().map_err(|_| Error::Input("too many outputs".into()))?,
+	};
+	transaction.inputs[0].sigscript = Some(signer.sign(&transaction.signing_payload())?);
+	Ok(transaction)
+}
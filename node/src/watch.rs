@@ -0,0 +1,288 @@
+//! Watch-only tracking of imported descriptors, behind `utxo_importAddress`.
+//!
+//! Unlike the spent index, which records the history of every pubkey, the node only keeps the
+//! history of the pubkeys imported, so custodians can follow their wallets without paying for an
+//! index of the whole chain. A background task scans each finalized block for the watched
+//! pubkeys, appending the transactions paying or spending from them to their histories in the
+//! client's auxiliary storage. Outputs created by the runtime, such as rewards, are listed under
+//! the outpoint of their first output, as in the spent index.
+//!
+//! An import may ask for a rescan from a given height, for wallets recovered from a seed: the task
+//! then scans the blocks from that height up to where tracking started, alongside tracking new
+//! ones, and reports its progress through `utxo_rescanStatus`. Rescanning blocks whose bodies
+//! were pruned finds nothing; where only the state was pruned, spends and outputs created by the
+//! runtime are missed.
+
+use crate::{service::FullClient, spent_index::scan_block};
+use codec::{Decode, Encode};
+use futures::StreamExt;
+use sc_client_api::{AuxStore, BlockchainEvents};
+use serde::{Deserialize, Serialize};
+use sp_blockchain::HeaderBackend;
+use sp_core::H256;
+use std::{
+	collections::{btree_map::Entry, BTreeMap, HashSet},
+	sync::{Arc, Mutex, MutexGuard},
+};
+use tokio::sync::Notify;
+use tumuchain_runtime::BlockNumber;
+use utxo::descriptor::Descriptor;
+
+/// Key of the watched descriptors and of the tracking cursor.
+const STATE_KEY: &[u8] = b"watch_state";
+
+/// Prefix of the history of each watched pubkey, followed by the pubkey.
+const HISTORY_PREFIX: &[u8] = b"watch_history";
+
+/// Blocks rescanned before checking for newly finalized ones.
+const RESCAN_BATCH: u32 = 100;
+
+/// A descriptor the node tracks.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Watched {
+	/// The descriptor, in its canonical form
+	pub descriptor: String,
+	/// The `pubkey` of the outputs it covers
+	pub pubkey: H256,
+	/// The rescan of past blocks, until it completes
+	pub rescan: Option<Rescan>,
+}
+
+/// Progress of a rescan, over the blocks from `from` until `until`, excluded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rescan {
+	/// First block rescanned
+	pub from: BlockNumber,
+	/// Next block to rescan
+	pub next: BlockNumber,
+	/// The block tracking started at, from which the history was kept already
+	pub until: BlockNumber,
+}
+
+/// What the node watches and how far it got.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchStatus {
+	/// The last finalized block scanned for the watched pubkeys
+	pub tracked_to: BlockNumber,
+	/// The watched descriptors, in the order they were imported
+	pub watched: Vec<Watched>,
+}
+
+#[derive(Debug, Default, Encode, Decode)]
+struct State {
+	/// The next finalized block to scan
+	next: BlockNumber,
+	watched: Vec<Watched>,
+}
+
+/// The watched descriptors, shared by the RPC importing them and the task tracking them.
+#[derive(Clone)]
+pub struct Watchlist {
+	client: Arc<FullClient>,
+	state: Arc<Mutex<State>>,
+	imported: Arc<Notify>,
+}
+
+impl Watchlist {
+	/// Load the watched descriptors from the client's auxiliary storage.
+	pub fn new(client: Arc<FullClient>) -> sp_blockchain::Result<Self> {
+		let state = match client.get_aux(STATE_KEY)? {
+			Some(data) => State::decode(&mut &data[..])
+				.map_err(|e| sp_blockchain::Error::Backend(format!("corrupt watch state: {e}")))?,
+			// Nothing to track in the blocks finalized so far
+			None => State { next: client.info().finalized_number + 1, watched: Vec::new() },
+		};
+		Ok(Self { client, state: Arc::new(Mutex::new(state)), imported: Default::default() })
+	}
+
+	/// Watch `descriptor` from the next finalized block on, rescanning the blocks from
+	/// `rescan_from` first if given. Importing a descriptor again only replaces its rescan.
+	pub fn import(
+		&self,
+		descriptor: &Descriptor,
+		rescan_from: Option<BlockNumber>,
+	) -> sp_blockchain::Result<Watched> {
+		let mut state = self.lock();
+		// Genesis pays and spends nothing
+		let rescan = rescan_from
+			.map(|from| from.max(1))
+			.filter(|from| *from < state.next)
+			.map(|from| Rescan { from, next: from, until: state.next });
+
+		let pubkey = descriptor.pubkey();
+		let watched = match state.watched.iter_mut().find(|watched| watched.pubkey == pubkey) {
+			Some(watched) => {
+				watched.rescan = rescan.or(watched.rescan);
+				watched.clone()
+			},
+			None => {
+				let watched = Watched { descriptor: descriptor.to_string(), pubkey, rescan };
+				state.watched.push(watched.clone());
+				watched
+			},
+		};
+		self.client.insert_aux(&[(STATE_KEY, &state.encode()[..])], &[])?;
+		drop(state);
+
+		self.imported.notify_one();
+		Ok(watched)
+	}
+
+	/// The watched descriptors and the progress of their rescans.
+	pub fn status(&self) -> WatchStatus {
+		let state = self.lock();
+		WatchStatus { tracked_to: state.next - 1, watched: state.watched.clone() }
+	}
+
+	/// The transactions paying or spending from `pubkey` found so far, oldest first, with the
+	/// numbers of their blocks, or `None` if it is not watched.
+	pub fn history(
+		&self,
+		pubkey: &H256,
+	) -> sp_blockchain::Result<Option<Vec<(BlockNumber, H256)>>> {
+		if !self.lock().watched.iter().any(|watched| watched.pubkey == *pubkey) {
+			return Ok(None)
+		}
+		self.read_history(pubkey).map(Some)
+	}
+
+	fn lock(&self) -> MutexGuard<'_, State> {
+		self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+
+	fn read_history(&self, pubkey: &H256) -> sp_blockchain::Result<Vec<(BlockNumber, H256)>> {
+		let Some(data) = self.client.get_aux(&[HISTORY_PREFIX, pubkey.as_bytes()].concat())? else {
+			return Ok(Vec::new())
+		};
+		Vec::decode(&mut &data[..])
+			.map_err(|e| sp_blockchain::Error::Backend(format!("corrupt watch history: {e}")))
+	}
+
+	/// Scan the finalized blocks not tracked yet.
+	fn track(&self) -> sp_blockchain::Result<()> {
+		let finalized = self.client.info().finalized_number;
+		loop {
+			let next = {
+				let mut state = self.lock();
+				if state.watched.is_empty() {
+					// Nothing to find, and nothing worth a write
+					state.next = state.next.max(finalized + 1);
+				}
+				state.next
+			};
+			if next > finalized {
+				return Ok(())
+			}
+
+			let touched = self.scan(next)?;
+			let mut state = self.lock();
+			let pubkeys =
+				state.watched.iter().map(|watched| watched.pubkey).collect::<HashSet<_>>();
+			state.next = next + 1;
+			self.record(&state, next, touched, &pubkeys)?;
+		}
+	}
+
+	/// Rescan a batch of past blocks, returning whether any rescan is left.
+	fn rescan(&self) -> sp_blockchain::Result<bool> {
+		for _ in 0..RESCAN_BATCH {
+			// The rescans furthest behind go first, so those over the same blocks scan them once
+			let next = self
+				.lock()
+				.watched
+				.iter()
+				.filter_map(|watched| watched.rescan)
+				.map(|rescan| rescan.next)
+				.min();
+			let Some(number) = next else { return Ok(false) };
+
+			let touched = match self.scan(number) {
+				Ok(touched) => touched,
+				Err(e) => {
+					log::warn!(target: "watch", "Skipping block #{number} in rescan: {e}");
+					Vec::new()
+				},
+			};
+			let mut state = self.lock();
+			let mut pubkeys = HashSet::new();
+			for watched in &mut state.watched {
+				// Imported again since, with another rescan
+				let Some(rescan) = watched.rescan.as_mut().filter(|r| r.next == number) else {
+					continue
+				};
+				pubkeys.insert(watched.pubkey);
+				rescan.next += 1;
+				if rescan.next >= rescan.until {
+					log::info!(target: "watch", "Rescan for {} complete", watched.descriptor);
+					watched.rescan = None;
+				}
+			}
+			self.record(&state, number, touched, &pubkeys)?;
+		}
+		Ok(true)
+	}
+
+	/// The pubkeys finalized block `number` pays or spends from, with the txid or runtime outputs
+	/// doing so.
+	fn scan(&self, number: BlockNumber) -> sp_blockchain::Result<Vec<(H256, H256)>> {
+		let hash = self
+			.client
+			.hash(number)?
+			.ok_or_else(|| sp_blockchain::Error::UnknownBlock(format!("#{number}")))?;
+		Ok(scan_block(&self.client, hash)?.touched)
+	}
+
+	/// Add the entries of block `number` for `pubkeys` to their histories, along with `state`.
+	fn record(
+		&self,
+		state: &State,
+		number: BlockNumber,
+		touched: Vec<(H256, H256)>,
+		pubkeys: &HashSet<H256>,
+	) -> sp_blockchain::Result<()> {
+		let mut entries = vec![(STATE_KEY.to_vec(), state.encode())];
+		let mut histories = BTreeMap::new();
+		for (pubkey, id) in touched.into_iter().filter(|(pubkey, _)| pubkeys.contains(pubkey)) {
+			let history = match histories.entry(pubkey) {
+				Entry::Occupied(entry) => entry.into_mut(),
+				Entry::Vacant(entry) => entry.insert(self.read_history(&pubkey)?),
+			};
+			// Rescans may cover blocks tracked already, and land before later entries
+			if !history.contains(&(number, id)) {
+				let at = history.partition_point(|(entry, _)| *entry <= number);
+				history.insert(at, (number, id));
+			}
+		}
+		entries.extend(histories.into_iter().map(|(pubkey, history)| {
+			([HISTORY_PREFIX, pubkey.as_bytes()].concat(), history.encode())
+		}));
+
+		let inserts = entries.iter().map(|(key, value)| (&key[..], &value[..])).collect::<Vec<_>>();
+		self.client.insert_aux(&inserts, &[])
+	}
+}
+
+/// Track the watched descriptors as blocks are finalized and run the rescans asked for, until the
+/// client's finality stream ends.
+pub async fn run(watchlist: Watchlist) {
+	let mut finality = watchlist.client.finality_notification_stream();
+
+	loop {
+		if let Err(e) = watchlist.track() {
+			log::warn!(target: "watch", "Failed to track watched descriptors: {e}");
+		}
+		match watchlist.rescan() {
+			Ok(true) => continue,
+			Ok(false) => {},
+			Err(e) => log::warn!(target: "watch", "Failed to rescan: {e}"),
+		}
+
+		tokio::select! {
+			notification = finality.next() => if notification.is_none() { break },
+			_ = watchlist.imported.notified() => {},
+		}
+	}
+}
@@ -0,0 +1,323 @@
+//! ZeroMQ publisher of new blocks and transactions, for wallet daemons built on Bitcoin's
+//! `-zmqpubhashblock` and `-zmqpubhashtx`.
+//!
+//! Served when the node is started with `--zmq-port`. The port speaks ZMTP 3.0, the wire protocol
+//! of ZeroMQ, as a PUB socket with the NULL mechanism, so any ZeroMQ SUB socket can connect and
+//! subscribe to the topics it wants. As with Bitcoin, each message has three frames: the topic,
+//! the body and a little-endian `u32` sequence number counting the messages of the topic.
+//!
+//! - `hashblock`: the hash of each block joining the best chain, in order, including the blocks a
+//!   reorganization switches to.
+//! - `hashtx`: the txid of each UTXO transaction entering the ready pool, and of each transaction
+//!   of a block joining the best chain. A transaction is usually published twice.
+//!
+//! Hashes are the 32 bytes as the node stores them, not reversed as Bitcoin's are. Like a ZeroMQ
+//! PUB socket, the publisher drops the messages of subscribers too slow to keep up with
+//! [`BACKLOG`] of them.
+
+use std::{net::SocketAddr, sync::Arc};
+
+use codec::{Decode, Encode};
+use futures::StreamExt;
+use sc_client_api::{BlockBackend, BlockchainEvents};
+use sc_transaction_pool_api::{InPoolTransaction, TransactionPool};
+use sp_core::H256;
+use sp_runtime::OpaqueExtrinsic;
+use tokio::{
+	io::{AsyncReadExt, AsyncWriteExt},
+	net::{tcp::OwnedReadHalf, TcpListener, TcpStream},
+	sync::{broadcast, mpsc},
+};
+use tumuchain_runtime::UncheckedExtrinsic;
+
+use crate::{
+	rpc::utxo::spent_transaction,
+	service::{FullClient, FullPool},
+};
+
+/// Messages kept for subscribers behind the latest, past which the oldest are dropped.
+const BACKLOG: usize = 10_000;
+
+/// Longest frame accepted from subscribers, in bytes. Connections sending longer ones are closed.
+const MAX_FRAME: u64 = 4096;
+
+/// Frame flags of ZMTP 3.0.
+const MORE: u8 = 0x01;
+const LONG: u8 = 0x02;
+const COMMAND: u8 = 0x04;
+
+/// Topics published.
+#[derive(Debug, Clone, Copy)]
+enum Topic {
+	/// Hashes of new best blocks
+	HashBlock,
+	/// Txids of new transactions
+	HashTx,
+}
+
+impl Topic {
+	fn name(self) -> &'static [u8] {
+		match self {
+			Self::HashBlock => b"hashblock",
+			Self::HashTx => b"hashtx",
+		}
+	}
+}
+
+/// A message to publish.
+#[derive(Debug, Clone)]
+struct Message {
+	topic: Topic,
+	body: H256,
+	sequence: u32,
+}
+
+/// Serve the publisher on `addr`, with the blocks of `client` and the transactions of `pool`.
+pub async fn run(addr: SocketAddr, client: Arc<FullClient>, pool: Arc<FullPool>) {
+	let listener = match TcpListener::bind(addr).await {
+		Ok(listener) => listener,
+		Err(e) => {
+			log::error!(target: "zmq", "Cannot listen for ZeroMQ on {addr}: {e}");
+			return
+		},
+	};
+	log::info!(target: "zmq", "ZeroMQ publisher listening on {addr}");
+
+	let (messages, _) = broadcast::channel(BACKLOG);
+	tokio::spawn(publish(client, pool, messages.clone()));
+	loop {
+		match listener.accept().await {
+			Ok((stream, peer)) => {
+				log::debug!(target: "zmq", "ZeroMQ subscriber {peer} connected");
+				tokio::spawn(serve(stream, messages.subscribe()));
+			},
+			Err(e) => log::warn!(target: "zmq", "Cannot accept a ZeroMQ connection: {e}"),
+		}
+	}
+}
+
+/// Send the messages of new best blocks and pool transactions to `messages`, until the import
+/// streams end.
+async fn publish(
+	client: Arc<FullClient>,
+	pool: Arc<FullPool>,
+	messages: broadcast::Sender<Message>,
+) {
+	let mut imports = client.import_notification_stream();
+	let mut pool_imports = pool.import_notification_stream();
+	let mut sequences = [0u32; 2];
+	let mut send = |topic: Topic, body| {
+		let sequence = &mut sequences[topic as usize];
+		// Fails only while nobody is subscribed
+		let _ = messages.send(Message { topic, body, sequence: *sequence });
+		*sequence = sequence.wrapping_add(1);
+	};
+
+	loop {
+		tokio::select! {
+			Some(notification) = imports.next() => {
+				if !notification.is_new_best {
+					continue
+				}
+				let mut enacted: Vec<H256> = notification
+					.tree_route
+					.as_ref()
+					.map(|route| route.enacted().iter().map(|block| block.hash).collect())
+					.unwrap_or_default();
+				enacted.push(notification.hash);
+				for hash in enacted {
+					send(Topic::HashBlock, hash);
+					let body = client.block_body(hash).ok().flatten().unwrap_or_default();
+					for txid in body.iter().filter_map(txid) {
+						send(Topic::HashTx, txid);
+					}
+				}
+			},
+			Some(hash) = pool_imports.next() => {
+				let Some(ready) = pool.ready_transaction(&hash) else { continue };
+				if let Some(txid) = txid(ready.data()) {
+					send(Topic::HashTx, txid);
+				}
+			},
+			else => return,
+		}
+	}
+}
+
+/// The txid of the UTXO transaction of `extrinsic`, if it is one.
+fn txid(extrinsic: &OpaqueExtrinsic) -> Option<H256> {
+	let extrinsic = UncheckedExtrinsic::decode(&mut &extrinsic.encode()[..]).ok()?;
+	spent_transaction(extrinsic.function).map(|transaction| transaction.txid())
+}
+
+/// A change to the topics a subscriber wants.
+enum Subscription {
+	Subscribe(Vec<u8>),
+	Cancel(Vec<u8>),
+}
+
+/// Publish `messages` to the subscriber on `stream` until it disconnects.
+async fn serve(mut stream: TcpStream, mut messages: broadcast::Receiver<Message>) {
+	if let Err(e) = handshake(&mut stream).await {
+		log::debug!(target: "zmq", "ZeroMQ handshake failed: {e}");
+		return
+	}
+	let (reader, mut writer) = stream.into_split();
+	let (subscriptions_tx, mut subscriptions) = mpsc::unbounded_channel();
+	tokio::spawn(read_subscriptions(reader, subscriptions_tx));
+
+	// Prefixes of the topics wanted, once per subscription as ZeroMQ counts them
+	let mut prefixes: Vec<Vec<u8>> = Vec::new();
+	loop {
+		tokio::select! {
+			subscription = subscriptions.recv() => match subscription {
+				Some(Subscription::Subscribe(prefix)) => prefixes.push(prefix),
+				Some(Subscription::Cancel(prefix)) => {
+					if let Some(at) = prefixes.iter().position(|p| *p == prefix) {
+						prefixes.swap_remove(at);
+					}
+				},
+				None => return,
+			},
+			message = messages.recv() => {
+				let message = match message {
+					Ok(message) => message,
+					Err(broadcast::error::RecvError::Lagged(skipped)) => {
+						log::debug!(target: "zmq", "ZeroMQ subscriber lagged, {skipped} dropped");
+						continue
+					},
+					Err(broadcast::error::RecvError::Closed) => return,
+				};
+				let topic = message.topic.name();
+				if !prefixes.iter().any(|prefix| topic.starts_with(prefix)) {
+					continue
+				}
+				let mut bytes = frame(topic, MORE);
+				bytes.extend(frame(message.body.as_bytes(), MORE));
+				bytes.extend(frame(&message.sequence.to_le_bytes(), 0));
+				if writer.write_all(&bytes).await.is_err() {
+					return
+				}
+			},
+		}
+	}
+}
+
+/// Exchange greetings and `READY` commands with the subscriber on `stream`.
+async fn handshake(stream: &mut TcpStream) -> std::io::Result<()> {
+	stream.write_all(&greeting()).await?;
+	let mut peer = [0u8; 64];
+	stream.read_exact(&mut peer).await?;
+	let null = peer[12..32].starts_with(b"NULL\0");
+	if peer[0] != 0xff || peer[9] != 0x7f || peer[10] < 3 || !null {
+		return Err(invalid("not a ZMTP 3 peer with the NULL mechanism"))
+	}
+
+	stream.write_all(&command(b"READY", &property(b"Socket-Type", b"PUB"))).await?;
+	let (flags, body) = read_frame(stream).await?;
+	if flags & COMMAND == 0 || !body.starts_with(b"\x05READY") {
+		return Err(invalid("expected a READY command"))
+	}
+	match socket_type(&body[6..]) {
+		Some(b"SUB" | b"XSUB") => Ok(()),
+		_ => Err(invalid("the peer is not a SUB or XSUB socket")),
+	}
+}
+
+/// The `Socket-Type` of the metadata `properties` of a `READY` command.
+fn socket_type(mut properties: &[u8]) -> Option<&[u8]> {
+	while let Some((&name_len, rest)) = properties.split_first() {
+		let name = rest.get(..name_len as usize)?;
+		let rest = &rest[name.len()..];
+		let value_len = u32::from_be_bytes(rest.get(..4)?.try_into().ok()?) as usize;
+		let value = rest.get(4..4 + value_len)?;
+		if name.eq_ignore_ascii_case(b"Socket-Type") {
+			return Some(value)
+		}
+		properties = &rest[4 + value_len..];
+	}
+	None
+}
+
+/// Send the subscriptions read from `reader` to `subscriptions`, until the subscriber
+/// disconnects or breaks the protocol.
+async fn read_subscriptions(
+	mut reader: OwnedReadHalf,
+	subscriptions: mpsc::UnboundedSender<Subscription>,
+) {
+	while let Ok((flags, body)) = read_frame(&mut reader).await {
+		// ZMTP 3.0 subscribes with messages, ZMTP 3.1 with commands
+		let subscription = match (flags & COMMAND != 0, body.split_first()) {
+			(false, Some((&1, topic))) => Subscription::Subscribe(topic.to_vec()),
+			(false, Some((&0, topic))) => Subscription::Cancel(topic.to_vec()),
+			(true, _) if body.starts_with(b"\x09SUBSCRIBE") =>
+				Subscription::Subscribe(body[10..].to_vec()),
+			(true, _) if body.starts_with(b"\x06CANCEL") =>
+				Subscription::Cancel(body[7..].to_vec()),
+			_ => continue,
+		};
+		if subscriptions.send(subscription).is_err() {
+			return
+		}
+	}
+}
+
+/// The greeting of a ZMTP 3.0 server with the NULL mechanism.
+fn greeting() -> [u8; 64] {
+	let mut greeting = [0u8; 64];
+	greeting[0] = 0xff;
+	greeting[9] = 0x7f;
+	greeting[10] = 3;
+	greeting[12..16].copy_from_slice(b"NULL");
+	greeting
+}
+
+/// The frame holding `body`, with `flags`.
+fn frame(body: &[u8], flags: u8) -> Vec<u8> {
+	let mut frame = Vec::with_capacity(body.len() + 9);
+	match u8::try_from(body.len()) {
+		Ok(len) => frame.extend([flags, len]),
+		Err(_) => {
+			frame.push(flags | LONG);
+			frame.extend((body.len() as u64).to_be_bytes());
+		},
+	}
+	frame.extend_from_slice(body);
+	frame
+}
+
+/// The frame of command `name` with `data`.
+fn command(name: &[u8], data: &[u8]) -> Vec<u8> {
+	let mut body = vec![name.len() as u8];
+	body.extend_from_slice(name);
+	body.extend_from_slice(data);
+	frame(&body, COMMAND)
+}
+
+/// The encoding of metadata property `name` of `value`.
+fn property(name: &[u8], value: &[u8]) -> Vec<u8> {
+	let mut property = vec![name.len() as u8];
+	property.extend_from_slice(name);
+	property.extend((value.len() as u32).to_be_bytes());
+	property.extend_from_slice(value);
+	property
+}
+
+/// The flags and body of the next frame of `reader`.
+async fn read_frame<R: AsyncReadExt + Unpin>(reader: &mut R) -> std::io::Result<(u8, Vec<u8>)> {
+	let flags = reader.read_u8().await?;
+	let len = match flags & LONG {
+		0 => u64::from(reader.read_u8().await?),
+		_ => reader.read_u64().await?,
+	};
+	if len > MAX_FRAME {
+		return Err(invalid("frame too long"))
+	}
+	let mut body = vec![0; len as usize];
+	reader.read_exact(&mut body).await?;
+	Ok((flags, body))
+}
+
+fn invalid(message: &str) -> std::io::Error {
+	std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,53 @@
+//! Benchmarking setup for pallet-airdrop
+#![cfg(feature = "runtime-benchmarks")]
+use super::*;
+
+use alloc::vec::Vec;
+use frame_benchmarking::v2::*;
+use frame_support::{traits::Get, BoundedVec};
+use sp_core::crypto::KeyTypeId;
+use sp_runtime::traits::{Bounded, Zero};
+
+const KEY_TYPE: KeyTypeId = KeyTypeId(*b"bnch");
+
+#[benchmarks]
+mod benchmarks {
+	use super::*;
+
+	#[benchmark]
+	fn claim_airdrop() {
+		let pubkey = H256(sp_io::crypto::sr25519_generate(KEY_TYPE, None).0);
+		let dest: T::AccountId = account("dest", 0, 0);
+		// Well above any existential deposit
+		let amount = BalanceOf::<T>::max_value() / 2u32.into();
+		Claims::<T>::insert(pubkey, amount);
+		let genesis_hash = frame_system::Pallet::<T>::block_hash(Zero::zero());
+		let payload = claim_payload(&genesis_hash, &dest);
+		let signature = sp_io::crypto::sr25519_sign(KEY_TYPE, &Public::from_h256(pubkey), &payload)
+			.expect("the key was generated in the keystore");
+
+		#[extrinsic_call]
+		_(frame_system::RawOrigin::None, pubkey, dest, H512::from_slice(signature.as_ref()));
+
+		assert!(!Claims::<T>::contains_key(pubkey));
+	}
+
+	#[benchmark]
+	fn add_claims(c: Linear<0, { T::MaxClaimsPerCall::get() }>) -> Result<(), BenchmarkError> {
+		let origin = T::ClaimsOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+		let amount = BalanceOf::<T>::from(1u32);
+		// Every claim adds to an existing one, the worst case
+		let claims: Vec<_> = (0..c).map(|i| (H256::from_low_u64_be(i.into()), amount)).collect();
+		for (pubkey, amount) in &claims {
+			Claims::<T>::insert(pubkey, amount);
+		}
+		let claims = BoundedVec::truncate_from(claims);
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, claims);
+
+		Ok(())
+	}
+
+	impl_benchmark_test_suite!(Pallet, crate::mock::new_test_ext(Vec::new()), crate::mock::Test);
+}
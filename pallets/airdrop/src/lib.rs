@@ -0,0 +1,248 @@
+//! # Airdrop Pallet
+//!
+//! Distributes an asset to the owners of the UTXO set at a snapshot.
+//!
+//! ## Overview
+//!
+//! The node's `airdrop-snapshot` command reads the UTXO set at a block and splits the airdrop
+//! among the pubkeys owning it with [`proportional_claims`]. The resulting list is loaded into
+//! [`Claims`] through the genesis config of a new chain, or by [`Config::ClaimsOrigin`] with
+//! [`Pallet::add_claims`].
+//!
+//! The owner of a pubkey claims with an unsigned [`Pallet::claim_airdrop`] carrying the key's
+//! signature of [`claim_payload`], which names the account the asset is minted to and the genesis
+//! hash of the chain, so a claim signed for one chain cannot be replayed on another airdropping
+//! to the same snapshot. No account or fee is needed beforehand. The genesis hash is only in
+//! storage once block 1 is initialized, so claims open then.
+//!
+//! Outputs locked by a script are owned by the script hash, which cannot sign. The snapshot
+//! leaves out the owners that are no sr25519 key, which most script hashes are not, so that
+//! their share goes to the keys that can claim.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use pallet::*;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+pub mod weights;
+pub use weights::*;
+
+extern crate alloc;
+
+use alloc::{collections::BTreeMap, vec::Vec};
+use codec::Encode;
+use frame_support::traits::fungible;
+use sp_core::{
+	sr25519::{Public, Signature},
+	H256, H512,
+};
+use sp_runtime::{helpers_128bit::multiply_by_rational_with_rounding, Rounding};
+use utxo::{TransactionOutput, Value};
+
+/// Domain separator of claim signatures.
+pub const CLAIM_CONTEXT: &[u8] = b"tumuchain/airdrop";
+
+type BalanceOf<T> =
+	<<T as Config>::Asset as fungible::Inspect<<T as frame_system::Config>::AccountId>>::Balance;
+
+/// The message a snapshotted key signs to have its airdrop minted to `dest` on the chain of
+/// `genesis_hash`.
+pub fn claim_payload<Hash: Encode, AccountId: Encode>(
+	genesis_hash: &Hash,
+	dest: &AccountId,
+) -> Vec<u8> {
+	(CLAIM_CONTEXT, genesis_hash, dest).encode()
+}
+
+/// Split `total` among the owners of `outputs` in proportion to the value they own.
+///
+/// Shares are rounded down, so slightly less than `total` is handed out, and owners whose share
+/// rounds down to zero are left out. Claims are sorted by pubkey.
+pub fn proportional_claims(
+	outputs: impl IntoIterator<Item = TransactionOutput>,
+	total: u128,
+) -> Vec<(H256, u128)> {
+	let mut holdings = BTreeMap::<H256, Value>::new();
+	for output in outputs {
+		let holding = holdings.entry(output.pubkey).or_default();
+		*holding = holding.saturating_add(output.value);
+	}
+	let supply = holdings.values().fold(0, |sum: Value, value| sum.saturating_add(*value));
+
+	holdings
+		.into_iter()
+		.filter_map(|(pubkey, value)| {
+			let share = multiply_by_rational_with_rounding(total, value, supply, Rounding::Down)?;
+			(share > 0).then_some((pubkey, share))
+		})
+		.collect()
+}
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+	use frame_support::{pallet_prelude::*, traits::fungible::Mutate};
+	use frame_system::pallet_prelude::*;
+	use sp_runtime::traits::{Saturating, Zero};
+
+	#[pallet::pallet]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// The overarching runtime event type.
+		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+		/// A type representing the weights required by the dispatchables of this pallet.
+		type WeightInfo: WeightInfo;
+
+		/// The asset airdropped.
+		type Asset: fungible::Mutate<Self::AccountId>;
+
+		/// Origin allowed to add claims after genesis.
+		type ClaimsOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+		/// Largest number of claims added by one call.
+		#[pallet::constant]
+		type MaxClaimsPerCall: Get<u32>;
+
+		/// Priority of claim transactions in the pool.
+		#[pallet::constant]
+		type UnsignedPriority: Get<TransactionPriority>;
+	}
+
+	/// Amounts still to be claimed, by snapshotted pubkey.
+	#[pallet::storage]
+	pub type Claims<T: Config> = StorageMap<_, Identity, H256, BalanceOf<T>, OptionQuery>;
+
+	#[pallet::genesis_config]
+	#[derive(frame_support::DefaultNoBound)]
+	pub struct GenesisConfig<T: Config> {
+		/// Claims as produced by `airdrop-snapshot`
+		pub claims: Vec<(H256, BalanceOf<T>)>,
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> BuildGenesisConfig for GenesisConfig<T> {
+		fn build(&self) {
+			for (pubkey, amount) in &self.claims {
+				Pallet::<T>::add_claim(*pubkey, *amount);
+			}
+		}
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// Claims were added for `count` pubkeys.
+		ClaimsAdded { count: u32 },
+		/// The airdrop of `pubkey` was minted to `dest`.
+		Claimed { pubkey: H256, dest: T::AccountId, amount: BalanceOf<T> },
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The pubkey has nothing to claim
+		NoClaim,
+		/// The signature is not the pubkey's signature of the claim
+		InvalidSignature,
+		/// Claims open at block 1, once the genesis hash they sign is known
+		ClaimsNotOpen,
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Mint the airdrop of `pubkey` to `dest`, given the key's signature of
+		/// [`claim_payload`].
+		#[pallet::call_index(0)]
+		#[pallet::weight(T::WeightInfo::claim_airdrop())]
+		pub fn claim_airdrop(
+			origin: OriginFor<T>,
+			pubkey: H256,
+			dest: T::AccountId,
+			signature: H512,
+		) -> DispatchResult {
+			ensure_none(origin)?;
+			let amount = Self::check_claim(&pubkey, &dest, &signature)?;
+
+			T::Asset::mint_into(&dest, amount)?;
+			Claims::<T>::remove(pubkey);
+			Self::deposit_event(Event::Claimed { pubkey, dest, amount });
+			Ok(())
+		}
+
+		/// Add claims, on top of any a pubkey already has.
+		#[pallet::call_index(1)]
+		#[pallet::weight(T::WeightInfo::add_claims(claims.len() as u32))]
+		pub fn add_claims(
+			origin: OriginFor<T>,
+			claims: BoundedVec<(H256, BalanceOf<T>), T::MaxClaimsPerCall>,
+		) -> DispatchResult {
+			T::ClaimsOrigin::ensure_origin(origin)?;
+			for (pubkey, amount) in &claims {
+				Self::add_claim(*pubkey, *amount);
+			}
+			Self::deposit_event(Event::ClaimsAdded { count: claims.len() as u32 });
+			Ok(())
+		}
+	}
+
+	#[pallet::validate_unsigned]
+	impl<T: Config> ValidateUnsigned for Pallet<T> {
+		type Call = Call<T>;
+
+		fn validate_unsigned(_source: TransactionSource, call: &Self::Call) -> TransactionValidity {
+			let Call::claim_airdrop { pubkey, dest, signature } = call else {
+				return InvalidTransaction::Call.into()
+			};
+			Self::check_claim(pubkey, dest, signature).map_err(|error| match error {
+				Error::<T>::NoClaim => InvalidTransaction::Stale,
+				Error::<T>::ClaimsNotOpen => InvalidTransaction::Future,
+				_ => InvalidTransaction::BadProof,
+			})?;
+
+			ValidTransaction::with_tag_prefix("Airdrop")
+				.priority(T::UnsignedPriority::get())
+				.and_provides(pubkey)
+				.propagate(true)
+				.build()
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// The amount `pubkey` can claim to `dest` with `signature`.
+		pub fn check_claim(
+			pubkey: &H256,
+			dest: &T::AccountId,
+			signature: &H512,
+		) -> Result<BalanceOf<T>, Error<T>> {
+			// Until then the genesis block's hash is a placeholder
+			let block_number = frame_system::Pallet::<T>::block_number();
+			ensure!(!block_number.is_zero(), Error::<T>::ClaimsNotOpen);
+			let amount = Claims::<T>::get(pubkey).ok_or(Error::<T>::NoClaim)?;
+			let genesis_hash = frame_system::Pallet::<T>::block_hash(BlockNumberFor::<T>::zero());
+			ensure!(
+				sp_io::crypto::sr25519_verify(
+					&Signature::from_raw(*signature.as_fixed_bytes()),
+					&claim_payload(&genesis_hash, dest),
+					&Public::from_h256(*pubkey),
+				),
+				Error::<T>::InvalidSignature
+			);
+			Ok(amount)
+		}
+
+		fn add_claim(pubkey: H256, amount: BalanceOf<T>) {
+			Claims::<T>::mutate(pubkey, |claim| {
+				*claim = Some(claim.unwrap_or_default().saturating_add(amount));
+			});
+		}
+	}
+}
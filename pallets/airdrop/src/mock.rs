@@ -0,0 +1,68 @@
+use crate as pallet_airdrop;
+use frame_support::{derive_impl, parameter_types};
+use sp_core::{sr25519, Pair, H256, H512};
+use sp_runtime::BuildStorage;
+
+type Block = frame_system::mocking::MockBlock<Test>;
+
+frame_support::construct_runtime!(
+	pub enum Test
+	{
+		System: frame_system,
+		Balances: pallet_balances,
+		Airdrop: pallet_airdrop,
+	}
+);
+
+#[derive_impl(frame_system::config_preludes::TestDefaultConfig)]
+impl frame_system::Config for Test {
+	type Block = Block;
+	type AccountData = pallet_balances::AccountData<u64>;
+}
+
+#[derive_impl(pallet_balances::config_preludes::TestDefaultConfig)]
+impl pallet_balances::Config for Test {
+	type AccountStore = System;
+}
+
+parameter_types! {
+	pub const MaxClaimsPerCall: u32 = 3;
+}
+
+impl pallet_airdrop::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type WeightInfo = ();
+	type Asset = Balances;
+	type ClaimsOrigin = frame_system::EnsureRoot<u64>;
+	type MaxClaimsPerCall = MaxClaimsPerCall;
+	type UnsignedPriority = frame_support::traits::ConstU64<{ u64::MAX / 2 }>;
+}
+
+/// Test externalities with `claims` in genesis.
+pub fn new_test_ext(claims: Vec<(H256, u64)>) -> sp_io::TestExternalities {
+	let mut storage = frame_system::GenesisConfig::<Test>::default().build_storage().unwrap();
+	pallet_airdrop::GenesisConfig::<Test> { claims }.assimilate_storage(&mut storage).unwrap();
+
+	let mut ext: sp_io::TestExternalities = storage.into();
+	// Benchmarks sign with keys generated through the keystore host functions
+	ext.register_extension(sp_keystore::KeystoreExt::new(
+		sp_keystore::testing::MemoryKeystore::new(),
+	));
+	ext.execute_with(|| System::set_block_number(1));
+	ext
+}
+
+/// The snapshotted pubkey of `owner`.
+pub fn pubkey(owner: &sr25519::Pair) -> H256 {
+	H256(owner.public().0)
+}
+
+/// The signature of `owner` claiming its airdrop to `dest` on the test chain.
+pub fn sign_claim(owner: &sr25519::Pair, dest: u64) -> H512 {
+	sign_claim_on(owner, System::block_hash(0), dest)
+}
+
+/// The signature of `owner` claiming its airdrop to `dest` on the chain of `genesis_hash`.
+pub fn sign_claim_on(owner: &sr25519::Pair, genesis_hash: H256, dest: u64) -> H512 {
+	H512::from_slice(owner.sign(&crate::claim_payload(&genesis_hash, &dest)).as_ref())
+}
@@ -0,0 +1,141 @@
+use crate::{mock::*, proportional_claims, Claims, Error, Event};
+use frame_support::{assert_noop, assert_ok, pallet_prelude::*};
+use sp_core::{sr25519, Pair, H256};
+use sp_runtime::{traits::BadOrigin, BoundedVec};
+use utxo::TransactionOutput;
+
+fn owner(seed: &str) -> sr25519::Pair {
+	sr25519::Pair::from_string(&format!("//{}", seed), None).unwrap()
+}
+
+fn output(pubkey: H256, value: u128) -> TransactionOutput {
+	TransactionOutput { value, pubkey, memo: None }
+}
+
+#[test]
+fn claims_are_proportional_to_holdings() {
+	let (a, b, c) = (H256::repeat_byte(1), H256::repeat_byte(2), H256::repeat_byte(3));
+
+	// Outputs of the same pubkey add up
+	let outputs = vec![output(b, 30), output(a, 50), output(b, 20)];
+	assert_eq!(proportional_claims(outputs, 1_000), vec![(a, 500), (b, 500)]);
+
+	// Shares round down, and those rounding to zero are dropped
+	let outputs = vec![output(a, 2), output(b, 1), output(c, 997)];
+	assert_eq!(proportional_claims(outputs, 500), vec![(a, 1), (c, 498)]);
+
+	assert_eq!(proportional_claims(vec![], 1_000), vec![]);
+}
+
+#[test]
+fn claim_mints_to_dest() {
+	let alice = owner("Alice");
+	new_test_ext(vec![(pubkey(&alice), 100)]).execute_with(|| {
+		assert_ok!(Airdrop::claim_airdrop(RuntimeOrigin::none(), pubkey(&alice), 7, sign_claim(&alice, 7)));
+		System::assert_last_event(Event::Claimed { pubkey: pubkey(&alice), dest: 7, amount: 100 }.into());
+		assert_eq!(Balances::free_balance(7), 100);
+		assert_eq!(pallet_balances::TotalIssuance::<Test>::get(), 100);
+		assert_eq!(Claims::<Test>::get(pubkey(&alice)), None);
+
+		// Only once
+		assert_noop!(
+			Airdrop::claim_airdrop(RuntimeOrigin::none(), pubkey(&alice), 7, sign_claim(&alice, 7)),
+			Error::<Test>::NoClaim
+		);
+	});
+}
+
+#[test]
+fn claim_needs_signature_of_pubkey_for_dest() {
+	let (alice, bob) = (owner("Alice"), owner("Bob"));
+	new_test_ext(vec![(pubkey(&alice), 100)]).execute_with(|| {
+		// Signed by another key
+		assert_noop!(
+			Airdrop::claim_airdrop(RuntimeOrigin::none(), pubkey(&alice), 7, sign_claim(&bob, 7)),
+			Error::<Test>::InvalidSignature
+		);
+		// Signed for another account
+		assert_noop!(
+			Airdrop::claim_airdrop(RuntimeOrigin::none(), pubkey(&alice), 8, sign_claim(&alice, 7)),
+			Error::<Test>::InvalidSignature
+		);
+		// Signed for another chain airdropping to the same snapshot
+		let other_chain = H256::repeat_byte(1);
+		assert_ne!(System::block_hash(0), other_chain);
+		let signature = sign_claim_on(&alice, other_chain, 7);
+		assert_noop!(
+			Airdrop::claim_airdrop(RuntimeOrigin::none(), pubkey(&alice), 7, signature),
+			Error::<Test>::InvalidSignature
+		);
+		// Nothing to claim
+		assert_noop!(
+			Airdrop::claim_airdrop(RuntimeOrigin::none(), pubkey(&bob), 7, sign_claim(&bob, 7)),
+			Error::<Test>::NoClaim
+		);
+		assert_noop!(
+			Airdrop::claim_airdrop(RuntimeOrigin::signed(1), pubkey(&alice), 7, sign_claim(&alice, 7)),
+			BadOrigin
+		);
+	});
+}
+
+#[test]
+fn claims_open_at_block_one() {
+	let alice = owner("Alice");
+	new_test_ext(vec![(pubkey(&alice), 100)]).execute_with(|| {
+		System::set_block_number(0);
+		let signature = sign_claim(&alice, 7);
+		assert_noop!(
+			Airdrop::claim_airdrop(RuntimeOrigin::none(), pubkey(&alice), 7, signature),
+			Error::<Test>::ClaimsNotOpen
+		);
+		let call = crate::Call::<Test>::claim_airdrop { pubkey: pubkey(&alice), dest: 7, signature };
+		assert_eq!(
+			Airdrop::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Future.into()
+		);
+
+		System::set_block_number(1);
+		assert_ok!(Airdrop::claim_airdrop(RuntimeOrigin::none(), pubkey(&alice), 7, signature));
+	});
+}
+
+#[test]
+fn add_claims_adds_to_existing() {
+	let (a, b) = (H256::repeat_byte(1), H256::repeat_byte(2));
+	new_test_ext(vec![(a, 100), (a, 10)]).execute_with(|| {
+		assert_eq!(Claims::<Test>::get(a), Some(110));
+
+		let claims = BoundedVec::truncate_from(vec![(a, 5), (b, 20)]);
+		assert_noop!(Airdrop::add_claims(RuntimeOrigin::signed(1), claims.clone()), BadOrigin);
+		assert_ok!(Airdrop::add_claims(RuntimeOrigin::root(), claims));
+		System::assert_last_event(Event::ClaimsAdded { count: 2 }.into());
+		assert_eq!(Claims::<Test>::get(a), Some(115));
+		assert_eq!(Claims::<Test>::get(b), Some(20));
+	});
+}
+
+#[test]
+fn validate_unsigned_checks_claim() {
+	let (alice, bob) = (owner("Alice"), owner("Bob"));
+	new_test_ext(vec![(pubkey(&alice), 100)]).execute_with(|| {
+		let call = |pubkey, signature| crate::Call::<Test>::claim_airdrop { pubkey, dest: 7, signature };
+
+		let valid = Airdrop::validate_unsigned(
+			TransactionSource::External,
+			&call(pubkey(&alice), sign_claim(&alice, 7)),
+		)
+		.unwrap();
+		assert_eq!(valid.provides, vec![("Airdrop", pubkey(&alice)).encode()]);
+		assert_eq!(valid.priority, u64::MAX / 2);
+
+		assert_eq!(
+			Airdrop::validate_unsigned(TransactionSource::External, &call(pubkey(&alice), sign_claim(&bob, 7))),
+			InvalidTransaction::BadProof.into()
+		);
+		assert_eq!(
+			Airdrop::validate_unsigned(TransactionSource::External, &call(pubkey(&bob), sign_claim(&bob, 7))),
+			InvalidTransaction::Stale.into()
+		);
+	});
+}
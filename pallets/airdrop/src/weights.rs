@@ -0,0 +1,52 @@
+//! Weights for pallet_airdrop
+//!
+//! PLACEHOLDER VALUES, NOT YET GENERATED ON REFERENCE HARDWARE.
+//! Regenerate with `./scripts/benchmark-weights.sh pallet_airdrop`.
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use core::marker::PhantomData;
+
+/// Weight functions needed for pallet_airdrop.
+pub trait WeightInfo {
+	fn claim_airdrop() -> Weight;
+	fn add_claims(c: u32, ) -> Weight;
+}
+
+/// Weights for pallet_airdrop using the Substrate node and recommended hardware.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	/// Storage: Airdrop Claims (r:1 w:1)
+	/// Storage: System Account (r:1 w:1)
+	fn claim_airdrop() -> Weight {
+		Weight::from_parts(70_000_000, 3_600)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Storage: Airdrop Claims (r:1000 w:1000)
+	/// The range of component `c` is `[0, 1000]`.
+	fn add_claims(c: u32, ) -> Weight {
+		Weight::from_parts(8_000_000, 0)
+			.saturating_add(Weight::from_parts(3_000_000, 2_500).saturating_mul(c.into()))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(c.into())))
+			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(c.into())))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	fn claim_airdrop() -> Weight {
+		Weight::from_parts(70_000_000, 3_600)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	fn add_claims(c: u32, ) -> Weight {
+		Weight::from_parts(8_000_000, 0)
+			.saturating_add(Weight::from_parts(3_000_000, 2_500).saturating_mul(c.into()))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(c.into())))
+			.saturating_add(RocksDbWeight::get().writes((1_u64).saturating_mul(c.into())))
+	}
+}
@@ -0,0 +1,54 @@
+//! Benchmarking setup for pallet-capacity
+#![cfg(feature = "runtime-benchmarks")]
+use super::*;
+
+use frame_benchmarking::v2::*;
+use frame_support::traits::Hooks;
+use frame_system::pallet_prelude::BlockNumberFor;
+
+#[benchmarks]
+mod benchmarks {
+	use super::*;
+
+	#[benchmark]
+	fn on_initialize_signal() {
+		frame_system::Pallet::<T>::deposit_log(signal_digest(2 * T::InitialBlockLength::get()));
+		let n: BlockNumberFor<T> = 1u32.into();
+
+		#[block]
+		{
+			Pallet::<T>::on_initialize(n);
+		}
+
+		assert_eq!(Tally::<T>::get().raise, 1);
+	}
+
+	#[benchmark]
+	fn on_initialize_adjust() {
+		let window = T::SignalWindow::get();
+		Tally::<T>::put(SignalTally { raise: window, lower: 0, blocks: window - 1 });
+		frame_system::Pallet::<T>::deposit_log(signal_digest(2 * T::InitialBlockLength::get()));
+		let n: BlockNumberFor<T> = window.into();
+
+		#[block]
+		{
+			Pallet::<T>::on_initialize(n);
+		}
+
+		assert_eq!(Tally::<T>::get(), SignalTally::default());
+	}
+
+	#[benchmark]
+	fn set_bounds() -> Result<(), BenchmarkError> {
+		let origin = T::BoundsOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+		let (min, max) = T::InitialBounds::get();
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, min, max);
+
+		assert_eq!(BlockLengthBounds::<T>::get(), (min, max));
+		Ok(())
+	}
+
+	impl_benchmark_test_suite!(Pallet, crate::mock::new_test_ext(), crate::mock::Test);
+}
@@ -0,0 +1,230 @@
+//! # Capacity Pallet
+//!
+//! Lets miners negotiate the block length limit without hard forks.
+//!
+//! ## Overview
+//!
+//! Every block author may signal the block length it would like the chain to allow by adding a
+//! [`CAPACITY_ENGINE_ID`] pre-runtime digest to its header (see [`signal_digest`]). A block
+//! without a signal counts as a vote for the current target.
+//!
+//! A signal above the current target is a vote to raise it, one below a vote to lower it. At the
+//! end of every window of [`Config::SignalWindow`] blocks, if a majority of the window voted the
+//! same way the target moves by [`Config::MaxAdjustment`] of its current value in that direction.
+//! It always stays within the [`BlockLengthBounds`] set by [`Config::BoundsOrigin`].
+//!
+//! Only the vote counts of the current window are stored, so signalling costs the same whatever
+//! the window length.
+//!
+//! The runtime feeds the target into `frame_system` through [`DynamicBlockLength`].
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use pallet::*;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+pub mod weights;
+pub use weights::*;
+
+extern crate alloc;
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use core::marker::PhantomData;
+use frame_support::traits::Get;
+use frame_system::limits::BlockLength;
+use scale_info::TypeInfo;
+use sp_runtime::{traits::{Saturating, Zero},
+	ConsensusEngineId, DigestItem, Perbill, RuntimeDebug};
+
+/// Engine id of the pre-runtime digest carrying a miner's block length signal.
+pub const CAPACITY_ENGINE_ID: ConsensusEngineId = *b"CAPS";
+
+/// The pre-runtime digest with which a block author signals a preferred block length in bytes.
+pub fn signal_digest(block_length: u32) -> DigestItem {
+	DigestItem::PreRuntime(CAPACITY_ENGINE_ID, block_length.encode())
+}
+
+/// Votes cast in the current signalling window.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo, RuntimeDebug)]
+pub struct SignalTally {
+	/// Blocks signalling a length above the target
+	pub raise: u32,
+	/// Blocks signalling a length below the target
+	pub lower: u32,
+	/// Blocks in the window so far, signalling or not
+	pub blocks: u32,
+}
+
+/// `frame_system` block length limits following the negotiated target, with `NormalRatio` of it
+/// available to normal extrinsics.
+pub struct DynamicBlockLength<T, NormalRatio>(PhantomData<(T, NormalRatio)>);
+
+impl<T: Config, NormalRatio: Get<Perbill>> Get<BlockLength> for DynamicBlockLength<T, NormalRatio> {
+	fn get() -> BlockLength {
+		BlockLength::max_with_normal_ratio(BlockLengthTarget::<T>::get(), NormalRatio::get())
+	}
+}
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+	use frame_support::pallet_prelude::*;
+	use frame_system::pallet_prelude::*;
+
+	#[pallet::pallet]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// The overarching runtime event type.
+		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+		/// A type representing the weights required by the dispatchables of this pallet.
+		type WeightInfo: WeightInfo;
+
+		/// Origin allowed to change [`BlockLengthBounds`].
+		type BoundsOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+		/// Block length target before any adjustment, in bytes.
+		#[pallet::constant]
+		type InitialBlockLength: Get<u32>;
+
+		/// Bounds on the target before governance sets any, in bytes.
+		#[pallet::constant]
+		type InitialBounds: Get<(u32, u32)>;
+
+		/// Number of blocks whose signals are tallied for each adjustment.
+		#[pallet::constant]
+		type SignalWindow: Get<u32>;
+
+		/// Largest change of the target in a single adjustment, relative to its current value.
+		#[pallet::constant]
+		type MaxAdjustment: Get<Perbill>;
+	}
+
+	#[pallet::type_value]
+	pub fn DefaultTarget<T: Config>() -> u32 {
+		T::InitialBlockLength::get()
+	}
+
+	#[pallet::type_value]
+	pub fn DefaultBounds<T: Config>() -> (u32, u32) {
+		T::InitialBounds::get()
+	}
+
+	/// The block length limit currently in force, in bytes.
+	#[pallet::storage]
+	pub type BlockLengthTarget<T: Config> = StorageValue<_, u32, ValueQuery, DefaultTarget<T>>;
+
+	/// Minimum and maximum the target can be adjusted to, in bytes.
+	#[pallet::storage]
+	pub type BlockLengthBounds<T: Config> = StorageValue<_, (u32, u32), ValueQuery, DefaultBounds<T>>;
+
+	/// Votes of the blocks in the current window.
+	#[pallet::storage]
+	pub type Tally<T: Config> = StorageValue<_, SignalTally, ValueQuery>;
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// A signalling window closed. `new` equals `old` when no majority formed.
+		WindowClosed { old: u32, new: u32, tally: SignalTally },
+		/// New bounds were set for the block length target.
+		BoundsSet { min: u32, max: u32 },
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The minimum is zero or above the maximum
+		InvalidBounds,
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(n: BlockNumberFor<T>) -> Weight {
+			let target = BlockLengthTarget::<T>::get();
+			Tally::<T>::mutate(|tally| {
+				match Self::block_signal() {
+					Some(signal) if signal > target => tally.raise.saturating_inc(),
+					Some(signal) if signal < target => tally.lower.saturating_inc(),
+					_ => {},
+				}
+				tally.blocks.saturating_inc();
+			});
+
+			let window: BlockNumberFor<T> = T::SignalWindow::get().max(1).into();
+			if (n % window).is_zero() {
+				Self::adjust();
+				T::WeightInfo::on_initialize_adjust()
+			} else {
+				T::WeightInfo::on_initialize_signal()
+			}
+		}
+
+		fn integrity_test() {
+			let (min, max) = T::InitialBounds::get();
+			assert!(min > 0 && min <= max, "InitialBounds must be a non-empty range above zero");
+			assert!(
+				(min..=max).contains(&T::InitialBlockLength::get()),
+				"InitialBlockLength must lie within InitialBounds"
+			);
+		}
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Set the bounds within which miners may move the block length target, clamping the
+		/// current target into them.
+		#[pallet::call_index(0)]
+		#[pallet::weight(T::WeightInfo::set_bounds())]
+		pub fn set_bounds(origin: OriginFor<T>, min: u32, max: u32) -> DispatchResult {
+			T::BoundsOrigin::ensure_origin(origin)?;
+			ensure!(min > 0 && min <= max, Error::<T>::InvalidBounds);
+
+			BlockLengthBounds::<T>::put((min, max));
+			BlockLengthTarget::<T>::mutate(|target| *target = (*target).clamp(min, max));
+			Self::deposit_event(Event::BoundsSet { min, max });
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// The block length signalled by the author of the current block, if any.
+		pub fn block_signal() -> Option<u32> {
+			frame_system::Pallet::<T>::digest()
+				.logs
+				.iter()
+				.find_map(|item| item.pre_runtime_try_to::<u32>(&CAPACITY_ENGINE_ID))
+		}
+
+		/// Move the target in the direction a majority of the window voted for, and start a
+		/// new window.
+		fn adjust() {
+			let tally = Tally::<T>::take();
+			// A majority of the full window, so silent blocks count against any change
+			let majority = T::SignalWindow::get().max(tally.blocks) / 2;
+
+			let old = BlockLengthTarget::<T>::get();
+			let step = T::MaxAdjustment::get().mul_ceil(old);
+			let (min, max) = BlockLengthBounds::<T>::get();
+			let new = if tally.raise > majority {
+				old.saturating_add(step)
+			} else if tally.lower > majority {
+				old.saturating_sub(step)
+			} else {
+				old
+			}
+			.clamp(min, max);
+
+			BlockLengthTarget::<T>::put(new);
+			Self::deposit_event(Event::WindowClosed { old, new, tally });
+		}
+	}
+}
@@ -0,0 +1,54 @@
+use crate as pallet_capacity;
+use frame_support::{derive_impl, parameter_types};
+use sp_runtime::{BuildStorage, Perbill};
+
+type Block = frame_system::mocking::MockBlock<Test>;
+
+frame_support::construct_runtime!(
+	pub enum Test
+	{
+		System: frame_system,
+		Capacity: pallet_capacity,
+	}
+);
+
+#[derive_impl(frame_system::config_preludes::TestDefaultConfig)]
+impl frame_system::Config for Test {
+	type Block = Block;
+}
+
+parameter_types! {
+	pub const InitialBlockLength: u32 = 1_000_000;
+	pub const InitialBounds: (u32, u32) = (500_000, 4_000_000);
+	pub const SignalWindow: u32 = 10;
+	pub const MaxAdjustment: Perbill = Perbill::from_percent(10);
+	pub const NormalRatio: Perbill = Perbill::from_percent(75);
+}
+
+impl pallet_capacity::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type WeightInfo = ();
+	type BoundsOrigin = frame_system::EnsureRoot<u64>;
+	type InitialBlockLength = InitialBlockLength;
+	type InitialBounds = InitialBounds;
+	type SignalWindow = SignalWindow;
+	type MaxAdjustment = MaxAdjustment;
+}
+
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	frame_system::GenesisConfig::<Test>::default().build_storage().unwrap().into()
+}
+
+/// Run blocks `from..=to`, each authored with `signal` if given.
+pub fn run_blocks(from: u64, to: u64, signal: Option<u32>) {
+	use frame_support::traits::Hooks;
+
+	for n in from..=to {
+		System::reset_events();
+		System::initialize(&n, &Default::default(), &Default::default());
+		if let Some(signal) = signal {
+			System::deposit_log(crate::signal_digest(signal));
+		}
+		Capacity::on_initialize(n);
+	}
+}
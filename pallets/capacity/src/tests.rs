@@ -0,0 +1,76 @@
+use crate::{
+	mock::*, BlockLengthBounds, BlockLengthTarget, DynamicBlockLength, Error, Event, SignalTally, Tally,
+};
+use frame_support::{assert_noop, assert_ok, traits::Get};
+use frame_system::limits::BlockLength;
+use frame_support::dispatch::DispatchClass;
+use sp_runtime::DispatchError;
+
+#[test]
+fn majority_moves_target_by_max_adjustment() {
+	new_test_ext().execute_with(|| {
+		// A window asking for double the capacity only gets 10% more
+		run_blocks(1, 10, Some(2_000_000));
+		assert_eq!(BlockLengthTarget::<Test>::get(), 1_100_000);
+		System::assert_last_event(
+			Event::WindowClosed {
+				old: 1_000_000,
+				new: 1_100_000,
+				tally: SignalTally { raise: 10, lower: 0, blocks: 10 },
+			}
+			.into(),
+		);
+		assert_eq!(Tally::<Test>::get(), SignalTally::default());
+
+		// Signals below the new target vote it back down
+		run_blocks(11, 20, Some(1_050_000));
+		assert_eq!(BlockLengthTarget::<Test>::get(), 990_000);
+	});
+}
+
+#[test]
+fn silent_miners_vote_for_the_current_target() {
+	new_test_ext().execute_with(|| {
+		// Five of ten blocks is not a majority
+		run_blocks(1, 5, Some(4_000_000));
+		run_blocks(6, 10, None);
+		assert_eq!(BlockLengthTarget::<Test>::get(), 1_000_000);
+
+		// Six is
+		run_blocks(11, 16, Some(4_000_000));
+		run_blocks(17, 20, None);
+		assert_eq!(BlockLengthTarget::<Test>::get(), 1_100_000);
+	});
+}
+
+#[test]
+fn target_stays_within_governance_bounds() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			Capacity::set_bounds(RuntimeOrigin::signed(1), 1, 2),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			Capacity::set_bounds(RuntimeOrigin::root(), 2_000_000, 1_000_000),
+			Error::<Test>::InvalidBounds
+		);
+
+		// Lowering the maximum clamps the current target right away
+		assert_ok!(Capacity::set_bounds(RuntimeOrigin::root(), 100_000, 900_000));
+		assert_eq!(BlockLengthBounds::<Test>::get(), (100_000, 900_000));
+		assert_eq!(BlockLengthTarget::<Test>::get(), 900_000);
+
+		run_blocks(1, 10, Some(4_000_000));
+		assert_eq!(BlockLengthTarget::<Test>::get(), 900_000);
+	});
+}
+
+#[test]
+fn system_block_length_follows_target() {
+	new_test_ext().execute_with(|| {
+		BlockLengthTarget::<Test>::put(2_000_000);
+		let limits: BlockLength = <DynamicBlockLength<Test, NormalRatio> as Get<BlockLength>>::get();
+		assert_eq!(*limits.max.get(DispatchClass::Operational), 2_000_000);
+		assert_eq!(*limits.max.get(DispatchClass::Normal), 1_500_000);
+	});
+}
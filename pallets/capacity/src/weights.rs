@@ -0,0 +1,64 @@
+//! Weights for pallet_capacity
+//!
+//! PLACEHOLDER VALUES, NOT YET GENERATED ON REFERENCE HARDWARE.
+//! Regenerate with `./scripts/benchmark-weights.sh pallet_capacity`.
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use core::marker::PhantomData;
+
+/// Weight functions needed for pallet_capacity.
+pub trait WeightInfo {
+	fn on_initialize_signal() -> Weight;
+	fn on_initialize_adjust() -> Weight;
+	fn set_bounds() -> Weight;
+}
+
+/// Weights for pallet_capacity using the Substrate node and recommended hardware.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	/// Storage: Capacity BlockLengthTarget (r:1 w:0)
+	/// Storage: Capacity Tally (r:1 w:1)
+	fn on_initialize_signal() -> Weight {
+		Weight::from_parts(6_000_000, 1_500)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: Capacity BlockLengthTarget (r:1 w:1)
+	/// Storage: Capacity BlockLengthBounds (r:1 w:0)
+	/// Storage: Capacity Tally (r:1 w:1)
+	fn on_initialize_adjust() -> Weight {
+		Weight::from_parts(10_000_000, 1_500)
+			.saturating_add(T::DbWeight::get().reads(3_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Storage: Capacity BlockLengthBounds (r:0 w:1)
+	/// Storage: Capacity BlockLengthTarget (r:1 w:1)
+	fn set_bounds() -> Weight {
+		Weight::from_parts(9_000_000, 1_500)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	fn on_initialize_signal() -> Weight {
+		Weight::from_parts(6_000_000, 1_500)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	fn on_initialize_adjust() -> Weight {
+		Weight::from_parts(10_000_000, 1_500)
+			.saturating_add(RocksDbWeight::get().reads(3_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	fn set_bounds() -> Weight {
+		Weight::from_parts(9_000_000, 1_500)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+}
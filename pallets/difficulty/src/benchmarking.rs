@@ -1,35 +1,77 @@
-//! Benchmarking setup for pallet-template
+//! Benchmarking setup for pallet-difficulty
 #![cfg(feature = "runtime-benchmarks")]
 use super::*;
 
 #[allow(unused)]
-use crate::Pallet as Template;
+use crate::Pallet as Difficulty;
 use frame_benchmarking::v2::*;
-use frame_system::RawOrigin;
+use frame_support::{traits::{Hooks, Time}, BoundedVec};
+use sp_core::U256;
 
 #[benchmarks]
 mod benchmarks {
 	use super::*;
 
+	// Worst case: the window is full, so every entry is shifted and the retarget sums over all
+	// `DIFFICULTY_ADJUST_WINDOW` data points.
 	#[benchmark]
-	fn do_something() {
-		let value = 100u32;
-		let caller: T::AccountId = whitelisted_caller();
+	fn on_finalize() {
+		let mut window = BoundedVec::new();
+		for _ in 0..DIFFICULTY_ADJUST_WINDOW {
+			let _ = window.try_push(DifficultyAndTimestamp {
+				difficulty: U256::from(T::MinDifficulty::get()),
+				timestamp: T::TimeProvider::now(),
+			});
+		}
+		PastDifficultiesAndTimestamps::<T>::put(window);
+		let block_number = frame_system::Pallet::<T>::block_number();
+
+		#[block]
+		{
+			Difficulty::<T>::on_finalize(block_number);
+		}
+
+		assert_eq!(
+			PastDifficultiesAndTimestamps::<T>::get().len(),
+			DIFFICULTY_ADJUST_WINDOW as usize
+		);
+	}
+
+	#[benchmark]
+	fn set_target_block_time() -> Result<(), BenchmarkError> {
+		let origin =
+			T::ParametersOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+
 		#[extrinsic_call]
-		do_something(RawOrigin::Signed(caller), value);
+		_(origin as T::RuntimeOrigin, Some(1_000));
 
-		assert_eq!(Something::<T>::get(), Some(value));
+		assert_eq!(TargetBlockTimeOverride::<T>::get(), Some(1_000));
+		Ok(())
 	}
 
 	#[benchmark]
-	fn cause_error() {
-		Something::<T>::put(100u32);
-		let caller: T::AccountId = whitelisted_caller();
+	fn set_damp_factor() -> Result<(), BenchmarkError> {
+		let origin =
+			T::ParametersOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, Some(4));
+
+		assert_eq!(DampFactorOverride::<T>::get(), Some(4));
+		Ok(())
+	}
+
+	#[benchmark]
+	fn set_clamp_factor() -> Result<(), BenchmarkError> {
+		let origin =
+			T::ParametersOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+
 		#[extrinsic_call]
-		cause_error(RawOrigin::Signed(caller));
+		_(origin as T::RuntimeOrigin, Some(4));
 
-		assert_eq!(Something::<T>::get(), Some(101u32));
+		assert_eq!(ClampFactorOverride::<T>::get(), Some(4));
+		Ok(())
 	}
 
-	impl_benchmark_test_suite!(Template, crate::mock::new_test_ext(), crate::mock::Test);
+	impl_benchmark_test_suite!(Difficulty, crate::mock::new_test_ext(), crate::mock::Test);
 }
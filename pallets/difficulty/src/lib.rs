@@ -42,6 +42,7 @@
 pub use pallet::*;
 use sp_core::U256;
 use core::cmp::{min, max};
+use frame_support::traits::OnTimestampSet;
 use sp_runtime::traits::{UniqueSaturatedInto, Time};
 
 #[cfg(test)]
@@ -57,12 +58,23 @@ pub use weights::*;
 
 const DIFFICULTY_ADJUST_WINDOW: u128 = 60;
 
+/// Target of the pallet's tracing spans, as given to `--tracing-targets`. The runtime only
+/// forwards them to the node when built with the `with-tracing` feature.
+pub const LOG_TARGET: &str = "runtime::difficulty";
+
+/// Number of recent blocks whose median timestamp a new block's must exceed, as in Bitcoin.
+const MEDIAN_TIME_SPAN: usize = 11;
+
+// The factors are at least 1 when set through the pallet, but a runtime may still configure 0,
+// which then means no damping and a retarget clamped to the goal.
 fn damp(actual: u128, goal: u128, damp_factor: u128) -> u128 {
-    (actual + (damp_factor - 1) * goal) / damp_factor
+    let damp_factor = damp_factor.max(1);
+    actual.saturating_add((damp_factor - 1).saturating_mul(goal)) / damp_factor
 }
 
 fn clamp(actual: u128, goal: u128, clamp_factor: u128) -> u128 {
-    max(goal / clamp_factor, min(actual, goal * clamp_factor))
+    let clamp_factor = clamp_factor.max(1);
+    max(goal / clamp_factor, min(actual, goal.saturating_mul(clamp_factor)))
 }
 
 #[frame_support::pallet]
@@ -84,30 +96,80 @@ pub mod pallet {
         type ClampFactor: Get<u128>;
         type MaxDifficulty: Get<u128>;
         type MinDifficulty: Get<u128>;
+
+        /// Origin allowed to override `TargetBlockTime`, `DampFactor` and `ClampFactor`.
+        type ParametersOrigin: EnsureOrigin<Self::RuntimeOrigin>;
     }
 
+    /// The moment type of the configured time provider.
+    pub type MomentOf<T> = <<T as Config>::TimeProvider as Time>::Moment;
+
     #[pallet::storage]
     #[pallet::getter(fn difficulty_and_timestamps)]
     pub type PastDifficultiesAndTimestamps<T: Config> = 
-        StorageValue<_, BoundedVec<DifficultyAndTimestamp<T::Moment>, ConstU32<60>>, ValueQuery>;
+        StorageValue<_, BoundedVec<DifficultyAndTimestamp<MomentOf<T>>, ConstU32<60>>, ValueQuery>;
 
     #[pallet::storage]
     #[pallet::getter(fn difficulty)]
     pub type CurrentDifficulty<T: Config> = StorageValue<_, Difficulty, ValueQuery>;
 
+    /// Target block time in force instead of [`Config::TargetBlockTime`], if set.
+    #[pallet::storage]
+    pub type TargetBlockTimeOverride<T: Config> = StorageValue<_, u128, OptionQuery>;
+
+    /// Damping factor in force instead of [`Config::DampFactor`], if set.
+    #[pallet::storage]
+    pub type DampFactorOverride<T: Config> = StorageValue<_, u128, OptionQuery>;
+
+    /// Clamping factor in force instead of [`Config::ClampFactor`], if set.
+    #[pallet::storage]
+    pub type ClampFactorOverride<T: Config> = StorageValue<_, u128, OptionQuery>;
+
+    /// Lowest difficulty in force instead of [`Config::MinDifficulty`], if set at genesis.
+    #[pallet::storage]
+    pub type MinDifficultyOverride<T: Config> = StorageValue<_, u128, OptionQuery>;
+
+    /// Highest difficulty in force instead of [`Config::MaxDifficulty`], if set at genesis.
+    #[pallet::storage]
+    pub type MaxDifficultyOverride<T: Config> = StorageValue<_, u128, OptionQuery>;
+
+    /// The difficulty a chain starts at, and the retarget parameters it starts with instead of
+    /// the runtime's constants, so a new network can be tuned in its chain spec without building
+    /// another runtime.
     #[pallet::genesis_config]
+    #[derive(frame_support::DefaultNoBound)]
     pub struct GenesisConfig<T: Config> {
+        /// Clamped to the minimum and maximum difficulty in force, like every retarget.
         pub initial_difficulty: Difficulty,
+        /// Target block time in milliseconds. Aura still authors blocks at the runtime's slot
+        /// duration, so only retargeting moves towards it.
+        pub target_block_time: Option<u128>,
+        pub min_difficulty: Option<u128>,
+        pub max_difficulty: Option<u128>,
+        #[serde(skip)]
+        pub _marker: PhantomData<T>,
     }
 
     #[pallet::genesis_build]
-    impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
+    impl<T: Config> BuildGenesisConfig for GenesisConfig<T> {
         fn build(&self) {
-            <CurrentDifficulty<T>>::put(self.initial_difficulty);
+            assert!(self.target_block_time != Some(0), "the target block time must be at least 1");
+            let min_difficulty = self.min_difficulty.unwrap_or_else(T::MinDifficulty::get);
+            let max_difficulty = self.max_difficulty.unwrap_or_else(T::MaxDifficulty::get);
+            assert!(min_difficulty >= 1, "the minimum difficulty must be at least 1");
+            assert!(min_difficulty <= max_difficulty, "the minimum difficulty exceeds the maximum");
+
+            let initial_difficulty = self
+                .initial_difficulty
+                .clamp(U256::from(min_difficulty), U256::from(max_difficulty));
+            <CurrentDifficulty<T>>::put(initial_difficulty);
+            <TargetBlockTimeOverride<T>>::set(self.target_block_time);
+            <MinDifficultyOverride<T>>::set(self.min_difficulty);
+            <MaxDifficultyOverride<T>>::set(self.max_difficulty);
         }
     }
 
-    #[derive(Encode, Decode, Clone, Copy, Eq, PartialEq, Debug, Default)]
+    #[derive(Encode, Decode, Clone, Copy, Eq, PartialEq, Debug, Default, TypeInfo, MaxEncodedLen)]
     pub struct DifficultyAndTimestamp<M> {
         pub difficulty: Difficulty,
         pub timestamp: M,
@@ -115,22 +177,49 @@ pub mod pallet {
 
     pub type Difficulty = U256;
 
+    /// The network hashrate estimated over recent blocks, by [`Pallet::network_hashrate`].
+    #[derive(Encode, Decode, Clone, Copy, Eq, PartialEq, Debug, TypeInfo)]
+    pub struct HashrateEstimate {
+        /// Hashes per second
+        pub hashrate: U256,
+        /// Blocks the estimate covers
+        pub blocks: u32,
+        /// Average time between those blocks, in milliseconds
+        pub average_block_time: u128,
+        /// The block time the difficulty is retargeted towards, in milliseconds
+        pub target_block_time: u128,
+        /// The difficulty the next block has to be sealed at
+        pub difficulty: Difficulty,
+    }
+
     #[pallet::event]
     #[pallet::generate_deposit(pub(super) fn deposit_event)]
     pub enum Event<T: Config> {
         DifficultyUpdated {
             difficulty: Difficulty,
         },
+        /// The target block time was overridden, or reset to the configured one with `None`.
+        TargetBlockTimeSet { value: Option<u128> },
+        /// The damping factor was overridden, or reset to the configured one with `None`.
+        DampFactorSet { value: Option<u128> },
+        /// The clamping factor was overridden, or reset to the configured one with `None`.
+        ClampFactorSet { value: Option<u128> },
     }
 
     #[pallet::error]
     pub enum Error<T> {
         FailedToUpdateDifficulty,
+        /// Parameters must be at least 1
+        ZeroParameter,
     }
 
     #[pallet::hooks]
-    impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
-        fn on_finalize(_block_number: T::BlockNumber) {
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_initialize(_block_number: BlockNumberFor<T>) -> Weight {
+            T::WeightInfo::on_finalize()
+        }
+
+        fn on_finalize(_block_number: BlockNumberFor<T>) {
             let mut data = Self::difficulty_and_timestamps();
             
             // If we haven't filled up the window yet, just add the new data point
@@ -153,18 +242,140 @@ pub mod pallet {
             <PastDifficultiesAndTimestamps<T>>::put(data);
             Self::update_difficulty();
         }
+
+        fn integrity_test() {
+            assert!(T::TargetBlockTime::get() >= 1, "TargetBlockTime must be at least 1");
+            assert!(T::DampFactor::get() >= 1, "DampFactor must be at least 1");
+            assert!(T::ClampFactor::get() >= 1, "ClampFactor must be at least 1");
+            assert!(
+                T::MinDifficulty::get() >= 1 && T::MinDifficulty::get() <= T::MaxDifficulty::get(),
+                "MinDifficulty must be at least 1 and at most MaxDifficulty"
+            );
+        }
+    }
+
+    #[pallet::call]
+    impl<T: Config> Pallet<T> {
+        /// Override the target block time, in milliseconds, or with `None` go back to
+        /// [`Config::TargetBlockTime`].
+        #[pallet::call_index(0)]
+        #[pallet::weight(T::WeightInfo::set_target_block_time())]
+        pub fn set_target_block_time(origin: OriginFor<T>, value: Option<u128>) -> DispatchResult {
+            T::ParametersOrigin::ensure_origin(origin)?;
+            ensure!(value != Some(0), Error::<T>::ZeroParameter);
+            <TargetBlockTimeOverride<T>>::set(value);
+            Self::deposit_event(Event::TargetBlockTimeSet { value });
+            Ok(())
+        }
+
+        /// Override how strongly the observed window length is damped towards the target, or
+        /// with `None` go back to [`Config::DampFactor`].
+        #[pallet::call_index(1)]
+        #[pallet::weight(T::WeightInfo::set_damp_factor())]
+        pub fn set_damp_factor(origin: OriginFor<T>, value: Option<u128>) -> DispatchResult {
+            T::ParametersOrigin::ensure_origin(origin)?;
+            ensure!(value != Some(0), Error::<T>::ZeroParameter);
+            <DampFactorOverride<T>>::set(value);
+            Self::deposit_event(Event::DampFactorSet { value });
+            Ok(())
+        }
+
+        /// Override the factor bounding a single retarget in either direction, or with `None`
+        /// go back to [`Config::ClampFactor`].
+        #[pallet::call_index(2)]
+        #[pallet::weight(T::WeightInfo::set_clamp_factor())]
+        pub fn set_clamp_factor(origin: OriginFor<T>, value: Option<u128>) -> DispatchResult {
+            T::ParametersOrigin::ensure_origin(origin)?;
+            ensure!(value != Some(0), Error::<T>::ZeroParameter);
+            <ClampFactorOverride<T>>::set(value);
+            Self::deposit_event(Event::ClampFactorSet { value });
+            Ok(())
+        }
     }
 
     impl<T: Config> Pallet<T> {
+        /// The target block time in force.
+        pub fn target_block_time() -> u128 {
+            <TargetBlockTimeOverride<T>>::get().unwrap_or_else(T::TargetBlockTime::get)
+        }
+
+        /// The damping factor in force.
+        pub fn damp_factor() -> u128 {
+            <DampFactorOverride<T>>::get().unwrap_or_else(T::DampFactor::get)
+        }
+
+        /// The clamping factor in force.
+        pub fn clamp_factor() -> u128 {
+            <ClampFactorOverride<T>>::get().unwrap_or_else(T::ClampFactor::get)
+        }
+
+        /// The lowest difficulty in force.
+        pub fn min_difficulty() -> u128 {
+            <MinDifficultyOverride<T>>::get().unwrap_or_else(T::MinDifficulty::get)
+        }
+
+        /// The highest difficulty in force.
+        pub fn max_difficulty() -> u128 {
+            <MaxDifficultyOverride<T>>::get().unwrap_or_else(T::MaxDifficulty::get)
+        }
+
+        /// The median timestamp of the last [`MEDIAN_TIME_SPAN`] blocks recorded, if any. A new
+        /// block must be timestamped after it.
+        pub fn median_time_past() -> Option<MomentOf<T>> {
+            let data = Self::difficulty_and_timestamps();
+            let recent = &data[data.len().saturating_sub(MEDIAN_TIME_SPAN)..];
+            let mut times = [MomentOf::<T>::default(); MEDIAN_TIME_SPAN];
+            for (time, item) in times.iter_mut().zip(recent) {
+                *time = item.timestamp;
+            }
+            let times = &mut times[..recent.len()];
+            times.sort_unstable();
+            times.get(times.len() / 2).copied()
+        }
+
+        /// The network hashrate estimated from the last `window` entries of the retarget window,
+        /// or `None` until two blocks with distinct timestamps are recorded.
+        ///
+        /// Finding a block takes `difficulty` hashes on average, so the hashrate is the sum of the
+        /// difficulties of the blocks after the first entry over the time since it.
+        pub fn network_hashrate(window: u32) -> Option<HashrateEstimate> {
+            let data = Self::difficulty_and_timestamps();
+            let window = min(window as usize, data.len());
+            if window < 2 {
+                return None
+            }
+            let recent = &data[data.len() - window..];
+            let first: u128 = recent[0].timestamp.unique_saturated_into();
+            let last: u128 = recent[window - 1].timestamp.unique_saturated_into();
+            let span = last.checked_sub(first).filter(|span| *span > 0)?;
+
+            let hashes = recent[1..]
+                .iter()
+                .fold(U256::zero(), |hashes, item| hashes.saturating_add(item.difficulty));
+            let blocks = window as u32 - 1;
+            Some(HashrateEstimate {
+                hashrate: hashes.saturating_mul(U256::from(1_000)) / U256::from(span),
+                blocks,
+                average_block_time: span / u128::from(blocks),
+                target_block_time: Self::target_block_time(),
+                difficulty: Self::difficulty(),
+            })
+        }
+
         fn update_difficulty() {
             let data = Self::difficulty_and_timestamps();
+            sp_tracing::enter_span!(sp_tracing::debug_span!(
+                target: LOG_TARGET,
+                "update_difficulty",
+                window = data.len(),
+            ));
             
             // Calculate timestamp delta
-            let mut ts_delta = 0;
+            let mut ts_delta: u128 = 0;
             for i in 1..data.len() {
                 let prev: u128 = data[i - 1].timestamp.unique_saturated_into();
                 let cur: u128 = data[i].timestamp.unique_saturated_into();
-                ts_delta += cur.saturating_sub(prev);
+                ts_delta = ts_delta.saturating_add(cur.saturating_sub(prev));
             }
 
             // Prevent division by zero
@@ -175,30 +386,34 @@ pub mod pallet {
             // Calculate difficulty sum
             let mut diff_sum = U256::zero();
             for item in data.iter() {
-                diff_sum += item.difficulty;
+                diff_sum = diff_sum.saturating_add(item.difficulty);
             }
 
             // Enforce minimum difficulty
-            if diff_sum < U256::from(T::MinDifficulty::get()) {
-                diff_sum = U256::from(T::MinDifficulty::get());
+            let min_difficulty = U256::from(Self::min_difficulty());
+            if diff_sum < min_difficulty {
+                diff_sum = min_difficulty;
             }
 
             // Calculate the average length of the adjustment window
-            let adjustment_window = DIFFICULTY_ADJUST_WINDOW * T::TargetBlockTime::get();
+            let target_block_time = Self::target_block_time();
+            let adjustment_window = DIFFICULTY_ADJUST_WINDOW.saturating_mul(target_block_time);
 
-            // Adjust time delta toward goal subject to dampening and clamping
+            // Adjust time delta toward goal subject to dampening and clamping. A zero target
+            // clamps it to zero, which is divided by as 1ms.
             let adj_ts = clamp(
-                damp(ts_delta, adjustment_window, T::DampFactor::get()),
+                damp(ts_delta, adjustment_window, Self::damp_factor()),
                 adjustment_window,
-                T::ClampFactor::get(),
-            );
+                Self::clamp_factor(),
+            )
+            .max(1);
 
             // Calculate new difficulty
             let difficulty = min(
-                U256::from(T::MaxDifficulty::get()),
+                U256::from(Self::max_difficulty()),
                 max(
-                    U256::from(T::MinDifficulty::get()),
-                    diff_sum * U256::from(T::TargetBlockTime::get()) / U256::from(adj_ts)
+                    min_difficulty,
+                    diff_sum.saturating_mul(U256::from(target_block_time)) / U256::from(adj_ts)
                 )
             );
 
@@ -207,4 +422,15 @@ pub mod pallet {
             Self::deposit_event(Event::DifficultyUpdated { difficulty });
         }
     }
-}
\ No newline at end of file
+}
+
+/// Rejects blocks timestamped at or before the median time past, so an author cannot drag the
+/// window's clock back to make the retarget read blocks as slower than they were. Timestamps too
+/// far in the future are rejected on import, by the importing node's clock.
+impl<T: Config> OnTimestampSet<MomentOf<T>> for Pallet<T> {
+    fn on_timestamp_set(now: MomentOf<T>) {
+        if let Some(median) = Self::median_time_past() {
+            assert!(now > median, "Timestamp must be later than the median time past");
+        }
+    }
+}
@@ -1,27 +1,220 @@
-use crate::{mock::*, Error, Event, Something};
-use frame_support::{assert_noop, assert_ok};
-
-#[test]
-fn it_works_for_default_value() {
-	new_test_ext().execute_with(|| {
-		// Go past genesis block so events get deposited
-		System::set_block_number(1);
-		// Dispatch a signed extrinsic.
-		assert_ok!(TemplateModule::do_something(RuntimeOrigin::signed(1), 42));
-		// Read pallet storage and assert an expected result.
-		assert_eq!(Something::<Test>::get(), Some(42));
-		// Assert that the correct event was deposited
-		System::assert_last_event(Event::SomethingStored { something: 42, who: 1 }.into());
-	});
-}
-
-#[test]
-fn correct_error_for_none_value() {
-	new_test_ext().execute_with(|| {
-		// Ensure the expected error is thrown when no value is present.
-		assert_noop!(
-			TemplateModule::cause_error(RuntimeOrigin::signed(1)),
-			Error::<Test>::NoneValue
-		);
-	});
+use crate::{
+    mock::*, CurrentDifficulty, DifficultyAndTimestamp, Error, Event, HashrateEstimate,
+    PastDifficultiesAndTimestamps, DIFFICULTY_ADJUST_WINDOW,
+};
+use frame_support::{assert_noop, assert_ok, traits::{Hooks, OnTimestampSet}};
+use sp_core::U256;
+use sp_runtime::{BuildStorage, DispatchError};
+
+#[test]
+fn window_fills_up_and_stays_bounded() {
+    new_test_ext().execute_with(|| {
+        CurrentDifficulty::<Test>::put(U256::from(1_000));
+
+        for n in 1..=(DIFFICULTY_ADJUST_WINDOW as u64 + 5) {
+            DifficultyPallet::on_finalize(n);
+        }
+
+        assert_eq!(
+            PastDifficultiesAndTimestamps::<Test>::get().len(),
+            DIFFICULTY_ADJUST_WINDOW as usize
+        );
+    });
+}
+
+#[test]
+fn governance_overrides_retarget_parameters() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            DifficultyPallet::set_damp_factor(RuntimeOrigin::signed(1), Some(10)),
+            DispatchError::BadOrigin
+        );
+        assert_noop!(
+            DifficultyPallet::set_target_block_time(RuntimeOrigin::root(), Some(0)),
+            Error::<Test>::ZeroParameter
+        );
+
+        // A weaker pull towards the target and a wider clamp let a fast window move further
+        assert_ok!(DifficultyPallet::set_damp_factor(RuntimeOrigin::root(), Some(10)));
+        System::assert_last_event(Event::DampFactorSet { value: Some(10) }.into());
+        assert_ok!(DifficultyPallet::set_clamp_factor(RuntimeOrigin::root(), Some(4)));
+        assert_eq!(DifficultyPallet::damp_factor(), 10);
+        assert_eq!(DifficultyPallet::clamp_factor(), 4);
+        CurrentDifficulty::<Test>::put(U256::from(1_000));
+        DifficultyPallet::on_finalize(1);
+        assert_eq!(CurrentDifficulty::<Test>::get(), U256::from(18));
+
+        // Resetting goes back to the configured constants
+        assert_ok!(DifficultyPallet::set_damp_factor(RuntimeOrigin::root(), None));
+        assert_ok!(DifficultyPallet::set_clamp_factor(RuntimeOrigin::root(), None));
+        assert_eq!(DifficultyPallet::damp_factor(), 2);
+        PastDifficultiesAndTimestamps::<Test>::kill();
+        CurrentDifficulty::<Test>::put(U256::from(1_000));
+        DifficultyPallet::on_finalize(1);
+        assert_eq!(CurrentDifficulty::<Test>::get(), U256::from(33));
+
+        assert_ok!(DifficultyPallet::set_target_block_time(RuntimeOrigin::root(), Some(20)));
+        assert_eq!(DifficultyPallet::target_block_time(), 20);
+    });
+}
+
+#[test]
+fn hashrate_is_estimated_over_recent_blocks() {
+    new_test_ext().execute_with(|| {
+        CurrentDifficulty::<Test>::put(U256::from(7_000));
+        let entry = |difficulty: u64, timestamp: u64| DifficultyAndTimestamp { difficulty: difficulty.into(), timestamp };
+        PastDifficultiesAndTimestamps::<Test>::put(frame_support::BoundedVec::truncate_from(vec![entry(1_000, 0)]));
+        assert_eq!(DifficultyPallet::network_hashrate(60), None);
+
+        PastDifficultiesAndTimestamps::<Test>::put(frame_support::BoundedVec::truncate_from(vec![
+            entry(1_000, 0),
+            entry(2_000, 1_000),
+            entry(4_000, 3_000),
+            entry(6_000, 4_000),
+        ]));
+        // 12_000 hashes over the 4 seconds after the first entry
+        assert_eq!(
+            DifficultyPallet::network_hashrate(60),
+            Some(HashrateEstimate {
+                hashrate: U256::from(3_000),
+                blocks: 3,
+                average_block_time: 1_333,
+                target_block_time: 10,
+                difficulty: U256::from(7_000),
+            })
+        );
+        // Over the last two entries only: 6_000 hashes in a second
+        assert_eq!(DifficultyPallet::network_hashrate(2).map(|estimate| estimate.hashrate), Some(U256::from(6_000)));
+        assert_eq!(DifficultyPallet::network_hashrate(1), None);
+
+        // Blocks sharing a timestamp give no estimate
+        PastDifficultiesAndTimestamps::<Test>::put(frame_support::BoundedVec::truncate_from(vec![
+            entry(1_000, 5_000),
+            entry(1_000, 5_000),
+        ]));
+        assert_eq!(DifficultyPallet::network_hashrate(60), None);
+    });
+}
+
+#[test]
+fn genesis_overrides_configured_parameters() {
+    let mut storage = frame_system::GenesisConfig::<Test>::default().build_storage().unwrap();
+    crate::GenesisConfig::<Test> {
+        initial_difficulty: U256::from(55),
+        target_block_time: Some(20),
+        min_difficulty: Some(50),
+        max_difficulty: Some(60),
+        ..Default::default()
+    }
+    .assimilate_storage(&mut storage)
+    .unwrap();
+
+    sp_io::TestExternalities::new(storage).execute_with(|| {
+        System::set_block_number(1);
+        assert_eq!(DifficultyPallet::difficulty(), U256::from(55));
+        assert_eq!(DifficultyPallet::target_block_time(), 20);
+
+        // Retargets stay within the bounds of the genesis config rather than the runtime's
+        DifficultyPallet::on_finalize(1);
+        assert_eq!(DifficultyPallet::difficulty(), U256::from(50));
+        PastDifficultiesAndTimestamps::<Test>::kill();
+        CurrentDifficulty::<Test>::put(U256::from(10_000));
+        DifficultyPallet::on_finalize(2);
+        assert_eq!(DifficultyPallet::difficulty(), U256::from(60));
+    });
+}
+
+#[test]
+fn genesis_clamps_the_initial_difficulty_to_its_bounds() {
+    let difficulty_at_genesis = |initial_difficulty: u64, min_difficulty, max_difficulty| {
+        let storage = crate::GenesisConfig::<Test> {
+            initial_difficulty: U256::from(initial_difficulty),
+            min_difficulty,
+            max_difficulty,
+            ..Default::default()
+        }
+        .build_storage()
+        .unwrap();
+        sp_io::TestExternalities::new(storage).execute_with(DifficultyPallet::difficulty)
+    };
+
+    assert_eq!(difficulty_at_genesis(5, Some(50), Some(60)), U256::from(50));
+    assert_eq!(difficulty_at_genesis(500, Some(50), Some(60)), U256::from(60));
+    assert_eq!(difficulty_at_genesis(55, Some(50), Some(60)), U256::from(55));
+    // Without overrides the runtime's MinDifficulty of 1 bounds it
+    assert_eq!(difficulty_at_genesis(0, None, None), U256::one());
+}
+
+#[test]
+#[should_panic(expected = "the minimum difficulty exceeds the maximum")]
+fn genesis_rejects_inverted_difficulty_bounds() {
+    let _ = crate::GenesisConfig::<Test> { min_difficulty: Some(10), max_difficulty: Some(5), ..Default::default() }
+        .build_storage();
+}
+
+#[test]
+fn timestamps_must_follow_the_median_time_past() {
+    new_test_ext().execute_with(|| {
+        // Nothing recorded yet, so any timestamp goes
+        assert_eq!(DifficultyPallet::median_time_past(), None);
+        DifficultyPallet::on_timestamp_set(0);
+
+        // The median of the last 11 blocks, out of order as they may be
+        let entries = [5, 1, 9, 3, 7, 2, 8, 4, 6, 10, 11, 20, 15];
+        PastDifficultiesAndTimestamps::<Test>::put(frame_support::BoundedVec::truncate_from(
+            entries.iter().map(|timestamp| DifficultyAndTimestamp { difficulty: U256::one(), timestamp: *timestamp * 1_000 }).collect::<Vec<_>>(),
+        ));
+        assert_eq!(DifficultyPallet::median_time_past(), Some(8_000));
+        DifficultyPallet::on_timestamp_set(8_001);
+    });
+}
+
+#[test]
+#[should_panic(expected = "Timestamp must be later than the median time past")]
+fn timestamp_at_the_median_time_past_is_rejected() {
+    new_test_ext().execute_with(|| {
+        let entries = [1_000, 2_000, 3_000];
+        PastDifficultiesAndTimestamps::<Test>::put(frame_support::BoundedVec::truncate_from(
+            entries.iter().map(|timestamp| DifficultyAndTimestamp { difficulty: U256::one(), timestamp: *timestamp }).collect::<Vec<_>>(),
+        ));
+        DifficultyPallet::on_timestamp_set(2_000);
+    });
+}
+
+#[test]
+fn retarget_saturates_at_numeric_bounds() {
+    new_test_ext().execute_with(|| {
+        // A full window of the largest difficulties sums past U256, and the largest target time
+        // overflows the window length
+        let entries = (0..DIFFICULTY_ADJUST_WINDOW as u64).map(|i| DifficultyAndTimestamp {
+            difficulty: U256::MAX,
+            timestamp: if i % 2 == 0 { 0 } else { u64::MAX },
+        });
+        PastDifficultiesAndTimestamps::<Test>::put(frame_support::BoundedVec::truncate_from(entries.collect::<Vec<_>>()));
+        CurrentDifficulty::<Test>::put(U256::MAX);
+        crate::TargetBlockTimeOverride::<Test>::put(u128::MAX);
+        DifficultyPallet::on_finalize(1);
+        assert_eq!(DifficultyPallet::difficulty(), U256::from(u128::MAX));
+
+        // Zero factors and target, which the calls refuse but storage can still hold, divide by
+        // nothing: the difficulty falls to the minimum
+        crate::TargetBlockTimeOverride::<Test>::put(0);
+        crate::DampFactorOverride::<Test>::put(0);
+        crate::ClampFactorOverride::<Test>::put(0);
+        DifficultyPallet::on_finalize(2);
+        assert_eq!(DifficultyPallet::difficulty(), U256::one());
+    });
+}
+
+#[test]
+fn hashrate_saturates_at_numeric_bounds() {
+    new_test_ext().execute_with(|| {
+        let entries = [(U256::MAX, 0), (U256::MAX, 1), (U256::MAX, 2)];
+        PastDifficultiesAndTimestamps::<Test>::put(frame_support::BoundedVec::truncate_from(
+            entries.iter().map(|(difficulty, timestamp)| DifficultyAndTimestamp { difficulty: *difficulty, timestamp: *timestamp }).collect::<Vec<_>>(),
+        ));
+        let estimate = DifficultyPallet::network_hashrate(60).unwrap();
+        assert_eq!(estimate.hashrate, U256::MAX / U256::from(2));
+        assert_eq!(estimate.blocks, 2);
+    });
 }
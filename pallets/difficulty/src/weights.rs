@@ -1,29 +1,7 @@
-
-//! Autogenerated weights for pallet_template
+//! Weights for pallet_difficulty
 //!
-//! THIS FILE WAS AUTO-GENERATED USING THE SUBSTRATE BENCHMARK CLI VERSION 4.0.0-dev
-//! DATE: 2023-04-06, STEPS: `50`, REPEAT: `20`, LOW RANGE: `[]`, HIGH RANGE: `[]`
-//! WORST CASE MAP SIZE: `1000000`
-//! HOSTNAME: `Alexs-MacBook-Pro-2.local`, CPU: `<UNKNOWN>`
-//! EXECUTION: Some(Wasm), WASM-EXECUTION: Compiled, CHAIN: Some("dev"), DB CACHE: 1024
-
-// Executed Command:
-// ../../target/release/node-template
-// benchmark
-// pallet
-// --chain
-// dev
-// --pallet
-// pallet_template
-// --extrinsic
-// *
-// --steps=50
-// --repeat=20
-// --wasm-execution=compiled
-// --output
-// pallets/template/src/weights.rs
-// --template
-// ../../.maintain/frame-weight-template.hbs
+//! PLACEHOLDER VALUES, NOT YET GENERATED ON REFERENCE HARDWARE.
+//! Regenerate with `./scripts/benchmark-weights.sh` (see the script for the exact command).
 
 #![cfg_attr(rustfmt, rustfmt_skip)]
 #![allow(unused_parens)]
@@ -32,59 +10,62 @@
 use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
 use core::marker::PhantomData;
 
-/// Weight functions needed for pallet_template.
+/// Weight functions needed for pallet_difficulty.
 pub trait WeightInfo {
-	fn do_something() -> Weight;
-	fn cause_error() -> Weight;
+	fn on_finalize() -> Weight;
+	fn set_target_block_time() -> Weight;
+	fn set_damp_factor() -> Weight;
+	fn set_clamp_factor() -> Weight;
 }
 
-/// Weights for pallet_template using the Substrate node and recommended hardware.
+/// Weights for pallet_difficulty using the Substrate node and recommended hardware.
 pub struct SubstrateWeight<T>(PhantomData<T>);
 impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
-	/// Storage: TemplateModule Something (r:0 w:1)
-	/// Proof: TemplateModule Something (max_values: Some(1), max_size: Some(4), added: 499, mode: MaxEncodedLen)
-	fn do_something() -> Weight {
-		// Proof Size summary in bytes:
-		//  Measured:  `0`
-		//  Estimated: `0`
-		// Minimum execution time: 8_000_000 picoseconds.
+	/// Storage: DifficultyPallet PastDifficultiesAndTimestamps (r:1 w:1)
+	/// Storage: DifficultyPallet CurrentDifficulty (r:1 w:1)
+	/// Storage: Timestamp Now (r:1 w:0)
+	/// Storage: DifficultyPallet TargetBlockTimeOverride (r:1 w:0)
+	/// Storage: DifficultyPallet DampFactorOverride (r:1 w:0)
+	/// Storage: DifficultyPallet ClampFactorOverride (r:1 w:0)
+	fn on_finalize() -> Weight {
+		Weight::from_parts(45_000_000, 4990)
+			.saturating_add(T::DbWeight::get().reads(6_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Storage: DifficultyPallet TargetBlockTimeOverride (r:0 w:1)
+	fn set_target_block_time() -> Weight {
+		Weight::from_parts(9_000_000, 0)
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: DifficultyPallet DampFactorOverride (r:0 w:1)
+	fn set_damp_factor() -> Weight {
 		Weight::from_parts(9_000_000, 0)
 			.saturating_add(T::DbWeight::get().writes(1_u64))
 	}
-	/// Storage: TemplateModule Something (r:1 w:1)
-	/// Proof: TemplateModule Something (max_values: Some(1), max_size: Some(4), added: 499, mode: MaxEncodedLen)
-	fn cause_error() -> Weight {
-		// Proof Size summary in bytes:
-		//  Measured:  `32`
-		//  Estimated: `1489`
-		// Minimum execution time: 6_000_000 picoseconds.
-		Weight::from_parts(6_000_000, 1489)
-			.saturating_add(T::DbWeight::get().reads(1_u64))
+	/// Storage: DifficultyPallet ClampFactorOverride (r:0 w:1)
+	fn set_clamp_factor() -> Weight {
+		Weight::from_parts(9_000_000, 0)
 			.saturating_add(T::DbWeight::get().writes(1_u64))
 	}
 }
 
 // For backwards compatibility and tests
 impl WeightInfo for () {
-	/// Storage: TemplateModule Something (r:0 w:1)
-	/// Proof: TemplateModule Something (max_values: Some(1), max_size: Some(4), added: 499, mode: MaxEncodedLen)
-	fn do_something() -> Weight {
-		// Proof Size summary in bytes:
-		//  Measured:  `0`
-		//  Estimated: `0`
-		// Minimum execution time: 8_000_000 picoseconds.
+	fn on_finalize() -> Weight {
+		Weight::from_parts(45_000_000, 4990)
+			.saturating_add(RocksDbWeight::get().reads(6_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	fn set_target_block_time() -> Weight {
+		Weight::from_parts(9_000_000, 0)
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	fn set_damp_factor() -> Weight {
 		Weight::from_parts(9_000_000, 0)
 			.saturating_add(RocksDbWeight::get().writes(1_u64))
 	}
-	/// Storage: TemplateModule Something (r:1 w:1)
-	/// Proof: TemplateModule Something (max_values: Some(1), max_size: Some(4), added: 499, mode: MaxEncodedLen)
-	fn cause_error() -> Weight {
-		// Proof Size summary in bytes:
-		//  Measured:  `32`
-		//  Estimated: `1489`
-		// Minimum execution time: 6_000_000 picoseconds.
-		Weight::from_parts(6_000_000, 1489)
-			.saturating_add(RocksDbWeight::get().reads(1_u64))
+	fn set_clamp_factor() -> Weight {
+		Weight::from_parts(9_000_000, 0)
 			.saturating_add(RocksDbWeight::get().writes(1_u64))
 	}
 }
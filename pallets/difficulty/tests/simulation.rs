@@ -0,0 +1,195 @@
+//! Deterministic simulation of the retarget under scripted hashrate curves.
+//!
+//! Each scenario mines thousands of blocks against a network whose hashrate follows a curve. The
+//! time to find a block is drawn from the exponential distribution of mean `difficulty /
+//! hashrate`, with a seeded generator so every run mines the same blocks, and the pallet
+//! retargets after each block with the runtime's parameters. The assertions bound how far block
+//! times stray from the target and how fast they come back, so a change to the retarget algorithm
+//! or its parameters that converges worse fails here:
+//!
+//! ```sh
+//! cargo test -p pallet-difficulty --test simulation
+//! ```
+
+use std::cell::Cell;
+
+use frame_support::{
+    derive_impl,
+    traits::{ConstU128, Hooks, OnTimestampSet, Time},
+    BoundedVec,
+};
+use pallet_difficulty::{CurrentDifficulty, DifficultyAndTimestamp, PastDifficultiesAndTimestamps};
+use sp_core::U256;
+use sp_runtime::BuildStorage;
+
+type Block = frame_system::mocking::MockBlock<Test>;
+
+frame_support::construct_runtime!(
+    pub enum Test
+    {
+        System: frame_system,
+        DifficultyPallet: pallet_difficulty,
+    }
+);
+
+#[derive_impl(frame_system::config_preludes::TestDefaultConfig)]
+impl frame_system::Config for Test {
+    type Block = Block;
+}
+
+/// Target block time of the runtime, in milliseconds.
+const TARGET: u64 = 6_000;
+
+// The runtime's retarget parameters
+impl pallet_difficulty::Config for Test {
+    type RuntimeEvent = RuntimeEvent;
+    type WeightInfo = ();
+    type TimeProvider = Clock;
+    type TargetBlockTime = ConstU128<{ TARGET as u128 }>;
+    type DampFactor = ConstU128<3>;
+    type ClampFactor = ConstU128<2>;
+    type MaxDifficulty = ConstU128<{ u128::MAX }>;
+    type MinDifficulty = ConstU128<1>;
+    type ParametersOrigin = frame_system::EnsureRoot<u64>;
+}
+
+thread_local! {
+    static NOW: Cell<u64> = const { Cell::new(0) };
+}
+
+/// The timestamp of the block being mined.
+pub struct Clock;
+
+impl Time for Clock {
+    type Moment = u64;
+
+    fn now() -> Self::Moment {
+        NOW.with(Cell::get)
+    }
+}
+
+/// Entries of the pallet's retarget window.
+const WINDOW: u64 = 60;
+
+/// Hashes per millisecond of the network before its hashrate changes.
+const BASE_HASHRATE: f64 = 1_000.0;
+
+/// Seeds each scenario is mined with.
+const SEEDS: [u64; 3] = [1, 2, 3];
+
+/// Xorshift generator of the times to find blocks.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A uniform draw in (0, 1].
+    fn unit(&mut self) -> f64 {
+        ((self.next() >> 11) + 1) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// The time each of `blocks` blocks took to find and the difficulty after each, the hashrate of
+/// block `n` being `hashrate(n)` times [`BASE_HASHRATE`]. The window starts full of blocks found
+/// on target at the base hashrate.
+fn mine(blocks: usize, seed: u64, hashrate: impl Fn(usize) -> f64) -> (Vec<u64>, Vec<U256>) {
+    let storage = frame_system::GenesisConfig::<Test>::default().build_storage().unwrap();
+    sp_io::TestExternalities::new(storage).execute_with(|| {
+        let difficulty = U256::from(BASE_HASHRATE as u64 * TARGET);
+        let window: Vec<_> = (0..WINDOW)
+            .map(|n| DifficultyAndTimestamp { difficulty, timestamp: n * TARGET })
+            .collect();
+        PastDifficultiesAndTimestamps::<Test>::put(BoundedVec::truncate_from(window));
+        CurrentDifficulty::<Test>::put(difficulty);
+
+        let mut rng = Rng(seed);
+        let mut last = (WINDOW - 1) * TARGET;
+        let mut times = Vec::with_capacity(blocks);
+        let mut difficulties = Vec::with_capacity(blocks);
+        for n in 0..blocks {
+            let difficulty = CurrentDifficulty::<Test>::get().low_u128() as f64;
+            let found = -rng.unit().ln() * difficulty / (BASE_HASHRATE * hashrate(n));
+            // Blocks found too quickly are timestamped just past the median time past
+            let median = DifficultyPallet::median_time_past().unwrap_or_default();
+            let timestamp = (last + found as u64).max(median + 1);
+            DifficultyPallet::on_timestamp_set(timestamp);
+            NOW.with(|now| now.set(timestamp));
+            DifficultyPallet::on_finalize(n as u64 + 1);
+
+            times.push(timestamp - last);
+            difficulties.push(CurrentDifficulty::<Test>::get());
+            last = timestamp;
+        }
+        (times, difficulties)
+    })
+}
+
+/// The mean of `times`, in target block times.
+fn mean(times: &[u64]) -> f64 {
+    times.iter().sum::<u64>() as f64 / times.len() as f64 / TARGET as f64
+}
+
+/// The means of consecutive runs of `len` of `times`, in target block times.
+fn windows(times: &[u64], len: usize) -> Vec<f64> {
+    times.chunks_exact(len).map(mean).collect()
+}
+
+#[test]
+fn steady_hashrate_holds_the_target() {
+    for seed in SEEDS {
+        let (times, _) = mine(4_000, seed, |_| 1.0);
+        // The window spans one interval fewer than its goal, so blocks come about 2% slow
+        let mean = mean(&times);
+        assert!((0.95..1.05).contains(&mean), "seed {seed}: mean block time {mean}");
+    }
+}
+
+#[test]
+fn tenfold_hashrate_step_converges() {
+    for seed in SEEDS {
+        let (times, difficulties) = mine(3_600, seed, |n| if n < 600 { 1.0 } else { 10.0 });
+
+        let equilibrium = U256::from(10 * BASE_HASHRATE as u64 * TARGET);
+        let converged = difficulties[600..].iter().position(|d| *d * 10 >= equilibrium * 9);
+        assert!(
+            converged.is_some_and(|blocks| blocks < 600),
+            "seed {seed}: difficulty reached 90% of the new equilibrium after {converged:?} blocks"
+        );
+        let settled = mean(&times[1_600..]);
+        assert!((0.95..1.07).contains(&settled), "seed {seed}: settled block time {settled}");
+    }
+}
+
+#[test]
+fn oscillating_pool_hashrate_averages_the_target() {
+    for seed in SEEDS {
+        // A pool of twice the rest of the network hops on and off every 30 blocks
+        let (times, _) = mine(4_200, seed, |n| if (n / 30) % 2 == 1 { 3.0 } else { 1.0 });
+
+        let mean = mean(&times[600..]);
+        assert!((0.95..1.05).contains(&mean), "seed {seed}: mean block time {mean}");
+        for window in windows(&times[600..], 300) {
+            assert!((0.8..1.25).contains(&window), "seed {seed}: block time {window} over 300");
+        }
+    }
+}
+
+#[test]
+fn gradual_hashrate_decline_is_tracked() {
+    for seed in SEEDS {
+        // 0.1% of the hashrate leaves every block, 95% over the run
+        let (times, _) = mine(3_000, seed, |n| 0.999f64.powi(n as i32));
+
+        // The retarget lags a decline, so blocks run slow, but not by much
+        let mean = mean(&times);
+        assert!((1.0..1.2).contains(&mean), "seed {seed}: mean block time {mean}");
+        for window in windows(&times, 300) {
+            assert!((0.9..1.3).contains(&window), "seed {seed}: block time {window} over 300");
+        }
+    }
+}
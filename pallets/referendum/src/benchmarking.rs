@@ -0,0 +1,122 @@
+//! Benchmarking setup for pallet-referendum
+#![cfg(feature = "runtime-benchmarks")]
+use super::*;
+
+use alloc::{boxed::Box, vec};
+use frame_benchmarking::v2::*;
+use frame_support::{
+	traits::{Get, Hooks},
+	BoundedVec,
+};
+use frame_system::pallet_prelude::BlockNumberFor;
+use sp_core::crypto::KeyTypeId;
+use utxo::TransactionOutput;
+
+const KEY_TYPE: KeyTypeId = KeyTypeId(*b"bnch");
+
+/// Open referendum 0 on a remark, ending at `end`.
+fn open<T: Config>(end: BlockNumberFor<T>) {
+	let call: <T as Config>::RuntimeCall = frame_system::Call::<T>::remark { remark: vec![] }.into();
+	let call = BoundedVec::try_from(call.encode()).expect("a remark fits MaxCallSize");
+	Referendums::<T>::insert(0, ReferendumInfo { call, end, ayes: 0, nays: 0, votes: 0 });
+	Ending::<T>::insert(end, BoundedVec::truncate_from(vec![0]));
+}
+
+#[benchmarks]
+mod benchmarks {
+	use super::*;
+
+	#[benchmark]
+	fn on_initialize_idle() {
+		let n: BlockNumberFor<T> = 1u32.into();
+
+		#[block]
+		{
+			Pallet::<T>::on_initialize(n);
+		}
+	}
+
+	#[benchmark]
+	fn close() {
+		let end: BlockNumberFor<T> = 1u32.into();
+		open::<T>(end);
+		Referendums::<T>::mutate(0, |info| {
+			if let Some(info) = info {
+				info.votes = 1;
+			}
+		});
+
+		#[block]
+		{
+			Pallet::<T>::close(0);
+		}
+
+		assert_eq!(Referendums::<T>::get(0), None);
+		assert!(Clearing::<T>::contains_key(0));
+	}
+
+	#[benchmark]
+	fn clear_votes(v: Linear<0, { T::MaxVotesCleared::get() }>) {
+		Clearing::<T>::insert(0, ());
+		for i in 0..v {
+			Votes::<T>::insert(0, H256::from_low_u64_be(i.into()), true);
+		}
+
+		#[block]
+		{
+			Pallet::<T>::clear_votes(T::MaxVotesCleared::get());
+		}
+
+		assert_eq!(Votes::<T>::iter_prefix(0).count(), 0);
+	}
+
+	#[benchmark]
+	fn propose() -> Result<(), BenchmarkError> {
+		let origin = T::ProposeOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+		let call: <T as Config>::RuntimeCall = frame_system::Call::<T>::remark { remark: vec![] }.into();
+
+		#[extrinsic_call]
+		_(origin as <T as frame_system::Config>::RuntimeOrigin, Box::new(call));
+
+		assert!(Referendums::<T>::contains_key(0));
+		Ok(())
+	}
+
+	#[benchmark]
+	fn vote() {
+		let end: BlockNumberFor<T> = 1u32.into();
+		open::<T>(end);
+
+		let voter = sp_io::crypto::sr25519_generate(KEY_TYPE, None);
+		let voter = H256(voter.0);
+		let outpoint = H256::repeat_byte(1);
+		utxo::UtxoStore::<T>::insert(
+			outpoint,
+			TransactionOutput {
+				value: T::MinVoteStake::get(),
+				pubkey: escrow_pubkey(&voter, 1),
+				memo: None,
+			},
+		);
+		let signature = sp_io::crypto::sr25519_sign(
+			KEY_TYPE,
+			&Public::from_h256(voter),
+			&vote_payload(0, &outpoint, true),
+		)
+		.expect("the key was generated in the keystore");
+		let vote = Vote {
+			outpoint,
+			voter,
+			unlock_height: 1,
+			aye: true,
+			signature: H512::from_slice(signature.as_ref()),
+		};
+
+		#[extrinsic_call]
+		_(frame_system::RawOrigin::None, 0, vote);
+
+		assert!(Votes::<T>::contains_key(0, outpoint));
+	}
+
+	impl_benchmark_test_suite!(Pallet, crate::mock::new_test_ext(), crate::mock::Test);
+}
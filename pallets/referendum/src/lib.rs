@@ -0,0 +1,442 @@
+//! # Referendum Pallet
+//!
+//! Stake-weighted referendums on a chain without accounts to vote from.
+//!
+//! ## Overview
+//!
+//! Coin holders vote with unspent outputs of the UTXO pallet locked to a vote-escrow script
+//! ([`escrow_script`]): the output pays the voter key, which can only spend it from a chosen
+//! `unlock_height` on. Since the stake of a vote has to stay locked until the referendum ends,
+//! it cannot be moved to another output and counted a second time.
+//!
+//! [`Config::ProposeOrigin`] submits a runtime call with [`Pallet::propose`], opening a
+//! referendum of [`Config::VotingPeriod`] blocks. A vote is an unsigned extrinsic carrying the
+//! voter key's signature of [`vote_payload`], so voting needs neither an account nor a fee. It
+//! adds the value of the escrowed output to the ayes or the nays. When the period is over, the
+//! call is dispatched if the ayes outweigh the nays and reach [`Config::MinApproval`].
+//!
+//! Passed calls are dispatched with the [`RawOrigin::Passed`] origin rather than as root, so a
+//! vote only reaches the operations the runtime lets [`EnsureReferendum`] perform. Calls heavier
+//! than [`Config::MaxCallWeight`] cannot be proposed, and are not dispatched if they have grown
+//! heavier by the time the referendum ends, since they run in `on_initialize`.
+//!
+//! The number of votes is not capped, since whoever filled the slots first would shut everyone
+//! else out. Instead each vote has to stake at least [`Config::MinVoteStake`], which bounds the
+//! votes of a referendum by the money supply, and the votes of a closed referendum are removed
+//! over the following blocks, [`Config::MaxVotesCleared`] at a time.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use pallet::*;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+pub mod weights;
+pub use weights::*;
+
+extern crate alloc;
+
+use alloc::{boxed::Box, vec::Vec};
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+use sp_core::{
+	sr25519::{Public, Signature},
+	H256, H512,
+};
+use frame_support::traits::EnsureOrigin;
+use sp_runtime::{traits::SaturatedConversion, RuntimeDebug};
+use utxo::{
+	script::{self, ScriptBuilder},
+	Value,
+};
+
+/// Index of a referendum.
+pub type ReferendumIndex = u32;
+
+/// Domain separator of vote signatures.
+pub const VOTE_CONTEXT: &[u8] = b"tumuchain/vote";
+
+/// The script locking a vote stake: `voter` can spend it from block `unlock_height` on.
+pub fn escrow_script(voter: &H256, unlock_height: u64) -> Vec<u8> {
+	ScriptBuilder::new()
+		.push_int(unlock_height)
+		.push_opcode(script::OP_CHECKLOCKTIMEVERIFY)
+		.push_opcode(script::OP_DROP)
+		.push_key(voter)
+		.push_opcode(script::OP_CHECKSIG)
+		.into_script()
+}
+
+/// The `pubkey` of outputs escrowed by [`escrow_script`].
+pub fn escrow_pubkey(voter: &H256, unlock_height: u64) -> H256 {
+	script::script_hash(&escrow_script(voter, unlock_height))
+}
+
+/// The message a voter signs to vote `aye` or nay on `index` with the stake at `outpoint`.
+pub fn vote_payload(index: ReferendumIndex, outpoint: &H256, aye: bool) -> Vec<u8> {
+	(VOTE_CONTEXT, index, outpoint, aye).encode()
+}
+
+/// A vote cast with an escrowed output.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo, RuntimeDebug)]
+pub struct Vote {
+	/// The escrowed output
+	pub outpoint: H256,
+	/// Key the output is escrowed to
+	pub voter: H256,
+	/// Height the output is escrowed until
+	pub unlock_height: u64,
+	/// For or against the proposal
+	pub aye: bool,
+	/// The voter key's signature of [`vote_payload`]
+	pub signature: H512,
+}
+
+/// A referendum in progress.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo, RuntimeDebug)]
+pub struct ReferendumInfo<BlockNumber, Call> {
+	/// The encoded call to dispatch if the referendum passes
+	pub call: Call,
+	/// Block at which voting ends and the result is applied
+	pub end: BlockNumber,
+	/// Stake voting for
+	pub ayes: Value,
+	/// Stake voting against
+	pub nays: Value,
+	/// Number of votes cast
+	pub votes: u32,
+}
+
+/// Ensures the origin of a call dispatched by a passed referendum, yielding its index.
+pub struct EnsureReferendum;
+
+impl<O: Into<Result<RawOrigin, O>> + From<RawOrigin>> EnsureOrigin<O> for EnsureReferendum {
+	type Success = ReferendumIndex;
+
+	fn try_origin(o: O) -> Result<Self::Success, O> {
+		o.into().map(|RawOrigin::Passed(index)| index)
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn try_successful_origin() -> Result<O, ()> {
+		Ok(O::from(RawOrigin::Passed(0)))
+	}
+}
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+	use frame_support::{
+		dispatch::{GetDispatchInfo, PostDispatchInfo},
+		pallet_prelude::*,
+	};
+	use frame_system::pallet_prelude::*;
+	use sp_runtime::traits::{Dispatchable, Saturating};
+
+	#[pallet::pallet]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config + utxo::Config {
+		/// The overarching runtime event type.
+		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+		/// The overarching runtime origin, which passed calls are dispatched with.
+		type RuntimeOrigin: From<RawOrigin>;
+
+		/// The runtime call proposals dispatch.
+		type RuntimeCall: Parameter
+			+ Dispatchable<
+				RuntimeOrigin = <Self as Config>::RuntimeOrigin,
+				PostInfo = PostDispatchInfo,
+			> + GetDispatchInfo
+			+ From<frame_system::Call<Self>>;
+
+		/// A type representing the weights required by the dispatchables of this pallet.
+		type WeightInfo: WeightInfo;
+
+		/// Origin allowed to put a call to the vote.
+		type ProposeOrigin: EnsureOrigin<<Self as frame_system::Config>::RuntimeOrigin>;
+
+		/// Number of blocks a referendum lasts.
+		#[pallet::constant]
+		type VotingPeriod: Get<BlockNumberFor<Self>>;
+
+		/// Least stake voting for a proposal for it to pass.
+		#[pallet::constant]
+		type MinApproval: Get<Value>;
+
+		/// Largest encoded call that can be proposed, in bytes.
+		#[pallet::constant]
+		type MaxCallSize: Get<u32>;
+
+		/// Largest weight of a call that can be proposed. Up to [`Config::MaxProposalsPerBlock`]
+		/// such calls are dispatched in the `on_initialize` of one block.
+		#[pallet::constant]
+		type MaxCallWeight: Get<Weight>;
+
+		/// Largest number of referendums opened in one block, and so ending in one block.
+		#[pallet::constant]
+		type MaxProposalsPerBlock: Get<u32>;
+
+		/// Least stake a vote can carry.
+		#[pallet::constant]
+		type MinVoteStake: Get<Value>;
+
+		/// Largest number of votes of closed referendums removed in one block.
+		#[pallet::constant]
+		type MaxVotesCleared: Get<u32>;
+
+		/// Priority of vote transactions in the pool.
+		#[pallet::constant]
+		type UnsignedPriority: Get<TransactionPriority>;
+	}
+
+	/// The origin of calls dispatched by a passed referendum.
+	#[derive(Clone, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo, RuntimeDebug)]
+	pub enum RawOrigin {
+		/// Referendum of this index passed
+		Passed(ReferendumIndex),
+	}
+
+	#[pallet::origin]
+	pub type Origin = RawOrigin;
+
+	/// Number of referendums opened so far, and so the index of the next one.
+	#[pallet::storage]
+	pub type ReferendumCount<T: Config> = StorageValue<_, ReferendumIndex, ValueQuery>;
+
+	/// Referendums in progress.
+	#[pallet::storage]
+	pub type Referendums<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		ReferendumIndex,
+		ReferendumInfo<BlockNumberFor<T>, BoundedVec<u8, <T as Config>::MaxCallSize>>,
+		OptionQuery,
+	>;
+
+	/// The referendums ending at a block.
+	#[pallet::storage]
+	pub type Ending<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		BlockNumberFor<T>,
+		BoundedVec<ReferendumIndex, T::MaxProposalsPerBlock>,
+		ValueQuery,
+	>;
+
+	/// The stakes that voted in each referendum in progress, with the side they took.
+	#[pallet::storage]
+	pub type Votes<T: Config> =
+		StorageDoubleMap<_, Twox64Concat, ReferendumIndex, Identity, H256, bool, OptionQuery>;
+
+	/// Closed referendums whose [`Votes`] are still being removed.
+	#[pallet::storage]
+	pub type Clearing<T: Config> = StorageMap<_, Twox64Concat, ReferendumIndex, (), OptionQuery>;
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// A referendum was opened.
+		Proposed { index: ReferendumIndex, end: BlockNumberFor<T> },
+		/// A stake voted.
+		Voted { index: ReferendumIndex, outpoint: H256, aye: bool, stake: Value },
+		/// A referendum passed and its call was dispatched.
+		Executed { index: ReferendumIndex, result: DispatchResult },
+		/// A referendum did not pass.
+		Rejected { index: ReferendumIndex, ayes: Value, nays: Value },
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The encoded call exceeds [`Config::MaxCallSize`]
+		CallTooLarge,
+		/// The weight of the call exceeds [`Config::MaxCallWeight`]
+		CallTooHeavy,
+		/// [`Config::MaxProposalsPerBlock`] referendums were already opened in this block
+		TooManyProposals,
+		/// No referendum with this index is in progress
+		UnknownReferendum,
+		/// The stake is below [`Config::MinVoteStake`]
+		StakeTooSmall,
+		/// The stake already voted in this referendum
+		AlreadyVoted,
+		/// The stake is not an unspent output
+		UnknownStake,
+		/// The stake is not escrowed to the voter until the given height
+		NotEscrowed,
+		/// The stake unlocks before the referendum ends
+		UnlocksTooEarly,
+		/// The signature is not the voter key's signature of the vote
+		InvalidSignature,
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(n: BlockNumberFor<T>) -> Weight {
+			Ending::<T>::take(n)
+				.into_iter()
+				.fold(<T as Config>::WeightInfo::on_initialize_idle(), |weight, index| {
+					weight.saturating_add(Self::close(index))
+				})
+				.saturating_add(Self::clear_votes(T::MaxVotesCleared::get()))
+		}
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Open a referendum on dispatching `call` with the [`RawOrigin::Passed`] origin.
+		#[pallet::call_index(0)]
+		#[pallet::weight(<T as Config>::WeightInfo::propose())]
+		pub fn propose(origin: OriginFor<T>, call: Box<<T as Config>::RuntimeCall>) -> DispatchResult {
+			T::ProposeOrigin::ensure_origin(origin)?;
+			ensure!(
+				!call.get_dispatch_info().weight.any_gt(T::MaxCallWeight::get()),
+				Error::<T>::CallTooHeavy
+			);
+			let call: BoundedVec<u8, <T as Config>::MaxCallSize> =
+				call.encode().try_into().map_err(|_| Error::<T>::CallTooLarge)?;
+
+			let index = ReferendumCount::<T>::get();
+			let end = frame_system::Pallet::<T>::block_number().saturating_add(T::VotingPeriod::get());
+			Ending::<T>::try_append(end, index).map_err(|_| Error::<T>::TooManyProposals)?;
+			Referendums::<T>::insert(index, ReferendumInfo { call, end, ayes: 0, nays: 0, votes: 0 });
+			ReferendumCount::<T>::put(index.wrapping_add(1));
+			Self::deposit_event(Event::Proposed { index, end });
+			Ok(())
+		}
+
+		/// Add the stake of `vote` to the side it takes in referendum `index`.
+		#[pallet::call_index(1)]
+		#[pallet::weight(<T as Config>::WeightInfo::vote())]
+		pub fn vote(origin: OriginFor<T>, index: ReferendumIndex, vote: Vote) -> DispatchResult {
+			ensure_none(origin)?;
+			let stake = Self::check_vote(index, &vote)?;
+
+			Referendums::<T>::mutate(index, |info| {
+				if let Some(info) = info {
+					let side = if vote.aye { &mut info.ayes } else { &mut info.nays };
+					*side = side.saturating_add(stake);
+					info.votes.saturating_inc();
+				}
+			});
+			Votes::<T>::insert(index, vote.outpoint, vote.aye);
+			Self::deposit_event(Event::Voted { index, outpoint: vote.outpoint, aye: vote.aye, stake });
+			Ok(())
+		}
+	}
+
+	#[pallet::validate_unsigned]
+	impl<T: Config> ValidateUnsigned for Pallet<T> {
+		type Call = Call<T>;
+
+		fn validate_unsigned(_source: TransactionSource, call: &Self::Call) -> TransactionValidity {
+			let Call::vote { index, vote } = call else { return InvalidTransaction::Call.into() };
+			Self::check_vote(*index, vote).map_err(|error| match error {
+				Error::<T>::UnknownReferendum | Error::<T>::AlreadyVoted => InvalidTransaction::Stale,
+				_ => InvalidTransaction::BadProof,
+			})?;
+			let end = Referendums::<T>::get(index).map(|info| info.end).unwrap_or_default();
+			let longevity = end.saturating_sub(frame_system::Pallet::<T>::block_number());
+
+			ValidTransaction::with_tag_prefix("Referendum")
+				.priority(T::UnsignedPriority::get())
+				.and_provides((index, vote.outpoint))
+				.longevity(longevity.saturated_into::<u64>().max(1))
+				.propagate(true)
+				.build()
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// The stake of `vote` if it can be counted in referendum `index`.
+		pub fn check_vote(index: ReferendumIndex, vote: &Vote) -> Result<Value, Error<T>> {
+			let info = Referendums::<T>::get(index).ok_or(Error::<T>::UnknownReferendum)?;
+			ensure!(!Votes::<T>::contains_key(index, vote.outpoint), Error::<T>::AlreadyVoted);
+			// Votes can be cast until block `end - 1`, and the stake is spendable from
+			// `unlock_height` on
+			ensure!(
+				vote.unlock_height >= info.end.saturated_into::<u64>(),
+				Error::<T>::UnlocksTooEarly
+			);
+
+			let stake = utxo::UtxoStore::<T>::get(vote.outpoint).ok_or(Error::<T>::UnknownStake)?;
+			ensure!(
+				stake.pubkey == escrow_pubkey(&vote.voter, vote.unlock_height),
+				Error::<T>::NotEscrowed
+			);
+			ensure!(stake.value >= T::MinVoteStake::get(), Error::<T>::StakeTooSmall);
+			ensure!(
+				sp_io::crypto::sr25519_verify(
+					&Signature::from_raw(*vote.signature.as_fixed_bytes()),
+					&vote_payload(index, &vote.outpoint, vote.aye),
+					&Public::from_h256(vote.voter),
+				),
+				Error::<T>::InvalidSignature
+			);
+			Ok(stake.value)
+		}
+
+		/// Count the votes of referendum `index`, dispatching its call if it passed.
+		pub(crate) fn close(index: ReferendumIndex) -> Weight {
+			let Some(info) = Referendums::<T>::take(index) else { return Weight::zero() };
+			// The votes can no longer be added to, and are removed in later blocks
+			if info.votes > 0 {
+				Clearing::<T>::insert(index, ());
+			}
+			let weight = <T as Config>::WeightInfo::close();
+
+			if info.ayes <= info.nays || info.ayes < T::MinApproval::get() {
+				Self::deposit_event(Event::Rejected { index, ayes: info.ayes, nays: info.nays });
+				return weight
+			}
+
+			// The call was encoded from a decoded call when it was proposed
+			let Ok(call) = <T as Config>::RuntimeCall::decode(&mut &info.call[..]) else {
+				Self::deposit_event(Event::Executed { index, result: Err(DispatchError::Corruption) });
+				return weight
+			};
+			let call_weight = call.get_dispatch_info().weight;
+			// The weight of a call may have grown with a runtime upgrade since it was proposed
+			if call_weight.any_gt(T::MaxCallWeight::get()) {
+				let result = Err(Error::<T>::CallTooHeavy.into());
+				Self::deposit_event(Event::Executed { index, result });
+				return weight
+			}
+			let result = call.dispatch(RawOrigin::Passed(index).into());
+			let actual_weight = match &result {
+				Ok(post_info) => post_info.actual_weight,
+				Err(error) => error.post_info.actual_weight,
+			};
+			Self::deposit_event(Event::Executed {
+				index,
+				result: result.map(|_| ()).map_err(|error| error.error),
+			});
+			weight.saturating_add(actual_weight.unwrap_or(call_weight))
+		}
+
+		/// Remove up to `limit` votes of closed referendums, returning the weight used.
+		pub(crate) fn clear_votes(limit: u32) -> Weight {
+			let mut left = limit;
+			let clearing: Vec<_> = Clearing::<T>::iter_keys().take(limit as usize).collect();
+			for index in clearing {
+				if left == 0 {
+					break
+				}
+				let removed = Votes::<T>::drain_prefix(index).take(left as usize).count() as u32;
+				left = left.saturating_sub(removed);
+				if !Votes::<T>::contains_prefix(index) {
+					Clearing::<T>::remove(index);
+				}
+			}
+			<T as Config>::WeightInfo::clear_votes(limit.saturating_sub(left))
+		}
+	}
+}
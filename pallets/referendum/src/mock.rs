@@ -0,0 +1,138 @@
+use crate as pallet_referendum;
+use frame_support::{derive_impl, parameter_types, weights::Weight};
+use sp_core::{sr25519, Pair, H256};
+use sp_runtime::{
+	traits::{BlakeTwo256, Hash},
+	BuildStorage, Perbill,
+};
+use utxo::{TransactionOutput, Value};
+
+type Block = frame_system::mocking::MockBlock<Test>;
+
+frame_support::construct_runtime!(
+	pub enum Test
+	{
+		System: frame_system,
+		Utxo: utxo,
+		Referendum: pallet_referendum,
+	}
+);
+
+#[derive_impl(frame_system::config_preludes::TestDefaultConfig)]
+impl frame_system::Config for Test {
+	type Block = Block;
+}
+
+pub struct NoAuthor;
+impl utxo::BlockAuthor for NoAuthor {
+	fn block_author() -> Option<sr25519::Public> {
+		None
+	}
+}
+
+parameter_types! {
+	pub const MaxMoney: Value = 21_000_000;
+	pub const ConsolidationDiscount: Perbill = Perbill::from_percent(50);
+	pub const UncleReward: Perbill = Perbill::from_percent(50);
+}
+
+impl utxo::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type BlockAuthor = NoAuthor;
+	type Issuance = ();
+	type WeightInfo = ();
+	type MaxTransactionSize = frame_support::traits::ConstU32<100>;
+	type MaxMoney = MaxMoney;
+	// A privileged call referendums may make
+	type MintOrigin = pallet_referendum::EnsureReferendum;
+	type MaxScriptSize = frame_support::traits::ConstU32<1_000>;
+	type MaxScriptOps = frame_support::traits::ConstU32<20>;
+	type MaxStackDepth = frame_support::traits::ConstU32<16>;
+	type ConsolidationDiscount = ConsolidationDiscount;
+	type RewardSmoothing = frame_support::traits::ConstU32<0>;
+	type UncleAuthor = ();
+	type UncleReward = UncleReward;
+	type MaxUncleDepth = frame_support::traits::ConstU32<3>;
+	type MaxUncles = frame_support::traits::ConstU32<2>;
+	type CanonicalOrderingFrom = frame_support::traits::ConstU64<{ u64::MAX }>;
+}
+
+parameter_types! {
+	pub const VotingPeriod: u64 = 10;
+	pub const MinApproval: Value = 100;
+	pub const MaxCallSize: u32 = 128;
+	pub static MaxCallWeight: Weight = Weight::MAX;
+	pub const MaxProposalsPerBlock: u32 = 2;
+	pub const MinVoteStake: Value = 10;
+	pub const MaxVotesCleared: u32 = 3;
+}
+
+impl pallet_referendum::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type RuntimeOrigin = RuntimeOrigin;
+	type RuntimeCall = RuntimeCall;
+	type WeightInfo = ();
+	type ProposeOrigin = frame_system::EnsureSigned<u64>;
+	type VotingPeriod = VotingPeriod;
+	type MinApproval = MinApproval;
+	type MaxCallSize = MaxCallSize;
+	type MaxCallWeight = MaxCallWeight;
+	type MaxProposalsPerBlock = MaxProposalsPerBlock;
+	type MinVoteStake = MinVoteStake;
+	type MaxVotesCleared = MaxVotesCleared;
+	type UnsignedPriority = frame_support::traits::ConstU64<{ u64::MAX / 2 }>;
+}
+
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	let mut ext: sp_io::TestExternalities =
+		frame_system::GenesisConfig::<Test>::default().build_storage().unwrap().into();
+	// Benchmarks sign with keys generated through the keystore host functions
+	ext.register_extension(sp_keystore::KeystoreExt::new(
+		sp_keystore::testing::MemoryKeystore::new(),
+	));
+	ext
+}
+
+/// Escrow an output of `value` to `voter` until `unlock_height`, returning its outpoint.
+pub fn escrow(voter: &sr25519::Pair, value: Value, unlock_height: u64) -> H256 {
+	let voter = H256(voter.public().0);
+	let outpoint = BlakeTwo256::hash_of(&(voter, value, unlock_height));
+	utxo::UtxoStore::<Test>::insert(
+		outpoint,
+		TransactionOutput {
+			value,
+			pubkey: crate::escrow_pubkey(&voter, unlock_height),
+			memo: None,
+		},
+	);
+	outpoint
+}
+
+/// The vote of `voter` with the stake at `outpoint`, escrowed until `unlock_height`.
+pub fn vote(
+	voter: &sr25519::Pair,
+	index: crate::ReferendumIndex,
+	outpoint: H256,
+	unlock_height: u64,
+	aye: bool,
+) -> crate::Vote {
+	let signature = voter.sign(&crate::vote_payload(index, &outpoint, aye));
+	crate::Vote {
+		outpoint,
+		voter: H256(voter.public().0),
+		unlock_height,
+		aye,
+		signature: sp_core::H512::from_slice(signature.as_ref()),
+	}
+}
+
+/// Run blocks `from..=to`.
+pub fn run_blocks(from: u64, to: u64) {
+	use frame_support::traits::Hooks;
+
+	for n in from..=to {
+		System::reset_events();
+		System::set_block_number(n);
+		Referendum::on_initialize(n);
+	}
+}
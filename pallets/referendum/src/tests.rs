@@ -0,0 +1,270 @@
+use crate::{mock::*, Clearing, Error, Event, Referendums, Votes};
+use frame_support::{assert_noop, assert_ok, dispatch::GetDispatchInfo, pallet_prelude::*};
+use sp_core::{sr25519, Pair, H256};
+use sp_runtime::DispatchError;
+use utxo::TransactionOutput;
+
+fn set_storage() -> Box<RuntimeCall> {
+	Box::new(RuntimeCall::System(frame_system::Call::set_storage {
+		items: vec![(b"key".to_vec(), b"value".to_vec())],
+	}))
+}
+
+/// A mint of `value`, which the mock lets referendums make.
+fn mint(value: u128) -> Box<RuntimeCall> {
+	let output = TransactionOutput { value, pubkey: H256::repeat_byte(1), memo: None };
+	Box::new(RuntimeCall::Utxo(utxo::Call::mint { outputs: BoundedVec::truncate_from(vec![output]) }))
+}
+
+fn voter(seed: &str) -> sr25519::Pair {
+	sr25519::Pair::from_string(&format!("//{}", seed), None).unwrap()
+}
+
+#[test]
+fn passed_referendum_dispatches_with_its_origin() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(Referendum::propose(RuntimeOrigin::signed(1), mint(50)));
+		System::assert_last_event(Event::Proposed { index: 0, end: 11 }.into());
+
+		let (alice, bob) = (voter("Alice"), voter("Bob"));
+		let aye = escrow(&alice, 150, 11);
+		let nay = escrow(&bob, 40, 20);
+		assert_ok!(Referendum::vote(RuntimeOrigin::none(), 0, vote(&alice, 0, aye, 11, true)));
+		assert_ok!(Referendum::vote(RuntimeOrigin::none(), 0, vote(&bob, 0, nay, 20, false)));
+		System::assert_last_event(Event::Voted { index: 0, outpoint: nay, aye: false, stake: 40 }.into());
+		assert_eq!(Referendums::<Test>::get(0).map(|info| (info.ayes, info.nays)), Some((150, 40)));
+
+		run_blocks(2, 10);
+		assert_eq!(utxo::UtxoTotalValue::<Test>::get(), 0);
+
+		run_blocks(11, 11);
+		System::assert_last_event(Event::Executed { index: 0, result: Ok(()) }.into());
+		assert_eq!(utxo::UtxoTotalValue::<Test>::get(), 50);
+		assert_eq!(Referendums::<Test>::get(0), None);
+		assert_eq!(Votes::<Test>::iter_prefix(0).count(), 0);
+	});
+}
+
+#[test]
+fn referendum_needs_majority_and_min_approval() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let (alice, bob) = (voter("Alice"), voter("Bob"));
+
+		// Outvoted
+		assert_ok!(Referendum::propose(RuntimeOrigin::signed(1), set_storage()));
+		let aye = escrow(&alice, 150, 11);
+		let nay = escrow(&bob, 150, 11);
+		assert_ok!(Referendum::vote(RuntimeOrigin::none(), 0, vote(&alice, 0, aye, 11, true)));
+		assert_ok!(Referendum::vote(RuntimeOrigin::none(), 0, vote(&bob, 0, nay, 11, false)));
+
+		// Unopposed, but below MinApproval
+		assert_ok!(Referendum::propose(RuntimeOrigin::signed(1), set_storage()));
+		let aye = escrow(&alice, 99, 11);
+		assert_ok!(Referendum::vote(RuntimeOrigin::none(), 1, vote(&alice, 1, aye, 11, true)));
+
+		run_blocks(2, 11);
+		System::assert_has_event(Event::Rejected { index: 0, ayes: 150, nays: 150 }.into());
+		System::assert_has_event(Event::Rejected { index: 1, ayes: 99, nays: 0 }.into());
+		assert_eq!(sp_io::storage::get(b"key"), None);
+	});
+}
+
+#[test]
+fn failed_dispatch_is_reported() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let call = Box::new(RuntimeCall::System(frame_system::Call::remark_with_event {
+			remark: vec![1],
+		}));
+		assert_ok!(Referendum::propose(RuntimeOrigin::signed(1), call));
+		let alice = voter("Alice");
+		let aye = escrow(&alice, 100, 11);
+		assert_ok!(Referendum::vote(RuntimeOrigin::none(), 0, vote(&alice, 0, aye, 11, true)));
+
+		run_blocks(2, 11);
+		System::assert_last_event(
+			Event::Executed { index: 0, result: Err(DispatchError::BadOrigin) }.into(),
+		);
+	});
+}
+
+#[test]
+fn passed_referendums_are_not_root() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(Referendum::propose(RuntimeOrigin::signed(1), set_storage()));
+		let alice = voter("Alice");
+		let aye = escrow(&alice, 1_000, 11);
+		assert_ok!(Referendum::vote(RuntimeOrigin::none(), 0, vote(&alice, 0, aye, 11, true)));
+
+		run_blocks(2, 11);
+		System::assert_last_event(
+			Event::Executed { index: 0, result: Err(DispatchError::BadOrigin) }.into(),
+		);
+		assert_eq!(sp_io::storage::get(b"key"), None);
+	});
+}
+
+#[test]
+fn heavy_calls_are_not_dispatched() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let limit = mint(50).get_dispatch_info().weight;
+		MaxCallWeight::set(&limit.saturating_sub(Weight::from_parts(1, 0)));
+		assert_noop!(
+			Referendum::propose(RuntimeOrigin::signed(1), mint(50)),
+			Error::<Test>::CallTooHeavy
+		);
+
+		// Nor when their weight grew past the limit while they were voted on
+		MaxCallWeight::set(&limit);
+		assert_ok!(Referendum::propose(RuntimeOrigin::signed(1), mint(50)));
+		let alice = voter("Alice");
+		let aye = escrow(&alice, 1_000, 11);
+		assert_ok!(Referendum::vote(RuntimeOrigin::none(), 0, vote(&alice, 0, aye, 11, true)));
+		MaxCallWeight::set(&limit.saturating_sub(Weight::from_parts(1, 0)));
+
+		run_blocks(2, 11);
+		let result = Err(Error::<Test>::CallTooHeavy.into());
+		System::assert_last_event(Event::Executed { index: 0, result }.into());
+		assert_eq!(utxo::UtxoTotalValue::<Test>::get(), 0);
+	});
+}
+
+#[test]
+fn proposals_are_bounded() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_noop!(Referendum::propose(RuntimeOrigin::root(), set_storage()), DispatchError::BadOrigin);
+		let large = Box::new(RuntimeCall::System(frame_system::Call::remark { remark: vec![0; 200] }));
+		assert_noop!(Referendum::propose(RuntimeOrigin::signed(1), large), Error::<Test>::CallTooLarge);
+
+		assert_ok!(Referendum::propose(RuntimeOrigin::signed(1), set_storage()));
+		assert_ok!(Referendum::propose(RuntimeOrigin::signed(1), set_storage()));
+		assert_noop!(
+			Referendum::propose(RuntimeOrigin::signed(1), set_storage()),
+			Error::<Test>::TooManyProposals
+		);
+
+		System::set_block_number(2);
+		assert_ok!(Referendum::propose(RuntimeOrigin::signed(1), set_storage()));
+		System::assert_last_event(Event::Proposed { index: 2, end: 12 }.into());
+	});
+}
+
+#[test]
+fn votes_need_escrowed_unspent_stake() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(Referendum::propose(RuntimeOrigin::signed(1), set_storage()));
+		let (alice, bob) = (voter("Alice"), voter("Bob"));
+
+		let early = escrow(&alice, 100, 10);
+		assert_noop!(
+			Referendum::vote(RuntimeOrigin::none(), 0, vote(&alice, 0, early, 10, true)),
+			Error::<Test>::UnlocksTooEarly
+		);
+
+		let stake = escrow(&alice, 100, 11);
+		assert_noop!(
+			Referendum::vote(RuntimeOrigin::signed(1), 0, vote(&alice, 0, stake, 11, true)),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			Referendum::vote(RuntimeOrigin::none(), 1, vote(&alice, 1, stake, 11, true)),
+			Error::<Test>::UnknownReferendum
+		);
+		// Claiming a later unlock height, or another key, than the output is escrowed with
+		assert_noop!(
+			Referendum::vote(RuntimeOrigin::none(), 0, vote(&alice, 0, stake, 12, true)),
+			Error::<Test>::NotEscrowed
+		);
+		assert_noop!(
+			Referendum::vote(RuntimeOrigin::none(), 0, vote(&bob, 0, stake, 11, true)),
+			Error::<Test>::NotEscrowed
+		);
+		// A signature of the opposite vote
+		let mut forged = vote(&alice, 0, stake, 11, true);
+		forged.aye = false;
+		assert_noop!(
+			Referendum::vote(RuntimeOrigin::none(), 0, forged),
+			Error::<Test>::InvalidSignature
+		);
+		let spent = escrow(&alice, 100, 12);
+		utxo::UtxoStore::<Test>::remove(spent);
+		assert_noop!(
+			Referendum::vote(RuntimeOrigin::none(), 0, vote(&alice, 0, spent, 12, true)),
+			Error::<Test>::UnknownStake
+		);
+
+		assert_ok!(Referendum::vote(RuntimeOrigin::none(), 0, vote(&alice, 0, stake, 11, true)));
+		assert_noop!(
+			Referendum::vote(RuntimeOrigin::none(), 0, vote(&alice, 0, stake, 11, false)),
+			Error::<Test>::AlreadyVoted
+		);
+
+		// MinVoteStake is ten
+		let dust = escrow(&bob, 9, 11);
+		assert_noop!(
+			Referendum::vote(RuntimeOrigin::none(), 0, vote(&bob, 0, dust, 11, true)),
+			Error::<Test>::StakeTooSmall
+		);
+	});
+}
+
+#[test]
+fn votes_cannot_be_crowded_out() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(Referendum::propose(RuntimeOrigin::signed(1), set_storage()));
+		let (proposer, opponent) = (voter("Alice"), voter("Bob"));
+
+		// The proposer votes with many small stakes, more than any count cap would allow
+		for value in 10..30 {
+			let stake = escrow(&proposer, value, 11);
+			assert_ok!(Referendum::vote(RuntimeOrigin::none(), 0, vote(&proposer, 0, stake, 11, true)));
+		}
+		let ayes = (10..30).sum::<u128>();
+
+		// and still cannot keep the opponent from voting
+		let stake = escrow(&opponent, 1_000, 11);
+		assert_ok!(Referendum::vote(RuntimeOrigin::none(), 0, vote(&opponent, 0, stake, 11, false)));
+
+		run_blocks(2, 11);
+		System::assert_has_event(Event::Rejected { index: 0, ayes, nays: 1_000 }.into());
+		assert_eq!(sp_io::storage::get(b"key"), None);
+
+		// The 21 votes are removed MaxVotesCleared at a time
+		assert_eq!(Votes::<Test>::iter_prefix(0).count(), 18);
+		run_blocks(12, 16);
+		assert_eq!(Votes::<Test>::iter_prefix(0).count(), 3);
+		assert!(Clearing::<Test>::contains_key(0));
+		run_blocks(17, 17);
+		assert_eq!(Votes::<Test>::iter_prefix(0).count(), 0);
+		assert!(!Clearing::<Test>::contains_key(0));
+	});
+}
+
+#[test]
+fn votes_are_valid_unsigned_transactions() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(Referendum::propose(RuntimeOrigin::signed(1), set_storage()));
+		let alice = voter("Alice");
+		let stake = escrow(&alice, 100, 11);
+		let call = crate::Call::vote { index: 0, vote: vote(&alice, 0, stake, 11, true) };
+
+		let validity = Referendum::validate_unsigned(TransactionSource::External, &call).unwrap();
+		assert_eq!(validity.provides, vec![("Referendum", (0u32, stake)).encode()]);
+		assert_eq!(validity.longevity, 10);
+
+		let crate::Call::vote { index, vote } = call.clone() else { unreachable!() };
+		assert_ok!(Referendum::vote(RuntimeOrigin::none(), index, vote));
+		assert_eq!(
+			Referendum::validate_unsigned(TransactionSource::External, &call),
+			Err(InvalidTransaction::Stale.into())
+		);
+	});
+}
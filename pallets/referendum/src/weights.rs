@@ -0,0 +1,97 @@
+//! Weights for pallet_referendum
+//!
+//! PLACEHOLDER VALUES, NOT YET GENERATED ON REFERENCE HARDWARE.
+//! Regenerate with `./scripts/benchmark-weights.sh pallet_referendum`.
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use core::marker::PhantomData;
+
+/// Weight functions needed for pallet_referendum.
+pub trait WeightInfo {
+	fn on_initialize_idle() -> Weight;
+	fn close() -> Weight;
+	fn clear_votes(v: u32, ) -> Weight;
+	fn propose() -> Weight;
+	fn vote() -> Weight;
+}
+
+/// Weights for pallet_referendum using the Substrate node and recommended hardware.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	/// Storage: Referendum Ending (r:1 w:1)
+	fn on_initialize_idle() -> Weight {
+		Weight::from_parts(3_000_000, 1_500)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: Referendum Referendums (r:1 w:1)
+	/// Storage: Referendum Clearing (r:0 w:1)
+	fn close() -> Weight {
+		Weight::from_parts(15_000_000, 2_000)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Storage: Referendum Clearing (r:1 w:1)
+	/// Storage: Referendum Votes (r:1001 w:1000)
+	/// The range of component `v` is `[0, 1000]`.
+	fn clear_votes(v: u32, ) -> Weight {
+		Weight::from_parts(5_000_000, 1_500)
+			.saturating_add(Weight::from_parts(1_500_000, 0).saturating_mul(v.into()))
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(v.into())))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(v.into())))
+	}
+	/// Storage: Referendum ReferendumCount (r:1 w:1)
+	/// Storage: Referendum Ending (r:1 w:1)
+	/// Storage: Referendum Referendums (r:0 w:1)
+	fn propose() -> Weight {
+		Weight::from_parts(20_000_000, 3_000)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(3_u64))
+	}
+	/// Storage: Referendum Referendums (r:1 w:1)
+	/// Storage: Referendum Votes (r:1 w:1)
+	/// Storage: Utxo UtxoStore (r:1 w:0)
+	fn vote() -> Weight {
+		Weight::from_parts(60_000_000, 3_500)
+			.saturating_add(T::DbWeight::get().reads(3_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	fn on_initialize_idle() -> Weight {
+		Weight::from_parts(3_000_000, 1_500)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	fn close() -> Weight {
+		Weight::from_parts(15_000_000, 2_000)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	fn clear_votes(v: u32, ) -> Weight {
+		Weight::from_parts(5_000_000, 1_500)
+			.saturating_add(Weight::from_parts(1_500_000, 0).saturating_mul(v.into()))
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(v.into())))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+			.saturating_add(RocksDbWeight::get().writes((1_u64).saturating_mul(v.into())))
+	}
+	fn propose() -> Weight {
+		Weight::from_parts(20_000_000, 3_000)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().writes(3_u64))
+	}
+	fn vote() -> Weight {
+		Weight::from_parts(60_000_000, 3_500)
+			.saturating_add(RocksDbWeight::get().reads(3_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+}
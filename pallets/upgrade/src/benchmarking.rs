@@ -0,0 +1,113 @@
+//! Benchmarking setup for pallet-upgrade
+#![cfg(feature = "runtime-benchmarks")]
+use super::*;
+
+use alloc::{vec, vec::Vec};
+use frame_benchmarking::v2::*;
+use frame_support::{
+	traits::{
+		fungible::{Inspect, Mutate},
+		Get, Hooks,
+	},
+	BoundedVec,
+};
+use frame_system::pallet_prelude::BlockNumberFor;
+use sp_runtime::Saturating;
+
+fn pending<T: Config>(code: Vec<u8>, signals: u32, blocks: u32) -> T::Hash {
+	let code_hash = T::Hashing::hash(&code);
+	Pending::<T>::put(PendingUpgrade { code_hash, windows_left: 2, signals, blocks });
+	PendingCode::<T>::put(BoundedVec::try_from(code).expect("benchmark code fits MaxCodeSize"));
+	code_hash
+}
+
+/// A proposing origin whose account can afford the deposit.
+fn funded_proposer<T: Config>() -> Result<T::RuntimeOrigin, BenchmarkError> {
+	let origin = T::ProposeOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+	let who =
+		T::ProposeOrigin::ensure_origin(origin.clone()).map_err(|_| BenchmarkError::Weightless)?;
+	let funds = T::Currency::minimum_balance().saturating_add(T::ProposalDeposit::get());
+	T::Currency::set_balance(&who, funds);
+	Ok(origin)
+}
+
+#[benchmarks]
+mod benchmarks {
+	use super::*;
+
+	#[benchmark]
+	fn on_initialize_idle() {
+		let n: BlockNumberFor<T> = 1u32.into();
+
+		#[block]
+		{
+			Pallet::<T>::on_initialize(n);
+		}
+
+		assert_eq!(Pending::<T>::get(), None);
+	}
+
+	#[benchmark]
+	fn on_initialize_signal() {
+		let code_hash = pending::<T>(vec![1], 0, 0);
+		frame_system::Pallet::<T>::deposit_log(signal_digest(code_hash));
+		let n: BlockNumberFor<T> = 1u32.into();
+
+		#[block]
+		{
+			Pallet::<T>::on_initialize(n);
+		}
+
+		assert_eq!(Pending::<T>::get().map(|pending| pending.signals), Some(1));
+	}
+
+	#[benchmark]
+	fn on_initialize_close() -> Result<(), BenchmarkError> {
+		// The last window of a proposal, which lapses and loses its deposit
+		Pallet::<T>::propose_upgrade(funded_proposer::<T>()?, vec![1])?;
+		let window = T::SignalWindow::get().max(1);
+		Pending::<T>::mutate(|pending| {
+			if let Some(pending) = pending {
+				pending.windows_left = 1;
+				pending.blocks = window - 1;
+			}
+		});
+		let n: BlockNumberFor<T> = window.into();
+
+		#[block]
+		{
+			Pallet::<T>::on_initialize(n);
+		}
+
+		assert_eq!(Pending::<T>::get(), None);
+		assert_eq!(Deposit::<T>::get(), None);
+		Ok(())
+	}
+
+	#[benchmark]
+	fn propose_upgrade(c: Linear<0, { T::MaxCodeSize::get() }>) -> Result<(), BenchmarkError> {
+		let origin = funded_proposer::<T>()?;
+		let code = vec![1u8; c as usize];
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, code);
+
+		assert!(Pending::<T>::exists());
+		Ok(())
+	}
+
+	#[benchmark]
+	fn cancel_upgrade() -> Result<(), BenchmarkError> {
+		let origin = T::CancelOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+		Pallet::<T>::propose_upgrade(funded_proposer::<T>()?, vec![1])?;
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin);
+
+		assert_eq!(Pending::<T>::get(), None);
+		assert_eq!(Deposit::<T>::get(), None);
+		Ok(())
+	}
+
+	impl_benchmark_test_suite!(Pallet, crate::mock::new_test_ext(), crate::mock::Test);
+}
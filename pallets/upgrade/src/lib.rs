@@ -0,0 +1,320 @@
+//! # Upgrade Pallet
+//!
+//! Lets miners, rather than a sudo key, decide when a runtime upgrade goes live.
+//!
+//! ## Overview
+//!
+//! [`Config::ProposeOrigin`] submits the Wasm code of an upgrade with
+//! [`Pallet::propose_upgrade`], holding [`Config::ProposalDeposit`] from the proposer. The code
+//! is stored, not yet applied. Block authors that have
+//! checked it, e.g. by reproducing its hash from source with `srtool`, approve it by adding an
+//! [`UPGRADE_ENGINE_ID`] pre-runtime digest with the code hash to their headers (see
+//! [`signal_digest`]).
+//!
+//! Signals are tallied over windows of [`Config::SignalWindow`] blocks. When a window closes
+//! with at least [`Config::Threshold`] of its blocks signalling the pending code, the code is
+//! applied through `frame_system::Pallet::set_code`, with all the usual checks of the new
+//! runtime version. Otherwise the tally restarts, and after [`Config::SignalWindows`] windows
+//! without approval the proposal lapses. [`Config::CancelOrigin`] can withdraw a proposal at
+//! any time, but nothing in this pallet can apply code that miners did not signal.
+//!
+//! A single upgrade can be pending at a time. So that nobody can keep it taken at no cost, the
+//! deposit is only released when miners approve the upgrade; a proposal that lapses or is
+//! withdrawn loses it.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use pallet::*;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+pub mod weights;
+pub use weights::*;
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use codec::{Decode, Encode, MaxEncodedLen};
+use frame_support::traits::fungible;
+use scale_info::TypeInfo;
+use sp_runtime::{
+	traits::{Hash, Zero},
+	ConsensusEngineId, DigestItem, Perbill, RuntimeDebug,
+};
+
+/// Engine id of the pre-runtime digest with which a miner approves the pending upgrade.
+pub const UPGRADE_ENGINE_ID: ConsensusEngineId = *b"UPGR";
+
+/// The pre-runtime digest with which a block author approves the upgrade to the code hashing
+/// to `code_hash`.
+pub fn signal_digest<H: Encode>(code_hash: H) -> DigestItem {
+	DigestItem::PreRuntime(UPGRADE_ENGINE_ID, code_hash.encode())
+}
+
+type BalanceOf<T> =
+	<<T as Config>::Currency as fungible::Inspect<<T as frame_system::Config>::AccountId>>::Balance;
+
+/// An upgrade waiting for miners' approval.
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo, RuntimeDebug)]
+pub struct PendingUpgrade<Hash> {
+	/// Hash of the proposed code
+	pub code_hash: Hash,
+	/// Windows left to approve the upgrade, the current one included
+	pub windows_left: u32,
+	/// Blocks of the current window signalling `code_hash`
+	pub signals: u32,
+	/// Blocks in the current window so far, signalling or not
+	pub blocks: u32,
+}
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+	use frame_support::{
+		pallet_prelude::*,
+		traits::{
+			fungible::MutateHold,
+			tokens::{Fortitude, Precision},
+		},
+	};
+	use frame_system::pallet_prelude::*;
+
+	#[pallet::pallet]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// The overarching runtime event type.
+		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+		/// A type representing the weights required by the dispatchables of this pallet.
+		type WeightInfo: WeightInfo;
+
+		/// The overarching hold reason.
+		type RuntimeHoldReason: From<HoldReason>;
+
+		/// The currency proposal deposits are held in.
+		type Currency: fungible::MutateHold<Self::AccountId, Reason = Self::RuntimeHoldReason>
+			+ fungible::Mutate<Self::AccountId>;
+
+		/// Origin allowed to propose an upgrade, paying the deposit from the account it yields.
+		type ProposeOrigin: EnsureOrigin<Self::RuntimeOrigin, Success = Self::AccountId>;
+
+		/// Origin allowed to withdraw the pending upgrade.
+		type CancelOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+		/// Largest code that can be proposed, in bytes.
+		#[pallet::constant]
+		type MaxCodeSize: Get<u32>;
+
+		/// Number of blocks whose signals are tallied together.
+		#[pallet::constant]
+		type SignalWindow: Get<u32>;
+
+		/// Share of a window that has to signal the pending code for it to be applied.
+		#[pallet::constant]
+		type Threshold: Get<Perbill>;
+
+		/// Number of windows a proposal stays open before it lapses.
+		#[pallet::constant]
+		type SignalWindows: Get<u32>;
+
+		/// Amount held from the proposer of an upgrade, forfeited unless miners approve it.
+		#[pallet::constant]
+		type ProposalDeposit: Get<BalanceOf<Self>>;
+	}
+
+	/// Reasons for this pallet to hold funds.
+	#[pallet::composite_enum]
+	pub enum HoldReason {
+		/// The deposit of the pending upgrade's proposer.
+		ProposalDeposit,
+	}
+
+	/// The upgrade miners are signalling for, with the tally of the current window.
+	#[pallet::storage]
+	pub type Pending<T: Config> = StorageValue<_, PendingUpgrade<T::Hash>, OptionQuery>;
+
+	/// The code of the [`Pending`] upgrade.
+	#[pallet::storage]
+	pub type PendingCode<T: Config> = StorageValue<_, BoundedVec<u8, T::MaxCodeSize>, OptionQuery>;
+
+	/// The proposer of the [`Pending`] upgrade and the deposit held from it.
+	#[pallet::storage]
+	pub type Deposit<T: Config> = StorageValue<_, (T::AccountId, BalanceOf<T>), OptionQuery>;
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// An upgrade was proposed and awaits miners' signals.
+		UpgradeProposed { code_hash: T::Hash },
+		/// A signalling window closed on the pending upgrade.
+		WindowClosed { code_hash: T::Hash, signals: u32, blocks: u32 },
+		/// Miners approved the upgrade and its code was set.
+		UpgradeEnacted { code_hash: T::Hash },
+		/// Miners approved the upgrade but its code could not be set.
+		UpgradeFailed { code_hash: T::Hash, error: DispatchError },
+		/// The upgrade was not approved within [`Config::SignalWindows`] windows.
+		UpgradeExpired { code_hash: T::Hash },
+		/// The pending upgrade was withdrawn.
+		UpgradeCancelled { code_hash: T::Hash },
+		/// The deposit of an approved upgrade was returned to its proposer.
+		DepositReleased { who: T::AccountId, amount: BalanceOf<T> },
+		/// The deposit of an upgrade miners did not approve was burned.
+		DepositSlashed { who: T::AccountId, amount: BalanceOf<T> },
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// Another upgrade is already pending
+		UpgradePending,
+		/// No upgrade is pending
+		NoPendingUpgrade,
+		/// The code exceeds [`Config::MaxCodeSize`]
+		CodeTooLarge,
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(n: BlockNumberFor<T>) -> Weight {
+			let Some(mut pending) = Pending::<T>::get() else {
+				return T::WeightInfo::on_initialize_idle()
+			};
+			if Self::block_signal() == Some(pending.code_hash) {
+				pending.signals.saturating_inc();
+			}
+			pending.blocks.saturating_inc();
+
+			let window: BlockNumberFor<T> = T::SignalWindow::get().max(1).into();
+			if (n % window).is_zero() {
+				Self::close_window(pending)
+			} else {
+				Pending::<T>::put(pending);
+				T::WeightInfo::on_initialize_signal()
+			}
+		}
+
+		fn integrity_test() {
+			assert!(!T::Threshold::get().is_zero(), "Threshold must be above zero");
+			assert!(T::SignalWindows::get() > 0, "SignalWindows must be above zero");
+		}
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Propose `code` as the next runtime, to be applied once miners signal it.
+		///
+		/// Holds [`Config::ProposalDeposit`] from the proposer until the upgrade is decided.
+		#[pallet::call_index(0)]
+		#[pallet::weight(T::WeightInfo::propose_upgrade(code.len() as u32))]
+		pub fn propose_upgrade(origin: OriginFor<T>, code: Vec<u8>) -> DispatchResult {
+			let who = T::ProposeOrigin::ensure_origin(origin)?;
+			ensure!(!Pending::<T>::exists(), Error::<T>::UpgradePending);
+			let code: BoundedVec<u8, T::MaxCodeSize> =
+				code.try_into().map_err(|_| Error::<T>::CodeTooLarge)?;
+
+			let deposit = T::ProposalDeposit::get();
+			T::Currency::hold(&HoldReason::ProposalDeposit.into(), &who, deposit)?;
+			Deposit::<T>::put((who, deposit));
+
+			let code_hash = T::Hashing::hash(&code);
+			Pending::<T>::put(PendingUpgrade {
+				code_hash,
+				windows_left: T::SignalWindows::get(),
+				signals: 0,
+				blocks: 0,
+			});
+			PendingCode::<T>::put(code);
+			Self::deposit_event(Event::UpgradeProposed { code_hash });
+			Ok(())
+		}
+
+		/// Withdraw the pending upgrade, burning its proposer's deposit.
+		#[pallet::call_index(1)]
+		#[pallet::weight(T::WeightInfo::cancel_upgrade())]
+		pub fn cancel_upgrade(origin: OriginFor<T>) -> DispatchResult {
+			T::CancelOrigin::ensure_origin(origin)?;
+			let pending = Pending::<T>::take().ok_or(Error::<T>::NoPendingUpgrade)?;
+			PendingCode::<T>::kill();
+			Self::settle_deposit(false);
+			Self::deposit_event(Event::UpgradeCancelled { code_hash: pending.code_hash });
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// The code hash signalled by the author of the current block, if any.
+		pub fn block_signal() -> Option<T::Hash> {
+			frame_system::Pallet::<T>::digest()
+				.logs
+				.iter()
+				.find_map(|item| item.pre_runtime_try_to::<T::Hash>(&UPGRADE_ENGINE_ID))
+		}
+
+		/// Apply the pending code if enough of the window signalled it, or start a new window.
+		fn close_window(mut pending: PendingUpgrade<T::Hash>) -> Weight {
+			let code_hash = pending.code_hash;
+			Self::deposit_event(Event::WindowClosed {
+				code_hash,
+				signals: pending.signals,
+				blocks: pending.blocks,
+			});
+
+			// A share of the full window, so a proposal made mid-window has fewer blocks to
+			// reach it
+			let threshold = T::Threshold::get().mul_ceil(T::SignalWindow::get().max(1));
+			if pending.signals >= threshold {
+				Pending::<T>::kill();
+				Self::settle_deposit(true);
+				let code = PendingCode::<T>::take().unwrap_or_default().into_inner();
+				match frame_system::Pallet::<T>::set_code(frame_system::RawOrigin::Root.into(), code) {
+					Ok(_) => Self::deposit_event(Event::UpgradeEnacted { code_hash }),
+					Err(e) => Self::deposit_event(Event::UpgradeFailed { code_hash, error: e.error }),
+				}
+				// Like `set_code`, the upgrade takes the whole block
+				return T::BlockWeights::get().max_block
+			}
+
+			if pending.windows_left <= 1 {
+				Pending::<T>::kill();
+				PendingCode::<T>::kill();
+				Self::settle_deposit(false);
+				Self::deposit_event(Event::UpgradeExpired { code_hash });
+			} else {
+				pending.windows_left -= 1;
+				pending.signals = 0;
+				pending.blocks = 0;
+				Pending::<T>::put(pending);
+			}
+			T::WeightInfo::on_initialize_close()
+		}
+
+		/// Return the deposit of the decided upgrade to its proposer if miners `approved` it,
+		/// and burn it otherwise.
+		fn settle_deposit(approved: bool) {
+			let Some((who, amount)) = Deposit::<T>::take() else { return };
+			let reason = HoldReason::ProposalDeposit.into();
+			if approved {
+				let released = T::Currency::release(&reason, &who, amount, Precision::BestEffort);
+				let amount = released.unwrap_or_default();
+				Self::deposit_event(Event::DepositReleased { who, amount });
+			} else {
+				let burned = T::Currency::burn_held(
+					&reason,
+					&who,
+					amount,
+					Precision::BestEffort,
+					Fortitude::Force,
+				);
+				let amount = burned.unwrap_or_default();
+				Self::deposit_event(Event::DepositSlashed { who, amount });
+			}
+		}
+	}
+}
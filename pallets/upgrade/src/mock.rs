@@ -0,0 +1,91 @@
+use crate as pallet_upgrade;
+use codec::Encode;
+use frame_support::{derive_impl, parameter_types};
+use sp_core::H256;
+use sp_runtime::{BuildStorage, Perbill};
+use sp_version::RuntimeVersion;
+
+type Block = frame_system::mocking::MockBlock<Test>;
+
+frame_support::construct_runtime!(
+	pub enum Test
+	{
+		System: frame_system,
+		Balances: pallet_balances,
+		Upgrade: pallet_upgrade,
+	}
+);
+
+#[derive_impl(frame_system::config_preludes::TestDefaultConfig)]
+impl frame_system::Config for Test {
+	type Block = Block;
+	type AccountData = pallet_balances::AccountData<u64>;
+}
+
+#[derive_impl(pallet_balances::config_preludes::TestDefaultConfig)]
+impl pallet_balances::Config for Test {
+	type AccountStore = System;
+}
+
+parameter_types! {
+	pub const SignalWindow: u32 = 10;
+	pub const Threshold: Perbill = Perbill::from_percent(80);
+	pub const SignalWindows: u32 = 3;
+	pub const ProposalDeposit: u64 = 100;
+}
+
+impl pallet_upgrade::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type WeightInfo = ();
+	type RuntimeHoldReason = RuntimeHoldReason;
+	type Currency = Balances;
+	type ProposeOrigin = frame_system::EnsureSigned<u64>;
+	type CancelOrigin = frame_system::EnsureRoot<u64>;
+	type MaxCodeSize = frame_support::traits::ConstU32<64>;
+	type SignalWindow = SignalWindow;
+	type Threshold = Threshold;
+	type SignalWindows = SignalWindows;
+	type ProposalDeposit = ProposalDeposit;
+}
+
+/// Reads the spec version of mock runtime code from its first byte, which must not be zero.
+struct ReadRuntimeVersion;
+
+impl sp_core::traits::ReadRuntimeVersion for ReadRuntimeVersion {
+	fn read_runtime_version(
+		&self,
+		wasm_code: &[u8],
+		_ext: &mut dyn sp_externalities::Externalities,
+	) -> Result<Vec<u8>, String> {
+		match wasm_code.first() {
+			Some(&spec_version) if spec_version > 0 =>
+				Ok(RuntimeVersion { spec_version: spec_version.into(), ..Default::default() }.encode()),
+			_ => Err("not a runtime".into()),
+		}
+	}
+}
+
+/// Test externalities in which accounts 1 and 2 hold 1000 and 50.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	let mut storage = frame_system::GenesisConfig::<Test>::default().build_storage().unwrap();
+	pallet_balances::GenesisConfig::<Test> { balances: vec![(1, 1000), (2, 50)] }
+		.assimilate_storage(&mut storage)
+		.unwrap();
+	let mut ext: sp_io::TestExternalities = storage.into();
+	ext.register_extension(sp_core::traits::ReadRuntimeVersionExt::new(ReadRuntimeVersion));
+	ext
+}
+
+/// Run blocks `from..=to`, each authored with `signal` if given.
+pub fn run_blocks(from: u64, to: u64, signal: Option<H256>) {
+	use frame_support::traits::Hooks;
+
+	for n in from..=to {
+		System::reset_events();
+		System::initialize(&n, &Default::default(), &Default::default());
+		if let Some(signal) = signal {
+			System::deposit_log(crate::signal_digest(signal));
+		}
+		Upgrade::on_initialize(n);
+	}
+}
@@ -0,0 +1,150 @@
+use crate::{mock::*, Deposit, Error, Event, Pending, PendingCode, PendingUpgrade};
+use frame_support::{assert_noop, assert_ok};
+use sp_core::{storage::well_known_keys::CODE, H256};
+use sp_runtime::{
+	traits::{BlakeTwo256, Hash},
+	DispatchError,
+};
+
+fn code(spec_version: u8) -> Vec<u8> {
+	vec![spec_version, 0xaa, 0xbb]
+}
+
+fn propose(code: Vec<u8>) -> H256 {
+	let code_hash = BlakeTwo256::hash(&code);
+	assert_ok!(Upgrade::propose_upgrade(RuntimeOrigin::signed(1), code));
+	code_hash
+}
+
+#[test]
+fn signalled_upgrade_is_enacted() {
+	new_test_ext().execute_with(|| {
+		let code_hash = propose(code(1));
+		assert_eq!(PendingCode::<Test>::get().unwrap().into_inner(), code(1));
+
+		// Eight of ten blocks reach the 80% threshold
+		run_blocks(1, 8, Some(code_hash));
+		run_blocks(9, 10, None);
+
+		System::assert_has_event(Event::WindowClosed { code_hash, signals: 8, blocks: 10 }.into());
+		System::assert_has_event(Event::UpgradeEnacted { code_hash }.into());
+		assert_eq!(sp_io::storage::get(CODE).map(|code| code.to_vec()), Some(code(1)));
+		assert_eq!(Pending::<Test>::get(), None);
+		assert_eq!(PendingCode::<Test>::get(), None);
+	});
+}
+
+#[test]
+fn approval_can_take_several_windows() {
+	new_test_ext().execute_with(|| {
+		let code_hash = propose(code(1));
+
+		// Seven is not enough, and signals for other code do not count
+		run_blocks(1, 7, Some(code_hash));
+		run_blocks(8, 10, Some(H256::repeat_byte(1)));
+		System::assert_has_event(Event::WindowClosed { code_hash, signals: 7, blocks: 10 }.into());
+		assert_eq!(
+			Pending::<Test>::get(),
+			Some(PendingUpgrade { code_hash, windows_left: 2, signals: 0, blocks: 0 })
+		);
+
+		run_blocks(11, 20, None);
+		run_blocks(21, 30, Some(code_hash));
+		assert_eq!(sp_io::storage::get(CODE).map(|code| code.to_vec()), Some(code(1)));
+		assert_eq!(Pending::<Test>::get(), None);
+	});
+}
+
+#[test]
+fn last_window_must_approve() {
+	new_test_ext().execute_with(|| {
+		let code_hash = propose(code(1));
+
+		run_blocks(1, 29, None);
+		run_blocks(30, 30, Some(code_hash));
+		System::assert_has_event(Event::UpgradeExpired { code_hash }.into());
+		assert_eq!(sp_io::storage::get(CODE), None);
+		assert_eq!(Pending::<Test>::get(), None);
+		assert_eq!(PendingCode::<Test>::get(), None);
+	});
+}
+
+#[test]
+fn invalid_code_is_dropped() {
+	new_test_ext().execute_with(|| {
+		let code_hash = propose(code(0));
+
+		run_blocks(1, 10, Some(code_hash));
+		System::assert_has_event(
+			Event::UpgradeFailed {
+				code_hash,
+				error: frame_system::Error::<Test>::FailedToExtractRuntimeVersion.into(),
+			}
+			.into(),
+		);
+		assert_eq!(sp_io::storage::get(CODE), None);
+		assert_eq!(Pending::<Test>::get(), None);
+	});
+}
+
+#[test]
+fn proposals_and_cancellation() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			Upgrade::propose_upgrade(RuntimeOrigin::root(), code(1)),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			Upgrade::propose_upgrade(RuntimeOrigin::signed(1), vec![1; 65]),
+			Error::<Test>::CodeTooLarge
+		);
+		assert_noop!(Upgrade::cancel_upgrade(RuntimeOrigin::root()), Error::<Test>::NoPendingUpgrade);
+
+		System::set_block_number(1);
+		let code_hash = propose(code(1));
+		System::assert_last_event(Event::UpgradeProposed { code_hash }.into());
+		assert_noop!(
+			Upgrade::propose_upgrade(RuntimeOrigin::signed(2), code(2)),
+			Error::<Test>::UpgradePending
+		);
+
+		assert_noop!(Upgrade::cancel_upgrade(RuntimeOrigin::signed(1)), DispatchError::BadOrigin);
+		assert_ok!(Upgrade::cancel_upgrade(RuntimeOrigin::root()));
+		System::assert_last_event(Event::UpgradeCancelled { code_hash }.into());
+		System::assert_has_event(Event::DepositSlashed { who: 1, amount: 100 }.into());
+		assert_eq!(Pending::<Test>::get(), None);
+		assert_eq!(PendingCode::<Test>::get(), None);
+		assert_eq!(Balances::total_balance(&1), 900);
+
+		// Miners cannot bring back a withdrawn upgrade
+		run_blocks(1, 10, Some(code_hash));
+		assert_eq!(sp_io::storage::get(CODE), None);
+	});
+}
+
+#[test]
+fn only_approved_upgrades_get_their_deposit_back() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		// Proposers have to afford the deposit
+		assert!(Upgrade::propose_upgrade(RuntimeOrigin::signed(2), code(1)).is_err());
+		assert_eq!(Pending::<Test>::get(), None);
+
+		let code_hash = propose(code(1));
+		assert_eq!(Deposit::<Test>::get(), Some((1, 100)));
+		assert_eq!(Balances::free_balance(1), 900);
+		run_blocks(1, 10, Some(code_hash));
+		System::assert_has_event(Event::UpgradeEnacted { code_hash }.into());
+		System::assert_has_event(Event::DepositReleased { who: 1, amount: 100 }.into());
+		assert_eq!(Deposit::<Test>::get(), None);
+		assert_eq!(Balances::free_balance(1), 1000);
+
+		// Keeping the slot taken with code miners ignore costs the deposit
+		let code_hash = propose(code(2));
+		run_blocks(11, 40, None);
+		System::assert_has_event(Event::UpgradeExpired { code_hash }.into());
+		System::assert_has_event(Event::DepositSlashed { who: 1, amount: 100 }.into());
+		assert_eq!(Deposit::<Test>::get(), None);
+		assert_eq!(Balances::total_balance(&1), 900);
+	});
+}
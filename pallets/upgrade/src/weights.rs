@@ -0,0 +1,97 @@
+//! Weights for pallet_upgrade
+//!
+//! PLACEHOLDER VALUES, NOT YET GENERATED ON REFERENCE HARDWARE.
+//! Regenerate with `./scripts/benchmark-weights.sh pallet_upgrade`.
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use core::marker::PhantomData;
+
+/// Weight functions needed for pallet_upgrade.
+pub trait WeightInfo {
+	fn on_initialize_idle() -> Weight;
+	fn on_initialize_signal() -> Weight;
+	fn on_initialize_close() -> Weight;
+	fn propose_upgrade(c: u32, ) -> Weight;
+	fn cancel_upgrade() -> Weight;
+}
+
+/// Weights for pallet_upgrade using the Substrate node and recommended hardware.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	/// Storage: Upgrade Pending (r:1 w:0)
+	fn on_initialize_idle() -> Weight {
+		Weight::from_parts(2_000_000, 1_500)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+	}
+	/// Storage: Upgrade Pending (r:1 w:1)
+	fn on_initialize_signal() -> Weight {
+		Weight::from_parts(6_000_000, 1_500)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: Upgrade Pending (r:1 w:1)
+	/// Storage: Upgrade PendingCode (r:0 w:1)
+	/// Storage: Upgrade Deposit (r:1 w:1)
+	/// Storage: Balances Holds (r:1 w:1)
+	/// Storage: System Account (r:1 w:1)
+	fn on_initialize_close() -> Weight {
+		Weight::from_parts(40_000_000, 3_600)
+			.saturating_add(T::DbWeight::get().reads(4_u64))
+			.saturating_add(T::DbWeight::get().writes(5_u64))
+	}
+	/// Storage: Upgrade Pending (r:1 w:1)
+	/// Storage: Upgrade PendingCode (r:0 w:1)
+	/// Storage: Upgrade Deposit (r:0 w:1)
+	/// Storage: Balances Holds (r:1 w:1)
+	/// Storage: System Account (r:1 w:1)
+	/// The range of component `c` is `[0, 3145728]`.
+	fn propose_upgrade(c: u32, ) -> Weight {
+		Weight::from_parts(40_000_000, 3_600)
+			.saturating_add(Weight::from_parts(2_000, 0).saturating_mul(c.into()))
+			.saturating_add(T::DbWeight::get().reads(3_u64))
+			.saturating_add(T::DbWeight::get().writes(5_u64))
+	}
+	/// Storage: Upgrade Pending (r:1 w:1)
+	/// Storage: Upgrade PendingCode (r:0 w:1)
+	/// Storage: Upgrade Deposit (r:1 w:1)
+	/// Storage: Balances Holds (r:1 w:1)
+	/// Storage: System Account (r:1 w:1)
+	fn cancel_upgrade() -> Weight {
+		Weight::from_parts(40_000_000, 3_600)
+			.saturating_add(T::DbWeight::get().reads(4_u64))
+			.saturating_add(T::DbWeight::get().writes(5_u64))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	fn on_initialize_idle() -> Weight {
+		Weight::from_parts(2_000_000, 1_500)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+	}
+	fn on_initialize_signal() -> Weight {
+		Weight::from_parts(6_000_000, 1_500)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	fn on_initialize_close() -> Weight {
+		Weight::from_parts(40_000_000, 3_600)
+			.saturating_add(RocksDbWeight::get().reads(4_u64))
+			.saturating_add(RocksDbWeight::get().writes(5_u64))
+	}
+	fn propose_upgrade(c: u32, ) -> Weight {
+		Weight::from_parts(40_000_000, 3_600)
+			.saturating_add(Weight::from_parts(2_000, 0).saturating_mul(c.into()))
+			.saturating_add(RocksDbWeight::get().reads(3_u64))
+			.saturating_add(RocksDbWeight::get().writes(5_u64))
+	}
+	fn cancel_upgrade() -> Weight {
+		Weight::from_parts(40_000_000, 3_600)
+			.saturating_add(RocksDbWeight::get().reads(4_u64))
+			.saturating_add(RocksDbWeight::get().writes(5_u64))
+	}
+}
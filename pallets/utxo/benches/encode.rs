@@ -0,0 +1,118 @@
+//! Criterion harness for the encodings hashed while validating a transaction.
+//!
+//! Compares [`Transaction::signing_payload`] with encoding a cleared copy, which it replaced,
+//! and reports the heap allocations each makes for a transaction of the maximum size:
+//!
+//! ```sh
+//! cargo bench -p utxo --bench encode
+//! ```
+
+use codec::Encode;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use sp_core::{H256, H512};
+use sp_runtime::{
+	traits::{BlakeTwo256, Hash},
+	BoundedVec,
+};
+use std::{
+	alloc::{GlobalAlloc, Layout, System},
+	sync::atomic::{AtomicUsize, Ordering},
+};
+use utxo::{
+	script::ScriptWitness, Transaction, TransactionInput, TransactionOutput, MAX_TRANSACTION_PARTS,
+	TRANSACTION_VERSION,
+};
+
+/// Counts allocations so that the report below does not depend on a profiler.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+		System.alloc(layout)
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		System.dealloc(ptr, layout)
+	}
+
+	unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+		ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+		System.realloc(ptr, layout, new_size)
+	}
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// A transaction with `parts` inputs, each revealing a script, and `parts` outputs.
+fn transaction(parts: u32) -> Transaction {
+	let inputs = (0..parts)
+		.map(|n| TransactionInput {
+			outpoint: BlakeTwo256::hash_of(&(b"input", n)),
+			sigscript: Some(H512::repeat_byte(1)),
+			witness: Some(ScriptWitness {
+				script: BoundedVec::truncate_from(vec![0x51; 100]),
+				stack: BoundedVec::truncate_from(vec![BoundedVec::truncate_from(vec![1; 64])]),
+			}),
+		})
+		.collect::<Vec<_>>();
+	let outputs = (0..parts)
+		.map(|n| TransactionOutput { value: 99, pubkey: BlakeTwo256::hash_of(&(b"output", n)), memo: None })
+		.collect::<Vec<_>>();
+
+	Transaction {
+		version: TRANSACTION_VERSION,
+		inputs: BoundedVec::try_from(inputs).unwrap(),
+		outputs: BoundedVec::try_from(outputs).unwrap(),
+	}
+}
+
+/// The signing payload as it used to be built, by encoding a copy with the witnesses cleared.
+fn cleared_copy_payload(transaction: &Transaction) -> Vec<u8> {
+	let mut cleared = transaction.clone();
+	for input in cleared.inputs.iter_mut() {
+		input.sigscript = None;
+		input.witness = None;
+	}
+	cleared.encode()
+}
+
+/// Heap allocations made by one call of `f`.
+fn allocations<R>(f: impl FnOnce() -> R) -> usize {
+	let before = ALLOCATIONS.load(Ordering::Relaxed);
+	black_box(f());
+	ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn bench_encode(c: &mut Criterion) {
+	let parts = MAX_TRANSACTION_PARTS;
+	let transaction = transaction(parts);
+	assert_eq!(transaction.signing_payload(), cleared_copy_payload(&transaction));
+
+	println!(
+		"allocations for {parts}x{parts}: signing payload {} (cleared copy {}), outpoints {} (hash_of {})",
+		allocations(|| transaction.signing_payload()),
+		allocations(|| cleared_copy_payload(&transaction)),
+		allocations(|| (0..parts as u64).map(|i| Transaction::outpoint_of(&H256::zero(), i)).last()),
+		allocations(|| (0..parts as u64).map(|i| BlakeTwo256::hash_of(&(H256::zero(), i))).last()),
+	);
+
+	let mut group = c.benchmark_group(format!("encode/{parts}x{parts}"));
+	group.bench_function("signing_payload", |b| b.iter(|| black_box(&transaction).signing_payload()));
+	group.bench_function("cleared_copy_payload", |b| {
+		b.iter(|| cleared_copy_payload(black_box(&transaction)))
+	});
+	group.bench_function("txid_and_outpoints", |b| {
+		b.iter(|| {
+			let txid = black_box(&transaction).txid();
+			(0..parts as u64).map(|i| Transaction::outpoint_of(&txid, i)).last()
+		})
+	});
+	group.finish();
+}
+
+criterion_group!(benches, bench_encode);
+criterion_main!(benches);
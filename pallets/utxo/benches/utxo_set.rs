@@ -0,0 +1,149 @@
+//! Criterion harness for profiling the UTXO pallet against a large UTXO set locally.
+//!
+//! Mirrors the runtime benchmarks without going through the node's benchmark CLI:
+//!
+//! ```sh
+//! cargo bench -p utxo --bench utxo_set
+//! ```
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use frame_support::{derive_impl, parameter_types, traits::Hooks};
+use sp_core::{crypto::KeyTypeId, sr25519::Public, H256, H512};
+use sp_keystore::{testing::MemoryKeystore, KeystoreExt};
+use sp_runtime::{
+	traits::{BlakeTwo256, Hash},
+	BoundedVec, BuildStorage, Perbill,
+};
+use utxo::{
+	Transaction, TransactionInput, TransactionOutput, UtxoStore, Value, TRANSACTION_VERSION,
+};
+
+/// Number of outputs in the UTXO set before measuring.
+const POPULATED_UTXOS: u32 = 1_000_000;
+
+const KEY_TYPE: KeyTypeId = KeyTypeId(*b"utxo");
+
+type Block = frame_system::mocking::MockBlock<Bench>;
+
+frame_support::construct_runtime!(
+	pub enum Bench
+	{
+		System: frame_system,
+		Utxo: utxo,
+	}
+);
+
+#[derive_impl(frame_system::config_preludes::TestDefaultConfig)]
+impl frame_system::Config for Bench {
+	type Block = Block;
+}
+
+parameter_types! {
+	pub const MaxTransactionSize: u32 = 100;
+	pub const MaxMoney: Value = u128::MAX;
+	pub const MaxScriptSize: u32 = utxo::script::MAX_SCRIPT_SIZE;
+	pub const MaxScriptOps: u32 = 201;
+	pub const ConsolidationDiscount: Perbill = Perbill::from_percent(50);
+	pub const MaxStackDepth: u32 = 1_000;
+	pub const RewardSmoothing: u32 = 0;
+	pub const UncleReward: Perbill = Perbill::from_percent(25);
+	pub const MaxUncleDepth: u32 = 6;
+	pub const MaxUncles: u32 = 2;
+	pub const CanonicalOrderingFrom: u64 = u64::MAX;
+}
+
+pub struct NoAuthor;
+impl utxo::BlockAuthor for NoAuthor {
+	fn block_author() -> Option<Public> {
+		None
+	}
+}
+
+impl utxo::Config for Bench {
+	type RuntimeEvent = RuntimeEvent;
+	type BlockAuthor = NoAuthor;
+	type Issuance = ();
+	type WeightInfo = ();
+	type MaxTransactionSize = MaxTransactionSize;
+	type MaxMoney = MaxMoney;
+	type MintOrigin = frame_system::EnsureRoot<u64>;
+	type MaxScriptSize = MaxScriptSize;
+	type MaxScriptOps = MaxScriptOps;
+	type MaxStackDepth = MaxStackDepth;
+	type ConsolidationDiscount = ConsolidationDiscount;
+	type RewardSmoothing = RewardSmoothing;
+	type UncleAuthor = ();
+	type UncleReward = UncleReward;
+	type MaxUncleDepth = MaxUncleDepth;
+	type MaxUncles = MaxUncles;
+	type CanonicalOrderingFrom = CanonicalOrderingFrom;
+}
+
+/// Externalities holding a populated UTXO set and a keystore for signing.
+fn populated_ext() -> sp_io::TestExternalities {
+	let storage = frame_system::GenesisConfig::<Bench>::default().build_storage().unwrap();
+	let mut ext = sp_io::TestExternalities::new(storage);
+	ext.register_extension(KeystoreExt::new(MemoryKeystore::new()));
+	ext.execute_with(|| {
+		for n in 0..POPULATED_UTXOS {
+			let utxo = TransactionOutput { value: 1, pubkey: H256::zero(), memo: None };
+			UtxoStore::<Bench>::insert(BlakeTwo256::hash_of(&(b"filler", n)), utxo);
+		}
+		System::set_block_number(1);
+	});
+	ext.commit_all().unwrap();
+	ext
+}
+
+/// A transaction spending `parts` freshly created UTXOs into `parts` outputs.
+fn signed_transaction(parts: u32) -> Transaction {
+	let signer = sp_io::crypto::sr25519_generate(KEY_TYPE, None);
+	let owner = H256::from_slice(signer.as_ref());
+
+	let inputs = (0..parts)
+		.map(|n| {
+			let utxo = TransactionOutput { value: 100, pubkey: owner, memo: None };
+			let outpoint = BlakeTwo256::hash_of(&(b"input", n));
+			UtxoStore::<Bench>::insert(outpoint, utxo);
+			TransactionInput { outpoint, sigscript: None, witness: None }
+		})
+		.collect::<Vec<_>>();
+	let outputs = (0..parts)
+		.map(|n| TransactionOutput { value: 99, pubkey: BlakeTwo256::hash_of(&(b"output", n)), memo: None })
+		.collect::<Vec<_>>();
+
+	let mut transaction = Transaction {
+		version: TRANSACTION_VERSION,
+		inputs: BoundedVec::try_from(inputs).unwrap(),
+		outputs: BoundedVec::try_from(outputs).unwrap(),
+	};
+	let signature = sp_io::crypto::sr25519_sign(KEY_TYPE, &signer, &transaction.signing_payload())
+		.expect("the key was generated in the keystore");
+	// Every input spends an output of the signer, so the first carries the signature for all
+	transaction.inputs[0].sigscript = Some(H512::from_slice(signature.as_ref()));
+	transaction
+}
+
+fn bench_utxo_set(c: &mut Criterion) {
+	let mut ext = populated_ext();
+
+	for parts in [1, 10, 100] {
+		c.bench_function(&format!("spend/{parts}x{parts}"), |b| {
+			b.iter_batched(
+				|| ext.execute_with(|| signed_transaction(parts)),
+				|transaction| {
+					ext.execute_with(|| {
+						sp_io::storage::start_transaction();
+						Utxo::spend(RuntimeOrigin::none(), transaction).unwrap();
+						Utxo::on_finalize(1);
+						sp_io::storage::rollback_transaction();
+					})
+				},
+				BatchSize::SmallInput,
+			)
+		});
+	}
+}
+
+criterion_group!(benches, bench_utxo_set);
+criterion_main!(benches);
@@ -1,54 +1,240 @@
 #![cfg(feature = "runtime-benchmarks")]
 use super::*;
 
-#[allow(unused)]
 use frame_benchmarking::v2::*;
 use frame_system::RawOrigin;
-use sp_core::{sr25519::Public, H256};
-use sp_runtime::traits::BlakeTwo256;
+use frame_support::traits::Hooks;
+use sp_core::{crypto::KeyTypeId, H256};
+use frame_system::pallet_prelude::{BlockNumberFor, HeaderFor};
+use sp_runtime::traits::{BlakeTwo256, Header as _, Zero};
 
+/// Size of the UTXO set the benchmarks run against, so storage access is measured at a realistic
+/// trie depth rather than against an almost empty trie. The benchmark test suite only checks that
+/// the benchmarks run, so it makes do with a small set.
+#[cfg(not(test))]
+const POPULATED_UTXOS: u32 = 1_000_000;
+#[cfg(test)]
+const POPULATED_UTXOS: u32 = 100;
 
-const SEED: u32 = 0;
+/// Upper bound of outputs created in one block that `on_finalize` flushes into the UTXO set.
+const MAX_BLOCK_OUTPUTS: u32 = 2_000;
+
+/// Upper bound of authors with pending rewards, reached with a `RewardSmoothing` of as many blocks.
+const MAX_PENDING_REWARDS: u32 = 100;
+
+const KEY_TYPE: KeyTypeId = KeyTypeId(*b"utxo");
+
+/// Fill the UTXO set with `count` unrelated outputs.
+fn populate_utxo_set<T: Config>(count: u32) {
+    for n in 0..count {
+        let utxo = TransactionOutput { value: 1, pubkey: H256::zero(), memo: None };
+        UtxoStore::<T>::insert(BlakeTwo256::hash_of(&(b"filler", n)), utxo);
+    }
+    UtxoTotalValue::<T>::mutate(|total| *total = total.saturating_add(count as Value));
+}
+
+/// A transaction spending `i` fresh UTXOs into `o` outputs, with every input signed.
+fn signed_transaction<T: Config>(i: u32, o: u32) -> Transaction {
+    let signer = sp_io::crypto::sr25519_generate(KEY_TYPE, None);
+    let pub_key = H256::from_slice(signer.as_ref());
+
+    let mut inputs = Vec::new();
+    let value_per_utxo = 100;
+    for _ in 0..i {
+        let hash = create_funded_utxo::<T>(value_per_utxo, pub_key);
+        inputs.push((hash, H512::zero()));
+    }
+    UtxoTotalValue::<T>::mutate(|total| *total = total.saturating_add(i as Value * value_per_utxo));
+
+    let mut outputs = Vec::new();
+    let value_per_output = (i as u128 * value_per_utxo) / (o as u128);
+    for n in 0..o {
+        outputs.push((value_per_output, BlakeTwo256::hash_of(&(b"output", n))));
+    }
+
+    let mut transaction = create_test_transaction(inputs, outputs);
+    let signature = sp_io::crypto::sr25519_sign(KEY_TYPE, &signer, &transaction.signing_payload())
+        .expect("key was generated in the keystore above; qed");
+    // One signature covers every input, so `spend` measures the UTXO set changes alone and the
+    // signatures checked are weighed by `check_signatures`
+    for input in transaction.inputs.iter_mut() {
+        input.sigscript = None;
+    }
+    transaction.inputs[0].sigscript = Some(H512::from_slice(signature.as_ref()));
+    transaction
+}
 
 fn assert_last_event<T: Config>(generic_event: Event<T>) {
     frame_system::Pallet::<T>::assert_last_event(generic_event.into());
 }
 
+/// An unspent output with a memo, so spending it also updates the memo index.
 fn create_funded_utxo<T: Config>(value: Value, pubkey: H256) -> H256 {
-    let utxo = TransactionOutput { value, pubkey };
+    let memo = H256::zero();
+    let utxo = TransactionOutput { value, pubkey, memo: Some(memo) };
     let hash = BlakeTwo256::hash_of(&utxo);
     UtxoStore::<T>::insert(hash, utxo);
+    MemoOutputs::<T>::insert(memo, hash, ());
     hash
 }
 
-benchmarks! {
-    spend {
-        let i in 1 .. MAX_TRANSACTION_PARTS as u32;
-        let o in 1 .. MAX_TRANSACTION_PARTS as u32;
-        
-        let caller: T::AccountId = whitelisted_caller();
-        let pub_key = H256::random();
-        
-        // Create input UTXOs
-        let mut inputs = Vec::new();
-        let value_per_utxo = 100;
-        for _ in 0..i {
-            let hash = create_funded_utxo::<T>(value_per_utxo, pub_key);
-            inputs.push((hash, H512::zero()));
+/// Most keys one `OP_CHECKMULTISIGVERIFY` pushed by a script can check within the configured
+/// limits, with the signatures on the witness stack.
+fn max_multisig_keys<T: Config>() -> u32 {
+    let limits = Pallet::<T>::script_limits();
+    // Each key takes a 33-byte push, and the stack holds it along with its signature
+    let by_size = limits.max_script_size.saturating_sub(8) / 33;
+    let by_depth = limits.max_stack_depth.saturating_sub(2) / 2;
+    by_size.min(by_depth).min(limits.max_ops.saturating_sub(1))
+}
+
+#[benchmarks]
+mod benchmarks {
+    use super::*;
+
+    #[benchmark]
+    fn spend(
+        i: Linear<1, MAX_TRANSACTION_PARTS>,
+        o: Linear<1, MAX_TRANSACTION_PARTS>,
+    ) -> Result<(), BenchmarkError> {
+        populate_utxo_set::<T>(POPULATED_UTXOS);
+        let transaction = signed_transaction::<T>(i, o);
+        let (_, fee) = Pallet::<T>::check_transaction(&transaction)?;
+
+        #[extrinsic_call]
+        _(RawOrigin::None, transaction.clone());
+
+        let output_total = transaction.output_value();
+        let input_total = output_total + fee;
+        assert_last_event::<T>(Event::TransactionSuccess { transaction, input_total, output_total, fee });
+        Ok(())
+    }
+
+    #[benchmark]
+    fn mint(o: Linear<1, MAX_TRANSACTION_PARTS>) -> Result<(), BenchmarkError> {
+        populate_utxo_set::<T>(POPULATED_UTXOS);
+        let outputs: BoundedVec<_, _> = (0..o)
+            .map(|n| TransactionOutput { value: 1, pubkey: BlakeTwo256::hash_of(&(b"mint", n)), memo: None })
+            .collect::<Vec<_>>()
+            .try_into()
+            .expect("at most MAX_TRANSACTION_PARTS outputs; qed");
+        let origin = T::MintOrigin::try_successful_origin()
+            .map_err(|_| BenchmarkError::Weightless)?;
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, outputs);
+
+        assert_eq!(BlockOutputs::<T>::iter().count(), o as usize);
+        Ok(())
+    }
+
+    #[benchmark]
+    fn on_finalize(b: Linear<1, MAX_BLOCK_OUTPUTS>, p: Linear<0, MAX_PENDING_REWARDS>) {
+        populate_utxo_set::<T>(POPULATED_UTXOS);
+        for n in 0..b {
+            let output = TransactionOutput { value: 1, pubkey: H256::zero(), memo: Some(H256::zero()) };
+            BlockOutputs::<T>::insert(BlakeTwo256::hash_of(&(b"block", n)), output);
+        }
+        // Authors still owed several slices, so every entry is paid and written back
+        for n in 0..p {
+            let pending = BoundedVec::truncate_from(alloc::vec![PendingReward { balance: 1_000, blocks: 10 }]);
+            PendingRewards::<T>::insert(BlakeTwo256::hash_of(&(b"author", n)), pending);
+        }
+        PendingRewardTotal::<T>::put(Value::from(p) * 1_000);
+        let block_number = frame_system::Pallet::<T>::block_number();
+
+        #[block]
+        {
+            Pallet::<T>::on_finalize(block_number);
+        }
+
+        assert_eq!(BlockOutputs::<T>::iter().count(), 0);
+        assert_eq!(PendingRewardTotal::<T>::get(), Value::from(p) * 900);
+    }
+
+    // Worst case per byte: back-to-back signature checks, each `<sig> <pubkey> OP_CHECKSIGVERIFY`
+    // taking 99 bytes, with the stack and op limits at their configured maximum.
+    #[benchmark]
+    fn execute_script(s: Linear<0, { T::MaxScriptSize::get() }>) {
+        let signer = sp_io::crypto::sr25519_generate(KEY_TYPE, None);
+        let payload = b"benchmark payload".to_vec();
+        let signature = sp_io::crypto::sr25519_sign(KEY_TYPE, &signer, &payload)
+            .expect("key was generated in the keystore above; qed");
+        let limits = Pallet::<T>::script_limits();
+        let checks = (s / 99).min(limits.max_ops);
+
+        let mut builder = script::ScriptBuilder::new();
+        for _ in 0..checks {
+            builder = builder
+                .push_data(signature.as_ref())
+                .push_data(signer.as_ref())
+                .push_opcode(script::OP_CHECKSIGVERIFY);
+        }
+        let script = builder.push_opcode(script::OP_1).into_script();
+        let context = ScriptContext { payload: &payload, block_number: 0 };
+
+        #[block]
+        {
+            script::execute(&script, Vec::new(), &context, &limits).expect("all signatures are valid; qed");
+        }
+    }
+
+    // A `c`-of-`c` multisig with every signature valid, so each key is checked once. The script
+    // bytes are paid by `execute_script`; this covers the checks they do not, as keys and
+    // signatures may come from the witness stack.
+    #[benchmark]
+    fn check_signatures(c: Linear<0, { max_multisig_keys::<T>() }>) {
+        let signer = sp_io::crypto::sr25519_generate(KEY_TYPE, None);
+        let payload = b"benchmark payload".to_vec();
+        let signature = sp_io::crypto::sr25519_sign(KEY_TYPE, &signer, &payload)
+            .expect("key was generated in the keystore above; qed");
+        let limits = Pallet::<T>::script_limits();
+
+        let mut builder = script::ScriptBuilder::new().push_int(c.into());
+        for _ in 0..c {
+            builder = builder.push_data(signer.as_ref());
         }
-        
-        // Create output definitions
-        let mut outputs = Vec::new();
-        let value_per_output = (i as u128 * value_per_utxo) / (o as u128);
-        for _ in 0..o {
-            outputs.push((value_per_output, H256::random()));
+        let script = builder
+            .push_int(c.into())
+            .push_opcode(script::OP_CHECKMULTISIGVERIFY)
+            .push_opcode(script::OP_1)
+            .into_script();
+        assert_eq!(script::signature_checks(&script, limits.max_ops), c);
+        let stack = alloc::vec![signature.as_ref().to_vec(); c as usize];
+        let context = ScriptContext { payload: &payload, block_number: 0 };
+
+        #[block]
+        {
+            script::execute(&script, stack, &context, &limits).expect("all signatures are valid; qed");
+        }
+    }
+
+    // Every reference is a valid uncle at the oldest height still allowed, so each one is fully
+    // checked and paid.
+    #[benchmark]
+    fn reward_uncles(u: Linear<0, { T::MaxUncles::get() }>) {
+        let n: BlockNumberFor<T> = (T::MaxUncleDepth::get() + 1).into();
+        let number: BlockNumberFor<T> = 1u32.into();
+        let parent = T::Hashing::hash_of(b"parent");
+        frame_system::BlockHash::<T>::insert(BlockNumberFor::<T>::zero(), parent);
+        for i in 0..u {
+            let mut header = HeaderFor::<T>::new(
+                number,
+                Default::default(),
+                T::Hashing::hash_of(&i),
+                parent,
+                Default::default(),
+            );
+            T::UncleAuthor::seal(&mut header);
+            frame_system::Pallet::<T>::deposit_log(uncle_digest(&header));
+        }
+
+        #[block]
+        {
+            Pallet::<T>::on_initialize(n);
         }
-        
-        let transaction = create_test_transaction(inputs, outputs);
 
-    }: _(RawOrigin::Signed(caller), transaction.clone())
-    verify {
-        assert_last_event::<T>(Event::TransactionSuccess { transaction }.into());
+        assert_eq!(RewardedUncles::<T>::iter_prefix(number).count(), u as usize);
     }
 
     impl_benchmark_test_suite!(Pallet, crate::mock::new_test_ext(), crate::mock::Test);
@@ -56,12 +242,14 @@ benchmarks! {
 
 fn create_test_transaction(inputs: Vec<(H256, H512)>, outputs: Vec<(Value, H256)>) -> Transaction {
     Transaction {
+        version: TRANSACTION_VERSION,
         inputs: BoundedVec::try_from(
             inputs
                 .into_iter()
                 .map(|(outpoint, sigscript)| TransactionInput {
                     outpoint,
-                    sigscript,
+                    sigscript: Some(sigscript),
+                    witness: None,
                 })
                 .collect::<Vec<_>>(),
         )
@@ -69,7 +257,7 @@ fn create_test_transaction(inputs: Vec<(H256, H512)>, outputs: Vec<(Value, H256)
         outputs: BoundedVec::try_from(
             outputs
                 .into_iter()
-                .map(|(value, pubkey)| TransactionOutput { value, pubkey })
+                .map(|(value, pubkey)| TransactionOutput { value, pubkey, memo: None })
                 .collect::<Vec<_>>(),
         )
         .unwrap(),
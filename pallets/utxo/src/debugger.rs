@@ -0,0 +1,51 @@
+//! Step-by-step execution of scripts, for script authors to try a spend before funding it.
+//!
+//! [`trace`] runs a script through the same interpreter as the pallet, recording the stack after
+//! each instruction, so a failing spend shows where it went wrong.
+
+use crate::script::{
+	assemble, execute_observed, instruction_at, ScriptContext, ScriptError, ScriptLimits,
+};
+
+/// The state of a script after one of its instructions.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Step {
+	/// Position of the instruction in the script
+	pub offset: usize,
+	/// The instruction, in assembly notation
+	pub instruction: String,
+	/// Whether the instruction was in a branch being executed, rather than skipped
+	pub executed: bool,
+	/// The stack after the instruction, bottom first
+	pub stack: Vec<Vec<u8>>,
+}
+
+/// The steps of a script's execution, and how it ended.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Trace {
+	/// The instructions that completed, in order
+	pub steps: Vec<Step>,
+	/// The outcome, as [`crate::script::execute`] would report it. A failure is at the
+	/// instruction after the last step, or at the end of the script once all of them completed.
+	pub result: Result<(), ScriptError>,
+}
+
+/// Run `script` on top of `initial_stack`, recording every step.
+pub fn trace(
+	script: &[u8],
+	initial_stack: Vec<Vec<u8>>,
+	context: &ScriptContext,
+	limits: &ScriptLimits,
+) -> Trace {
+	let mut steps = Vec::new();
+	let result =
+		execute_observed(script, initial_stack, context, limits, |offset, executed, stack| {
+			// The interpreter read the instruction already, so it decodes
+			let instruction = match instruction_at(script, offset) {
+				Ok((instruction, _)) => assemble(instruction),
+				Err(_) => String::new(),
+			};
+			steps.push(Step { offset, instruction, executed, stack: stack.to_vec() });
+		});
+	Trace { steps, result }
+}
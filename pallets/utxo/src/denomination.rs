@@ -0,0 +1,95 @@
+//! The chain's denomination: how a [`Value`] of base units reads as an amount of coins.
+//!
+//! Values are integers of base units everywhere on chain and over RPC; people read and type
+//! amounts of [`SYMBOL`], with up to [`DECIMALS`] decimal places. [`FormattedValue`] and
+//! [`parse_value`] convert between the two without allocating, so the runtime can use them too.
+
+use core::fmt;
+
+use crate::Value;
+
+/// Ticker of the chain's coin.
+pub const SYMBOL: &str = "TUMU";
+
+/// Decimal places of a coin: a value of one base unit is `10^-DECIMALS` of a coin.
+pub const DECIMALS: u32 = 12;
+
+/// The value of one coin, in base units.
+pub const UNIT: Value = 10u128.pow(DECIMALS);
+
+/// Displays a value as an amount of coins, with no trailing zeros after the decimal point and no
+/// point for whole coins, for example `1.5` or `21000000`. The symbol is left to the caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormattedValue(pub Value);
+
+impl fmt::Display for FormattedValue {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let (coins, mut fraction) = (self.0 / UNIT, self.0 % UNIT);
+		write!(f, "{coins}")?;
+		if fraction == 0 {
+			return Ok(())
+		}
+		let mut places = DECIMALS as usize;
+		while fraction % 10 == 0 {
+			fraction /= 10;
+			places -= 1;
+		}
+		write!(f, ".{fraction:0places$}")
+	}
+}
+
+/// The value displayed by [`FormattedValue`], as a string.
+#[cfg(feature = "std")]
+pub fn format_value(value: Value) -> String {
+	FormattedValue(value).to_string()
+}
+
+/// Reasons an amount does not parse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseValueError {
+	/// Not decimal digits with at most one decimal point between them
+	Invalid,
+	/// More decimal places than [`DECIMALS`]
+	TooManyDecimals,
+	/// The value does not fit a [`Value`]
+	Overflow,
+}
+
+impl fmt::Display for ParseValueError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Invalid => write!(f, "expected an amount such as 12 or 0.5"),
+			Self::TooManyDecimals => write!(f, "at most {DECIMALS} decimal places"),
+			Self::Overflow => write!(f, "amount too large"),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseValueError {}
+
+/// The value of an amount of coins such as `12`, `0.5` or `1.000000000001`, in base units.
+pub fn parse_value(amount: &str) -> Result<Value, ParseValueError> {
+	let (coins, fraction) = match amount.split_once('.') {
+		Some((coins, fraction)) => (coins, fraction),
+		None => (amount, "0"),
+	};
+	let is_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+	if !is_digits(coins) || !is_digits(fraction) {
+		return Err(ParseValueError::Invalid)
+	}
+	if fraction.len() > DECIMALS as usize {
+		return Err(ParseValueError::TooManyDecimals)
+	}
+
+	let digits = |s: &str| {
+		s.bytes().try_fold(0 as Value, |value, b| {
+			value.checked_mul(10)?.checked_add(Value::from(b - b'0'))
+		})
+	};
+	let fraction_places = DECIMALS - fraction.len() as u32;
+	digits(coins)
+		.and_then(|coins| coins.checked_mul(UNIT))
+		.and_then(|coins| coins.checked_add(digits(fraction)? * 10u128.pow(fraction_places)))
+		.ok_or(ParseValueError::Overflow)
+}
@@ -0,0 +1,42 @@
+//! Deposit address derivation for custodians.
+//!
+//! A custodian holds a single sr25519 root key and hands out one deposit pubkey per user. Deposit
+//! keys are derived along the soft path `/<account>/<index>`, so the full set of deposit pubkeys
+//! can be generated from the root *public* key alone (watch-only), while only the holder of the
+//! root secret can sign for any of them.
+
+use sp_core::{
+	crypto::{Derive, DeriveJunction},
+	sr25519, H256,
+};
+
+/// The soft derivation path of the deposit key for (`account`, `index`).
+pub fn deposit_path(account: u32, index: u32) -> [DeriveJunction; 2] {
+	[DeriveJunction::soft(account), DeriveJunction::soft(index)]
+}
+
+/// Derives the deposit pubkey for (`account`, `index`) from the custodian's root public key.
+pub fn deposit_pubkey(root: &sr25519::Public, account: u32, index: u32) -> Option<H256> {
+	root.derive(deposit_path(account, index).into_iter())
+		.map(|public| H256::from_slice(public.as_ref()))
+}
+
+/// Derives the deposit keypair for (`account`, `index`) from the custodian's root keypair.
+pub fn deposit_pair(root: &sr25519::Pair, account: u32, index: u32) -> sr25519::Pair {
+	use sp_core::Pair;
+	root.derive(deposit_path(account, index).into_iter(), None)
+		.expect("soft derivation of an sr25519 pair never fails; qed")
+		.0
+}
+
+/// Maps a deposit pubkey back to its index by scanning `indices` for the given `account`.
+pub fn find_deposit_index(
+	root: &sr25519::Public,
+	account: u32,
+	pubkey: &H256,
+	indices: impl IntoIterator<Item = u32>,
+) -> Option<u32> {
+	indices
+		.into_iter()
+		.find(|index| deposit_pubkey(root, account, *index).as_ref() == Some(pubkey))
+}
@@ -0,0 +1,278 @@
+//! Output descriptors: strings describing who can spend an output, without any secret.
+//!
+//! Watch-only wallets and indexers are told what to watch with a descriptor instead of keys.
+//! Each descriptor determines the `pubkey` of the outputs it covers, and for script outputs the
+//! script a spender has to reveal:
+//!
+//! - `pk(KEY)`: outputs paying `KEY` directly;
+//! - `multi(K,KEY_1,...,KEY_N)`: `K`-of-`N` multisig, signatures in key order;
+//! - `htlc(RECIPIENT,SENDER,HASH,TIMEOUT)`: spendable by `RECIPIENT` with the SHA-256 preimage of
+//!   `HASH`, or by `SENDER` from block `TIMEOUT` on;
+//! - `script(HEX)`: any other script, given in full.
+//!
+//! Keys are 0x-prefixed hex or SS58, hashes and scripts 0x-prefixed hex. Descriptors are
+//! displayed in canonical form, with keys in hex and no whitespace, so they can be compared as
+//! strings.
+
+use core::{fmt, str::FromStr};
+
+use sp_core::{crypto::Ss58Codec, sr25519, H256};
+
+use crate::{
+	script::{self, Instruction, ScriptBuilder},
+	TransactionOutput,
+};
+
+/// Largest number of keys in a `multi` descriptor.
+pub const MAX_MULTI_KEYS: usize = 20;
+
+/// The ownership of an output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Descriptor {
+	/// Paid to a single key
+	Pk(H256),
+	/// Spendable with signatures of `threshold` of `keys`
+	Multi { threshold: u32, keys: Vec<H256> },
+	/// Hash time-locked: the recipient claims with the preimage of `hash`, the sender takes the
+	/// funds back once block `timeout` is reached
+	Htlc { recipient: H256, sender: H256, hash: H256, timeout: u64 },
+	/// Locked to an arbitrary script
+	Script(Vec<u8>),
+}
+
+/// Reasons a descriptor string does not parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DescriptorError {
+	/// Not of the form `name(arguments)`
+	Syntax,
+	/// No descriptor of this name exists
+	UnknownDescriptor(String),
+	/// The descriptor takes a different number of arguments
+	WrongArgumentCount,
+	/// An argument is neither a hex nor an SS58 public key
+	InvalidKey(String),
+	/// An argument is not 0x-prefixed hex of the expected length
+	InvalidHex(String),
+	/// An argument is not a decimal number
+	InvalidNumber(String),
+	/// The multisig threshold is zero or above the number of keys, or there are too many keys
+	InvalidThreshold,
+	/// The script is longer than [`script::MAX_SCRIPT_SIZE`]
+	ScriptTooLarge,
+}
+
+impl fmt::Display for DescriptorError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Syntax => write!(f, "expected name(arguments)"),
+			Self::UnknownDescriptor(name) => write!(f, "unknown descriptor {}", name),
+			Self::WrongArgumentCount => write!(f, "wrong number of arguments"),
+			Self::InvalidKey(arg) => write!(f, "invalid public key {}", arg),
+			Self::InvalidHex(arg) => write!(f, "invalid hex {}", arg),
+			Self::InvalidNumber(arg) => write!(f, "invalid number {}", arg),
+			Self::InvalidThreshold =>
+				write!(f, "threshold must be between 1 and at most {} keys", MAX_MULTI_KEYS),
+			Self::ScriptTooLarge => write!(f, "script exceeds {} bytes", script::MAX_SCRIPT_SIZE),
+		}
+	}
+}
+
+impl std::error::Error for DescriptorError {}
+
+impl Descriptor {
+	/// The script a spender has to reveal, or `None` for outputs paying a key directly.
+	pub fn script(&self) -> Option<Vec<u8>> {
+		match self {
+			Self::Pk(_) => None,
+			Self::Multi { threshold, keys } => {
+				let builder = keys
+					.iter()
+					.fold(ScriptBuilder::new().push_int(u64::from(*threshold)), |builder, key| {
+						builder.push_key(key)
+					});
+				Some(
+					builder
+						.push_int(keys.len() as u64)
+						.push_opcode(script::OP_CHECKMULTISIG)
+						.into_script(),
+				)
+			},
+			Self::Htlc { recipient, sender, hash, timeout } => Some(
+				ScriptBuilder::new()
+					.push_opcode(script::OP_IF)
+					.push_opcode(script::OP_SHA256)
+					.push_data(hash.as_bytes())
+					.push_opcode(script::OP_EQUALVERIFY)
+					.push_key(recipient)
+					.push_opcode(script::OP_CHECKSIG)
+					.push_opcode(script::OP_ELSE)
+					.push_int(*timeout)
+					.push_opcode(script::OP_CHECKLOCKTIMEVERIFY)
+					.push_opcode(script::OP_DROP)
+					.push_key(sender)
+					.push_opcode(script::OP_CHECKSIG)
+					.push_opcode(script::OP_ENDIF)
+					.into_script(),
+			),
+			Self::Script(script) => Some(script.clone()),
+		}
+	}
+
+	/// The `pubkey` of the outputs this descriptor covers.
+	pub fn pubkey(&self) -> H256 {
+		match self {
+			Self::Pk(key) => *key,
+			_ => script::script_hash(&self.script().unwrap_or_default()),
+		}
+	}
+
+	/// Whether `output` is owned as described.
+	pub fn matches(&self, output: &TransactionOutput) -> bool {
+		output.pubkey == self.pubkey()
+	}
+
+	/// Recognise the `multi` or `htlc` descriptor `script` was built from, falling back to a
+	/// `script` descriptor.
+	pub fn from_script(script: &[u8]) -> Self {
+		Self::from_template(script)
+			.filter(|descriptor| descriptor.script().as_deref() == Some(script))
+			.unwrap_or_else(|| Self::Script(script.to_vec()))
+	}
+
+	/// The descriptor whose script has the same shape as `script`, which may still differ in
+	/// push encodings.
+	fn from_template(script: &[u8]) -> Option<Self> {
+		use script::Instruction::{Op, Push};
+
+		let key = |data: &[u8]| <[u8; 32]>::try_from(data).ok().map(H256);
+		let number = |instruction: &Instruction| match instruction {
+			Op(opcode @ script::OP_1..=script::OP_16) => Some(u64::from(opcode - script::OP_1 + 1)),
+			Push(data) if data.len() <= 8 => {
+				let mut bytes = [0u8; 8];
+				bytes[..data.len()].copy_from_slice(data);
+				Some(u64::from_le_bytes(bytes))
+			},
+			_ => None,
+		};
+
+		match script::instructions(script).ok()?.as_slice() {
+			[threshold, keys @ .., count, Op(script::OP_CHECKMULTISIG)] => {
+				let keys = keys
+					.iter()
+					.map(|instruction| match instruction {
+						Push(data) => key(data),
+						_ => None,
+					})
+					.collect::<Option<Vec<_>>>()?;
+				let threshold = u32::try_from(number(threshold)?).ok()?;
+				let valid = threshold > 0 &&
+					threshold as usize <= keys.len() &&
+					keys.len() <= MAX_MULTI_KEYS &&
+					number(count)? == keys.len() as u64;
+				valid.then_some(Self::Multi { threshold, keys })
+			},
+			[
+				Op(script::OP_IF),
+				Op(script::OP_SHA256),
+				Push(hash),
+				Op(script::OP_EQUALVERIFY),
+				Push(recipient),
+				Op(script::OP_CHECKSIG),
+				Op(script::OP_ELSE),
+				timeout,
+				Op(script::OP_CHECKLOCKTIMEVERIFY),
+				Op(script::OP_DROP),
+				Push(sender),
+				Op(script::OP_CHECKSIG),
+				Op(script::OP_ENDIF),
+			] => Some(Self::Htlc {
+				recipient: key(recipient)?,
+				sender: key(sender)?,
+				hash: key(hash)?,
+				timeout: number(timeout)?,
+			}),
+			_ => None,
+		}
+	}
+}
+
+fn parse_key(arg: &str) -> Result<H256, DescriptorError> {
+	if arg.starts_with("0x") {
+		return parse_hash(arg).map_err(|_| DescriptorError::InvalidKey(arg.into()))
+	}
+	sr25519::Public::from_ss58check(arg)
+		.map(|key| H256(key.0))
+		.map_err(|_| DescriptorError::InvalidKey(arg.into()))
+}
+
+fn parse_hex(arg: &str) -> Result<Vec<u8>, DescriptorError> {
+	arg.strip_prefix("0x")
+		.and_then(|hex| sp_core::bytes::from_hex(hex).ok())
+		.ok_or_else(|| DescriptorError::InvalidHex(arg.into()))
+}
+
+fn parse_hash(arg: &str) -> Result<H256, DescriptorError> {
+	let bytes = parse_hex(arg)?;
+	<[u8; 32]>::try_from(bytes)
+		.map(H256)
+		.map_err(|_| DescriptorError::InvalidHex(arg.into()))
+}
+
+fn parse_number<N: FromStr>(arg: &str) -> Result<N, DescriptorError> {
+	arg.parse().map_err(|_| DescriptorError::InvalidNumber(arg.into()))
+}
+
+impl FromStr for Descriptor {
+	type Err = DescriptorError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (name, rest) = s.trim().split_once('(').ok_or(DescriptorError::Syntax)?;
+		let body = rest.strip_suffix(')').ok_or(DescriptorError::Syntax)?;
+		let args: Vec<&str> = body.split(',').map(str::trim).collect();
+
+		match (name.trim(), args.as_slice()) {
+			("pk", [key]) => Ok(Self::Pk(parse_key(key)?)),
+			("multi", [threshold, keys @ ..]) => {
+				let threshold = parse_number(threshold)?;
+				let keys = keys.iter().map(|key| parse_key(key)).collect::<Result<Vec<_>, _>>()?;
+				if threshold == 0 || threshold as usize > keys.len() || keys.len() > MAX_MULTI_KEYS {
+					return Err(DescriptorError::InvalidThreshold)
+				}
+				Ok(Self::Multi { threshold, keys })
+			},
+			("htlc", [recipient, sender, hash, timeout]) => Ok(Self::Htlc {
+				recipient: parse_key(recipient)?,
+				sender: parse_key(sender)?,
+				hash: parse_hash(hash)?,
+				timeout: parse_number(timeout)?,
+			}),
+			("script", [hex]) => {
+				let script = parse_hex(hex)?;
+				if script.len() > script::MAX_SCRIPT_SIZE as usize {
+					return Err(DescriptorError::ScriptTooLarge)
+				}
+				Ok(Self::Script(script))
+			},
+			("pk" | "multi" | "htlc" | "script", _) => Err(DescriptorError::WrongArgumentCount),
+			(name, _) => Err(DescriptorError::UnknownDescriptor(name.into())),
+		}
+	}
+}
+
+impl fmt::Display for Descriptor {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Pk(key) => write!(f, "pk({:?})", key),
+			Self::Multi { threshold, keys } => {
+				write!(f, "multi({}", threshold)?;
+				for key in keys {
+					write!(f, ",{:?}", key)?;
+				}
+				write!(f, ")")
+			},
+			Self::Htlc { recipient, sender, hash, timeout } =>
+				write!(f, "htlc({:?},{:?},{:?},{})", recipient, sender, hash, timeout),
+			Self::Script(script) => write!(f, "script({})", sp_core::bytes::to_hex(script, false)),
+		}
+	}
+}
@@ -60,10 +60,149 @@ mod benchmarking;
 pub mod weights;
 pub use weights::*;
 
-pub type Value = u128;
+// Custodian deposit key derivation. Relies on sr25519 soft derivation, which is only available
+// with `std`.
+#[cfg(feature = "std")]
+pub mod derivation;
 
-/// Maximum number of inputs or outputs in a transaction
-pub const MAX_TRANSACTION_PARTS: u32 = 100;
+// MuSig2 key aggregation and signing rounds for multi-party custody. Off-chain only: the result
+// is a plain sr25519 key and signature.
+#[cfg(feature = "std")]
+pub mod musig;
+
+// Output descriptors telling watch-only tooling what to watch. Off-chain only.
+#[cfg(feature = "std")]
+pub mod descriptor;
+
+// Step-by-step script execution for script authors. Off-chain only.
+#[cfg(feature = "std")]
+pub mod debugger;
+
+// Helpers for vaults: deposits behind a pre-signed unvault transaction and a recovery key.
+#[cfg(feature = "std")]
+pub mod vault;
+
+// The coin's symbol and decimal places, and conversions between values and amounts of coins.
+pub mod denomination;
+
+pub mod script;
+
+pub mod migrations;
+
+extern crate alloc;
+
+use alloc::{
+	collections::{BTreeMap, BTreeSet},
+	vec::Vec,
+};
+use script::{ScriptContext, ScriptError, ScriptLimits};
+use sp_core::{
+	sr25519::{Public, Signature},
+	H256,
+};
+use codec::Encode;
+use sp_runtime::{
+	traits::{BlakeTwo256, CheckedSub, Hash, Header as _, SaturatedConversion, Saturating, Zero},
+	ConsensusEngineId, DigestItem, ModuleError, Perbill,
+};
+
+pub use utxo_primitives::{
+	is_canonical_signature, Transaction, TransactionBuilder, TransactionInput, TransactionOutput,
+	TransactionVersion, Value, MAX_TRANSACTION_PARTS, TRANSACTION_VERSION, WITNESS_SCALE_FACTOR,
+};
+
+/// Most bytes of state an unspent output takes: the key and value of its [`UtxoStore`] entry,
+/// and of its [`MemoOutputs`] entry when it carries a memo, each with the 32 bytes of its pallet
+/// and item prefix. Fee and dust parameters pricing the growth of the UTXO set can be derived
+/// from it; the tests check it against the storage info of the pallet.
+pub const MAX_UTXO_STORAGE_BYTES: u32 = 257;
+
+/// Most blocks [`Config::RewardSmoothing`] may spread a reward over, bounding the credits in
+/// [`PendingRewards`].
+pub const MAX_REWARD_SMOOTHING: u32 = 1_000;
+
+/// Target of the pallet's tracing spans, as given to `--tracing-targets`. The runtime only
+/// forwards them to the node when built with the `with-tracing` feature.
+pub const LOG_TARGET: &str = "runtime::utxo";
+
+/// A source for the author of the block being built, who is paid the block reward.
+pub trait BlockAuthor {
+	fn block_author() -> Option<sp_core::sr25519::Public>;
+}
+
+/// Engine id of the pre-runtime digest naming the key the block reward is paid to.
+pub const REWARD_ENGINE_ID: ConsensusEngineId = *b"rwrd";
+
+/// The pre-runtime digest with which a block author has its reward paid to `pubkey` rather
+/// than to the key it authors with.
+pub fn reward_digest(pubkey: &Public) -> DigestItem {
+	DigestItem::PreRuntime(REWARD_ENGINE_ID, pubkey.encode())
+}
+
+/// Pays the block reward of blocks authored according to `A` to the key named by their
+/// [`reward_digest`], if any.
+pub struct RewardAddressOr<T, A>(core::marker::PhantomData<(T, A)>);
+
+impl<T: frame_system::Config, A: BlockAuthor> BlockAuthor for RewardAddressOr<T, A> {
+	fn block_author() -> Option<Public> {
+		let author = A::block_author()?;
+		let reward_address = frame_system::Pallet::<T>::digest()
+			.logs
+			.iter()
+			.find_map(|item| item.pre_runtime_try_to::<Public>(&REWARD_ENGINE_ID));
+		Some(reward_address.unwrap_or(author))
+	}
+}
+
+/// Engine id of the pre-runtime digests referencing competing block headers.
+pub const UNCLE_ENGINE_ID: ConsensusEngineId = *b"uncl";
+
+/// The pre-runtime digest with which a block author references the competing block `header`,
+/// whose author is then paid an uncle reward.
+pub fn uncle_digest<Header: Encode>(header: &Header) -> DigestItem {
+	DigestItem::PreRuntime(UNCLE_ENGINE_ID, header.encode())
+}
+
+/// Recovers the author of a competing block header from its seal.
+pub trait UncleAuthor<Header> {
+	/// The author of `header`, or `None` if it does not carry a valid seal.
+	fn uncle_author(header: &Header) -> Option<sp_core::sr25519::Public>;
+
+	/// Seal `header` so that [`Self::uncle_author`] accepts it, returning its author.
+	#[cfg(feature = "runtime-benchmarks")]
+	fn seal(header: &mut Header) -> sp_core::sr25519::Public;
+}
+
+// No uncle is ever valid, which disables uncle rewards
+impl<Header> UncleAuthor<Header> for () {
+	fn uncle_author(_header: &Header) -> Option<sp_core::sr25519::Public> {
+		None
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn seal(_header: &mut Header) -> sp_core::sr25519::Public {
+		sp_core::sr25519::Public::from_raw([0; 32])
+	}
+}
+
+/// A trait for types that can provide the amount of issuance to award to the block
+/// author for the given block number.
+pub trait Issuance<BlockNumber, Balance> {
+	fn issuance(block: BlockNumber) -> Balance;
+}
+
+// Minimal implementations for when you don't actually want any issuance
+impl Issuance<u32, u128> for () {
+	fn issuance(_block: u32) -> u128 {
+		0
+	}
+}
+
+impl Issuance<u64, u128> for () {
+	fn issuance(_block: u64) -> u128 {
+		0
+	}
+}
 
 // All pallet logic is defined in its own module and must be annotated by the `pallet` attribute.
 #[frame_support::pallet]
@@ -72,10 +211,15 @@ pub mod pallet {
 	use super::*;
 	use frame_support::pallet_prelude::*;
 	use frame_system::pallet_prelude::*;
+	use serde::{Deserialize, Serialize};
 
 	// The `Pallet` struct serves as a placeholder to implement traits, methods and dispatchables
 	// (`Call`s) in this pallet.
+	/// The in-code storage version. Version 1 added [`TransactionOutput::memo`].
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
 	#[pallet::pallet]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	/// The pallet's configuration trait.
@@ -92,41 +236,92 @@ pub mod pallet {
         type BlockAuthor: BlockAuthor;
 
         /// A source to determine the issuance portion of the block reward
-        type Issuance: Issuance<<Self as frame_system::Config>::BlockNumber, Value>;
+        type Issuance: Issuance<BlockNumberFor<Self>, Value>;
+
+        /// A type representing the weights required by the dispatchables of this pallet.
+        type WeightInfo: WeightInfo;
 
         #[pallet::constant]
         type MaxTransactionSize: Get<u32>;
-	}
 
-	/// Single transaction to be dispatched
-	#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
-	#[derive(PartialEq, Eq, PartialOrd, Ord, Default, Clone, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
-	pub struct Transaction {
-		/// UTXOs to be used as inputs for current transaction
-		pub inputs: BoundedVec<TransactionInput, ConstU32<MAX_TRANSACTION_PARTS>>,
-		/// UTXOs to be created as a result of current transaction dispatch
-		pub outputs: BoundedVec<TransactionOutput, ConstU32<MAX_TRANSACTION_PARTS>>,
+        /// Upper bound on the value of any output, any transaction's input or output sum, and
+        /// the whole UTXO set
+        #[pallet::constant]
+        type MaxMoney: Get<Value>;
+
+        /// Origin allowed to create outputs out of thin air with [`Pallet::mint`]
+        type MintOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+        /// Maximum length in bytes of a script revealed by a witness, at most
+        /// [`script::MAX_SCRIPT_SIZE`]
+        #[pallet::constant]
+        type MaxScriptSize: Get<u32>;
+
+        /// Maximum number of opcodes a single script may execute
+        #[pallet::constant]
+        type MaxScriptOps: Get<u32>;
+
+        /// Maximum number of items on a script's stack
+        #[pallet::constant]
+        type MaxStackDepth: Get<u32>;
+
+        /// Incentive for transactions that shrink the UTXO set. For every input beyond the number
+        /// of outputs, this fraction of the per-input weight is waived and this fraction of the
+        /// fee is added to the pool priority.
+        #[pallet::constant]
+        type ConsolidationDiscount: Get<Perbill>;
+
+        /// Number of blocks a block reward is paid out over, in equal slices starting with the
+        /// next block, so that a reorg replacing one block only gains a fraction of its reward.
+        /// Zero pays the whole reward in the block itself. At most [`MAX_REWARD_SMOOTHING`].
+        #[pallet::constant]
+        type RewardSmoothing: Get<u32>;
+
+        /// Validates the seals of competing headers referenced with [`uncle_digest`]
+        type UncleAuthor: UncleAuthor<HeaderFor<Self>>;
+
+        /// Fraction of the issuance at its height paid to the author of a referenced uncle
+        #[pallet::constant]
+        type UncleReward: Get<Perbill>;
+
+        /// How many blocks back an uncle's parent may be. An uncle at height `n` can be
+        /// referenced up to block `n + MaxUncleDepth`.
+        #[pallet::constant]
+        type MaxUncleDepth: Get<u32>;
+
+        /// Maximum number of uncle references looked at per block; any beyond are ignored
+        #[pallet::constant]
+        type MaxUncles: Get<u32>;
+
+        /// First block whose transactions must be in canonical order, see
+        /// [`Transaction::sort_canonically`]. Only restricting what is valid, the rule is a soft
+        /// fork: set to a future block by a runtime upgrade miners approve, it leaves the blocks
+        /// before it valid. `BlockNumber::MAX` never enforces it.
+        #[pallet::constant]
+        type CanonicalOrderingFrom: Get<BlockNumberFor<Self>>;
 	}
 
-    /// Single transaction input that refers to one UTXO
-    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
-    #[derive(PartialEq, Eq, PartialOrd, Ord, Default, Clone, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
-    pub struct TransactionInput {
-        /// Reference to an UTXO to be spent
-        pub outpoint: H256,
-        /// Proof that transaction owner is authorized to spend referred UTXO &
-        /// that the entire transaction is untampered
-        pub sigscript: H512,
+    /// A block reward credited to an author under [`Config::RewardSmoothing`] and not paid out
+    /// yet.
+    #[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    pub struct PendingReward {
+        /// Value still to be paid out
+        pub balance: Value,
+        /// Number of blocks left to pay it out over
+        pub blocks: u32,
     }
 
-    /// Single transaction output to create upon transaction dispatch
-    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
-    #[derive(PartialEq, Eq, PartialOrd, Ord, Default, Clone, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
-    pub struct TransactionOutput {
-        /// Value associated with this output
-        pub value: Value,
-        /// Public key associated with this output
-        pub pubkey: H256,
+    /// What an author has mined, as kept in [`AuthorStats`].
+    #[derive(Serialize, Deserialize)]
+    #[derive(PartialEq, Eq, Clone, Default, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    pub struct AuthorStatistics {
+        /// Blocks of this chain whose reward went to the author
+        pub blocks_authored: u32,
+        /// Uncles of the author rewarded
+        pub uncles_rewarded: u32,
+        /// Block and uncle rewards earned, including those still being paid out under
+        /// [`Config::RewardSmoothing`]
+        pub rewards_earned: Value,
     }
 
 	/// storage items.
@@ -139,31 +334,120 @@ pub mod pallet {
         OptionQuery
     >;
 
+    /// Outpoints of the outputs in [`UtxoStore`] carrying a memo, by memo.
+    ///
+    /// Memos are chosen by payers, so they are hashed into the key to keep the trie balanced.
+    #[pallet::storage]
+    pub type MemoOutputs<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        H256,
+        Identity,
+        H256,
+        (),
+        OptionQuery
+    >;
+
+    /// Outputs created by transactions earlier in the current block.
+    ///
+    /// Acts as an overlay on top of [`UtxoStore`] so a later transaction in the same block can
+    /// spend them. Whatever is left unspent is flushed into [`UtxoStore`] in `on_finalize`, which
+    /// also spares outputs created and spent within one block from ever touching the UTXO set.
+    #[pallet::storage]
+    pub type BlockOutputs<T: Config> = StorageMap<
+        _,
+        Identity,
+        H256,
+        TransactionOutput,
+        OptionQuery
+    >;
+
+    /// Sum of the values of all unspent outputs, including [`BlockOutputs`].
+    #[pallet::storage]
+    #[pallet::getter(fn utxo_total_value)]
+    pub type UtxoTotalValue<T: Config> = StorageValue<_, Value, ValueQuery>;
+
+    /// Total value ever created, by block issuance and by [`Pallet::mint`].
+    #[pallet::storage]
+    #[pallet::getter(fn total_issuance)]
+    pub type TotalIssuance<T: Config> = StorageValue<_, Value, ValueQuery>;
+
     #[pallet::storage]
     #[pallet::getter(fn reward_total)]
     pub type RewardTotal<T: Config> = StorageValue<_, Value, ValueQuery>;
 
+    /// Block rewards credited to authors and not paid out yet, by author pubkey, oldest first.
+    ///
+    /// Each credit is paid out over the [`Config::RewardSmoothing`] blocks following it, whatever
+    /// the author is credited since. One author is credited per block, so there are never more
+    /// credits in all, or authors, than that.
+    #[pallet::storage]
+    pub type PendingRewards<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        H256,
+        BoundedVec<PendingReward, ConstU32<MAX_REWARD_SMOOTHING>>,
+        OptionQuery
+    >;
+
+    /// Sum of the balances in [`PendingRewards`], which count towards `MaxMoney` but are not part
+    /// of [`UtxoTotalValue`] yet.
+    #[pallet::storage]
+    #[pallet::getter(fn pending_reward_total)]
+    pub type PendingRewardTotal<T: Config> = StorageValue<_, Value, ValueQuery>;
+
+    /// Uncles already rewarded, by height and hash.
+    ///
+    /// Heights drop out once they are more than [`Config::MaxUncleDepth`] blocks behind, as their
+    /// uncles can no longer be referenced then.
+    #[pallet::storage]
+    pub type RewardedUncles<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        BlockNumberFor<T>,
+        Identity,
+        T::Hash,
+        (),
+        OptionQuery
+    >;
+
+    /// What each author has mined since genesis, by the pubkey the rewards were paid to.
+    ///
+    /// Authors redirecting their rewards with a [`crate::reward_digest`] are counted under the key they
+    /// redirect to. Entries are never removed, so this grows with the number of reward keys ever
+    /// used; it is read off-chain only.
+    #[pallet::storage]
+    pub type AuthorStats<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        H256,
+        AuthorStatistics,
+        ValueQuery
+    >;
+
 	#[pallet::genesis_config]
-    pub struct GenesisConfig {
+    #[derive(frame_support::DefaultNoBound)]
+    pub struct GenesisConfig<T: Config> {
         pub genesis_utxos: Vec<TransactionOutput>,
-    }
-
-    #[cfg(feature = "std")]
-    impl Default for GenesisConfig {
-        fn default() -> Self {
-            Self {
-                genesis_utxos: Default::default(),
-            }
-        }
+        #[serde(skip)]
+        pub _marker: PhantomData<T>,
     }
 
     #[pallet::genesis_build]
-    impl<T: Config> GenesisBuild<T> for GenesisConfig {
+    impl<T: Config> BuildGenesisConfig for GenesisConfig<T> {
         fn build(&self) {
+            let mut total: Value = 0;
             for utxo in &self.genesis_utxos {
                 let hash = BlakeTwo256::hash_of(utxo);
+                if let Some(memo) = utxo.memo {
+                    <MemoOutputs<T>>::insert(memo, hash, ());
+                }
                 <UtxoStore<T>>::insert(hash, utxo);
+                total = total.checked_add(utxo.value).expect("genesis UTXO total overflows");
             }
+            assert!(total <= T::MaxMoney::get(), "genesis UTXOs exceed MaxMoney");
+            <UtxoTotalValue<T>>::put(total);
+            <TotalIssuance<T>>::put(total);
         }
     }
 
@@ -180,12 +464,24 @@ pub mod pallet {
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
 	pub enum Event<T: Config> {
-        /// Transaction was executed successfully
-        TransactionSuccess { transaction: Transaction },
+        /// Transaction was executed successfully. The values it spent and created, and the fee
+        /// left to the block author, let deposits be credited from the event alone.
+        TransactionSuccess { transaction: Transaction, input_total: Value, output_total: Value, fee: Value },
         /// Rewards were issued
         RewardsIssued { amount: Value, utxo_hash: H256 },
         /// Rewards were wasted
         RewardsWasted,
+        /// Outputs were minted by the privileged mint origin
+        Minted { outputs: BoundedVec<TransactionOutput, ConstU32<MAX_TRANSACTION_PARTS>>, total: Value },
+        /// A block reward was credited to its author, to be paid out over the next `blocks` blocks
+        RewardScheduled { author: H256, amount: Value, blocks: u32 },
+        /// A slice of an author's pending rewards was paid out
+        PendingRewardPaid { author: H256, amount: Value, utxo_hash: H256 },
+        /// The author of a competing block referenced by this one was paid an uncle reward
+        UncleRewarded { uncle: T::Hash, author: H256, amount: Value, utxo_hash: H256 },
+        /// An uncle reference was ignored: it is malformed, too old, rewarded before, part of
+        /// this chain or not validly sealed
+        UncleRejected { uncle: T::Hash },
 	}
 
 	/// Errors that can be returned by this pallet.
@@ -222,6 +518,45 @@ pub mod pallet {
         OutputExceedsInput,
         /// Output index overflow
         OutputIndexOverflow,
+        /// An output value or a transaction's input or output sum exceeds `MaxMoney`
+        ExceedsMaxMoney,
+        /// The witness script does not hash to the spent output's pubkey
+        ScriptHashMismatch,
+        /// The witness script is longer than `MaxScriptSize`
+        ScriptTooLarge,
+        /// The witness script executes more than `MaxScriptOps` opcodes
+        TooManyScriptOps,
+        /// The witness script's stack grows past `MaxStackDepth`
+        ScriptStackOverflow,
+        /// The witness script failed or left a false value on the stack
+        ScriptFailed,
+        /// The first input spending outputs of a pubkey, which the later ones rely on, carries no
+        /// sigscript
+        MissingSignature,
+        /// The transaction's version is not one this runtime validates
+        UnsupportedVersion,
+        /// The inputs or outputs are not in canonical order, required from `CanonicalOrderingFrom`
+        NonCanonicalOrder,
+        /// An output is locked to the zero pubkey, which no signature or script can spend
+        UnspendableDestination,
+        /// A signature is not in its canonical encoding, which would let others re-encode it
+        NonCanonicalSignature,
+        /// An input carries both a witness and a sigscript
+        SignatureWithWitness,
+        /// An input repeats the signature of an earlier input spending outputs of the same key
+        RedundantSignature,
+	}
+
+	impl<T> From<ScriptError> for Error<T> {
+		fn from(error: ScriptError) -> Self {
+			match error {
+				ScriptError::ScriptTooLarge => Error::<T>::ScriptTooLarge,
+				ScriptError::TooManyOps => Error::<T>::TooManyScriptOps,
+				ScriptError::StackOverflow => Error::<T>::ScriptStackOverflow,
+				ScriptError::NonCanonicalSignature => Error::<T>::NonCanonicalSignature,
+				_ => Error::<T>::ScriptFailed,
+			}
+		}
 	}
 
 	/// The pallet's dispatchable functions ([`Call`]s).
@@ -238,58 +573,211 @@ pub mod pallet {
 	/// The [`weight`] macro is used to assign a weight to each call.
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
-		/// An example dispatchable that takes a single u32 value as a parameter, writes the value
-		/// to storage and emits an event.
+		/// Spend UTXOs and create new ones.
 		///
-		/// It checks that the _origin_ for this call is _Signed_ and returns a dispatch
-		/// error if it isn't. Learn more about origins here: <https://docs.substrate.io/build/origins/>
+		/// Submitted as an unsigned extrinsic: authorization comes from the sigscripts, which are
+		/// checked both here and in [`ValidateUnsigned`] before the transaction enters the pool.
         #[pallet::call_index(0)]
-        #[pallet::weight({
-            let transaction_size = transaction.inputs.len().saturating_add(transaction.outputs.len());
-            (10_000 as Weight)
-                .saturating_mul(transaction_size as Weight)
-                .saturating_add(10_000 as Weight)
-        })]
+        #[pallet::weight(Pallet::<T>::spend_weight(transaction))]
         pub fn spend(
             origin: OriginFor<T>,
             transaction: Transaction,
         ) -> DispatchResult {
-            ensure_signed(origin)?;
+            ensure_none(origin)?;
 
-            let transaction_validity = Self::validate_transaction(&transaction)?;
+            let (transaction_validity, fee) = Self::check_transaction(&transaction)?;
             ensure!(
                 transaction_validity.requires.is_empty(),
                 Error::<T>::MissingInputUtxo
             );
 
-            Self::update_storage(&transaction, transaction_validity.priority as Value)?;
+            Self::update_storage(&transaction, fee)?;
 
-            Self::deposit_event(Event::TransactionSuccess { transaction });
+            // Both sums were checked not to overflow
+            let output_total = transaction.output_value();
+            let input_total = output_total.saturating_add(fee);
+            Self::deposit_event(Event::TransactionSuccess { transaction, input_total, output_total, fee });
+            Ok(())
+        }
+
+		/// Create new outputs without spending any inputs, for bridged asset issuance and testnet
+		/// funding.
+		///
+		/// Only [`Config::MintOrigin`] may call this; it has no unsigned or signed-user path.
+		/// Minted value counts towards [`TotalIssuance`] and is bounded by `MaxMoney`.
+        #[pallet::call_index(1)]
+        #[pallet::weight(T::WeightInfo::mint(outputs.len() as u32))]
+        pub fn mint(
+            origin: OriginFor<T>,
+            outputs: BoundedVec<TransactionOutput, ConstU32<MAX_TRANSACTION_PARTS>>,
+        ) -> DispatchResult {
+            T::MintOrigin::ensure_origin(origin)?;
+            ensure!(!outputs.is_empty(), Error::<T>::NoOutputs);
+
+            let mut total: Value = 0;
+            for output in outputs.iter() {
+                ensure!(output.value > 0, Error::<T>::ZeroValueOutput);
+                ensure!(!output.pubkey.is_zero(), Error::<T>::UnspendableDestination);
+                total = total.checked_add(output.value).ok_or(Error::<T>::ValueOverflow)?;
+            }
+            let new_utxo_total = <UtxoTotalValue<T>>::get()
+                .checked_add(total)
+                .ok_or(Error::<T>::ValueOverflow)?;
+            ensure!(new_utxo_total <= T::MaxMoney::get(), Error::<T>::ExceedsMaxMoney);
+
+            // Outpoints commit to the block and extrinsic so identical mints never collide
+            let block_number = <frame_system::Pallet<T>>::block_number().saturated_into::<u64>();
+            let extrinsic_index = <frame_system::Pallet<T>>::extrinsic_index().unwrap_or_default();
+            for (index, output) in outputs.iter().enumerate() {
+                let hash = BlakeTwo256::hash_of(&(&outputs, block_number, extrinsic_index, index as u64));
+                ensure!(Self::utxo(&hash).is_none(), Error::<T>::OutputAlreadyExists);
+                <BlockOutputs<T>>::insert(hash, output);
+            }
+
+            <UtxoTotalValue<T>>::put(new_utxo_total);
+            <TotalIssuance<T>>::mutate(|issued| *issued = issued.saturating_add(total));
+
+            Self::deposit_event(Event::Minted { outputs, total });
             Ok(())
         }
 	}
 
+	#[pallet::validate_unsigned]
+	impl<T: Config> ValidateUnsigned for Pallet<T> {
+		type Call = Call<T>;
+
+		fn validate_unsigned(_source: TransactionSource, call: &Self::Call) -> TransactionValidity {
+			match call {
+				Call::spend { transaction } => Self::validate_transaction(transaction)
+					.map_err(|error| Self::invalid_transaction(error).into()),
+				_ => InvalidTransaction::Call.into(),
+			}
+		}
+
+		/// Inside a block every input must already exist, in the UTXO set or among the outputs
+		/// of spends applied earlier in the block. A block applying a spend before one it depends
+		/// on, or a second spend of the same output, is invalid instead of carrying a failed
+		/// spend, so the author cannot fill it with spends that pay no fee.
+		///
+		/// Dependencies cannot form cycles: outpoints commit to the txid, which commits to the
+		/// inputs, so a spend can only depend on transactions that existed before it.
+		fn pre_dispatch(call: &Self::Call) -> Result<(), TransactionValidityError> {
+			match call {
+				Call::spend { transaction } => {
+					let validity = Self::validate_transaction(transaction)
+						.map_err(Self::invalid_transaction)?;
+					if !validity.requires.is_empty() {
+						return Err(Self::invalid_transaction(Error::<T>::MissingInputUtxo.into()).into())
+					}
+					Ok(())
+				},
+				_ => Err(InvalidTransaction::Call.into()),
+			}
+		}
+	}
+
 	#[pallet::hooks]
     impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_initialize(n: BlockNumberFor<T>) -> Weight {
+            let (uncles, pruned) = Self::reward_uncles(n);
+
+            // Flushing the outputs each spend leaves in `BlockOutputs` is part of the spend
+            // weight; this only covers paying out the block reward and the pending ones, and
+            // counting them and the uncle rewards in `AuthorStats`.
+            T::WeightInfo::on_finalize(0, T::RewardSmoothing::get())
+                .saturating_add(T::WeightInfo::reward_uncles(uncles))
+                .saturating_add(T::DbWeight::get().reads_writes(uncles.saturating_add(1).into(), uncles.saturating_add(1).into()))
+                .saturating_add(T::DbWeight::get().writes(pruned.into()))
+        }
+
         fn on_finalize(_n: BlockNumberFor<T>) {
+            Self::flush_block_outputs();
+            Self::pay_pending_rewards();
+
             match T::BlockAuthor::block_author() {
                 None => Self::deposit_event(Event::RewardsWasted),
                 Some(author) => Self::disperse_reward(&author),
             }
         }
+
+        fn integrity_test() {
+            assert!(
+                T::MaxTransactionSize::get() <= MAX_TRANSACTION_PARTS,
+                "MaxTransactionSize must not exceed MAX_TRANSACTION_PARTS"
+            );
+            // Zero-valued outputs are dust, so the smallest output allowed is 1
+            assert!(T::MaxMoney::get() >= 1, "MaxMoney must allow outputs above dust");
+            assert!(
+                T::MaxScriptSize::get() <= script::MAX_SCRIPT_SIZE,
+                "MaxScriptSize must not exceed script::MAX_SCRIPT_SIZE"
+            );
+            assert!(
+                T::RewardSmoothing::get() <= MAX_REWARD_SMOOTHING,
+                "RewardSmoothing must not exceed MAX_REWARD_SMOOTHING"
+            );
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn try_state(_n: BlockNumberFor<T>) -> Result<(), sp_runtime::TryRuntimeError> {
+            let total = <UtxoTotalValue<T>>::get();
+            ensure!(total <= T::MaxMoney::get(), "UtxoTotalValue exceeds MaxMoney");
+
+            let mut sum: Value = 0;
+            for (_, utxo) in <UtxoStore<T>>::iter().chain(<BlockOutputs<T>>::iter()) {
+                sum = sum.checked_add(utxo.value).ok_or("UTXO set value overflows")?;
+            }
+            ensure!(sum == total, "UtxoTotalValue does not match the UTXO set");
+
+            let mut pending: Value = 0;
+            for reward in <PendingRewards<T>>::iter_values().flatten() {
+                pending = pending.checked_add(reward.balance).ok_or("pending rewards overflow")?;
+            }
+            ensure!(pending == <PendingRewardTotal<T>>::get(), "PendingRewardTotal does not match PendingRewards");
+            ensure!(total.saturating_add(pending) <= T::MaxMoney::get(), "pending rewards exceed MaxMoney");
+
+            for (memo, outpoint, ()) in <MemoOutputs<T>>::iter() {
+                let utxo = <UtxoStore<T>>::get(outpoint).ok_or("MemoOutputs lists a spent output")?;
+                ensure!(utxo.memo == Some(memo), "MemoOutputs lists an output under another memo");
+            }
+            Ok(())
+        }
     }
 
 	impl<T: Config> Pallet<T> {
 		/// Validate transaction for validity, errors, & race conditions
 		pub fn validate_transaction(transaction: &Transaction) -> Result<ValidTransaction, DispatchError> {
+			Self::check_transaction(transaction).map(|(validity, _)| validity)
+		}
+
+		/// Validate a transaction, also returning its fee.
+		///
+		/// The fee is only known, and only returned as non-zero, once no input is missing.
+		/// Transactions are validated by the rules of their version.
+		pub fn check_transaction(transaction: &Transaction) -> Result<(ValidTransaction, Value), DispatchError> {
+			sp_tracing::enter_span!(sp_tracing::debug_span!(
+				target: LOG_TARGET,
+				"validate_transaction",
+				size = transaction.encoded_size(),
+				inputs = transaction.inputs.len(),
+				outputs = transaction.outputs.len(),
+			));
+			match transaction.known_version() {
+				Ok(TransactionVersion::V1) => Self::check_v1_transaction(transaction),
+				Err(_) => Err(Error::<T>::UnsupportedVersion.into()),
+			}
+		}
+
+		/// Validate a transaction of [`TransactionVersion::V1`], also returning its fee.
+		fn check_v1_transaction(transaction: &Transaction) -> Result<(ValidTransaction, Value), DispatchError> {
 			// Check basic requirements
 			ensure!(!transaction.inputs.is_empty(), Error::<T>::NoInputs);
 			ensure!(!transaction.outputs.is_empty(), Error::<T>::NoOutputs);
 	
-			// Check for duplicate inputs
-			let input_set: BTreeMap<_, ()> = transaction.inputs
+			// Check for duplicate inputs, by outpoint: a signed and a borrowed input would
+			// otherwise count the same output twice
+			let input_set: BTreeSet<H256> = transaction.inputs
 				.iter()
-				.map(|input| (input, ()))
+				.map(|input| input.outpoint)
 				.collect();
 			ensure!(
 				input_set.len() == transaction.inputs.len(),
@@ -305,28 +793,40 @@ pub mod pallet {
 				output_set.len() == transaction.outputs.len(),
 				Error::<T>::DuplicateOutput
 			);
+
+			if <frame_system::Pallet<T>>::block_number() >= T::CanonicalOrderingFrom::get() {
+				ensure!(transaction.is_canonically_ordered(), Error::<T>::NonCanonicalOrder);
+			}
 	
 			let mut total_input: Value = 0;
 			let mut total_output: Value = 0;
 			let mut output_index: u64 = 0;
 			let simple_transaction = Self::get_simple_transaction(transaction);
+			let txid = BlakeTwo256::hash(&simple_transaction);
+	
+			// Pubkeys of the inputs without witness, in order, with whether each carries the
+			// signature
+			let mut input_keys = Vec::with_capacity(transaction.inputs.len());
 	
 			// Variables for transaction pool
-			let mut missing_utxos = Vec::new();
-			let mut new_utxos = Vec::new();
+			let mut missing_utxos = Vec::with_capacity(transaction.inputs.len());
+			let mut new_utxos = Vec::with_capacity(transaction.outputs.len());
 			let mut reward = 0;
 	
 			// Validate inputs
 			for input in transaction.inputs.iter() {
-				if let Some(input_utxo) = <UtxoStore<T>>::get(&input.outpoint) {
-					ensure!(
-						sp_io::crypto::sr25519_verify(
-							&Signature::from_raw(*input.sigscript.as_fixed_bytes()),
-							&simple_transaction,
-							&Public::from_h256(input_utxo.pubkey)
-						),
-						Error::<T>::InvalidSignature
-					);
+				// Otherwise relayers could add any sigscript to a witness input
+				ensure!(
+					input.witness.is_none() || input.sigscript.is_none(),
+					Error::<T>::SignatureWithWitness
+				);
+				if let Some(input_utxo) = Self::utxo(&input.outpoint) {
+					if input.witness.is_some() || input.sigscript.is_some() {
+						Self::check_authorization(input, &input_utxo, &simple_transaction)?;
+					}
+					if input.witness.is_none() {
+						input_keys.push((input_utxo.pubkey, input.sigscript.is_some()));
+					}
 					total_input = total_input.checked_add(input_utxo.value)
 						.ok_or(Error::<T>::ValueOverflow)?;
 				} else {
@@ -337,13 +837,16 @@ pub mod pallet {
 			// Validate outputs
 			for output in transaction.outputs.iter() {
 				ensure!(output.value > 0, Error::<T>::ZeroValueOutput);
+				// Nothing can sign for the zero pubkey, so coins sent to it would be lost
+				ensure!(!output.pubkey.is_zero(), Error::<T>::UnspendableDestination);
+				ensure!(output.value <= T::MaxMoney::get(), Error::<T>::ExceedsMaxMoney);
 				
-				let hash = BlakeTwo256::hash_of(&(&transaction.encode(), output_index));
+				let hash = Transaction::outpoint_of(&txid, output_index);
 				output_index = output_index.checked_add(1)
 					.ok_or(Error::<T>::OutputIndexOverflow)?;
 				
 				ensure!(
-					!<UtxoStore<T>>::contains_key(hash),
+					!<UtxoStore<T>>::contains_key(hash) && !<BlockOutputs<T>>::contains_key(hash),
 					Error::<T>::OutputAlreadyExists
 				);
 				
@@ -353,8 +856,22 @@ pub mod pallet {
 				new_utxos.push(hash.as_fixed_bytes().to_vec());
 			}
 	
+			ensure!(
+				total_input <= T::MaxMoney::get() && total_output <= T::MaxMoney::get(),
+				Error::<T>::ExceedsMaxMoney
+			);
+
 			// Verify input/output value relationship
 			if missing_utxos.is_empty() {
+				// Exactly the first input of each key carries its signature, so that relayers can
+				// neither strip a copy of it nor add one. Only decidable once every input is known:
+				// the first input of a key may be the missing one.
+				let mut signed_keys = BTreeSet::new();
+				for (key, signed) in input_keys {
+					let first = signed_keys.insert(key);
+					ensure!(first || !signed, Error::<T>::RedundantSignature);
+					ensure!(!first || signed, Error::<T>::MissingSignature);
+				}
 				ensure!(
 					total_input >= total_output,
 					Error::<T>::OutputExceedsInput
@@ -363,49 +880,260 @@ pub mod pallet {
 					.ok_or(Error::<T>::RewardError)?;
 			}
 	
-			Ok(ValidTransaction {
-				requires: missing_utxos,
-				provides: new_utxos,
-				priority: reward as u64,
-				longevity: TransactionLongevity::max_value(),
-				propagate: true,
-			})
+			let priority = Self::priority(
+				reward,
+				transaction.inputs.len() as u32,
+				transaction.outputs.len() as u32,
+			);
+			Ok((
+				ValidTransaction {
+					requires: missing_utxos,
+					provides: new_utxos,
+					priority,
+					longevity: TransactionLongevity::max_value(),
+					propagate: true,
+				},
+				reward,
+			))
+		}
+
+		/// Weight of [`Pallet::spend`], with the consolidation discount applied.
+		///
+		/// Inputs and outputs are charged for the UTXO set changes they make, and witness data
+		/// for the signatures it has checked and the scripts it runs. Inputs sharing a signature
+		/// are charged for it once, so the weight, like the [`Transaction::virtual_size`] the
+		/// fee market prices transactions by, grows less with spending outputs than with
+		/// creating them.
+		pub fn spend_weight(transaction: &Transaction) -> Weight {
+			let inputs = transaction.inputs.len() as u32;
+			let outputs = transaction.outputs.len() as u32;
+			let max_ops = T::MaxScriptOps::get();
+			let sigscripts = transaction
+				.inputs
+				.iter()
+				.filter(|input| input.witness.is_none() && input.sigscript.is_some())
+				.count() as u32;
+			let checks = transaction
+				.inputs
+				.iter()
+				.filter_map(|input| input.witness.as_ref())
+				.map(|witness| script::signature_checks(&witness.script, max_ops))
+				.fold(sigscripts, u32::saturating_add);
+			let weight = T::WeightInfo::spend(inputs, outputs)
+				.saturating_add(T::WeightInfo::execute_script(transaction.script_bytes()))
+				.saturating_add(T::WeightInfo::check_signatures(checks));
+
+			let per_input = T::WeightInfo::spend(2, 1).saturating_sub(T::WeightInfo::spend(1, 1));
+			let waived = per_input.saturating_mul(inputs.saturating_sub(outputs).into());
+			let discount = T::ConsolidationDiscount::get();
+			weight.saturating_sub(Weight::from_parts(
+				discount.mul_floor(waived.ref_time()),
+				discount.mul_floor(waived.proof_size()),
+			))
+		}
+
+		/// Pool priority of a transaction paying `fee`, raised for transactions that shrink the
+		/// UTXO set.
+		pub fn priority(fee: Value, inputs: u32, outputs: u32) -> TransactionPriority {
+			let fee: u64 = fee.saturated_into();
+			let bonus = T::ConsolidationDiscount::get()
+				.mul_floor(fee)
+				.saturating_mul(inputs.saturating_sub(outputs).into());
+			fee.saturating_add(bonus)
 		}
 	
+		/// Maps a validation failure to the rejection reported to pool submitters.
+		///
+		/// Errors of this pallet become `InvalidTransaction::Custom(index)`, where `index` is the
+		/// position of the variant in [`Error`], so RPC clients can tell e.g. a bad signature
+		/// (`InvalidSignature`) apart from dust (`ZeroValueOutput`).
+		pub fn invalid_transaction(error: DispatchError) -> InvalidTransaction {
+			match error {
+				DispatchError::Module(ModuleError { error, .. }) => InvalidTransaction::Custom(error[0]),
+				DispatchError::Arithmetic(_) => InvalidTransaction::Custom(
+					Error::<T>::ValueOverflow.encode()[0]
+				),
+				_ => InvalidTransaction::Call,
+			}
+		}
+
+		/// The configured bounds on script execution.
+		pub fn script_limits() -> ScriptLimits {
+			ScriptLimits {
+				max_script_size: T::MaxScriptSize::get(),
+				max_ops: T::MaxScriptOps::get(),
+				max_stack_depth: T::MaxStackDepth::get(),
+			}
+		}
+
+		/// Check that `input` is authorized to spend `utxo`.
+		///
+		/// Without a witness the sigscript must be the owner's signature over `payload`, in its
+		/// canonical encoding. With one, the witness script must hash to the output's pubkey and
+		/// execute successfully, and the caller has checked that there is no sigscript. Inputs
+		/// covered by another input's signature are checked by the caller instead.
+		fn check_authorization(
+			input: &TransactionInput,
+			utxo: &TransactionOutput,
+			payload: &[u8],
+		) -> DispatchResult {
+			// The span's duration is the verify time of the input
+			sp_tracing::enter_span!(sp_tracing::trace_span!(
+				target: LOG_TARGET,
+				"verify_input",
+				witness = input.witness.is_some(),
+			));
+			match (&input.witness, &input.sigscript) {
+				(None, None) => return Err(Error::<T>::MissingSignature.into()),
+				(None, Some(sigscript)) => {
+					ensure!(
+						is_canonical_signature(sigscript.as_fixed_bytes()),
+						Error::<T>::NonCanonicalSignature
+					);
+					ensure!(
+						sp_io::crypto::sr25519_verify(
+							&Signature::from_raw(*sigscript.as_fixed_bytes()),
+							payload,
+							&Public::from_h256(utxo.pubkey)
+						),
+						Error::<T>::InvalidSignature
+					)
+				},
+				(Some(witness), _) => {
+					ensure!(
+						script::script_hash(&witness.script) == utxo.pubkey,
+						Error::<T>::ScriptHashMismatch
+					);
+					let context = ScriptContext {
+						payload,
+						block_number: <frame_system::Pallet<T>>::block_number().saturated_into(),
+					};
+					let stack = witness.stack.iter().map(|item| item.to_vec()).collect();
+					script::execute(&witness.script, stack, &context, &Self::script_limits())
+						.map_err(Error::<T>::from)?;
+				},
+			}
+			Ok(())
+		}
+
 		/// Update storage to reflect changes made by transaction
 		fn update_storage(transaction: &Transaction, reward: Value) -> DispatchResult {
+			sp_tracing::enter_span!(sp_tracing::debug_span!(
+				target: LOG_TARGET,
+				"update_storage",
+				inputs = transaction.inputs.len(),
+				outputs = transaction.outputs.len(),
+				fee = reward,
+			));
 			// Calculate new reward total
 			let new_total = <RewardTotal<T>>::get()
 				.checked_add(reward)
 				.ok_or(Error::<T>::RewardError)?;
 			<RewardTotal<T>>::put(new_total);
-	
-			// Remove spent UTXOs
+
+			// Remove spent UTXOs, which may have been created earlier in this block
+			let mut taken: Value = 0;
 			for input in transaction.inputs.iter() {
-				<UtxoStore<T>>::remove(input.outpoint);
+				let spent = match <BlockOutputs<T>>::take(input.outpoint) {
+					Some(spent) => spent,
+					None => match <UtxoStore<T>>::take(input.outpoint) {
+						Some(spent) => {
+							if let Some(memo) = spent.memo {
+								<MemoOutputs<T>>::remove(memo, input.outpoint);
+							}
+							spent
+						},
+						None => continue,
+					},
+				};
+				taken = taken.checked_add(spent.value).ok_or(Error::<T>::ValueOverflow)?;
 			}
 	
-			// Add new UTXOs
+			// Add new UTXOs, keyed by outpoints derived from the txid
+			let txid = transaction.txid();
 			let mut index: u64 = 0;
+			let mut created: Value = 0;
 			for output in transaction.outputs.iter() {
-				let hash = BlakeTwo256::hash_of(&(&transaction.encode(), index));
+				let hash = Transaction::outpoint_of(&txid, index);
 				index = index.checked_add(1)
 					.ok_or(Error::<T>::OutputIndexOverflow)?;
-				<UtxoStore<T>>::insert(hash, output);
+				created = created.checked_add(output.value).ok_or(Error::<T>::ValueOverflow)?;
+				<BlockOutputs<T>>::insert(hash, output);
 			}
+
+			// Track the set by what actually left and entered it, the fee leaving it until it is
+			// paid out to the block author
+			let utxo_total = <UtxoTotalValue<T>>::get()
+				.saturating_sub(taken)
+				.checked_add(created)
+				.ok_or(Error::<T>::ValueOverflow)?;
+			ensure!(utxo_total <= T::MaxMoney::get(), Error::<T>::ExceedsMaxMoney);
+			<UtxoTotalValue<T>>::put(utxo_total);
 	
 			Ok(())
 		}
+
+		/// Look up an unspent output, including outputs created earlier in the current block.
+		pub fn utxo(outpoint: &H256) -> Option<TransactionOutput> {
+			<UtxoStore<T>>::get(outpoint).or_else(|| <BlockOutputs<T>>::get(outpoint))
+		}
+
+		/// Move the outputs created in this block that are still unspent into the UTXO set.
+		fn flush_block_outputs() {
+			for (hash, output) in <BlockOutputs<T>>::drain() {
+				if let Some(memo) = output.memo {
+					<MemoOutputs<T>>::insert(memo, hash, ());
+				}
+				<UtxoStore<T>>::insert(hash, output);
+			}
+		}
+
+		/// The unspent outputs carrying `memo`, with their outpoints. Outputs created in the
+		/// current block are only included once it is finalized.
+		pub fn utxos_by_memo(memo: H256) -> Vec<(H256, TransactionOutput)> {
+			<MemoOutputs<T>>::iter_key_prefix(memo)
+				.filter_map(|outpoint| <UtxoStore<T>>::get(outpoint).map(|utxo| (outpoint, utxo)))
+				.collect()
+		}
 	
-		/// Redistribute combined reward value to block author
+		/// Redistribute combined reward value to block author, or credit it to their pending
+		/// rewards if [`Config::RewardSmoothing`] is set
 		fn disperse_reward(author: &Public) {
-			let reward = RewardTotal::<T>::take() + 
-				T::Issuance::issuance(frame_system::Pallet::<T>::block_number());
-	
-			let utxo = TransactionOutput {
-				value: reward,
-				pubkey: H256::from_slice(author.as_slice()),
-			};
+			let fees = RewardTotal::<T>::take();
+			let issuance = Self::capped_issuance(frame_system::Pallet::<T>::block_number(), fees);
+			sp_tracing::enter_span!(sp_tracing::debug_span!(
+				target: LOG_TARGET,
+				"disperse_reward",
+				fees,
+				issuance,
+			));
+			let reward = fees.saturating_add(issuance);
+			<TotalIssuance<T>>::mutate(|issued| *issued = issued.saturating_add(issuance));
+			let pubkey = H256::from_slice(author.as_slice());
+			<AuthorStats<T>>::mutate(pubkey, |stats| {
+				stats.blocks_authored = stats.blocks_authored.saturating_add(1);
+				stats.rewards_earned = stats.rewards_earned.saturating_add(reward);
+			});
+
+			let blocks = T::RewardSmoothing::get();
+			if blocks > 0 {
+				<PendingRewards<T>>::mutate(pubkey, |pending| {
+					let credits = pending.get_or_insert_with(BoundedVec::new);
+					// Earlier credits keep their schedule. There are fewer than `blocks` of them,
+					// short of a lowered `RewardSmoothing` still paying out longer ones
+					if let Err(credit) = credits.try_push(PendingReward { balance: reward, blocks }) {
+						if let Some(last) = credits.last_mut() {
+							last.balance = last.balance.saturating_add(credit.balance);
+						}
+					}
+				});
+				<PendingRewardTotal<T>>::mutate(|total| *total = total.saturating_add(reward));
+				Self::deposit_event(Event::RewardScheduled { author: pubkey, amount: reward, blocks });
+				return
+			}
+
+			<UtxoTotalValue<T>>::mutate(|total| *total = total.saturating_add(reward));
+			let utxo = TransactionOutput { value: reward, pubkey, memo: None };
 	
 			let hash = BlakeTwo256::hash_of(&(&utxo,
 				<frame_system::Pallet<T>>::block_number().saturated_into::<u64>()));
@@ -413,21 +1141,168 @@ pub mod pallet {
 			<UtxoStore<T>>::insert(hash, utxo);
 			Self::deposit_event(Event::RewardsIssued { amount: reward, utxo_hash: hash });
 		}
+
+		/// Pay the authors of the uncles referenced in the digest of block `n`, and forget the
+		/// uncles too old to be referenced any more. Returns the number of references looked at
+		/// and of rewarded uncles forgotten.
+		fn reward_uncles(n: BlockNumberFor<T>) -> (u32, u32) {
+			let depth = BlockNumberFor::<T>::from(T::MaxUncleDepth::get());
+			let pruned = n
+				.checked_sub(&depth.saturating_add(1u32.into()))
+				.map(|expired| <RewardedUncles<T>>::clear_prefix(expired, u32::MAX, None).unique)
+				.unwrap_or(0);
+
+			let digest = <frame_system::Pallet<T>>::digest();
+			let references = digest
+				.logs
+				.iter()
+				.filter_map(|item| item.pre_runtime_try_to::<HeaderFor<T>>(&UNCLE_ENGINE_ID))
+				.take(T::MaxUncles::get() as usize);
+
+			let mut uncles = 0;
+			for header in references {
+				uncles = uncles.saturating_add(1);
+				let uncle = header.hash();
+				match Self::check_uncle(n, &header) {
+					Some(author) => Self::pay_uncle(&header, author),
+					None => Self::deposit_event(Event::UncleRejected { uncle }),
+				}
+			}
+			(uncles, pruned)
+		}
+
+		/// The author of `header` if block `n` may reference it as an uncle: a validly sealed
+		/// block off this chain, whose parent is one of the last `MaxUncleDepth` blocks of it,
+		/// and that was not rewarded yet.
+		fn check_uncle(n: BlockNumberFor<T>, header: &HeaderFor<T>) -> Option<Public> {
+			let number = *header.number();
+			let depth = BlockNumberFor::<T>::from(T::MaxUncleDepth::get());
+			if number.is_zero() || number >= n || n.saturating_sub(number) > depth {
+				return None
+			}
+			let parent_number = number.saturating_sub(1u32.into());
+			let parent = <frame_system::BlockHash<T>>::try_get(parent_number).ok()?;
+			let ancestor = <frame_system::BlockHash<T>>::get(number);
+			let uncle = header.hash();
+			if *header.parent_hash() != parent ||
+				uncle == ancestor ||
+				<RewardedUncles<T>>::contains_key(number, uncle)
+			{
+				return None
+			}
+			T::UncleAuthor::uncle_author(header)
+		}
+
+		/// Mint the uncle reward for `header` to `author`.
+		fn pay_uncle(header: &HeaderFor<T>, author: Public) {
+			let number = *header.number();
+			let uncle = header.hash();
+			<RewardedUncles<T>>::insert(number, uncle, ());
+
+			// Uncle rewards are new issuance, so they are capped by MaxMoney as well
+			let headroom = T::MaxMoney::get().saturating_sub(
+				<UtxoTotalValue<T>>::get()
+					.saturating_add(<PendingRewardTotal<T>>::get())
+					.saturating_add(<RewardTotal<T>>::get()),
+			);
+			let amount = T::UncleReward::get()
+				.mul_floor(T::Issuance::issuance(number))
+				.min(headroom);
+			if amount == 0 {
+				return
+			}
+
+			let pubkey = H256::from_slice(author.as_slice());
+			let utxo = TransactionOutput { value: amount, pubkey, memo: None };
+			let hash = BlakeTwo256::hash_of(&(&utxo, uncle));
+			<UtxoStore<T>>::insert(hash, utxo);
+			<UtxoTotalValue<T>>::mutate(|total| *total = total.saturating_add(amount));
+			<TotalIssuance<T>>::mutate(|issued| *issued = issued.saturating_add(amount));
+			<AuthorStats<T>>::mutate(pubkey, |stats| {
+				stats.uncles_rewarded = stats.uncles_rewarded.saturating_add(1);
+				stats.rewards_earned = stats.rewards_earned.saturating_add(amount);
+			});
+			Self::deposit_event(Event::UncleRewarded { uncle, author: pubkey, amount, utxo_hash: hash });
+		}
+
+		/// The issuance of block `n`, less whatever would push the value in existence past
+		/// `MaxMoney` once `fees` are paid out as well.
+		fn capped_issuance(n: BlockNumberFor<T>, fees: Value) -> Value {
+			// Never mint past MaxMoney, whatever the issuance schedule says
+			let headroom = T::MaxMoney::get().saturating_sub(
+				<UtxoTotalValue<T>>::get()
+					.saturating_add(<PendingRewardTotal<T>>::get())
+					.saturating_add(fees),
+			);
+			T::Issuance::issuance(n).min(headroom)
+		}
+
+		/// The reward of the author of the next block as of the current state: the fees of the
+		/// transactions included so far plus the next block's issuance. Fees of transactions that
+		/// are still in the pool are not counted.
+		pub fn pending_reward() -> Value {
+			let fees = <RewardTotal<T>>::get();
+			let next = frame_system::Pallet::<T>::block_number().saturating_add(1u32.into());
+			fees.saturating_add(Self::capped_issuance(next, fees))
+		}
+
+		/// The `limit` authors with the most blocks, ties broken by the rewards earned, then by
+		/// pubkey. Reads all of [`AuthorStats`], so it is meant for runtime API calls only.
+		pub fn leaderboard(limit: u32) -> Vec<(H256, AuthorStatistics)> {
+			let mut authors: Vec<_> = <AuthorStats<T>>::iter().collect();
+			authors.sort_by(|(a, x), (b, y)| {
+				y.blocks_authored
+					.cmp(&x.blocks_authored)
+					.then(y.rewards_earned.cmp(&x.rewards_earned))
+					.then(a.cmp(b))
+			});
+			authors.truncate(limit as usize);
+			authors
+		}
+
+		/// Pay every author with pending rewards an equal slice of each of their credits, in one
+		/// output.
+		fn pay_pending_rewards() {
+			let block_number = <frame_system::Pallet<T>>::block_number().saturated_into::<u64>();
+			let mut paid: Value = 0;
+
+			<PendingRewards<T>>::translate(|author, mut credits: BoundedVec<PendingReward, _>| {
+				let mut slice: Value = 0;
+				for credit in credits.iter_mut() {
+					// The last slice of a credit takes whatever rounding left over
+					let part = credit.balance / Value::from(credit.blocks.max(1));
+					credit.balance = credit.balance.saturating_sub(part);
+					credit.blocks = credit.blocks.saturating_sub(1);
+					slice = slice.saturating_add(part);
+				}
+				credits.retain(|credit| credit.blocks > 0);
+
+				if slice > 0 {
+					let utxo = TransactionOutput { value: slice, pubkey: author, memo: None };
+					// Tagged so a slice cannot collide with a reward paid at once in the same block
+					let hash = BlakeTwo256::hash_of(&(&utxo, block_number, b"pending"));
+					<UtxoStore<T>>::insert(hash, utxo);
+					paid = paid.saturating_add(slice);
+					Self::deposit_event(Event::PendingRewardPaid { author, amount: slice, utxo_hash: hash });
+				}
+
+				(!credits.is_empty()).then_some(credits)
+			});
+
+			<PendingRewardTotal<T>>::mutate(|total| *total = total.saturating_sub(paid));
+			<UtxoTotalValue<T>>::mutate(|total| *total = total.saturating_add(paid));
+		}
 	
 		/// Strips a transaction of its signature fields
 		pub fn get_simple_transaction(transaction: &Transaction) -> Vec<u8> {
-			let mut trx = transaction.clone();
-			for input in trx.inputs.iter_mut() {
-				input.sigscript = H512::zero();
-			}
-			trx.encode()
+			transaction.signing_payload()
 		}
 	
 		/// Helper for checking missing UTXOs
 		pub fn get_missing_utxos(transaction: &Transaction) -> Vec<&H256> {
 			let mut missing_utxos = Vec::new();
 			for input in transaction.inputs.iter() {
-				if <UtxoStore<T>>::get(&input.outpoint).is_none() {
+				if Self::utxo(&input.outpoint).is_none() {
 					missing_utxos.push(&input.outpoint);
 				}
 			}
@@ -0,0 +1,45 @@
+//! Storage migrations of the UTXO pallet.
+
+use frame_support::{
+	migrations::VersionedMigration, pallet_prelude::*, traits::UncheckedOnRuntimeUpgrade,
+};
+
+use sp_core::H256;
+
+use crate::{Config, Pallet, TransactionOutput, UtxoStore, Value};
+
+/// Migrations to storage version 1, which added [`TransactionOutput::memo`].
+pub mod v1 {
+	use super::*;
+
+	/// The encoding of an output before memos existed.
+	#[derive(Encode, Decode)]
+	struct OldTransactionOutput {
+		value: Value,
+		pubkey: H256,
+	}
+
+	/// Re-encodes every unspent output without a memo. None can have one yet, so
+	/// [`crate::MemoOutputs`] stays empty.
+	pub struct InnerMigrateV0ToV1<T>(PhantomData<T>);
+
+	impl<T: Config> UncheckedOnRuntimeUpgrade for InnerMigrateV0ToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			let mut translated: u64 = 0;
+			<UtxoStore<T>>::translate::<OldTransactionOutput, _>(|_, old| {
+				translated += 1;
+				Some(TransactionOutput { value: old.value, pubkey: old.pubkey, memo: None })
+			});
+			T::DbWeight::get().reads_writes(translated, translated)
+		}
+	}
+
+	/// [`InnerMigrateV0ToV1`], run only while the pallet is at storage version 0.
+	pub type MigrateV0ToV1<T> = VersionedMigration<
+		0,
+		1,
+		InnerMigrateV0ToV1<T>,
+		Pallet<T>,
+		<T as frame_system::Config>::DbWeight,
+	>;
+}
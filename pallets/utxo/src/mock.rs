@@ -1,64 +1,60 @@
 use crate::*;
-use frame_support::{
-    parameter_types,
-    traits::{ConstU16, ConstU64},
-};
-use sp_core::{H256, sr25519::Public};
-use sp_runtime::{
-    testing::Header,
-    traits::{BlakeTwo256, IdentityLookup},
-};
+use frame_support::{derive_impl, parameter_types};
+use frame_system::pallet_prelude::HeaderFor;
+use sp_core::sr25519::Public;
+use sp_runtime::BuildStorage;
 
-type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
 type Block = frame_system::mocking::MockBlock<Test>;
 
 frame_support::construct_runtime!(
-    pub enum Test where
-        Block = Block,
-        NodeBlock = Block,
-        UncheckedExtrinsic = UncheckedExtrinsic,
+    pub enum Test
     {
-        System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
-        Utxo: crate::pallet::{Pallet, Call, Storage, Event<T>},
+        System: frame_system,
+        Utxo: crate,
     }
 );
 
 parameter_types! {
-    pub const BlockHashCount: u64 = 250;
     pub const MaxTransactionSize: u32 = 100;
+    pub const MaxMoney: Value = 21_000_000;
+    pub const MaxScriptSize: u32 = 1_000;
+    pub const MaxScriptOps: u32 = 20;
+    pub const ConsolidationDiscount: Perbill = Perbill::from_percent(50);
+    pub const MaxStackDepth: u32 = 16;
+    pub static RewardSmoothing: u32 = 0;
+    pub static Author: Option<Public> = None;
+    pub static UncleReward: Perbill = Perbill::from_percent(50);
+    pub const MaxUncleDepth: u32 = 3;
+    pub const MaxUncles: u32 = 2;
+    pub static CanonicalOrderingFrom: u64 = u64::MAX;
 }
 
+/// Engine id of the mock seal, which simply names the author.
+pub const MOCK_SEAL: sp_runtime::ConsensusEngineId = *b"mock";
+
+pub struct MockUncleAuthor;
+impl UncleAuthor<HeaderFor<Test>> for MockUncleAuthor {
+    fn uncle_author(header: &HeaderFor<Test>) -> Option<Public> {
+        header.digest.logs.last()?.seal_try_to(&MOCK_SEAL)
+    }
+
+    #[cfg(feature = "runtime-benchmarks")]
+    fn seal(header: &mut HeaderFor<Test>) -> Public {
+        let author = Public::from_raw([9; 32]);
+        header.digest.push(DigestItem::Seal(MOCK_SEAL, author.encode()));
+        author
+    }
+}
+
+#[derive_impl(frame_system::config_preludes::TestDefaultConfig)]
 impl frame_system::Config for Test {
-    type BaseCallFilter = frame_support::traits::Everything;
-    type BlockWeights = ();
-    type BlockLength = ();
-    type DbWeight = ();
-    type RuntimeOrigin = RuntimeOrigin;
-    type RuntimeCall = RuntimeCall;
-    type Index = u64;
-    type BlockNumber = u64;
-    type Hash = H256;
-    type Hashing = BlakeTwo256;
-    type AccountId = u64;
-    type Lookup = IdentityLookup<Self::AccountId>;
-    type Header = Header;
-    type RuntimeEvent = RuntimeEvent;
-    type BlockHashCount = BlockHashCount;
-    type Version = ();
-    type PalletInfo = PalletInfo;
-    type AccountData = ();
-    type OnNewAccount = ();
-    type OnKilledAccount = ();
-    type SystemWeightInfo = ();
-    type SS58Prefix = ConstU16<42>;
-    type OnSetCode = ();
-    type MaxConsumers = frame_support::traits::ConstU32<16>;
+    type Block = Block;
 }
 
 pub struct MockBlockAuthor;
 impl BlockAuthor for MockBlockAuthor {
     fn block_author() -> Option<Public> {
-        None
+        Author::get()
     }
 }
 
@@ -73,12 +69,30 @@ impl crate::pallet::Config for Test {
     type RuntimeEvent = RuntimeEvent;
     type BlockAuthor = MockBlockAuthor;
     type Issuance = MockIssuance;
+    type WeightInfo = ();
     type MaxTransactionSize = MaxTransactionSize;
+    type MaxMoney = MaxMoney;
+    type MintOrigin = frame_system::EnsureRoot<u64>;
+    type MaxScriptSize = MaxScriptSize;
+    type MaxScriptOps = MaxScriptOps;
+    type MaxStackDepth = MaxStackDepth;
+    type ConsolidationDiscount = ConsolidationDiscount;
+    type RewardSmoothing = RewardSmoothing;
+    type UncleAuthor = MockUncleAuthor;
+    type UncleReward = UncleReward;
+    type MaxUncleDepth = MaxUncleDepth;
+    type MaxUncles = MaxUncles;
+    type CanonicalOrderingFrom = CanonicalOrderingFrom;
 }
 
 pub fn new_test_ext() -> sp_io::TestExternalities {
-    let t = frame_system::GenesisConfig::default()
-        .build_storage::<Test>()
+    let t = frame_system::GenesisConfig::<Test>::default()
+        .build_storage()
         .unwrap();
-    t.into()
-}
\ No newline at end of file
+    let mut ext: sp_io::TestExternalities = t.into();
+    // Benchmarks sign with keys generated through the keystore host functions
+    ext.register_extension(sp_keystore::KeystoreExt::new(
+        sp_keystore::testing::MemoryKeystore::new(),
+    ));
+    ext
+}
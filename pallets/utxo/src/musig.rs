@@ -0,0 +1,228 @@
+//! MuSig2 signing for outputs owned by several parties.
+//!
+//! `n` sr25519 keys are aggregated into a single key, and the holders jointly produce one
+//! ordinary sr25519 signature for it. An output paying the aggregate key and the input spending
+//! it are indistinguishable from single-key ones on chain, so custody by any number of signers
+//! costs the same 32-byte pubkey and 64-byte sigscript, and consensus needs no changes.
+//!
+//! Signing takes two rounds:
+//!
+//! 1. every signer calls [`generate_nonce`] and shares the [`PublicNonce`];
+//! 2. once all nonces are in, every signer calls [`partial_sign`] and shares the
+//!    [`PartialSignature`], which anyone can combine with [`aggregate_signatures`].
+//!
+//! Round 1 does not depend on the message, so nonces can be exchanged ahead of time. A
+//! [`SecretNonce`] must never be used twice; [`partial_sign`] consumes it to make that hard.
+//!
+//! Key aggregation weights every key by a coefficient hashed from the full key set, which
+//! prevents rogue-key attacks by a signer choosing its key after seeing the others.
+
+use curve25519_dalek::{
+	ristretto::{CompressedRistretto, RistrettoPoint},
+	scalar::Scalar,
+};
+use rand_core::{OsRng, RngCore};
+use schnorrkel::context::{signing_context, SigningTranscript};
+use sp_core::{
+	hashing::blake2_512,
+	sr25519::{Pair, Public},
+	Pair as _, H256, H512,
+};
+
+/// Signing context of sr25519 signatures verified by the runtime.
+const SIGNING_CTX: &[u8] = b"substrate";
+
+/// Reasons a MuSig2 session can fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MusigError {
+	/// No keys or nonces were given
+	Empty,
+	/// The same key appears twice in the key set
+	DuplicateKey,
+	/// Bytes that should encode a Ristretto point do not
+	InvalidPoint,
+	/// The signing key is not part of the aggregated key set
+	UnknownSigner,
+	/// The keypair's secret could not be read
+	InvalidSecret,
+}
+
+fn decompress(bytes: &[u8]) -> Result<RistrettoPoint, MusigError> {
+	CompressedRistretto::from_slice(bytes)
+		.map_err(|_| MusigError::InvalidPoint)?
+		.decompress()
+		.ok_or(MusigError::InvalidPoint)
+}
+
+fn key_bytes(key: &Public) -> [u8; 32] {
+	let mut bytes = [0u8; 32];
+	bytes.copy_from_slice(key.as_ref());
+	bytes
+}
+
+fn hash_to_scalar(tag: &[u8], parts: &[&[u8]]) -> Scalar {
+	let mut preimage = tag.to_vec();
+	for part in parts {
+		preimage.extend_from_slice(part);
+	}
+	Scalar::from_bytes_mod_order_wide(&blake2_512(&preimage))
+}
+
+/// An aggregated key set, with each key's coefficient.
+#[derive(Debug, Clone)]
+pub struct KeyAggregation {
+	keys: Vec<[u8; 32]>,
+	coefficients: Vec<Scalar>,
+	aggregate: RistrettoPoint,
+}
+
+/// Aggregate `keys` into a single key. The order of `keys` does not matter.
+pub fn aggregate_keys(keys: &[Public]) -> Result<KeyAggregation, MusigError> {
+	let mut keys: Vec<[u8; 32]> = keys.iter().map(key_bytes).collect();
+	keys.sort();
+	if keys.is_empty() {
+		return Err(MusigError::Empty)
+	}
+	if keys.windows(2).any(|pair| pair[0] == pair[1]) {
+		return Err(MusigError::DuplicateKey)
+	}
+
+	let key_set = keys.concat();
+	let mut coefficients = Vec::with_capacity(keys.len());
+	let mut aggregate = RistrettoPoint::default();
+	for key in &keys {
+		let coefficient = hash_to_scalar(b"musig2/keyagg", &[&key_set, key]);
+		aggregate += coefficient * decompress(key)?;
+		coefficients.push(coefficient);
+	}
+	Ok(KeyAggregation { keys, coefficients, aggregate })
+}
+
+impl KeyAggregation {
+	/// The aggregate key, to be used as an output's `pubkey`.
+	pub fn public(&self) -> H256 {
+		H256(self.aggregate.compress().to_bytes())
+	}
+
+	fn coefficient_of(&self, key: &Public) -> Result<Scalar, MusigError> {
+		self.keys
+			.iter()
+			.position(|candidate| *candidate == key_bytes(key))
+			.map(|index| self.coefficients[index])
+			.ok_or(MusigError::UnknownSigner)
+	}
+}
+
+/// A signer's secret nonce pair for one signing session.
+pub struct SecretNonce(Scalar, Scalar);
+
+/// The public half of a [`SecretNonce`], shared with the other signers in round 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicNonce(pub [u8; 64]);
+
+/// The combination of every signer's [`PublicNonce`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AggregateNonce(RistrettoPoint, RistrettoPoint);
+
+/// A signer's share of the final signature, shared in round 2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartialSignature(pub [u8; 32]);
+
+fn random_scalar() -> Scalar {
+	let mut bytes = [0u8; 64];
+	OsRng.fill_bytes(&mut bytes);
+	Scalar::from_bytes_mod_order_wide(&bytes)
+}
+
+/// Round 1: draw a fresh nonce pair.
+pub fn generate_nonce() -> (SecretNonce, PublicNonce) {
+	let (r1, r2) = (random_scalar(), random_scalar());
+	let mut public = [0u8; 64];
+	public[..32].copy_from_slice(RistrettoPoint::mul_base(&r1).compress().as_bytes());
+	public[32..].copy_from_slice(RistrettoPoint::mul_base(&r2).compress().as_bytes());
+	(SecretNonce(r1, r2), PublicNonce(public))
+}
+
+/// Combine the public nonces of all signers.
+pub fn aggregate_nonces(nonces: &[PublicNonce]) -> Result<AggregateNonce, MusigError> {
+	if nonces.is_empty() {
+		return Err(MusigError::Empty)
+	}
+	let mut aggregate = AggregateNonce(RistrettoPoint::default(), RistrettoPoint::default());
+	for nonce in nonces {
+		aggregate.0 += decompress(&nonce.0[..32])?;
+		aggregate.1 += decompress(&nonce.0[32..])?;
+	}
+	Ok(aggregate)
+}
+
+/// The session's nonce coefficient `b`, final nonce `R` and challenge `c`.
+fn session(
+	keys: &KeyAggregation,
+	nonce: &AggregateNonce,
+	message: &[u8],
+) -> (Scalar, CompressedRistretto, Scalar) {
+	let aggregate = keys.aggregate.compress();
+	let b = hash_to_scalar(
+		b"musig2/noncecoef",
+		&[
+			aggregate.as_bytes(),
+			nonce.0.compress().as_bytes(),
+			nonce.1.compress().as_bytes(),
+			message,
+		],
+	);
+	let r = (nonce.0 + b * nonce.1).compress();
+
+	// Same transcript as schnorrkel's `sign`, so the result verifies as a plain sr25519 signature
+	let mut transcript = signing_context(SIGNING_CTX).bytes(message);
+	transcript.proto_name(b"Schnorr-sig");
+	transcript.commit_point(b"sign:pk", &aggregate);
+	transcript.commit_point(b"sign:R", &r);
+	let c = transcript.challenge_scalar(b"sign:c");
+	(b, r, c)
+}
+
+/// Round 2: sign `message` with `pair`, consuming the signer's nonce.
+pub fn partial_sign(
+	keys: &KeyAggregation,
+	pair: &Pair,
+	secret_nonce: SecretNonce,
+	nonce: &AggregateNonce,
+	message: &[u8],
+) -> Result<PartialSignature, MusigError> {
+	let coefficient = keys.coefficient_of(&pair.public())?;
+	let secret = pair.to_raw_vec();
+	let secret = secret.get(..32).ok_or(MusigError::InvalidSecret)?;
+	let secret: Option<Scalar> =
+		Scalar::from_canonical_bytes(secret.try_into().expect("slice of 32 bytes; qed")).into();
+	let secret = secret.ok_or(MusigError::InvalidSecret)?;
+
+	let (b, _, c) = session(keys, nonce, message);
+	let SecretNonce(r1, r2) = secret_nonce;
+	Ok(PartialSignature((r1 + b * r2 + c * coefficient * secret).to_bytes()))
+}
+
+/// Combine every signer's partial signature into the sigscript spending the aggregate key.
+pub fn aggregate_signatures(
+	keys: &KeyAggregation,
+	nonce: &AggregateNonce,
+	message: &[u8],
+	partials: &[PartialSignature],
+) -> Result<H512, MusigError> {
+	if partials.is_empty() {
+		return Err(MusigError::Empty)
+	}
+	let (_, r, _) = session(keys, nonce, message);
+	let s: Scalar = partials
+		.iter()
+		.map(|partial| Scalar::from_bytes_mod_order(partial.0))
+		.sum();
+
+	let mut signature = [0u8; 64];
+	signature[..32].copy_from_slice(r.as_bytes());
+	signature[32..].copy_from_slice(s.as_bytes());
+	// Marks the signature as schnorrkel rather than legacy ed25519-style
+	signature[63] |= 128;
+	Ok(H512(signature))
+}
@@ -0,0 +1,462 @@
+//! A small stack-based script language for outputs that need more than a single key.
+//!
+//! An output is locked to a script by setting its `pubkey` to the [`script_hash`] of the script.
+//! The spending input reveals the script and the initial stack in its [`ScriptWitness`], and the
+//! spend is valid when the script runs to completion leaving a true value on top of the stack.
+//!
+//! The opcodes follow Bitcoin's numbering where a Bitcoin equivalent exists. Signatures are
+//! sr25519 over the transaction's signing payload, and `OP_BLAKE2_256` takes the slot of
+//! `OP_HASH256`.
+//!
+//! So that a spend has a single valid witness, signatures checked are either empty or in their
+//! canonical encoding, see [`is_canonical_signature`], and `OP_CHECKSIG` and `OP_CHECKMULTISIG`
+//! only fail on empty ones: a check the spender means to fail takes an empty signature, where
+//! any other bytes would do as well.
+//!
+//! Execution cost is bounded by [`ScriptLimits`]: the script length, the number of executed
+//! opcodes and the stack depth are all capped by configuration.
+
+use alloc::{format, string::String, vec, vec::Vec};
+use sp_core::{
+	hexdisplay::HexDisplay,
+	sr25519::{Public, Signature},
+};
+use sp_runtime::RuntimeDebug;
+use utxo_primitives::is_canonical_signature;
+
+// Scripts as transactions carry them, shared with wallets
+pub use utxo_primitives::script::*;
+
+/// Configured bounds on script execution.
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub struct ScriptLimits {
+	/// Maximum script length in bytes
+	pub max_script_size: u32,
+	/// Maximum number of non-push opcodes, counted whether or not their branch executes
+	pub max_ops: u32,
+	/// Maximum number of items on the stack at any point
+	pub max_stack_depth: u32,
+}
+
+/// What a script can observe about the transaction spending it.
+pub struct ScriptContext<'a> {
+	/// The transaction's signing payload, which signatures commit to
+	pub payload: &'a [u8],
+	/// Number of the block the transaction is validated in
+	pub block_number: u64,
+}
+
+/// Reasons a script fails to execute.
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum ScriptError {
+	/// The script is longer than `max_script_size`
+	ScriptTooLarge,
+	/// More than `max_ops` opcodes were encountered
+	TooManyOps,
+	/// The stack grew past `max_stack_depth`
+	StackOverflow,
+	/// An opcode needed more items than the stack holds
+	StackUnderflow,
+	/// A push runs past the end of the script or exceeds `MAX_STACK_ITEM_SIZE`
+	BadPush,
+	/// Unknown opcode, or `OP_RETURN`
+	InvalidOpcode,
+	/// `OP_ELSE` or `OP_ENDIF` without `OP_IF`, or an `OP_IF` left open
+	UnbalancedConditional,
+	/// A numeric operand is out of range
+	InvalidNumber,
+	/// A `*VERIFY` opcode found a false value
+	VerifyFailed,
+	/// `OP_CHECKLOCKTIMEVERIFY` with a height above the current block
+	LockTimeNotReached,
+	/// The script finished with an empty stack or a false value on top
+	EvalFalse,
+	/// A signature is neither empty nor 64 bytes in its canonical encoding
+	NonCanonicalSignature,
+	/// `OP_CHECKSIG` or `OP_CHECKMULTISIG` failed with a signature that is not empty
+	NullFail,
+}
+
+/// Whether a stack item counts as true: any non-zero byte.
+fn cast_to_bool(item: &[u8]) -> bool {
+	item.iter().any(|byte| *byte != 0)
+}
+
+/// Decode a little-endian unsigned number of at most eight bytes.
+fn read_number(item: &[u8]) -> Result<u64, ScriptError> {
+	if item.len() > 8 {
+		return Err(ScriptError::InvalidNumber)
+	}
+	let mut bytes = [0u8; 8];
+	bytes[..item.len()].copy_from_slice(item);
+	Ok(u64::from_le_bytes(bytes))
+}
+
+/// The signature a stack item holds, `None` for an empty item.
+fn decode_signature(item: &[u8]) -> Result<Option<[u8; 64]>, ScriptError> {
+	if item.is_empty() {
+		return Ok(None)
+	}
+	match <[u8; 64]>::try_from(item) {
+		Ok(signature) if is_canonical_signature(&signature) => Ok(Some(signature)),
+		_ => Err(ScriptError::NonCanonicalSignature),
+	}
+}
+
+fn check_signature(signature: &[u8; 64], pubkey: &[u8], payload: &[u8]) -> bool {
+	match <[u8; 32]>::try_from(pubkey) {
+		Ok(pubkey) => sp_io::crypto::sr25519_verify(
+			&Signature::from_raw(*signature),
+			payload,
+			&Public::from_raw(pubkey),
+		),
+		Err(_) => false,
+	}
+}
+
+struct Stack {
+	items: Vec<Vec<u8>>,
+	max_depth: usize,
+}
+
+impl Stack {
+	fn push(&mut self, item: Vec<u8>) -> Result<(), ScriptError> {
+		if self.items.len() >= self.max_depth {
+			return Err(ScriptError::StackOverflow)
+		}
+		self.items.push(item);
+		Ok(())
+	}
+
+	fn pop(&mut self) -> Result<Vec<u8>, ScriptError> {
+		self.items.pop().ok_or(ScriptError::StackUnderflow)
+	}
+
+	fn top(&self) -> Result<&Vec<u8>, ScriptError> {
+		self.items.last().ok_or(ScriptError::StackUnderflow)
+	}
+
+	fn push_bool(&mut self, value: bool) -> Result<(), ScriptError> {
+		self.push(if value { vec![1] } else { Vec::new() })
+	}
+}
+
+/// Read the data of the push opcode at `script[pc]`, returning it with the position after it.
+fn read_push(script: &[u8], pc: usize) -> Result<(&[u8], usize), ScriptError> {
+	let opcode = script[pc];
+	let (len, start) = match opcode {
+		0x01..=0x4b => (opcode as usize, pc + 1),
+		OP_PUSHDATA1 => (*script.get(pc + 1).ok_or(ScriptError::BadPush)? as usize, pc + 2),
+		OP_PUSHDATA2 => {
+			let len = script.get(pc + 1..pc + 3).ok_or(ScriptError::BadPush)?;
+			(u16::from_le_bytes([len[0], len[1]]) as usize, pc + 3)
+		},
+		_ => (0, pc + 1),
+	};
+	if len > MAX_STACK_ITEM_SIZE as usize {
+		return Err(ScriptError::BadPush)
+	}
+	let data = script.get(start..start + len).ok_or(ScriptError::BadPush)?;
+	Ok((data, start + len))
+}
+
+/// One step of a script: pushed data or an opcode.
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum Instruction<'a> {
+	/// Data pushed by `OP_0`, a direct push or `OP_PUSHDATA1`/`OP_PUSHDATA2`
+	Push(&'a [u8]),
+	/// Any other opcode, including `OP_1`..`OP_16`
+	Op(u8),
+}
+
+/// Split `script` into its instructions without running it.
+pub fn instructions(script: &[u8]) -> Result<Vec<Instruction>, ScriptError> {
+	let mut instructions = Vec::new();
+	let mut pc = 0;
+	while pc < script.len() {
+		let (instruction, next) = instruction_at(script, pc)?;
+		instructions.push(instruction);
+		pc = next;
+	}
+	Ok(instructions)
+}
+
+/// The instruction at `script[pc]`, with the position after it.
+pub fn instruction_at(script: &[u8], pc: usize) -> Result<(Instruction, usize), ScriptError> {
+	match *script.get(pc).ok_or(ScriptError::BadPush)? {
+		opcode if opcode <= OP_PUSHDATA2 => {
+			let (data, next) = read_push(script, pc)?;
+			Ok((Instruction::Push(data), next))
+		},
+		opcode => Ok((Instruction::Op(opcode), pc + 1)),
+	}
+}
+
+/// Upper bound on the signatures `script` checks when run with `max_ops`, found without running
+/// it. An `OP_CHECKMULTISIG` counts as many checks as the key count pushed right before it, or
+/// as `max_ops` when the count comes from elsewhere. Each check costs an op, so the bound never
+/// exceeds `max_ops`.
+pub fn signature_checks(script: &[u8], max_ops: u32) -> u32 {
+	let mut checks: u32 = 0;
+	let mut previous = None;
+	let mut pc = 0;
+	// A malformed instruction fails the script before anything after it runs
+	while let Ok((instruction, next)) = instruction_at(script, pc) {
+		checks = checks.saturating_add(match instruction {
+			Instruction::Op(OP_CHECKSIG | OP_CHECKSIGVERIFY) => 1,
+			Instruction::Op(OP_CHECKMULTISIG | OP_CHECKMULTISIGVERIFY) => match previous {
+				Some(Instruction::Op(n @ OP_1..=OP_16)) => u32::from(n - OP_1 + 1),
+				Some(Instruction::Push(data)) =>
+					read_number(data).map_or(max_ops, |n| n.min(max_ops.into()) as u32),
+				_ => max_ops,
+			},
+			_ => 0,
+		});
+		previous = Some(instruction);
+		pc = next;
+	}
+	checks.min(max_ops)
+}
+
+/// The name of a non-push opcode the interpreter knows, other than `OP_1`..`OP_16`.
+pub fn opcode_name(opcode: u8) -> Option<&'static str> {
+	Some(match opcode {
+		OP_IF => "OP_IF",
+		OP_NOTIF => "OP_NOTIF",
+		OP_ELSE => "OP_ELSE",
+		OP_ENDIF => "OP_ENDIF",
+		OP_VERIFY => "OP_VERIFY",
+		OP_RETURN => "OP_RETURN",
+		OP_DROP => "OP_DROP",
+		OP_DUP => "OP_DUP",
+		OP_SWAP => "OP_SWAP",
+		OP_EQUAL => "OP_EQUAL",
+		OP_EQUALVERIFY => "OP_EQUALVERIFY",
+		OP_SHA256 => "OP_SHA256",
+		OP_BLAKE2_256 => "OP_BLAKE2_256",
+		OP_CHECKSIG => "OP_CHECKSIG",
+		OP_CHECKSIGVERIFY => "OP_CHECKSIGVERIFY",
+		OP_CHECKMULTISIG => "OP_CHECKMULTISIG",
+		OP_CHECKMULTISIGVERIFY => "OP_CHECKMULTISIGVERIFY",
+		OP_CHECKLOCKTIMEVERIFY => "OP_CHECKLOCKTIMEVERIFY",
+		_ => return None,
+	})
+}
+
+/// The script in assembly notation, e.g. `OP_2 0x… 0x… OP_2 OP_CHECKMULTISIG`. Unknown opcodes
+/// show as `OP_UNKNOWN_0x..`.
+pub fn disassemble(script: &[u8]) -> Result<String, ScriptError> {
+	let words: Vec<String> = instructions(script)?.into_iter().map(assemble).collect();
+	Ok(words.join(" "))
+}
+
+/// `instruction` in assembly notation.
+pub fn assemble(instruction: Instruction) -> String {
+	match instruction {
+		Instruction::Push(data) if data.is_empty() => "OP_0".into(),
+		Instruction::Push(data) => format!("0x{}", HexDisplay::from(&data)),
+		Instruction::Op(opcode @ OP_1..=OP_16) => format!("OP_{}", opcode - OP_1 + 1),
+		Instruction::Op(opcode) => match opcode_name(opcode) {
+			Some(name) => name.into(),
+			None => format!("OP_UNKNOWN_0x{:02x}", opcode),
+		},
+	}
+}
+
+/// Run `script` on top of `initial_stack`.
+///
+/// Succeeds when the script runs to the end, every conditional is closed and the top of the
+/// stack is true.
+pub fn execute(
+	script: &[u8],
+	initial_stack: Vec<Vec<u8>>,
+	context: &ScriptContext,
+	limits: &ScriptLimits,
+) -> Result<(), ScriptError> {
+	execute_observed(script, initial_stack, context, limits, |_, _, _| {})
+}
+
+/// Run `script` like [`execute`], calling `observe` after each instruction with its position in
+/// the script, whether it was in a branch being executed, and the stack, bottom first.
+pub(crate) fn execute_observed(
+	script: &[u8],
+	initial_stack: Vec<Vec<u8>>,
+	context: &ScriptContext,
+	limits: &ScriptLimits,
+	mut observe: impl FnMut(usize, bool, &[Vec<u8>]),
+) -> Result<(), ScriptError> {
+	if script.len() > limits.max_script_size as usize {
+		return Err(ScriptError::ScriptTooLarge)
+	}
+	if initial_stack.len() > limits.max_stack_depth as usize {
+		return Err(ScriptError::StackOverflow)
+	}
+
+	let mut stack = Stack { items: initial_stack, max_depth: limits.max_stack_depth as usize };
+	// One entry per open `OP_IF`, true when that branch is being executed
+	let mut conditions: Vec<bool> = Vec::new();
+	let mut ops: u32 = 0;
+	let mut pc = 0;
+	// The instruction last started, observed once it completes
+	let mut last = None;
+
+	loop {
+		if let Some((at, executing)) = last.take() {
+			observe(at, executing, &stack.items);
+		}
+		if pc >= script.len() {
+			break
+		}
+		let opcode = script[pc];
+		let executing = conditions.iter().all(|taken| *taken);
+		last = Some((pc, executing));
+
+		if opcode <= OP_PUSHDATA2 {
+			let (data, next) = read_push(script, pc)?;
+			if executing {
+				stack.push(data.to_vec())?;
+			}
+			pc = next;
+			continue
+		}
+		pc += 1;
+
+		if opcode > OP_16 {
+			ops += 1;
+			if ops > limits.max_ops {
+				return Err(ScriptError::TooManyOps)
+			}
+		}
+
+		match opcode {
+			OP_IF | OP_NOTIF => {
+				let taken = if executing {
+					let condition = cast_to_bool(&stack.pop()?);
+					if opcode == OP_IF { condition } else { !condition }
+				} else {
+					false
+				};
+				conditions.push(taken);
+				continue
+			},
+			OP_ELSE => {
+				let taken = conditions.last_mut().ok_or(ScriptError::UnbalancedConditional)?;
+				*taken = !*taken;
+				continue
+			},
+			OP_ENDIF => {
+				conditions.pop().ok_or(ScriptError::UnbalancedConditional)?;
+				continue
+			},
+			_ if !executing => continue,
+			_ => {},
+		}
+
+		match opcode {
+			OP_1..=OP_16 => stack.push(vec![opcode - OP_1 + 1])?,
+			OP_VERIFY =>
+				if !cast_to_bool(&stack.pop()?) {
+					return Err(ScriptError::VerifyFailed)
+				},
+			OP_DROP => {
+				stack.pop()?;
+			},
+			OP_DUP => {
+				let top = stack.top()?.clone();
+				stack.push(top)?;
+			},
+			OP_SWAP => {
+				let a = stack.pop()?;
+				let b = stack.pop()?;
+				stack.push(a)?;
+				stack.push(b)?;
+			},
+			OP_EQUAL | OP_EQUALVERIFY => {
+				let equal = stack.pop()? == stack.pop()?;
+				if opcode == OP_EQUALVERIFY {
+					if !equal {
+						return Err(ScriptError::VerifyFailed)
+					}
+				} else {
+					stack.push_bool(equal)?;
+				}
+			},
+			OP_SHA256 => {
+				let item = stack.pop()?;
+				stack.push(sp_io::hashing::sha2_256(&item).to_vec())?;
+			},
+			OP_BLAKE2_256 => {
+				let item = stack.pop()?;
+				stack.push(sp_io::hashing::blake2_256(&item).to_vec())?;
+			},
+			OP_CHECKSIG | OP_CHECKSIGVERIFY => {
+				let pubkey = stack.pop()?;
+				let signature = decode_signature(&stack.pop()?)?;
+				let valid = signature
+					.is_some_and(|signature| check_signature(&signature, &pubkey, context.payload));
+				if opcode == OP_CHECKSIGVERIFY {
+					if !valid {
+						return Err(ScriptError::VerifyFailed)
+					}
+				} else if !valid && signature.is_some() {
+					return Err(ScriptError::NullFail)
+				} else {
+					stack.push_bool(valid)?;
+				}
+			},
+			OP_CHECKMULTISIG | OP_CHECKMULTISIGVERIFY => {
+				// <sig_1> .. <sig_m> <m> <pubkey_1> .. <pubkey_n> <n>, signatures in key order
+				let n = read_number(&stack.pop()?)? as usize;
+				if n > limits.max_stack_depth as usize {
+					return Err(ScriptError::InvalidNumber)
+				}
+				ops = ops.saturating_add(n as u32);
+				if ops > limits.max_ops {
+					return Err(ScriptError::TooManyOps)
+				}
+				let mut pubkeys = Vec::with_capacity(n);
+				for _ in 0..n {
+					pubkeys.push(stack.pop()?);
+				}
+				let m = read_number(&stack.pop()?)? as usize;
+				if m > n {
+					return Err(ScriptError::InvalidNumber)
+				}
+				let mut signatures = Vec::with_capacity(m);
+				for _ in 0..m {
+					signatures.push(decode_signature(&stack.pop()?)?);
+				}
+
+				// Both were popped last first; walk them from the top of the stack down
+				let mut keys = pubkeys.iter();
+				let valid = signatures.iter().all(|signature| {
+					signature.is_some_and(|signature| {
+						keys.any(|pubkey| check_signature(&signature, pubkey, context.payload))
+					})
+				});
+				if opcode == OP_CHECKMULTISIGVERIFY {
+					if !valid {
+						return Err(ScriptError::VerifyFailed)
+					}
+				} else if !valid && signatures.iter().any(Option::is_some) {
+					return Err(ScriptError::NullFail)
+				} else {
+					stack.push_bool(valid)?;
+				}
+			},
+			OP_CHECKLOCKTIMEVERIFY =>
+				if read_number(stack.top()?)? > context.block_number {
+					return Err(ScriptError::LockTimeNotReached)
+				},
+			_ => return Err(ScriptError::InvalidOpcode),
+		}
+	}
+
+	if !conditions.is_empty() {
+		return Err(ScriptError::UnbalancedConditional)
+	}
+	match stack.items.last() {
+		Some(top) if cast_to_bool(top) => Ok(()),
+		_ => Err(ScriptError::EvalFalse),
+	}
+}
@@ -1,21 +1,37 @@
 use super::*;
-use crate::mock::{new_test_ext, Test, Utxo};
-use frame_support::{assert_noop, assert_ok};
+use codec::Encode;
+use crate::mock::{new_test_ext, MaxMoney, RuntimeEvent, RuntimeOrigin, System, Test, Utxo, MOCK_SEAL};
+use frame_support::{assert_noop, assert_ok, traits::{Get, Hooks}, BoundedVec};
 use sp_core::{
-    sr25519::{Public, Signature},
+    sr25519::{self, Public, Signature},
+    Pair,
     testing::SR25519,
     H256, H512,
 };
-use sp_runtime::traits::BlakeTwo256;
+use frame_system::pallet_prelude::HeaderFor;
+use sp_runtime::{traits::{BlakeTwo256, Header as _}, Digest};
+
+/// Sign the inputs, all spending outputs of `pair`: the first carries the signature, the others
+/// rely on it.
+fn sign_inputs(transaction: &mut Transaction, pair: &sr25519::Pair) {
+    let payload = transaction.signing_payload();
+    let sigscript = H512::from_slice(pair.sign(&payload).as_ref());
+    for input in transaction.inputs.iter_mut() {
+        input.sigscript = None;
+    }
+    transaction.inputs[0].sigscript = Some(sigscript);
+}
 
 fn create_test_transaction(inputs: Vec<(H256, H512)>, outputs: Vec<(Value, H256)>) -> Transaction {
     Transaction {
+        version: TRANSACTION_VERSION,
         inputs: BoundedVec::try_from(
             inputs
                 .into_iter()
                 .map(|(outpoint, sigscript)| TransactionInput {
                     outpoint,
-                    sigscript,
+                    sigscript: Some(sigscript),
+                    witness: None,
                 })
                 .collect::<Vec<_>>(),
         )
@@ -23,7 +39,7 @@ fn create_test_transaction(inputs: Vec<(H256, H512)>, outputs: Vec<(Value, H256)
         outputs: BoundedVec::try_from(
             outputs
                 .into_iter()
-                .map(|(value, pubkey)| TransactionOutput { value, pubkey })
+                .map(|(value, pubkey)| TransactionOutput { value, pubkey, memo: None })
                 .collect::<Vec<_>>(),
         )
         .unwrap(),
@@ -37,6 +53,7 @@ fn test_simple_transaction() {
         let genesis_utxo = TransactionOutput {
             value: 100,
             pubkey: H256::random(),
+            memo: None,
         };
         let genesis_hash = BlakeTwo256::hash_of(&genesis_utxo);
         UtxoStore::<Test>::insert(genesis_hash, genesis_utxo.clone());
@@ -57,13 +74,36 @@ fn test_simple_transaction() {
         assert!(UtxoStore::<Test>::get(genesis_hash).is_none());
 
         // Verify new UTXO exists
-        let new_hash = BlakeTwo256::hash_of(&(&transaction.encode(), 0u64));
-        let new_utxo = UtxoStore::<Test>::get(new_hash).unwrap();
+        let new_hash = transaction.outpoint(0);
+        let new_utxo = Utxo::utxo(&new_hash).unwrap();
         assert_eq!(new_utxo.value, 50);
         assert_eq!(new_utxo.pubkey, new_pubkey);
     });
 }
 
+#[test]
+fn test_spend_event_reports_sums_and_fee() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let pair = sr25519::Pair::from_seed(&[3; 32]);
+        let pubkey = H256(pair.public().0);
+        let funding = [TransactionOutput { value: 70, pubkey, memo: None }, TransactionOutput { value: 30, pubkey, memo: None }];
+        let outpoints: Vec<_> = funding.iter().map(|utxo| {
+            let hash = BlakeTwo256::hash_of(utxo);
+            UtxoStore::<Test>::insert(hash, utxo.clone());
+            (hash, H512::zero())
+        }).collect();
+
+        let mut transaction = create_test_transaction(outpoints, vec![(60, H256::random()), (25, pubkey)]);
+        sign_inputs(&mut transaction, &pair);
+        assert_ok!(Utxo::spend(RuntimeOrigin::none(), transaction.clone()));
+
+        System::assert_last_event(
+            Event::TransactionSuccess { transaction, input_total: 100, output_total: 85, fee: 15 }.into(),
+        );
+    });
+}
+
 #[test]
 fn test_invalid_transaction() {
     new_test_ext().execute_with(|| {
@@ -102,6 +142,7 @@ fn test_output_exceeds_input() {
         let genesis_utxo = TransactionOutput {
             value: 100,
             pubkey: H256::random(),
+            memo: None,
         };
         let genesis_hash = BlakeTwo256::hash_of(&genesis_utxo);
         UtxoStore::<Test>::insert(genesis_hash, genesis_utxo.clone());
@@ -118,6 +159,24 @@ fn test_output_exceeds_input() {
     });
 }
 
+#[test]
+fn test_utxo_storage_footprint() {
+    use codec::MaxEncodedLen;
+    use frame_support::traits::StorageInfoTrait;
+
+    // Every item is bounded, so the footprint of an output is a true worst case
+    let info = Utxo::storage_info();
+    assert!(info.iter().all(|item| item.max_size.is_some()), "unbounded storage: {info:?}");
+
+    // Each entry takes the 32 bytes of its prefix on top of its key and value
+    let footprint = |name: &[u8]| {
+        let item = info.iter().find(|item| item.storage_name == name).unwrap();
+        32 + item.max_size.unwrap()
+    };
+    assert_eq!(footprint(b"UtxoStore"), 64 + TransactionOutput::max_encoded_len() as u32);
+    assert_eq!(footprint(b"UtxoStore") + footprint(b"MemoOutputs"), MAX_UTXO_STORAGE_BYTES);
+}
+
 #[test]
 fn test_zero_value_output() {
     new_test_ext().execute_with(|| {
@@ -133,6 +192,26 @@ fn test_zero_value_output() {
     });
 }
 
+#[test]
+fn test_outputs_to_the_zero_pubkey_are_rejected() {
+    new_test_ext().execute_with(|| {
+        let transaction = create_test_transaction(
+            vec![(H256::random(), H512::zero())],
+            vec![(50, H256::random()), (50, H256::zero())],
+        );
+        assert_noop!(
+            Utxo::validate_transaction(&transaction),
+            Error::<Test>::UnspendableDestination
+        );
+
+        // Minting to it would lose the issuance just the same
+        let outputs: BoundedVec<_, _> = vec![TransactionOutput { value: 500, pubkey: H256::zero(), memo: None }]
+            .try_into()
+            .unwrap();
+        assert_noop!(Utxo::mint(RuntimeOrigin::root(), outputs), Error::<Test>::UnspendableDestination);
+    });
+}
+
 #[test]
 fn test_reward_dispersion() {
     new_test_ext().execute_with(|| {
@@ -152,10 +231,1206 @@ fn test_reward_dispersion() {
         let utxo_hash = BlakeTwo256::hash_of(&(&TransactionOutput {
             value: 200, // 100 from reward + 100 from issuance
             pubkey: H256::from_slice(author.as_slice()),
+            memo: None,
         }, 0u64));
 
         let author_utxo = UtxoStore::<Test>::get(utxo_hash).unwrap();
         assert_eq!(author_utxo.value, 200);
         assert_eq!(author_utxo.pubkey, H256::from_slice(author.as_slice()));
     });
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_deposit_derivation_round_trip() {
+    use sp_core::{sr25519, Pair};
+
+    let root = sr25519::Pair::from_string("//Custodian", None).unwrap();
+    let root_public = root.public();
+
+    let pubkey = derivation::deposit_pubkey(&root_public, 7, 42).unwrap();
+    // The secret side derives the same key as the watch-only side
+    let pair = derivation::deposit_pair(&root, 7, 42);
+    assert_eq!(H256::from_slice(pair.public().as_ref()), pubkey);
+
+    // Distinct users get distinct deposit keys
+    assert_ne!(derivation::deposit_pubkey(&root_public, 7, 43).unwrap(), pubkey);
+    assert_ne!(derivation::deposit_pubkey(&root_public, 8, 42).unwrap(), pubkey);
+
+    // And the key maps back to its index
+    assert_eq!(derivation::find_deposit_index(&root_public, 7, &pubkey, 0..100), Some(42));
+    assert_eq!(derivation::find_deposit_index(&root_public, 8, &pubkey, 0..100), None);
+}
+
+#[test]
+fn test_txid_excludes_sigscripts() {
+    let outpoint = H256::random();
+    let transaction = create_test_transaction(
+        vec![(outpoint, H512::zero())],
+        vec![(50, H256::random())],
+    );
+    let mut mutated = transaction.clone();
+    mutated.inputs[0].sigscript = Some(H512::repeat_byte(1));
+
+    // Changing the witness changes the wtxid but neither the txid nor the outpoints
+    assert_eq!(transaction.txid(), mutated.txid());
+    assert_eq!(transaction.outpoint(0), mutated.outpoint(0));
+    assert_ne!(transaction.wtxid(), mutated.wtxid());
+}
+
+#[test]
+fn test_signing_payload_is_encoding_of_cleared_transaction() {
+    let mut transaction = create_test_transaction(
+        vec![(H256::random(), H512::random()), (H256::random(), H512::random())],
+        vec![(50, H256::random()), (25, H256::random())],
+    );
+    transaction.inputs[1].witness = Some(ScriptWitness {
+        script: BoundedVec::truncate_from(vec![0x51; 100]),
+        stack: BoundedVec::truncate_from(vec![BoundedVec::truncate_from(vec![1, 2, 3])]),
+    });
+    transaction.outputs[1].memo = Some(H256::random());
+
+    let mut cleared = transaction.clone();
+    for input in cleared.inputs.iter_mut() {
+        input.sigscript = None;
+        input.witness = None;
+    }
+    let payload = transaction.signing_payload();
+    assert_eq!(payload, cleared.encode());
+    // Allocated at its final size
+    assert_eq!(payload.capacity(), payload.len());
+
+    assert_eq!(
+        Transaction::outpoint_of(&transaction.txid(), 7),
+        BlakeTwo256::hash_of(&(transaction.txid(), 7u64))
+    );
+}
+
+#[test]
+fn test_pre_dispatch_enforces_application_order() {
+    use sp_runtime::transaction_validity::{InvalidTransaction, TransactionValidityError};
+    use frame_support::unsigned::ValidateUnsigned;
+
+    new_test_ext().execute_with(|| {
+        let pair = sr25519::Pair::from_seed(&[1; 32]);
+        let owner = H256(pair.public().0);
+        let genesis_utxo = TransactionOutput { value: 100, pubkey: owner, memo: None };
+        let genesis_hash = BlakeTwo256::hash_of(&genesis_utxo);
+        UtxoStore::<Test>::insert(genesis_hash, genesis_utxo);
+        let missing_input = Err(TransactionValidityError::Invalid(InvalidTransaction::Custom(
+            Error::<Test>::MissingInputUtxo.encode()[0],
+        )));
+
+        let mut a = create_test_transaction(vec![(genesis_hash, H512::zero())], vec![(90, owner)]);
+        sign_inputs(&mut a, &pair);
+        let mut b = create_test_transaction(vec![(a.outpoint(0), H512::zero())], vec![(80, owner)]);
+        sign_inputs(&mut b, &pair);
+        let mut conflict = create_test_transaction(vec![(genesis_hash, H512::zero())], vec![(50, owner)]);
+        sign_inputs(&mut conflict, &pair);
+
+        // The pool accepts B as waiting for A, a block may not apply it first
+        assert!(!Utxo::validate_transaction(&b).unwrap().requires.is_empty());
+        assert_eq!(Utxo::pre_dispatch(&Call::spend { transaction: b.clone() }), missing_input);
+
+        assert_ok!(Utxo::pre_dispatch(&Call::spend { transaction: a.clone() }));
+        assert_ok!(Utxo::update_storage(&a, 10));
+        assert_ok!(Utxo::pre_dispatch(&Call::spend { transaction: b.clone() }));
+
+        // Once A is applied, a conflicting spend of the same output cannot be
+        assert_eq!(Utxo::pre_dispatch(&Call::spend { transaction: conflict }), missing_input);
+    });
+}
+
+#[test]
+fn test_chained_spend_within_block() {
+    new_test_ext().execute_with(|| {
+        let pair = sr25519::Pair::from_seed(&[1; 32]);
+        let owner = H256::from_slice(pair.public().as_ref());
+        let genesis_utxo = TransactionOutput { value: 100, pubkey: owner, memo: None };
+        let genesis_hash = BlakeTwo256::hash_of(&genesis_utxo);
+        UtxoStore::<Test>::insert(genesis_hash, genesis_utxo);
+
+        // A spends the genesis output back to the same key
+        let mut a = create_test_transaction(vec![(genesis_hash, H512::zero())], vec![(90, owner)]);
+        sign_inputs(&mut a, &pair);
+        assert_ok!(Utxo::validate_transaction(&a));
+        assert_ok!(Utxo::update_storage(&a, 10));
+
+        // B spends A's output before the block is finalized
+        let a_outpoint = a.outpoint(0);
+        let mut b = create_test_transaction(vec![(a_outpoint, H512::zero())], vec![(80, H256::random())]);
+        sign_inputs(&mut b, &pair);
+        let validity = Utxo::validate_transaction(&b).unwrap();
+        assert!(validity.requires.is_empty());
+        assert_ok!(Utxo::update_storage(&b, 10));
+
+        // A's output never reaches the UTXO set, B's does once the block is finalized
+        Utxo::on_finalize(1);
+        assert!(UtxoStore::<Test>::get(a_outpoint).is_none());
+        assert!(UtxoStore::<Test>::get(b.outpoint(0)).is_some());
+        assert_eq!(BlockOutputs::<Test>::iter().count(), 0);
+    });
+}
+
+#[test]
+fn test_output_exceeds_max_money() {
+    new_test_ext().execute_with(|| {
+        let transaction = create_test_transaction(
+            vec![(H256::random(), H512::zero())],
+            vec![(MaxMoney::get() + 1, H256::random())],
+        );
+
+        assert_noop!(
+            Utxo::validate_transaction(&transaction),
+            Error::<Test>::ExceedsMaxMoney
+        );
+    });
+}
+
+#[test]
+fn test_issuance_capped_at_max_money() {
+    new_test_ext().execute_with(|| {
+        // Only 30 units of headroom left below MaxMoney, 100 would be issued
+        UtxoTotalValue::<Test>::put(MaxMoney::get() - 50);
+        RewardTotal::<Test>::put(20);
+
+        let author = Public::from_raw([0; 32]);
+        Utxo::disperse_reward(&author);
+
+        assert_eq!(UtxoTotalValue::<Test>::get(), MaxMoney::get());
+    });
+}
+
+#[test]
+fn test_utxo_total_tracks_the_outputs_spent_and_created() {
+    new_test_ext().execute_with(|| {
+        let owner = H256::random();
+        let utxo = TransactionOutput { value: 100, pubkey: owner, memo: None };
+        let outpoint = BlakeTwo256::hash_of(&utxo);
+        UtxoStore::<Test>::insert(outpoint, utxo);
+        UtxoTotalValue::<Test>::put(100);
+
+        // A transaction counting its one input twice, had validation let it through, grows the
+        // set, and the total grows with it rather than hiding the inflation
+        let doubled = create_test_transaction(
+            vec![(outpoint, H512::zero()), (outpoint, H512::zero())],
+            vec![(150, owner)],
+        );
+        assert_ok!(Utxo::update_storage(&doubled, 50));
+        let set_value: Value = UtxoStore::<Test>::iter_values()
+            .chain(BlockOutputs::<Test>::iter_values())
+            .map(|utxo| utxo.value)
+            .sum();
+        assert_eq!(UtxoTotalValue::<Test>::get(), set_value);
+        assert_eq!(set_value, 150);
+        #[cfg(feature = "try-runtime")]
+        assert_ok!(Utxo::try_state(0));
+
+        // Past MaxMoney such a transaction fails
+        let outpoint = doubled.outpoint(0);
+        let doubled = create_test_transaction(
+            vec![(outpoint, H512::zero()), (outpoint, H512::zero())],
+            vec![(MaxMoney::get(), owner), (1, owner)],
+        );
+        assert_eq!(
+            Utxo::update_storage(&doubled, 0),
+            Err(Error::<Test>::ExceedsMaxMoney.into())
+        );
+    });
+}
+
+#[test]
+fn test_reward_dispersal_saturates_at_value_bounds() {
+    new_test_ext().execute_with(|| {
+        let author = Public::from_raw([0; 32]);
+        let pubkey = H256(author.0);
+        UtxoTotalValue::<Test>::put(Value::MAX - 1);
+        RewardTotal::<Test>::put(Value::MAX);
+        AuthorStats::<Test>::insert(
+            pubkey,
+            AuthorStatistics { blocks_authored: u32::MAX, uncles_rewarded: 0, rewards_earned: Value::MAX - 1 },
+        );
+        assert_eq!(Utxo::pending_reward(), Value::MAX);
+
+        // Nothing is issued past MaxMoney, and the fees, the totals and the counters saturate
+        Utxo::disperse_reward(&author);
+        assert_eq!(TotalIssuance::<Test>::get(), 0);
+        assert_eq!(UtxoTotalValue::<Test>::get(), Value::MAX);
+        assert_eq!(
+            AuthorStats::<Test>::get(pubkey),
+            AuthorStatistics { blocks_authored: u32::MAX, uncles_rewarded: 0, rewards_earned: Value::MAX },
+        );
+
+        // Smoothed rewards saturate the pending balances the same way
+        crate::mock::RewardSmoothing::set(4);
+        RewardTotal::<Test>::put(Value::MAX);
+        PendingRewardTotal::<Test>::put(Value::MAX - 1);
+        let earlier = PendingReward { balance: Value::MAX - 1, blocks: 1 };
+        PendingRewards::<Test>::insert(pubkey, BoundedVec::truncate_from(vec![earlier.clone()]));
+        Utxo::disperse_reward(&author);
+        assert_eq!(
+            PendingRewards::<Test>::get(pubkey).unwrap().into_inner(),
+            vec![earlier, PendingReward { balance: Value::MAX, blocks: 4 }],
+        );
+        assert_eq!(PendingRewardTotal::<Test>::get(), Value::MAX);
+    });
+}
+
+#[test]
+fn test_pending_reward_matches_next_payout() {
+    new_test_ext().execute_with(|| {
+        let author = Public::from_raw([0; 32]);
+        RewardTotal::<Test>::put(20);
+        assert_eq!(Utxo::pending_reward(), 120);
+
+        // Capped like the payout itself once MaxMoney is near
+        UtxoTotalValue::<Test>::put(MaxMoney::get() - 50);
+        assert_eq!(Utxo::pending_reward(), 50);
+        System::set_block_number(1);
+        Utxo::disperse_reward(&author);
+        assert_eq!(UtxoTotalValue::<Test>::get() - (MaxMoney::get() - 50), 50);
+    });
+}
+
+#[test]
+fn test_mint_requires_mint_origin() {
+    new_test_ext().execute_with(|| {
+        let outputs: BoundedVec<_, _> = vec![TransactionOutput { value: 500, pubkey: H256::random(), memo: None }]
+            .try_into()
+            .unwrap();
+
+        assert_noop!(
+            Utxo::mint(RuntimeOrigin::signed(1), outputs.clone()),
+            sp_runtime::DispatchError::BadOrigin
+        );
+
+        assert_ok!(Utxo::mint(RuntimeOrigin::root(), outputs));
+        assert_eq!(TotalIssuance::<Test>::get(), 500);
+        assert_eq!(UtxoTotalValue::<Test>::get(), 500);
+        assert_eq!(BlockOutputs::<Test>::iter().count(), 1);
+    });
+}
+
+#[test]
+fn test_pool_rejections_are_distinguishable() {
+    use sp_runtime::transaction_validity::{InvalidTransaction, TransactionSource, TransactionValidityError};
+    use frame_support::unsigned::ValidateUnsigned;
+
+    new_test_ext().execute_with(|| {
+        let code_of = |error: Error<Test>| InvalidTransaction::Custom(error.encode()[0]);
+
+        let dust = create_test_transaction(
+            vec![(H256::random(), H512::zero())],
+            vec![(0, H256::random())],
+        );
+        assert_eq!(
+            Utxo::validate_unsigned(TransactionSource::External, &Call::spend { transaction: dust }),
+            Err(TransactionValidityError::Invalid(code_of(Error::<Test>::ZeroValueOutput)))
+        );
+
+        let genesis_utxo = TransactionOutput { value: 100, pubkey: H256::random(), memo: None };
+        let genesis_hash = BlakeTwo256::hash_of(&genesis_utxo);
+        UtxoStore::<Test>::insert(genesis_hash, genesis_utxo);
+        let mut forged = create_test_transaction(
+            vec![(genesis_hash, H512::zero())],
+            vec![(50, H256::random())],
+        );
+        assert_eq!(
+            Utxo::validate_unsigned(TransactionSource::External, &Call::spend { transaction: forged.clone() }),
+            Err(TransactionValidityError::Invalid(code_of(Error::<Test>::NonCanonicalSignature)))
+        );
+        sign_inputs(&mut forged, &sr25519::Pair::from_seed(&[9; 32]));
+        assert_eq!(
+            Utxo::validate_unsigned(TransactionSource::External, &Call::spend { transaction: forged }),
+            Err(TransactionValidityError::Invalid(code_of(Error::<Test>::InvalidSignature)))
+        );
+
+        let mut future = create_test_transaction(vec![(genesis_hash, H512::zero())], vec![(50, H256::random())]);
+        future.version = TRANSACTION_VERSION + 1;
+        assert_eq!(
+            Utxo::validate_unsigned(TransactionSource::External, &Call::spend { transaction: future.clone() }),
+            Err(TransactionValidityError::Invalid(code_of(Error::<Test>::UnsupportedVersion)))
+        );
+        assert_noop!(Utxo::spend(RuntimeOrigin::none(), future), Error::<Test>::UnsupportedVersion);
+    });
+}
+
+#[test]
+fn test_canonical_ordering_is_enforced_from_its_activation_block() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(10);
+        let mut transaction = create_test_transaction(
+            vec![(H256::repeat_byte(2), H512::zero()), (H256::repeat_byte(1), H512::zero())],
+            vec![(50, H256::repeat_byte(3)), (40, H256::repeat_byte(4))],
+        );
+        assert!(!transaction.is_canonically_ordered());
+        assert_ok!(Utxo::validate_transaction(&transaction));
+
+        // Activated from the next block, the rule leaves earlier ones alone
+        crate::mock::CanonicalOrderingFrom::set(11);
+        assert_ok!(Utxo::validate_transaction(&transaction));
+
+        System::set_block_number(11);
+        assert_noop!(Utxo::validate_transaction(&transaction), Error::<Test>::NonCanonicalOrder);
+
+        transaction.sort_canonically();
+        assert!(transaction.is_canonically_ordered());
+        assert_eq!(transaction.inputs[0].outpoint, H256::repeat_byte(1));
+        assert_eq!(transaction.outputs[0].value, 40);
+        assert_ok!(Utxo::validate_transaction(&transaction));
+    });
+}
+
+/// Lock a fresh UTXO to `script` and build a transaction spending it with `stack` as witness.
+fn script_spend(script: Vec<u8>, stack: Vec<Vec<u8>>) -> Transaction {
+    let utxo = TransactionOutput { value: 100, pubkey: script::script_hash(&script), memo: None };
+    let outpoint = BlakeTwo256::hash_of(&utxo);
+    UtxoStore::<Test>::insert(outpoint, utxo);
+
+    let mut transaction = create_test_transaction(vec![(outpoint, H512::zero())], vec![(90, H256::random())]);
+    transaction.inputs[0].sigscript = None;
+    transaction.inputs[0].witness = Some(script::ScriptWitness {
+        script: script.try_into().unwrap(),
+        stack: stack
+            .into_iter()
+            .map(|item| item.try_into().unwrap())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap(),
+    });
+    transaction
+}
+
+#[test]
+fn test_descriptors() {
+    use descriptor::{Descriptor, DescriptorError};
+    use sp_core::crypto::Ss58Codec;
+
+    new_test_ext().execute_with(|| {
+        let alice = sr25519::Pair::from_seed(&[1; 32]);
+        let bob = sr25519::Pair::from_seed(&[2; 32]);
+        let (a, b) = (H256(alice.public().0), H256(bob.public().0));
+
+        // Display is canonical and parses back, whatever the key encoding on input
+        let multi: Descriptor = format!("multi(2, {}, {:?})", alice.public().to_ss58check(), b).parse().unwrap();
+        assert_eq!(multi, Descriptor::Multi { threshold: 2, keys: vec![a, b] });
+        assert_eq!(multi.to_string().parse::<Descriptor>(), Ok(multi.clone()));
+        assert_eq!(format!("pk({:?})", a).parse(), Ok(Descriptor::Pk(a)));
+
+        // A multi descriptor watches the same output as the equivalent hand-built script
+        let redeem = script::ScriptBuilder::new()
+            .push_int(2)
+            .push_data(alice.public().as_ref())
+            .push_data(bob.public().as_ref())
+            .push_int(2)
+            .push_opcode(script::OP_CHECKMULTISIG)
+            .into_script();
+        assert!(multi.matches(&TransactionOutput { value: 1, pubkey: script::script_hash(&redeem), memo: None }));
+        assert_eq!(format!("script({})", sp_core::bytes::to_hex(&redeem, false)).parse::<Descriptor>().unwrap().pubkey(), multi.pubkey());
+
+        // The recipient of an HTLC claims with the preimage
+        let preimage = b"secret".to_vec();
+        let hash = H256(sp_io::hashing::sha2_256(&preimage));
+        let htlc: Descriptor = format!("htlc({:?},{:?},{:?},50)", a, b, hash).parse().unwrap();
+        assert_eq!(htlc.to_string().parse::<Descriptor>(), Ok(htlc.clone()));
+        let mut transaction = script_spend(htlc.script().unwrap(), vec![]);
+        let signature = alice.sign(&transaction.signing_payload()).as_ref().to_vec();
+        transaction.inputs[0].witness.as_mut().unwrap().stack =
+            vec![signature.try_into().unwrap(), preimage.try_into().unwrap(), vec![1].try_into().unwrap()]
+                .try_into()
+                .unwrap();
+        assert_ok!(Utxo::validate_transaction(&transaction));
+
+        assert_eq!("multi(3,0x00)".parse::<Descriptor>(), Err(DescriptorError::InvalidKey("0x00".into())));
+        assert_eq!(format!("multi(3,{:?},{:?})", a, b).parse::<Descriptor>(), Err(DescriptorError::InvalidThreshold));
+        assert_eq!(format!("pk({:?},{:?})", a, b).parse::<Descriptor>(), Err(DescriptorError::WrongArgumentCount));
+        assert_eq!("wsh(0x00)".parse::<Descriptor>(), Err(DescriptorError::UnknownDescriptor("wsh".into())));
+    });
+}
+
+#[test]
+fn test_utxos_by_memo() {
+    new_test_ext().execute_with(|| {
+        let pair = sr25519::Pair::from_seed(&[1; 32]);
+        let owner = H256(pair.public().0);
+        let genesis_utxo = TransactionOutput { value: 100, pubkey: owner, memo: None };
+        let genesis_hash = BlakeTwo256::hash_of(&genesis_utxo);
+        UtxoStore::<Test>::insert(genesis_hash, genesis_utxo);
+
+        // Two payments referencing the same invoice, the memo is part of the txid
+        let invoice = BlakeTwo256::hash_of(b"invoice 42");
+        let mut payment = create_test_transaction(vec![(genesis_hash, H512::zero())], vec![(40, owner), (50, owner)]);
+        let txid = payment.txid();
+        payment.outputs[0].memo = Some(invoice);
+        payment.outputs[1].memo = Some(invoice);
+        assert_ne!(payment.txid(), txid);
+        sign_inputs(&mut payment, &pair);
+        assert_ok!(Utxo::validate_transaction(&payment));
+        assert_ok!(Utxo::update_storage(&payment, 10));
+
+        // Listed once the block is finalized
+        assert!(Utxo::utxos_by_memo(invoice).is_empty());
+        Utxo::on_finalize(1);
+        let mut found = Utxo::utxos_by_memo(invoice);
+        found.sort();
+        let mut expected = vec![(payment.outpoint(0), payment.outputs[0].clone()), (payment.outpoint(1), payment.outputs[1].clone())];
+        expected.sort();
+        assert_eq!(found, expected);
+        assert!(Utxo::utxos_by_memo(H256::zero()).is_empty());
+
+        // Spent outputs leave the index
+        let mut spend = create_test_transaction(vec![(payment.outpoint(0), H512::zero())], vec![(30, owner)]);
+        sign_inputs(&mut spend, &pair);
+        assert_ok!(Utxo::update_storage(&spend, 10));
+        assert_eq!(Utxo::utxos_by_memo(invoice), vec![(payment.outpoint(1), payment.outputs[1].clone())]);
+        assert!(!MemoOutputs::<Test>::contains_key(invoice, payment.outpoint(0)));
+    });
+}
+
+#[test]
+fn test_vault() {
+    use vault::{Vault, VaultError, VaultKey};
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        let owner = sr25519::Pair::from_seed(&[1; 32]);
+        let recovery = sr25519::Pair::from_seed(&[2; 32]);
+        let vault = Vault::new(H256(owner.public().0), H256(recovery.public().0), 10);
+
+        // The deposit pays the throwaway key, which only ever signs the unvault transaction
+        let key = VaultKey::generate();
+        let deposit = TransactionOutput { value: 100, pubkey: key.pubkey(), memo: None };
+        let deposit_outpoint = BlakeTwo256::hash_of(&deposit);
+        UtxoStore::<Test>::insert(deposit_outpoint, deposit);
+        let unvault = key.presign_unvault(&vault, deposit_outpoint, 100, 10).unwrap();
+        assert_eq!(unvault.outputs[0], TransactionOutput { value: 90, pubkey: vault.unvault_pubkey(), memo: None });
+        assert_ok!(Utxo::validate_transaction(&unvault));
+        assert_ok!(Utxo::update_storage(&unvault, 10));
+        Utxo::on_finalize(1);
+
+        // The owner waits for the unlock height, the recovery key does not
+        let payout = vec![TransactionOutput { value: 80, pubkey: H256::random(), memo: None }];
+        let withdrawal = vault.withdraw(&unvault, payout.clone(), &owner).unwrap();
+        assert_noop!(Utxo::validate_transaction(&withdrawal), Error::<Test>::ScriptFailed);
+        assert_ok!(Utxo::validate_transaction(&vault.recover(&unvault, payout.clone(), &recovery).unwrap()));
+        System::set_block_number(10);
+        assert_ok!(Utxo::validate_transaction(&withdrawal));
+
+        // Each path only takes its own key
+        assert_eq!(vault.withdraw(&unvault, payout.clone(), &recovery), Err(VaultError::WrongKey));
+        assert_eq!(vault.recover(&unvault, vec![], &recovery), Err(VaultError::InvalidOutputs));
+        assert_eq!(VaultKey::generate().presign_unvault(&vault, deposit_outpoint, 10, 10), Err(VaultError::FeeTooHigh));
+    });
+}
+
+#[test]
+fn test_multisig_script_spend() {
+    new_test_ext().execute_with(|| {
+        let alice = sr25519::Pair::from_seed(&[1; 32]);
+        let bob = sr25519::Pair::from_seed(&[2; 32]);
+        let redeem = script::ScriptBuilder::new()
+            .push_int(2)
+            .push_data(alice.public().as_ref())
+            .push_data(bob.public().as_ref())
+            .push_int(2)
+            .push_opcode(script::OP_CHECKMULTISIG)
+            .into_script();
+
+        // Witnesses are not part of the signing payload, so sign before attaching signatures
+        let mut transaction = script_spend(redeem.clone(), vec![]);
+        let payload = transaction.signing_payload();
+        let signatures = vec![alice.sign(&payload).as_ref().to_vec(), bob.sign(&payload).as_ref().to_vec()];
+        transaction.inputs[0].witness.as_mut().unwrap().stack = signatures
+            .iter()
+            .cloned()
+            .map(|item| item.try_into().unwrap())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+        assert_ok!(Utxo::validate_transaction(&transaction));
+
+        // Signatures out of key order do not satisfy the script
+        transaction.inputs[0].witness.as_mut().unwrap().stack.swap(0, 1);
+        assert_noop!(Utxo::validate_transaction(&transaction), Error::<Test>::ScriptFailed);
+
+        // Nor does a script other than the one the output commits to
+        transaction.inputs[0].witness.as_mut().unwrap().script =
+            script::ScriptBuilder::new().push_int(1).into_script().try_into().unwrap();
+        assert_noop!(Utxo::validate_transaction(&transaction), Error::<Test>::ScriptHashMismatch);
+    });
+}
+
+#[test]
+fn test_signatures_have_a_single_encoding() {
+    use script::{OP_CHECKSIG, OP_ELSE, OP_ENDIF, OP_IF};
+
+    new_test_ext().execute_with(|| {
+        let pair = sr25519::Pair::from_seed(&[1; 32]);
+        let utxo = TransactionOutput { value: 100, pubkey: H256(pair.public().0), memo: None };
+        let outpoint = BlakeTwo256::hash_of(&utxo);
+        UtxoStore::<Test>::insert(outpoint, utxo);
+        let mut transaction = create_test_transaction(vec![(outpoint, H512::zero())], vec![(90, H256::random())]);
+        sign_inputs(&mut transaction, &pair);
+        assert_ok!(Utxo::validate_transaction(&transaction));
+
+        // The same signature without schnorrkel's marker bit is refused as such
+        let mut unmarked = transaction.inputs[0].sigscript.unwrap();
+        unmarked.0[63] &= 0x7f;
+        transaction.inputs[0].sigscript = Some(unmarked);
+        assert_noop!(Utxo::validate_transaction(&transaction), Error::<Test>::NonCanonicalSignature);
+
+        // A script passing when the signature check fails only takes an empty signature
+        let redeem = script::ScriptBuilder::new()
+            .push_key(&H256(pair.public().0))
+            .push_opcode(OP_CHECKSIG)
+            .push_opcode(OP_IF)
+            .push_int(0)
+            .push_opcode(OP_ELSE)
+            .push_int(1)
+            .push_opcode(OP_ENDIF)
+            .into_script();
+        assert_ok!(Utxo::validate_transaction(&script_spend(redeem.clone(), vec![vec![]])));
+        let stranger = sr25519::Pair::from_seed(&[9; 32]).sign(b"anything");
+        assert_noop!(
+            Utxo::validate_transaction(&script_spend(redeem.clone(), vec![stranger.as_ref().to_vec()])),
+            Error::<Test>::ScriptFailed
+        );
+        assert_noop!(
+            Utxo::validate_transaction(&script_spend(redeem, vec![vec![1; 64]])),
+            Error::<Test>::NonCanonicalSignature
+        );
+    });
+}
+
+#[test]
+fn test_relayers_cannot_add_or_strip_signatures() {
+    new_test_ext().execute_with(|| {
+        let pair = sr25519::Pair::from_seed(&[1; 32]);
+        let owner = H256(pair.public().0);
+        let outpoints: Vec<_> = (0..2u64)
+            .map(|n| {
+                let utxo = TransactionOutput { value: 100 + n as Value, pubkey: owner, memo: None };
+                let outpoint = BlakeTwo256::hash_of(&utxo);
+                UtxoStore::<Test>::insert(outpoint, utxo);
+                (outpoint, H512::zero())
+            })
+            .collect();
+        let mut transaction = create_test_transaction(outpoints, vec![(150, H256::random())]);
+        sign_inputs(&mut transaction, &pair);
+        assert_ok!(Utxo::validate_transaction(&transaction));
+
+        // Copying the signature to the input relying on it, or moving it there, changes the
+        // wtxid but not the txid
+        let mut copied = transaction.clone();
+        copied.inputs[1].sigscript = copied.inputs[0].sigscript;
+        assert_eq!(copied.txid(), transaction.txid());
+        assert_ne!(copied.wtxid(), transaction.wtxid());
+        assert_noop!(Utxo::validate_transaction(&copied), Error::<Test>::RedundantSignature);
+        let mut moved = transaction.clone();
+        moved.inputs[1].sigscript = moved.inputs[0].sigscript.take();
+        assert_noop!(Utxo::validate_transaction(&moved), Error::<Test>::MissingSignature);
+
+        // Nor can a sigscript be added to an input spending with a witness
+        let mut script_spend = script_spend(vec![script::OP_1], vec![]);
+        assert_ok!(Utxo::validate_transaction(&script_spend));
+        script_spend.inputs[0].sigscript = Some(H512::repeat_byte(1));
+        assert_noop!(Utxo::validate_transaction(&script_spend), Error::<Test>::SignatureWithWitness);
+    });
+}
+
+#[test]
+fn test_script_debugger() {
+    use debugger::trace;
+    use script::{ScriptContext, ScriptError, OP_ELSE, OP_ENDIF, OP_EQUAL, OP_IF, OP_VERIFY};
+
+    new_test_ext().execute_with(|| {
+        let context = ScriptContext { payload: &[], block_number: 0 };
+        let limits = Utxo::script_limits();
+
+        // Every instruction is a step, those of the branch not taken included but not executed
+        let script = script::ScriptBuilder::new()
+            .push_opcode(OP_IF)
+            .push_int(2)
+            .push_opcode(OP_ELSE)
+            .push_int(3)
+            .push_opcode(OP_ENDIF)
+            .push_int(2)
+            .push_opcode(OP_EQUAL)
+            .into_script();
+        let traced = trace(&script, vec![vec![1]], &context, &limits);
+        assert_eq!(traced.result, Ok(()));
+        let steps: Vec<_> = traced
+            .steps
+            .iter()
+            .map(|step| (step.offset, step.instruction.as_str(), step.executed, step.stack.clone()))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                (0, "OP_IF", true, vec![]),
+                (1, "OP_2", true, vec![vec![2]]),
+                (2, "OP_ELSE", true, vec![vec![2]]),
+                (3, "OP_3", false, vec![vec![2]]),
+                (4, "OP_ENDIF", false, vec![vec![2]]),
+                (5, "OP_2", true, vec![vec![2], vec![2]]),
+                (6, "OP_EQUAL", true, vec![vec![1]]),
+            ]
+        );
+        assert_eq!(script::execute(&script, vec![vec![1]], &context, &limits), traced.result);
+
+        // A failing instruction is not a step: the failure is at the one after the last step
+        let script = script::ScriptBuilder::new()
+            .push_int(1)
+            .push_opcode(OP_VERIFY)
+            .push_data(&[])
+            .push_opcode(OP_VERIFY)
+            .into_script();
+        let traced = trace(&script, vec![], &context, &limits);
+        assert_eq!(traced.result, Err(ScriptError::VerifyFailed));
+        assert_eq!(traced.steps.len(), 3);
+        assert_eq!(traced.steps[2].instruction, "OP_0");
+    });
+}
+
+#[test]
+fn test_script_resource_limits() {
+    new_test_ext().execute_with(|| {
+        // MaxScriptOps is 20: `OP_1` pushes are free, each `OP_DROP` counts
+        let mut builder = script::ScriptBuilder::new().push_int(1);
+        for _ in 0..20 {
+            builder = builder.push_int(1).push_opcode(script::OP_DROP);
+        }
+        let within_ops = builder.clone().into_script();
+        assert_ok!(Utxo::validate_transaction(&script_spend(within_ops, vec![])));
+        let too_many_ops = builder.push_int(1).push_opcode(script::OP_DROP).into_script();
+        assert_noop!(
+            Utxo::validate_transaction(&script_spend(too_many_ops, vec![])),
+            Error::<Test>::TooManyScriptOps
+        );
+
+        // MaxStackDepth is 16, counting the witness items
+        let deep = vec![script::OP_1; 10];
+        assert_ok!(Utxo::validate_transaction(&script_spend(deep.clone(), vec![vec![1]; 6])));
+        assert_noop!(
+            Utxo::validate_transaction(&script_spend(deep, vec![vec![1]; 7])),
+            Error::<Test>::ScriptStackOverflow
+        );
+
+        // MaxScriptSize is 1_000 bytes
+        let mut large = vec![script::OP_1];
+        large.extend([script::OP_1, script::OP_DROP].repeat(500));
+        assert_noop!(
+            Utxo::validate_transaction(&script_spend(large, vec![])),
+            Error::<Test>::ScriptTooLarge
+        );
+    });
+}
+
+#[test]
+fn test_script_signature_checks_bound() {
+    use script::{signature_checks, ScriptBuilder, OP_CHECKMULTISIG, OP_CHECKSIG, OP_CHECKSIGVERIFY, OP_IF, OP_ENDIF};
+
+    let key = H256::repeat_byte(1);
+    // Branches not taken are counted too
+    let single = ScriptBuilder::new()
+        .push_opcode(OP_IF)
+        .push_key(&key)
+        .push_opcode(OP_CHECKSIGVERIFY)
+        .push_opcode(OP_ENDIF)
+        .push_key(&key)
+        .push_opcode(OP_CHECKSIG)
+        .into_script();
+    assert_eq!(signature_checks(&single, 20), 2);
+
+    // A multisig checks at most as many signatures as it has keys
+    let multisig = |count: u64| {
+        let mut builder = ScriptBuilder::new().push_int(1);
+        for _ in 0..count {
+            builder = builder.push_key(&key);
+        }
+        builder.push_int(count).push_opcode(OP_CHECKMULTISIG).into_script()
+    };
+    assert_eq!(signature_checks(&multisig(3), 20), 3);
+    assert_eq!(signature_checks(&multisig(17), 20), 17);
+    assert_eq!(signature_checks(&multisig(3), 2), 2);
+
+    // Keys counted by the witness stack may be as many as the ops allow
+    assert_eq!(signature_checks(&[OP_CHECKMULTISIG], 20), 20);
+    assert_eq!(signature_checks(&[OP_CHECKSIG, OP_CHECKMULTISIG], 20), 20);
+
+    new_test_ext().execute_with(|| {
+        let witness_keys = script_spend(vec![OP_CHECKMULTISIG], vec![]);
+        let script_keys = script_spend(multisig(1), vec![]);
+        assert!(Utxo::spend_weight(&witness_keys).ref_time() > Utxo::spend_weight(&script_keys).ref_time());
+    });
+}
+
+#[test]
+fn test_musig_aggregate_key_spend() {
+    new_test_ext().execute_with(|| {
+        let signers: Vec<_> = (1..=3u8).map(|seed| sr25519::Pair::from_seed(&[seed; 32])).collect();
+        let keys: Vec<_> = signers.iter().map(|pair| pair.public()).collect();
+        let aggregation = musig::aggregate_keys(&keys).unwrap();
+
+        let utxo = TransactionOutput { value: 100, pubkey: aggregation.public(), memo: None };
+        let outpoint = BlakeTwo256::hash_of(&utxo);
+        UtxoStore::<Test>::insert(outpoint, utxo);
+        let mut transaction = create_test_transaction(vec![(outpoint, H512::zero())], vec![(90, H256::random())]);
+        let payload = transaction.signing_payload();
+
+        // Round 1: exchange nonces
+        let (secret_nonces, public_nonces): (Vec<_>, Vec<_>) =
+            signers.iter().map(|_| musig::generate_nonce()).unzip();
+        let nonce = musig::aggregate_nonces(&public_nonces).unwrap();
+
+        // Round 2: exchange partial signatures and combine them
+        let partials: Vec<_> = signers
+            .iter()
+            .zip(secret_nonces)
+            .map(|(pair, secret)| musig::partial_sign(&aggregation, pair, secret, &nonce, &payload).unwrap())
+            .collect();
+        transaction.inputs[0].sigscript =
+            Some(musig::aggregate_signatures(&aggregation, &nonce, &payload, &partials).unwrap());
+        assert_ok!(Utxo::validate_transaction(&transaction));
+
+        // Every signer has to take part
+        transaction.inputs[0].sigscript =
+            Some(musig::aggregate_signatures(&aggregation, &nonce, &payload, &partials[..2]).unwrap());
+        assert_noop!(Utxo::validate_transaction(&transaction), Error::<Test>::InvalidSignature);
+    });
+}
+
+#[test]
+fn test_one_signature_covers_inputs_of_the_same_key() {
+    new_test_ext().execute_with(|| {
+        let pair = sr25519::Pair::from_seed(&[1; 32]);
+        let owner = H256::from_slice(pair.public().as_ref());
+        let outpoints: Vec<_> = (0..3u64)
+            .map(|n| {
+                let utxo = TransactionOutput { value: 100 + n as Value, pubkey: owner, memo: None };
+                let outpoint = BlakeTwo256::hash_of(&utxo);
+                UtxoStore::<Test>::insert(outpoint, utxo);
+                (outpoint, H512::zero())
+            })
+            .collect();
+
+        // Only the first input carries the signature
+        let mut consolidation = create_test_transaction(outpoints, vec![(300, H256::random())]);
+        let full_size = {
+            let mut signed = consolidation.clone();
+            for input in signed.inputs.iter_mut() {
+                input.sigscript = Some(H512::zero());
+            }
+            signed.encoded_size()
+        };
+        let signature = H512::from_slice(pair.sign(&consolidation.signing_payload()).as_ref());
+        for input in consolidation.inputs.iter_mut() {
+            input.sigscript = None;
+        }
+        consolidation.inputs[0].sigscript = Some(signature);
+        assert_ok!(Utxo::validate_transaction(&consolidation));
+        assert_eq!(consolidation.encoded_size(), full_size - 2 * 64);
+
+        // An unsigned input whose key nobody signs for is rejected
+        let stranger = TransactionOutput { value: 100, pubkey: H256::random(), memo: None };
+        let stranger_outpoint = BlakeTwo256::hash_of(&stranger);
+        UtxoStore::<Test>::insert(stranger_outpoint, stranger);
+        consolidation.inputs.try_push(TransactionInput { outpoint: stranger_outpoint, sigscript: None, witness: None }).unwrap();
+        let payload = consolidation.signing_payload();
+        consolidation.inputs[0].sigscript = Some(H512::from_slice(pair.sign(&payload).as_ref()));
+        assert_noop!(Utxo::validate_transaction(&consolidation), Error::<Test>::MissingSignature);
+    });
+}
+
+#[test]
+fn test_outpoint_spent_by_a_signed_and_a_borrowed_input_is_a_duplicate() {
+    new_test_ext().execute_with(|| {
+        let pair = sr25519::Pair::from_seed(&[1; 32]);
+        let owner = H256::from_slice(pair.public().as_ref());
+        let utxo = TransactionOutput { value: 100, pubkey: owner, memo: None };
+        let outpoint = BlakeTwo256::hash_of(&utxo);
+        UtxoStore::<Test>::insert(outpoint, utxo);
+
+        // The second input borrows the first one's signature for the same output
+        let mut transaction = create_test_transaction(
+            vec![(outpoint, H512::zero()), (outpoint, H512::zero())],
+            vec![(200, owner)],
+        );
+        transaction.inputs[1].sigscript = None;
+        let signature = H512::from_slice(pair.sign(&transaction.signing_payload()).as_ref());
+        transaction.inputs[0].sigscript = Some(signature);
+
+        assert_noop!(Utxo::validate_transaction(&transaction), Error::<Test>::DuplicateInput);
+        assert_noop!(Utxo::spend(RuntimeOrigin::none(), transaction), Error::<Test>::DuplicateInput);
+    });
+}
+
+#[test]
+fn test_consolidation_is_cheaper_per_input() {
+    new_test_ext().execute_with(|| {
+        let transaction = |inputs: usize, outputs: usize| {
+            create_test_transaction(
+                (0..inputs).map(|_| (H256::random(), H512::zero())).collect(),
+                (0..outputs).map(|_| (1, H256::random())).collect(),
+            )
+        };
+        // Every input carries a sigscript
+        let undiscounted = |inputs, outputs| {
+            <() as WeightInfo>::spend(inputs, outputs)
+                .saturating_add(<() as WeightInfo>::execute_script(0))
+                .saturating_add(<() as WeightInfo>::check_signatures(inputs))
+        };
+
+        // Nine of the ten inputs of a ten-to-one consolidation get half their weight waived
+        let consolidation = Utxo::spend_weight(&transaction(10, 1));
+        assert!(consolidation.ref_time() < undiscounted(10, 1).ref_time());
+        assert!(consolidation.ref_time() / 10 < Utxo::spend_weight(&transaction(1, 1)).ref_time());
+        // Transactions that do not shrink the UTXO set get no discount
+        assert_eq!(Utxo::spend_weight(&transaction(2, 2)), undiscounted(2, 2));
+
+        // Inputs covered by another input's signature are not charged for one
+        let mut shared = transaction(3, 3);
+        shared.inputs[1].sigscript = None;
+        shared.inputs[2].sigscript = None;
+        let two_signatures = <() as WeightInfo>::check_signatures(2).ref_time() - <() as WeightInfo>::check_signatures(0).ref_time();
+        assert_eq!(Utxo::spend_weight(&shared).ref_time(), undiscounted(3, 3).ref_time() - two_signatures);
+
+        // Half the fee is added to the priority for every input beyond the outputs
+        assert_eq!(Utxo::priority(100, 10, 1), 100 + 9 * 50);
+        assert_eq!(Utxo::priority(100, 1, 10), 100);
+    });
+}
+
+#[test]
+fn test_smoothed_reward_paid_over_following_blocks() {
+    new_test_ext().execute_with(|| {
+        crate::mock::RewardSmoothing::set(4);
+        let author = H256::repeat_byte(7);
+        RewardTotal::<Test>::put(102);
+
+        // 102 in fees and 100 issued are credited, not paid
+        Utxo::disperse_reward(&Public::from_raw(author.0));
+        assert_eq!(PendingRewardTotal::<Test>::get(), 202);
+        assert_eq!(UtxoTotalValue::<Test>::get(), 0);
+        assert_eq!(TotalIssuance::<Test>::get(), 100);
+        assert_eq!(UtxoStore::<Test>::iter().count(), 0);
+
+        // Each of the next four blocks pays a quarter, the last one the rounding leftover
+        let mut paid = Vec::new();
+        for n in 2..=5 {
+            System::set_block_number(n);
+            Utxo::on_finalize(n);
+            paid.push(UtxoTotalValue::<Test>::get() - paid.iter().sum::<Value>());
+        }
+        assert_eq!(paid, vec![50, 50, 51, 51]);
+        assert!(PendingRewards::<Test>::get(author).is_none());
+        assert_eq!(PendingRewardTotal::<Test>::get(), 0);
+        assert!(UtxoStore::<Test>::iter().all(|(_, utxo)| utxo.pubkey == author));
+        assert_eq!(UtxoStore::<Test>::iter().count(), 4);
+    });
+}
+
+#[test]
+fn test_smoothed_reward_credits_keep_their_schedule() {
+    new_test_ext().execute_with(|| {
+        crate::mock::RewardSmoothing::set(4);
+        let author = Public::from_raw([7; 32]);
+        crate::mock::Author::set(Some(author));
+
+        System::set_block_number(1);
+        Utxo::on_finalize(1);
+        System::set_block_number(2);
+        Utxo::on_finalize(2);
+
+        // Block 2 paid a quarter of block 1's reward, then credited its own alongside
+        let pending = PendingRewards::<Test>::get(H256(author.0)).unwrap();
+        assert_eq!(
+            pending.into_inner(),
+            vec![PendingReward { balance: 75, blocks: 3 }, PendingReward { balance: 100, blocks: 4 }],
+        );
+        assert_eq!(UtxoTotalValue::<Test>::get() + PendingRewardTotal::<Test>::get(), 200);
+
+        // Issuance never pushes the UTXO set and the pending rewards together past MaxMoney
+        UtxoTotalValue::<Test>::put(MaxMoney::get() - 200);
+        System::set_block_number(3);
+        Utxo::on_finalize(3);
+        assert_eq!(UtxoTotalValue::<Test>::get() + PendingRewardTotal::<Test>::get(), MaxMoney::get());
+    });
+}
+
+#[test]
+fn test_smoothed_rewards_finish_paying_after_their_own_period() {
+    new_test_ext().execute_with(|| {
+        crate::mock::RewardSmoothing::set(4);
+        let author = Public::from_raw([7; 32]);
+
+        // The author is credited 100 in blocks 1 and 3 and nothing in between or after
+        let mut paid = Vec::new();
+        for n in 1..=8 {
+            crate::mock::Author::set((n == 1 || n == 3).then_some(author));
+            System::set_block_number(n);
+            Utxo::on_finalize(n);
+            paid.push(UtxoTotalValue::<Test>::get() - paid.iter().sum::<Value>());
+        }
+
+        // Block 1's reward is paid in blocks 2 to 5 and block 3's in blocks 4 to 7
+        assert_eq!(paid, vec![0, 25, 25, 50, 50, 25, 25, 0]);
+        assert!(PendingRewards::<Test>::get(H256(author.0)).is_none());
+        assert_eq!(PendingRewardTotal::<Test>::get(), 0);
+    });
+}
+
+fn uncle(number: u64, parent: H256, salt: u8, author: Option<Public>) -> HeaderFor<Test> {
+    let mut header = HeaderFor::<Test>::new(number, H256::zero(), H256::repeat_byte(salt), parent, Default::default());
+    if let Some(author) = author {
+        header.digest.push(DigestItem::Seal(MOCK_SEAL, author.encode()));
+    }
+    header
+}
+
+fn start_block_with_uncles(n: u64, uncles: &[HeaderFor<Test>]) {
+    let digest = Digest { logs: uncles.iter().map(uncle_digest).collect() };
+    System::initialize(&n, &H256::repeat_byte(n as u8), &digest);
+    Utxo::on_initialize(n);
+}
+
+fn uncle_events() -> Vec<Event<Test>> {
+    System::events()
+        .into_iter()
+        .filter_map(|record| match record.event {
+            RuntimeEvent::Utxo(event @ (Event::UncleRewarded { .. } | Event::UncleRejected { .. })) => Some(event),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_reward_address_digest() {
+    use crate::mock::Author;
+    type RewardAddress = RewardAddressOr<Test, crate::mock::MockBlockAuthor>;
+
+    new_test_ext().execute_with(|| {
+        let author = Public::from_raw([1; 32]);
+        let cold_key = Public::from_raw([2; 32]);
+
+        // Without an author there is nobody to redirect the reward for
+        System::deposit_log(reward_digest(&cold_key));
+        assert_eq!(RewardAddress::block_author(), None);
+
+        Author::set(Some(author));
+        assert_eq!(RewardAddress::block_author(), Some(cold_key));
+        System::initialize(&2, &Default::default(), &Default::default());
+        assert_eq!(RewardAddress::block_author(), Some(author));
+    });
+}
+
+#[test]
+fn test_uncle_rewards() {
+    new_test_ext().execute_with(|| {
+        let author = Public::from_raw([3; 32]);
+        let parent = H256::repeat_byte(2);
+        frame_system::BlockHash::<Test>::insert(2, parent);
+
+        // A sealed sibling of block 3 is paid half the issuance, but only once
+        let sibling = uncle(3, parent, 1, Some(author));
+        start_block_with_uncles(5, &[sibling.clone(), sibling.clone()]);
+        let utxo = TransactionOutput { value: 50, pubkey: H256(author.0), memo: None };
+        let utxo_hash = BlakeTwo256::hash_of(&(&utxo, sibling.hash()));
+        assert_eq!(UtxoStore::<Test>::get(utxo_hash), Some(utxo));
+        assert_eq!(UtxoTotalValue::<Test>::get(), 50);
+        assert_eq!(TotalIssuance::<Test>::get(), 50);
+        assert_eq!(
+            uncle_events(),
+            vec![
+                Event::UncleRewarded { uncle: sibling.hash(), author: H256(author.0), amount: 50, utxo_hash },
+                Event::UncleRejected { uncle: sibling.hash() },
+            ]
+        );
+
+        // Unsealed headers, headers off a block not in this chain and blocks of this chain are not uncles
+        let unsealed = uncle(3, parent, 2, None);
+        let orphaned = uncle(3, H256::repeat_byte(9), 3, Some(author));
+        let ancestor = uncle(3, parent, 4, Some(author));
+        frame_system::BlockHash::<Test>::insert(3, ancestor.hash());
+        System::reset_events();
+        start_block_with_uncles(6, &[unsealed.clone(), orphaned.clone()]);
+        assert_eq!(
+            uncle_events(),
+            vec![Event::UncleRejected { uncle: unsealed.hash() }, Event::UncleRejected { uncle: orphaned.hash() }]
+        );
+        System::reset_events();
+        start_block_with_uncles(6, &[ancestor.clone()]);
+        assert_eq!(uncle_events(), vec![Event::UncleRejected { uncle: ancestor.hash() }]);
+        assert_eq!(UtxoTotalValue::<Test>::get(), 50);
+
+        // Past MaxUncleDepth an uncle is too old, and the rewarded ones of its height are forgotten
+        let stale = uncle(3, parent, 5, Some(author));
+        System::reset_events();
+        start_block_with_uncles(7, &[stale.clone()]);
+        assert_eq!(uncle_events(), vec![Event::UncleRejected { uncle: stale.hash() }]);
+        assert!(!RewardedUncles::<Test>::contains_key(3, sibling.hash()));
+        assert_eq!(UtxoTotalValue::<Test>::get(), 50);
+    });
+}
+
+#[test]
+fn test_self_referenced_uncles_mint_nothing_without_an_uncle_reward() {
+    new_test_ext().execute_with(|| {
+        // An author whose seal is free signs competing headers of its own and cites them
+        let author = Public::from_raw([3; 32]);
+        crate::mock::Author::set(Some(author));
+        let parent = H256::repeat_byte(2);
+        frame_system::BlockHash::<Test>::insert(2, parent);
+        let own_uncles = |salt: u8| [uncle(3, parent, salt, Some(author)), uncle(3, parent, salt + 1, Some(author))];
+
+        // Every block would mint a full issuance on top of its own reward
+        start_block_with_uncles(4, &own_uncles(1));
+        assert_eq!(TotalIssuance::<Test>::get(), 100);
+
+        // As the runtime does until there is a costly seal, pay nothing for them
+        crate::mock::UncleReward::set(Perbill::zero());
+        for n in 5..=6 {
+            start_block_with_uncles(n, &own_uncles(2 * n as u8));
+        }
+        assert_eq!(TotalIssuance::<Test>::get(), 100);
+        assert_eq!(UtxoTotalValue::<Test>::get(), 100);
+        assert!(uncle_events().iter().all(|event| !matches!(event, Event::UncleRejected { .. })));
+    });
+}
+
+#[test]
+fn test_author_stats_leaderboard() {
+    use crate::mock::Author;
+
+    new_test_ext().execute_with(|| {
+        let (a, b, c) = (Public::from_raw([1; 32]), Public::from_raw([2; 32]), Public::from_raw([3; 32]));
+        for (n, author, fees) in [(1, a, 0), (2, a, 0), (3, b, 30), (4, c, 0)] {
+            Author::set(Some(author));
+            RewardTotal::<Test>::put(fees);
+            System::set_block_number(n);
+            Utxo::on_finalize(n);
+        }
+
+        // Uncle rewards are counted apart from blocks, but add to the rewards earned
+        let parent = H256::repeat_byte(2);
+        frame_system::BlockHash::<Test>::insert(2, parent);
+        start_block_with_uncles(5, &[uncle(3, parent, 1, Some(a))]);
+        assert_eq!(
+            AuthorStats::<Test>::get(H256(a.0)),
+            AuthorStatistics { blocks_authored: 2, uncles_rewarded: 1, rewards_earned: 250 }
+        );
+        assert_eq!(AuthorStats::<Test>::get(H256::repeat_byte(9)), AuthorStatistics::default());
+
+        // Ranked by blocks, then by rewards
+        let ranked: Vec<_> = Utxo::leaderboard(10).into_iter().map(|(pubkey, _)| pubkey).collect();
+        assert_eq!(ranked, vec![H256(a.0), H256(b.0), H256(c.0)]);
+        assert_eq!(Utxo::leaderboard(1).len(), 1);
+        assert_eq!(Utxo::leaderboard(1)[0].1.rewards_earned, 250);
+    });
+}
+
+#[test]
+fn test_values_format_and_parse_as_coins() {
+    use denomination::{format_value, parse_value, ParseValueError, UNIT};
+
+    assert_eq!(format_value(0), "0");
+    assert_eq!(format_value(50 * UNIT), "50");
+    assert_eq!(format_value(UNIT + UNIT / 2), "1.5");
+    assert_eq!(format_value(1), "0.000000000001");
+    assert_eq!(format_value(Value::MAX), "340282366920938463463374607.431768211455");
+
+    for value in [0, 1, 10, UNIT - 1, UNIT, 21_000_000 * UNIT + 7, Value::MAX] {
+        assert_eq!(parse_value(&format_value(value)), Ok(value));
+    }
+    assert_eq!(parse_value("2.5"), Ok(2 * UNIT + UNIT / 2));
+    assert_eq!(parse_value("0.100"), Ok(UNIT / 10));
+    assert_eq!(parse_value("007"), Ok(7 * UNIT));
+
+    for invalid in ["", ".", "1.", ".5", "-1", "+1", "1.2.3", "1,5", " 1", "1e3"] {
+        assert_eq!(parse_value(invalid), Err(ParseValueError::Invalid), "{invalid:?}");
+    }
+    assert_eq!(parse_value("0.0000000000001"), Err(ParseValueError::TooManyDecimals));
+    assert_eq!(parse_value("340282366920938463463374607.431768211456"), Err(ParseValueError::Overflow));
+    assert_eq!(parse_value("1000000000000000000000000000"), Err(ParseValueError::Overflow));
+}
+
+/// The name of type `id` of `registry`, with its type parameters.
+fn metadata_type_name(registry: &scale_info::PortableRegistry, id: u32) -> String {
+    use scale_info::TypeDef;
+
+    let ty = registry.resolve(id).expect("registered types resolve");
+    let params: Vec<_> = ty.type_params.iter().filter_map(|param| param.ty.as_ref()).map(|param| metadata_type_name(registry, param.id)).collect();
+    match (ty.path.segments.last(), &ty.type_def) {
+        (Some(name), _) if params.is_empty() => name.clone(),
+        (Some(name), _) => format!("{name}<{}>", params.join(", ")),
+        (None, TypeDef::Sequence(sequence)) => format!("Vec<{}>", metadata_type_name(registry, sequence.type_param.id)),
+        (None, TypeDef::Array(array)) => format!("[{}; {}]", metadata_type_name(registry, array.type_param.id), array.len),
+        (None, TypeDef::Primitive(primitive)) => format!("{primitive:?}").to_lowercase(),
+        (None, TypeDef::Compact(compact)) => format!("Compact<{}>", metadata_type_name(registry, compact.type_param.id)),
+        (None, TypeDef::Tuple(tuple)) => {
+            let fields: Vec<_> = tuple.fields.iter().map(|field| metadata_type_name(registry, field.id)).collect();
+            format!("({})", fields.join(", "))
+        },
+        (None, other) => format!("{other:?}"),
+    }
+}
+
+/// Type `T` as the runtime metadata describes it: its path, then its fields or variants, with
+/// their docs if `docs`.
+fn metadata_snapshot<T: scale_info::TypeInfo + 'static>(docs: bool) -> String {
+    use scale_info::{form::PortableForm, Field, PortableRegistry, Registry, TypeDef};
+
+    fn push_docs(lines: &mut Vec<String>, indent: &str, docs: &[String]) {
+        lines.extend(docs.iter().map(|doc| format!("{indent}/// {}", doc.trim()).trim_end().to_string()));
+    }
+    fn push_fields(lines: &mut Vec<String>, registry: &PortableRegistry, indent: &str, fields: &[Field<PortableForm>], docs: bool) {
+        for (index, field) in fields.iter().enumerate() {
+            if docs {
+                push_docs(lines, indent, &field.docs);
+            }
+            let name = field.name.clone().unwrap_or_else(|| index.to_string());
+            lines.push(format!("{indent}{name}: {}", metadata_type_name(registry, field.ty.id)));
+        }
+    }
+
+    let mut registry = Registry::new();
+    let id = registry.register_type(&scale_info::meta_type::<T>()).id;
+    let registry = PortableRegistry::from(registry);
+    let ty = registry.resolve(id).expect("registered types resolve");
+    let mut lines = vec![ty.path.segments.join("::")];
+    match &ty.type_def {
+        TypeDef::Composite(composite) => push_fields(&mut lines, &registry, "    ", &composite.fields, docs),
+        TypeDef::Variant(variant) =>
+            for variant in &variant.variants {
+                if docs {
+                    push_docs(&mut lines, "    ", &variant.docs);
+                }
+                lines.push(format!("    {} {}", variant.index, variant.name));
+                push_fields(&mut lines, &registry, "        ", &variant.fields, docs);
+            },
+        other => lines.push(format!("    {other:?}")),
+    }
+    lines.join("\n")
+}
+
+#[test]
+fn test_metadata_matches_snapshot() {
+    // What generic tools such as polkadot-js decode and build transactions from. A change here
+    // breaks them: update `snapshots/metadata.txt` with `UPDATE_METADATA_SNAPSHOT=1` only when
+    // the change is meant to.
+    let snapshot = [
+        metadata_snapshot::<Transaction>(true),
+        metadata_snapshot::<TransactionInput>(true),
+        metadata_snapshot::<TransactionOutput>(true),
+        metadata_snapshot::<script::ScriptWitness>(true),
+        metadata_snapshot::<Call<Test>>(false),
+        metadata_snapshot::<Event<Test>>(false),
+        metadata_snapshot::<Error<Test>>(false),
+    ]
+    .join("\n\n") + "\n";
+
+    if std::env::var_os("UPDATE_METADATA_SNAPSHOT").is_some() {
+        std::fs::write(concat!(env!("CARGO_MANIFEST_DIR"), "/snapshots/metadata.txt"), &snapshot).unwrap();
+        return;
+    }
+    assert_eq!(snapshot, include_str!("../snapshots/metadata.txt"));
+}
@@ -0,0 +1,203 @@
+//! Vaults: self-custody in which a stolen spending key is not enough to take the funds.
+//!
+//! A vault involves two keys held by the owner: the everyday `owner` key and a `recovery` key
+//! kept offline. Funds go through two stages:
+//!
+//! 1. The deposit pays a throwaway [`VaultKey`]. Right after the deposit, that key pre-signs the
+//!    one transaction it will ever sign: the *unvault* transaction, moving the deposit to the
+//!    unvault script. Then the key is dropped, so the deposit can only ever be unvaulted.
+//! 2. The unvault output is spendable by the `recovery` key at any time, and by the `owner` key
+//!    from `unlock_height` on.
+//!
+//! To spend, the owner broadcasts the unvault transaction and waits for `unlock_height`. If
+//! the owner key is stolen, the thief has to go through the same steps. The owner sees the
+//! unvault transaction on chain and moves the funds away with the recovery key before the
+//! thief can.
+//!
+//! The script language only has absolute timelocks ([`script::OP_CHECKLOCKTIMEVERIFY`]). The
+//! delay therefore ends at a height fixed when the unvault transaction is signed, not a number
+//! of blocks after it is included. Once `unlock_height` has passed, the owner key can spend the
+//! unvault output as soon as it exists. Before that happens, the recovery key should move the
+//! funds into a new vault with a later unlock height.
+//!
+//! ```ignore
+//! let vault = Vault::new(owner, recovery, unlock_height);
+//! let key = VaultKey::generate();
+//! // ... deposit `value` to `key.pubkey()` at `deposit` ...
+//! let unvault = key.presign_unvault(&vault, deposit, value, fee)?;
+//! // Keep `unvault` somewhere safe. Later, once it is included:
+//! let withdrawal = vault.withdraw(&unvault, outputs, &owner_pair)?;
+//! ```
+
+use core::fmt;
+
+use sp_core::{sr25519, Pair, H256, H512};
+
+use crate::{
+	script::{self, ScriptBuilder, ScriptWitness},
+	Transaction, TransactionInput, TransactionOutput, Value, TRANSACTION_VERSION,
+};
+
+/// Reasons a vault transaction cannot be built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VaultError {
+	/// The key given is not the one the vault path requires
+	WrongKey,
+	/// The fee leaves nothing to put in the vault
+	FeeTooHigh,
+	/// No outputs, or more than [`crate::MAX_TRANSACTION_PARTS`]
+	InvalidOutputs,
+}
+
+impl fmt::Display for VaultError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::WrongKey => write!(f, "key does not belong to the vault"),
+			Self::FeeTooHigh => write!(f, "fee exceeds the deposit"),
+			Self::InvalidOutputs =>
+				write!(f, "between 1 and {} outputs required", crate::MAX_TRANSACTION_PARTS),
+		}
+	}
+}
+
+impl std::error::Error for VaultError {}
+
+/// The keys and delay guarding unvaulted funds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vault {
+	/// Key spending unvaulted funds once `unlock_height` is reached
+	pub owner: H256,
+	/// Key spending unvaulted funds at any time
+	pub recovery: H256,
+	/// First block in which the owner key can spend
+	pub unlock_height: u64,
+}
+
+impl Vault {
+	/// A vault releasing funds to `owner` at `unlock_height`, or earlier to `recovery`.
+	pub fn new(owner: H256, recovery: H256, unlock_height: u64) -> Self {
+		Self { owner, recovery, unlock_height }
+	}
+
+	/// The script guarding the unvault output.
+	pub fn unvault_script(&self) -> Vec<u8> {
+		ScriptBuilder::new()
+			.push_opcode(script::OP_IF)
+			.push_key(&self.recovery)
+			.push_opcode(script::OP_CHECKSIG)
+			.push_opcode(script::OP_ELSE)
+			.push_int(self.unlock_height)
+			.push_opcode(script::OP_CHECKLOCKTIMEVERIFY)
+			.push_opcode(script::OP_DROP)
+			.push_key(&self.owner)
+			.push_opcode(script::OP_CHECKSIG)
+			.push_opcode(script::OP_ENDIF)
+			.into_script()
+	}
+
+	/// The `pubkey` of the unvault output.
+	pub fn unvault_pubkey(&self) -> H256 {
+		script::script_hash(&self.unvault_script())
+	}
+
+	/// Spend the output of `unvault` to `outputs` with the owner key. Valid from
+	/// `unlock_height` on.
+	pub fn withdraw(
+		&self,
+		unvault: &Transaction,
+		outputs: Vec<TransactionOutput>,
+		owner: &sr25519::Pair,
+	) -> Result<Transaction, VaultError> {
+		if H256(owner.public().0) != self.owner {
+			return Err(VaultError::WrongKey)
+		}
+		self.spend_unvault(unvault, outputs, owner, false)
+	}
+
+	/// Spend the output of `unvault` to `outputs` with the recovery key. Valid at any time.
+	pub fn recover(
+		&self,
+		unvault: &Transaction,
+		outputs: Vec<TransactionOutput>,
+		recovery: &sr25519::Pair,
+	) -> Result<Transaction, VaultError> {
+		if H256(recovery.public().0) != self.recovery {
+			return Err(VaultError::WrongKey)
+		}
+		self.spend_unvault(unvault, outputs, recovery, true)
+	}
+
+	/// A transaction spending the unvault output, signed by `pair` for the recovery branch if
+	/// `recovery` is set and the owner branch otherwise.
+	fn spend_unvault(
+		&self,
+		unvault: &Transaction,
+		outputs: Vec<TransactionOutput>,
+		pair: &sr25519::Pair,
+		recovery: bool,
+	) -> Result<Transaction, VaultError> {
+		if outputs.is_empty() {
+			return Err(VaultError::InvalidOutputs)
+		}
+		let input = TransactionInput { outpoint: unvault.outpoint(0), sigscript: None, witness: None };
+		let mut transaction = Transaction {
+			version: TRANSACTION_VERSION,
+			inputs: vec![input].try_into().map_err(|_| VaultError::InvalidOutputs)?,
+			outputs: outputs.try_into().map_err(|_| VaultError::InvalidOutputs)?,
+		};
+
+		// Witnesses are not part of the signing payload. The top item selects the branch.
+		let signature = pair.sign(&transaction.signing_payload()).as_ref().to_vec();
+		let branch = if recovery { vec![1] } else { vec![] };
+		let witness = ScriptWitness {
+			script: self.unvault_script().try_into().expect("vault scripts are a few dozen bytes"),
+			stack: vec![
+				signature.try_into().expect("signatures fit a stack item"),
+				branch.try_into().expect("one byte fits a stack item"),
+			]
+			.try_into()
+			.expect("two items fit a witness"),
+		};
+		transaction.inputs[0].witness = Some(witness);
+		Ok(transaction)
+	}
+}
+
+/// The throwaway key a vault deposit is paid to.
+///
+/// It can only be used once, by [`VaultKey::presign_unvault`], which consumes it.
+pub struct VaultKey(sr25519::Pair);
+
+impl VaultKey {
+	/// A fresh random key.
+	pub fn generate() -> Self {
+		Self(sr25519::Pair::generate().0)
+	}
+
+	/// The pubkey vault deposits pay.
+	pub fn pubkey(&self) -> H256 {
+		H256(self.0.public().0)
+	}
+
+	/// Sign the transaction moving the `value` deposited at `deposit` into `vault`, less `fee`,
+	/// and drop the key.
+	pub fn presign_unvault(
+		self,
+		vault: &Vault,
+		deposit: H256,
+		value: Value,
+		fee: Value,
+	) -> Result<Transaction, VaultError> {
+		let value = value.checked_sub(fee).filter(|value| *value > 0).ok_or(VaultError::FeeTooHigh)?;
+		let input = TransactionInput { outpoint: deposit, sigscript: None, witness: None };
+		let output = TransactionOutput { value, pubkey: vault.unvault_pubkey(), memo: None };
+		let mut transaction = Transaction {
+			version: TRANSACTION_VERSION,
+			inputs: vec![input].try_into().expect("one input is within bounds"),
+			outputs: vec![output].try_into().expect("one output is within bounds"),
+		};
+		let signature = self.0.sign(&transaction.signing_payload());
+		transaction.inputs[0].sigscript = Some(H512::from_slice(signature.as_ref()));
+		Ok(transaction)
+	}
+}
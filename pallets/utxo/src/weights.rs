@@ -1,29 +1,7 @@
-
-//! Autogenerated weights for pallet_template
+//! Weights for utxo
 //!
-//! THIS FILE WAS AUTO-GENERATED USING THE SUBSTRATE BENCHMARK CLI VERSION 4.0.0-dev
-//! DATE: 2023-04-06, STEPS: `50`, REPEAT: `20`, LOW RANGE: `[]`, HIGH RANGE: `[]`
-//! WORST CASE MAP SIZE: `1000000`
-//! HOSTNAME: `Alexs-MacBook-Pro-2.local`, CPU: `<UNKNOWN>`
-//! EXECUTION: Some(Wasm), WASM-EXECUTION: Compiled, CHAIN: Some("dev"), DB CACHE: 1024
-
-// Executed Command:
-// ../../target/release/node-template
-// benchmark
-// pallet
-// --chain
-// dev
-// --pallet
-// pallet_template
-// --extrinsic
-// *
-// --steps=50
-// --repeat=20
-// --wasm-execution=compiled
-// --output
-// pallets/template/src/weights.rs
-// --template
-// ../../.maintain/frame-weight-template.hbs
+//! PLACEHOLDER VALUES, NOT YET GENERATED ON REFERENCE HARDWARE.
+//! Regenerate with `./scripts/benchmark-weights.sh` (see the script for the exact command).
 
 #![cfg_attr(rustfmt, rustfmt_skip)]
 #![allow(unused_parens)]
@@ -32,59 +10,143 @@
 use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
 use core::marker::PhantomData;
 
-/// Weight functions needed for pallet_template.
+/// Weight functions needed for utxo.
 pub trait WeightInfo {
-	fn do_something() -> Weight;
-	fn cause_error() -> Weight;
+	fn spend(i: u32, o: u32, ) -> Weight;
+	fn mint(o: u32, ) -> Weight;
+	fn on_finalize(b: u32, p: u32, ) -> Weight;
+	fn execute_script(s: u32, ) -> Weight;
+	fn check_signatures(c: u32, ) -> Weight;
+	fn reward_uncles(u: u32, ) -> Weight;
 }
 
-/// Weights for pallet_template using the Substrate node and recommended hardware.
+/// Weights for utxo using the Substrate node and recommended hardware.
 pub struct SubstrateWeight<T>(PhantomData<T>);
 impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
-	/// Storage: TemplateModule Something (r:0 w:1)
-	/// Proof: TemplateModule Something (max_values: Some(1), max_size: Some(4), added: 499, mode: MaxEncodedLen)
-	fn do_something() -> Weight {
-		// Proof Size summary in bytes:
-		//  Measured:  `0`
-		//  Estimated: `0`
-		// Minimum execution time: 8_000_000 picoseconds.
-		Weight::from_parts(9_000_000, 0)
-			.saturating_add(T::DbWeight::get().writes(1_u64))
+	/// Storage: Utxo UtxoStore (r:100 w:100)
+	/// Storage: Utxo BlockOutputs (r:200 w:100)
+	/// Storage: Utxo RewardTotal (r:1 w:1)
+	/// Storage: Utxo UtxoTotalValue (r:1 w:1)
+	/// The range of component `i` is `[1, 100]`.
+	/// The range of component `o` is `[1, 100]`.
+	fn spend(i: u32, o: u32, ) -> Weight {
+		Weight::from_parts(20_000_000, 1489)
+			.saturating_add(Weight::from_parts(12_000_000, 2550).saturating_mul(i.into()))
+			.saturating_add(Weight::from_parts(12_000_000, 2550).saturating_mul(o.into()))
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().reads((2_u64).saturating_mul(i.into())))
+			.saturating_add(T::DbWeight::get().reads((2_u64).saturating_mul(o.into())))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(i.into())))
+			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(o.into())))
+	}
+	/// Storage: Utxo UtxoStore (r:100 w:0)
+	/// Storage: Utxo BlockOutputs (r:100 w:100)
+	/// Storage: Utxo UtxoTotalValue (r:1 w:1)
+	/// Storage: Utxo TotalIssuance (r:1 w:1)
+	/// The range of component `o` is `[1, 100]`.
+	fn mint(o: u32, ) -> Weight {
+		Weight::from_parts(15_000_000, 1489)
+			.saturating_add(Weight::from_parts(10_000_000, 2550).saturating_mul(o.into()))
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().reads((2_u64).saturating_mul(o.into())))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(o.into())))
+	}
+	/// Storage: Utxo BlockOutputs (r:2000 w:2000)
+	/// Storage: Utxo UtxoStore (r:0 w:2000)
+	/// Storage: Utxo RewardTotal (r:1 w:1)
+	/// Storage: Utxo UtxoTotalValue (r:1 w:1)
+	/// Storage: Utxo TotalIssuance (r:1 w:1)
+	/// Storage: Utxo PendingRewards (r:101 w:101)
+	/// Storage: Utxo PendingRewardTotal (r:1 w:1)
+	/// The range of component `b` is `[1, 2000]`.
+	/// The range of component `p` is `[0, 100]`.
+	fn on_finalize(b: u32, p: u32, ) -> Weight {
+		Weight::from_parts(30_000_000, 3593)
+			.saturating_add(Weight::from_parts(8_000_000, 2550).saturating_mul(b.into()))
+			.saturating_add(Weight::from_parts(14_000_000, 2568).saturating_mul(p.into()))
+			.saturating_add(T::DbWeight::get().reads(5_u64))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(b.into())))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(p.into())))
+			.saturating_add(T::DbWeight::get().writes(6_u64))
+			.saturating_add(T::DbWeight::get().writes((2_u64).saturating_mul(b.into())))
+			.saturating_add(T::DbWeight::get().writes((2_u64).saturating_mul(p.into())))
+	}
+	/// The range of component `s` is `[0, 10000]`.
+	fn execute_script(s: u32, ) -> Weight {
+		Weight::from_parts(2_000_000, 0)
+			.saturating_add(Weight::from_parts(600_000, 0).saturating_mul(s.into()))
 	}
-	/// Storage: TemplateModule Something (r:1 w:1)
-	/// Proof: TemplateModule Something (max_values: Some(1), max_size: Some(4), added: 499, mode: MaxEncodedLen)
-	fn cause_error() -> Weight {
-		// Proof Size summary in bytes:
-		//  Measured:  `32`
-		//  Estimated: `1489`
-		// Minimum execution time: 6_000_000 picoseconds.
-		Weight::from_parts(6_000_000, 1489)
+	/// The range of component `c` is `[0, 200]`.
+	fn check_signatures(c: u32, ) -> Weight {
+		Weight::from_parts(3_000_000, 0)
+			.saturating_add(Weight::from_parts(48_000_000, 0).saturating_mul(c.into()))
+	}
+	/// Storage: System Digest (r:1 w:0)
+	/// Storage: System BlockHash (r:4 w:0)
+	/// Storage: Utxo RewardedUncles (r:2 w:2)
+	/// Storage: Utxo UtxoTotalValue (r:2 w:2)
+	/// Storage: Utxo PendingRewardTotal (r:2 w:0)
+	/// Storage: Utxo RewardTotal (r:2 w:0)
+	/// Storage: Utxo TotalIssuance (r:2 w:2)
+	/// Storage: Utxo UtxoStore (r:0 w:2)
+	/// Storage: Aura Authorities (r:2 w:0)
+	/// The range of component `u` is `[0, 2]`.
+	fn reward_uncles(u: u32, ) -> Weight {
+		Weight::from_parts(6_000_000, 1517)
+			.saturating_add(Weight::from_parts(95_000_000, 3593).saturating_mul(u.into()))
 			.saturating_add(T::DbWeight::get().reads(1_u64))
-			.saturating_add(T::DbWeight::get().writes(1_u64))
+			.saturating_add(T::DbWeight::get().reads((8_u64).saturating_mul(u.into())))
+			.saturating_add(T::DbWeight::get().writes((4_u64).saturating_mul(u.into())))
 	}
 }
 
 // For backwards compatibility and tests
 impl WeightInfo for () {
-	/// Storage: TemplateModule Something (r:0 w:1)
-	/// Proof: TemplateModule Something (max_values: Some(1), max_size: Some(4), added: 499, mode: MaxEncodedLen)
-	fn do_something() -> Weight {
-		// Proof Size summary in bytes:
-		//  Measured:  `0`
-		//  Estimated: `0`
-		// Minimum execution time: 8_000_000 picoseconds.
-		Weight::from_parts(9_000_000, 0)
-			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	fn spend(i: u32, o: u32, ) -> Weight {
+		Weight::from_parts(20_000_000, 1489)
+			.saturating_add(Weight::from_parts(12_000_000, 2550).saturating_mul(i.into()))
+			.saturating_add(Weight::from_parts(12_000_000, 2550).saturating_mul(o.into()))
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().reads((2_u64).saturating_mul(i.into())))
+			.saturating_add(RocksDbWeight::get().reads((2_u64).saturating_mul(o.into())))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+			.saturating_add(RocksDbWeight::get().writes((1_u64).saturating_mul(i.into())))
+			.saturating_add(RocksDbWeight::get().writes((1_u64).saturating_mul(o.into())))
+	}
+	fn mint(o: u32, ) -> Weight {
+		Weight::from_parts(15_000_000, 1489)
+			.saturating_add(Weight::from_parts(10_000_000, 2550).saturating_mul(o.into()))
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().reads((2_u64).saturating_mul(o.into())))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+			.saturating_add(RocksDbWeight::get().writes((1_u64).saturating_mul(o.into())))
+	}
+	fn on_finalize(b: u32, p: u32, ) -> Weight {
+		Weight::from_parts(30_000_000, 3593)
+			.saturating_add(Weight::from_parts(8_000_000, 2550).saturating_mul(b.into()))
+			.saturating_add(Weight::from_parts(14_000_000, 2568).saturating_mul(p.into()))
+			.saturating_add(RocksDbWeight::get().reads(5_u64))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(b.into())))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(p.into())))
+			.saturating_add(RocksDbWeight::get().writes(6_u64))
+			.saturating_add(RocksDbWeight::get().writes((2_u64).saturating_mul(b.into())))
+			.saturating_add(RocksDbWeight::get().writes((2_u64).saturating_mul(p.into())))
+	}
+	fn execute_script(s: u32, ) -> Weight {
+		Weight::from_parts(2_000_000, 0)
+			.saturating_add(Weight::from_parts(600_000, 0).saturating_mul(s.into()))
+	}
+	fn check_signatures(c: u32, ) -> Weight {
+		Weight::from_parts(3_000_000, 0)
+			.saturating_add(Weight::from_parts(48_000_000, 0).saturating_mul(c.into()))
 	}
-	/// Storage: TemplateModule Something (r:1 w:1)
-	/// Proof: TemplateModule Something (max_values: Some(1), max_size: Some(4), added: 499, mode: MaxEncodedLen)
-	fn cause_error() -> Weight {
-		// Proof Size summary in bytes:
-		//  Measured:  `32`
-		//  Estimated: `1489`
-		// Minimum execution time: 6_000_000 picoseconds.
-		Weight::from_parts(6_000_000, 1489)
+	fn reward_uncles(u: u32, ) -> Weight {
+		Weight::from_parts(6_000_000, 1517)
+			.saturating_add(Weight::from_parts(95_000_000, 3593).saturating_mul(u.into()))
 			.saturating_add(RocksDbWeight::get().reads(1_u64))
-			.saturating_add(RocksDbWeight::get().writes(1_u64))
+			.saturating_add(RocksDbWeight::get().reads((8_u64).saturating_mul(u.into())))
+			.saturating_add(RocksDbWeight::get().writes((4_u64).saturating_mul(u.into())))
 	}
 }
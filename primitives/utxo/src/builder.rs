@@ -0,0 +1,145 @@
+//! Assembling transactions input by input and output by output.
+//!
+//! ```
+//! use sp_core::{H256, H512};
+//! use utxo_primitives::{TransactionBuilder, TransactionOutput};
+//!
+//! let (outpoint, recipient) = (H256::repeat_byte(1), H256::repeat_byte(2));
+//! let mut transaction = TransactionBuilder::new()
+//! 	.input(outpoint)?
+//! 	.output(TransactionOutput { value: 90, pubkey: recipient, memo: None })?
+//! 	.build()?;
+//! # let sign = |_: &[u8]| H512::zero();
+//! transaction.inputs[0].sigscript = Some(sign(&transaction.signing_payload()));
+//! # Ok::<(), utxo_primitives::BuildError>(())
+//! ```
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use sp_core::{RuntimeDebug, H256};
+
+use crate::{
+	script::ScriptWitness, Transaction, TransactionInput, TransactionOutput, MAX_TRANSACTION_PARTS,
+	TRANSACTION_VERSION,
+};
+
+/// Reasons a transaction cannot be built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildError {
+	/// More than [`MAX_TRANSACTION_PARTS`] inputs
+	TooManyInputs,
+	/// More than [`MAX_TRANSACTION_PARTS`] outputs
+	TooManyOutputs,
+	/// The transaction spends nothing
+	NoInputs,
+	/// The transaction creates nothing
+	NoOutputs,
+	/// An outpoint is spent twice
+	DuplicateInput,
+	/// The same output is created twice
+	DuplicateOutput,
+	/// An output carries no value
+	ZeroValueOutput,
+	/// An output is locked to the zero pubkey, which nothing can spend
+	UnspendableDestination,
+}
+
+impl fmt::Display for BuildError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::TooManyInputs => write!(f, "more than {MAX_TRANSACTION_PARTS} inputs"),
+			Self::TooManyOutputs => write!(f, "more than {MAX_TRANSACTION_PARTS} outputs"),
+			Self::NoInputs => write!(f, "no inputs"),
+			Self::NoOutputs => write!(f, "no outputs"),
+			Self::DuplicateInput => write!(f, "an outpoint is spent twice"),
+			Self::DuplicateOutput => write!(f, "an output is created twice"),
+			Self::ZeroValueOutput => write!(f, "an output has no value"),
+			Self::UnspendableDestination => write!(f, "an output pays the zero pubkey"),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BuildError {}
+
+/// Assembles an unsigned transaction, checking what can be checked without the chain state.
+#[derive(Default, Clone, RuntimeDebug)]
+pub struct TransactionBuilder {
+	inputs: Vec<TransactionInput>,
+	outputs: Vec<TransactionOutput>,
+	canonical: bool,
+}
+
+impl TransactionBuilder {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Spend the output at `outpoint`, authorized by a signature set once the transaction is
+	/// built.
+	pub fn input(self, outpoint: H256) -> Result<Self, BuildError> {
+		self.push_input(TransactionInput { outpoint, sigscript: None, witness: None })
+	}
+
+	/// Spend the output at `outpoint`, locked to the script `witness` reveals. Its signatures,
+	/// if any, are part of the witness stack, over the same signing payload.
+	pub fn script_input(self, outpoint: H256, witness: ScriptWitness) -> Result<Self, BuildError> {
+		self.push_input(TransactionInput { outpoint, sigscript: None, witness: Some(witness) })
+	}
+
+	/// Create `output`.
+	pub fn output(mut self, output: TransactionOutput) -> Result<Self, BuildError> {
+		if output.value == 0 {
+			return Err(BuildError::ZeroValueOutput)
+		}
+		if output.pubkey.is_zero() {
+			return Err(BuildError::UnspendableDestination)
+		}
+		if self.outputs.contains(&output) {
+			return Err(BuildError::DuplicateOutput)
+		}
+		if self.outputs.len() >= MAX_TRANSACTION_PARTS as usize {
+			return Err(BuildError::TooManyOutputs)
+		}
+		self.outputs.push(output);
+		Ok(self)
+	}
+
+	/// Build the transaction in canonical order, see [`Transaction::sort_canonically`], rather
+	/// than in the order of the inputs and outputs given.
+	pub fn canonical_order(mut self) -> Self {
+		self.canonical = true;
+		self
+	}
+
+	/// The transaction, with no signatures yet.
+	pub fn build(self) -> Result<Transaction, BuildError> {
+		if self.inputs.is_empty() {
+			return Err(BuildError::NoInputs)
+		}
+		if self.outputs.is_empty() {
+			return Err(BuildError::NoOutputs)
+		}
+		let mut transaction = Transaction {
+			version: TRANSACTION_VERSION,
+			inputs: self.inputs.try_into().map_err(|_| BuildError::TooManyInputs)?,
+			outputs: self.outputs.try_into().map_err(|_| BuildError::TooManyOutputs)?,
+		};
+		if self.canonical {
+			transaction.sort_canonically();
+		}
+		Ok(transaction)
+	}
+
+	fn push_input(mut self, input: TransactionInput) -> Result<Self, BuildError> {
+		if self.inputs.iter().any(|spent| spent.outpoint == input.outpoint) {
+			return Err(BuildError::DuplicateInput)
+		}
+		if self.inputs.len() >= MAX_TRANSACTION_PARTS as usize {
+			return Err(BuildError::TooManyInputs)
+		}
+		self.inputs.push(input);
+		Ok(self)
+	}
+}
@@ -0,0 +1,274 @@
+//! The transactions of tumuchain's UTXO ledger, and what it takes to build and sign them.
+//!
+//! This is the part of the `utxo` pallet a wallet needs, which the pallet re-exports. It is
+//! `no_std` and calls no host functions, so it builds for browsers, through the `tumuchain-wasm`
+//! bindings, and for embedded signers alike.
+//!
+//! A [`TransactionBuilder`] assembles a transaction. Every input without a witness then carries
+//! the sr25519 signature, by the key its output pays, of the transaction's
+//! [`Transaction::signing_payload`]; the signature is made with the `substrate` signing context,
+//! as by `sp_core::sr25519::Pair::sign`, and in the encoding [`is_canonical_signature`] accepts,
+//! which `sign` always produces. Inputs spending outputs of the same key can share one
+//! signature, carried by the first of them. Outputs locked to a script are built with
+//! [`script::ScriptBuilder`] and spent with a [`script::ScriptWitness`] instead.
+//!
+//! Transactions start with a version byte, naming the [`TransactionVersion`] whose rules
+//! validate them. Every version shares the layout of the transaction, later ones only changing
+//! what its fields mean, such as the scripts of witnesses, so any node decodes any transaction
+//! and rejects those of versions it does not know as such rather than as undecodable.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod builder;
+pub mod script;
+
+#[cfg(test)]
+mod tests;
+
+pub use builder::{BuildError, TransactionBuilder};
+
+use alloc::vec::Vec;
+use codec::{Compact, Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+use serde::{Deserialize, Serialize};
+use sp_core::{bounded::BoundedVec, hashing::blake2_256, ConstU32, RuntimeDebug, H256, H512};
+
+use script::ScriptWitness;
+
+pub type Value = u128;
+
+/// Maximum number of inputs or outputs in a transaction
+pub const MAX_TRANSACTION_PARTS: u32 = 100;
+
+/// Factor by which the witness bytes of a transaction, its sigscripts and witnesses, count less
+/// than its other bytes towards its [`Transaction::virtual_size`]
+pub const WITNESS_SCALE_FACTOR: u32 = 4;
+
+/// Version of the transactions [`TransactionBuilder`] builds
+pub const TRANSACTION_VERSION: u8 = TransactionVersion::V1 as u8;
+
+/// Versions of the transaction format, each validated by its own rules
+#[derive(PartialEq, Eq, Clone, Copy, RuntimeDebug)]
+#[repr(u8)]
+pub enum TransactionVersion {
+	/// Sigscripts signed with sr25519, and witnesses run by the original script interpreter
+	V1 = 1,
+}
+
+impl TryFrom<u8> for TransactionVersion {
+	type Error = u8;
+
+	/// The version of byte `version`, or the byte back if no version has it.
+	fn try_from(version: u8) -> Result<Self, u8> {
+		match version {
+			1 => Ok(Self::V1),
+			unknown => Err(unknown),
+		}
+	}
+}
+
+/// The order of the group sr25519 works in, little-endian.
+const GROUP_ORDER: [u8; 32] = [
+	0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+	0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x10,
+];
+
+/// The prime `2^255 - 19` of the field Ristretto points are encoded in, little-endian.
+const FIELD_PRIME: [u8; 32] = [
+	0xed, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+	0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f,
+];
+
+/// Whether `signature` is an sr25519 signature in its one canonical encoding: the point `R` as a
+/// non-negative field element below `2^255 - 19`, then the scalar `s` below the group order,
+/// with schnorrkel's marker bit set on the last byte.
+///
+/// A signature in any other encoding of the same `R` and `s` would verify with a lenient
+/// verifier, letting anyone relaying a transaction change its wtxid. Checking the encoding
+/// itself keeps that from depending on the verifier.
+pub fn is_canonical_signature(signature: &[u8; 64]) -> bool {
+	let (r, s) = signature.split_at(32);
+	let mut s: [u8; 32] = s.try_into().expect("64 bytes split in halves; qed");
+	if s[31] & 0x80 == 0 {
+		return false
+	}
+	s[31] &= 0x7f;
+	r[0] & 1 == 0 && below(r, &FIELD_PRIME) && below(&s, &GROUP_ORDER)
+}
+
+/// Whether the little-endian number `a` is below `b`.
+fn below(a: &[u8], b: &[u8; 32]) -> bool {
+	a.iter().rev().lt(b.iter().rev())
+}
+
+/// Single transaction to be dispatched
+#[derive(Serialize, Deserialize)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct Transaction {
+	/// The [`TransactionVersion`] whose rules validate this transaction, kept as a byte so
+	/// transactions of unknown versions still decode
+	#[serde(default = "transaction_version")]
+	pub version: u8,
+	/// UTXOs to be used as inputs for current transaction
+	pub inputs: BoundedVec<TransactionInput, ConstU32<MAX_TRANSACTION_PARTS>>,
+	/// UTXOs to be created as a result of current transaction dispatch
+	pub outputs: BoundedVec<TransactionOutput, ConstU32<MAX_TRANSACTION_PARTS>>,
+}
+
+fn transaction_version() -> u8 {
+	TRANSACTION_VERSION
+}
+
+impl Default for Transaction {
+	fn default() -> Self {
+		Self {
+			version: TRANSACTION_VERSION,
+			inputs: Default::default(),
+			outputs: Default::default(),
+		}
+	}
+}
+
+impl Transaction {
+	/// The version whose rules validate the transaction, `Err` with the version byte if unknown.
+	pub fn known_version(&self) -> Result<TransactionVersion, u8> {
+		TransactionVersion::try_from(self.version)
+	}
+
+	/// The transaction identifier.
+	///
+	/// Commits to everything except the sigscripts, so a third party re-encoding a signature
+	/// cannot change the txid of an in-flight transaction. Output outpoints are derived from it.
+	pub fn txid(&self) -> H256 {
+		H256(blake2_256(&self.signing_payload()))
+	}
+
+	/// The witness transaction identifier, committing to the sigscripts as well.
+	pub fn wtxid(&self) -> H256 {
+		H256(self.using_encoded(blake2_256))
+	}
+
+	/// The encoding of the transaction with its sigscripts and witnesses cleared, which is
+	/// what inputs sign. It commits to the version, so a signature holds for one version only.
+	///
+	/// Written straight into a buffer of the final size rather than encoding a cleared copy,
+	/// which would clone every witness script only to drop it.
+	pub fn signing_payload(&self) -> Vec<u8> {
+		// Outpoint followed by two `None`s
+		const CLEARED_INPUT_SIZE: usize = 32 + 1 + 1;
+
+		let inputs = Compact(self.inputs.len() as u32);
+		let mut payload = Vec::with_capacity(
+			1 + inputs.size_hint() +
+				self.inputs.len() * CLEARED_INPUT_SIZE +
+				self.outputs.encoded_size(),
+		);
+		payload.push(self.version);
+		inputs.encode_to(&mut payload);
+		for input in self.inputs.iter() {
+			(&input.outpoint, None::<H512>, None::<ScriptWitness>).encode_to(&mut payload);
+		}
+		self.outputs.encode_to(&mut payload);
+		payload
+	}
+
+	/// Bytes of the sigscripts and witnesses, which the txid does not commit to.
+	pub fn witness_size(&self) -> u32 {
+		self.inputs
+			.iter()
+			.map(|input| {
+				let sigscript = input.sigscript.as_ref().map_or(0, |signature| signature.0.len());
+				let witness = input.witness.as_ref().map_or(0, Encode::encoded_size);
+				(sigscript + witness) as u32
+			})
+			.sum()
+	}
+
+	/// The size fee rates are charged by. Witness bytes count for a [`WITNESS_SCALE_FACTOR`]th,
+	/// the bytes naming inputs and creating outputs in full, so that spending outputs costs less
+	/// than creating them and the fee market prices the growth of the UTXO set highest.
+	pub fn virtual_size(&self) -> u32 {
+		let witness = self.witness_size();
+		let base = self.encoded_size() as u32 - witness;
+		base + witness.div_ceil(WITNESS_SCALE_FACTOR)
+	}
+
+	/// Total length of the scripts revealed by the inputs, which execution weight scales with.
+	pub fn script_bytes(&self) -> u32 {
+		self.inputs
+			.iter()
+			.filter_map(|input| input.witness.as_ref())
+			.map(|witness| witness.script.len() as u32)
+			.sum()
+	}
+
+	/// Whether the inputs are sorted by outpoint, and the outputs by value, then pubkey, then
+	/// memo, as [`Self::sort_canonically`] leaves them.
+	pub fn is_canonically_ordered(&self) -> bool {
+		self.inputs.windows(2).all(|pair| pair[0].outpoint <= pair[1].outpoint) &&
+			self.outputs.windows(2).all(|pair| pair[0] <= pair[1])
+	}
+
+	/// Sort the inputs by outpoint, and the outputs by value, then pubkey, then memo, so their
+	/// order tells nothing of the wallet that built the transaction. The order is signed, so
+	/// this is done before signing.
+	pub fn sort_canonically(&mut self) {
+		self.inputs.sort_by_key(|input| input.outpoint);
+		self.outputs.sort();
+	}
+
+	/// Sum of the output values, saturating at `Value::MAX`.
+	pub fn output_value(&self) -> Value {
+		self.outputs.iter().fold(0, |total: Value, output| total.saturating_add(output.value))
+	}
+
+	/// The outpoint of the output at `index`.
+	pub fn outpoint(&self, index: u64) -> H256 {
+		Self::outpoint_of(&self.txid(), index)
+	}
+
+	/// The outpoint of the output at `index` of the transaction with the given `txid`.
+	pub fn outpoint_of(txid: &H256, index: u64) -> H256 {
+		// The encoding of `(txid, index)`, on the stack since it is derived for every output
+		let mut preimage = [0u8; 40];
+		preimage[..32].copy_from_slice(txid.as_bytes());
+		preimage[32..].copy_from_slice(&index.to_le_bytes());
+		H256(blake2_256(&preimage))
+	}
+}
+
+/// Single transaction input that refers to one UTXO
+#[derive(Serialize, Deserialize)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Default, Clone, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct TransactionInput {
+	/// Reference to an UTXO to be spent
+	pub outpoint: H256,
+	/// Proof that transaction owner is authorized to spend referred UTXO &
+	/// that the entire transaction is untampered: the 64-byte sr25519 signature of the
+	/// transaction's signing payload by the key the spent output pays
+	///
+	/// Every input signs the same payload, so inputs spending outputs with the same pubkey
+	/// would all carry the same signature. Only the first of them does: the others leave this
+	/// `None` and are covered by it.
+	pub sigscript: Option<H512>,
+	/// Script and initial stack unlocking a UTXO locked to a script hash, in which case
+	/// `sigscript` must be `None`
+	pub witness: Option<ScriptWitness>,
+}
+
+/// Single transaction output to create upon transaction dispatch
+#[derive(Serialize, Deserialize)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Default, Clone, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct TransactionOutput {
+	/// Value associated with this output
+	pub value: Value,
+	/// Public key associated with this output: the sr25519 key that can spend it, or the
+	/// `script_hash` of the script that can
+	pub pubkey: H256,
+	/// Commitment chosen by the payer, e.g. the hash of an invoice, that the output can be
+	/// looked up by through the pallet's `MemoOutputs`
+	#[serde(default)]
+	pub memo: Option<H256>,
+}
@@ -0,0 +1,107 @@
+//! Scripts as outputs and inputs carry them: the opcodes, the witness revealing a script, and a
+//! builder assembling one.
+//!
+//! The interpreter running scripts is the pallet's `utxo::script`, which re-exports this module.
+
+use alloc::vec::Vec;
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+use serde::{Deserialize, Serialize};
+use sp_core::{bounded::BoundedVec, hashing::blake2_256, ConstU32, RuntimeDebug, H256};
+
+/// Hard ceiling on the length of a script, whatever the configured limit.
+pub const MAX_SCRIPT_SIZE: u32 = 10_000;
+/// Largest item that can be pushed onto the stack.
+pub const MAX_STACK_ITEM_SIZE: u32 = 520;
+/// Hard ceiling on the number of items in a witness' initial stack.
+pub const MAX_WITNESS_ITEMS: u32 = 100;
+
+pub type Script = BoundedVec<u8, ConstU32<MAX_SCRIPT_SIZE>>;
+pub type StackItem = BoundedVec<u8, ConstU32<MAX_STACK_ITEM_SIZE>>;
+
+pub const OP_0: u8 = 0x00;
+pub const OP_PUSHDATA1: u8 = 0x4c;
+pub const OP_PUSHDATA2: u8 = 0x4d;
+pub const OP_1: u8 = 0x51;
+pub const OP_16: u8 = 0x60;
+pub const OP_IF: u8 = 0x63;
+pub const OP_NOTIF: u8 = 0x64;
+pub const OP_ELSE: u8 = 0x67;
+pub const OP_ENDIF: u8 = 0x68;
+pub const OP_VERIFY: u8 = 0x69;
+pub const OP_RETURN: u8 = 0x6a;
+pub const OP_DROP: u8 = 0x75;
+pub const OP_DUP: u8 = 0x76;
+pub const OP_SWAP: u8 = 0x7c;
+pub const OP_EQUAL: u8 = 0x87;
+pub const OP_EQUALVERIFY: u8 = 0x88;
+pub const OP_SHA256: u8 = 0xa8;
+pub const OP_BLAKE2_256: u8 = 0xaa;
+pub const OP_CHECKSIG: u8 = 0xac;
+pub const OP_CHECKSIGVERIFY: u8 = 0xad;
+pub const OP_CHECKMULTISIG: u8 = 0xae;
+pub const OP_CHECKMULTISIGVERIFY: u8 = 0xaf;
+pub const OP_CHECKLOCKTIMEVERIFY: u8 = 0xb1;
+
+/// The part of an input that unlocks an output committed to a script hash.
+#[derive(Serialize, Deserialize)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Default, Clone, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct ScriptWitness {
+	/// The script whose hash the spent output is locked to
+	pub script: Script,
+	/// Items pushed onto the stack before the script runs, bottom first
+	pub stack: BoundedVec<StackItem, ConstU32<MAX_WITNESS_ITEMS>>,
+}
+
+/// The hash an output's `pubkey` must hold to be spendable by `script`.
+pub fn script_hash(script: &[u8]) -> H256 {
+	H256(blake2_256(script))
+}
+
+/// Assembles scripts without hand-encoding push opcodes.
+#[derive(Default, Clone, RuntimeDebug)]
+pub struct ScriptBuilder(Vec<u8>);
+
+impl ScriptBuilder {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn push_opcode(mut self, opcode: u8) -> Self {
+		self.0.push(opcode);
+		self
+	}
+
+	/// Push `data` with the shortest push opcode that fits it.
+	pub fn push_data(mut self, data: &[u8]) -> Self {
+		match data.len() {
+			0 => self.0.push(OP_0),
+			len @ 1..=0x4b => self.0.push(len as u8),
+			len @ 0x4c..=0xff => self.0.extend([OP_PUSHDATA1, len as u8]),
+			len => self.0.extend([OP_PUSHDATA2, len as u8, (len >> 8) as u8]),
+		}
+		self.0.extend_from_slice(data);
+		self
+	}
+
+	/// Push a number, using `OP_1`..`OP_16` for small values.
+	pub fn push_int(self, value: u64) -> Self {
+		match value {
+			0 => self.push_opcode(OP_0),
+			1..=16 => self.push_opcode(OP_1 + value as u8 - 1),
+			_ => {
+				let bytes = value.to_le_bytes();
+				let len = 8 - value.leading_zeros() as usize / 8;
+				self.push_data(&bytes[..len])
+			},
+		}
+	}
+
+	pub fn push_key(self, pubkey: &H256) -> Self {
+		self.push_data(pubkey.as_bytes())
+	}
+
+	pub fn into_script(self) -> Vec<u8> {
+		self.0
+	}
+}
@@ -0,0 +1,256 @@
+use crate::{
+    script::{script_hash, ScriptBuilder, ScriptWitness, OP_1},
+    is_canonical_signature, BuildError, Transaction, TransactionBuilder, TransactionInput, TransactionOutput,
+    TransactionVersion, MAX_TRANSACTION_PARTS, TRANSACTION_VERSION, WITNESS_SCALE_FACTOR,
+};
+use codec::{Decode, Encode};
+use sp_core::{bytes::from_hex, hashing::blake2_256, H256, H512};
+
+fn output(value: u128, byte: u8) -> TransactionOutput {
+    TransactionOutput { value, pubkey: H256::repeat_byte(byte), memo: None }
+}
+
+#[test]
+fn builder_checks_what_the_pallet_would_reject() {
+    let outpoint = H256::repeat_byte(1);
+    assert_eq!(TransactionBuilder::new().output(output(1, 2)).unwrap().build(), Err(BuildError::NoInputs));
+    assert_eq!(TransactionBuilder::new().input(outpoint).unwrap().build(), Err(BuildError::NoOutputs));
+    assert_eq!(TransactionBuilder::new().input(outpoint).unwrap().input(outpoint).err(), Some(BuildError::DuplicateInput));
+    assert_eq!(TransactionBuilder::new().output(output(0, 2)).err(), Some(BuildError::ZeroValueOutput));
+    assert_eq!(TransactionBuilder::new().output(output(1, 0)).err(), Some(BuildError::UnspendableDestination));
+    assert_eq!(
+        TransactionBuilder::new().output(output(1, 2)).unwrap().output(output(1, 2)).err(),
+        Some(BuildError::DuplicateOutput)
+    );
+
+    let full = (0..MAX_TRANSACTION_PARTS).try_fold(TransactionBuilder::new(), |builder, n| {
+        builder.input(H256::from_low_u64_be(n.into()))
+    });
+    assert_eq!(full.unwrap().input(outpoint).err(), Some(BuildError::TooManyInputs));
+}
+
+#[test]
+fn signatures_change_neither_payload_nor_txid() {
+    let mut transaction = TransactionBuilder::new()
+        .input(H256::repeat_byte(1))
+        .unwrap()
+        .script_input(H256::repeat_byte(2), Default::default())
+        .unwrap()
+        .output(output(90, 3))
+        .unwrap()
+        .build()
+        .unwrap();
+    let (payload, txid, wtxid) = (transaction.signing_payload(), transaction.txid(), transaction.wtxid());
+    assert_eq!(txid, H256(blake2_256(&payload)));
+    assert_eq!(wtxid, H256(blake2_256(&transaction.encode())));
+
+    transaction.inputs[0].sigscript = Some(H512::repeat_byte(4));
+    transaction.inputs[1].witness.as_mut().unwrap().script = ScriptBuilder::new().push_opcode(OP_1).into_script().try_into().unwrap();
+    assert_eq!(transaction.signing_payload(), payload);
+    assert_eq!(transaction.txid(), txid);
+    assert_ne!(transaction.wtxid(), wtxid);
+
+    // The payload is the encoding of the transaction with signatures and witnesses cleared
+    let mut cleared = transaction.clone();
+    for input in cleared.inputs.iter_mut() {
+        input.sigscript = None;
+        input.witness = None;
+    }
+    assert_eq!(cleared.encode(), payload);
+    assert_eq!(Transaction::outpoint_of(&txid, 0), transaction.outpoint(0));
+    assert_eq!(script_hash(&[OP_1]), H256(blake2_256(&[OP_1])));
+}
+
+/// Asserts the wire format of `transaction`: its encoding, signing payload, ids and first
+/// outpoints. Wallets build and sign transactions from these bytes, so they must never change by
+/// accident.
+fn assert_golden(transaction: &Transaction, encoding: &[&str], payload: &[&str], txid: &str, wtxid: &str, outpoints: [&str; 2]) {
+    let encoding = from_hex(&encoding.concat()).unwrap();
+    assert_eq!(transaction.encode(), encoding, "encoding changed");
+    assert_eq!(Transaction::decode(&mut &encoding[..]).ok().as_ref(), Some(transaction), "decoding changed");
+    assert_eq!(transaction.signing_payload(), from_hex(&payload.concat()).unwrap(), "signing payload changed");
+    assert_eq!(transaction.txid(), H256::from_slice(&from_hex(txid).unwrap()), "txid changed");
+    assert_eq!(transaction.wtxid(), H256::from_slice(&from_hex(wtxid).unwrap()), "wtxid changed");
+    for (index, outpoint) in outpoints.into_iter().enumerate() {
+        assert_eq!(transaction.outpoint(index as u64), H256::from_slice(&from_hex(outpoint).unwrap()), "outpoint {index} changed");
+    }
+}
+
+#[test]
+fn signed_transaction_matches_golden_vector() {
+    let transaction = Transaction {
+        version: TRANSACTION_VERSION,
+        inputs: vec![TransactionInput { outpoint: H256::repeat_byte(0x11), sigscript: Some(H512::repeat_byte(0x22)), witness: None }].try_into().unwrap(),
+        outputs: vec![
+            TransactionOutput { value: 1_000_000_000_000, pubkey: H256::repeat_byte(0x33), memo: None },
+            TransactionOutput { value: 5, pubkey: H256::repeat_byte(0x44), memo: Some(H256::repeat_byte(0x55)) },
+        ]
+        .try_into()
+        .unwrap(),
+    };
+    let input = [
+        "1111111111111111111111111111111111111111111111111111111111111111", // outpoint
+    ];
+    let outputs = [
+        "08",                                                               // two outputs
+        "0010a5d4e80000000000000000000000",                                 // value, u128 little-endian
+        "3333333333333333333333333333333333333333333333333333333333333333", // pubkey
+        "00",                                                               // no memo
+        "05000000000000000000000000000000",                                 // value
+        "4444444444444444444444444444444444444444444444444444444444444444", // pubkey
+        "01",                                                               // memo
+        "5555555555555555555555555555555555555555555555555555555555555555",
+    ];
+    let encoding = [
+        &["0x01", "04"][..], // version 1, one input
+        &input,
+        &["01", "22222222222222222222222222222222222222222222222222222222222222222222222222222222222222222222222222222222222222222222222222222222"], // sigscript
+        &["00"], // no witness
+        &outputs,
+    ]
+    .concat();
+    // Sigscripts and witnesses are cleared
+    let payload = [&["0x01", "04"][..], &input, &["00", "00"], &outputs].concat();
+
+    assert_golden(
+        &transaction,
+        &encoding,
+        &payload,
+        "0x30fd0b63395928f0a7b9fcd5488a84360cb9716d15a631f56892adef21b04f1d",
+        "0x2f5530bc8f7ac1169a27bdb3d9f483c753d3d1e56220a85164c97cea47e199dd",
+        [
+            "0x04494241d2db0c6a3d693b4974b52e3ee0157126a132eb44c6554889db512709",
+            "0xf958810db504eba92ffbdc16b73d9dc063c00bbf257a3957f23ab1b7ff74b47d",
+        ],
+    );
+}
+
+#[test]
+fn script_spend_matches_golden_vector() {
+    let witness = ScriptWitness {
+        script: vec![OP_1].try_into().unwrap(),
+        stack: vec![vec![1, 2].try_into().unwrap(), Default::default()].try_into().unwrap(),
+    };
+    let transaction = Transaction {
+        version: TRANSACTION_VERSION,
+        inputs: vec![TransactionInput { outpoint: H256::repeat_byte(0x66), sigscript: None, witness: Some(witness) }].try_into().unwrap(),
+        outputs: vec![TransactionOutput { value: u128::MAX, pubkey: H256::repeat_byte(0x77), memo: None }].try_into().unwrap(),
+    };
+    let outpoint = "6666666666666666666666666666666666666666666666666666666666666666";
+    let outputs = [
+        "04",                                                               // one output
+        "ffffffffffffffffffffffffffffffff",                                 // value
+        "7777777777777777777777777777777777777777777777777777777777777777", // pubkey
+        "00",                                                               // no memo
+    ];
+    let encoding = [
+        &["0x01", "04", outpoint][..],
+        &["00"], // no sigscript
+        &["01", "04", "51"], // witness, with a script of one byte
+        &["08", "08", "0102", "00"], // and a stack of two items, the second empty
+        &outputs,
+    ]
+    .concat();
+    let payload = [&["0x01", "04", outpoint, "00", "00"][..], &outputs].concat();
+
+    assert_golden(
+        &transaction,
+        &encoding,
+        &payload,
+        "0x635b6f9098d715188967aeb689e4caee4dd50819a9e6adda5665a93d1ecac372",
+        "0xbc70c57c9fac7faca60db7107d993db17dd0cfec9abfec0a0f2c8bad127530d4",
+        [
+            "0x3bc1ec1738e7cffb20eb446bb7ed98a15b0c57e6a0205f14a4715750df6cff25",
+            "0xf669a9979cb86606f489693e0dd7e02169f9755910ccaec51e668fcf42cedba2",
+        ],
+    );
+}
+
+#[test]
+fn unknown_versions_still_decode() {
+    let transaction = TransactionBuilder::new().input(H256::repeat_byte(1)).unwrap().output(output(1, 2)).unwrap().build().unwrap();
+    assert_eq!(transaction.known_version(), Ok(TransactionVersion::V1));
+    assert_eq!(Transaction::default().known_version(), Ok(TransactionVersion::V1));
+
+    let future = Transaction { version: 0xff, ..transaction.clone() };
+    let decoded = Transaction::decode(&mut &future.encode()[..]).unwrap();
+    assert_eq!(decoded.known_version(), Err(0xff));
+    // Signatures hold for one version only
+    assert_ne!(decoded.signing_payload(), transaction.signing_payload());
+}
+
+#[test]
+fn canonical_order_sorts_inputs_and_outputs() {
+    let builder = TransactionBuilder::new()
+        .input(H256::repeat_byte(3))
+        .unwrap()
+        .input(H256::repeat_byte(1))
+        .unwrap()
+        .output(output(20, 1))
+        .unwrap()
+        .output(output(10, 2))
+        .unwrap()
+        .output(output(10, 1))
+        .unwrap();
+    let given = builder.clone().build().unwrap();
+    assert!(!given.is_canonically_ordered());
+
+    let canonical = builder.canonical_order().build().unwrap();
+    assert!(canonical.is_canonically_ordered());
+    assert_eq!(canonical.inputs.iter().map(|input| input.outpoint).collect::<Vec<_>>(), [H256::repeat_byte(1), H256::repeat_byte(3)]);
+    assert_eq!(canonical.outputs.to_vec(), [output(10, 1), output(10, 2), output(20, 1)]);
+}
+
+#[test]
+fn witness_bytes_are_discounted_from_the_virtual_size() {
+    let unsigned = TransactionBuilder::new().input(H256::repeat_byte(1)).unwrap().output(output(1, 2)).unwrap().build().unwrap();
+    assert_eq!(unsigned.witness_size(), 0);
+    assert_eq!(unsigned.virtual_size() as usize, unsigned.encoded_size());
+
+    let mut signed = unsigned.clone();
+    signed.inputs[0].sigscript = Some(H512::repeat_byte(3));
+    assert_eq!(signed.witness_size(), 64);
+    assert_eq!(signed.encoded_size(), unsigned.encoded_size() + 64);
+    assert_eq!(signed.virtual_size(), unsigned.virtual_size() + 64 / WITNESS_SCALE_FACTOR);
+
+    // A witness counts with its length prefixes, rounded up
+    let witness = ScriptWitness { script: vec![OP_1].try_into().unwrap(), stack: Default::default() };
+    let mut script_spend = unsigned.clone();
+    script_spend.inputs[0].witness = Some(witness);
+    assert_eq!(script_spend.witness_size(), 3);
+    assert_eq!(script_spend.virtual_size(), unsigned.virtual_size() + 1);
+
+    // Creating an output costs more than the signature spending one
+    let mut paying_more = signed.clone();
+    paying_more.outputs.try_push(output(1, 3)).unwrap();
+    assert!(paying_more.virtual_size() - signed.virtual_size() > signed.virtual_size() - unsigned.virtual_size());
+}
+
+#[test]
+fn signatures_have_a_single_canonical_encoding() {
+    // `R` then `s`, little-endian, with schnorrkel's marker bit set on the last byte
+    let signature = |r: &str, s: &str| {
+        let mut signature = [0u8; 64];
+        signature[..32].copy_from_slice(&from_hex(r).unwrap());
+        signature[32..].copy_from_slice(&from_hex(s).unwrap());
+        signature[63] |= 0x80;
+        signature
+    };
+    let zero = "00".repeat(32);
+    let group_order = "edd3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010";
+    let below_order = "ecd3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010";
+    let field_prime = "edffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f";
+    let below_prime = "ecffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f";
+
+    assert!(is_canonical_signature(&signature(&zero, below_order)));
+    assert!(is_canonical_signature(&signature(below_prime, &zero)));
+
+    // `s` not reduced, `R` not reduced or negative
+    assert!(!is_canonical_signature(&signature(&zero, group_order)));
+    assert!(!is_canonical_signature(&signature(field_prime, &zero)));
+    assert!(!is_canonical_signature(&signature(&format!("01{}", "00".repeat(31)), &zero)));
+
+    let mut unmarked = signature(&zero, &zero);
+    unmarked[63] &= 0x7f;
+    assert!(!is_canonical_signature(&unmarked));
+}
@@ -0,0 +1,51 @@
+//! Chain-specific runtime APIs exposed to the node and its RPC layer.
+
+use alloc::vec::Vec;
+use sp_core::{H256, U256};
+
+use crate::{Balance, BlockNumber};
+
+sp_api::decl_runtime_apis! {
+	/// Emission schedule queries, so wallets, pools and explorers don't have to reimplement it.
+	pub trait IssuanceApi {
+		/// The issuance awarded to the author of block `block_number`.
+		fn reward_at(block_number: BlockNumber) -> Balance;
+
+		/// The first block after `block_number` at which the issuance is halved, if any.
+		fn next_halving_block(block_number: BlockNumber) -> Option<BlockNumber>;
+	}
+
+	/// Block reward queries for mining pools.
+	pub trait RewardApi {
+		/// The reward of the author of the next block: the fees collected so far plus its
+		/// issuance. Fees of transactions still in the pool are not included.
+		fn pending_reward() -> Balance;
+	}
+
+	/// Per-author mining statistics, so pool members can check what the pool was paid against
+	/// the chain itself.
+	pub trait MinerStatsApi {
+		/// What the author paid at `pubkey` has mined since genesis.
+		fn author_stats(pubkey: H256) -> utxo::AuthorStatistics;
+
+		/// The `limit` authors with the most blocks, ties broken by the rewards earned.
+		fn leaderboard(limit: u32) -> Vec<(H256, utxo::AuthorStatistics)>;
+	}
+
+	/// Difficulty queries, for mining dashboards. Blocks are authored with Aura, so no import
+	/// queue verifies seals against the difficulty yet.
+	pub trait DifficultyApi {
+		/// The difficulty the next block has to be sealed at.
+		fn difficulty() -> U256;
+
+		/// The network hashrate estimated over the last `window` blocks of the retarget window,
+		/// once it holds two blocks with distinct timestamps.
+		fn network_hashrate(window: u32) -> Option<pallet_difficulty::HashrateEstimate>;
+	}
+
+	/// Lookup of outputs by the memo their payer attached, for matching deposits to invoices.
+	pub trait MemoApi {
+		/// The unspent outputs carrying `memo`, with their outpoints.
+		fn utxos_by_memo(memo: H256) -> Vec<(H256, utxo::TransactionOutput)>;
+	}
+}
@@ -0,0 +1,53 @@
+//! Origins of the privileged calls of the chain's own pallets.
+//!
+//! Each privileged operation has an origin of its own, so that moving one of them away from
+//! the sudo key, to a council or to miner signalling, does not move the others with it:
+//!
+//! | Operation | Call | Origin |
+//! |-----------|------|--------|
+//! | Create outputs out of thin air | `Utxo::mint` | [`MintOrigin`] |
+//! | Override the difficulty retarget parameters | `Difficulty::set_*` | [`DifficultyParametersOrigin`] |
+//! | Bound the miner-negotiated block length | `Capacity::set_bounds` | [`BlockLengthBoundsOrigin`] |
+//! | Put a runtime upgrade to the miners | `Upgrade::propose_upgrade` | [`UpgradeProposeOrigin`] |
+//! | Withdraw a proposed runtime upgrade | `Upgrade::cancel_upgrade` | [`UpgradeCancelOrigin`] |
+//! | Put a call to a stake-weighted vote | `Referendum::propose` | [`ReferendumProposeOrigin`] |
+//! | Open airdrop claims after genesis | `Airdrop::add_claims` | [`AirdropClaimsOrigin`] |
+//!
+//! Root also reaches the root-only calls of the FRAME pallets, such as `System::set_code` and
+//! `Balances::force_transfer`. Calls dispatch as root while `Sudo` is part of the runtime, when
+//! the sudo key sends them.
+//!
+//! Calls passing a `Referendum` dispatch with its own origin, [`ByReferendum`], never as root.
+//! A referendum can pass with 0.1% of the money supply and no opposition, so it only reaches the
+//! operations that tune the chain: the difficulty and block length parameters, and withdrawing a
+//! runtime upgrade. Minting, adding airdrop claims and setting the code are out of its reach; the
+//! code only changes through miner signalling in `Upgrade`.
+
+use crate::AccountId;
+use frame_support::traits::EitherOfDiverse;
+use frame_system::{EnsureRoot, EnsureSigned};
+
+/// The origin of calls passed by a referendum.
+pub type ByReferendum = pallet_referendum::EnsureReferendum;
+
+/// May call `Utxo::mint`.
+pub type MintOrigin = EnsureRoot<AccountId>;
+
+/// May override the target block time, damp and clamp factors of the difficulty retarget.
+pub type DifficultyParametersOrigin = EitherOfDiverse<EnsureRoot<AccountId>, ByReferendum>;
+
+/// May set the bounds within which miners move the block length limit.
+pub type BlockLengthBoundsOrigin = EitherOfDiverse<EnsureRoot<AccountId>, ByReferendum>;
+
+/// May propose runtime upgrades. Anyone can, since only miners decide whether they apply, against
+/// a deposit lost if they do not.
+pub type UpgradeProposeOrigin = EnsureSigned<AccountId>;
+
+/// May withdraw the pending runtime upgrade before miners approve it.
+pub type UpgradeCancelOrigin = EitherOfDiverse<EnsureRoot<AccountId>, ByReferendum>;
+
+/// May open referendums. Anyone can, since only the stake voting decides whether they pass.
+pub type ReferendumProposeOrigin = EnsureSigned<AccountId>;
+
+/// May add airdrop claims, which mint the airdropped asset once claimed.
+pub type AirdropClaimsOrigin = EnsureRoot<AccountId>;
@@ -1,21 +1,7 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
-/// A trait for types that can provide the amount of issuance to award to the block
-/// author for the given block number.
-pub trait Issuance<BlockNumber, Balance> {
-	fn issuance(block: BlockNumber) -> Balance;
-}
-
-// Minimal implementations for when you don't actually want any issuance
-impl Issuance<u32, u128> for () {
-	fn issuance(_block: u32) -> u128 {
-		0
-	}
-}
-
-impl Issuance<u64, u128> for () {
-	fn issuance(_block: u64) -> u128 { 0 }
-}
+/// The Issuance trait consumed by the UTXO pallet.
+pub use utxo::Issuance;
 
 /// A type that provides block issuance according to bitcoin's rules
 /// Initial issuance is 50 / block
@@ -24,9 +10,21 @@ impl Issuance<u64, u128> for () {
 pub struct BitcoinHalving;
 
 /// The number of blocks between each halvening.
-const HALVING_INTERVAL: u32 = 210_000;
-/// The per-block issuance before any halvenings. Decimal places should be accounted for here.
-const INITIAL_ISSUANCE: u32 = 50;
+pub const HALVING_INTERVAL: u32 = 210_000;
+/// The per-block issuance before any halvenings: 50 coins, in base units.
+pub const INITIAL_ISSUANCE: u128 = 50 * utxo::denomination::UNIT;
+
+impl BitcoinHalving {
+	/// The first block strictly after `block` at which the issuance is halved, or `None` once the
+	/// subsidy has reached zero and no further halvings take effect.
+	pub fn next_halving_block(block: u32) -> Option<u32> {
+		let halvings = block / HALVING_INTERVAL;
+		if halvings >= u128::BITS || (INITIAL_ISSUANCE >> halvings) == 0 {
+			return None;
+		}
+		halvings.checked_add(1)?.checked_mul(HALVING_INTERVAL)
+	}
+}
 
 impl Issuance<u32, u128> for BitcoinHalving {
 
@@ -39,6 +37,6 @@ impl Issuance<u32, u128> for BitcoinHalving {
 
 		// Subsidy is cut in half every 210,000 blocks which will occur
 		// approximately every 4 years.
-		(INITIAL_ISSUANCE >> halvings).into()
+		INITIAL_ISSUANCE >> halvings
 	}
 }
\ No newline at end of file
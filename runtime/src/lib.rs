@@ -48,6 +48,14 @@ pub use sp_runtime::{Perbill, Permill};
 /// Import the template pallet.
 pub use pallet_template;
 
+/// Import the UTXO, difficulty, capacity, upgrade, referendum and airdrop pallets.
+pub use pallet_airdrop;
+pub use pallet_capacity;
+pub use pallet_difficulty;
+pub use pallet_referendum;
+pub use pallet_upgrade;
+pub use utxo;
+
 /// An index to a block.
 pub type BlockNumber = u32;
 
@@ -71,6 +79,12 @@ pub type Hash = sp_core::H256;
 /// The Issuance trait in `./issuance.rs`
 pub mod issuance;
 
+/// Chain-specific runtime APIs in `./apis.rs`
+pub mod apis;
+
+/// Origins of privileged calls in `./governance.rs`
+pub mod governance;
+
 
 /// Opaque types. These are used by the CLI to instantiate machinery that don't need to know
 /// the specifics of the runtime. They can then be made to be agnostic over specific formats
@@ -149,8 +163,7 @@ parameter_types! {
 			Weight::from_parts(2u64 * WEIGHT_REF_TIME_PER_SECOND, u64::MAX),
 			NORMAL_DISPATCH_RATIO,
 		);
-	pub BlockLength: frame_system::limits::BlockLength = frame_system::limits::BlockLength
-		::max_with_normal_ratio(5 * 1024 * 1024, NORMAL_DISPATCH_RATIO);
+	pub const NormalDispatchRatio: Perbill = NORMAL_DISPATCH_RATIO;
 	pub const SS58Prefix: u8 = 42;
 }
 
@@ -163,8 +176,8 @@ impl frame_system::Config for Runtime {
 	type Block = Block;
 	/// Block & extrinsics weights: base values and limits.
 	type BlockWeights = BlockWeights;
-	/// The maximum length of a block (in bytes).
-	type BlockLength = BlockLength;
+	/// The maximum length of a block (in bytes), negotiated by miners through pallet-capacity.
+	type BlockLength = pallet_capacity::DynamicBlockLength<Runtime, NormalDispatchRatio>;
 	/// The identifier used to distinguish between accounts.
 	type AccountId = AccountId;
 	/// The type for storing how many extrinsics an account has signed.
@@ -207,7 +220,8 @@ impl pallet_grandpa::Config for Runtime {
 impl pallet_timestamp::Config for Runtime {
 	/// A timestamp: milliseconds since the unix epoch.
 	type Moment = u64;
-	type OnTimestampSet = Aura;
+	/// Aura checks the slot, the difficulty pallet the median time past.
+	type OnTimestampSet = (Aura, Difficulty);
 	type MinimumPeriod = ConstU64<{ SLOT_DURATION / 2 }>;
 	type WeightInfo = ();
 }
@@ -258,6 +272,213 @@ impl pallet_template::Config for Runtime {
 	type WeightInfo = pallet_template::weights::SubstrateWeight<Runtime>;
 }
 
+/// Hard cap on the value held in the UTXO set, matching the total of the halving schedule.
+pub const MAX_MONEY: utxo::Value = 21_000_000 * utxo::denomination::UNIT;
+
+parameter_types! {
+	pub const MaxTransactionSize: u32 = utxo::MAX_TRANSACTION_PARTS;
+	pub const MaxMoney: utxo::Value = MAX_MONEY;
+	pub const MaxScriptSize: u32 = utxo::script::MAX_SCRIPT_SIZE;
+	pub const MaxScriptOps: u32 = 201;
+	pub const ConsolidationDiscount: Perbill = Perbill::from_percent(50);
+	pub const MaxStackDepth: u32 = 1_000;
+	/// Block rewards are paid out at once. Spreading them over a number of blocks makes a reorg
+	/// replacing a single block less profitable.
+	pub const RewardSmoothing: u32 = 0;
+	/// Nothing until blocks carry a proof-of-work seal. An Aura seal costs nothing, so an
+	/// authority could sign any number of competing headers for its own slots and mint an uncle
+	/// reward for each. Uncle references are still checked and recorded.
+	pub const UncleReward: Perbill = Perbill::from_percent(0);
+	pub const MaxUncleDepth: u32 = 6;
+	pub const MaxUncles: u32 = 2;
+	/// Not enforced yet. Wallets can already build transactions in canonical order; a runtime
+	/// upgrade setting a block ahead of its approval turns the rule on.
+	pub const CanonicalOrderingFrom: BlockNumber = BlockNumber::MAX;
+}
+
+/// Pays the block reward to the Aura authority that authored the block.
+pub struct AuraAuthor;
+impl utxo::BlockAuthor for AuraAuthor {
+	fn block_author() -> Option<sp_core::sr25519::Public> {
+		use frame_support::traits::FindAuthor;
+
+		let digest = System::digest();
+		let pre_runtime_digests = digest.logs.iter().filter_map(|d| d.as_pre_runtime());
+		let index = <Aura as FindAuthor<u32>>::find_author(pre_runtime_digests)?;
+		pallet_aura::Authorities::<Runtime>::get()
+			.get(index as usize)
+			.map(|authority| authority.clone().into_inner())
+	}
+}
+
+/// Accepts competing headers sealed by the Aura authority of their slot. Such a seal is free to
+/// make, which is why [`UncleReward`] is zero.
+pub struct AuraUncleAuthor;
+impl utxo::UncleAuthor<Header> for AuraUncleAuthor {
+	fn uncle_author(header: &Header) -> Option<sp_core::sr25519::Public> {
+		use sp_consensus_aura::{sr25519::AuthoritySignature, Slot, AURA_ENGINE_ID};
+		use sp_runtime::{traits::Header as _, RuntimeAppPublic};
+
+		// Aura signs the hash of the header without its seal
+		let mut header = header.clone();
+		let signature = header
+			.digest_mut()
+			.pop()?
+			.seal_try_to::<AuthoritySignature>(&AURA_ENGINE_ID)?;
+		let slot = header
+			.digest()
+			.logs
+			.iter()
+			.find_map(|item| item.pre_runtime_try_to::<Slot>(&AURA_ENGINE_ID))?;
+
+		let authorities = pallet_aura::Authorities::<Runtime>::get();
+		let index = u64::from(slot).checked_rem(authorities.len() as u64)?;
+		let author = authorities.get(index as usize)?;
+		author
+			.verify(&header.hash(), &signature)
+			.then(|| author.clone().into_inner())
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn seal(header: &mut Header) -> sp_core::sr25519::Public {
+		use codec::Encode;
+		use sp_consensus_aura::{Slot, AURA_ENGINE_ID};
+		use sp_runtime::{traits::Header as _, DigestItem, RuntimeAppPublic};
+
+		let author = AuraId::generate_pair(None);
+		pallet_aura::Authorities::<Runtime>::put(frame_support::BoundedVec::truncate_from(vec![author.clone()]));
+		header.digest_mut().push(DigestItem::PreRuntime(AURA_ENGINE_ID, Slot::from(0).encode()));
+		let signature = author
+			.sign(&header.hash())
+			.expect("key was generated in the benchmark keystore; qed");
+		header.digest_mut().push(DigestItem::Seal(AURA_ENGINE_ID, signature.encode()));
+		author.into_inner()
+	}
+}
+
+/// Configure the UTXO pallet in pallets/utxo.
+impl utxo::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type BlockAuthor = utxo::RewardAddressOr<Runtime, AuraAuthor>;
+	type Issuance = issuance::BitcoinHalving;
+	type WeightInfo = utxo::weights::SubstrateWeight<Runtime>;
+	type MaxTransactionSize = MaxTransactionSize;
+	type MaxMoney = MaxMoney;
+	type MintOrigin = governance::MintOrigin;
+	type MaxScriptSize = MaxScriptSize;
+	type MaxScriptOps = MaxScriptOps;
+	type MaxStackDepth = MaxStackDepth;
+	type ConsolidationDiscount = ConsolidationDiscount;
+	type RewardSmoothing = RewardSmoothing;
+	type UncleAuthor = AuraUncleAuthor;
+	type UncleReward = UncleReward;
+	type MaxUncleDepth = MaxUncleDepth;
+	type MaxUncles = MaxUncles;
+	type CanonicalOrderingFrom = CanonicalOrderingFrom;
+}
+
+/// Configure the difficulty pallet in pallets/difficulty.
+impl pallet_difficulty::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type WeightInfo = pallet_difficulty::weights::SubstrateWeight<Runtime>;
+	type TimeProvider = Timestamp;
+	type TargetBlockTime = ConstU128<{ MILLISECS_PER_BLOCK as u128 }>;
+	type DampFactor = ConstU128<3>;
+	type ClampFactor = ConstU128<2>;
+	type MaxDifficulty = ConstU128<{ u128::MAX }>;
+	type MinDifficulty = ConstU128<1>;
+	type ParametersOrigin = governance::DifficultyParametersOrigin;
+}
+
+parameter_types! {
+	/// Bounds on the miner-negotiated block length until governance changes them.
+	pub const InitialBlockLengthBounds: (u32, u32) = (1024 * 1024, 32 * 1024 * 1024);
+	/// Roughly a week of blocks per adjustment, so capacity only moves on a sustained majority.
+	pub const BlockLengthSignalWindow: u32 = 7 * DAYS;
+	pub const MaxBlockLengthAdjustment: Perbill = Perbill::from_percent(10);
+}
+
+/// Configure the capacity pallet in pallets/capacity.
+impl pallet_capacity::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type WeightInfo = pallet_capacity::weights::SubstrateWeight<Runtime>;
+	type BoundsOrigin = governance::BlockLengthBoundsOrigin;
+	type InitialBlockLength = ConstU32<{ 5 * 1024 * 1024 }>;
+	type InitialBounds = InitialBlockLengthBounds;
+	type SignalWindow = BlockLengthSignalWindow;
+	type MaxAdjustment = MaxBlockLengthAdjustment;
+}
+
+parameter_types! {
+	/// As in Bitcoin's BIP 9, an upgrade needs the signals of most blocks over a sustained
+	/// period, and lapses if miners do not give them within a few weeks.
+	pub const UpgradeSignalWindow: u32 = DAYS;
+	pub const UpgradeThreshold: Perbill = Perbill::from_percent(90);
+	pub const UpgradeSignalWindows: u32 = 28;
+	/// Lost unless miners approve the upgrade, so that the single slot for a pending upgrade
+	/// cannot be kept taken for free.
+	pub const UpgradeProposalDeposit: Balance = 1_000_000_000 * EXISTENTIAL_DEPOSIT;
+}
+
+/// Configure the upgrade pallet in pallets/upgrade.
+impl pallet_upgrade::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type WeightInfo = pallet_upgrade::weights::SubstrateWeight<Runtime>;
+	type RuntimeHoldReason = RuntimeHoldReason;
+	type Currency = Balances;
+	type ProposeOrigin = governance::UpgradeProposeOrigin;
+	type CancelOrigin = governance::UpgradeCancelOrigin;
+	type MaxCodeSize = ConstU32<{ 3 * 1024 * 1024 }>;
+	type SignalWindow = UpgradeSignalWindow;
+	type Threshold = UpgradeThreshold;
+	type SignalWindows = UpgradeSignalWindows;
+	type ProposalDeposit = UpgradeProposalDeposit;
+}
+
+parameter_types! {
+	pub const ReferendumVotingPeriod: BlockNumber = 7 * DAYS;
+	/// A referendum passes only if at least 0.1% of the money supply votes for it.
+	pub const ReferendumMinApproval: utxo::Value = MAX_MONEY / 1000;
+	/// A vote stakes at least one coin, so votes cannot be cast with dust by the thousand.
+	pub const ReferendumMinVoteStake: utxo::Value = utxo::denomination::UNIT;
+	pub const ReferendumUnsignedPriority: sp_runtime::transaction_validity::TransactionPriority =
+		u64::MAX / 2;
+	/// The calls of the referendums ending in a block take at most 40% of it together.
+	pub ReferendumMaxCallWeight: Weight = Perbill::from_percent(10) * BlockWeights::get().max_block;
+}
+
+/// Configure the referendum pallet in pallets/referendum.
+impl pallet_referendum::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type RuntimeOrigin = RuntimeOrigin;
+	type RuntimeCall = RuntimeCall;
+	type WeightInfo = pallet_referendum::weights::SubstrateWeight<Runtime>;
+	type ProposeOrigin = governance::ReferendumProposeOrigin;
+	type VotingPeriod = ReferendumVotingPeriod;
+	type MinApproval = ReferendumMinApproval;
+	type MaxCallSize = ConstU32<{ 16 * 1024 }>;
+	type MaxCallWeight = ReferendumMaxCallWeight;
+	type MaxProposalsPerBlock = ConstU32<4>;
+	type MinVoteStake = ReferendumMinVoteStake;
+	type MaxVotesCleared = ConstU32<1_000>;
+	type UnsignedPriority = ReferendumUnsignedPriority;
+}
+
+parameter_types! {
+	pub const AirdropUnsignedPriority: sp_runtime::transaction_validity::TransactionPriority =
+		u64::MAX / 2;
+}
+
+/// Configure the airdrop pallet in pallets/airdrop.
+impl pallet_airdrop::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type WeightInfo = pallet_airdrop::weights::SubstrateWeight<Runtime>;
+	type Asset = Balances;
+	type ClaimsOrigin = governance::AirdropClaimsOrigin;
+	type MaxClaimsPerCall = ConstU32<1_000>;
+	type UnsignedPriority = AirdropUnsignedPriority;
+}
+
 // Create the runtime by composing the FRAME pallets that were previously configured.
 #[frame_support::runtime]
 mod runtime {
@@ -299,6 +520,24 @@ mod runtime {
 	// Include the custom logic from the pallet-template in the runtime.
 	#[runtime::pallet_index(7)]
 	pub type TemplateModule = pallet_template;
+
+	#[runtime::pallet_index(8)]
+	pub type Utxo = utxo;
+
+	#[runtime::pallet_index(9)]
+	pub type Difficulty = pallet_difficulty;
+
+	#[runtime::pallet_index(10)]
+	pub type Capacity = pallet_capacity;
+
+	#[runtime::pallet_index(11)]
+	pub type Upgrade = pallet_upgrade;
+
+	#[runtime::pallet_index(12)]
+	pub type Referendum = pallet_referendum;
+
+	#[runtime::pallet_index(13)]
+	pub type Airdrop = pallet_airdrop;
 }
 
 /// The address format for describing accounts.
@@ -323,7 +562,7 @@ pub type SignedExtra = (
 ///
 /// This can be a tuple of types, each implementing `OnRuntimeUpgrade`.
 #[allow(unused_parens)]
-type Migrations = ();
+type Migrations = (utxo::migrations::v1::MigrateV0ToV1<Runtime>);
 
 /// Unchecked extrinsic type as expected by this runtime.
 pub type UncheckedExtrinsic =
@@ -349,6 +588,12 @@ mod benches {
 		[pallet_timestamp, Timestamp]
 		[pallet_sudo, Sudo]
 		[pallet_template, TemplateModule]
+		[utxo, Utxo]
+		[pallet_difficulty, Difficulty]
+		[pallet_capacity, Capacity]
+		[pallet_upgrade, Upgrade]
+		[pallet_referendum, Referendum]
+		[pallet_airdrop, Airdrop]
 	);
 }
 
@@ -470,6 +715,48 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl apis::IssuanceApi<Block> for Runtime {
+		fn reward_at(block_number: BlockNumber) -> Balance {
+			<issuance::BitcoinHalving as issuance::Issuance<BlockNumber, Balance>>::issuance(block_number)
+		}
+
+		fn next_halving_block(block_number: BlockNumber) -> Option<BlockNumber> {
+			issuance::BitcoinHalving::next_halving_block(block_number)
+		}
+	}
+
+	impl apis::RewardApi<Block> for Runtime {
+		fn pending_reward() -> Balance {
+			Utxo::pending_reward()
+		}
+	}
+
+	impl apis::MinerStatsApi<Block> for Runtime {
+		fn author_stats(pubkey: sp_core::H256) -> utxo::AuthorStatistics {
+			utxo::AuthorStats::<Runtime>::get(pubkey)
+		}
+
+		fn leaderboard(limit: u32) -> Vec<(sp_core::H256, utxo::AuthorStatistics)> {
+			Utxo::leaderboard(limit)
+		}
+	}
+
+	impl apis::DifficultyApi<Block> for Runtime {
+		fn difficulty() -> sp_core::U256 {
+			Difficulty::difficulty()
+		}
+
+		fn network_hashrate(window: u32) -> Option<pallet_difficulty::HashrateEstimate> {
+			Difficulty::network_hashrate(window)
+		}
+	}
+
+	impl apis::MemoApi<Block> for Runtime {
+		fn utxos_by_memo(memo: sp_core::H256) -> Vec<(sp_core::H256, utxo::TransactionOutput)> {
+			Utxo::utxos_by_memo(memo)
+		}
+	}
+
 	impl frame_system_rpc_runtime_api::AccountNonceApi<Block, AccountId, Nonce> for Runtime {
 		fn account_nonce(account: AccountId) -> Nonce {
 			System::account_nonce(account)
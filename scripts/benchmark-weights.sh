@@ -0,0 +1,44 @@
+#!/usr/bin/env bash
+# Regenerates the `weights.rs` files of the chain's pallets on the current machine.
+#
+# Usage: ./scripts/benchmark-weights.sh [pallet...]
+# With no arguments every pallet below is benchmarked.
+set -euo pipefail
+
+cd "$(dirname "$0")/.."
+
+# pallet name as registered in `define_benchmarks!` => crate directory
+declare -A PALLETS=(
+	[utxo]=pallets/utxo
+	[pallet_difficulty]=pallets/difficulty
+	[pallet_capacity]=pallets/capacity
+	[pallet_upgrade]=pallets/upgrade
+	[pallet_referendum]=pallets/referendum
+	[pallet_airdrop]=pallets/airdrop
+	[pallet_template]=pallets/template
+)
+
+STEPS=${STEPS:-50}
+REPEAT=${REPEAT:-20}
+NODE=./target/release/tumuchain-node
+
+cargo build --release --locked --features runtime-benchmarks --package tumuchain-node
+
+selected=("$@")
+if [ ${#selected[@]} -eq 0 ]; then
+	selected=("${!PALLETS[@]}")
+fi
+
+for pallet in "${selected[@]}"; do
+	dir=${PALLETS[$pallet]:?unknown pallet $pallet}
+	echo "Benchmarking $pallet into $dir/src/weights.rs"
+	"$NODE" benchmark pallet \
+		--chain dev \
+		--pallet "$pallet" \
+		--extrinsic '*' \
+		--steps "$STEPS" \
+		--repeat "$REPEAT" \
+		--wasm-execution=compiled \
+		--output "$dir/src/weights.rs" \
+		--template .maintain/frame-weight-template.hbs
+done